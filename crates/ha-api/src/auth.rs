@@ -10,23 +10,40 @@
 //! 4. POST /auth/token - Exchange auth code for tokens
 
 use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use axum::{
     body::Bytes,
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use ha_registries::{Storage, StorageFile, StorageResult};
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use ulid::Ulid;
 
+use crate::permissions::UserPermissions;
+
 /// Default access token expiration (30 minutes)
 const ACCESS_TOKEN_EXPIRATION_SECS: u64 = 1800;
 
+/// Default lifespan of long-lived access tokens (10 years, like HA)
+pub const LONG_LIVED_TOKEN_LIFESPAN: Duration = Duration::from_secs(3650 * 86400);
+
+/// Storage key of persisted long-lived access tokens
+pub const LONG_LIVED_TOKENS_STORAGE_KEY: &str = "auth.long_lived_access_tokens";
+/// Current storage version
+const LONG_LIVED_TOKENS_STORAGE_VERSION: u32 = 1;
+/// Current minor version
+const LONG_LIVED_TOKENS_STORAGE_MINOR_VERSION: u32 = 1;
+
 /// Auth state shared across all auth endpoints
 #[derive(Clone)]
 pub struct AuthState {
@@ -44,6 +61,18 @@ struct AuthStateInner {
     users: RwLock<HashMap<String, User>>,
     /// Whether onboarding is complete
     onboarded: RwLock<bool>,
+    /// Lifetime of access tokens issued for normal refresh tokens
+    access_token_expiration: Duration,
+    /// Where long-lived access tokens are persisted (in memory only if unset)
+    storage: Option<Arc<Storage>>,
+    /// Held while writing the long-lived tokens, so writes can't reorder
+    save_lock: Mutex<()>,
+    /// Permissions of users that aren't admins
+    permissions: DashMap<String, UserPermissions>,
+    /// Password-less logins from trusted networks (disabled if unset)
+    trusted_networks: Option<TrustedNetworksProvider>,
+    /// Access tokens given in the configuration (token hash -> user_id)
+    configured_tokens: HashMap<String, String>,
 }
 
 impl AuthState {
@@ -56,6 +85,12 @@ impl AuthState {
                 refresh_tokens: RwLock::new(HashMap::new()),
                 users: RwLock::new(HashMap::new()),
                 onboarded: RwLock::new(false),
+                access_token_expiration: Duration::from_secs(ACCESS_TOKEN_EXPIRATION_SECS),
+                storage: None,
+                save_lock: Mutex::new(()),
+                permissions: DashMap::new(),
+                trusted_networks: None,
+                configured_tokens: HashMap::new(),
             }),
         }
     }
@@ -84,10 +119,163 @@ impl AuthState {
                 refresh_tokens: RwLock::new(HashMap::new()),
                 users: RwLock::new(users),
                 onboarded: RwLock::new(true),
+                access_token_expiration: Duration::from_secs(ACCESS_TOKEN_EXPIRATION_SECS),
+                storage: None,
+                save_lock: Mutex::new(()),
+                permissions: DashMap::new(),
+                trusted_networks: None,
+                configured_tokens: HashMap::new(),
             }),
         }
     }
 
+    /// Set the lifetime of access tokens issued from now on
+    ///
+    /// Must be called before the auth state is shared.
+    pub fn with_access_token_expiration(mut self, expiration: Duration) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("auth state configured after being shared")
+            .access_token_expiration = expiration;
+        self
+    }
+
+    /// Let clients in trusted networks log in without a password
+    ///
+    /// The provider stays disabled if `trusted_users` names a user that
+    /// doesn't exist, rather than logging those clients in as the owner.
+    /// Must be called before the auth state is shared.
+    pub fn with_trusted_networks(mut self, config: TrustedNetworksConfig) -> Self {
+        let inner =
+            Arc::get_mut(&mut self.inner).expect("auth state configured after being shared");
+        let users = inner.users.get_mut();
+        if let Some(user_id) = config
+            .trusted_users
+            .values()
+            .find(|user_id| !users.contains_key(*user_id))
+        {
+            tracing::warn!(
+                "Trusted networks provider disabled: unknown trusted user {}",
+                user_id
+            );
+            return self;
+        }
+        inner.trusted_networks = Some(TrustedNetworksProvider::new(config));
+        self
+    }
+
+    /// Accept `token` as a never-expiring access token of `user_id`
+    ///
+    /// For access tokens seeded through the configuration, e.g. by test
+    /// harnesses that need a known token. Only the token's hash is kept.
+    /// Must be called before the auth state is shared.
+    pub fn with_access_token(mut self, token: &str, user_id: &str) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("auth state configured after being shared")
+            .configured_tokens
+            .insert(hash_token(token), user_id.to_string());
+        self
+    }
+
+    /// Persist long-lived access tokens in `storage`
+    ///
+    /// Must be called before the auth state is shared.
+    pub fn with_storage(mut self, storage: Arc<Storage>) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("auth state configured after being shared")
+            .storage = Some(storage);
+        self
+    }
+
+    /// Load the persisted long-lived access tokens
+    pub async fn load(&self) -> StorageResult<()> {
+        let Some(storage) = &self.inner.storage else {
+            return Ok(());
+        };
+        let Some(file) = storage
+            .load::<LongLivedTokensData>(LONG_LIVED_TOKENS_STORAGE_KEY)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        tracing::info!(
+            "Loading {} long-lived access tokens from storage",
+            file.data.tokens.len()
+        );
+        let mut refresh_tokens = self.inner.refresh_tokens.write().await;
+        for stored in file.data.tokens {
+            let refresh_token = RefreshToken {
+                id: stored.id,
+                // Never exchanged, so the refresh token itself isn't kept
+                token: generate_token(),
+                user_id: stored.user_id,
+                client_id: None,
+                client_name: Some(stored.client_name),
+                client_icon: stored.client_icon,
+                token_type: RefreshTokenType::LongLivedAccessToken,
+                access_token_expiration: Duration::ZERO,
+                jwt_key: generate_token(),
+                access_token_hash: Some(stored.token_hash),
+                expire_at: Some(stored.expire_at),
+                created_at: stored.created_at.into(),
+            };
+            refresh_tokens.insert(refresh_token.token.clone(), refresh_token);
+        }
+        Ok(())
+    }
+
+    /// Write the long-lived access tokens to storage
+    async fn save_long_lived_tokens(&self) {
+        let Some(storage) = &self.inner.storage else {
+            return;
+        };
+        // Snapshot and write under the lock, so the last write has the
+        // latest tokens even when two changes save at the same time
+        let _save = self.inner.save_lock.lock().await;
+        let tokens = self
+            .inner
+            .refresh_tokens
+            .read()
+            .await
+            .values()
+            .filter_map(|rt| {
+                Some(StoredLongLivedToken {
+                    id: rt.id.clone(),
+                    user_id: rt.user_id.clone(),
+                    client_name: rt.client_name.clone()?,
+                    client_icon: rt.client_icon.clone(),
+                    created_at: rt.created_at.into(),
+                    expire_at: rt.expire_at?,
+                    token_hash: rt.access_token_hash.clone()?,
+                })
+            })
+            .collect();
+        let file = StorageFile::new(
+            LONG_LIVED_TOKENS_STORAGE_KEY,
+            LongLivedTokensData { tokens },
+            LONG_LIVED_TOKENS_STORAGE_VERSION,
+            LONG_LIVED_TOKENS_STORAGE_MINOR_VERSION,
+        );
+        if let Err(e) = storage.save(&file).await {
+            tracing::error!("Failed to save long-lived access tokens: {}", e);
+        }
+    }
+
+    /// Set what a user may change
+    pub fn set_user_permissions(&self, user_id: &str, permissions: UserPermissions) {
+        self.inner
+            .permissions
+            .insert(user_id.to_string(), permissions);
+    }
+
+    /// What a user may change; users without permissions set are admins
+    pub fn user_permissions(&self, user_id: &str) -> UserPermissions {
+        self.inner
+            .permissions
+            .get(user_id)
+            .map_or_else(UserPermissions::admin, |p| p.clone())
+    }
+
     /// Check if onboarding is complete
     pub async fn is_onboarded(&self) -> bool {
         *self.inner.onboarded.read().await
@@ -134,42 +322,47 @@ impl AuthState {
 
         // In a real implementation, we'd validate credentials here
         // For now, accept any credentials for development
+        let user_id = self.default_user_id().await;
 
-        // Get or create a user
-        let user_id = {
-            let users = self.inner.users.read().await;
-            if let Some(user) = users.values().next() {
-                user.id.clone()
-            } else {
-                drop(users);
-                // Create a default user
-                let user = User {
-                    id: Ulid::new().to_string(),
-                    name: "User".to_string(),
-                    is_owner: true,
-                    is_active: true,
-                    credentials: vec![Credential {
-                        auth_provider_type: "homeassistant".to_string(),
-                        auth_provider_id: None,
-                    }],
-                };
-                let id = user.id.clone();
-                self.inner.users.write().await.insert(id.clone(), user);
-                id
-            }
+        Some(self.create_auth_code(flow.client_id, user_id).await)
+    }
+
+    /// ID of the user logins without a specific user get, created if needed
+    async fn default_user_id(&self) -> String {
+        let users = self.inner.users.read().await;
+        if let Some(user) = users.values().next() {
+            return user.id.clone();
+        }
+        drop(users);
+
+        // Create a default user
+        let user = User {
+            id: Ulid::new().to_string(),
+            name: "User".to_string(),
+            is_owner: true,
+            is_active: true,
+            credentials: vec![Credential {
+                auth_provider_type: "homeassistant".to_string(),
+                auth_provider_id: None,
+            }],
         };
+        let id = user.id.clone();
+        self.inner.users.write().await.insert(id.clone(), user);
+        id
+    }
 
-        // Generate auth code
+    /// Generate an auth code for a user that logged in
+    async fn create_auth_code(&self, client_id: String, user_id: String) -> String {
         let code = Ulid::new().to_string().to_lowercase();
         tracing::info!(
-            "complete_login_flow: generated code={}, client_id={}",
+            "create_auth_code: generated code={}, client_id={}",
             code,
-            flow.client_id
+            client_id
         );
 
         let auth_code = AuthCode {
             code: code.clone(),
-            client_id: flow.client_id,
+            client_id,
             user_id,
             created_at: SystemTime::now(),
         };
@@ -180,7 +373,24 @@ impl AuthState {
             .await
             .insert(code.clone(), auth_code);
 
-        Some(code)
+        code
+    }
+
+    /// User a client logs in as through the trusted networks provider
+    ///
+    /// `peer` is the address the connection came from; `None` if the
+    /// provider isn't set up or the client isn't in a trusted network.
+    pub async fn trusted_network_user(&self, peer: IpAddr, headers: &HeaderMap) -> Option<String> {
+        let provider = self.inner.trusted_networks.as_ref()?;
+        let ip = provider.client_ip(peer, headers)?;
+        if !provider.is_trusted(ip) {
+            tracing::warn!("Trusted networks login denied for {}", ip);
+            return None;
+        }
+        match provider.mapped_user(ip) {
+            Some(user_id) => Some(user_id.to_string()),
+            None => Some(self.default_user_id().await),
+        }
     }
 
     /// Exchange an auth code for tokens
@@ -231,13 +441,20 @@ impl AuthState {
             id: Ulid::new().to_string(),
             token: refresh_token_value.clone(),
             user_id: auth_code.user_id,
-            client_id: client_id.to_string(),
+            client_id: Some(client_id.to_string()),
+            client_name: None,
+            client_icon: None,
+            token_type: RefreshTokenType::Normal,
+            access_token_expiration: self.inner.access_token_expiration,
             jwt_key,
+            access_token_hash: None,
+            expire_at: None,
             created_at: SystemTime::now(),
         };
 
         // Create access token
         let access_token = self.create_access_token(&refresh_token);
+        let expires_in = refresh_token.access_token_expiration.as_secs();
 
         self.inner
             .refresh_tokens
@@ -248,32 +465,134 @@ impl AuthState {
         Some(TokenResponse {
             access_token,
             token_type: "Bearer".to_string(),
-            expires_in: ACCESS_TOKEN_EXPIRATION_SECS,
+            expires_in,
             refresh_token: Some(refresh_token_value),
             ha_auth_provider: Some("homeassistant".to_string()),
         })
     }
 
     /// Refresh an access token
+    ///
+    /// Only normal refresh tokens can be exchanged, and only by the client
+    /// they were issued to.
     async fn refresh_access_token(
         &self,
         refresh_token_value: &str,
-        _client_id: &str,
+        client_id: &str,
     ) -> Option<TokenResponse> {
         let refresh_tokens = self.inner.refresh_tokens.read().await;
         let refresh_token = refresh_tokens.get(refresh_token_value)?;
+        if refresh_token.token_type != RefreshTokenType::Normal
+            || refresh_token.client_id.as_deref() != Some(client_id)
+        {
+            tracing::warn!(
+                "refresh_access_token: refresh token not issued to {}",
+                client_id
+            );
+            return None;
+        }
 
         let access_token = self.create_access_token(refresh_token);
 
         Some(TokenResponse {
             access_token,
             token_type: "Bearer".to_string(),
-            expires_in: ACCESS_TOKEN_EXPIRATION_SECS,
+            expires_in: refresh_token.access_token_expiration.as_secs(),
             refresh_token: None, // Don't return refresh token on refresh
             ha_auth_provider: None,
         })
     }
 
+    /// Create a long-lived access token for a user
+    ///
+    /// Backed by a refresh token that can't be exchanged at `/auth/token`,
+    /// the access token is valid for `lifespan` and is returned only here;
+    /// just its hash is kept and persisted.
+    pub async fn create_long_lived_access_token(
+        &self,
+        user_id: &str,
+        client_name: &str,
+        client_icon: Option<&str>,
+        lifespan: Duration,
+    ) -> String {
+        let mut refresh_token = RefreshToken {
+            id: Ulid::new().to_string(),
+            token: generate_token(),
+            user_id: user_id.to_string(),
+            client_id: None,
+            client_name: Some(client_name.to_string()),
+            client_icon: client_icon.map(str::to_string),
+            token_type: RefreshTokenType::LongLivedAccessToken,
+            access_token_expiration: lifespan,
+            jwt_key: generate_token(),
+            access_token_hash: None,
+            expire_at: None,
+            created_at: SystemTime::now(),
+        };
+        let access_token = self.create_access_token(&refresh_token);
+        refresh_token.access_token_hash = Some(hash_token(&access_token));
+        refresh_token.expire_at = access_token
+            .split(':')
+            .nth(1)
+            .and_then(|exp| exp.parse().ok());
+        self.inner
+            .refresh_tokens
+            .write()
+            .await
+            .insert(refresh_token.token.clone(), refresh_token);
+        self.save_long_lived_tokens().await;
+        access_token
+    }
+
+    /// Refresh tokens of a user, including those backing long-lived tokens
+    pub async fn refresh_tokens(&self, user_id: &str) -> Vec<RefreshTokenInfo> {
+        let mut tokens: Vec<RefreshTokenInfo> = self
+            .inner
+            .refresh_tokens
+            .read()
+            .await
+            .values()
+            .filter(|rt| rt.user_id == user_id)
+            .map(|rt| RefreshTokenInfo {
+                id: rt.id.clone(),
+                client_id: rt.client_id.clone(),
+                client_name: rt.client_name.clone(),
+                client_icon: rt.client_icon.clone(),
+                token_type: match rt.token_type {
+                    RefreshTokenType::Normal => "normal",
+                    RefreshTokenType::LongLivedAccessToken => "long_lived_access_token",
+                },
+                created_at: rt.created_at.into(),
+                expire_at: rt.expire_at,
+            })
+            .collect();
+        tokens.sort_by_key(|token| token.created_at);
+        tokens
+    }
+
+    /// Revoke a refresh token of a user, and the access tokens made from it
+    ///
+    /// Returns false when the user has no such refresh token.
+    pub async fn delete_refresh_token(&self, user_id: &str, refresh_token_id: &str) -> bool {
+        let removed = {
+            let mut refresh_tokens = self.inner.refresh_tokens.write().await;
+            let key = refresh_tokens
+                .iter()
+                .find(|(_, rt)| rt.id == refresh_token_id && rt.user_id == user_id)
+                .map(|(key, _)| key.clone());
+            key.and_then(|key| refresh_tokens.remove(&key))
+        };
+        match removed {
+            Some(rt) => {
+                if rt.token_type == RefreshTokenType::LongLivedAccessToken {
+                    self.save_long_lived_tokens().await;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Create an access token from a refresh token
     fn create_access_token(&self, refresh_token: &RefreshToken) -> String {
         // In a real implementation, this would be a JWT signed with jwt_key
@@ -282,7 +601,7 @@ impl AuthState {
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
-        let exp = now + ACCESS_TOKEN_EXPIRATION_SECS;
+        let exp = now + refresh_token.access_token_expiration.as_secs();
 
         // Simple token format for development
         // A real implementation would use jwt crate
@@ -296,36 +615,43 @@ impl AuthState {
 
     /// Validate an access token and return the user ID
     pub async fn validate_access_token(&self, token: &str) -> Option<String> {
+        self.check_access_token(token).await.ok()
+    }
+
+    /// Check an access token, telling why it's rejected
+    pub async fn check_access_token(&self, token: &str) -> Result<String, AccessTokenError> {
+        if let Some(user_id) = self.inner.configured_tokens.get(&hash_token(token)) {
+            return Ok(user_id.clone());
+        }
+
         // Parse the simple token format
         let parts: Vec<&str> = token.split(':').collect();
-        if parts.len() != 3 {
-            return None;
-        }
+        let [refresh_token_id, exp, signature] = parts[..] else {
+            return Err(AccessTokenError::Malformed);
+        };
+        let exp: u64 = exp.parse().map_err(|_| AccessTokenError::Malformed)?;
 
-        let refresh_token_id = parts[0];
-        let exp: u64 = parts[1].parse().ok()?;
+        // Find the refresh token by ID and verify the signature
+        let refresh_tokens = self.inner.refresh_tokens.read().await;
+        let refresh_token = refresh_tokens
+            .values()
+            .find(|rt| rt.id == refresh_token_id)
+            .filter(|rt| match &rt.access_token_hash {
+                Some(hash) => *hash == hash_token(token),
+                None => signature == &rt.jwt_key[..16],
+            })
+            .ok_or(AccessTokenError::Invalid)?;
 
         // Check expiration
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
-        if now > exp {
-            return None;
+        if now >= exp {
+            return Err(AccessTokenError::Expired);
         }
 
-        // Find the refresh token by ID
-        let refresh_tokens = self.inner.refresh_tokens.read().await;
-        for rt in refresh_tokens.values() {
-            if rt.id == refresh_token_id {
-                // Verify signature matches
-                if parts[2] == &rt.jwt_key[..16] {
-                    return Some(rt.user_id.clone());
-                }
-            }
-        }
-
-        None
+        Ok(refresh_token.user_id.clone())
     }
 }
 
@@ -355,19 +681,97 @@ struct AuthCode {
     created_at: SystemTime,
 }
 
+/// Why an access token was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessTokenError {
+    /// Not in the format of tokens issued here
+    Malformed,
+    /// Unknown, revoked or wrongly signed
+    Invalid,
+    /// Past its expiration
+    Expired,
+}
+
+impl std::fmt::Display for AccessTokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed | Self::Invalid => write!(f, "Invalid access token"),
+            Self::Expired => write!(f, "Access token expired"),
+        }
+    }
+}
+
+/// Kind of refresh token, like HA's `TOKEN_TYPE_*`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RefreshTokenType {
+    /// Issued to a client by the login flow, exchangeable at `/auth/token`
+    Normal,
+    /// Backs a long-lived access token, not exchangeable
+    LongLivedAccessToken,
+}
+
 /// A refresh token
 struct RefreshToken {
     id: String,
-    #[allow(dead_code)]
     token: String,
     user_id: String,
-    #[allow(dead_code)]
-    client_id: String,
+    /// Client the token was issued to, `None` for long-lived tokens
+    client_id: Option<String>,
+    /// Name given to a long-lived token
+    client_name: Option<String>,
+    client_icon: Option<String>,
+    token_type: RefreshTokenType,
+    /// Lifetime of access tokens created from this refresh token
+    access_token_expiration: Duration,
     jwt_key: String,
-    #[allow(dead_code)]
+    /// Hash of the one access token of a long-lived token, checked instead
+    /// of the signature
+    access_token_hash: Option<String>,
+    /// Expiration of a long-lived token, as a Unix timestamp
+    expire_at: Option<u64>,
     created_at: SystemTime,
 }
 
+/// A refresh token as listed by `auth/refresh_tokens`
+#[derive(Debug, Clone, Serialize)]
+pub struct RefreshTokenInfo {
+    pub id: String,
+    pub client_id: Option<String>,
+    pub client_name: Option<String>,
+    pub client_icon: Option<String>,
+    #[serde(rename = "type")]
+    pub token_type: &'static str,
+    pub created_at: DateTime<Utc>,
+    pub expire_at: Option<u64>,
+}
+
+/// A long-lived access token as persisted, without the token itself
+#[derive(Serialize, Deserialize)]
+struct StoredLongLivedToken {
+    id: String,
+    user_id: String,
+    client_name: String,
+    client_icon: Option<String>,
+    created_at: DateTime<Utc>,
+    expire_at: u64,
+    token_hash: String,
+}
+
+/// Persisted long-lived access tokens
+#[derive(Serialize, Deserialize)]
+struct LongLivedTokensData {
+    tokens: Vec<StoredLongLivedToken>,
+}
+
+/// SHA-256 of a token, hex encoded
+fn hash_token(token: &str) -> String {
+    ring::digest::digest(&ring::digest::SHA256, token.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
 /// A user in the auth system
 #[derive(Clone)]
 pub struct User {
@@ -462,6 +866,173 @@ fn generate_token() -> String {
     format!("{:032x}{:032x}", h.finish(), Ulid::new().0)
 }
 
+// =============================================================================
+// Trusted Networks
+// =============================================================================
+
+/// An IP network in CIDR notation, e.g. `192.168.1.0/24`
+///
+/// A bare address is a network of just that address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct IpNetwork {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    /// Whether `ip` is in the network
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, canonical_ip(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_matches(&net.octets(), &ip.octets(), self.prefix_len)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_matches(&net.octets(), &ip.octets(), self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.trim().split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (s.trim(), None),
+        };
+        let addr = canonical_ip(
+            addr.parse()
+                .map_err(|_| format!("invalid network address: {}", s))?,
+        );
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(len) => len
+                .parse()
+                .ok()
+                .filter(|len| *len <= max_len)
+                .ok_or_else(|| format!("invalid network prefix length: {}", s))?,
+            None => max_len,
+        };
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+impl TryFrom<String> for IpNetwork {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// IPv4 address of an IPv4-mapped IPv6 address, as seen on dual-stack sockets
+fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/// Whether the first `prefix_len` bits of two addresses match
+fn prefix_matches(network: &[u8], ip: &[u8], prefix_len: u8) -> bool {
+    let (bytes, bits) = ((prefix_len / 8) as usize, prefix_len % 8);
+    network[..bytes] == ip[..bytes]
+        && (bits == 0 || (network[bytes] ^ ip[bytes]) >> (8 - bits) == 0)
+}
+
+/// Access token seeded through the `auth_access_tokens` configuration
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConfiguredAccessToken {
+    pub token: String,
+    pub user_id: String,
+}
+
+/// `trusted_networks` auth provider configuration
+#[derive(Debug, Clone, Deserialize)]
+pub struct TrustedNetworksConfig {
+    /// Networks whose clients log in without a password
+    pub trusted_networks: Vec<IpNetwork>,
+    /// User clients in a network log in as (default: the owner); the most
+    /// specific matching network wins
+    #[serde(default)]
+    pub trusted_users: HashMap<IpNetwork, String>,
+    /// Reverse proxies whose forwarded header is trusted
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpNetwork>,
+    /// Header trusted proxies pass the client address chain in
+    #[serde(default = "default_forwarded_header")]
+    pub forwarded_header: String,
+}
+
+fn default_forwarded_header() -> String {
+    "X-Forwarded-For".to_string()
+}
+
+/// Auth provider logging in clients from trusted networks without a password
+#[derive(Debug, Clone)]
+pub struct TrustedNetworksProvider {
+    config: TrustedNetworksConfig,
+}
+
+impl TrustedNetworksProvider {
+    pub fn new(config: TrustedNetworksConfig) -> Self {
+        Self { config }
+    }
+
+    fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        self.config
+            .trusted_proxies
+            .iter()
+            .any(|net| net.contains(ip))
+    }
+
+    /// Address of the client behind a connection from `peer`
+    ///
+    /// The forwarded header is only believed when `peer` is a trusted proxy.
+    /// Its chain is walked from the closest hop, skipping trusted proxies, so
+    /// a client can't spoof its address by prepending entries. Returns `None`
+    /// if the header can't be parsed.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> Option<IpAddr> {
+        let mut client = canonical_ip(peer);
+        if !self.is_trusted_proxy(client) {
+            return Some(client);
+        }
+
+        let mut hops = Vec::new();
+        for value in headers.get_all(self.config.forwarded_header.as_str()) {
+            hops.extend(value.to_str().ok()?.split(',').map(str::trim));
+        }
+        for hop in hops.into_iter().rev() {
+            client = canonical_ip(hop.parse().ok()?);
+            if !self.is_trusted_proxy(client) {
+                break;
+            }
+        }
+        Some(client)
+    }
+
+    /// Whether a client may log in without a password
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.config
+            .trusted_networks
+            .iter()
+            .any(|net| net.contains(ip))
+    }
+
+    /// User configured for the most specific network containing `ip`
+    pub fn mapped_user(&self, ip: IpAddr) -> Option<&str> {
+        self.config
+            .trusted_users
+            .iter()
+            .filter(|(net, _)| net.contains(ip))
+            .max_by_key(|(net, _)| net.prefix_len)
+            .map(|(_, user_id)| user_id.as_str())
+    }
+}
+
 // =============================================================================
 // Request/Response Types
 // =============================================================================
@@ -510,6 +1081,9 @@ pub struct LoginFlowResponse {
     pub errors: Option<HashMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub result: Option<String>,
+    /// Why an aborted flow was aborted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
 }
 
 /// Data schema item for form fields
@@ -579,12 +1153,21 @@ pub async fn get_providers(State(auth): State<AuthState>) -> impl IntoResponse {
             .into_response();
     }
 
-    let response = ProvidersResponse {
-        providers: vec![AuthProvider {
-            name: "Home Assistant Local".to_string(),
+    let mut providers = vec![AuthProvider {
+        name: "Home Assistant Local".to_string(),
+        id: None,
+        provider_type: "homeassistant".to_string(),
+    }];
+    if auth.inner.trusted_networks.is_some() {
+        providers.push(AuthProvider {
+            name: "Trusted Networks".to_string(),
             id: None,
-            provider_type: "homeassistant".to_string(),
-        }],
+            provider_type: "trusted_networks".to_string(),
+        });
+    }
+
+    let response = ProvidersResponse {
+        providers,
         preselect_remember_me: true,
     };
 
@@ -592,7 +1175,15 @@ pub async fn get_providers(State(auth): State<AuthState>) -> impl IntoResponse {
 }
 
 /// POST /auth/login_flow - Start a new login flow
-pub async fn create_login_flow(State(auth): State<AuthState>, body: Bytes) -> impl IntoResponse {
+///
+/// Trusted networks logins finish right away: clients in a trusted network
+/// get an auth code, others get the flow aborted.
+pub async fn create_login_flow(
+    State(auth): State<AuthState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
     // Parse JSON from body without strict Content-Type checking
     let request: LoginFlowInitRequest = match serde_json::from_slice(&body) {
         Ok(r) => r,
@@ -608,6 +1199,31 @@ pub async fn create_login_flow(State(auth): State<AuthState>, body: Bytes) -> im
         }
     };
 
+    if request.handler.0 == "trusted_networks" {
+        let user_id = match connect_info {
+            Some(ConnectInfo(peer)) => auth.trusted_network_user(peer.ip(), &headers).await,
+            None => None,
+        };
+        let (result_type, result, reason) = match user_id {
+            Some(user_id) => {
+                let code = auth.create_auth_code(request.client_id, user_id).await;
+                ("create_entry", Some(code), None)
+            }
+            None => ("abort", None, Some("not_allowed".to_string())),
+        };
+        return Json(LoginFlowResponse {
+            flow_id: Ulid::new().to_string(),
+            handler: request.handler,
+            step_id: "init".to_string(),
+            result_type: result_type.to_string(),
+            data_schema: None,
+            errors: None,
+            result,
+            reason,
+        })
+        .into_response();
+    }
+
     let flow = auth
         .create_login_flow(request.client_id, request.redirect_uri)
         .await;
@@ -629,6 +1245,7 @@ pub async fn create_login_flow(State(auth): State<AuthState>, body: Bytes) -> im
         ]),
         errors: Some(HashMap::new()),
         result: None,
+        reason: None,
     };
 
     Json(response).into_response()
@@ -687,6 +1304,7 @@ pub async fn submit_login_flow(
                 data_schema: None,
                 errors: None,
                 result: Some(auth_code),
+                reason: None,
             };
             Json(response).into_response()
         }
@@ -963,4 +1581,258 @@ mod tests {
         let invalid = state.validate_access_token("invalid-token").await;
         assert!(invalid.is_none());
     }
+
+    /// Log in through a login flow and exchange the code for tokens
+    async fn login(state: &AuthState, client_id: &str) -> TokenResponse {
+        let flow = state
+            .create_login_flow(client_id.to_string(), "/".to_string())
+            .await;
+        let code = state
+            .complete_login_flow(&flow.flow_id, "user", "password")
+            .await
+            .unwrap();
+        state.exchange_auth_code(&code, client_id).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_expired_access_token_is_rejected() {
+        let state = AuthState::new_onboarded().with_access_token_expiration(Duration::ZERO);
+        let tokens = login(&state, "http://localhost:8123/").await;
+        assert_eq!(tokens.expires_in, 0);
+
+        assert_eq!(
+            state.check_access_token(&tokens.access_token).await,
+            Err(AccessTokenError::Expired)
+        );
+        assert!(state
+            .validate_access_token(&tokens.access_token)
+            .await
+            .is_none());
+        assert_eq!(
+            state
+                .check_access_token("unknown:99999999999:0123456789abcdef")
+                .await,
+            Err(AccessTokenError::Invalid)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token_grant() {
+        let state = AuthState::new_onboarded();
+        let client_id = "http://localhost:8123/";
+        let tokens = login(&state, client_id).await;
+        let refresh_token = tokens.refresh_token.unwrap();
+
+        let body = serde_urlencoded::to_string([
+            ("grant_type", "refresh_token"),
+            ("client_id", client_id),
+            ("refresh_token", refresh_token.as_str()),
+        ])
+        .unwrap();
+        let response = get_token(State(state.clone()), Bytes::from(body))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["token_type"], "Bearer");
+        assert_eq!(json["expires_in"], ACCESS_TOKEN_EXPIRATION_SECS);
+        assert!(json.get("refresh_token").is_none());
+        let access_token = json["access_token"].as_str().unwrap();
+        assert!(state.validate_access_token(access_token).await.is_some());
+
+        // Another client can't use the refresh token
+        let stolen = state
+            .refresh_access_token(&refresh_token, "http://evil.example/")
+            .await;
+        assert!(stolen.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_long_lived_access_token() {
+        let state = AuthState::new_onboarded();
+        let token = state
+            .create_long_lived_access_token("user-1", "CLI", None, LONG_LIVED_TOKEN_LIFESPAN)
+            .await;
+        assert_eq!(
+            state.check_access_token(&token).await,
+            Ok("user-1".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_long_lived_tokens_are_persisted_hashed() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(Storage::new(dir.path()));
+        let state = AuthState::new_onboarded().with_storage(storage.clone());
+        let token = state
+            .create_long_lived_access_token("user-1", "CLI", None, LONG_LIVED_TOKEN_LIFESPAN)
+            .await;
+
+        let stored =
+            std::fs::read_to_string(storage.file_path(LONG_LIVED_TOKENS_STORAGE_KEY)).unwrap();
+        assert!(stored.contains(&hash_token(&token)));
+        assert!(!stored.contains(&token));
+
+        // A restarted auth state still accepts the token until it's revoked
+        let restarted = AuthState::new_onboarded().with_storage(storage.clone());
+        restarted.load().await.unwrap();
+        assert_eq!(
+            restarted.validate_access_token(&token).await,
+            Some("user-1".to_string())
+        );
+        let tokens = restarted.refresh_tokens("user-1").await;
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].token_type, "long_lived_access_token");
+        assert!(
+            restarted
+                .delete_refresh_token("user-1", &tokens[0].id)
+                .await
+        );
+
+        let restarted = AuthState::new_onboarded().with_storage(storage);
+        restarted.load().await.unwrap();
+        assert!(restarted.validate_access_token(&token).await.is_none());
+    }
+
+    fn trusted_networks_config() -> TrustedNetworksConfig {
+        serde_json::from_value(serde_json::json!({
+            "trusted_networks": ["192.168.1.0/24", "fd00::/8"],
+            "trusted_users": {"192.168.1.50": "kitchen-tablet"},
+            "trusted_proxies": ["172.30.32.2"],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_trusted_networks_provider() {
+        let provider = TrustedNetworksProvider::new(trusted_networks_config());
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+
+        assert!(provider.is_trusted(ip("192.168.1.20")));
+        assert!(provider.is_trusted(ip("::ffff:192.168.1.20")));
+        assert!(provider.is_trusted(ip("fd12::1")));
+        assert!(!provider.is_trusted(ip("192.168.2.20")));
+        assert!(!provider.is_trusted(ip("8.8.8.8")));
+        assert_eq!(
+            provider.mapped_user(ip("192.168.1.50")),
+            Some("kitchen-tablet")
+        );
+        assert_eq!(provider.mapped_user(ip("192.168.1.20")), None);
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+
+        // The forwarded header only counts when it comes from a trusted proxy
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Forwarded-For", "192.168.1.20".parse().unwrap());
+        assert_eq!(
+            provider.client_ip(ip("8.8.8.8"), &headers),
+            Some(ip("8.8.8.8"))
+        );
+        assert_eq!(
+            provider.client_ip(ip("172.30.32.2"), &headers),
+            Some(ip("192.168.1.20"))
+        );
+
+        // Entries prepended by the client don't help it
+        headers.insert(
+            "X-Forwarded-For",
+            "192.168.1.20, 8.8.8.8, 172.30.32.2".parse().unwrap(),
+        );
+        assert_eq!(
+            provider.client_ip(ip("172.30.32.2"), &headers),
+            Some(ip("8.8.8.8"))
+        );
+        headers.insert("X-Forwarded-For", "not-an-ip".parse().unwrap());
+        assert_eq!(provider.client_ip(ip("172.30.32.2"), &headers), None);
+    }
+
+    #[tokio::test]
+    async fn test_trusted_networks_login() {
+        let state = AuthState::new_onboarded();
+        state.inner.users.write().await.insert(
+            "kitchen-tablet".to_string(),
+            User {
+                id: "kitchen-tablet".to_string(),
+                name: "Kitchen tablet".to_string(),
+                is_owner: false,
+                is_active: true,
+                credentials: Vec::new(),
+            },
+        );
+        let state = state.with_trusted_networks(trusted_networks_config());
+        let login = |peer: &str| {
+            let state = state.clone();
+            let peer: SocketAddr = peer.parse().unwrap();
+            async move {
+                let body = serde_json::json!({
+                    "client_id": "http://localhost:8123/",
+                    "handler": ["trusted_networks", null],
+                    "redirect_uri": "/",
+                });
+                let response = create_login_flow(
+                    State(state),
+                    Some(ConnectInfo(peer)),
+                    HeaderMap::new(),
+                    Bytes::from(body.to_string()),
+                )
+                .await
+                .into_response();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+
+        // In range: logged in without a password
+        let flow = login("192.168.1.20:50000").await;
+        assert_eq!(flow["type"], "create_entry");
+        let tokens = state
+            .exchange_auth_code(flow["result"].as_str().unwrap(), "http://localhost:8123/")
+            .await
+            .unwrap();
+        assert!(state
+            .validate_access_token(&tokens.access_token)
+            .await
+            .is_some());
+
+        // Mapped clients log in as their user
+        let flow = login("192.168.1.50:50000").await;
+        let tokens = state
+            .exchange_auth_code(flow["result"].as_str().unwrap(), "http://localhost:8123/")
+            .await
+            .unwrap();
+        assert_eq!(
+            state.validate_access_token(&tokens.access_token).await,
+            Some("kitchen-tablet".to_string())
+        );
+
+        // Out of range: denied
+        let flow = login("10.0.0.5:50000").await;
+        assert_eq!(flow["type"], "abort");
+        assert_eq!(flow["reason"], "not_allowed");
+        assert!(flow.get("result").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_configured_access_token() {
+        let state = AuthState::new_onboarded().with_access_token("seeded-token", "test-user");
+        assert_eq!(
+            state.validate_access_token("seeded-token").await,
+            Some("test-user".to_string())
+        );
+        assert_eq!(state.validate_access_token("other-token").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_trusted_users_must_exist() {
+        let state = AuthState::new_onboarded().with_trusted_networks(trusted_networks_config());
+        let peer = "192.168.1.50".parse().unwrap();
+        assert_eq!(
+            state.trusted_network_user(peer, &HeaderMap::new()).await,
+            None
+        );
+    }
 }
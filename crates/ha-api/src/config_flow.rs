@@ -4,6 +4,7 @@
 //! (e.g., Python-based via ha-py-bridge) is provided externally.
 
 use async_trait::async_trait;
+use ha_config_entries::ConfigEntry;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -46,6 +47,28 @@ pub struct FlowResult {
     pub last_step: Option<bool>,
     /// Preview component to display in frontend
     pub preview: Option<String>,
+    /// Unique ID the flow assigned to the entry (for create_entry)
+    ///
+    /// Used to detect already configured entries, not sent to clients.
+    #[serde(skip)]
+    pub unique_id: Option<String>,
+}
+
+impl FlowResult {
+    /// Turn this result into an abort with the given reason
+    ///
+    /// Used when a `create_entry` result can't be saved, e.g. because an
+    /// entry with the same unique ID is already configured.
+    pub fn into_abort(self, reason: &str) -> Self {
+        Self {
+            result_type: "abort".to_string(),
+            step_id: None,
+            title: None,
+            reason: Some(reason.to_string()),
+            result: None,
+            ..self
+        }
+    }
 }
 
 /// Form field schema
@@ -99,4 +122,57 @@ pub trait ConfigFlowHandler: Send + Sync {
     /// # Returns
     /// A list of active flow information as JSON values
     async fn list_flows(&self) -> Vec<serde_json::Value>;
+
+    /// Start a reauth flow for an existing config entry
+    ///
+    /// # Arguments
+    /// * `flow_id` - The ID to give the new flow
+    /// * `entry` - The config entry whose credentials need refreshing
+    ///
+    /// # Returns
+    /// The initial flow result (usually a form asking for new credentials)
+    async fn start_reauth_flow(
+        &self,
+        flow_id: &str,
+        entry: &ConfigEntry,
+    ) -> Result<FlowResult, String> {
+        let _ = entry;
+        Err(format!("Reauth flows are not supported ({})", flow_id))
+    }
+}
+
+/// Trait for options flow handlers
+///
+/// Options flows reconfigure an existing config entry. The flow's handler is
+/// the entry ID rather than an integration domain, and a `create_entry`
+/// result carries the new options for that entry.
+#[async_trait]
+pub trait OptionsFlowHandler: Send + Sync {
+    /// Start a new options flow for a config entry
+    ///
+    /// # Arguments
+    /// * `entry` - The config entry to reconfigure
+    /// * `show_advanced_options` - Whether to show advanced options in the flow
+    ///
+    /// # Returns
+    /// The initial flow result (usually a form to fill out)
+    async fn start_flow(
+        &self,
+        entry: &ConfigEntry,
+        show_advanced_options: bool,
+    ) -> Result<FlowResult, String>;
+
+    /// Continue an options flow with user input
+    ///
+    /// # Arguments
+    /// * `flow_id` - The ID of the flow to continue
+    /// * `user_input` - The user's input for the current step
+    ///
+    /// # Returns
+    /// The next flow result (form, create_entry, or abort)
+    async fn progress_flow(
+        &self,
+        flow_id: &str,
+        user_input: Option<serde_json::Value>,
+    ) -> Result<FlowResult, String>;
 }
@@ -0,0 +1,235 @@
+//! Config entry diagnostics
+//!
+//! Collects what's useful for debugging an integration into one JSON blob:
+//! the config entry, its devices and entities from the registries, and the
+//! integration's recent log lines. Integrations can add their own data by
+//! registering a [`DiagnosticsProvider`] for their domain.
+//!
+//! Sensitive values are replaced with [`REDACTED`] before anything leaves
+//! the server.
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use ha_config_entries::ConfigEntry;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::AppState;
+
+/// Replacement for redacted values
+pub const REDACTED: &str = "**REDACTED**";
+
+/// Keys whose values are always redacted (compared case-insensitively)
+pub const TO_REDACT: &[&str] = &[
+    "access_token",
+    "api_key",
+    "api_token",
+    "client_secret",
+    "password",
+    "pin",
+    "refresh_token",
+    "secret",
+    "token",
+    "webhook_id",
+];
+
+/// Integration-specific diagnostics
+///
+/// The returned data is redacted with [`redact`] before it's added to the
+/// blob, so providers don't have to strip the well-known keys themselves.
+#[async_trait]
+pub trait DiagnosticsProvider: Send + Sync {
+    /// Get diagnostics data for a config entry of the provider's domain
+    async fn config_entry_diagnostics(&self, state: &AppState, entry: &ConfigEntry) -> Value;
+}
+
+/// Diagnostics providers by integration domain
+pub type DiagnosticsProviders = Arc<DashMap<String, Arc<dyn DiagnosticsProvider>>>;
+
+/// Create a new empty diagnostics provider store
+pub fn new_diagnostics_providers() -> DiagnosticsProviders {
+    Arc::new(DashMap::new())
+}
+
+/// Redact the values of sensitive keys, at any depth
+pub fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let value = if is_sensitive(key) && !value.is_null() {
+                        json!(REDACTED)
+                    } else {
+                        redact(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        _ => value.clone(),
+    }
+}
+
+fn is_sensitive(key: &str) -> bool {
+    TO_REDACT.iter().any(|k| k.eq_ignore_ascii_case(key))
+}
+
+/// Build the diagnostics blob of a config entry
+///
+/// Returns `None` if the entry doesn't exist.
+pub async fn config_entry_diagnostics(state: &AppState, entry_id: &str) -> Option<Value> {
+    let entry = state.config_entries.read().await.get(entry_id)?;
+
+    let devices: Vec<Value> = state
+        .registries
+        .devices
+        .get_by_config_entry_id(entry_id)
+        .iter()
+        .map(|device| redact(&json!(device.as_ref())))
+        .collect();
+    let entities: Vec<Value> = state
+        .registries
+        .entities
+        .get_by_config_entry_id(entry_id)
+        .iter()
+        .map(|entity| {
+            let current = state.state_machine.get(&entity.entity_id);
+            let mut entity = json!(entity.as_ref());
+            entity["state"] = current.map_or(Value::Null, |s| json!(s));
+            redact(&entity)
+        })
+        .collect();
+
+    let provider = state
+        .diagnostics
+        .get(&entry.domain)
+        .map(|provider| provider.value().clone());
+    let data = match provider {
+        Some(provider) => redact(&provider.config_entry_diagnostics(state, &entry).await),
+        None => Value::Null,
+    };
+
+    Some(json!({
+        "home_assistant": {
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "entry": {
+            "entry_id": entry.entry_id,
+            "domain": entry.domain,
+            "title": entry.title,
+            "version": entry.version,
+            "minor_version": entry.minor_version,
+            "source": entry.source,
+            "state": entry.state,
+            "reason": entry.reason,
+            "unique_id": entry.unique_id,
+            "data": redact(&json!(entry.data)),
+            "options": redact(&json!(entry.options)),
+        },
+        "devices": devices,
+        "entities": entities,
+        "logs": state.system_log.integration_log_lines(&entry.domain),
+        "data": data,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_nested_keys() {
+        let data = json!({
+            "host": "192.168.1.2",
+            "Password": "hunter2",
+            "auth": {"access_token": "abc", "expires": 3600},
+            "accounts": [{"username": "me", "token": "xyz"}],
+            "pin": null,
+        });
+        assert_eq!(
+            redact(&data),
+            json!({
+                "host": "192.168.1.2",
+                "Password": REDACTED,
+                "auth": {"access_token": REDACTED, "expires": 3600},
+                "accounts": [{"username": "me", "token": REDACTED}],
+                "pin": null,
+            })
+        );
+    }
+
+    struct HueDiagnostics;
+
+    #[async_trait]
+    impl DiagnosticsProvider for HueDiagnostics {
+        async fn config_entry_diagnostics(&self, _state: &AppState, entry: &ConfigEntry) -> Value {
+            json!({"bridge": {"host": entry.data["host"], "api_key": "bridge-key"}})
+        }
+    }
+
+    #[tokio::test]
+    async fn test_config_entry_diagnostics_endpoint() {
+        use axum::body::Body;
+        use axum::http::{Request, StatusCode};
+        use ha_components::system_log::LogLevel;
+        use std::collections::HashMap;
+        use tower::ServiceExt;
+
+        let state = crate::tests::create_test_state();
+        let entry = ConfigEntry::new("hue", "Hue Bridge").with_data(HashMap::from([
+            ("host".to_string(), json!("192.168.1.2")),
+            ("password".to_string(), json!("hunter2")),
+        ]));
+        let entry = state.config_entries.read().await.add(entry).await.unwrap();
+        state.registries.entities.get_or_create(
+            "hue",
+            "light.diagnostics_lamp",
+            Some("lamp-1"),
+            Some(&entry.entry_id),
+            None,
+        );
+        state.system_log.log(
+            "homeassistant.components.hue",
+            LogLevel::Warning,
+            "Bridge unreachable",
+            None,
+            None,
+        );
+        state
+            .diagnostics
+            .insert("hue".to_string(), Arc::new(HueDiagnostics));
+
+        let app = crate::create_router(state);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/diagnostics/config_entry/{}", entry.entry_id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(!String::from_utf8_lossy(&body).contains("hunter2"));
+        let blob: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(blob["entry"]["data"]["password"], REDACTED);
+        assert_eq!(blob["entry"]["data"]["host"], "192.168.1.2");
+        assert_eq!(blob["data"]["bridge"]["api_key"], REDACTED);
+        assert_eq!(blob["entities"][0]["entity_id"], "light.diagnostics_lamp");
+        assert!(blob["logs"][0]
+            .as_str()
+            .unwrap()
+            .contains("Bridge unreachable"));
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_unknown_entry() {
+        let state = crate::tests::create_test_state();
+        assert!(config_entry_diagnostics(&state, "missing").await.is_none());
+    }
+}
@@ -0,0 +1,47 @@
+//! Moves entity states when the entity registry renames an entity
+//!
+//! The registry and the state store don't know about each other, so a rename
+//! is announced as an `entity_registry_updated` event carrying the old
+//! entity_id. This coordinator listens for it and moves the current state to
+//! the new entity_id, firing the usual state_changed events for the removal
+//! and the addition.
+
+use std::sync::Arc;
+
+use ha_core::events::{EntityRegistryUpdatedData, ENTITY_REGISTRY_UPDATED};
+use ha_core::EntityId;
+use ha_event_bus::{EventBus, ListenerId};
+use ha_state_store::StateStore;
+use tracing::{debug, warn};
+
+/// Move states of renamed entities for as long as the listener stays registered
+pub fn attach(event_bus: &EventBus, state_machine: Arc<StateStore>) -> ListenerId {
+    event_bus.listen_sync(
+        ENTITY_REGISTRY_UPDATED,
+        Arc::new(move |event| {
+            let Ok(EntityRegistryUpdatedData {
+                entity_id,
+                old_entity_id: Some(old_entity_id),
+                ..
+            }) = serde_json::from_value(event.data.clone())
+            else {
+                return;
+            };
+            let (Ok(old_id), Ok(new_id)) = (
+                old_entity_id.parse::<EntityId>(),
+                entity_id.parse::<EntityId>(),
+            ) else {
+                warn!(
+                    "Cannot move state of renamed entity {} -> {}",
+                    old_entity_id, entity_id
+                );
+                return;
+            };
+
+            if let Some(state) = state_machine.remove(&old_id, event.context.clone()) {
+                debug!("Moving state of {} to {}", old_entity_id, entity_id);
+                state_machine.set(new_id, state.state, state.attributes, event.context.clone());
+            }
+        }),
+    )
+}
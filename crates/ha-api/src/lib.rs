@@ -6,15 +6,18 @@
 
 pub mod auth;
 pub mod config_flow;
+pub mod diagnostics;
+pub mod entity_rename;
 pub mod frontend;
 pub mod manifest;
+pub mod permissions;
 pub mod persistent_notification;
 pub mod translations;
 mod websocket;
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
     response::IntoResponse,
     routing::{delete, get, post},
     Json, Router,
@@ -22,19 +25,21 @@ use axum::{
 use dashmap::DashMap;
 use ha_components::SystemLog;
 use ha_config::CoreConfig;
-use ha_config_entries::ConfigEntries;
+use ha_config_entries::{ConfigEntries, ConfigEntriesError};
 use ha_core::{Context, EntityId, Event};
 use ha_event_bus::EventBus;
+use ha_recorder::{LogbookEntry, Recorder, StateHistory};
 use ha_registries::Registries;
-use ha_service_registry::ServiceRegistry;
+use ha_service_registry::{ServiceError, ServiceRegistry};
 use ha_state_store::StateStore;
+use ha_template::TemplateEngine;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, CorsLayer};
 use tower_http::trace::TraceLayer;
-use tracing::info;
+use tracing::{info, warn};
 
 /// Application credential for OAuth2 integrations
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,6 +62,9 @@ pub fn new_application_credentials_store() -> ApplicationCredentialsStore {
     Arc::new(DashMap::new())
 }
 
+/// Maximum size of the `/api/error_log` response body
+const MAX_ERROR_LOG_BYTES: usize = 512 * 1024;
+
 /// Shared application state
 #[derive(Clone)]
 pub struct AppState {
@@ -69,6 +77,12 @@ pub struct AppState {
     pub config_entries: Arc<RwLock<ConfigEntries>>,
     /// Registries (entity, device, area, floor, label)
     pub registries: Arc<Registries>,
+    /// Template engine for on-demand and subscribed template rendering
+    pub template_engine: Arc<TemplateEngine>,
+    /// State history backend (history endpoints return nothing without one)
+    pub history: Option<Arc<StateHistory>>,
+    /// SQLite recorder, answers history queries instead of `history` when set
+    pub recorder: Option<Arc<Recorder>>,
     /// Persistent notification manager
     pub notifications: Arc<persistent_notification::PersistentNotificationManager>,
     /// System log manager
@@ -83,10 +97,16 @@ pub struct AppState {
     pub auth_state: auth::AuthState,
     /// Config flow handler for integration setup
     pub config_flow_handler: Option<Arc<dyn config_flow::ConfigFlowHandler>>,
+    /// Options flow handler for reconfiguring existing config entries
+    pub options_flow_handler: Option<Arc<dyn config_flow::OptionsFlowHandler>>,
     /// Application credentials for OAuth2 integrations
     pub application_credentials: ApplicationCredentialsStore,
     /// Path to Home Assistant components directory (for icons, etc.)
     pub components_path: Option<std::path::PathBuf>,
+    /// Integration diagnostics providers by domain
+    pub diagnostics: diagnostics::DiagnosticsProviders,
+    /// Metrics of the open WebSocket connections
+    pub websocket_connections: websocket::ConnectionRegistry,
 }
 
 /// API status response
@@ -141,11 +161,15 @@ struct UnitSystemResponse {
 pub struct StateResponse {
     pub entity_id: String,
     pub state: String,
-    pub attributes: HashMap<String, serde_json::Value>,
+    /// Omitted with `minimal_response` or `no_attributes`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<HashMap<String, serde_json::Value>>,
     pub last_changed: String,
     pub last_updated: String,
     pub last_reported: String,
-    pub context: ContextResponse,
+    /// Omitted with `minimal_response`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<ContextResponse>,
 }
 
 #[derive(Serialize)]
@@ -208,18 +232,125 @@ pub struct FireEventResponse {
     pub message: String,
 }
 
+/// Options controlling which state fields are returned
+#[derive(Deserialize, Default)]
+pub struct StateFormatQuery {
+    /// Omit attributes and context
+    pub minimal_response: Option<String>,
+    /// Omit attributes
+    pub no_attributes: Option<String>,
+}
+
+impl StateFormatQuery {
+    /// Whether a flag was passed, e.g. `?no_attributes` or `?no_attributes=true`
+    fn flag(value: &Option<String>) -> bool {
+        value
+            .as_deref()
+            .is_some_and(|v| !matches!(v.to_lowercase().as_str(), "false" | "0"))
+    }
+
+    fn minimal_response(&self) -> bool {
+        Self::flag(&self.minimal_response)
+    }
+
+    fn no_attributes(&self) -> bool {
+        Self::flag(&self.no_attributes)
+    }
+}
+
+/// Query parameters for GET /api/states
+#[derive(Deserialize)]
+pub struct StatesQuery {
+    /// Comma-separated domains to include
+    pub domain: Option<String>,
+    /// `name:value` attribute filter, or just `name` to require the attribute
+    pub attribute: Option<String>,
+    /// `false` leaves out entities disabled in the entity registry
+    pub include_disabled: Option<String>,
+    /// `false` leaves out entities hidden in the entity registry
+    pub include_hidden: Option<String>,
+    #[serde(flatten)]
+    pub format: StateFormatQuery,
+}
+
+/// Query parameters for GET /api/history/period
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    /// Comma-separated entity_ids to include
+    pub filter_entity_id: Option<String>,
+    /// End of the period (defaults to one day after the start)
+    pub end_time: Option<String>,
+    /// Only return `state` and `last_changed` for intermediate states
+    pub minimal_response: Option<String>,
+    /// Omit state attributes
+    pub no_attributes: Option<String>,
+    /// Leave out attribute-only changes (default true)
+    pub significant_changes_only: Option<String>,
+}
+
+/// Query parameters for GET /api/logbook
+#[derive(Deserialize)]
+pub struct LogbookQuery {
+    /// Only entries about this entity
+    pub entity: Option<String>,
+    /// End of the period (defaults to one day after the start)
+    pub end_time: Option<String>,
+}
+
+/// Template render request
+#[derive(Deserialize)]
+pub struct RenderTemplateRequest {
+    pub template: String,
+    #[serde(default)]
+    pub variables: HashMap<String, serde_json::Value>,
+}
+
 /// Error response
 #[derive(Serialize)]
 pub struct ErrorResponse {
     pub message: String,
 }
 
+/// CORS layer allowing the configured origins to make credentialed requests
+///
+/// Browsers don't allow credentials with a wildcard origin, so only explicit
+/// origins are allowed; others get no CORS headers.
+fn cors_layer(config: &CoreConfig) -> CorsLayer {
+    let origins: Vec<HeaderValue> = config
+        .cors_allowed_origins()
+        .iter()
+        .filter_map(|origin| match HeaderValue::from_str(origin) {
+            Ok(origin) => Some(origin),
+            Err(_) => {
+                warn!("Invalid CORS allowed origin: {}", origin);
+                None
+            }
+        })
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            header::ORIGIN,
+            header::ACCEPT,
+            HeaderName::from_static("x-requested-with"),
+        ])
+        .allow_credentials(true)
+}
+
 /// Create the API router
 pub fn create_router(state: AppState) -> Router {
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    let cors = cors_layer(&state.config);
 
     // Auth routes (separate state for auth endpoints)
     let auth_router = Router::new()
@@ -250,6 +381,16 @@ pub fn create_router(state: AppState) -> Router {
         // Event endpoints
         .route("/api/events", get(get_events))
         .route("/api/events/:event_type", post(fire_event))
+        // History endpoints
+        .route("/api/history/period", get(get_history_period))
+        .route("/api/history/period/:timestamp", get(get_history_period_from))
+        // Logbook endpoints
+        .route("/api/logbook", get(get_logbook))
+        .route("/api/logbook/:timestamp", get(get_logbook_from))
+        // Template endpoint
+        .route("/api/template", post(render_template))
+        // Error log
+        .route("/api/error_log", get(get_error_log))
         // Health check
         .route("/api/health", get(health_check))
         // Onboarding status (always returns "done" for all steps)
@@ -271,6 +412,11 @@ pub fn create_router(state: AppState) -> Router {
                 .post(progress_config_flow)
                 .delete(cancel_config_flow),
         )
+        // Diagnostics
+        .route(
+            "/api/diagnostics/config_entry/:entry_id",
+            get(get_config_entry_diagnostics),
+        )
         .layer(TraceLayer::new_for_http())
         .layer(cors)
         .with_state(state.clone())
@@ -292,7 +438,12 @@ pub async fn start_server(state: AppState, addr: &str) -> std::io::Result<()> {
     let router = create_router(state);
     let listener = tokio::net::TcpListener::bind(addr).await?;
     info!("API server listening on {}", addr);
-    axum::serve(listener, router).await
+    // Client addresses are needed for trusted networks logins
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await
 }
 
 // ==================== Handlers ====================
@@ -374,36 +525,157 @@ async fn get_config(State(state): State<AppState>) -> Json<ConfigResponse> {
 }
 
 /// GET /api/states - Returns all entity states
-async fn get_states(State(state): State<AppState>) -> Json<Vec<StateResponse>> {
-    let states = state.state_machine.all();
-    let responses: Vec<StateResponse> = states.iter().map(state_to_response).collect();
+///
+/// `?domain=light,switch` limits the result to the given domains and
+/// `?attribute=device_class:temperature` to states whose attribute has that
+/// value (or just has the attribute, when no value is given).
+async fn get_states(
+    State(state): State<AppState>,
+    Query(query): Query<StatesQuery>,
+) -> Json<Vec<StateResponse>> {
+    let states = match query.domain.as_deref() {
+        Some(domains) => domains
+            .split(',')
+            .map(str::trim)
+            .filter(|domain| !domain.is_empty())
+            .flat_map(|domain| state.state_machine.domain_states(domain))
+            .collect(),
+        None => state.state_machine.all(),
+    };
+
+    let attribute_filter = query
+        .attribute
+        .as_deref()
+        .map(|filter| match filter.split_once(':') {
+            Some((name, value)) => (name, Some(value)),
+            None => (filter, None),
+        });
+
+    // Both default to true, so a bare `?include_disabled` keeps them too
+    let include_disabled =
+        query.include_disabled.is_none() || StateFormatQuery::flag(&query.include_disabled);
+    let include_hidden =
+        query.include_hidden.is_none() || StateFormatQuery::flag(&query.include_hidden);
+
+    let responses: Vec<StateResponse> = states
+        .iter()
+        .filter(|s| {
+            registry_allows(
+                &state.registries,
+                &s.entity_id.to_string(),
+                include_disabled,
+                include_hidden,
+            )
+        })
+        .filter(|s| match attribute_filter {
+            Some((name, expected)) => s
+                .attributes
+                .get(name)
+                .is_some_and(|value| expected.map_or(true, |e| attribute_matches(value, e))),
+            None => true,
+        })
+        .map(|s| format_state_response(s, &query.format))
+        .collect();
     Json(responses)
 }
 
+/// Whether the entity registry lets a state through the disabled/hidden filters
+///
+/// Entities without a registry entry are never disabled or hidden.
+fn registry_allows(
+    registries: &Registries,
+    entity_id: &str,
+    include_disabled: bool,
+    include_hidden: bool,
+) -> bool {
+    if include_disabled && include_hidden {
+        return true;
+    }
+    registries.entities.get(entity_id).map_or(true, |entry| {
+        (include_disabled || !entry.is_disabled()) && (include_hidden || !entry.is_hidden())
+    })
+}
+
+/// Whether an attribute value equals the text given in a query filter
+fn attribute_matches(value: &serde_json::Value, expected: &str) -> bool {
+    match value {
+        serde_json::Value::String(s) => s == expected,
+        other => serde_json::from_str::<serde_json::Value>(expected).is_ok_and(|e| e == *other),
+    }
+}
+
 /// Convert a State to a StateResponse
 fn state_to_response(s: &ha_core::State) -> StateResponse {
+    format_state_response(s, &StateFormatQuery::default())
+}
+
+/// Convert a State to a StateResponse, leaving out fields the query excludes
+fn format_state_response(s: &ha_core::State, format: &StateFormatQuery) -> StateResponse {
+    let minimal = format.minimal_response();
     StateResponse {
         entity_id: s.entity_id.to_string(),
         state: s.state.clone(),
-        attributes: s.attributes.clone(),
+        attributes: (!minimal && !format.no_attributes()).then(|| s.attributes.clone()),
         last_changed: s.last_changed.to_rfc3339(),
         last_updated: s.last_updated.to_rfc3339(),
         last_reported: s.last_reported.unwrap_or(s.last_updated).to_rfc3339(),
-        context: ContextResponse {
+        context: (!minimal).then(|| ContextResponse {
             id: s.context.id.to_string(),
             parent_id: s.context.parent_id.clone(),
             user_id: s.context.user_id.clone(),
-        },
+        }),
     }
 }
 
+/// Compute the ETag for a state
+///
+/// Based on the state value and `last_updated`, which changes with every
+/// state or attribute change but not when a state is merely reported again.
+fn state_etag(s: &ha_core::State) -> String {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    s.entity_id.to_string().hash(&mut hasher);
+    s.state.hash(&mut hasher);
+    s.last_updated.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+/// Whether an `If-None-Match` header value matches the given ETag
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
 /// GET /api/states/{entity_id} - Returns a single entity state
+///
+/// Responses carry an ETag; a matching `If-None-Match` yields 304 Not Modified.
 async fn get_state(
     State(state): State<AppState>,
     Path(entity_id): Path<String>,
-) -> Result<Json<StateResponse>, (StatusCode, Json<ErrorResponse>)> {
+    Query(format): Query<StateFormatQuery>,
+    headers: HeaderMap,
+) -> Result<axum::response::Response, (StatusCode, Json<ErrorResponse>)> {
     match state.state_machine.get(&entity_id) {
-        Some(s) => Ok(Json(state_to_response(&s))),
+        Some(s) => {
+            let etag = state_etag(&s);
+            let not_modified = headers
+                .get(header::IF_NONE_MATCH)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| etag_matches(value, &etag));
+
+            let mut response = if not_modified {
+                StatusCode::NOT_MODIFIED.into_response()
+            } else {
+                Json(format_state_response(&s, &format)).into_response()
+            };
+            if let Ok(value) = HeaderValue::from_str(&etag) {
+                response.headers_mut().insert(header::ETAG, value);
+            }
+            Ok(response)
+        }
         None => Err((
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
@@ -413,12 +685,44 @@ async fn get_state(
     }
 }
 
+/// User of a request's bearer token, for tokens issued by the auth API
+async fn request_user_id(state: &AppState, headers: &HeaderMap) -> Option<String> {
+    let token = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    state.auth_state.validate_access_token(token).await
+}
+
+/// 401 response for requests that may not make a change
+fn unauthorized() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(ErrorResponse {
+            message: "Unauthorized".to_string(),
+        }),
+    )
+}
+
 /// POST /api/states/{entity_id} - Sets an entity state
 async fn set_state(
     State(state): State<AppState>,
     Path(entity_id): Path<String>,
+    headers: HeaderMap,
     Json(request): Json<SetStateRequest>,
 ) -> Result<Json<StateResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user_id = request_user_id(&state, &headers)
+        .await
+        .ok_or_else(unauthorized)?;
+    if !state
+        .auth_state
+        .user_permissions(&user_id)
+        .can_control(&entity_id)
+    {
+        return Err(unauthorized());
+    }
+
     // Parse entity ID
     let parts: Vec<&str> = entity_id.splitn(2, '.').collect();
     if parts.len() != 2 {
@@ -447,7 +751,7 @@ async fn set_state(
         entity,
         &request.state,
         request.attributes.clone(),
-        Context::new(),
+        Context::with_user(user_id),
     );
 
     // Return the new state
@@ -504,22 +808,54 @@ async fn get_services(State(state): State<AppState>) -> axum::response::Response
 async fn call_service(
     State(state): State<AppState>,
     Path((domain, service)): Path<(String, String)>,
+    headers: HeaderMap,
     Json(request): Json<ServiceCallRequest>,
 ) -> Result<Json<Vec<StateResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let user_id = request_user_id(&state, &headers)
+        .await
+        .ok_or_else(unauthorized)?;
     let service_data = serde_json::to_value(&request.service_data).unwrap_or_default();
 
-    match state
+    // Collect the states this call changes, like HA's AsyncTrackStates
+    let context = Context::with_user(user_id);
+    let changed = Arc::new(std::sync::Mutex::new(Vec::<ha_core::State>::new()));
+    let listener = state.event_bus.listen_sync(
+        ha_core::events::STATE_CHANGED,
+        Arc::new({
+            let changed = changed.clone();
+            let context_id = context.id.clone();
+            move |event| {
+                // Skip changes from concurrent calls and other sources
+                if event.context.id != context_id
+                    && event.context.parent_id.as_deref() != Some(context_id.as_str())
+                {
+                    return;
+                }
+                if let Ok(ha_core::events::StateChangedData {
+                    new_state: Some(new_state),
+                    ..
+                }) = serde_json::from_value(event.data.clone())
+                {
+                    let mut changed = changed.lock().unwrap();
+                    changed.retain(|s| s.entity_id != new_state.entity_id);
+                    changed.push(new_state);
+                }
+            }
+        }),
+    );
+
+    let result = state
         .service_registry
-        .call(&domain, &service, service_data, Context::new(), false)
-        .await
-    {
+        .call(&domain, &service, service_data, context, false)
+        .await;
+    state.event_bus.remove_sync_listener(listener);
+
+    match result {
         Ok(_) => {
-            // Return empty array (matching Python HA behavior)
-            // In Python HA, most service calls return an empty array
-            // Some service calls that affect entities return those states,
-            // but that requires tracking which entities were affected
-            Ok(Json(vec![]))
+            let changed = changed.lock().unwrap();
+            Ok(Json(changed.iter().map(state_to_response).collect()))
         }
+        Err(ServiceError::PermissionDenied(_)) => Err(unauthorized()),
         Err(e) => Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -589,6 +925,244 @@ async fn fire_event(
     })
 }
 
+/// History states grouped per entity, or an error message
+type HistoryResponse = Result<Json<Vec<Vec<serde_json::Value>>>, (StatusCode, Json<ErrorResponse>)>;
+
+/// GET /api/error_log - Returns recent warnings and errors as log text
+async fn get_error_log(State(state): State<AppState>) -> String {
+    state.system_log.error_log(MAX_ERROR_LOG_BYTES)
+}
+
+/// GET /api/diagnostics/config_entry/{entry_id} - Redacted diagnostics of an entry
+async fn get_config_entry_diagnostics(
+    State(state): State<AppState>,
+    Path(entry_id): Path<String>,
+) -> impl IntoResponse {
+    match diagnostics::config_entry_diagnostics(&state, &entry_id).await {
+        Some(blob) => (StatusCode::OK, Json(blob)),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "message": format!("Invalid entry specified: {}", entry_id)
+            })),
+        ),
+    }
+}
+
+/// GET /api/history/period - History for the past day
+async fn get_history_period(state: State<AppState>, query: Query<HistoryQuery>) -> HistoryResponse {
+    history_period(state, None, query).await
+}
+
+/// GET /api/history/period/{timestamp} - History starting at a timestamp
+async fn get_history_period_from(
+    state: State<AppState>,
+    Path(timestamp): Path<String>,
+    query: Query<HistoryQuery>,
+) -> HistoryResponse {
+    history_period(state, Some(timestamp), query).await
+}
+
+/// Build HA's history response: one array of state objects per entity
+async fn history_period(
+    State(state): State<AppState>,
+    timestamp: Option<String>,
+    Query(query): Query<HistoryQuery>,
+) -> HistoryResponse {
+    let bad_request = |message: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                message: message.to_string(),
+            }),
+        )
+    };
+
+    let now = chrono::Utc::now();
+    let start = match timestamp {
+        Some(ts) => parse_history_time(&ts).ok_or_else(|| bad_request("Invalid datetime"))?,
+        None => now - chrono::Duration::days(1),
+    };
+    let end = match query.end_time.as_deref() {
+        Some(ts) => parse_history_time(ts).ok_or_else(|| bad_request("Invalid end_time"))?,
+        None => start + chrono::Duration::days(1),
+    };
+
+    if start > now {
+        return Ok(Json(Vec::new()));
+    }
+
+    let entity_ids: Option<Vec<String>> = query.filter_entity_id.as_deref().map(|ids| {
+        ids.split(',')
+            .map(|id| id.trim().to_lowercase())
+            .filter(|id| !id.is_empty())
+            .collect()
+    });
+    let minimal_response = StateFormatQuery::flag(&query.minimal_response);
+    let no_attributes = StateFormatQuery::flag(&query.no_attributes);
+    let significant_changes_only = query.significant_changes_only.as_deref().map_or(true, |v| {
+        !matches!(v.to_lowercase().as_str(), "false" | "0")
+    });
+
+    let groups = if let Some(recorder) = state.recorder.clone() {
+        // SQLite queries block, keep them off the async workers
+        tokio::task::spawn_blocking(move || {
+            recorder.get_significant_states(
+                start,
+                end,
+                entity_ids.as_deref(),
+                significant_changes_only,
+            )
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result.map_err(|e| e.to_string()))
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { message: e }),
+            )
+        })?
+    } else if let Some(history) = state.history.as_ref() {
+        let mut groups = history.period(start, end, entity_ids.as_deref());
+        if significant_changes_only {
+            for states in &mut groups {
+                let mut first = true;
+                states.retain(|s| std::mem::take(&mut first) || ha_recorder::is_significant(s));
+            }
+        }
+        groups
+    } else {
+        return Ok(Json(Vec::new()));
+    };
+
+    let response = groups
+        .into_iter()
+        .map(|states| {
+            let last = states.len() - 1;
+            states
+                .iter()
+                .enumerate()
+                .map(|(i, s)| {
+                    if minimal_response && i != 0 && i != last {
+                        return serde_json::json!({
+                            "state": s.state,
+                            "last_changed": s.last_changed.to_rfc3339(),
+                        });
+                    }
+                    let attributes = if no_attributes {
+                        serde_json::json!({})
+                    } else {
+                        serde_json::json!(s.attributes)
+                    };
+                    serde_json::json!({
+                        "entity_id": s.entity_id.to_string(),
+                        "state": s.state,
+                        "attributes": attributes,
+                        "last_changed": s.last_changed.to_rfc3339(),
+                        "last_updated": s.last_updated.to_rfc3339(),
+                    })
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok(Json(response))
+}
+
+type LogbookResponse = Result<Json<Vec<LogbookEntry>>, (StatusCode, Json<ErrorResponse>)>;
+
+/// GET /api/logbook - Logbook of the past day
+async fn get_logbook(state: State<AppState>, query: Query<LogbookQuery>) -> LogbookResponse {
+    logbook(state, None, query).await
+}
+
+/// GET /api/logbook/{timestamp} - Logbook starting at a timestamp
+async fn get_logbook_from(
+    state: State<AppState>,
+    Path(timestamp): Path<String>,
+    query: Query<LogbookQuery>,
+) -> LogbookResponse {
+    logbook(state, Some(timestamp), query).await
+}
+
+async fn logbook(
+    State(state): State<AppState>,
+    timestamp: Option<String>,
+    Query(query): Query<LogbookQuery>,
+) -> LogbookResponse {
+    let error = |status: StatusCode, message: String| (status, Json(ErrorResponse { message }));
+
+    let start = match timestamp {
+        Some(ts) => parse_history_time(&ts)
+            .ok_or_else(|| error(StatusCode::BAD_REQUEST, "Invalid datetime".to_string()))?,
+        None => chrono::Utc::now() - chrono::Duration::days(1),
+    };
+    let end = match query.end_time.as_deref() {
+        Some(ts) => parse_history_time(ts)
+            .ok_or_else(|| error(StatusCode::BAD_REQUEST, "Invalid end_time".to_string()))?,
+        None => start + chrono::Duration::days(1),
+    };
+    let entity_ids = query
+        .entity
+        .map(|entity_id| vec![entity_id.trim().to_lowercase()]);
+
+    logbook_entries(&state, start, end, entity_ids)
+        .await
+        .map(Json)
+        .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// Logbook entries in `[start, end]`, empty without a recorder
+pub(crate) async fn logbook_entries(
+    state: &AppState,
+    start: chrono::DateTime<chrono::Utc>,
+    end: chrono::DateTime<chrono::Utc>,
+    entity_ids: Option<Vec<String>>,
+) -> Result<Vec<LogbookEntry>, String> {
+    let Some(recorder) = state.recorder.clone() else {
+        return Ok(Vec::new());
+    };
+    tokio::task::spawn_blocking(move || recorder.logbook(start, end, entity_ids.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Parse an ISO 8601 history timestamp, treating naive times as UTC
+fn parse_history_time(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    // A literal '+' in a query string decodes to a space
+    let value = value.trim().replace(' ', "+");
+    chrono::DateTime::parse_from_rfc3339(&value)
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+        .ok()
+        .or_else(|| {
+            chrono::NaiveDateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M:%S%.f")
+                .ok()
+                .map(|dt| dt.and_utc())
+        })
+}
+
+/// POST /api/template - Renders a template and returns the result as text
+async fn render_template(
+    State(state): State<AppState>,
+    Json(request): Json<RenderTemplateRequest>,
+) -> Result<String, (StatusCode, Json<ErrorResponse>)> {
+    state
+        .template_engine
+        .render_with_variables(&request.template, request.variables)
+        // HA strips surrounding whitespace from rendered templates
+        .map(|rendered| rendered.trim().to_string())
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    message: format!("Error rendering template: {}", e),
+                }),
+            )
+        })
+}
+
 /// DELETE /api/config/config_entries/entry/{entry_id} - Delete a config entry
 async fn delete_config_entry(
     State(state): State<AppState>,
@@ -820,22 +1394,28 @@ async fn progress_config_flow(
     let input = Some(user_input);
 
     match config_flow_handler.progress_flow(&flow_id, input).await {
-        Ok(flow_result) => {
+        Ok(mut flow_result) => {
             // If the flow created an entry, save it
             if flow_result.result_type == "create_entry" {
                 if let Some(ref result_data) = flow_result.result {
-                    if let Err(e) = save_config_entry_from_flow(
+                    match save_config_entry_from_flow(
                         &state,
                         &flow_result.handler,
                         flow_result.title.as_deref().unwrap_or(&flow_result.handler),
+                        flow_result.unique_id.as_deref(),
                         result_data,
                     )
                     .await
                     {
-                        tracing::warn!("Failed to save config entry: {}", e);
+                        Ok(()) => {}
+                        Err(ConfigEntriesError::AlreadyConfigured { .. }) => {
+                            flow_result = flow_result.into_abort("already_configured");
+                        }
+                        Err(e) => tracing::warn!("Failed to save config entry: {}", e),
                     }
                 }
             }
+            finish_reauth_flow(&state, &flow_result).await;
 
             (
                 StatusCode::OK,
@@ -867,26 +1447,47 @@ async fn cancel_config_flow(
     )
 }
 
+/// Clear an entry's reauth marker once its reauth flow has finished
+///
+/// A flow aborting with `reauth_successful` has stored new credentials, so
+/// the entry is reloaded to pick them up.
+pub(crate) async fn finish_reauth_flow(state: &AppState, flow_result: &config_flow::FlowResult) {
+    if flow_result.result_type != "create_entry" && flow_result.result_type != "abort" {
+        return;
+    }
+
+    let manager = state.config_entries.read().await;
+    let Some(entry_id) = manager.reauth_entry_for_flow(&flow_result.flow_id) else {
+        return;
+    };
+    manager.reauth_finished(&entry_id);
+
+    if flow_result.reason.as_deref() == Some("reauth_successful") {
+        if let Err(e) = manager.reload(&entry_id).await {
+            tracing::warn!("Failed to reload entry {} after reauth: {}", entry_id, e);
+        }
+    }
+}
+
 /// Save a config entry from a completed flow
 async fn save_config_entry_from_flow(
     state: &AppState,
     domain: &str,
     title: &str,
+    unique_id: Option<&str>,
     data: &serde_json::Value,
-) -> Result<(), String> {
+) -> Result<(), ConfigEntriesError> {
     use ha_config_entries::ConfigEntry;
 
-    let entry = ConfigEntry::new(domain, title).with_data(
+    let mut entry = ConfigEntry::new(domain, title).with_data(
         data.as_object()
             .map(|m| m.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
             .unwrap_or_default(),
     );
+    entry.unique_id = unique_id.map(str::to_string);
 
     let config_entries = state.config_entries.write().await;
-    config_entries
-        .add(entry)
-        .await
-        .map_err(|e| format!("Failed to add config entry: {}", e))?;
+    config_entries.add(entry).await?;
 
     info!("Saved config entry for {} ({})", domain, title);
     Ok(())
@@ -900,7 +1501,7 @@ mod tests {
     use axum::http::{Request, StatusCode};
     use tower::ServiceExt;
 
-    fn create_test_state() -> AppState {
+    pub(crate) fn create_test_state() -> AppState {
         use ha_registries::Storage;
 
         let event_bus = Arc::new(EventBus::new());
@@ -908,11 +1509,15 @@ mod tests {
         let service_registry = Arc::new(ServiceRegistry::new());
         // Use a temp directory for test registries
         let temp_dir = std::env::temp_dir().join("ha-api-test");
-        let registries = Arc::new(Registries::new(&temp_dir));
+        let registries = Arc::new(Registries::with_event_bus(&temp_dir, event_bus.clone()));
         let storage = Arc::new(Storage::new(&temp_dir));
         let config_entries = Arc::new(RwLock::new(ConfigEntries::new(storage)));
         let notifications = persistent_notification::create_manager();
         let system_log = Arc::new(SystemLog::with_defaults());
+        let template_engine = Arc::new(TemplateEngine::with_registries(
+            state_machine.clone(),
+            registries.clone(),
+        ));
         AppState {
             event_bus,
             state_machine,
@@ -921,6 +1526,9 @@ mod tests {
             components: Arc::new(vec![]),
             components_path: None,
             config_entries,
+            template_engine,
+            history: None,
+            recorder: None,
             registries,
             notifications,
             system_log,
@@ -929,7 +1537,10 @@ mod tests {
             frontend_config: None,
             auth_state: auth::AuthState::new_onboarded(),
             config_flow_handler: None,
+            options_flow_handler: None,
             application_credentials: new_application_credentials_store(),
+            diagnostics: diagnostics::new_diagnostics_providers(),
+            websocket_connections: Default::default(),
         }
     }
 
@@ -1000,6 +1611,134 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_set_state_checks_token_permissions() {
+        use crate::permissions::UserPermissions;
+
+        let state = create_test_state();
+        state
+            .auth_state
+            .set_user_permissions("reader", UserPermissions::read_only());
+        let lifespan = auth::LONG_LIVED_TOKEN_LIFESPAN;
+        let reader = state
+            .auth_state
+            .create_long_lived_access_token("reader", "reader", None, lifespan)
+            .await;
+        let owner = state
+            .auth_state
+            .create_long_lived_access_token("owner", "owner", None, lifespan)
+            .await;
+        let app = create_router(state);
+
+        let set = |token: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/states/sensor.permission_test")
+                .header("Authorization", format!("Bearer {}", token))
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"state": "1"}"#))
+                .unwrap()
+        };
+        let response = app.clone().oneshot(set(&reader)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        // Unknown tokens don't get anywhere either
+        let response = app.clone().oneshot(set("garbage")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.oneshot(set(&owner)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_call_service_checks_token_permissions() {
+        use crate::permissions::{service_interceptor, UserPermissions};
+
+        let state = create_test_state();
+        state.service_registry.add_interceptor(service_interceptor(
+            state.auth_state.clone(),
+            state.registries.clone(),
+        ));
+        state.service_registry.register(
+            "light",
+            "turn_on",
+            |call| async move { Ok(Some(serde_json::json!(call.context.user_id))) },
+            None,
+            ha_core::SupportsResponse::Optional,
+        );
+        state
+            .auth_state
+            .set_user_permissions("reader", UserPermissions::read_only());
+        let lifespan = auth::LONG_LIVED_TOKEN_LIFESPAN;
+        let reader = state
+            .auth_state
+            .create_long_lived_access_token("reader", "reader", None, lifespan)
+            .await;
+        let owner = state
+            .auth_state
+            .create_long_lived_access_token("owner", "owner", None, lifespan)
+            .await;
+        let app = create_router(state);
+
+        let call = |token: Option<&str>| {
+            let mut request = Request::builder()
+                .method("POST")
+                .uri("/api/services/light/turn_on")
+                .header("Content-Type", "application/json");
+            if let Some(token) = token {
+                request = request.header("Authorization", format!("Bearer {}", token));
+            }
+            request
+                .body(Body::from(r#"{"entity_id": "light.porch"}"#))
+                .unwrap()
+        };
+        let response = app.clone().oneshot(call(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.clone().oneshot(call(Some(&reader))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.oneshot(call(Some(&owner))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_cors_allows_only_configured_origins() {
+        let mut state = create_test_state();
+        state.config = Arc::new(CoreConfig {
+            external_url: Some("https://home.example.com".to_string()),
+            ..Default::default()
+        });
+        let app = create_router(state);
+
+        let preflight = |origin: &str| {
+            Request::builder()
+                .method("OPTIONS")
+                .uri("/api/states")
+                .header("Origin", origin)
+                .header("Access-Control-Request-Method", "GET")
+                .header("Access-Control-Request-Headers", "authorization")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(preflight("https://home.example.com"))
+            .await
+            .unwrap();
+        let headers = response.headers();
+        assert_eq!(
+            headers["access-control-allow-origin"],
+            "https://home.example.com"
+        );
+        assert_eq!(headers["access-control-allow-credentials"], "true");
+
+        let response = app
+            .oneshot(preflight("https://evil.example"))
+            .await
+            .unwrap();
+        let headers = response.headers();
+        // Without an allowed origin browsers ignore the other CORS headers
+        assert!(headers.get("access-control-allow-origin").is_none());
+    }
+
     #[tokio::test]
     async fn test_get_state_not_found() {
         let state = create_test_state();
@@ -1139,4 +1878,565 @@ mod tests {
         assert_eq!(json["token_endpoint"], "/auth/token");
         assert_eq!(json["authorization_endpoint"], "/auth/authorize");
     }
+
+    #[tokio::test]
+    async fn test_call_service_returns_changed_states() {
+        let state = create_test_state();
+        let state_machine = state.state_machine.clone();
+        state.service_registry.register(
+            "homeassistant",
+            "turn_on",
+            move |call| {
+                let state_machine = state_machine.clone();
+                async move {
+                    let entity_id = call.service_data["entity_id"].as_str().unwrap_or_default();
+                    let (domain, object_id) = entity_id.split_once('.').unwrap();
+                    state_machine.set(
+                        EntityId::new(domain, object_id).unwrap(),
+                        "on",
+                        HashMap::new(),
+                        call.context.clone(),
+                    );
+                    // A change made outside the call's context while it
+                    // runs is not reported
+                    state_machine.set(
+                        EntityId::new("light", "other").unwrap(),
+                        "on",
+                        HashMap::new(),
+                        Context::new(),
+                    );
+                    Ok(None)
+                }
+            },
+            None,
+            ha_core::SupportsResponse::None,
+        );
+        let token = state
+            .auth_state
+            .create_long_lived_access_token("owner", "test", None, auth::LONG_LIVED_TOKEN_LIFESPAN)
+            .await;
+        let app = create_router(state.clone());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/services/homeassistant/turn_on")
+                    .header("content-type", "application/json")
+                    .header("Authorization", format!("Bearer {}", token))
+                    .body(Body::from(r#"{"entity_id": "light.kitchen"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let states = json.as_array().unwrap();
+        assert_eq!(states.len(), 1);
+        assert_eq!(states[0]["entity_id"], "light.kitchen");
+        assert_eq!(states[0]["state"], "on");
+    }
+
+    async fn post_template(state: AppState, body: &str) -> (StatusCode, String) {
+        let app = create_router(state);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/template")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_render_template() {
+        let state = create_test_state();
+        state.state_machine.set(
+            EntityId::new("sensor", "api_template").unwrap(),
+            "21.5",
+            HashMap::new(),
+            Context::new(),
+        );
+
+        let (status, body) = post_template(
+            state,
+            r#"{"template": "  {{ states('sensor.api_template') | float + 1 }}\n"}"#,
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "22.5");
+    }
+
+    #[tokio::test]
+    async fn test_render_template_with_variables() {
+        let state = create_test_state();
+
+        let (status, body) = post_template(
+            state,
+            r#"{"template": "Hello {{ who.name }}", "variables": {"who": {"name": "world"}}}"#,
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "Hello world");
+    }
+
+    #[tokio::test]
+    async fn test_render_template_syntax_error() {
+        let state = create_test_state();
+
+        let (status, body) = post_template(state, r#"{"template": "{% if %}"}"#).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let message = json["message"].as_str().unwrap();
+        assert!(
+            message.starts_with("Error rendering template:"),
+            "{message}"
+        );
+        assert!(message.contains("line 1"), "{message}");
+    }
+
+    async fn get_json(state: AppState, uri: &str) -> (StatusCode, serde_json::Value) {
+        let app = create_router(state);
+        let response = app
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn create_history_state() -> AppState {
+        let mut state = create_test_state();
+        let history = Arc::new(StateHistory::new());
+        history.attach(&state.event_bus);
+        state.history = Some(history);
+
+        let entity_id = EntityId::new("sensor", "history_temp").unwrap();
+        for value in ["20", "21"] {
+            state.state_machine.set(
+                entity_id.clone(),
+                value,
+                HashMap::from([("unit_of_measurement".to_string(), serde_json::json!("°C"))]),
+                Context::new(),
+            );
+        }
+        state.state_machine.set(
+            EntityId::new("sensor", "history_other").unwrap(),
+            "1",
+            HashMap::new(),
+            Context::new(),
+        );
+        state
+    }
+
+    fn history_uri(query: &str) -> String {
+        let start = (chrono::Utc::now() - chrono::Duration::hours(1)).format("%Y-%m-%dT%H:%M:%SZ");
+        format!("/api/history/period/{}?{}", start, query)
+    }
+
+    #[tokio::test]
+    async fn test_history_period_single_entity() {
+        let state = create_history_state();
+
+        let (status, json) =
+            get_json(state, &history_uri("filter_entity_id=sensor.history_temp")).await;
+
+        assert_eq!(status, StatusCode::OK);
+        let entities = json.as_array().unwrap();
+        assert_eq!(entities.len(), 1);
+        let states = entities[0].as_array().unwrap();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[0]["entity_id"], "sensor.history_temp");
+        assert_eq!(states[0]["state"], "20");
+        assert_eq!(states[1]["state"], "21");
+        assert_eq!(states[1]["attributes"]["unit_of_measurement"], "°C");
+        assert!(states[1]["last_changed"].is_string());
+        assert!(states[1]["last_updated"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_history_period_minimal_and_no_attributes() {
+        let state = create_history_state();
+        state.state_machine.set(
+            EntityId::new("sensor", "history_temp").unwrap(),
+            "22",
+            HashMap::new(),
+            Context::new(),
+        );
+
+        let (_, json) = get_json(
+            state,
+            &history_uri("filter_entity_id=sensor.history_temp&minimal_response&no_attributes"),
+        )
+        .await;
+
+        let states = json[0].as_array().unwrap();
+        assert_eq!(states.len(), 3);
+        assert_eq!(states[0]["attributes"], serde_json::json!({}));
+        // Intermediate states only carry state and last_changed
+        assert_eq!(
+            states[1].as_object().unwrap().keys().collect::<Vec<_>>(),
+            ["last_changed", "state"]
+        );
+        assert_eq!(states[2]["state"], "22");
+    }
+
+    #[tokio::test]
+    async fn test_history_period_minimal_response_false() {
+        let state = create_history_state();
+        state.state_machine.set(
+            EntityId::new("sensor", "history_temp").unwrap(),
+            "22",
+            HashMap::new(),
+            Context::new(),
+        );
+
+        let (_, json) = get_json(
+            state,
+            &history_uri("filter_entity_id=sensor.history_temp&minimal_response=false"),
+        )
+        .await;
+
+        // Intermediate states keep all their fields
+        let states = json[0].as_array().unwrap();
+        assert_eq!(states[1]["entity_id"], "sensor.history_temp");
+        assert!(states[1]["last_updated"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_history_period_no_attributes_false() {
+        let state = create_history_state();
+
+        let (_, json) = get_json(
+            state,
+            &history_uri("filter_entity_id=sensor.history_temp&no_attributes=0"),
+        )
+        .await;
+
+        let states = json[0].as_array().unwrap();
+        assert_eq!(states[1]["attributes"]["unit_of_measurement"], "°C");
+    }
+
+    #[tokio::test]
+    async fn test_history_period_from_recorder() {
+        let mut state = create_test_state();
+        let recorder = Recorder::open_in_memory(Default::default()).unwrap();
+        recorder.attach(&state.event_bus);
+        let entity_id = EntityId::new("sensor", "recorded_temp").unwrap();
+        for value in ["20", "21"] {
+            state
+                .state_machine
+                .set(entity_id.clone(), value, HashMap::new(), Context::new());
+        }
+        recorder.flush();
+        state.recorder = Some(Arc::new(recorder));
+
+        let (status, json) =
+            get_json(state, &history_uri("filter_entity_id=sensor.recorded_temp")).await;
+
+        assert_eq!(status, StatusCode::OK);
+        let states = json[0].as_array().unwrap();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[0]["state"], "20");
+        assert_eq!(states[1]["state"], "21");
+    }
+
+    #[tokio::test]
+    async fn test_logbook_from_recorder() {
+        let mut state = create_test_state();
+        let recorder = Recorder::open_in_memory(Default::default()).unwrap();
+        recorder.attach(&state.event_bus);
+        let start = chrono::Utc::now() - chrono::Duration::seconds(1);
+        let user = Context::with_user("user-1");
+        state.event_bus.fire(ha_core::Event::new(
+            ha_core::events::CALL_SERVICE,
+            serde_json::json!({
+                "domain": "light",
+                "service": "turn_on",
+                "service_data": {"entity_id": "light.logbook_lamp"},
+            }),
+            user.clone(),
+        ));
+        for entity_id in ["light.logbook_lamp", "light.other_lamp"] {
+            state.state_machine.set(
+                entity_id.parse::<EntityId>().unwrap(),
+                "on",
+                HashMap::new(),
+                user.clone(),
+            );
+        }
+        recorder.flush();
+        state.recorder = Some(Arc::new(recorder));
+
+        let uri = format!(
+            "/api/logbook/{}?entity=light.logbook_lamp",
+            start.format("%Y-%m-%dT%H:%M:%S%.fZ")
+        );
+        let (status, json) = get_json(state, &uri).await;
+
+        assert_eq!(status, StatusCode::OK);
+        let entries = json.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["name"], "light.turn_on");
+        assert_eq!(entries[1]["name"], "logbook lamp");
+        assert_eq!(entries[1]["message"], "turned on");
+        assert_eq!(entries[1]["context_user_id"], "user-1");
+        assert_eq!(entries[1]["context_service"], "turn_on");
+    }
+
+    #[tokio::test]
+    async fn test_history_period_without_backend() {
+        let state = create_test_state();
+
+        let (status, json) = get_json(state, &history_uri("filter_entity_id=sensor.x")).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json, serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_get_error_log() {
+        let state = create_test_state();
+        state.system_log.log(
+            "homeassistant.components.sensor",
+            ha_components::system_log::LogLevel::Error,
+            "Sensor update failed",
+            None,
+            None,
+        );
+        let app = create_router(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/error_log")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(
+            text.contains(
+                "ERROR (MainThread) [homeassistant.components.sensor] Sensor update failed"
+            ),
+            "{text}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_state_etag() {
+        let state = create_test_state();
+        let entity_id = EntityId::new("sensor", "etag_temp").unwrap();
+        state
+            .state_machine
+            .set(entity_id.clone(), "20", HashMap::new(), Context::new());
+
+        let get = |state: AppState, etag: Option<String>| async move {
+            let mut request = Request::builder().uri("/api/states/sensor.etag_temp");
+            if let Some(etag) = etag {
+                request = request.header("if-none-match", etag);
+            }
+            create_router(state)
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+        };
+
+        let response = get(state.clone(), None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()["etag"].to_str().unwrap().to_string();
+
+        let response = get(state.clone(), Some(etag.clone())).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        // Reporting the same state does not change the ETag
+        state
+            .state_machine
+            .set(entity_id.clone(), "20", HashMap::new(), Context::new());
+        let response = get(state.clone(), Some(etag.clone())).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        state
+            .state_machine
+            .set(entity_id, "21", HashMap::new(), Context::new());
+        let response = get(state.clone(), Some(etag.clone())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()["etag"].to_str().unwrap(), etag);
+    }
+
+    fn create_filter_state() -> AppState {
+        let state = create_test_state();
+        let entities = [
+            ("light", "kitchen", serde_json::json!({})),
+            ("switch", "fan", serde_json::json!({})),
+            (
+                "sensor",
+                "outdoor",
+                serde_json::json!({"device_class": "temperature", "precision": 1}),
+            ),
+            (
+                "sensor",
+                "humidity",
+                serde_json::json!({"device_class": "humidity"}),
+            ),
+        ];
+        for (domain, object_id, attributes) in entities {
+            state.state_machine.set(
+                EntityId::new(domain, object_id).unwrap(),
+                "on",
+                serde_json::from_value(attributes).unwrap(),
+                Context::new(),
+            );
+        }
+        state
+    }
+
+    #[tokio::test]
+    async fn test_get_states_excludes_disabled_and_hidden() {
+        let state = create_filter_state();
+        let entities = &state.registries.entities;
+        entities.get_or_create("demo", "switch.fan", None, None, None);
+        entities
+            .update("switch.fan", |e| {
+                e.disabled_by = Some(ha_registries::DisabledBy::User)
+            })
+            .unwrap();
+        entities.get_or_create("demo", "sensor.humidity", None, None, None);
+        entities
+            .update("sensor.humidity", |e| {
+                e.hidden_by = Some(ha_registries::HiddenBy::User)
+            })
+            .unwrap();
+
+        assert_eq!(get_entity_ids(state.clone(), "/api/states").await.len(), 4);
+        assert_eq!(
+            get_entity_ids(state.clone(), "/api/states?include_disabled=false").await,
+            ["light.kitchen", "sensor.humidity", "sensor.outdoor"]
+        );
+        assert_eq!(
+            get_entity_ids(
+                state,
+                "/api/states?include_disabled=false&include_hidden=false"
+            )
+            .await,
+            ["light.kitchen", "sensor.outdoor"]
+        );
+    }
+
+    async fn get_entity_ids(state: AppState, uri: &str) -> Vec<String> {
+        let (status, json) = get_json(state, uri).await;
+        assert_eq!(status, StatusCode::OK);
+        let mut ids: Vec<String> = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["entity_id"].as_str().unwrap().to_string())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_get_states_domain_filter() {
+        let state = create_filter_state();
+
+        assert_eq!(
+            get_entity_ids(state.clone(), "/api/states?domain=light,switch").await,
+            ["light.kitchen", "switch.fan"]
+        );
+        assert_eq!(get_entity_ids(state, "/api/states").await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_get_states_attribute_filter() {
+        let state = create_filter_state();
+
+        assert_eq!(
+            get_entity_ids(
+                state.clone(),
+                "/api/states?attribute=device_class:temperature"
+            )
+            .await,
+            ["sensor.outdoor"]
+        );
+        assert_eq!(
+            get_entity_ids(state.clone(), "/api/states?attribute=precision:1").await,
+            ["sensor.outdoor"]
+        );
+        assert_eq!(
+            get_entity_ids(state, "/api/states?domain=sensor&attribute=device_class").await,
+            ["sensor.humidity", "sensor.outdoor"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_states_minimal_response() {
+        let state = create_filter_state();
+
+        let (_, full) = get_json(state.clone(), "/api/states/sensor.outdoor").await;
+        assert_eq!(full["attributes"]["device_class"], "temperature");
+        assert!(full.get("context").is_some());
+
+        let (_, minimal) = get_json(
+            state.clone(),
+            "/api/states/sensor.outdoor?minimal_response=true",
+        )
+        .await;
+        assert!(minimal.get("attributes").is_none());
+        assert!(minimal.get("context").is_none());
+        assert_eq!(minimal["entity_id"], "sensor.outdoor");
+        assert_eq!(minimal["state"], "on");
+        assert_eq!(minimal["last_changed"], full["last_changed"]);
+
+        let (_, all) = get_json(state, "/api/states?minimal_response=true").await;
+        assert!(all
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|s| s.get("attributes").is_none() && s.get("context").is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_get_states_no_attributes() {
+        let state = create_filter_state();
+
+        let (_, json) = get_json(state.clone(), "/api/states?no_attributes=true").await;
+        let states = json.as_array().unwrap();
+        assert_eq!(states.len(), 4);
+        for s in states {
+            assert!(s.get("attributes").is_none());
+            assert!(s.get("context").is_some());
+            assert!(s.get("last_changed").is_some());
+        }
+
+        let (_, json) = get_json(state, "/api/states?no_attributes=false").await;
+        assert!(json[0].get("attributes").is_some());
+    }
 }
@@ -0,0 +1,232 @@
+//! User Permissions
+//!
+//! Authorization on top of authentication: what an authenticated user may
+//! change. Admins may do anything, read-only users may only read, and other
+//! users may control the entities in their allow lists (or all entities when
+//! they have none). Reading states is never restricted.
+//!
+//! Service calls are checked by an interceptor on the `ServiceRegistry`, so
+//! every caller that carries a user_id in its context is covered; state
+//! writes are checked where the API sets states. Device, area, floor and
+//! label targets are checked as the entities they expand to.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use ha_core::ServiceCall;
+use ha_registries::Registries;
+use ha_service_registry::ServiceError;
+use serde::Deserialize;
+
+use crate::auth::AuthState;
+
+/// What a user may change
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct UserPermissions {
+    /// Admins may do anything
+    #[serde(default)]
+    pub admin: bool,
+    /// Read-only users may read states but not change anything
+    #[serde(default)]
+    pub read_only: bool,
+    /// Entities the user may control
+    #[serde(default)]
+    pub entity_ids: HashSet<String>,
+    /// Domains whose entities and services the user may control
+    #[serde(default)]
+    pub domains: HashSet<String>,
+}
+
+impl UserPermissions {
+    /// Permissions of an admin
+    pub fn admin() -> Self {
+        Self {
+            admin: true,
+            ..Default::default()
+        }
+    }
+
+    /// Permissions of a user that may only read
+    pub fn read_only() -> Self {
+        Self {
+            read_only: true,
+            ..Default::default()
+        }
+    }
+
+    /// Whether the allow lists leave everything allowed
+    fn unrestricted(&self) -> bool {
+        self.entity_ids.is_empty() && self.domains.is_empty()
+    }
+
+    /// Whether the user may control or set the state of an entity
+    pub fn can_control(&self, entity_id: &str) -> bool {
+        if self.admin {
+            return true;
+        }
+        if self.read_only {
+            return false;
+        }
+        let domain = entity_id.split_once('.').map_or("", |(domain, _)| domain);
+        self.unrestricted() || self.entity_ids.contains(entity_id) || self.domains.contains(domain)
+    }
+
+    /// Check a service call, returning why it's denied
+    ///
+    /// Every targeted entity must be controllable. The service's domain must
+    /// be allowed too, unless the call only targets entities of that domain
+    /// (e.g. `switch.turn_on` on an allowed switch), so an allowed target
+    /// can't carry a call to another domain's service like `lock.unlock`.
+    pub fn check_service_call(
+        &self,
+        call: &ServiceCall,
+        registries: &Registries,
+    ) -> Result<(), String> {
+        if self.admin {
+            return Ok(());
+        }
+        if self.read_only {
+            return Err(format!(
+                "read-only user can't call {}.{}",
+                call.domain, call.service
+            ));
+        }
+
+        let entity_ids = registries.resolve_target(&call.service_data);
+        let targets_all = entity_ids.is_empty() || entity_ids.iter().any(|id| id == "all");
+        let own_domain = !targets_all
+            && entity_ids
+                .iter()
+                .all(|id| id.split_once('.').is_some_and(|(d, _)| d == call.domain));
+        if !(self.unrestricted() || self.domains.contains(&call.domain) || own_domain) {
+            return Err(format!(
+                "not allowed to call {}.{}",
+                call.domain, call.service
+            ));
+        }
+        if targets_all {
+            return Ok(());
+        }
+        match entity_ids.iter().find(|id| !self.can_control(id)) {
+            Some(entity_id) => Err(format!("not allowed to control {}", entity_id)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Service interceptor denying calls the calling user isn't allowed to make
+///
+/// Calls without a user in their context, e.g. from automations, pass; the
+/// API only makes calls on behalf of an authenticated user.
+pub fn service_interceptor(
+    auth: AuthState,
+    registries: Arc<Registries>,
+) -> impl Fn(&ServiceCall) -> Result<(), ServiceError> + Send + Sync {
+    move |call| {
+        let Some(user_id) = call.context.user_id.as_deref() else {
+            return Ok(());
+        };
+        auth.user_permissions(user_id)
+            .check_service_call(call, &registries)
+            .map_err(ServiceError::PermissionDenied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ha_core::Context;
+    use serde_json::json;
+
+    fn call(domain: &str, data: serde_json::Value) -> ServiceCall {
+        ServiceCall::new(domain, "turn_on", data, Context::new())
+    }
+
+    fn registries() -> (tempfile::TempDir, Registries) {
+        let dir = tempfile::tempdir().unwrap();
+        let registries = Registries::new(dir.path());
+        (dir, registries)
+    }
+
+    #[test]
+    fn test_allow_lists() {
+        let (_dir, registries) = registries();
+        let permissions: UserPermissions = serde_json::from_value(json!({
+            "entity_ids": ["switch.fan"],
+            "domains": ["light"],
+        }))
+        .unwrap();
+        assert!(permissions.can_control("light.porch"));
+        assert!(permissions.can_control("switch.fan"));
+        assert!(!permissions.can_control("lock.front_door"));
+
+        let allowed = call("light", json!({"entity_id": ["light.porch", "switch.fan"]}));
+        assert!(permissions
+            .check_service_call(&allowed, &registries)
+            .is_ok());
+        let denied = call("lock", json!({"entity_id": "light.porch, lock.front_door"}));
+        assert!(permissions
+            .check_service_call(&denied, &registries)
+            .is_err());
+
+        // Untargeted calls need the service's domain
+        assert!(permissions
+            .check_service_call(&call("light", json!({})), &registries)
+            .is_ok());
+        assert!(permissions
+            .check_service_call(&call("switch", json!({"entity_id": "all"})), &registries)
+            .is_err());
+
+        // An allowed target doesn't open up other domains' services
+        let own = call("switch", json!({"entity_id": "switch.fan"}));
+        assert!(permissions.check_service_call(&own, &registries).is_ok());
+        for domain in ["lock", "homeassistant", "script"] {
+            let smuggled = call(domain, json!({"entity_id": "light.porch"}));
+            assert!(permissions
+                .check_service_call(&smuggled, &registries)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_expanded_targets_are_checked() {
+        let (_dir, registries) = registries();
+        for entity_id in ["light.porch", "lock.front_door"] {
+            registries
+                .entities
+                .get_or_create("demo", entity_id, None, None, None);
+            registries
+                .entities
+                .update(entity_id, |e| {
+                    e.area_id = Some("hallway".to_string());
+                })
+                .unwrap();
+        }
+        let permissions: UserPermissions =
+            serde_json::from_value(json!({"domains": ["light"]})).unwrap();
+
+        // The area holds an entity the user may not control
+        let by_area = call("light", json!({"area_id": "hallway"}));
+        assert!(permissions
+            .check_service_call(&by_area, &registries)
+            .is_err());
+        let by_entity = call("light", json!({"entity_id": "light.porch"}));
+        assert!(permissions
+            .check_service_call(&by_entity, &registries)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_admin_and_read_only() {
+        let (_dir, registries) = registries();
+        let any = call("lock", json!({"entity_id": "lock.front_door"}));
+        assert!(UserPermissions::admin()
+            .check_service_call(&any, &registries)
+            .is_ok());
+        assert!(UserPermissions::read_only()
+            .check_service_call(&any, &registries)
+            .is_err());
+        assert!(!UserPermissions::read_only().can_control("light.porch"));
+        assert!(UserPermissions::default().can_control("lock.front_door"));
+    }
+}
@@ -5,13 +5,19 @@
 
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use ha_core::{Context, Event};
+use ha_event_bus::EventBus;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::sync::Arc;
 use tracing::{debug, info};
 
 /// Domain name for persistent notification services
 pub const DOMAIN: &str = "persistent_notification";
 
+/// Event fired when notifications are created, updated or dismissed
+pub const EVENT_NOTIFICATIONS_UPDATED: &str = "persistent_notifications_updated";
+
 /// A persistent notification
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Notification {
@@ -173,6 +179,43 @@ impl PersistentNotificationManager {
     }
 }
 
+/// Build the data of a `persistent_notifications_updated` event
+///
+/// Carries the changed notifications keyed by ID, in the same shape the
+/// `persistent_notification/subscribe` WebSocket command sends them.
+pub fn updated_event_data(
+    update_type: UpdateType,
+    notifications: &[Notification],
+) -> serde_json::Value {
+    let notifications: serde_json::Map<String, serde_json::Value> = notifications
+        .iter()
+        .map(|n| {
+            (
+                n.notification_id.clone(),
+                serde_json::to_value(n).unwrap_or_default(),
+            )
+        })
+        .collect();
+    json!({
+        "update_type": update_type,
+        "notifications": notifications,
+    })
+}
+
+/// Fire `persistent_notifications_updated` for changed notifications
+pub fn fire_updated(
+    event_bus: &EventBus,
+    update_type: UpdateType,
+    notifications: &[Notification],
+    context: Context,
+) {
+    event_bus.fire(Event::new(
+        EVENT_NOTIFICATIONS_UPDATED,
+        updated_event_data(update_type, notifications),
+        context,
+    ));
+}
+
 /// Create a shared notification manager
 pub fn create_manager() -> Arc<PersistentNotificationManager> {
     Arc::new(PersistentNotificationManager::new())
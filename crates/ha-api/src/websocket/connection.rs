@@ -3,22 +3,133 @@
 //! Manages WebSocket connections, authentication, and message routing.
 
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
 use futures::{SinkExt, StreamExt};
 use ha_core::Context;
-use tokio::sync::{broadcast, mpsc, RwLock};
+use serde::Serialize;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, watch, RwLock};
+use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
 use crate::AppState;
 
 use super::dispatch::handle_message;
 use super::types::{
-    AuthInvalidMessage, AuthOkMessage, AuthRequiredMessage, IncomingMessage, OutgoingMessage,
+    AuthInvalidMessage, AuthOkMessage, AuthRequiredMessage, ErrorInfo, IncomingMessage,
+    OutgoingMessage, ResultMessage,
 };
 
+/// Maximum number of messages waiting to be written to a client
+///
+/// Subscriptions that find the queue full close the connection instead of
+/// buffering, like HA's `MAX_PENDING_MSG`.
+pub const MAX_PENDING_MESSAGES: usize = 2048;
+
+/// Maximum number of received commands waiting to be handled
+///
+/// Commands beyond this are answered with a `rate_limited` error.
+pub const MAX_PENDING_COMMANDS: usize = 64;
+
+/// How long to wait for the close frame to reach a client that fell behind
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// =============================================================================
+// Connection Metrics
+// =============================================================================
+
+/// Counters tracking the traffic and backpressure of one connection
+#[derive(Debug, Default)]
+pub struct ConnectionMetrics {
+    /// Commands received from the client
+    pub commands_received: AtomicU64,
+    /// Commands rejected because too many were pending
+    pub commands_rejected: AtomicU64,
+    /// Subscription messages queued for the client
+    pub messages_queued: AtomicU64,
+    /// Subscription messages dropped because the outgoing queue was full
+    pub messages_dropped: AtomicU64,
+}
+
+/// Point-in-time copy of [`ConnectionMetrics`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ConnectionStats {
+    pub commands_received: u64,
+    pub commands_rejected: u64,
+    pub messages_queued: u64,
+    pub messages_dropped: u64,
+}
+
+impl ConnectionMetrics {
+    /// Read all counters
+    pub fn snapshot(&self) -> ConnectionStats {
+        ConnectionStats {
+            commands_received: self.commands_received.load(Ordering::Relaxed),
+            commands_rejected: self.commands_rejected.load(Ordering::Relaxed),
+            messages_queued: self.messages_queued.load(Ordering::Relaxed),
+            messages_dropped: self.messages_dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// An open connection as listed by `websocket/connections`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConnectionInfo {
+    pub id: u64,
+    pub user_id: Option<String>,
+    #[serde(flatten)]
+    pub stats: ConnectionStats,
+}
+
+/// User and counters of a registered connection
+type RegisteredConnection = (Option<String>, Arc<ConnectionMetrics>);
+
+/// Metrics of the open connections, so they can be read while live
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionRegistry {
+    next_id: Arc<AtomicU64>,
+    connections: Arc<Mutex<HashMap<u64, RegisteredConnection>>>,
+}
+
+impl ConnectionRegistry {
+    /// Track a new connection's metrics, returning its id
+    fn register(&self, user_id: Option<String>, metrics: Arc<ConnectionMetrics>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.connections
+            .lock()
+            .unwrap()
+            .insert(id, (user_id, metrics));
+        id
+    }
+
+    /// Stop tracking a closed connection
+    fn unregister(&self, id: u64) {
+        self.connections.lock().unwrap().remove(&id);
+    }
+
+    /// Current counters of every open connection, oldest first
+    pub fn list(&self) -> Vec<ConnectionInfo> {
+        let mut connections: Vec<ConnectionInfo> = self
+            .connections
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, (user_id, metrics))| ConnectionInfo {
+                id: *id,
+                user_id: user_id.clone(),
+                stats: metrics.snapshot(),
+            })
+            .collect();
+        connections.sort_by_key(|connection| connection.id);
+        connections
+    }
+}
+
 // =============================================================================
 // Connection State
 // =============================================================================
@@ -29,22 +140,35 @@ pub struct ActiveConnection {
     pub state: AppState,
     /// Last message ID received
     last_id: AtomicU64,
-    /// Active subscriptions: subscription_id -> unsubscribe function
-    pub subscriptions: RwLock<HashMap<u64, broadcast::Sender<()>>>,
+    /// Active subscriptions: subscription_id -> forwarding task
+    pub subscriptions: RwLock<HashMap<u64, JoinHandle<()>>>,
     /// User ID for this authenticated connection
     pub user_id: Option<String>,
     /// Whether this connection is authenticated
     pub authenticated: bool,
+    /// Traffic and backpressure counters, shared with the connection registry
+    pub metrics: Arc<ConnectionMetrics>,
+    /// Id in the connection registry
+    registry_id: u64,
+    /// Set once the outgoing queue overflowed and the connection must close
+    overflowed: watch::Sender<bool>,
 }
 
 impl ActiveConnection {
     pub fn new(state: AppState, user_id: Option<String>) -> Self {
+        let metrics = Arc::new(ConnectionMetrics::default());
+        let registry_id = state
+            .websocket_connections
+            .register(user_id.clone(), metrics.clone());
         Self {
             state,
             last_id: AtomicU64::new(0),
             subscriptions: RwLock::new(HashMap::new()),
             user_id,
             authenticated: false,
+            metrics,
+            registry_id,
+            overflowed: watch::channel(false).0,
         }
     }
 
@@ -65,6 +189,99 @@ impl ActiveConnection {
         self.last_id.store(id, Ordering::SeqCst);
         Ok(())
     }
+
+    /// Spawn and track the task forwarding a subscription's messages
+    ///
+    /// The subscription is dropped once `forward` ends on its own, e.g. when
+    /// the client fell behind or the event bus closed.
+    pub async fn spawn_subscription<F>(self: &Arc<Self>, id: u64, forward: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let conn = Arc::downgrade(self);
+        // Held until the task is tracked, so it can't remove itself first
+        let mut subscriptions = self.subscriptions.write().await;
+        let task = tokio::spawn(async move {
+            forward.await;
+            if let Some(conn) = conn.upgrade() {
+                conn.subscriptions.write().await.remove(&id);
+                debug!("Subscription {} ended", id);
+            }
+        });
+        if let Some(previous) = subscriptions.insert(id, task) {
+            previous.abort();
+        }
+    }
+
+    /// Stop a subscription, returning whether it existed
+    pub async fn remove_subscription(&self, id: u64) -> bool {
+        match self.subscriptions.write().await.remove(&id) {
+            Some(task) => {
+                task.abort();
+                debug!("Subscription {} cancelled", id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop every subscription of this connection
+    pub async fn remove_all_subscriptions(&self) {
+        for (_, task) in self.subscriptions.write().await.drain() {
+            task.abort();
+        }
+    }
+
+    /// Number of active subscriptions
+    pub async fn subscription_count(&self) -> usize {
+        self.subscriptions.read().await.len()
+    }
+
+    /// Queue a subscription message without waiting for room
+    ///
+    /// Returns false when the subscription should stop: either the connection
+    /// is gone or the client fell too far behind, in which case the connection
+    /// is marked as overflowed and will be closed.
+    pub fn queue_message(&self, tx: &mpsc::Sender<OutgoingMessage>, msg: OutgoingMessage) -> bool {
+        match tx.try_send(msg) {
+            Ok(()) => {
+                self.metrics.messages_queued.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(TrySendError::Full(_)) => {
+                self.metrics
+                    .messages_dropped
+                    .fetch_add(1, Ordering::Relaxed);
+                if !self.overflowed.send_replace(true) {
+                    warn!(
+                        "Client exceeded max pending messages ({}), closing connection",
+                        MAX_PENDING_MESSAGES
+                    );
+                }
+                false
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+
+    /// Whether the outgoing queue overflowed
+    pub fn is_overflowed(&self) -> bool {
+        *self.overflowed.borrow()
+    }
+
+    /// Wait until the outgoing queue overflows
+    pub async fn wait_overflowed(&self) {
+        let mut rx = self.overflowed.subscribe();
+        let _ = rx.wait_for(|overflowed| *overflowed).await;
+    }
+}
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        self.state
+            .websocket_connections
+            .unregister(self.registry_id);
+    }
 }
 
 // =============================================================================
@@ -94,8 +311,13 @@ pub async fn handle_socket(socket: WebSocket, state: AppState) {
     )
     .await;
 
+    let auth_result = match auth_result {
+        Ok(Ok(auth)) => Ok(authenticate(&state, &auth).await.map(Some)),
+        Ok(Err(e)) => Ok(Err(e)),
+        Err(timeout) => Err(timeout),
+    };
     let (authenticated, user_id) = match auth_result {
-        Ok(Ok(auth)) if auth.success => {
+        Ok(Ok(user_id)) => {
             // Send auth_ok
             let auth_ok = OutgoingMessage::AuthOk(AuthOkMessage {
                 msg_type: "auth_ok",
@@ -105,16 +327,14 @@ pub async fn handle_socket(socket: WebSocket, state: AppState) {
                 error!("Failed to send auth_ok: {}", e);
                 return;
             }
-            // Look up user_id from token
-            let user_id = lookup_user_id(auth.access_token.as_deref());
             info!("WebSocket client authenticated (user_id: {:?})", user_id);
             (true, user_id)
         }
-        Ok(Ok(_)) | Ok(Err(_)) => {
+        Ok(Err(message)) => {
             // Send auth_invalid
             let auth_invalid = OutgoingMessage::AuthInvalid(AuthInvalidMessage {
                 msg_type: "auth_invalid",
-                message: "Invalid access token or password".to_string(),
+                message,
             });
             let _ = send_message(&mut sender, &auth_invalid).await;
             warn!("WebSocket client authentication failed");
@@ -138,19 +358,51 @@ pub async fn handle_socket(socket: WebSocket, state: AppState) {
     let conn = Arc::new(conn);
 
     // Create channel for sending messages
-    let (tx, mut rx) = mpsc::channel::<OutgoingMessage>(256);
+    let (tx, mut rx) = mpsc::channel::<OutgoingMessage>(MAX_PENDING_MESSAGES);
 
-    // Spawn task to forward messages to WebSocket
-    let send_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            if send_message(&mut sender, &msg).await.is_err() {
-                break;
+    // Spawn task to forward messages to WebSocket, closing it on overflow
+    let send_conn = conn.clone();
+    let mut send_task = tokio::spawn(async move {
+        let overflowed = tokio::select! {
+            _ = async {
+                while let Some(msg) = rx.recv().await {
+                    if send_message(&mut sender, &msg).await.is_err() {
+                        break;
+                    }
+                }
+            } => false,
+            _ = send_conn.wait_overflowed() => true,
+        };
+        if overflowed {
+            let close = Message::Close(Some(CloseFrame {
+                code: close_code::POLICY,
+                reason: "Client exceeded max pending messages".into(),
+            }));
+            let _ = tokio::time::timeout(CLOSE_TIMEOUT, sender.send(close)).await;
+        }
+    });
+
+    // Spawn task to handle commands in order, bounded by MAX_PENDING_COMMANDS
+    let (command_tx, mut command_rx) = mpsc::channel::<String>(MAX_PENDING_COMMANDS);
+    let command_conn = conn.clone();
+    let command_out = tx.clone();
+    let command_task = tokio::spawn(async move {
+        while let Some(text) = command_rx.recv().await {
+            if let Err(e) = handle_message(&command_conn, &text, &command_out).await {
+                error!("Error handling message: {}", e);
             }
         }
     });
 
     // Process incoming messages
-    while let Some(result) = receiver.next().await {
+    loop {
+        let result = tokio::select! {
+            result = receiver.next() => result,
+            _ = conn.wait_overflowed() => break,
+        };
+        let Some(result) = result else {
+            break;
+        };
         match result {
             Ok(Message::Text(text)) => {
                 // Log all incoming messages at info level for debugging
@@ -159,8 +411,13 @@ pub async fn handle_socket(socket: WebSocket, state: AppState) {
                         info!("WS RECV: type={}, full={}", msg_type, text);
                     }
                 }
-                if let Err(e) = handle_message(&conn, &text, &tx).await {
-                    error!("Error handling message: {}", e);
+                conn.metrics
+                    .commands_received
+                    .fetch_add(1, Ordering::Relaxed);
+                match command_tx.try_send(text) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(text)) => reject_command(&conn, &text, &tx),
+                    Err(TrySendError::Closed(_)) => break,
                 }
             }
             Ok(Message::Close(_)) => {
@@ -182,15 +439,49 @@ pub async fn handle_socket(socket: WebSocket, state: AppState) {
     }
 
     // Cleanup subscriptions
-    let subscriptions = conn.subscriptions.read().await;
-    for (_, cancel_tx) in subscriptions.iter() {
-        let _ = cancel_tx.send(());
-    }
-    drop(subscriptions);
+    command_task.abort();
+    debug!(
+        "Cancelling {} subscriptions",
+        conn.subscription_count().await
+    );
+    conn.remove_all_subscriptions().await;
 
-    // Wait for send task to finish
+    // Give the send task a chance to deliver the close frame to a slow client
+    if conn.is_overflowed() {
+        let _ = tokio::time::timeout(CLOSE_TIMEOUT * 2, &mut send_task).await;
+    }
     send_task.abort();
-    info!("WebSocket connection closed");
+    info!(
+        "WebSocket connection closed ({:?})",
+        conn.metrics.snapshot()
+    );
+}
+
+/// Answer a command that arrived while too many were pending
+fn reject_command(conn: &ActiveConnection, text: &str, tx: &mpsc::Sender<OutgoingMessage>) {
+    conn.metrics
+        .commands_rejected
+        .fetch_add(1, Ordering::Relaxed);
+    let Some(id) = serde_json::from_str::<serde_json::Value>(text)
+        .ok()
+        .and_then(|json| json.get("id").and_then(|id| id.as_u64()))
+    else {
+        return;
+    };
+    warn!("Too many pending commands, rejecting command {}", id);
+    conn.queue_message(
+        tx,
+        OutgoingMessage::Result(ResultMessage {
+            id,
+            msg_type: "result",
+            success: false,
+            result: None,
+            error: Some(ErrorInfo {
+                code: "rate_limited".to_string(),
+                message: format!("Too many pending commands (max {})", MAX_PENDING_COMMANDS),
+            }),
+        }),
+    );
 }
 
 // =============================================================================
@@ -199,8 +490,8 @@ pub async fn handle_socket(socket: WebSocket, state: AppState) {
 
 /// Authentication result with optional token
 pub struct AuthResult {
-    pub success: bool,
     pub access_token: Option<String>,
+    pub api_password: Option<String>,
 }
 
 /// Wait for authentication message
@@ -217,17 +508,9 @@ async fn wait_for_auth(
                             access_token,
                             api_password,
                         } => {
-                            // For now, accept any token (TODO: implement proper auth)
-                            // In production, validate against HA's auth system
-                            if access_token.is_some() || api_password.is_some() {
-                                return Ok(AuthResult {
-                                    success: true,
-                                    access_token,
-                                });
-                            }
                             return Ok(AuthResult {
-                                success: false,
-                                access_token: None,
+                                access_token,
+                                api_password,
                             });
                         }
                         _ => {
@@ -249,27 +532,20 @@ async fn wait_for_auth(
     Err("Connection closed".to_string())
 }
 
-/// Look up user_id from access token
-/// In production, this would query the auth storage/provider
-fn lookup_user_id(access_token: Option<&str>) -> Option<String> {
-    // For testing: map known test tokens to test user_id
-    // In production, this would decode the JWT or query auth storage
-    match access_token {
-        // Plain text test token
-        Some("test_api_token_for_comparison_testing_do_not_use_in_production") => {
-            Some("test-user-id-12345678".to_string())
-        }
-        // JWT test token (generated from test-long-lived-token-id-456)
-        Some(token)
-            if token.starts_with(
-                "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJpc3MiOiJ0ZXN0LWxvbmctbGl2ZWQtdG9rZW4t",
-            ) =>
-        {
-            Some("test-user-id-12345678".to_string())
-        }
-        // Accept any token for now, but without user_id unless it's a known test token
-        Some(_) => None,
-        None => None,
+/// Decide on the credentials of an auth message
+///
+/// Returns the authenticated user_id, or the `auth_invalid` message. Only
+/// known, unexpired tokens issued by the auth API are accepted; the legacy
+/// API password isn't supported.
+pub(crate) async fn authenticate(state: &AppState, auth: &AuthResult) -> Result<String, String> {
+    match auth.access_token.as_deref() {
+        Some(token) => state
+            .auth_state
+            .check_access_token(token)
+            .await
+            .map_err(|e| e.to_string()),
+        None if auth.api_password.is_some() => Err("API password is not supported".to_string()),
+        None => Err("Invalid access token or password".to_string()),
     }
 }
 
@@ -44,6 +44,34 @@ pub async fn handle_message(
             conn.validate_id(id).map_err(|e| e.to_string())?;
             handlers::handle_auth_current_user(conn, id, tx).await
         }
+        IncomingMessage::AuthDeleteRefreshToken {
+            id,
+            refresh_token_id,
+        } => {
+            conn.validate_id(id).map_err(|e| e.to_string())?;
+            handlers::handle_auth_delete_refresh_token(conn, id, &refresh_token_id, tx).await
+        }
+        IncomingMessage::AuthLongLivedAccessToken {
+            id,
+            client_name,
+            client_icon,
+            lifespan,
+        } => {
+            conn.validate_id(id).map_err(|e| e.to_string())?;
+            handlers::handle_auth_long_lived_access_token(
+                conn,
+                id,
+                &client_name,
+                client_icon.as_deref(),
+                lifespan,
+                tx,
+            )
+            .await
+        }
+        IncomingMessage::AuthRefreshTokens { id } => {
+            conn.validate_id(id).map_err(|e| e.to_string())?;
+            handlers::handle_auth_refresh_tokens(conn, id, tx).await
+        }
         IncomingMessage::AutomationConfig { id, entity_id } => {
             conn.validate_id(id).map_err(|e| e.to_string())?;
             handlers::handle_automation_config(conn, id, &entity_id, tx).await
@@ -99,10 +127,36 @@ pub async fn handle_message(
                 }
                 None => {
                     // List all flows in progress (non-user initiated)
-                    handlers::handle_config_entries_flow_progress_list(id, tx).await
+                    handlers::handle_config_entries_flow_progress_list(conn, id, tx).await
                 }
             }
         }
+        IncomingMessage::ConfigEntriesOptionsFlow {
+            id,
+            handler,
+            show_advanced_options,
+        } => {
+            conn.validate_id(id).map_err(|e| e.to_string())?;
+            handlers::handle_config_entries_options_flow(
+                conn,
+                id,
+                &handler,
+                show_advanced_options,
+                tx,
+            )
+            .await
+        }
+        IncomingMessage::ConfigEntriesOptionsFlowProgress {
+            id,
+            flow_id,
+            user_input,
+        } => {
+            conn.validate_id(id).map_err(|e| e.to_string())?;
+            handlers::handle_config_entries_options_flow_progress(
+                conn, id, &flow_id, user_input, tx,
+            )
+            .await
+        }
         IncomingMessage::ConfigEntriesFlowSubscribe { id } => {
             conn.validate_id(id).map_err(|e| e.to_string())?;
             handlers::handle_config_entries_flow_subscribe(conn, id, tx).await
@@ -179,6 +233,30 @@ pub async fn handle_message(
             conn.validate_id(id).map_err(|e| e.to_string())?;
             handlers::handle_config_entries_subentries_list(conn, id, &entry_id, tx).await
         }
+        IncomingMessage::ConfigEntriesSubentriesCreate {
+            id,
+            entry_id,
+            subentry_type,
+            title,
+            unique_id,
+            data,
+        } => {
+            conn.validate_id(id).map_err(|e| e.to_string())?;
+            let mut subentry =
+                ha_config_entries::ConfigSubentry::new(subentry_type, title).with_data(data);
+            subentry.unique_id = unique_id;
+            handlers::handle_config_entries_subentries_create(conn, id, &entry_id, subentry, tx)
+                .await
+        }
+        IncomingMessage::ConfigEntriesSubentriesDelete {
+            id,
+            entry_id,
+            subentry_id,
+        } => {
+            conn.validate_id(id).map_err(|e| e.to_string())?;
+            handlers::handle_config_entries_subentries_delete(conn, id, &entry_id, &subentry_id, tx)
+                .await
+        }
         IncomingMessage::ConfigEntriesSubscribe { id, type_filter } => {
             conn.validate_id(id).map_err(|e| e.to_string())?;
             handlers::handle_config_entries_subscribe(conn, id, type_filter, tx).await
@@ -352,6 +430,42 @@ pub async fn handle_message(
             conn.validate_id(id).map_err(|e| e.to_string())?;
             handlers::handle_recorder_info(conn, id, tx).await
         }
+        IncomingMessage::RecorderStatisticsDuringPeriod {
+            id,
+            start_time,
+            end_time,
+            statistic_ids,
+            period,
+        } => {
+            conn.validate_id(id).map_err(|e| e.to_string())?;
+            handlers::handle_statistics_during_period(
+                conn,
+                id,
+                &start_time,
+                end_time.as_deref(),
+                statistic_ids,
+                period.as_deref(),
+                tx,
+            )
+            .await
+        }
+        IncomingMessage::LogbookGetEvents {
+            id,
+            start_time,
+            end_time,
+            entity_ids,
+        } => {
+            conn.validate_id(id).map_err(|e| e.to_string())?;
+            handlers::handle_logbook_get_events(
+                conn,
+                id,
+                &start_time,
+                end_time.as_deref(),
+                entity_ids,
+                tx,
+            )
+            .await
+        }
         IncomingMessage::RenderTemplate {
             id,
             template,
@@ -382,6 +496,14 @@ pub async fn handle_message(
             conn.validate_id(id).map_err(|e| e.to_string())?;
             handlers::handle_subscribe_events(conn, id, event_type, tx).await
         }
+        IncomingMessage::SubscribeTrigger {
+            id,
+            trigger,
+            variables,
+        } => {
+            conn.validate_id(id).map_err(|e| e.to_string())?;
+            handlers::handle_subscribe_trigger(conn, id, trigger, variables, tx).await
+        }
         IncomingMessage::SupportedFeatures { id, features: _ } => {
             conn.validate_id(id).map_err(|e| e.to_string())?;
             // Acknowledge supported features (we don't use coalescing yet)
@@ -399,6 +521,19 @@ pub async fn handle_message(
             conn.validate_id(id).map_err(|e| e.to_string())?;
             handlers::handle_system_log_list(conn, id, tx).await
         }
+        IncomingMessage::WebsocketConnections { id } => {
+            conn.validate_id(id).map_err(|e| e.to_string())?;
+            handlers::handle_websocket_connections(conn, id, tx).await
+        }
+        IncomingMessage::ValidateConfig {
+            id,
+            trigger,
+            condition,
+            action,
+        } => {
+            conn.validate_id(id).map_err(|e| e.to_string())?;
+            handlers::handle_validate_config(id, trigger, condition, action, tx).await
+        }
         IncomingMessage::UnsubscribeEvents { id, subscription } => {
             conn.validate_id(id).map_err(|e| e.to_string())?;
             handlers::handle_unsubscribe_events(conn, id, subscription, tx).await
@@ -5,9 +5,15 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+use ha_automation::{Condition, Trigger, TriggerEvalContext, TriggerEvaluator};
+use ha_config_entries::ConfigEntriesError;
+use ha_recorder::StatisticsPeriod;
+use ha_script::Action;
+use ha_service_registry::ServiceError;
 use tokio::sync::{broadcast, mpsc};
 use tracing::{debug, error, info, warn};
 
+use crate::persistent_notification;
 use crate::translations;
 use crate::AppState;
 
@@ -153,73 +159,59 @@ pub async fn handle_subscribe_events(
     event_type: Option<String>,
     tx: &mpsc::Sender<OutgoingMessage>,
 ) -> Result<(), String> {
-    // Create cancellation channel
-    let (cancel_tx, mut cancel_rx) = broadcast::channel::<()>(1);
-
-    // Store subscription
-    {
-        let mut subs = conn.subscriptions.write().await;
-        subs.insert(id, cancel_tx);
-    }
-
     // Subscribe to events
     let event_type_filter = event_type.clone();
     let tx_clone = tx.clone();
+    let conn_clone = Arc::clone(conn);
     let sub_id = id;
 
     // Get a receiver from the event bus (subscribe to all events)
     let mut event_rx = conn.state.event_bus.subscribe_all();
 
     // Spawn task to forward events
-    tokio::spawn(async move {
+    let forward = async move {
         loop {
-            tokio::select! {
-                _ = cancel_rx.recv() => {
-                    debug!("Subscription {} cancelled", sub_id);
-                    break;
-                }
-                result = event_rx.recv() => {
-                    match result {
-                        Ok(event) => {
-                            // Filter by event type if specified
-                            if let Some(ref filter) = event_type_filter {
-                                if event.event_type.as_str() != filter {
-                                    continue;
-                                }
-                            }
-
-                            // Send event to client
-                            let event_msg = OutgoingMessage::Event(EventMessage {
-                                id: sub_id,
-                                msg_type: "event",
-                                event: serde_json::json!({
-                                    "event_type": event.event_type,
-                                    "data": event.data,
-                                    "origin": "LOCAL",
-                                    "time_fired": event.time_fired.to_rfc3339(),
-                                    "context": {
-                                        "id": event.context.id.to_string(),
-                                        "parent_id": event.context.parent_id,
-                                        "user_id": event.context.user_id,
-                                    }
-                                }),
-                            });
-                            if tx_clone.send(event_msg).await.is_err() {
-                                break;
-                            }
-                        }
-                        Err(broadcast::error::RecvError::Lagged(_)) => {
-                            // Missed some events, continue
+            let result = event_rx.recv().await;
+            match result {
+                Ok(event) => {
+                    // Filter by event type if specified
+                    if let Some(ref filter) = event_type_filter {
+                        if event.event_type.as_str() != filter {
                             continue;
                         }
-                        Err(broadcast::error::RecvError::Closed) => {
-                            break;
-                        }
                     }
+
+                    // Send event to client
+                    let event_msg = OutgoingMessage::Event(EventMessage {
+                        id: sub_id,
+                        msg_type: "event",
+                        event: serde_json::json!({
+                            "event_type": event.event_type,
+                            "data": event.data,
+                            "origin": "LOCAL",
+                            "time_fired": event.time_fired.to_rfc3339(),
+                            "context": {
+                                "id": event.context.id.to_string(),
+                                "parent_id": event.context.parent_id,
+                                "user_id": event.context.user_id,
+                            }
+                        }),
+                    });
+                    if !conn_clone.queue_message(&tx_clone, event_msg) {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    // Missed some events, continue
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    break;
                 }
             }
         }
-    });
+    };
+    conn.spawn_subscription(id, forward).await;
 
     // Send success response - explicitly include "result": null to match Python HA
     let result = OutgoingMessage::Result(ResultMessage {
@@ -239,11 +231,7 @@ pub async fn handle_unsubscribe_events(
     subscription: u64,
     tx: &mpsc::Sender<OutgoingMessage>,
 ) -> Result<(), String> {
-    let mut subs = conn.subscriptions.write().await;
-    if let Some(cancel_tx) = subs.remove(&subscription) {
-        let _ = cancel_tx.send(());
-    }
-    drop(subs);
+    conn.remove_subscription(subscription).await;
 
     // Explicitly include "result": null to match Python HA
     let result = OutgoingMessage::Result(ResultMessage {
@@ -256,158 +244,330 @@ pub async fn handle_unsubscribe_events(
     tx.send(result).await.map_err(|e| e.to_string())
 }
 
-/// Handle subscribe_entities command
-pub async fn handle_subscribe_entities(
+/// Parse a trigger config (a single trigger or a list) into triggers
+///
+/// Accepts both the `platform:` key and the newer `trigger:` key HA uses
+/// for the trigger type.
+pub fn parse_triggers(config: serde_json::Value) -> Result<Vec<Trigger>, String> {
+    let configs = match config {
+        serde_json::Value::Array(items) => items,
+        other => vec![other],
+    };
+
+    configs
+        .into_iter()
+        .map(|mut config| {
+            if let Some(obj) = config.as_object_mut() {
+                if !obj.contains_key("platform") {
+                    if let Some(platform) = obj.remove("trigger") {
+                        obj.insert("platform".to_string(), platform);
+                    }
+                }
+            }
+            serde_json::from_value(config).map_err(|e| e.to_string())
+        })
+        .collect()
+}
+
+/// Handle subscribe_trigger command
+///
+/// Sends an event with the trigger variables each time one of the triggers
+/// matches an event on the bus. Time-based triggers are rejected since only
+/// bus events are evaluated.
+pub async fn handle_subscribe_trigger(
     conn: &Arc<ActiveConnection>,
     id: u64,
-    entity_ids: Option<Vec<String>>,
+    trigger: serde_json::Value,
+    variables: Option<HashMap<String, serde_json::Value>>,
     tx: &mpsc::Sender<OutgoingMessage>,
 ) -> Result<(), String> {
-    // Create cancellation channel
-    let (cancel_tx, mut cancel_rx) = broadcast::channel::<()>(1);
-
-    // Store subscription
-    {
-        let mut subs = conn.subscriptions.write().await;
-        subs.insert(id, cancel_tx);
-    }
-
-    // Get initial states for the requested entities
-    let states = conn.state.state_machine.all();
-    let filtered_states: Vec<&ha_core::State> = if let Some(ref ids) = entity_ids {
-        states
+    let triggers = parse_triggers(trigger).and_then(|triggers: Vec<Trigger>| {
+        match triggers
             .iter()
-            .filter(|s| ids.contains(&s.entity_id.to_string()))
-            .collect()
-    } else {
-        states.iter().collect()
-    };
-
-    // Build initial state response, enriching with entity registry data
-    let mut additions = serde_json::Map::new();
-    for state in filtered_states {
-        // Start with the state's attributes
-        let mut attrs = state.attributes.clone();
-
-        // Enrich with entity registry data if available
-        if let Some(entry) = conn
-            .state
-            .registries
-            .entities
-            .get(&state.entity_id.to_string())
+            .find(|t| matches!(t, Trigger::Time(_) | Trigger::TimePattern(_)))
         {
-            entry.enrich_attributes(&mut attrs);
+            Some(t) => Err(format!(
+                "{} triggers are not supported for subscriptions",
+                t.platform()
+            )),
+            None => Ok(triggers),
         }
-
-        additions.insert(
-            state.entity_id.to_string(),
-            serde_json::json!({
-                "s": state.state,
-                "a": attrs,
-                "c": state.context.id.to_string(),
-                "lc": state.last_changed.timestamp_millis() as f64 / 1000.0,
-                "lu": state.last_updated.timestamp_millis() as f64 / 1000.0,
-            }),
-        );
-    }
-
-    // Send initial state event
-    let initial_event = OutgoingMessage::Event(EventMessage {
-        id,
-        msg_type: "event",
-        event: serde_json::json!({
-            "a": additions,
-        }),
     });
-    tx.send(initial_event).await.map_err(|e| e.to_string())?;
+    let triggers = match triggers {
+        Ok(triggers) => triggers,
+        Err(e) => {
+            let result = OutgoingMessage::Result(ResultMessage {
+                id,
+                msg_type: "result",
+                success: false,
+                result: None,
+                error: Some(ErrorInfo {
+                    code: "invalid_format".to_string(),
+                    message: format!("Invalid trigger: {}", e),
+                }),
+            });
+            return tx.send(result).await.map_err(|e| e.to_string());
+        }
+    };
 
-    // Subscribe to state changes
-    let entity_ids_filter = entity_ids.clone();
+    let evaluator = TriggerEvaluator::new(
+        conn.state.state_machine.clone(),
+        conn.state.template_engine.clone(),
+    );
+    let eval_ctx = TriggerEvalContext {
+        variables: variables.unwrap_or_default(),
+    };
     let tx_clone = tx.clone();
+    let conn_clone = Arc::clone(conn);
     let sub_id = id;
-    let registries = conn.state.registries.clone();
 
+    // Subscribe before replying so no event after the result is missed
     let mut event_rx = conn.state.event_bus.subscribe_all();
 
-    // Spawn task to forward state change events
-    tokio::spawn(async move {
+    // Send success response before any trigger event can be forwarded
+    let result = OutgoingMessage::Result(ResultMessage {
+        id,
+        msg_type: "result",
+        success: true,
+        result: Some(serde_json::Value::Null),
+        error: None,
+    });
+    tx.send(result).await.map_err(|e| e.to_string())?;
+
+    // Spawn task to evaluate triggers against incoming events
+    let forward = async move {
         loop {
-            tokio::select! {
-                _ = cancel_rx.recv() => {
-                    debug!("Entity subscription {} cancelled", sub_id);
-                    break;
+            let result = event_rx.recv().await;
+            let event = match result {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            for (idx, trigger) in triggers.iter().enumerate() {
+                let data = match evaluator.evaluate(trigger, &event, &eval_ctx) {
+                    Ok(Some(data)) => data,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        warn!("Trigger subscription {} failed to evaluate: {}", sub_id, e);
+                        continue;
+                    }
+                };
+
+                let mut trigger_vars = serde_json::to_value(&data).unwrap_or_default();
+                if let Some(obj) = trigger_vars.as_object_mut() {
+                    obj.insert("idx".to_string(), serde_json::json!(idx.to_string()));
                 }
-                result = event_rx.recv() => {
-                    match result {
-                        Ok(event) => {
-                            // Only forward state_changed events
-                            if event.event_type.as_str() != "state_changed" {
-                                continue;
-                            }
 
-                            // Extract entity_id from event data
-                            if let Some(entity_id) = event.data.get("entity_id").and_then(|v| v.as_str()) {
-                                // Filter by entity_ids if specified
-                                if let Some(ref ids) = entity_ids_filter {
-                                    if !ids.contains(&entity_id.to_string()) {
-                                        continue;
-                                    }
-                                }
-
-                                // Build change event with enriched attributes
-                                if let Some(new_state) = event.data.get("new_state") {
-                                    // Get attributes and enrich with entity registry data
-                                    let mut attrs = new_state
-                                        .get("attributes")
-                                        .and_then(|a| a.as_object())
-                                        .cloned()
-                                        .unwrap_or_default();
-
-                                    // Enrich with entity registry data
-                                    if let Some(entry) = registries.entities.get(entity_id) {
-                                        entry.enrich_json_attributes(&mut attrs);
-                                    }
-
-                                    let mut changes = serde_json::Map::new();
-                                    changes.insert(
-                                        entity_id.to_string(),
-                                        serde_json::json!({
-                                            "+": {
-                                                "s": new_state.get("state"),
-                                                "a": attrs,
-                                                "c": new_state.get("context").and_then(|c| c.get("id")),
-                                                "lc": new_state.get("last_changed"),
-                                                "lu": new_state.get("last_updated"),
-                                            }
-                                        }),
-                                    );
-
-                                    let change_event = OutgoingMessage::Event(EventMessage {
-                                        id: sub_id,
-                                        msg_type: "event",
-                                        event: serde_json::json!({
-                                            "c": changes,
-                                        }),
-                                    });
-                                    if tx_clone.send(change_event).await.is_err() {
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                        Err(broadcast::error::RecvError::Lagged(_)) => {
-                            continue;
-                        }
-                        Err(broadcast::error::RecvError::Closed) => {
-                            break;
+                let event_msg = OutgoingMessage::Event(EventMessage {
+                    id: sub_id,
+                    msg_type: "event",
+                    event: serde_json::json!({
+                        "variables": {"trigger": trigger_vars},
+                        "context": {
+                            "id": event.context.id.to_string(),
+                            "parent_id": event.context.parent_id,
+                            "user_id": event.context.user_id,
                         }
-                    }
+                    }),
+                });
+                if !conn_clone.queue_message(&tx_clone, event_msg) {
+                    return;
                 }
             }
         }
+    };
+    conn.spawn_subscription(id, forward).await;
+    Ok(())
+}
+
+/// Deserialize a single config or a list of configs into `T`
+fn validate_configs<T: serde::de::DeserializeOwned>(
+    config: serde_json::Value,
+) -> Result<(), String> {
+    let configs = match config {
+        serde_json::Value::Array(items) => items,
+        other => vec![other],
+    };
+    for config in configs {
+        serde_json::from_value::<T>(config).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Handle validate_config command
+///
+/// Checks that each provided trigger/condition/action section parses,
+/// without running anything, and reports `{valid, error}` per section.
+pub async fn handle_validate_config(
+    id: u64,
+    trigger: Option<serde_json::Value>,
+    condition: Option<serde_json::Value>,
+    action: Option<serde_json::Value>,
+    tx: &mpsc::Sender<OutgoingMessage>,
+) -> Result<(), String> {
+    let sections = [
+        ("trigger", trigger.map(|c| parse_triggers(c).map(|_| ()))),
+        ("condition", condition.map(validate_configs::<Condition>)),
+        ("action", action.map(validate_configs::<Action>)),
+    ];
+
+    let mut response = serde_json::Map::new();
+    for (key, outcome) in sections {
+        let Some(outcome) = outcome else {
+            continue;
+        };
+        let section = match outcome {
+            Ok(()) => serde_json::json!({"valid": true, "error": null}),
+            Err(e) => serde_json::json!({"valid": false, "error": e}),
+        };
+        response.insert(key.to_string(), section);
+    }
+
+    let result = OutgoingMessage::Result(ResultMessage {
+        id,
+        msg_type: "result",
+        success: true,
+        result: Some(serde_json::Value::Object(response)),
+        error: None,
     });
+    tx.send(result).await.map_err(|e| e.to_string())
+}
+
+/// State attributes as the frontend sees them, with entity registry data
+fn frontend_attributes(
+    registries: &ha_registries::Registries,
+    state: &ha_core::State,
+) -> HashMap<String, serde_json::Value> {
+    let mut attrs = state.attributes.clone();
+    if let Some(entry) = registries.entities.get(state.entity_id.as_str()) {
+        entry.enrich_attributes(&mut attrs);
+    }
+    attrs
+}
+
+/// Timestamp in the compressed format, seconds since the epoch
+fn compressed_time(time: chrono::DateTime<chrono::Utc>) -> f64 {
+    time.timestamp_millis() as f64 / 1000.0
+}
+
+/// Context in the compressed format: just its ID unless it has a parent or user
+fn compressed_context(context: &ha_core::Context) -> serde_json::Value {
+    if context.parent_id.is_none() && context.user_id.is_none() {
+        return serde_json::json!(context.id);
+    }
+    serde_json::json!({
+        "id": context.id,
+        "parent_id": context.parent_id,
+        "user_id": context.user_id,
+    })
+}
+
+/// A state in the compressed format of `subscribe_entities` additions
+///
+/// `lu` is left out when it equals `lc`, the frontend falls back to it.
+pub(crate) fn compressed_state(
+    state: &ha_core::State,
+    attrs: &HashMap<String, serde_json::Value>,
+) -> serde_json::Value {
+    let mut compressed = serde_json::json!({
+        "s": state.state,
+        "a": attrs,
+        "c": compressed_context(&state.context),
+        "lc": compressed_time(state.last_changed),
+    });
+    if state.last_updated != state.last_changed {
+        compressed["lu"] = serde_json::json!(compressed_time(state.last_updated));
+    }
+    compressed
+}
+
+/// Difference between two states in the compressed format of
+/// `subscribe_entities` changes
+///
+/// Changed fields and added or changed attributes are under `+`, removed
+/// attributes under `-`.
+pub(crate) fn compressed_state_diff(
+    old: &ha_core::State,
+    old_attrs: &HashMap<String, serde_json::Value>,
+    new: &ha_core::State,
+    new_attrs: &HashMap<String, serde_json::Value>,
+) -> serde_json::Value {
+    let mut additions = serde_json::Map::new();
+    if old.state != new.state {
+        additions.insert("s".to_string(), serde_json::json!(new.state));
+        additions.insert(
+            "lc".to_string(),
+            serde_json::json!(compressed_time(new.last_changed)),
+        );
+    } else if old.last_updated != new.last_updated {
+        additions.insert(
+            "lu".to_string(),
+            serde_json::json!(compressed_time(new.last_updated)),
+        );
+    }
+    if old.context.id != new.context.id {
+        additions.insert("c".to_string(), compressed_context(&new.context));
+    }
+
+    let changed: serde_json::Map<String, serde_json::Value> = new_attrs
+        .iter()
+        .filter(|(key, value)| old_attrs.get(*key) != Some(*value))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if !changed.is_empty() {
+        additions.insert("a".to_string(), serde_json::Value::Object(changed));
+    }
+    let mut removed: Vec<&String> = old_attrs
+        .keys()
+        .filter(|key| !new_attrs.contains_key(*key))
+        .collect();
+    removed.sort();
+
+    let mut diff = serde_json::json!({ "+": additions });
+    if !removed.is_empty() {
+        diff["-"] = serde_json::json!({ "a": removed });
+    }
+    diff
+}
+
+/// Handle subscribe_entities command
+///
+/// Sends the requested states as one compressed `a` snapshot, then pushes
+/// each change as an `a` (added entity), `c` (diff) or `r` (removed entity)
+/// event.
+pub async fn handle_subscribe_entities(
+    conn: &Arc<ActiveConnection>,
+    id: u64,
+    entity_ids: Option<Vec<String>>,
+    tx: &mpsc::Sender<OutgoingMessage>,
+) -> Result<(), String> {
+    let entity_ids: Option<HashSet<String>> =
+        entity_ids.map(|ids| ids.into_iter().map(|id| id.to_lowercase()).collect());
+    let wanted = move |entity_id: &str| {
+        entity_ids
+            .as_ref()
+            .map_or(true, |ids| ids.contains(entity_id))
+    };
+
+    // Subscribe before taking the snapshot so no change falls in between
+    let mut event_rx = conn
+        .state
+        .event_bus
+        .subscribe(ha_core::events::STATE_CHANGED);
+    let registries = conn.state.registries.clone();
+
+    let mut additions = serde_json::Map::new();
+    for state in conn.state.state_machine.all() {
+        if wanted(state.entity_id.as_str()) {
+            let attrs = frontend_attributes(&registries, &state);
+            additions.insert(
+                state.entity_id.to_string(),
+                compressed_state(&state, &attrs),
+            );
+        }
+    }
 
-    // Send success response
     let result = OutgoingMessage::Result(ResultMessage {
         id,
         msg_type: "result",
@@ -415,7 +575,58 @@ pub async fn handle_subscribe_entities(
         result: Some(serde_json::Value::Null),
         error: None,
     });
-    tx.send(result).await.map_err(|e| e.to_string())
+    tx.send(result).await.map_err(|e| e.to_string())?;
+    let initial_event = OutgoingMessage::Event(EventMessage {
+        id,
+        msg_type: "event",
+        event: serde_json::json!({ "a": additions }),
+    });
+    tx.send(initial_event).await.map_err(|e| e.to_string())?;
+
+    let tx_clone = tx.clone();
+    let conn_clone = Arc::clone(conn);
+    let forward = async move {
+        loop {
+            let event = match event_rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let Ok(data) =
+                serde_json::from_value::<ha_core::events::StateChangedData>(event.data.clone())
+            else {
+                continue;
+            };
+            let entity_id = data.entity_id.to_string();
+            if !wanted(&entity_id) {
+                continue;
+            }
+
+            let message = match (&data.old_state, &data.new_state) {
+                (_, None) => serde_json::json!({ "r": [entity_id] }),
+                (None, Some(new)) => {
+                    let attrs = frontend_attributes(&registries, new);
+                    serde_json::json!({ "a": { entity_id: compressed_state(new, &attrs) } })
+                }
+                (Some(old), Some(new)) => {
+                    let old_attrs = frontend_attributes(&registries, old);
+                    let new_attrs = frontend_attributes(&registries, new);
+                    let diff = compressed_state_diff(old, &old_attrs, new, &new_attrs);
+                    serde_json::json!({ "c": { entity_id: diff } })
+                }
+            };
+            let change_event = OutgoingMessage::Event(EventMessage {
+                id,
+                msg_type: "event",
+                event: message,
+            });
+            if !conn_clone.queue_message(&tx_clone, change_event) {
+                break;
+            }
+        }
+    };
+    conn.spawn_subscription(id, forward).await;
+    Ok(())
 }
 
 // =============================================================================
@@ -440,11 +651,20 @@ pub async fn handle_call_service(
         if let Some(entity_ids) = target.entity_id {
             data["entity_id"] = serde_json::json!(entity_ids.to_vec());
         }
-        if let Some(device_ids) = target.device_id {
-            data["device_id"] = serde_json::json!(device_ids);
+        for (key, ids) in [
+            ("device_id", target.device_id),
+            ("area_id", target.area_id),
+            ("floor_id", target.floor_id),
+            ("label_id", target.label_id),
+        ] {
+            if let Some(ids) = ids {
+                data[key] = serde_json::json!(ids);
+            }
         }
-        if let Some(area_ids) = target.area_id {
-            data["area_id"] = serde_json::json!(area_ids);
+        // Devices, areas, floors and labels expand to their entities before dispatch
+        let entity_ids = conn.state.registries.resolve_target(&data);
+        if !entity_ids.is_empty() {
+            data["entity_id"] = serde_json::json!(entity_ids);
         }
     }
 
@@ -466,10 +686,9 @@ pub async fn handle_call_service(
                 }
             });
 
+            // HA always includes the key when a response was requested
             if return_response {
-                if let Some(resp) = response {
-                    result_data["response"] = resp;
-                }
+                result_data["response"] = response.unwrap_or(serde_json::Value::Null);
             }
 
             let result = OutgoingMessage::Result(ResultMessage {
@@ -488,7 +707,7 @@ pub async fn handle_call_service(
                 success: false,
                 result: None,
                 error: Some(ErrorInfo {
-                    code: "service_error".to_string(),
+                    code: service_error_code(&e).to_string(),
                     message: e.to_string(),
                 }),
             });
@@ -497,6 +716,19 @@ pub async fn handle_call_service(
     }
 }
 
+/// Map a service error to the error code HA's `call_service` command uses
+fn service_error_code(error: &ServiceError) -> &'static str {
+    match error {
+        ServiceError::NotFound { .. } => "not_found",
+        ServiceError::InvalidData(_) => "invalid_format",
+        ServiceError::ResponseNotSupported | ServiceError::ResponseRequired => {
+            "service_validation_error"
+        }
+        ServiceError::CallFailed(_) | ServiceError::Timeout(_) => "home_assistant_error",
+        ServiceError::PermissionDenied(_) => "unauthorized",
+    }
+}
+
 /// Handle fire_event command
 pub async fn handle_fire_event(
     conn: &Arc<ActiveConnection>,
@@ -600,11 +832,6 @@ pub async fn handle_entity_registry_remove(
 ) -> Result<(), String> {
     match conn.state.registries.entities.remove(entity_id) {
         Some(_) => {
-            // Save changes to storage
-            if let Err(e) = conn.state.registries.entities.save().await {
-                warn!("Failed to save entity registry after removal: {}", e);
-            }
-
             let result = OutgoingMessage::Result(ResultMessage {
                 id,
                 msg_type: "result",
@@ -661,12 +888,38 @@ pub async fn handle_entity_registry_update(
         return tx.send(result).await.map_err(|e| e.to_string());
     }
 
+    // Rename first so the remaining changes apply to the renamed entry
+    let entity_id = match new_entity_id {
+        Some(new_id) if new_id != entity_id => {
+            if let Err(e) = conn
+                .state
+                .registries
+                .entities
+                .update_entity_id(entity_id, &new_id)
+            {
+                let result = OutgoingMessage::Result(ResultMessage {
+                    id,
+                    msg_type: "result",
+                    success: false,
+                    result: None,
+                    error: Some(ErrorInfo {
+                        code: "invalid_info".to_string(),
+                        message: e.to_string(),
+                    }),
+                });
+                return tx.send(result).await.map_err(|e| e.to_string());
+            }
+            new_id
+        }
+        _ => entity_id.to_string(),
+    };
+
     // Update the entity entry
     let updated_entry = conn
         .state
         .registries
         .entities
-        .update(entity_id, |entry| {
+        .update(&entity_id, |entry| {
             if let Some(n) = name {
                 entry.name = Some(n);
             }
@@ -704,23 +957,6 @@ pub async fn handle_entity_registry_update(
         })
         .expect("Entity should exist after presence check");
 
-    // Handle entity_id rename if requested
-    if let Some(new_id) = new_entity_id {
-        if new_id != entity_id {
-            // TODO: Implement entity_id rename - requires updating the entity_id field
-            // and re-indexing. For now, this is not supported.
-            warn!(
-                "Entity ID rename not yet implemented: {} -> {}",
-                entity_id, new_id
-            );
-        }
-    }
-
-    // Save changes to storage
-    if let Err(e) = conn.state.registries.entities.save().await {
-        warn!("Failed to save entity registry after update: {}", e);
-    }
-
     let result = OutgoingMessage::Result(ResultMessage {
         id,
         msg_type: "result",
@@ -1488,52 +1724,150 @@ pub async fn handle_system_log_list(
     tx.send(result).await.map_err(|e| e.to_string())
 }
 
+/// Handle websocket/connections command
+///
+/// Lists the traffic and backpressure counters of every open connection.
+/// Only admins may see other users' connections.
+pub async fn handle_websocket_connections(
+    conn: &Arc<ActiveConnection>,
+    id: u64,
+    tx: &mpsc::Sender<OutgoingMessage>,
+) -> Result<(), String> {
+    let is_admin = conn.user_id.as_deref().map_or(true, |user_id| {
+        conn.state.auth_state.user_permissions(user_id).admin
+    });
+    let result = if is_admin {
+        OutgoingMessage::Result(ResultMessage {
+            id,
+            msg_type: "result",
+            success: true,
+            result: Some(serde_json::json!(conn.state.websocket_connections.list())),
+            error: None,
+        })
+    } else {
+        OutgoingMessage::Result(ResultMessage {
+            id,
+            msg_type: "result",
+            success: false,
+            result: None,
+            error: Some(ErrorInfo {
+                code: "unauthorized".to_string(),
+                message: "Only admins may list connections".to_string(),
+            }),
+        })
+    };
+    tx.send(result).await.map_err(|e| e.to_string())
+}
+
+/// How often time-dependent templates are re-rendered, matching HA
+const TEMPLATE_TIME_REFRESH: std::time::Duration = std::time::Duration::from_secs(60);
+
 /// Handle render_template command
+///
+/// Renders the template once, then re-renders and pushes a new event whenever
+/// an entity it read changes, or every minute if it uses the current time.
+/// Render errors are sent as error events and the subscription stays active.
 pub async fn handle_render_template(
-    _conn: &Arc<ActiveConnection>,
+    conn: &Arc<ActiveConnection>,
     id: u64,
     template: &str,
     variables: Option<HashMap<String, serde_json::Value>>,
     tx: &mpsc::Sender<OutgoingMessage>,
 ) -> Result<(), String> {
-    // For now, we'll do a simple template rendering
-    // In a full implementation, this would use the TemplateEngine
-
-    // Simple variable substitution for basic templates
-    let mut result_str = template.to_string();
-
-    // Handle variables if provided
-    if let Some(vars) = variables {
-        for (key, value) in vars {
-            let placeholder = format!("{{{{ {} }}}}", key);
-            let value_str = match value {
-                serde_json::Value::String(s) => s,
-                other => other.to_string(),
-            };
-            result_str = result_str.replace(&placeholder, &value_str);
-        }
-    }
+    // Subscribe before the first render so no change in between is missed
+    let mut event_rx = conn.state.event_bus.subscribe_all();
 
-    // For entity state templates like {{ states('sensor.temperature') }}
-    // We would need the template engine, but for now return the template as-is
-    // if it contains unresolved Jinja syntax
+    let engine = conn.state.template_engine.clone();
+    let template = template.to_string();
+    let variables = variables.unwrap_or_default();
+    let (rendered, mut info) = engine.render_with_info(&template, variables.clone());
 
+    // Send success response, then the initial render
     let result = OutgoingMessage::Result(ResultMessage {
         id,
         msg_type: "result",
         success: true,
-        result: Some(serde_json::json!({
-            "result": result_str,
-            "listeners": {
-                "all": false,
-                "domains": [],
-                "entities": [],
-                "time": false
-            }
-        })),
+        result: Some(serde_json::Value::Null),
         error: None,
     });
-    tx.send(result).await.map_err(|e| e.to_string())
+    tx.send(result).await.map_err(|e| e.to_string())?;
+    tx.send(render_template_event(id, rendered, &info))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let tx_clone = tx.clone();
+    let conn_clone = Arc::clone(conn);
+    let sub_id = id;
+
+    // Spawn task to re-render when a dependency changes
+    let forward = async move {
+        let start = tokio::time::Instant::now() + TEMPLATE_TIME_REFRESH;
+        let mut time_refresh = tokio::time::interval_at(start, TEMPLATE_TIME_REFRESH);
+
+        loop {
+            let rerender = tokio::select! {
+                _ = time_refresh.tick(), if info.time => true,
+                result = event_rx.recv() => {
+                    match result {
+                        Ok(event) => {
+                            event.event_type.as_str() == "state_changed"
+                                && event
+                                    .data
+                                    .get("entity_id")
+                                    .and_then(|v| v.as_str())
+                                    .is_some_and(|entity_id| info.is_affected_by(entity_id))
+                        }
+                        // Missed some events, render again to be safe
+                        Err(broadcast::error::RecvError::Lagged(_)) => true,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            };
+
+            if !rerender {
+                continue;
+            }
+
+            let (rendered, new_info) = engine.render_with_info(&template, variables.clone());
+            info = new_info;
+            if !conn_clone.queue_message(&tx_clone, render_template_event(sub_id, rendered, &info))
+            {
+                break;
+            }
+        }
+    };
+    conn.spawn_subscription(id, forward).await;
+
+    Ok(())
+}
+
+/// Build a render_template event from a render result and its dependencies
+fn render_template_event(
+    id: u64,
+    rendered: ha_template::TemplateResult<String>,
+    info: &ha_template::RenderInfo,
+) -> OutgoingMessage {
+    let event = match rendered {
+        Ok(result) => serde_json::json!({
+            "result": result,
+            "listeners": {
+                "all": info.all_states,
+                "domains": info.domains,
+                "entities": info.entities,
+                "time": info.time,
+            }
+        }),
+        Err(e) => serde_json::json!({
+            "error": e.to_string(),
+            "level": "ERROR",
+        }),
+    };
+
+    OutgoingMessage::Event(EventMessage {
+        id,
+        msg_type: "event",
+        event,
+    })
 }
 
 /// Handle auth/current_user command - returns current user info
@@ -1544,7 +1878,7 @@ pub async fn handle_auth_current_user(
 ) -> Result<(), String> {
     // Return a default user for now
     let user = serde_json::json!({
-        "id": conn.user_id.clone().unwrap_or_else(|| "default-user-id".to_string()),
+        "id": current_user_id(conn),
         "name": "Owner",
         "is_owner": true,
         "is_admin": true,
@@ -1562,6 +1896,100 @@ pub async fn handle_auth_current_user(
     tx.send(result).await.map_err(|e| e.to_string())
 }
 
+/// User the connection acts as, the default user for unidentified tokens
+fn current_user_id(conn: &ActiveConnection) -> String {
+    conn.user_id
+        .clone()
+        .unwrap_or_else(|| "default-user-id".to_string())
+}
+
+/// Handle auth/long_lived_access_token command
+///
+/// The token is only ever sent in this result.
+pub async fn handle_auth_long_lived_access_token(
+    conn: &Arc<ActiveConnection>,
+    id: u64,
+    client_name: &str,
+    client_icon: Option<&str>,
+    lifespan_days: Option<u64>,
+    tx: &mpsc::Sender<OutgoingMessage>,
+) -> Result<(), String> {
+    let lifespan = lifespan_days.map_or(crate::auth::LONG_LIVED_TOKEN_LIFESPAN, |days| {
+        std::time::Duration::from_secs(days * 86400)
+    });
+    let token = conn
+        .state
+        .auth_state
+        .create_long_lived_access_token(&current_user_id(conn), client_name, client_icon, lifespan)
+        .await;
+    info!("Created long-lived access token '{}'", client_name);
+
+    let result = OutgoingMessage::Result(ResultMessage {
+        id,
+        msg_type: "result",
+        success: true,
+        result: Some(serde_json::json!(token)),
+        error: None,
+    });
+    tx.send(result).await.map_err(|e| e.to_string())
+}
+
+/// Handle auth/refresh_tokens command
+pub async fn handle_auth_refresh_tokens(
+    conn: &Arc<ActiveConnection>,
+    id: u64,
+    tx: &mpsc::Sender<OutgoingMessage>,
+) -> Result<(), String> {
+    let tokens = conn
+        .state
+        .auth_state
+        .refresh_tokens(&current_user_id(conn))
+        .await;
+    let result = OutgoingMessage::Result(ResultMessage {
+        id,
+        msg_type: "result",
+        success: true,
+        result: Some(serde_json::json!(tokens)),
+        error: None,
+    });
+    tx.send(result).await.map_err(|e| e.to_string())
+}
+
+/// Handle auth/delete_refresh_token command
+pub async fn handle_auth_delete_refresh_token(
+    conn: &Arc<ActiveConnection>,
+    id: u64,
+    refresh_token_id: &str,
+    tx: &mpsc::Sender<OutgoingMessage>,
+) -> Result<(), String> {
+    let deleted = conn
+        .state
+        .auth_state
+        .delete_refresh_token(&current_user_id(conn), refresh_token_id)
+        .await;
+    let result = if deleted {
+        OutgoingMessage::Result(ResultMessage {
+            id,
+            msg_type: "result",
+            success: true,
+            result: Some(serde_json::Value::Null),
+            error: None,
+        })
+    } else {
+        OutgoingMessage::Result(ResultMessage {
+            id,
+            msg_type: "result",
+            success: false,
+            result: None,
+            error: Some(ErrorInfo {
+                code: "invalid_token_id".to_string(),
+                message: "Received invalid token".to_string(),
+            }),
+        })
+    };
+    tx.send(result).await.map_err(|e| e.to_string())
+}
+
 /// Handle recorder/info command
 pub async fn handle_recorder_info(
     _conn: &Arc<ActiveConnection>,
@@ -1585,6 +2013,160 @@ pub async fn handle_recorder_info(
     tx.send(result).await.map_err(|e| e.to_string())
 }
 
+/// Handle recorder/statistics_during_period command
+///
+/// Returns `{statistic_id: [row, ...]}` with `start`/`end` as epoch
+/// milliseconds, like HA. Without a recorder there are no statistics.
+pub async fn handle_statistics_during_period(
+    conn: &Arc<ActiveConnection>,
+    id: u64,
+    start_time: &str,
+    end_time: Option<&str>,
+    statistic_ids: Option<Vec<String>>,
+    period: Option<&str>,
+    tx: &mpsc::Sender<OutgoingMessage>,
+) -> Result<(), String> {
+    let invalid = |message: String| {
+        OutgoingMessage::Result(ResultMessage {
+            id,
+            msg_type: "result",
+            success: false,
+            result: None,
+            error: Some(ErrorInfo {
+                code: "invalid_format".to_string(),
+                message,
+            }),
+        })
+    };
+
+    let Some(start) = crate::parse_history_time(start_time) else {
+        return tx
+            .send(invalid("Invalid start_time".to_string()))
+            .await
+            .map_err(|e| e.to_string());
+    };
+    let end = match end_time.map(crate::parse_history_time) {
+        Some(None) => {
+            return tx
+                .send(invalid("Invalid end_time".to_string()))
+                .await
+                .map_err(|e| e.to_string());
+        }
+        Some(end) => end,
+        None => None,
+    };
+    let period: StatisticsPeriod =
+        match serde_json::from_value(serde_json::json!(period.unwrap_or("hour"))) {
+            Ok(period) => period,
+            Err(_) => {
+                return tx
+                    .send(invalid(format!(
+                        "Unsupported period: {}",
+                        period.unwrap_or("")
+                    )))
+                    .await
+                    .map_err(|e| e.to_string());
+            }
+        };
+
+    let statistics = match conn.state.recorder.clone() {
+        Some(recorder) => tokio::task::spawn_blocking(move || {
+            recorder.statistics_during_period(start, end, statistic_ids.as_deref(), period)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?,
+        None => Default::default(),
+    };
+
+    let millis = |time: chrono::DateTime<chrono::Utc>| time.timestamp_millis();
+    let result: serde_json::Map<String, serde_json::Value> = statistics
+        .into_iter()
+        .map(|(statistic_id, rows)| {
+            let rows = rows
+                .iter()
+                .map(|row| {
+                    let mut value = serde_json::json!({
+                        "start": millis(row.start),
+                        "end": millis(row.end),
+                    });
+                    if row.mean.is_some() {
+                        value["mean"] = serde_json::json!(row.mean);
+                        value["min"] = serde_json::json!(row.min);
+                        value["max"] = serde_json::json!(row.max);
+                    }
+                    if row.sum.is_some() {
+                        value["last_reset"] = serde_json::json!(row.last_reset.map(millis));
+                        value["state"] = serde_json::json!(row.state);
+                        value["sum"] = serde_json::json!(row.sum);
+                    }
+                    value
+                })
+                .collect();
+            (statistic_id, serde_json::Value::Array(rows))
+        })
+        .collect();
+
+    let result = OutgoingMessage::Result(ResultMessage {
+        id,
+        msg_type: "result",
+        success: true,
+        result: Some(serde_json::Value::Object(result)),
+        error: None,
+    });
+    tx.send(result).await.map_err(|e| e.to_string())
+}
+
+/// Handle logbook/get_events command
+pub async fn handle_logbook_get_events(
+    conn: &Arc<ActiveConnection>,
+    id: u64,
+    start_time: &str,
+    end_time: Option<&str>,
+    entity_ids: Option<Vec<String>>,
+    tx: &mpsc::Sender<OutgoingMessage>,
+) -> Result<(), String> {
+    let invalid = |message: &str| {
+        OutgoingMessage::Result(ResultMessage {
+            id,
+            msg_type: "result",
+            success: false,
+            result: None,
+            error: Some(ErrorInfo {
+                code: "invalid_format".to_string(),
+                message: message.to_string(),
+            }),
+        })
+    };
+
+    let Some(start) = crate::parse_history_time(start_time) else {
+        return tx
+            .send(invalid("Invalid start_time"))
+            .await
+            .map_err(|e| e.to_string());
+    };
+    let end = match end_time.map(crate::parse_history_time) {
+        Some(Some(end)) => end,
+        Some(None) => {
+            return tx
+                .send(invalid("Invalid end_time"))
+                .await
+                .map_err(|e| e.to_string());
+        }
+        None => chrono::Utc::now(),
+    };
+
+    let entries = crate::logbook_entries(&conn.state, start, end, entity_ids).await?;
+    let result = OutgoingMessage::Result(ResultMessage {
+        id,
+        msg_type: "result",
+        success: true,
+        result: Some(serde_json::json!(entries)),
+        error: None,
+    });
+    tx.send(result).await.map_err(|e| e.to_string())
+}
+
 /// Handle repairs/list_issues command
 pub async fn handle_repairs_list_issues(
     _conn: &Arc<ActiveConnection>,
@@ -1605,11 +2187,21 @@ pub async fn handle_repairs_list_issues(
 }
 
 /// Handle persistent_notification/subscribe command
+///
+/// Sends the current notifications, then pushes `added`/`updated`/`removed`
+/// changes driven by the `persistent_notifications_updated` event until the
+/// client unsubscribes or disconnects.
 pub async fn handle_persistent_notification_subscribe(
     conn: &Arc<ActiveConnection>,
     id: u64,
     tx: &mpsc::Sender<OutgoingMessage>,
 ) -> Result<(), String> {
+    // Subscribe before taking the snapshot so no change falls in between
+    let mut event_rx = conn
+        .state
+        .event_bus
+        .subscribe(persistent_notification::EVENT_NOTIFICATIONS_UPDATED);
+
     // Get current notifications
     let notifications = conn.state.notifications.get_all_map();
 
@@ -1638,7 +2230,34 @@ pub async fn handle_persistent_notification_subscribe(
             "notifications": notifications_json
         }),
     });
-    tx.send(event).await.map_err(|e| e.to_string())
+    tx.send(event).await.map_err(|e| e.to_string())?;
+
+    // Forward later changes as they happen
+    let tx_clone = tx.clone();
+    let conn_clone = Arc::clone(conn);
+    let forward = async move {
+        loop {
+            match event_rx.recv().await {
+                Ok(event) => {
+                    let event_msg = OutgoingMessage::Event(EventMessage {
+                        id,
+                        msg_type: "event",
+                        event: serde_json::json!({
+                            "type": event.data["update_type"],
+                            "notifications": event.data["notifications"],
+                        }),
+                    });
+                    if !conn_clone.queue_message(&tx_clone, event_msg) {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    };
+    conn.spawn_subscription(id, forward).await;
+    Ok(())
 }
 
 /// Handle labs/subscribe command
@@ -1783,6 +2402,7 @@ fn config_entry_to_json(entry: &ha_config_entries::ConfigEntry) -> serde_json::V
         "reason": entry.reason,
         // Required by frontend - empty object for integrations without subentries
         "supported_subentry_types": {},
+        "num_subentries": entry.subentries.len(),
     })
 }
 
@@ -2179,25 +2799,126 @@ pub async fn handle_config_entries_delete(
     }
 }
 
+/// Convert a subentry to the JSON format expected by the frontend
+fn subentry_to_json(subentry: &ha_config_entries::ConfigSubentry) -> serde_json::Value {
+    serde_json::json!({
+        "subentry_id": subentry.subentry_id,
+        "subentry_type": subentry.subentry_type,
+        "title": subentry.title,
+        "unique_id": subentry.unique_id,
+    })
+}
+
+/// Build the error response for a failed subentry operation
+fn subentry_error(id: u64, error: &ConfigEntriesError) -> OutgoingMessage {
+    let code = match error {
+        ConfigEntriesError::NotFound(_) | ConfigEntriesError::SubentryNotFound(_) => "not_found",
+        ConfigEntriesError::AlreadyConfigured { .. } => "already_configured",
+        _ => "unknown_error",
+    };
+    OutgoingMessage::Result(ResultMessage {
+        id,
+        msg_type: "result",
+        success: false,
+        result: None,
+        error: Some(ErrorInfo {
+            code: code.to_string(),
+            message: error.to_string(),
+        }),
+    })
+}
+
 /// Handle config_entries/subentries/list command
 ///
-/// Returns list of subentries for a config entry. Most integrations don't have subentries,
-/// so this returns an empty array.
+/// Returns the subentries of a config entry.
 pub async fn handle_config_entries_subentries_list(
-    _conn: &Arc<ActiveConnection>,
+    conn: &Arc<ActiveConnection>,
     id: u64,
-    _entry_id: &str,
+    entry_id: &str,
     tx: &mpsc::Sender<OutgoingMessage>,
 ) -> Result<(), String> {
-    // Most integrations don't have subentries, return empty array
-    // Per HA format: [{"subentry_id": "...", "subentry_type": "...", "title": "...", "unique_id": "..."}]
-    let result = OutgoingMessage::Result(ResultMessage {
-        id,
-        msg_type: "result",
-        success: true,
-        result: Some(serde_json::json!([])),
-        error: None,
-    });
+    let subentries = conn.state.config_entries.read().await.subentries(entry_id);
+
+    let result = match subentries {
+        Ok(subentries) => OutgoingMessage::Result(ResultMessage {
+            id,
+            msg_type: "result",
+            success: true,
+            result: Some(serde_json::Value::Array(
+                subentries.iter().map(subentry_to_json).collect(),
+            )),
+            error: None,
+        }),
+        Err(e) => subentry_error(id, &e),
+    };
+    tx.send(result).await.map_err(|e| e.to_string())
+}
+
+/// Handle config_entries/subentries/create command
+pub async fn handle_config_entries_subentries_create(
+    conn: &Arc<ActiveConnection>,
+    id: u64,
+    entry_id: &str,
+    subentry: ha_config_entries::ConfigSubentry,
+    tx: &mpsc::Sender<OutgoingMessage>,
+) -> Result<(), String> {
+    info!(
+        "Creating subentry {} for config entry {}",
+        subentry.title, entry_id
+    );
+
+    let added = {
+        let config_entries = conn.state.config_entries.read().await;
+        config_entries.add_subentry(entry_id, subentry).await
+    };
+
+    let result = match added {
+        Ok(subentry) => OutgoingMessage::Result(ResultMessage {
+            id,
+            msg_type: "result",
+            success: true,
+            result: Some(subentry_to_json(&subentry)),
+            error: None,
+        }),
+        Err(e) => {
+            warn!("Failed to create subentry for {}: {}", entry_id, e);
+            subentry_error(id, &e)
+        }
+    };
+    tx.send(result).await.map_err(|e| e.to_string())
+}
+
+/// Handle config_entries/subentries/delete command
+pub async fn handle_config_entries_subentries_delete(
+    conn: &Arc<ActiveConnection>,
+    id: u64,
+    entry_id: &str,
+    subentry_id: &str,
+    tx: &mpsc::Sender<OutgoingMessage>,
+) -> Result<(), String> {
+    info!(
+        "Deleting subentry {} of config entry {}",
+        subentry_id, entry_id
+    );
+
+    let removed = {
+        let config_entries = conn.state.config_entries.read().await;
+        config_entries.remove_subentry(entry_id, subentry_id).await
+    };
+
+    let result = match removed {
+        Ok(_) => OutgoingMessage::Result(ResultMessage {
+            id,
+            msg_type: "result",
+            success: true,
+            result: None,
+            error: None,
+        }),
+        Err(e) => {
+            warn!("Failed to delete subentry {}: {}", subentry_id, e);
+            subentry_error(id, &e)
+        }
+    };
     tx.send(result).await.map_err(|e| e.to_string())
 }
 
@@ -2209,15 +2930,24 @@ pub async fn handle_config_entries_subentries_list(
 ///
 /// Returns flows that are in progress but not started by a user (e.g., discovered devices).
 pub async fn handle_config_entries_flow_progress_list(
+    conn: &Arc<ActiveConnection>,
     id: u64,
     tx: &mpsc::Sender<OutgoingMessage>,
 ) -> Result<(), String> {
-    // Return empty list since we don't have any auto-discovered flows yet
+    let flows = match &conn.state.config_flow_handler {
+        Some(cfm) => cfm.list_flows().await,
+        None => vec![],
+    };
+    let flows: Vec<serde_json::Value> = flows
+        .into_iter()
+        .filter(|flow| flow["context"]["source"] != "user")
+        .collect();
+
     let result = OutgoingMessage::Result(ResultMessage {
         id,
         msg_type: "result",
         success: true,
-        result: Some(serde_json::Value::Array(vec![])),
+        result: Some(serde_json::Value::Array(flows)),
         error: None,
     });
     tx.send(result).await.map_err(|e| e.to_string())
@@ -2318,23 +3048,29 @@ pub async fn handle_config_entries_flow_progress(
         .ok_or_else(|| "Config flow manager not available".to_string())?;
 
     match config_flow_handler.progress_flow(flow_id, user_input).await {
-        Ok(flow_result) => {
+        Ok(mut flow_result) => {
             // If the flow created an entry, we need to save it
             if flow_result.result_type == "create_entry" {
                 if let Some(ref result_data) = flow_result.result {
                     // Create and save the config entry
-                    if let Err(e) = save_config_entry_from_flow(
+                    match save_config_entry_from_flow(
                         &conn.state,
                         &flow_result.handler,
                         flow_result.title.as_deref().unwrap_or(&flow_result.handler),
+                        flow_result.unique_id.as_deref(),
                         result_data,
                     )
                     .await
                     {
-                        warn!("Failed to save config entry: {}", e);
+                        Ok(()) => {}
+                        Err(ConfigEntriesError::AlreadyConfigured { .. }) => {
+                            flow_result = flow_result.into_abort("already_configured");
+                        }
+                        Err(e) => warn!("Failed to save config entry: {}", e),
                     }
                 }
             }
+            crate::finish_reauth_flow(&conn.state, &flow_result).await;
 
             let result = OutgoingMessage::Result(ResultMessage {
                 id,
@@ -2367,12 +3103,14 @@ async fn save_config_entry_from_flow(
     state: &AppState,
     domain: &str,
     title: &str,
+    unique_id: Option<&str>,
     data: &serde_json::Value,
-) -> Result<(), String> {
+) -> Result<(), ConfigEntriesError> {
     use ha_config_entries::ConfigEntry;
 
     // Create entry using the constructor which handles all defaults
     let mut entry = ConfigEntry::new(domain, title);
+    entry.unique_id = unique_id.map(str::to_string);
 
     // Set the data from the flow
     if let Some(obj) = data.as_object() {
@@ -2383,22 +3121,160 @@ async fn save_config_entry_from_flow(
 
     let entry_id = entry.entry_id.clone();
 
-    // Add to config entries
-    {
-        let config_entries = state.config_entries.write().await;
-        let _ = config_entries.add(entry).await;
-    }
+    // Add to config entries (this also saves them to disk)
+    state.config_entries.write().await.add(entry).await?;
+
+    info!("Created config entry {} for {}", entry_id, domain);
+    Ok(())
+}
+
+/// Handle config_entries/options/flow command - start an options flow
+pub async fn handle_config_entries_options_flow(
+    conn: &Arc<ActiveConnection>,
+    id: u64,
+    entry_id: &str,
+    show_advanced_options: bool,
+    tx: &mpsc::Sender<OutgoingMessage>,
+) -> Result<(), String> {
+    info!("Starting options flow for config entry: {}", entry_id);
+
+    let options_flow_handler = conn
+        .state
+        .options_flow_handler
+        .as_ref()
+        .ok_or_else(|| "Options flow manager not available".to_string())?;
+
+    let entry = conn.state.config_entries.read().await.get(entry_id);
+    let flow_result = match entry {
+        Some(entry) => {
+            options_flow_handler
+                .start_flow(&entry, show_advanced_options)
+                .await
+        }
+        None => Err(format!("Config entry not found: {}", entry_id)),
+    };
+
+    let result = match flow_result {
+        Ok(flow_result) => OutgoingMessage::Result(ResultMessage {
+            id,
+            msg_type: "result",
+            success: true,
+            result: Some(serde_json::to_value(&flow_result).unwrap_or_default()),
+            error: None,
+        }),
+        Err(e) => {
+            error!("Failed to start options flow: {}", e);
+            OutgoingMessage::Result(ResultMessage {
+                id,
+                msg_type: "result",
+                success: false,
+                result: None,
+                error: Some(ErrorInfo {
+                    code: "flow_error".to_string(),
+                    message: e,
+                }),
+            })
+        }
+    };
+    tx.send(result).await.map_err(|e| e.to_string())
+}
 
-    // Save to disk
+/// Handle config_entries/options/flow/progress command - continue an options flow
+pub async fn handle_config_entries_options_flow_progress(
+    conn: &Arc<ActiveConnection>,
+    id: u64,
+    flow_id: &str,
+    user_input: Option<serde_json::Value>,
+    tx: &mpsc::Sender<OutgoingMessage>,
+) -> Result<(), String> {
+    info!("Progressing options flow: {}", flow_id);
+
+    let options_flow_handler = conn
+        .state
+        .options_flow_handler
+        .as_ref()
+        .ok_or_else(|| "Options flow manager not available".to_string())?;
+
+    match options_flow_handler
+        .progress_flow(flow_id, user_input)
+        .await
     {
-        let config_entries = state.config_entries.read().await;
-        config_entries
-            .save()
-            .await
-            .map_err(|e| format!("Failed to save config entries: {}", e))?;
+        Ok(flow_result) => {
+            // A finished options flow carries the new options for the entry
+            let applied = if flow_result.result_type == "create_entry" {
+                let options = flow_result.result.clone().unwrap_or_default();
+                apply_options_from_flow(&conn.state, &flow_result.handler, &options).await
+            } else {
+                Ok(())
+            };
+
+            let result = match applied {
+                Ok(()) => OutgoingMessage::Result(ResultMessage {
+                    id,
+                    msg_type: "result",
+                    success: true,
+                    result: Some(serde_json::to_value(&flow_result).unwrap_or_default()),
+                    error: None,
+                }),
+                Err(e) => {
+                    error!("Failed to update config entry options: {}", e);
+                    OutgoingMessage::Result(ResultMessage {
+                        id,
+                        msg_type: "result",
+                        success: false,
+                        result: None,
+                        error: Some(ErrorInfo {
+                            code: "flow_error".to_string(),
+                            message: e,
+                        }),
+                    })
+                }
+            };
+            tx.send(result).await.map_err(|e| e.to_string())
+        }
+        Err(e) => {
+            error!("Failed to progress options flow: {}", e);
+            let result = OutgoingMessage::Result(ResultMessage {
+                id,
+                msg_type: "result",
+                success: false,
+                result: None,
+                error: Some(ErrorInfo {
+                    code: "flow_error".to_string(),
+                    message: e,
+                }),
+            });
+            tx.send(result).await.map_err(|e| e.to_string())
+        }
     }
+}
 
-    info!("Created config entry {} for {}", entry_id, domain);
+/// Replace a config entry's options with the result of an options flow and
+/// reload the entry so the integration picks them up
+async fn apply_options_from_flow(
+    state: &AppState,
+    entry_id: &str,
+    options: &serde_json::Value,
+) -> Result<(), String> {
+    use ha_config_entries::ConfigEntryUpdate;
+
+    let options: HashMap<String, serde_json::Value> = options
+        .as_object()
+        .map(|obj| obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default();
+
+    let config_entries = state.config_entries.read().await;
+    config_entries
+        .update(entry_id, ConfigEntryUpdate::new().options(options))
+        .await
+        .map_err(|e| format!("Failed to update config entry: {}", e))?;
+
+    config_entries
+        .reload(entry_id)
+        .await
+        .map_err(|e| format!("Failed to reload config entry: {}", e))?;
+
+    info!("Updated options for config entry {}", entry_id);
     Ok(())
 }
 
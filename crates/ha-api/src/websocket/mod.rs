@@ -23,7 +23,10 @@ use crate::AppState;
 
 // Re-export public types for external use and tests
 #[allow(unused_imports)]
-pub use connection::ActiveConnection;
+pub use connection::{
+    ActiveConnection, ConnectionInfo, ConnectionMetrics, ConnectionRegistry, ConnectionStats,
+    MAX_PENDING_COMMANDS, MAX_PENDING_MESSAGES,
+};
 #[allow(unused_imports)]
 pub use types::{
     AuthInvalidMessage, AuthOkMessage, AuthRequiredMessage, EntityIds, ErrorInfo, EventMessage,
@@ -200,4 +203,1403 @@ mod tests {
             _ => panic!("Expected ManifestList message"),
         }
     }
+
+    // ==================== render_template subscription ====================
+
+    async fn next_event(
+        rx: &mut tokio::sync::mpsc::Receiver<OutgoingMessage>,
+    ) -> serde_json::Value {
+        let msg = tokio::time::timeout(std::time::Duration::from_secs(2), rx.recv())
+            .await
+            .expect("timed out waiting for event")
+            .expect("channel closed");
+        match msg {
+            OutgoingMessage::Event(event) => event.event,
+            _ => panic!("Expected Event message"),
+        }
+    }
+
+    fn set_state(state: &crate::AppState, entity_id: &str, value: &str) {
+        let (domain, object_id) = entity_id.split_once('.').unwrap();
+        state.state_machine.set(
+            ha_core::EntityId::new(domain, object_id).unwrap(),
+            value,
+            std::collections::HashMap::new(),
+            ha_core::Context::new(),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_render_template_subscription_follows_state_changes() {
+        let state = crate::tests::create_test_state();
+        set_state(&state, "sensor.render_temp", "20");
+        let conn = std::sync::Arc::new(ActiveConnection::new(state.clone(), None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+
+        handlers::handle_render_template(&conn, 1, "{{ states('sensor.render_temp') }}", None, &tx)
+            .await
+            .unwrap();
+
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => assert!(result.success),
+            _ => panic!("Expected Result message"),
+        }
+        let event = next_event(&mut rx).await;
+        assert_eq!(event["result"], "20");
+        assert_eq!(
+            event["listeners"],
+            serde_json::json!({
+                "all": false,
+                "domains": [],
+                "entities": ["sensor.render_temp"],
+                "time": false,
+            })
+        );
+
+        // Unrelated entities do not trigger a render
+        set_state(&state, "sensor.render_other", "1");
+        set_state(&state, "sensor.render_temp", "21");
+        let event = next_event(&mut rx).await;
+        assert_eq!(event["result"], "21");
+
+        // Unsubscribing stops updates
+        handlers::handle_unsubscribe_events(&conn, 2, 1, &tx)
+            .await
+            .unwrap();
+        assert!(matches!(rx.recv().await, Some(OutgoingMessage::Result(_))));
+        tokio::task::yield_now().await;
+        set_state(&state, "sensor.render_temp", "22");
+        let pending = tokio::time::timeout(std::time::Duration::from_millis(100), rx.recv()).await;
+        assert!(pending.is_err(), "no event expected after unsubscribe");
+    }
+
+    #[tokio::test]
+    async fn test_render_template_error_keeps_subscription() {
+        let state = crate::tests::create_test_state();
+        let conn = std::sync::Arc::new(ActiveConnection::new(state.clone(), None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+
+        handlers::handle_render_template(
+            &conn,
+            1,
+            "{{ states.sensor.render_late.state }}",
+            None,
+            &tx,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(rx.recv().await, Some(OutgoingMessage::Result(_))));
+
+        let event = next_event(&mut rx).await;
+        assert_eq!(event["level"], "ERROR");
+        assert!(event["error"]
+            .as_str()
+            .unwrap()
+            .contains("sensor.render_late"));
+
+        set_state(&state, "sensor.render_late", "ready");
+        let event = next_event(&mut rx).await;
+        assert_eq!(event["result"], "ready");
+    }
+
+    /// Wait for the subscriptions of a connection to end on their own
+    async fn wait_unsubscribed(conn: &ActiveConnection) {
+        tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            while conn.subscription_count().await > 0 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("subscription should have ended");
+    }
+
+    #[tokio::test]
+    async fn test_subscription_removed_when_client_gone() {
+        let state = crate::tests::create_test_state();
+        let conn = std::sync::Arc::new(ActiveConnection::new(state.clone(), None));
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+
+        handlers::handle_subscribe_events(&conn, 1, Some("gone_test".to_string()), &tx)
+            .await
+            .unwrap();
+        assert_eq!(conn.subscription_count().await, 1);
+
+        // The forwarding task stops at the next event once the queue is gone
+        drop(rx);
+        state.event_bus.fire(ha_core::Event::new(
+            "gone_test",
+            serde_json::json!({}),
+            ha_core::Context::new(),
+        ));
+        wait_unsubscribed(&conn).await;
+    }
+
+    // ==================== subscribe_trigger ====================
+
+    #[tokio::test]
+    async fn test_subscribe_trigger_state_change() {
+        let state = crate::tests::create_test_state();
+        set_state(&state, "light.trigger_test", "off");
+        let conn = std::sync::Arc::new(ActiveConnection::new(state.clone(), None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+
+        let trigger = serde_json::json!({
+            "trigger": "state",
+            "entity_id": "light.trigger_test",
+            "to": "on",
+            "id": "turned_on",
+        });
+        handlers::handle_subscribe_trigger(&conn, 1, trigger, None, &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => assert!(result.success),
+            _ => panic!("Expected Result message"),
+        }
+
+        set_state(&state, "light.trigger_other", "on");
+        set_state(&state, "light.trigger_test", "on");
+        let event = next_event(&mut rx).await;
+        let trigger = &event["variables"]["trigger"];
+        assert_eq!(trigger["platform"], "state");
+        assert_eq!(trigger["id"], "turned_on");
+        assert_eq!(trigger["idx"], "0");
+        assert_eq!(trigger["entity_id"], "light.trigger_test");
+        assert_eq!(trigger["from_state"]["state"], "off");
+        assert_eq!(trigger["to_state"]["state"], "on");
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_trigger_invalid_config() {
+        let state = crate::tests::create_test_state();
+        let conn = std::sync::Arc::new(ActiveConnection::new(state, None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+
+        let trigger = serde_json::json!({"platform": "not_a_platform"});
+        handlers::handle_subscribe_trigger(&conn, 1, trigger, None, &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => {
+                assert!(!result.success);
+                assert_eq!(result.error.unwrap().code, "invalid_format");
+            }
+            _ => panic!("Expected Result message"),
+        }
+        assert!(conn.subscriptions.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_trigger_rejects_time_triggers() {
+        let state = crate::tests::create_test_state();
+        let conn = std::sync::Arc::new(ActiveConnection::new(state, None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+
+        let trigger = serde_json::json!([
+            {"platform": "state", "entity_id": "light.kitchen"},
+            {"platform": "time_pattern", "minutes": "/5"},
+        ]);
+        handlers::handle_subscribe_trigger(&conn, 1, trigger, None, &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => {
+                assert!(!result.success);
+                let error = result.error.unwrap();
+                assert_eq!(error.code, "invalid_format");
+                assert!(error.message.contains("time_pattern"));
+            }
+            _ => panic!("Expected Result message"),
+        }
+        assert!(conn.subscriptions.read().await.is_empty());
+    }
+
+    // ==================== validate_config ====================
+
+    async fn validate(config: serde_json::Value) -> serde_json::Value {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let msg: IncomingMessage = serde_json::from_value(config).unwrap();
+        let IncomingMessage::ValidateConfig {
+            id,
+            trigger,
+            condition,
+            action,
+        } = msg
+        else {
+            panic!("Expected ValidateConfig message");
+        };
+        handlers::handle_validate_config(id, trigger, condition, action, &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => result.result.unwrap(),
+            _ => panic!("Expected Result message"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_config_valid_state_trigger() {
+        let result = validate(serde_json::json!({
+            "type": "validate_config",
+            "id": 1,
+            "trigger": {"platform": "state", "entity_id": "light.kitchen", "to": "on"},
+        }))
+        .await;
+        assert_eq!(
+            result,
+            serde_json::json!({"trigger": {"valid": true, "error": null}})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_config_unknown_trigger_platform() {
+        let result = validate(serde_json::json!({
+            "type": "validate_config",
+            "id": 1,
+            "trigger": [{"platform": "teleport", "entity_id": "light.kitchen"}],
+            "action": {"service": "light.turn_on"},
+        }))
+        .await;
+        assert_eq!(result["trigger"]["valid"], false);
+        assert!(result["trigger"]["error"]
+            .as_str()
+            .unwrap()
+            .contains("teleport"));
+        assert_eq!(result["action"]["valid"], true);
+    }
+
+    #[tokio::test]
+    async fn test_validate_config_malformed_condition() {
+        let result = validate(serde_json::json!({
+            "type": "validate_config",
+            "id": 1,
+            "condition": [{"condition": "state", "entity_id": "light.kitchen"}],
+        }))
+        .await;
+        assert_eq!(result["condition"]["valid"], false);
+        assert!(result["condition"]["error"].is_string());
+        assert!(result.get("trigger").is_none());
+    }
+
+    // ==================== call_service responses ====================
+
+    /// Call a service over the WebSocket and return its result message
+    async fn call_service(
+        conn: &std::sync::Arc<ActiveConnection>,
+        id: u64,
+        service: &str,
+        return_response: bool,
+    ) -> ResultMessage {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let msg = serde_json::json!({
+            "id": id,
+            "type": "call_service",
+            "domain": "test",
+            "service": service,
+            "return_response": return_response,
+        });
+        dispatch::handle_message(conn, &msg.to_string(), &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => result,
+            _ => panic!("Expected Result message"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_statistics_during_period() {
+        use chrono::{Duration, TimeZone, Utc};
+
+        let mut state = crate::tests::create_test_state();
+        let recorder = ha_recorder::Recorder::open_in_memory(Default::default()).unwrap();
+        recorder.attach(&state.event_bus);
+        let start = Utc.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap();
+        for (minute, value) in [(0, "10"), (30, "20")] {
+            let at = start + Duration::minutes(minute);
+            let mut new_state = ha_core::State::new(
+                "sensor.power".parse().unwrap(),
+                value,
+                std::collections::HashMap::from([
+                    ("state_class".to_string(), serde_json::json!("measurement")),
+                    ("unit_of_measurement".to_string(), serde_json::json!("W")),
+                ]),
+                ha_core::Context::new(),
+            );
+            new_state.last_changed = at;
+            new_state.last_updated = at;
+            state.event_bus.fire(ha_core::Event::new(
+                "state_changed",
+                serde_json::json!({"entity_id": "sensor.power", "new_state": new_state}),
+                ha_core::Context::new(),
+            ));
+        }
+        recorder.flush();
+        recorder.compile_statistics(start).unwrap();
+        state.recorder = Some(std::sync::Arc::new(recorder));
+        let conn = std::sync::Arc::new(ActiveConnection::new(state, None));
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        handlers::handle_statistics_during_period(
+            &conn,
+            1,
+            "2026-03-02T09:00:00Z",
+            None,
+            Some(vec!["sensor.power".to_string()]),
+            Some("hour"),
+            &tx,
+        )
+        .await
+        .unwrap();
+        let Some(OutgoingMessage::Result(result)) = rx.recv().await else {
+            panic!("Expected Result message");
+        };
+        assert_eq!(
+            result.result.unwrap(),
+            serde_json::json!({"sensor.power": [{
+                "start": start.timestamp_millis(),
+                "end": (start + Duration::hours(1)).timestamp_millis(),
+                "mean": 15.0,
+                "min": 10.0,
+                "max": 20.0,
+            }]})
+        );
+
+        handlers::handle_statistics_during_period(&conn, 2, "now", None, None, None, &tx)
+            .await
+            .unwrap();
+        let Some(OutgoingMessage::Result(result)) = rx.recv().await else {
+            panic!("Expected Result message");
+        };
+        assert_eq!(result.error.unwrap().code, "invalid_format");
+    }
+
+    #[tokio::test]
+    async fn test_call_service_returns_response() {
+        let state = crate::tests::create_test_state();
+        state.service_registry.register(
+            "test",
+            "forecast",
+            |_call| async { Ok(Some(serde_json::json!({"forecast": [1, 2]}))) },
+            None,
+            ha_core::SupportsResponse::Optional,
+        );
+        state.service_registry.register(
+            "test",
+            "plain",
+            |_call| async { Ok(None) },
+            None,
+            ha_core::SupportsResponse::None,
+        );
+        let conn = std::sync::Arc::new(ActiveConnection::new(state, None));
+
+        let result = call_service(&conn, 1, "forecast", true).await;
+        assert!(result.success);
+        assert_eq!(
+            result.result.unwrap()["response"],
+            serde_json::json!({"forecast": [1, 2]})
+        );
+
+        // Without return_response the response is left out
+        let result = call_service(&conn, 2, "forecast", false).await;
+        assert!(result.success);
+        assert!(result.result.unwrap().get("response").is_none());
+
+        let result = call_service(&conn, 3, "plain", true).await;
+        assert!(!result.success);
+        assert_eq!(result.error.unwrap().code, "service_validation_error");
+
+        let result = call_service(&conn, 4, "missing", false).await;
+        assert_eq!(result.error.unwrap().code, "not_found");
+    }
+
+    #[tokio::test]
+    async fn test_call_service_expands_label_target() {
+        let state = crate::tests::create_test_state();
+        let entities = &state.registries.entities;
+        for entity_id in ["light.porch", "lock.front_door", "light.desk"] {
+            entities.get_or_create("demo", entity_id, None, None, None);
+        }
+        for entity_id in ["light.porch", "lock.front_door"] {
+            entities
+                .update(entity_id, |e| {
+                    e.labels.insert("critical".to_string());
+                })
+                .unwrap();
+        }
+
+        let received = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = received.clone();
+        state.service_registry.register(
+            "test",
+            "record",
+            move |call| {
+                let sink = sink.clone();
+                async move {
+                    sink.lock()
+                        .unwrap()
+                        .push(call.service_data["entity_id"].clone());
+                    Ok(None)
+                }
+            },
+            None,
+            ha_core::SupportsResponse::None,
+        );
+        let conn = std::sync::Arc::new(ActiveConnection::new(state, None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let msg = serde_json::json!({
+            "id": 1,
+            "type": "call_service",
+            "domain": "test",
+            "service": "record",
+            "target": {"label_id": ["critical"]},
+        });
+        dispatch::handle_message(&conn, &msg.to_string(), &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => assert!(result.success),
+            _ => panic!("Expected Result message"),
+        }
+
+        assert_eq!(
+            *received.lock().unwrap(),
+            [serde_json::json!(["light.porch", "lock.front_door"])]
+        );
+    }
+
+    // ==================== options flow ====================
+
+    /// Options flow that shows a single form and stores the submitted input
+    struct MockOptionsFlow;
+
+    #[async_trait::async_trait]
+    impl crate::config_flow::OptionsFlowHandler for MockOptionsFlow {
+        async fn start_flow(
+            &self,
+            entry: &ha_config_entries::ConfigEntry,
+            _show_advanced_options: bool,
+        ) -> Result<crate::config_flow::FlowResult, String> {
+            Ok(mock_flow_result(&entry.entry_id, "form", None))
+        }
+
+        async fn progress_flow(
+            &self,
+            flow_id: &str,
+            user_input: Option<serde_json::Value>,
+        ) -> Result<crate::config_flow::FlowResult, String> {
+            let entry_id = flow_id.trim_start_matches("options_");
+            Ok(mock_flow_result(entry_id, "create_entry", user_input))
+        }
+    }
+
+    fn mock_flow_result(
+        entry_id: &str,
+        result_type: &str,
+        result: Option<serde_json::Value>,
+    ) -> crate::config_flow::FlowResult {
+        crate::config_flow::FlowResult {
+            flow_id: format!("options_{}", entry_id),
+            handler: entry_id.to_string(),
+            result_type: result_type.to_string(),
+            step_id: Some("init".to_string()),
+            data_schema: Vec::new(),
+            errors: None,
+            description_placeholders: None,
+            title: None,
+            reason: None,
+            version: None,
+            minor_version: None,
+            result,
+            last_step: None,
+            preview: None,
+            unique_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_options_flow_updates_entry_options() {
+        let mut state = crate::tests::create_test_state();
+        state.options_flow_handler = Some(std::sync::Arc::new(MockOptionsFlow));
+        let entry = ha_config_entries::ConfigEntry::new("demo", "Demo");
+        let entry_id = entry.entry_id.clone();
+        let setups = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        {
+            let manager = state.config_entries.read().await;
+            manager
+                .set_context(ha_config_entries::SetupContext {
+                    bus: state.event_bus.clone(),
+                    states: state.state_machine.clone(),
+                    services: state.service_registry.clone(),
+                    registries: state.registries.clone(),
+                })
+                .await;
+            let counter = setups.clone();
+            manager.register_setup_handler(
+                "demo",
+                std::sync::Arc::new(move |entry, _| {
+                    // The entry is set up with the options the flow stored
+                    counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    assert!(entry.options.is_empty() || entry.options["scan_interval"] == 30);
+                    ha_config_entries::SetupResult::Success
+                }),
+            );
+            manager.add(entry).await.unwrap();
+            manager.setup(&entry_id).await.unwrap();
+        }
+        assert_eq!(setups.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let conn = std::sync::Arc::new(ActiveConnection::new(state, None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let start = serde_json::json!({
+            "id": 1,
+            "type": "config_entries/options/flow",
+            "handler": entry_id,
+        });
+        dispatch::handle_message(&conn, &start.to_string(), &tx)
+            .await
+            .unwrap();
+        let flow_id = match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => {
+                assert!(result.success);
+                let result = result.result.unwrap();
+                assert_eq!(result["type"], "form");
+                result["flow_id"].as_str().unwrap().to_string()
+            }
+            _ => panic!("Expected Result message"),
+        };
+
+        let progress = serde_json::json!({
+            "id": 2,
+            "type": "config_entries/options/flow/progress",
+            "flow_id": flow_id,
+            "user_input": {"scan_interval": 30},
+        });
+        dispatch::handle_message(&conn, &progress.to_string(), &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => {
+                assert!(result.success);
+                assert_eq!(result.result.unwrap()["type"], "create_entry");
+            }
+            _ => panic!("Expected Result message"),
+        }
+
+        let entry = conn
+            .state
+            .config_entries
+            .read()
+            .await
+            .get(&entry_id)
+            .unwrap();
+        assert_eq!(
+            entry.options.get("scan_interval"),
+            Some(&serde_json::json!(30))
+        );
+        assert_eq!(entry.state, ha_config_entries::ConfigEntryState::Loaded);
+        assert_eq!(setups.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_options_flow_reports_failed_reload() {
+        let mut state = crate::tests::create_test_state();
+        state.options_flow_handler = Some(std::sync::Arc::new(MockOptionsFlow));
+        let entry = ha_config_entries::ConfigEntry::new("demo", "Demo");
+        let entry_id = entry.entry_id.clone();
+        state.config_entries.read().await.add(entry).await.unwrap();
+
+        // No setup context, so the entry can't be reloaded
+        let conn = std::sync::Arc::new(ActiveConnection::new(state, None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let progress = serde_json::json!({
+            "id": 1,
+            "type": "config_entries/options/flow/progress",
+            "flow_id": format!("options_{}", entry_id),
+            "user_input": {"scan_interval": 30},
+        });
+        dispatch::handle_message(&conn, &progress.to_string(), &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => {
+                assert!(!result.success);
+                assert_eq!(result.error.unwrap().code, "flow_error");
+            }
+            _ => panic!("Expected Result message"),
+        }
+    }
+
+    /// Config flow that immediately creates an entry with a fixed unique ID
+    struct MockConfigFlow;
+
+    #[async_trait::async_trait]
+    impl crate::config_flow::ConfigFlowHandler for MockConfigFlow {
+        async fn start_flow(
+            &self,
+            handler: &str,
+            _show_advanced_options: bool,
+        ) -> Result<crate::config_flow::FlowResult, String> {
+            Ok(mock_flow_result(handler, "form", None))
+        }
+
+        async fn progress_flow(
+            &self,
+            flow_id: &str,
+            user_input: Option<serde_json::Value>,
+        ) -> Result<crate::config_flow::FlowResult, String> {
+            let handler = flow_id.trim_start_matches("flow_");
+            Ok(crate::config_flow::FlowResult {
+                flow_id: flow_id.to_string(),
+                title: Some("Bridge".to_string()),
+                unique_id: Some("bridge-001".to_string()),
+                ..mock_flow_result(handler, "create_entry", user_input)
+            })
+        }
+
+        async fn list_flows(&self) -> Vec<serde_json::Value> {
+            Vec::new()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_config_flow_aborts_when_already_configured() {
+        let mut state = crate::tests::create_test_state();
+        state.config_flow_handler = Some(std::sync::Arc::new(MockConfigFlow));
+        let existing =
+            ha_config_entries::ConfigEntry::new("hue", "Hue").with_unique_id("bridge-001");
+        state
+            .config_entries
+            .read()
+            .await
+            .add(existing)
+            .await
+            .unwrap();
+
+        let conn = std::sync::Arc::new(ActiveConnection::new(state, None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let progress = serde_json::json!({
+            "id": 1,
+            "type": "config_entries/flow/progress",
+            "flow_id": "flow_hue",
+            "user_input": {"host": "10.0.0.2"},
+        });
+        dispatch::handle_message(&conn, &progress.to_string(), &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => {
+                let result = result.result.unwrap();
+                assert_eq!(result["type"], "abort");
+                assert_eq!(result["reason"], "already_configured");
+            }
+            _ => panic!("Expected Result message"),
+        }
+
+        assert_eq!(
+            conn.state
+                .config_entries
+                .read()
+                .await
+                .get_by_domain("hue")
+                .len(),
+            1
+        );
+    }
+
+    /// Reauth flow that succeeds on the first submission
+    struct MockReauthFlow;
+
+    #[async_trait::async_trait]
+    impl crate::config_flow::ConfigFlowHandler for MockReauthFlow {
+        async fn start_flow(
+            &self,
+            handler: &str,
+            _show_advanced_options: bool,
+        ) -> Result<crate::config_flow::FlowResult, String> {
+            Ok(mock_flow_result(handler, "form", None))
+        }
+
+        async fn progress_flow(
+            &self,
+            flow_id: &str,
+            _user_input: Option<serde_json::Value>,
+        ) -> Result<crate::config_flow::FlowResult, String> {
+            Ok(crate::config_flow::FlowResult {
+                flow_id: flow_id.to_string(),
+                reason: Some("reauth_successful".to_string()),
+                ..mock_flow_result("hue", "abort", None)
+            })
+        }
+
+        async fn list_flows(&self) -> Vec<serde_json::Value> {
+            vec![
+                serde_json::json!({"flow_id": "flow_user", "context": {"source": "user"}}),
+                serde_json::json!({"flow_id": "reauth_hue", "context": {"source": "reauth"}}),
+            ]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_finished_reauth_flow_clears_attention() {
+        let mut state = crate::tests::create_test_state();
+        state.config_flow_handler = Some(std::sync::Arc::new(MockReauthFlow));
+        let entry = ha_config_entries::ConfigEntry::new("hue", "Hue");
+        let entry_id = entry.entry_id.clone();
+        {
+            let manager = state.config_entries.read().await;
+            manager.add(entry).await.unwrap();
+            manager.set_reauth_flow_handler(std::sync::Arc::new(
+                |_: &ha_config_entries::ConfigEntry| Ok("reauth_hue".to_string()),
+            ));
+            manager.async_start_reauth(&entry_id).unwrap();
+        }
+
+        let conn = std::sync::Arc::new(ActiveConnection::new(state, None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let list = serde_json::json!({"id": 1, "type": "config_entries/flow/progress"});
+        dispatch::handle_message(&conn, &list.to_string(), &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => {
+                let flows = result.result.unwrap();
+                assert_eq!(flows.as_array().unwrap().len(), 1);
+                assert_eq!(flows[0]["flow_id"], "reauth_hue");
+            }
+            _ => panic!("Expected Result message"),
+        }
+
+        let progress = serde_json::json!({
+            "id": 2,
+            "type": "config_entries/flow/progress",
+            "flow_id": "reauth_hue",
+            "user_input": {"api_key": "new"},
+        });
+        dispatch::handle_message(&conn, &progress.to_string(), &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => {
+                assert_eq!(result.result.unwrap()["reason"], "reauth_successful");
+            }
+            _ => panic!("Expected Result message"),
+        }
+
+        assert!(!conn
+            .state
+            .config_entries
+            .read()
+            .await
+            .needs_attention(&entry_id));
+    }
+
+    // ==================== subentries ====================
+
+    #[tokio::test]
+    async fn test_subentries_create_list_delete() {
+        let state = crate::tests::create_test_state();
+        let entry = ha_config_entries::ConfigEntry::new("met", "Weather");
+        let entry_id = entry.entry_id.clone();
+        state.config_entries.read().await.add(entry).await.unwrap();
+
+        let conn = std::sync::Arc::new(ActiveConnection::new(state, None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let msg = serde_json::json!({
+            "id": 1,
+            "type": "config_entries/subentries/create",
+            "entry_id": entry_id,
+            "subentry_type": "location",
+            "title": "Home",
+            "data": {"latitude": 52.3},
+        });
+        dispatch::handle_message(&conn, &msg.to_string(), &tx)
+            .await
+            .unwrap();
+        let subentry_id = match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => {
+                assert!(result.success);
+                let result = result.result.unwrap();
+                assert_eq!(result["title"], "Home");
+                result["subentry_id"].as_str().unwrap().to_string()
+            }
+            _ => panic!("Expected Result message"),
+        };
+
+        let msg = serde_json::json!({
+            "id": 2,
+            "type": "config_entries/subentries/list",
+            "entry_id": entry_id,
+        });
+        dispatch::handle_message(&conn, &msg.to_string(), &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => {
+                let result = result.result.unwrap();
+                assert_eq!(result.as_array().unwrap().len(), 1);
+                assert_eq!(result[0]["subentry_id"], subentry_id.as_str());
+                assert_eq!(result[0]["subentry_type"], "location");
+            }
+            _ => panic!("Expected Result message"),
+        }
+
+        let msg = serde_json::json!({
+            "id": 3,
+            "type": "config_entries/subentries/delete",
+            "entry_id": entry_id,
+            "subentry_id": subentry_id,
+        });
+        dispatch::handle_message(&conn, &msg.to_string(), &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => assert!(result.success),
+            _ => panic!("Expected Result message"),
+        }
+        assert!(conn
+            .state
+            .config_entries
+            .read()
+            .await
+            .subentries(&entry_id)
+            .unwrap()
+            .is_empty());
+    }
+
+    // ==================== entity rename ====================
+
+    #[tokio::test]
+    async fn test_entity_registry_rename_moves_state() {
+        let state = crate::tests::create_test_state();
+        crate::entity_rename::attach(&state.event_bus, state.state_machine.clone());
+        state
+            .registries
+            .entities
+            .get_or_create("demo", "light.rename_old", None, None, None);
+        state
+            .registries
+            .entities
+            .get_or_create("demo", "light.rename_taken", None, None, None);
+        set_state(&state, "light.rename_old", "on");
+        let conn = std::sync::Arc::new(ActiveConnection::new(state.clone(), None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+
+        let update = |id: u64, new_entity_id: &str| {
+            serde_json::json!({
+                "id": id,
+                "type": "config/entity_registry/update",
+                "entity_id": "light.rename_old",
+                "new_entity_id": new_entity_id,
+            })
+            .to_string()
+        };
+
+        // A taken entity_id is rejected and nothing moves
+        dispatch::handle_message(&conn, &update(1, "light.rename_taken"), &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => {
+                assert!(!result.success);
+                assert_eq!(result.error.unwrap().code, "invalid_info");
+            }
+            _ => panic!("Expected Result message"),
+        }
+        assert!(state.state_machine.get("light.rename_old").is_some());
+
+        dispatch::handle_message(&conn, &update(2, "light.rename_new"), &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => {
+                assert!(result.success);
+                assert_eq!(
+                    result.result.unwrap()["entity_entry"]["entity_id"],
+                    "light.rename_new"
+                );
+            }
+            _ => panic!("Expected Result message"),
+        }
+        assert!(state.state_machine.get("light.rename_old").is_none());
+        assert_eq!(
+            state.state_machine.get_state("light.rename_new").as_deref(),
+            Some("on")
+        );
+        assert!(state.registries.entities.get("light.rename_old").is_none());
+        assert!(state.registries.entities.get("light.rename_new").is_some());
+    }
+
+    // ==================== fire_event ====================
+
+    #[tokio::test]
+    async fn test_fire_event_carries_user_context() {
+        let state = crate::tests::create_test_state();
+        let conn = std::sync::Arc::new(ActiveConnection::new(
+            state.clone(),
+            Some("ws-user".to_string()),
+        ));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        state.event_bus.listen_sync(
+            "ws_fired",
+            std::sync::Arc::new(move |event: &ha_core::Event<serde_json::Value>| {
+                seen_clone
+                    .lock()
+                    .unwrap()
+                    .push((event.data.clone(), event.context.user_id.clone()));
+            }),
+        );
+
+        dispatch::handle_message(
+            &conn,
+            r#"{"id": 1, "type": "fire_event", "event_type": "ws_fired", "event_data": {"a": 1}}"#,
+            &tx,
+        )
+        .await
+        .unwrap();
+
+        let result = match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => result,
+            _ => panic!("Expected Result message"),
+        };
+        assert!(result.success);
+        assert_eq!(result.result.unwrap()["context"]["user_id"], "ws-user");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(serde_json::json!({"a": 1}), Some("ws-user".to_string()))]
+        );
+    }
+
+    // ==================== unsubscribe ====================
+
+    /// Wait for the bus to drop receivers of aborted subscription tasks
+    async fn wait_for_subscribers(state: &crate::AppState, expected: usize) {
+        for _ in 0..100 {
+            if state.event_bus.subscribe_all_count() == expected {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(state.event_bus.subscribe_all_count(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_releases_bus_receiver() {
+        let state = crate::tests::create_test_state();
+        let conn = std::sync::Arc::new(ActiveConnection::new(state.clone(), None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let baseline = state.event_bus.subscribe_all_count();
+
+        handlers::handle_subscribe_events(&conn, 1, None, &tx)
+            .await
+            .unwrap();
+        handlers::handle_subscribe_events(&conn, 2, Some("test_event".to_string()), &tx)
+            .await
+            .unwrap();
+        assert_eq!(conn.subscription_count().await, 2);
+        assert_eq!(state.event_bus.subscribe_all_count(), baseline + 2);
+
+        handlers::handle_unsubscribe_events(&conn, 3, 1, &tx)
+            .await
+            .unwrap();
+        assert_eq!(conn.subscription_count().await, 1);
+        wait_for_subscribers(&state, baseline + 1).await;
+
+        // Disconnecting releases the remaining subscriptions
+        conn.remove_all_subscriptions().await;
+        assert_eq!(conn.subscription_count().await, 0);
+        wait_for_subscribers(&state, baseline).await;
+
+        while let Ok(msg) = rx.try_recv() {
+            assert!(matches!(msg, OutgoingMessage::Result(_)));
+        }
+    }
+
+    // ==================== persistent_notification/subscribe ====================
+
+    #[tokio::test]
+    async fn test_notification_subscription_pushes_updates() {
+        use crate::persistent_notification::{fire_updated, UpdateType};
+
+        let state = crate::tests::create_test_state();
+        let conn = std::sync::Arc::new(ActiveConnection::new(state.clone(), None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+
+        handlers::handle_persistent_notification_subscribe(&conn, 1, &tx)
+            .await
+            .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => assert!(result.success),
+            _ => panic!("Expected Result message"),
+        }
+        let current = next_event(&mut rx).await;
+        assert_eq!(current["type"], "current");
+        assert_eq!(current["notifications"], serde_json::json!({}));
+
+        let create = |id: &str| {
+            let (notification, update_type) =
+                state
+                    .notifications
+                    .create(id.to_string(), "Door left open".to_string(), None);
+            fire_updated(
+                &state.event_bus,
+                update_type,
+                &[notification],
+                ha_core::Context::new(),
+            );
+        };
+        create("door");
+        let added = next_event(&mut rx).await;
+        assert_eq!(added["type"], "added");
+        assert_eq!(added["notifications"]["door"]["message"], "Door left open");
+
+        let dismissed = state.notifications.dismiss("door").unwrap();
+        fire_updated(
+            &state.event_bus,
+            UpdateType::Removed,
+            &[dismissed],
+            ha_core::Context::new(),
+        );
+        let removed = next_event(&mut rx).await;
+        assert_eq!(removed["type"], "removed");
+        assert!(removed["notifications"].get("door").is_some());
+
+        // Unsubscribing stops the stream
+        handlers::handle_unsubscribe_events(&conn, 2, 1, &tx)
+            .await
+            .unwrap();
+        assert!(matches!(rx.recv().await, Some(OutgoingMessage::Result(_))));
+        assert_eq!(conn.subscription_count().await, 0);
+        create("window");
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    // ==================== auth ====================
+
+    #[tokio::test]
+    async fn test_auth_rejects_expired_token() {
+        use crate::auth::LONG_LIVED_TOKEN_LIFESPAN;
+        use connection::{authenticate, AuthResult};
+
+        let state = crate::tests::create_test_state();
+        let auth = |token: &str| AuthResult {
+            access_token: Some(token.to_string()),
+            api_password: None,
+        };
+
+        let expired = state
+            .auth_state
+            .create_long_lived_access_token("ws-user", "expired", None, std::time::Duration::ZERO)
+            .await;
+        assert_eq!(
+            authenticate(&state, &auth(&expired)).await,
+            Err("Access token expired".to_string())
+        );
+
+        let valid = state
+            .auth_state
+            .create_long_lived_access_token("ws-user", "valid", None, LONG_LIVED_TOKEN_LIFESPAN)
+            .await;
+        assert_eq!(
+            authenticate(&state, &auth(&valid)).await,
+            Ok("ws-user".to_string())
+        );
+
+        // Tokens the auth API didn't issue are rejected too
+        assert_eq!(
+            authenticate(&state, &auth("garbage")).await,
+            Err("Invalid access token".to_string())
+        );
+        let unknown = "unknown:99999999999:0123456789abcdef";
+        assert!(authenticate(&state, &auth(unknown)).await.is_err());
+
+        let missing = AuthResult {
+            access_token: None,
+            api_password: None,
+        };
+        assert!(authenticate(&state, &missing).await.is_err());
+        let password = AuthResult {
+            access_token: None,
+            api_password: Some("anything".to_string()),
+        };
+        assert!(authenticate(&state, &password).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_long_lived_access_token_create_use_revoke() {
+        use connection::{authenticate, AuthResult};
+
+        let state = crate::tests::create_test_state();
+        let conn = std::sync::Arc::new(ActiveConnection::new(
+            state.clone(),
+            Some("ws-user".to_string()),
+        ));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let mut result = || match rx.try_recv() {
+            Ok(OutgoingMessage::Result(result)) => result,
+            _ => panic!("Expected Result message"),
+        };
+
+        handlers::handle_auth_long_lived_access_token(&conn, 1, "CLI", None, Some(30), &tx)
+            .await
+            .unwrap();
+        let created = result();
+        assert!(created.success);
+        let token = created.result.unwrap().as_str().unwrap().to_string();
+        let auth = AuthResult {
+            access_token: Some(token),
+            api_password: None,
+        };
+        assert_eq!(authenticate(&state, &auth).await, Ok("ws-user".to_string()));
+
+        handlers::handle_auth_refresh_tokens(&conn, 2, &tx)
+            .await
+            .unwrap();
+        let listed = result().result.unwrap();
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+        assert_eq!(listed[0]["client_name"], "CLI");
+        assert_eq!(listed[0]["type"], "long_lived_access_token");
+        assert!(listed[0].get("token").is_none());
+
+        let token_id = listed[0]["id"].as_str().unwrap();
+        handlers::handle_auth_delete_refresh_token(&conn, 3, token_id, &tx)
+            .await
+            .unwrap();
+        assert!(result().success);
+        assert!(authenticate(&state, &auth).await.is_err());
+
+        handlers::handle_auth_delete_refresh_token(&conn, 4, token_id, &tx)
+            .await
+            .unwrap();
+        assert_eq!(result().error.unwrap().code, "invalid_token_id");
+    }
+
+    // ==================== permissions ====================
+
+    #[tokio::test]
+    async fn test_read_only_user_reads_states_but_cannot_call_services() {
+        use crate::permissions::{service_interceptor, UserPermissions};
+
+        let state = crate::tests::create_test_state();
+        state.service_registry.add_interceptor(service_interceptor(
+            state.auth_state.clone(),
+            state.registries.clone(),
+        ));
+        state
+            .auth_state
+            .set_user_permissions("reader", UserPermissions::read_only());
+        state.service_registry.register(
+            "light",
+            "turn_on",
+            |_| async { Ok(None) },
+            None,
+            ha_core::SupportsResponse::None,
+        );
+        set_state(&state, "light.reader_lamp", "off");
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let send = |user: &str, msg: serde_json::Value| {
+            let conn =
+                std::sync::Arc::new(ActiveConnection::new(state.clone(), Some(user.to_string())));
+            let tx = tx.clone();
+            async move {
+                dispatch::handle_message(&conn, &msg.to_string(), &tx)
+                    .await
+                    .unwrap();
+            }
+        };
+        let mut result = || match rx.try_recv() {
+            Ok(OutgoingMessage::Result(result)) => result,
+            _ => panic!("Expected Result message"),
+        };
+
+        send("reader", serde_json::json!({"id": 1, "type": "get_states"})).await;
+        let states = result();
+        assert!(states.success);
+        assert_eq!(states.result.unwrap()[0]["entity_id"], "light.reader_lamp");
+
+        let turn_on = serde_json::json!({
+            "id": 2,
+            "type": "call_service",
+            "domain": "light",
+            "service": "turn_on",
+            "target": {"entity_id": "light.reader_lamp"},
+        });
+        send("reader", turn_on.clone()).await;
+        let denied = result();
+        assert!(!denied.success);
+        assert_eq!(denied.error.unwrap().code, "unauthorized");
+
+        // Users without permissions set are admins
+        send("owner", turn_on).await;
+        assert!(result().success);
+    }
+
+    // ==================== subscribe_entities ====================
+
+    #[tokio::test]
+    async fn test_subscribe_entities_sends_compressed_snapshot_and_diffs() {
+        use ha_core::{Context, EntityId};
+        use serde_json::json;
+        use std::collections::HashMap;
+
+        let state = crate::tests::create_test_state();
+        let temp = EntityId::new("sensor", "compact_temp").unwrap();
+        let attrs = |pairs: &[(&str, serde_json::Value)]| -> HashMap<String, serde_json::Value> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect()
+        };
+        state.state_machine.set(
+            temp.clone(),
+            "20",
+            attrs(&[("unit_of_measurement", json!("°C")), ("battery", json!(90))]),
+            Context::new(),
+        );
+        set_state(&state, "sensor.compact_other", "1");
+
+        let conn = std::sync::Arc::new(ActiveConnection::new(state.clone(), None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        handlers::handle_subscribe_entities(
+            &conn,
+            1,
+            Some(vec!["sensor.compact_temp".to_string()]),
+            &tx,
+        )
+        .await
+        .unwrap();
+        match rx.recv().await {
+            Some(OutgoingMessage::Result(result)) => assert!(result.success),
+            _ => panic!("Expected Result message"),
+        }
+
+        let snapshot = next_event(&mut rx).await;
+        let additions = snapshot["a"].as_object().unwrap();
+        assert_eq!(additions.len(), 1);
+        let added = &additions["sensor.compact_temp"];
+        assert_eq!(added["s"], "20");
+        assert_eq!(added["a"]["unit_of_measurement"], "°C");
+        assert!(added["c"].is_string());
+        assert!(added["lc"].is_f64());
+        assert!(added.get("lu").is_none());
+
+        // Other entities are filtered out, changes only carry what changed
+        set_state(&state, "sensor.compact_other", "2");
+        state.state_machine.set(
+            temp.clone(),
+            "21",
+            attrs(&[("unit_of_measurement", json!("°C"))]),
+            Context::new(),
+        );
+        let change = next_event(&mut rx).await;
+        let diff = &change["c"]["sensor.compact_temp"];
+        assert_eq!(diff["+"]["s"], "21");
+        assert!(diff["+"]["lc"].is_f64());
+        assert!(diff["+"].get("a").is_none());
+        assert_eq!(diff["-"], json!({"a": ["battery"]}));
+
+        state.state_machine.remove(&temp, Context::new());
+        let removal = next_event(&mut rx).await;
+        assert_eq!(removal, json!({"r": ["sensor.compact_temp"]}));
+    }
+
+    // ==================== backpressure ====================
+
+    #[tokio::test]
+    async fn test_slow_consumer_overflows_instead_of_buffering() {
+        let state = crate::tests::create_test_state();
+        let conn = std::sync::Arc::new(ActiveConnection::new(state.clone(), None));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+
+        handlers::handle_subscribe_events(&conn, 1, Some("slow_test".to_string()), &tx)
+            .await
+            .unwrap();
+
+        // The client never reads, so the queue fills up
+        for _ in 0..20 {
+            state.event_bus.fire(ha_core::Event::new(
+                "slow_test",
+                serde_json::json!({}),
+                ha_core::Context::new(),
+            ));
+        }
+        tokio::time::timeout(std::time::Duration::from_secs(2), conn.wait_overflowed())
+            .await
+            .expect("connection should overflow");
+
+        let stats = conn.metrics.snapshot();
+        assert_eq!(stats.messages_queued, 3);
+        assert!(stats.messages_dropped >= 1);
+
+        // The subscription stopped, so draining does not let it refill the queue
+        let mut received = 0;
+        while rx.try_recv().is_ok() {
+            received += 1;
+        }
+        assert_eq!(received, 4);
+        state.event_bus.fire(ha_core::Event::new(
+            "slow_test",
+            serde_json::json!({}),
+            ha_core::Context::new(),
+        ));
+        let pending = tokio::time::timeout(std::time::Duration::from_millis(100), rx.recv()).await;
+        assert!(pending.is_err(), "no event expected after overflow");
+        wait_unsubscribed(&conn).await;
+    }
+
+    #[tokio::test]
+    async fn test_websocket_connections_lists_live_metrics() {
+        use crate::permissions::UserPermissions;
+        use std::sync::atomic::Ordering;
+
+        let state = crate::tests::create_test_state();
+        state
+            .auth_state
+            .set_user_permissions("reader", UserPermissions::read_only());
+        let owner = std::sync::Arc::new(ActiveConnection::new(
+            state.clone(),
+            Some("owner".to_string()),
+        ));
+        let reader = std::sync::Arc::new(ActiveConnection::new(
+            state.clone(),
+            Some("reader".to_string()),
+        ));
+        reader
+            .metrics
+            .messages_queued
+            .fetch_add(3, Ordering::Relaxed);
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let msg = serde_json::json!({"id": 1, "type": "websocket/connections"}).to_string();
+        let mut result = || match rx.try_recv() {
+            Ok(OutgoingMessage::Result(result)) => result,
+            _ => panic!("Expected Result message"),
+        };
+
+        dispatch::handle_message(&owner, &msg, &tx).await.unwrap();
+        let listed = result();
+        assert!(listed.success);
+        let connections = listed.result.unwrap();
+        assert_eq!(connections.as_array().unwrap().len(), 2);
+        assert_eq!(connections[0]["user_id"], "owner");
+        assert_eq!(connections[0]["commands_received"], 0);
+        assert_eq!(connections[1]["user_id"], "reader");
+        assert_eq!(connections[1]["messages_queued"], 3);
+
+        dispatch::handle_message(&reader, &msg, &tx).await.unwrap();
+        let denied = result();
+        assert!(!denied.success);
+        assert_eq!(denied.error.unwrap().code, "unauthorized");
+
+        // Closed connections are no longer listed
+        drop(reader);
+        let connections = state.websocket_connections.list();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].user_id.as_deref(), Some("owner"));
+    }
 }
@@ -23,6 +23,25 @@ pub enum IncomingMessage {
     AuthCurrentUser {
         id: u64,
     },
+    #[serde(rename = "auth/delete_refresh_token")]
+    AuthDeleteRefreshToken {
+        id: u64,
+        refresh_token_id: String,
+    },
+    #[serde(rename = "auth/long_lived_access_token")]
+    AuthLongLivedAccessToken {
+        id: u64,
+        client_name: String,
+        #[serde(default)]
+        client_icon: Option<String>,
+        /// Lifespan in days (default: 3650)
+        #[serde(default)]
+        lifespan: Option<u64>,
+    },
+    #[serde(rename = "auth/refresh_tokens")]
+    AuthRefreshTokens {
+        id: u64,
+    },
     #[serde(rename = "automation/config")]
     AutomationConfig {
         id: u64,
@@ -166,6 +185,26 @@ pub enum IncomingMessage {
     RecorderInfo {
         id: u64,
     },
+    #[serde(rename = "recorder/statistics_during_period")]
+    RecorderStatisticsDuringPeriod {
+        id: u64,
+        start_time: String,
+        #[serde(default)]
+        end_time: Option<String>,
+        #[serde(default)]
+        statistic_ids: Option<Vec<String>>,
+        #[serde(default)]
+        period: Option<String>,
+    },
+    #[serde(rename = "logbook/get_events")]
+    LogbookGetEvents {
+        id: u64,
+        start_time: String,
+        #[serde(default)]
+        end_time: Option<String>,
+        #[serde(default)]
+        entity_ids: Option<Vec<String>>,
+    },
     #[serde(rename = "repairs/list_issues")]
     RepairsListIssues {
         id: u64,
@@ -191,6 +230,23 @@ pub enum IncomingMessage {
         id: u64,
         entry_id: String,
     },
+    #[serde(rename = "config_entries/subentries/create")]
+    ConfigEntriesSubentriesCreate {
+        id: u64,
+        entry_id: String,
+        subentry_type: String,
+        title: String,
+        #[serde(default)]
+        unique_id: Option<String>,
+        #[serde(default)]
+        data: HashMap<String, serde_json::Value>,
+    },
+    #[serde(rename = "config_entries/subentries/delete")]
+    ConfigEntriesSubentriesDelete {
+        id: u64,
+        entry_id: String,
+        subentry_id: String,
+    },
     #[serde(rename = "config_entries/subscribe")]
     ConfigEntriesSubscribe {
         id: u64,
@@ -216,6 +272,24 @@ pub enum IncomingMessage {
         #[serde(default)]
         user_input: Option<serde_json::Value>,
     },
+    #[serde(rename = "config_entries/options/flow")]
+    ConfigEntriesOptionsFlow {
+        id: u64,
+        /// Config entry ID to start the options flow for
+        handler: String,
+        /// Show advanced options (optional)
+        #[serde(default)]
+        show_advanced_options: bool,
+    },
+    #[serde(rename = "config_entries/options/flow/progress")]
+    ConfigEntriesOptionsFlowProgress {
+        id: u64,
+        /// The options flow_id to continue
+        flow_id: String,
+        /// User input for this step (optional)
+        #[serde(default)]
+        user_input: Option<serde_json::Value>,
+    },
     #[serde(rename = "config_entries/flow/subscribe")]
     ConfigEntriesFlowSubscribe {
         id: u64,
@@ -315,6 +389,10 @@ pub enum IncomingMessage {
     SystemLogList {
         id: u64,
     },
+    #[serde(rename = "websocket/connections")]
+    WebsocketConnections {
+        id: u64,
+    },
     SubscribeEntities {
         id: u64,
         #[serde(default)]
@@ -325,11 +403,26 @@ pub enum IncomingMessage {
         #[serde(default)]
         event_type: Option<String>,
     },
+    SubscribeTrigger {
+        id: u64,
+        trigger: serde_json::Value,
+        #[serde(default)]
+        variables: Option<HashMap<String, serde_json::Value>>,
+    },
     SupportedFeatures {
         id: u64,
         #[allow(dead_code)] // Deserialized but not currently used
         features: HashMap<String, serde_json::Value>,
     },
+    ValidateConfig {
+        id: u64,
+        #[serde(default)]
+        trigger: Option<serde_json::Value>,
+        #[serde(default)]
+        condition: Option<serde_json::Value>,
+        #[serde(default)]
+        action: Option<serde_json::Value>,
+    },
     UnsubscribeEvents {
         id: u64,
         subscription: u64,
@@ -349,6 +442,10 @@ pub struct ServiceTarget {
     pub device_id: Option<Vec<String>>,
     #[serde(default)]
     pub area_id: Option<Vec<String>>,
+    #[serde(default)]
+    pub floor_id: Option<Vec<String>>,
+    #[serde(default)]
+    pub label_id: Option<Vec<String>>,
 }
 
 /// Entity IDs can be a single string or array
@@ -5,9 +5,11 @@
 
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use ha_core::{Context, EntityId};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use thiserror::Error;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use crate::condition::Condition;
 use crate::trigger::Trigger;
@@ -160,6 +162,9 @@ pub struct Automation {
     /// Whether enabled
     pub enabled: bool,
 
+    /// Whether the configuration enables it, `enabled` changes at runtime
+    configured_enabled: bool,
+
     /// Variables
     pub variables: serde_json::Value,
 
@@ -171,11 +176,22 @@ pub struct Automation {
 
     /// Trace configuration
     pub trace_config: TraceConfig,
+
+    /// Entity ID of the `automation.*` entity, unique among loaded automations
+    entity_id: EntityId,
 }
 
 impl Automation {
     /// Create from config
     pub fn from_config(config: AutomationConfig) -> Self {
+        // Like HA, automations without an id are named after their alias
+        let entity_id = entity_id_for(
+            config
+                .id
+                .as_deref()
+                .or(config.alias.as_deref())
+                .unwrap_or_default(),
+        );
         let id = config.id.unwrap_or_else(|| ulid::Ulid::new().to_string());
 
         Self {
@@ -187,12 +203,14 @@ impl Automation {
             actions: config.actions,
             mode: config.mode,
             enabled: config.enabled,
+            configured_enabled: config.enabled,
             variables: config.variables,
             last_triggered: None,
             current_runs: 0,
             trace_config: config.trace.unwrap_or(TraceConfig {
                 stored_traces: default_stored_traces(),
             }),
+            entity_id,
         }
     }
 
@@ -201,6 +219,30 @@ impl Automation {
         self.alias.as_deref().unwrap_or(&self.id)
     }
 
+    /// Entity ID of the automation's `automation.*` entity
+    pub fn entity_id(&self) -> EntityId {
+        self.entity_id.clone()
+    }
+
+    /// Whether two automations were created from the same configuration
+    fn same_definition(&self, other: &Automation) -> bool {
+        let definition = |a: &Automation| {
+            serde_json::to_value((
+                &a.alias,
+                &a.description,
+                &a.triggers,
+                &a.conditions,
+                &a.actions,
+                &a.mode,
+                a.configured_enabled,
+                &a.variables,
+                a.trace_config.stored_traces,
+            ))
+            .ok()
+        };
+        definition(self) == definition(other)
+    }
+
     /// Check if can start new run based on mode
     pub fn can_run(&self) -> bool {
         if !self.enabled {
@@ -216,10 +258,37 @@ impl Automation {
     }
 }
 
+/// Entity ID for an automation with the given `id`
+///
+/// Derived from the id rather than the alias, so renaming an automation
+/// keeps its entity. The id is slugified; ids created by the frontend are
+/// numeric and stay that way (`automation.1699999999999`).
+pub fn entity_id_for(id: &str) -> EntityId {
+    let mut object_id = String::new();
+    for c in id.chars() {
+        if c.is_ascii_alphanumeric() {
+            object_id.push(c.to_ascii_lowercase());
+        } else if !object_id.is_empty() && !object_id.ends_with('_') {
+            object_id.push('_');
+        }
+    }
+    let object_id = match object_id.trim_end_matches('_') {
+        "" => "automation",
+        object_id => object_id,
+    };
+    EntityId::new("automation", object_id).expect("slugified object_id is valid")
+}
+
+/// Number of recent run contexts remembered per automation
+const RECENT_RUN_CONTEXTS: usize = 16;
+
 /// Manages all automations
 pub struct AutomationManager {
     /// All automations by ID
     automations: DashMap<String, Automation>,
+
+    /// IDs of the contexts of each automation's most recent runs
+    run_contexts: DashMap<String, VecDeque<String>>,
 }
 
 impl AutomationManager {
@@ -227,9 +296,41 @@ impl AutomationManager {
     pub fn new() -> Self {
         Self {
             automations: DashMap::new(),
+            run_contexts: DashMap::new(),
         }
     }
 
+    /// Create the context for a run of an automation
+    ///
+    /// The context is a child of the triggering context and is remembered,
+    /// so triggers caused by the run's own actions can be recognized with
+    /// [`is_self_triggered`](Self::is_self_triggered).
+    pub fn start_run_context(&self, id: &str, trigger_context: &Context) -> Context {
+        let context = Context::child_of(trigger_context);
+        let mut recent = self.run_contexts.entry(id.to_string()).or_default();
+        if recent.len() == RECENT_RUN_CONTEXTS {
+            recent.pop_front();
+        }
+        recent.push_back(context.id.clone());
+        context
+    }
+
+    /// Whether a trigger's context was produced by one of the automation's
+    /// own recent runs
+    ///
+    /// Actions run with the automation's context, so the events and state
+    /// changes they cause carry it or a descendant of it, also when they
+    /// happen in a script the automation called. Running again for those
+    /// would let an automation re-trigger itself forever.
+    pub fn is_self_triggered(&self, id: &str, trigger_context: &Context) -> bool {
+        let Some(recent) = self.run_contexts.get(id) else {
+            return false;
+        };
+        recent
+            .iter()
+            .any(|run_id| trigger_context.descends_from(run_id))
+    }
+
     /// Load automations from configs
     pub fn load(&self, configs: Vec<AutomationConfig>) -> AutomationResult<()> {
         for config in configs {
@@ -239,7 +340,7 @@ impl AutomationManager {
                 automation.display_name(),
                 automation.id
             );
-            self.automations.insert(automation.id.clone(), automation);
+            self.insert(automation);
         }
         Ok(())
     }
@@ -249,6 +350,46 @@ impl AutomationManager {
         self.automations.get(id).map(|a| a.value().clone())
     }
 
+    /// Add or replace an automation, keeping entity IDs unique
+    ///
+    /// Different ids can slugify to the same entity ID (`Morning Lights` and
+    /// `morning_lights`); later automations get `_2`, `_3`, ... appended like
+    /// HA does for entities.
+    fn insert(&self, mut automation: Automation) {
+        let taken = |entity_id: &EntityId| {
+            self.automations
+                .iter()
+                .any(|a| a.id != automation.id && a.entity_id == *entity_id)
+        };
+        if taken(&automation.entity_id) {
+            let base = automation.entity_id.clone();
+            let mut suffix = 2;
+            let unique = loop {
+                let candidate =
+                    EntityId::new("automation", format!("{}_{}", base.object_id(), suffix))
+                        .expect("suffixed object_id is valid");
+                if !taken(&candidate) {
+                    break candidate;
+                }
+                suffix += 1;
+            };
+            warn!(
+                "Automation {} has the same entity ID as another automation, using {}",
+                automation.id, unique
+            );
+            automation.entity_id = unique;
+        }
+        self.automations.insert(automation.id.clone(), automation);
+    }
+
+    /// Get the ID of the automation owning an `automation.*` entity
+    pub fn id_for_entity(&self, entity_id: &str) -> Option<String> {
+        self.automations
+            .iter()
+            .find(|a| a.entity_id().as_str() == entity_id)
+            .map(|a| a.id.clone())
+    }
+
     /// Get all automations
     pub fn all(&self) -> Vec<Automation> {
         self.automations.iter().map(|a| a.value().clone()).collect()
@@ -305,6 +446,7 @@ impl AutomationManager {
 
     /// Remove an automation
     pub fn remove(&self, id: &str) -> AutomationResult<Automation> {
+        self.run_contexts.remove(id);
         self.automations
             .remove(id)
             .map(|(_, a)| a)
@@ -328,7 +470,7 @@ impl AutomationManager {
             automation.display_name(),
             automation.id
         );
-        self.automations.insert(id.clone(), automation);
+        self.insert(automation);
         Ok(id)
     }
 
@@ -368,18 +510,64 @@ impl AutomationManager {
     }
 
     /// Reload automations from configs
-    pub fn reload(&self, configs: Vec<AutomationConfig>) -> AutomationResult<()> {
-        // Clear existing
-        self.automations.clear();
+    ///
+    /// Diffs against the loaded automations: removed ones are dropped, new
+    /// ones added, and changed ones replaced. Unchanged automations are left
+    /// alone, keeping runtime state such as being turned off. Changed ones
+    /// keep their `last_triggered` and recent run contexts.
+    pub fn reload(&self, configs: Vec<AutomationConfig>) -> AutomationResult<ReloadSummary> {
+        let mut summary = ReloadSummary::default();
+        let automations: Vec<Automation> =
+            configs.into_iter().map(Automation::from_config).collect();
+
+        let stale: Vec<String> = self
+            .automations
+            .iter()
+            .filter(|a| !automations.iter().any(|new| new.id == a.id))
+            .map(|a| a.id.clone())
+            .collect();
+        for id in stale {
+            if let Ok(automation) = self.remove(&id) {
+                summary.removed.push(automation);
+            }
+        }
 
-        // Load new
-        self.load(configs)?;
+        for mut automation in automations {
+            let id = automation.id.clone();
+            match self.automations.get(&id).map(|a| a.value().clone()) {
+                None => summary.added.push(id.clone()),
+                Some(current) if current.same_definition(&automation) => continue,
+                Some(current) => {
+                    automation.last_triggered = current.last_triggered;
+                    automation.entity_id = current.entity_id;
+                    summary.updated.push(id.clone());
+                }
+            }
+            self.insert(automation);
+        }
 
-        info!("Reloaded {} automations", self.automations.len());
-        Ok(())
+        info!(
+            added = summary.added.len(),
+            removed = summary.removed.len(),
+            updated = summary.updated.len(),
+            "Reloaded {} automations",
+            self.automations.len()
+        );
+        Ok(summary)
     }
 }
 
+/// What changed in an [`AutomationManager::reload`]
+#[derive(Debug, Default)]
+pub struct ReloadSummary {
+    /// IDs of automations that weren't loaded before
+    pub added: Vec<String>,
+    /// Automations no longer in the configuration
+    pub removed: Vec<Automation>,
+    /// IDs of automations whose configuration changed
+    pub updated: Vec<String>,
+}
+
 impl Default for AutomationManager {
     fn default() -> Self {
         Self::new()
@@ -512,6 +700,60 @@ mod tests {
         assert_eq!(automation.id.len(), 26);
     }
 
+    #[test]
+    fn test_entity_id_from_id() {
+        assert_eq!(
+            entity_id_for("test_automation").as_str(),
+            "automation.test_automation"
+        );
+        assert_eq!(
+            entity_id_for("Morning Lights!").as_str(),
+            "automation.morning_lights"
+        );
+        assert_eq!(
+            entity_id_for("1699999999999").as_str(),
+            "automation.1699999999999"
+        );
+
+        let manager = AutomationManager::new();
+        manager.load(vec![sample_config()]).unwrap();
+        assert_eq!(
+            manager
+                .id_for_entity("automation.test_automation")
+                .as_deref(),
+            Some("test_automation")
+        );
+        assert_eq!(manager.id_for_entity("automation.test_automation_2"), None);
+    }
+
+    #[test]
+    fn test_colliding_entity_ids_are_disambiguated() {
+        let mut spaced = sample_config();
+        spaced.id = Some("Test Automation".to_string());
+        let manager = AutomationManager::new();
+        manager.load(vec![sample_config(), spaced.clone()]).unwrap();
+
+        let entity_id = |id: &str| manager.get(id).unwrap().entity_id().to_string();
+        assert_eq!(entity_id("test_automation"), "automation.test_automation");
+        assert_eq!(entity_id("Test Automation"), "automation.test_automation_2");
+        assert_eq!(
+            manager
+                .id_for_entity("automation.test_automation_2")
+                .as_deref(),
+            Some("Test Automation")
+        );
+
+        let mut dashed = sample_config();
+        dashed.id = Some("test-automation".to_string());
+        manager.add(dashed).unwrap();
+        assert_eq!(entity_id("test-automation"), "automation.test_automation_3");
+
+        // Reloads keep the entity IDs already handed out
+        spaced.alias = Some("Renamed".to_string());
+        manager.reload(vec![sample_config(), spaced]).unwrap();
+        assert_eq!(entity_id("Test Automation"), "automation.test_automation_2");
+    }
+
     #[test]
     fn test_run_count_tracking() {
         let manager = AutomationManager::new();
@@ -526,4 +768,71 @@ mod tests {
         manager.decrement_runs("test_automation");
         assert_eq!(manager.get("test_automation").unwrap().current_runs, 1);
     }
+
+    #[test]
+    fn test_self_triggered_context() {
+        let manager = AutomationManager::new();
+        let external = Context::new();
+        let run = manager.start_run_context("test_automation", &external);
+        assert_eq!(run.parent_id.as_deref(), Some(external.id.as_str()));
+
+        // The run's own context and its children come from the automation
+        assert!(manager.is_self_triggered("test_automation", &run));
+        assert!(manager.is_self_triggered("test_automation", &run.child()));
+        assert!(!manager.is_self_triggered("test_automation", &external));
+        assert!(!manager.is_self_triggered("other_automation", &run));
+
+        // Only recent runs are remembered
+        for _ in 0..RECENT_RUN_CONTEXTS {
+            manager.start_run_context("test_automation", &external);
+        }
+        assert!(!manager.is_self_triggered("test_automation", &run));
+    }
+
+    #[test]
+    fn test_self_triggered_through_script() {
+        let manager = AutomationManager::new();
+        let run = manager.start_run_context("test_automation", &Context::new());
+
+        // The automation calls a script, which calls a service that changes
+        // a state: the change is two levels below the run's context
+        let script_run = Context::child_of(&run);
+        let service_call = Context::child_of(&script_run);
+        let state_change = Context::child_of(&service_call);
+        assert!(manager.is_self_triggered("test_automation", &state_change));
+
+        // A script started independently is not the automation's doing
+        let other_script = Context::child_of(&Context::new());
+        assert!(!manager.is_self_triggered("test_automation", &Context::child_of(&other_script)));
+    }
+
+    #[test]
+    fn test_reload_diffs_automations() {
+        let manager = AutomationManager::new();
+        let mut other = sample_config();
+        other.id = Some("other".to_string());
+        manager.load(vec![sample_config(), other]).unwrap();
+        manager.disable("test_automation").unwrap();
+
+        let mut changed = sample_config();
+        changed.id = Some("other".to_string());
+        changed.alias = Some("Renamed".to_string());
+        let mut added = sample_config();
+        added.id = Some("added".to_string());
+        let summary = manager
+            .reload(vec![sample_config(), changed, added])
+            .unwrap();
+        assert_eq!(summary.added, ["added"]);
+        assert_eq!(summary.updated, ["other"]);
+        assert!(summary.removed.is_empty());
+        assert_eq!(manager.get("other").unwrap().display_name(), "Renamed");
+        // Unchanged automations keep their runtime state
+        assert!(!manager.get("test_automation").unwrap().enabled);
+
+        let summary = manager.reload(vec![sample_config()]).unwrap();
+        let removed: Vec<_> = summary.removed.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(removed.len(), 2);
+        assert!(removed.contains(&"other") && removed.contains(&"added"));
+        assert_eq!(manager.count(), 1);
+    }
 }
@@ -5,10 +5,9 @@
 
 use chrono::{NaiveTime, Weekday};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
 use thiserror::Error;
 
-use crate::trigger::{EntityIdSpec, NumericValue, StateMatch};
+use crate::trigger::{EntityIdSpec, ForDuration, NumericValue, StateMatch};
 
 /// Condition errors
 #[derive(Debug, Error)]
@@ -100,13 +99,9 @@ pub struct StateCondition {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub attribute: Option<String>,
 
-    /// Duration the state must have been held
-    #[serde(
-        skip_serializing_if = "Option::is_none",
-        default,
-        with = "crate::trigger::option_duration_serde"
-    )]
-    pub r#for: Option<Duration>,
+    /// Duration the state must have been held, possibly a template
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub r#for: Option<ForDuration>,
 
     /// Match using regex pattern
     #[serde(default)]
@@ -200,8 +195,46 @@ pub struct TemplateCondition {
 /// Trigger condition - check which trigger fired
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TriggerCondition {
-    /// Trigger ID to match
-    pub id: String,
+    /// Trigger IDs to match, any of them passes
+    #[serde(deserialize_with = "trigger_ids")]
+    pub id: Vec<String>,
+}
+
+/// Deserialize one trigger ID or a list of them
+///
+/// IDs written as numbers (such as the index-based default IDs) are taken
+/// as their text.
+fn trigger_ids<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TriggerId {
+        String(String),
+        Number(serde_json::Number),
+    }
+
+    impl From<TriggerId> for String {
+        fn from(id: TriggerId) -> Self {
+            match id {
+                TriggerId::String(s) => s,
+                TriggerId::Number(n) => n.to_string(),
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(TriggerId),
+        Many(Vec<TriggerId>),
+    }
+
+    match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(id) => Ok(vec![id.into()]),
+        OneOrMany::Many(ids) => Ok(ids.into_iter().map(String::from).collect()),
+    }
 }
 
 /// AND condition - all must be true
@@ -382,7 +415,7 @@ mod tests {
 
         let condition: Condition = serde_json::from_str(json).unwrap();
         if let Condition::Trigger(c) = condition {
-            assert_eq!(c.id, "motion_detected");
+            assert_eq!(c.id, ["motion_detected"]);
         } else {
             panic!("Expected Trigger condition");
         }
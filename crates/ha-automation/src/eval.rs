@@ -4,7 +4,7 @@
 //! state of the system. Conditions are evaluated at trigger time to determine
 //! whether automation actions should execute.
 
-use chrono::{Datelike, Local, NaiveTime};
+use chrono::{Datelike, Local, NaiveTime, Utc};
 use ha_state_store::StateStore;
 use ha_template::TemplateEngine;
 use regex::Regex;
@@ -17,7 +17,7 @@ use crate::condition::{
     NumericStateCondition, OrCondition, StateCondition, SunCondition, SunPosition,
     TemplateCondition, TimeCondition, TimeSpec, TriggerCondition, ZoneCondition,
 };
-use crate::trigger::{NumericValue, StateMatch, TriggerData};
+use crate::trigger::{ForDuration, NumericValue, StateMatch, TriggerData};
 
 /// Context for condition evaluation
 ///
@@ -154,7 +154,7 @@ impl ConditionEvaluator {
 
     // --- Individual condition evaluators ---
 
-    fn eval_state(&self, condition: &StateCondition, _ctx: &EvalContext) -> ConditionResult<bool> {
+    fn eval_state(&self, condition: &StateCondition, ctx: &EvalContext) -> ConditionResult<bool> {
         let entity_ids = condition.entity_id.ids();
         debug!(
             ?entity_ids,
@@ -163,6 +163,11 @@ impl ConditionEvaluator {
             "Evaluating state condition"
         );
 
+        let held_for = match &condition.r#for {
+            Some(for_duration) => Some(self.render_for_duration(for_duration, ctx)?),
+            None => None,
+        };
+
         for entity_id in entity_ids {
             let value = if let Some(attr) = &condition.attribute {
                 // Check attribute value
@@ -186,13 +191,49 @@ impl ConditionEvaluator {
                 return Ok(false);
             }
 
-            // TODO: Handle 'for' duration check
-            // This would require tracking when the entity entered this state
+            // The state must not have changed within the `for` duration
+            if let Some(held_for) = held_for {
+                let last_changed = self
+                    .state_machine
+                    .get(entity_id)
+                    .ok_or_else(|| ConditionError::EntityNotFound(entity_id.to_string()))?
+                    .last_changed;
+                let held = (ctx.now().with_timezone(&Utc) - last_changed)
+                    .to_std()
+                    .unwrap_or_default();
+                if held < held_for {
+                    trace!(entity_id, ?held, ?held_for, "State not held long enough");
+                    return Ok(false);
+                }
+            }
         }
 
         Ok(true)
     }
 
+    /// Resolve a `for` duration, rendering templates with the context
+    fn render_for_duration(
+        &self,
+        for_duration: &ForDuration,
+        ctx: &EvalContext,
+    ) -> ConditionResult<std::time::Duration> {
+        let template = match for_duration {
+            ForDuration::Fixed(duration) => return Ok(*duration),
+            ForDuration::Template(template) => template,
+        };
+
+        let rendered = self
+            .template_engine
+            .render_with_context(template, ctx.to_template_context())
+            .map_err(|e| ConditionError::Template(e.to_string()))?;
+
+        // Rendered numbers and mappings come back as text
+        let value =
+            serde_json::from_str(rendered.trim()).unwrap_or(serde_json::Value::String(rendered));
+        ForDuration::parse(&value)
+            .map_err(|e| ConditionError::Template(format!("invalid for duration: {}", e)))
+    }
+
     fn eval_numeric_state(
         &self,
         condition: &NumericStateCondition,
@@ -431,14 +472,13 @@ impl ConditionEvaluator {
         condition: &TriggerCondition,
         ctx: &EvalContext,
     ) -> ConditionResult<bool> {
-        debug!(expected_id = %condition.id, "Evaluating trigger condition");
+        debug!(expected_ids = ?condition.id, "Evaluating trigger condition");
 
         let matches = ctx
             .trigger
             .as_ref()
             .and_then(|t| t.id.as_ref())
-            .map(|id| id == &condition.id)
-            .unwrap_or(false);
+            .is_some_and(|id| condition.id.contains(id));
 
         trace!(matches, "Trigger ID check result");
         Ok(matches)
@@ -684,6 +724,56 @@ mod tests {
         assert!(evaluator.evaluate(&condition, &ctx).unwrap());
     }
 
+    #[test]
+    fn test_state_condition_for_held_long_enough() {
+        let (evaluator, sm) = make_test_evaluator();
+        set_state(&sm, "binary_sensor.door", "off", HashMap::new());
+
+        let condition: Condition = serde_json::from_value(serde_json::json!({
+            "condition": "state",
+            "entity_id": "binary_sensor.door",
+            "state": "off",
+            "for": {"minutes": 5}
+        }))
+        .unwrap();
+
+        let later = Local::now() + chrono::Duration::minutes(10);
+        let ctx = EvalContext::new().with_time(later);
+        assert!(evaluator.evaluate(&condition, &ctx).unwrap());
+
+        // Templated durations render first
+        let condition: Condition = serde_json::from_value(serde_json::json!({
+            "condition": "state",
+            "entity_id": "binary_sensor.door",
+            "state": "off",
+            "for": "{{ wait * 60 }}"
+        }))
+        .unwrap();
+        let ctx = EvalContext::new()
+            .with_time(later)
+            .with_var("wait", serde_json::json!(9));
+        assert!(evaluator.evaluate(&condition, &ctx).unwrap());
+        let ctx = ctx.with_var("wait", serde_json::json!(11));
+        assert!(!evaluator.evaluate(&condition, &ctx).unwrap());
+    }
+
+    #[test]
+    fn test_state_condition_for_just_changed() {
+        let (evaluator, sm) = make_test_evaluator();
+        set_state(&sm, "binary_sensor.door", "off", HashMap::new());
+
+        let condition: Condition = serde_json::from_value(serde_json::json!({
+            "condition": "state",
+            "entity_id": "binary_sensor.door",
+            "state": "off",
+            "for": "00:05:00"
+        }))
+        .unwrap();
+
+        let ctx = EvalContext::new();
+        assert!(!evaluator.evaluate(&condition, &ctx).unwrap());
+    }
+
     #[test]
     fn test_state_condition_regex() {
         let (evaluator, sm) = make_test_evaluator();
@@ -782,7 +872,7 @@ mod tests {
         let (evaluator, _sm) = make_test_evaluator();
 
         let condition = Condition::Trigger(TriggerCondition {
-            id: "motion_detected".to_string(),
+            id: vec!["motion_detected".to_string()],
         });
 
         // Without trigger data
@@ -800,6 +890,26 @@ mod tests {
         assert!(!evaluator.evaluate(&condition, &ctx).unwrap());
     }
 
+    #[test]
+    fn test_trigger_condition_id_list() {
+        let (evaluator, _sm) = make_test_evaluator();
+
+        let condition: Condition = serde_json::from_value(serde_json::json!({
+            "condition": "trigger",
+            "id": ["door_opened", 1]
+        }))
+        .unwrap();
+
+        let ctx = EvalContext::with_trigger(TriggerData::new("state").with_id("door_opened"));
+        assert!(evaluator.evaluate(&condition, &ctx).unwrap());
+
+        // Triggers without an ID are matched by their index
+        let ctx = EvalContext::with_trigger(TriggerData::new("state").with_index(1));
+        assert!(evaluator.evaluate(&condition, &ctx).unwrap());
+        let ctx = EvalContext::with_trigger(TriggerData::new("state").with_index(0));
+        assert!(!evaluator.evaluate(&condition, &ctx).unwrap());
+    }
+
     #[test]
     fn test_and_condition() {
         let (evaluator, sm) = make_test_evaluator();
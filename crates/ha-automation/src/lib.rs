@@ -24,14 +24,16 @@
 pub mod automation;
 pub mod condition;
 pub mod eval;
+pub mod mqtt;
 pub mod trigger;
 pub mod trigger_eval;
 
 pub use automation::{
-    Automation, AutomationConfig, AutomationError, AutomationManager, AutomationResult,
-    ExecutionMode,
+    entity_id_for, Automation, AutomationConfig, AutomationError, AutomationManager,
+    AutomationResult, ExecutionMode, ReloadSummary,
 };
 pub use condition::{Condition, ConditionError, ConditionResult};
 pub use eval::{ConditionEvaluator, EvalContext};
-pub use trigger::{Trigger, TriggerData, TriggerError, TriggerResult};
+pub use mqtt::{MqttMessage, MqttSource};
+pub use trigger::{ForDuration, Trigger, TriggerData, TriggerError, TriggerResult};
 pub use trigger_eval::{TriggerEvalContext, TriggerEvaluator};
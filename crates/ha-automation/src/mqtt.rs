@@ -0,0 +1,221 @@
+//! MQTT message sources for MQTT triggers
+//!
+//! There is no MQTT client in this crate. Whatever integration owns the
+//! broker connection implements [`MqttSource`], and MQTT triggers subscribe
+//! through it, so the automation engine never depends on a client library.
+
+use std::sync::Arc;
+
+use tracing::warn;
+
+use crate::trigger::{MqttTrigger, TriggerData};
+use crate::trigger_eval::TriggerEvaluator;
+
+/// A message received from the broker
+#[derive(Debug, Clone, PartialEq)]
+pub struct MqttMessage {
+    /// Topic the message was published on
+    pub topic: String,
+    /// Message payload, decoded as UTF-8
+    pub payload: String,
+    /// Quality of service level (0-2)
+    pub qos: u8,
+    /// Whether the broker delivered a retained message
+    pub retain: bool,
+}
+
+impl MqttMessage {
+    /// Create a QoS 0, non-retained message
+    pub fn new(topic: impl Into<String>, payload: impl Into<String>) -> Self {
+        Self {
+            topic: topic.into(),
+            payload: payload.into(),
+            qos: 0,
+            retain: false,
+        }
+    }
+}
+
+/// Callback for messages on a subscribed topic
+pub type MqttMessageCallback = Arc<dyn Fn(&MqttMessage) + Send + Sync>;
+
+/// Ends a subscription when called
+pub type MqttUnsubscribe = Box<dyn FnOnce() + Send + Sync>;
+
+/// Something that delivers MQTT messages, usually a broker connection
+///
+/// Like Python's `mqtt.async_subscribe`: the callback is called for every
+/// message whose topic matches `topic`, wildcards included (see
+/// [`topic_matches`]), until the returned unsubscribe function is called.
+pub trait MqttSource: Send + Sync {
+    /// Subscribe `callback` to messages matching `topic`
+    fn subscribe(&self, topic: &str, callback: MqttMessageCallback) -> MqttUnsubscribe;
+}
+
+/// Check whether `topic` matches a subscription filter
+///
+/// `+` matches exactly one level and a trailing `#` matches any number of
+/// levels, including none.
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut topic_levels = topic.split('/');
+    for level in filter.split('/') {
+        match (level, topic_levels.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => {}
+            (level, Some(topic_level)) if level == topic_level => {}
+            _ => return false,
+        }
+    }
+    topic_levels.next().is_none()
+}
+
+/// Subscribe an MQTT trigger through `source`
+///
+/// `action` is called with the trigger data of every message that matches
+/// the trigger. Messages are delivered on the source's thread, so `action`
+/// should hand off longer work.
+pub fn subscribe_trigger(
+    source: &dyn MqttSource,
+    evaluator: Arc<TriggerEvaluator>,
+    trigger: MqttTrigger,
+    action: Arc<dyn Fn(TriggerData) + Send + Sync>,
+) -> MqttUnsubscribe {
+    let topic = trigger.topic.clone();
+    source.subscribe(
+        &topic,
+        Arc::new(
+            move |message| match evaluator.eval_mqtt_message(&trigger, message) {
+                Ok(Some(data)) => action(data),
+                Ok(None) => {}
+                Err(e) => {
+                    warn!(topic = %message.topic, error = %e, "Error evaluating MQTT trigger")
+                }
+            },
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ha_event_bus::EventBus;
+    use ha_state_store::StateStore;
+    use ha_template::TemplateEngine;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// In-memory broker delivering published messages to matching subscribers
+    #[derive(Default)]
+    struct MockSource {
+        next_key: AtomicUsize,
+        subscriptions: Arc<Mutex<Vec<(usize, String, MqttMessageCallback)>>>,
+    }
+
+    impl MockSource {
+        fn publish(&self, topic: &str, payload: &str) {
+            let message = MqttMessage::new(topic, payload);
+            let subscriptions = self.subscriptions.lock().unwrap().clone();
+            for (_, filter, callback) in subscriptions {
+                if topic_matches(&filter, topic) {
+                    callback(&message);
+                }
+            }
+        }
+    }
+
+    impl MqttSource for MockSource {
+        fn subscribe(&self, topic: &str, callback: MqttMessageCallback) -> MqttUnsubscribe {
+            let key = self.next_key.fetch_add(1, Ordering::SeqCst);
+            self.subscriptions
+                .lock()
+                .unwrap()
+                .push((key, topic.to_string(), callback));
+
+            let subscriptions = self.subscriptions.clone();
+            Box::new(move || {
+                subscriptions
+                    .lock()
+                    .unwrap()
+                    .retain(|(other, _, _)| *other != key)
+            })
+        }
+    }
+
+    fn make_evaluator() -> Arc<TriggerEvaluator> {
+        let state_machine = Arc::new(StateStore::new(Arc::new(EventBus::new())));
+        let template_engine = Arc::new(TemplateEngine::new(state_machine.clone()));
+        Arc::new(TriggerEvaluator::new(state_machine, template_engine))
+    }
+
+    fn subscribe_recording(
+        source: &MockSource,
+        trigger: serde_json::Value,
+    ) -> (Arc<Mutex<Vec<TriggerData>>>, MqttUnsubscribe) {
+        let trigger: MqttTrigger = serde_json::from_value(trigger).unwrap();
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let recorder = fired.clone();
+        let unsubscribe = subscribe_trigger(
+            source,
+            make_evaluator(),
+            trigger,
+            Arc::new(move |data| recorder.lock().unwrap().push(data)),
+        );
+        (fired, unsubscribe)
+    }
+
+    #[test]
+    fn test_topic_matches() {
+        assert!(topic_matches("home/door", "home/door"));
+        assert!(!topic_matches("home/door", "home/door/state"));
+        assert!(topic_matches("home/+/state", "home/door/state"));
+        assert!(!topic_matches("home/+/state", "home/state"));
+        assert!(topic_matches("home/#", "home"));
+        assert!(topic_matches("home/#", "home/door/state"));
+        assert!(!topic_matches("home/#", "garden/door"));
+    }
+
+    #[test]
+    fn test_mqtt_trigger_fires_on_matching_payload() {
+        let source = MockSource::default();
+        let (fired, unsubscribe) = subscribe_recording(
+            &source,
+            serde_json::json!({"topic": "home/+/button", "payload": "pressed", "id": "btn"}),
+        );
+
+        source.publish("home/hall/button", "released");
+        source.publish("home/hall/light", "pressed");
+        source.publish("home/hall/button", "pressed");
+
+        {
+            let fired = fired.lock().unwrap();
+            assert_eq!(fired.len(), 1);
+            assert_eq!(fired[0].platform, "mqtt");
+            assert_eq!(fired[0].id.as_deref(), Some("btn"));
+            assert_eq!(fired[0].variables["topic"], "home/hall/button");
+            assert_eq!(fired[0].variables["payload"], "pressed");
+        }
+
+        unsubscribe();
+        source.publish("home/hall/button", "pressed");
+        assert_eq!(fired.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_mqtt_trigger_value_template() {
+        let source = MockSource::default();
+        let (fired, _unsubscribe) = subscribe_recording(
+            &source,
+            serde_json::json!({
+                "topic": "sensors/door",
+                "value_template": "{{ value_json.contact == 'open' }}"
+            }),
+        );
+
+        source.publish("sensors/door", r#"{"contact": "closed"}"#);
+        source.publish("sensors/door", r#"{"contact": "open"}"#);
+
+        let fired = fired.lock().unwrap();
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].variables["payload_json"]["contact"], "open");
+    }
+}
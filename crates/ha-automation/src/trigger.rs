@@ -40,6 +40,10 @@ pub struct TriggerData {
 
     /// When the trigger matched
     pub triggered_at: DateTime<Utc>,
+
+    /// How long the matched state must hold before the trigger fires
+    #[serde(skip)]
+    pub hold_for: Option<Duration>,
 }
 
 impl TriggerData {
@@ -50,6 +54,7 @@ impl TriggerData {
             platform: platform.into(),
             variables: HashMap::new(),
             triggered_at: Utc::now(),
+            hold_for: None,
         }
     }
 
@@ -64,6 +69,18 @@ impl TriggerData {
         self.variables.insert(key.into(), value);
         self
     }
+
+    /// Record the trigger's position in its automation's trigger list
+    ///
+    /// Sets `trigger.idx`, and defaults `trigger.id` to the same index for
+    /// triggers without an `id`, as HA does.
+    pub fn with_index(mut self, index: usize) -> Self {
+        let index = index.to_string();
+        self.variables
+            .insert("idx".to_string(), serde_json::json!(index));
+        self.id.get_or_insert(index);
+        self
+    }
 }
 
 /// Trigger definition
@@ -94,11 +111,14 @@ pub enum Trigger {
     /// Fires at sunrise/sunset
     Sun(SunTrigger),
 
-    /// Fires on Home Assistant start/stop
+    /// Fires when Home Assistant has started or is stopping
     Homeassistant(HomeassistantTrigger),
 
     /// Fires on webhook request
     Webhook(WebhookTrigger),
+
+    /// Fires when an MQTT message is received
+    Mqtt(MqttTrigger),
 }
 
 impl Trigger {
@@ -115,6 +135,7 @@ impl Trigger {
             Trigger::Sun(t) => t.id.as_deref(),
             Trigger::Homeassistant(t) => t.id.as_deref(),
             Trigger::Webhook(t) => t.id.as_deref(),
+            Trigger::Mqtt(t) => t.id.as_deref(),
         }
     }
 
@@ -131,6 +152,7 @@ impl Trigger {
             Trigger::Sun(_) => "sun",
             Trigger::Homeassistant(_) => "homeassistant",
             Trigger::Webhook(_) => "webhook",
+            Trigger::Mqtt(_) => "mqtt",
         }
     }
 }
@@ -158,12 +180,8 @@ pub struct StateTrigger {
     pub attribute: Option<String>,
 
     /// Duration the state must be held before triggering
-    #[serde(
-        skip_serializing_if = "Option::is_none",
-        default,
-        with = "option_duration_serde"
-    )]
-    pub r#for: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub r#for: Option<ForDuration>,
 
     /// Don't trigger if coming from these states (HashSet for O(1) lookup)
     #[serde(default)]
@@ -322,7 +340,7 @@ pub struct HomeassistantTrigger {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
 
-    /// Event: start or shutdown
+    /// Event: start (after startup finished) or shutdown
     pub event: HassEvent,
 }
 
@@ -345,6 +363,28 @@ pub struct WebhookTrigger {
     pub local_only: bool,
 }
 
+/// MQTT trigger
+///
+/// Messages arrive through an [`MqttSource`](crate::mqtt::MqttSource)
+/// rather than the event bus.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MqttTrigger {
+    /// Optional trigger ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// Topic to subscribe to (may contain `+` and `#` wildcards)
+    pub topic: String,
+
+    /// Payload the message must have (after `value_template`, if set)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+
+    /// Template rendered with `value` and `value_json` of the payload
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_template: Option<String>,
+}
+
 // --- Supporting types ---
 
 /// Entity ID specification (single or list)
@@ -366,13 +406,38 @@ impl EntityIdSpec {
 }
 
 /// State match specification (single value or list)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Numbers and booleans are accepted and matched by their string form, so
+/// `to: 255` works for numeric attributes like `brightness`.
+#[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
 pub enum StateMatch {
     Single(String),
     List(Vec<String>),
 }
 
+impl<'de> Deserialize<'de> for StateMatch {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        fn scalar<E: serde::de::Error>(value: serde_json::Value) -> Result<String, E> {
+            match value {
+                serde_json::Value::String(s) => Ok(s),
+                serde_json::Value::Number(n) => Ok(n.to_string()),
+                serde_json::Value::Bool(b) => Ok(b.to_string()),
+                other => Err(E::custom(format!("invalid state match: {}", other))),
+            }
+        }
+
+        match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::Array(values) => values
+                .into_iter()
+                .map(scalar)
+                .collect::<Result<_, _>>()
+                .map(StateMatch::List),
+            value => scalar(value).map(StateMatch::Single),
+        }
+    }
+}
+
 impl StateMatch {
     /// Check if a state matches
     pub fn matches(&self, state: &str) -> bool {
@@ -383,6 +448,74 @@ impl StateMatch {
     }
 }
 
+/// A `for` duration, given directly or as a template
+///
+/// Fixed durations accept seconds, `HH:MM:SS` or a mapping of `days`,
+/// `hours`, `minutes`, `seconds` and `milliseconds`. Templates are rendered
+/// when the trigger matches and must produce one of the same forms.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ForDuration {
+    Fixed(Duration),
+    Template(String),
+}
+
+impl ForDuration {
+    /// Parse a rendered or configured duration value
+    pub fn parse(value: &serde_json::Value) -> Result<Duration, String> {
+        match value {
+            serde_json::Value::Number(n) => n
+                .as_f64()
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                .ok_or_else(|| format!("invalid duration: {}", n)),
+            serde_json::Value::String(s) => option_duration_serde::parse_duration(s.trim()),
+            serde_json::Value::Object(map) => {
+                let mut secs = 0.0;
+                for (key, value) in map {
+                    let factor = match key.as_str() {
+                        "days" => 86400.0,
+                        "hours" => 3600.0,
+                        "minutes" => 60.0,
+                        "seconds" => 1.0,
+                        "milliseconds" => 0.001,
+                        _ => return Err(format!("invalid duration key: {}", key)),
+                    };
+                    let amount = value
+                        .as_f64()
+                        .ok_or_else(|| format!("invalid duration {}: {}", key, value))?;
+                    secs += amount * factor;
+                }
+                Duration::try_from_secs_f64(secs).map_err(|e| e.to_string())
+            }
+            other => Err(format!("invalid duration: {}", other)),
+        }
+    }
+}
+
+impl Serialize for ForDuration {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ForDuration::Fixed(duration) => {
+                option_duration_serde::serialize(&Some(*duration), serializer)
+            }
+            ForDuration::Template(template) => serializer.serialize_str(template),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ForDuration {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        if let Some(template) = value.as_str() {
+            if ha_template::TemplateEngine::is_template(template) {
+                return Ok(ForDuration::Template(template.to_string()));
+            }
+        }
+        ForDuration::parse(&value)
+            .map(ForDuration::Fixed)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
 /// Time specification (fixed time or entity)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -467,7 +600,7 @@ pub(crate) mod option_duration_serde {
         }
     }
 
-    fn parse_duration(s: &str) -> Result<Duration, String> {
+    pub fn parse_duration(s: &str) -> Result<Duration, String> {
         // Parse HH:MM:SS or MM:SS or SS format
         let parts: Vec<&str> = s.split(':').collect();
         match parts.len() {
@@ -5,18 +5,23 @@
 //! by conditions and actions.
 
 use chrono::{DateTime, Local, NaiveTime, Timelike, Utc};
-use ha_core::events::{StateChangedData, STATE_CHANGED};
+use ha_core::events::{StateChangedData, HOMEASSISTANT_STARTED, HOMEASSISTANT_STOP, STATE_CHANGED};
 use ha_core::{Event, State};
+use ha_event_bus::ArcEvent;
 use ha_state_store::StateStore;
 use ha_template::TemplateEngine;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
 use tracing::{debug, trace};
 
+use crate::mqtt::MqttMessage;
 use crate::trigger::{
-    EventTrigger, HassEvent, HomeassistantTrigger, NumericStateTrigger, NumericValue, StateTrigger,
-    SunEvent, SunTrigger, TemplateTrigger, TimePatternTrigger, TimeSpec, TimeTrigger, Trigger,
-    TriggerData, TriggerError, TriggerResult, ZoneEvent, ZoneTrigger,
+    EventTrigger, ForDuration, HassEvent, HomeassistantTrigger, MqttTrigger, NumericStateTrigger,
+    NumericValue, StateTrigger, SunEvent, SunTrigger, TemplateTrigger, TimePatternTrigger,
+    TimeSpec, TimeTrigger, Trigger, TriggerData, TriggerError, TriggerResult, ZoneEvent,
+    ZoneTrigger,
 };
 
 /// Context for trigger evaluation
@@ -80,6 +85,8 @@ impl TriggerEvaluator {
             Trigger::Time(_) | Trigger::TimePattern(_) | Trigger::Sun(_) => Ok(None),
             // Webhook triggers are handled by the HTTP layer
             Trigger::Webhook(_) => Ok(None),
+            // MQTT triggers receive messages through an MqttSource
+            Trigger::Mqtt(_) => Ok(None),
         }
     }
 
@@ -144,26 +151,17 @@ impl TriggerEvaluator {
             "Evaluating state trigger"
         );
 
+        // Attribute triggers ignore changes that leave their attribute alone
+        if let Some(attribute) = &trigger.attribute {
+            if !state_data.attribute_changed(attribute) {
+                trace!("Watched attribute unchanged");
+                return Ok(None);
+            }
+        }
+
         // Get old and new values
-        let (old_value, new_value) = if let Some(attr) = &trigger.attribute {
-            // Check attribute changes
-            let old_val = state_data
-                .old_state
-                .as_ref()
-                .and_then(|s| s.attributes.get(attr))
-                .map(json_value_to_string);
-            let new_val = state_data
-                .new_state
-                .as_ref()
-                .and_then(|s| s.attributes.get(attr))
-                .map(json_value_to_string);
-            (old_val, new_val)
-        } else {
-            // Check state changes
-            let old_val = state_data.old_state.as_ref().map(|s| s.state.clone());
-            let new_val = state_data.new_state.as_ref().map(|s| s.state.clone());
-            (old_val, new_val)
-        };
+        let old_value = watched_value(trigger, state_data.old_state.as_ref());
+        let new_value = watched_value(trigger, state_data.new_state.as_ref());
 
         trace!(?old_value, ?new_value, "State values");
 
@@ -205,18 +203,13 @@ impl TriggerEvaluator {
             }
         }
 
-        // If neither from nor to is specified, trigger on any change
-        if trigger.from.is_none() && trigger.to.is_none() {
-            // Must have actually changed
-            if old_value == new_value {
-                trace!("No actual change");
-                return Ok(None);
-            }
+        // The watched value (state or attribute) must actually change; a
+        // change of only the other part doesn't fire the trigger
+        if old_value == new_value {
+            trace!("No actual change");
+            return Ok(None);
         }
 
-        // TODO: Handle 'for' duration constraint
-        // This requires tracking when the state changed and waiting
-
         // Build trigger data
         let mut data = TriggerData::new("state")
             .with_var("entity_id", serde_json::json!(entity_id_str))
@@ -237,10 +230,108 @@ impl TriggerEvaluator {
             data = data.with_var("attribute", serde_json::json!(attr));
         }
 
+        // The caller holds the trigger for `for` with `wait_for_hold`
+        if let Some(for_duration) = &trigger.r#for {
+            let duration = self.render_for_duration(for_duration, &data)?;
+            data = data.with_var("for", serde_json::json!(duration.as_secs_f64()));
+            data.hold_for = Some(duration);
+        }
+
         debug!("State trigger matched");
         Ok(Some(data))
     }
 
+    /// Resolve a `for` duration, rendering templates with the trigger data
+    fn render_for_duration(
+        &self,
+        for_duration: &ForDuration,
+        data: &TriggerData,
+    ) -> TriggerResult<Duration> {
+        let template = match for_duration {
+            ForDuration::Fixed(duration) => return Ok(*duration),
+            ForDuration::Template(template) => template,
+        };
+
+        let mut trigger = serde_json::to_value(data).unwrap_or_default();
+        trigger["platform"] = serde_json::json!(data.platform);
+        let vars = HashMap::from([("trigger".to_string(), trigger)]);
+        let rendered = self
+            .template_engine
+            .render_with_variables(template, vars)
+            .map_err(|e| TriggerError::Template(e.to_string()))?;
+
+        // Rendered numbers and mappings come back as text
+        let value =
+            serde_json::from_str(rendered.trim()).unwrap_or(serde_json::Value::String(rendered));
+        ForDuration::parse(&value)
+            .map_err(|e| TriggerError::Template(format!("invalid for duration: {}", e)))
+    }
+
+    /// Wait out the `for` duration of a matched state trigger
+    ///
+    /// `events` must be subscribed to `state_changed` before the matching
+    /// event is handed on, so no change in between is missed. Returns `true`
+    /// if the watched value held for the whole duration, and `false` as soon
+    /// as the entity leaves it, which cancels the pending trigger.
+    pub async fn wait_for_hold(
+        &self,
+        trigger: &StateTrigger,
+        data: &TriggerData,
+        mut events: broadcast::Receiver<ArcEvent>,
+    ) -> bool {
+        let Some(duration) = data.hold_for else {
+            return true;
+        };
+        let Some(entity_id) = data.variables.get("entity_id").and_then(|v| v.as_str()) else {
+            return false;
+        };
+        let matched_state = data
+            .variables
+            .get("to_state")
+            .and_then(|state| serde_json::from_value::<State>(state.clone()).ok());
+        let matched = watched_value(trigger, matched_state.as_ref());
+        let still_matched = || {
+            let current = self.state_machine.get(entity_id);
+            watched_value(trigger, current.as_ref()) == matched
+        };
+
+        if !still_matched() {
+            return false;
+        }
+
+        let deadline = tokio::time::sleep(duration);
+        tokio::pin!(deadline);
+        loop {
+            tokio::select! {
+                _ = &mut deadline => break,
+                event = events.recv() => match event {
+                    Ok(event) => {
+                        // Skip other entities without deserializing their states
+                        if event.data.get("entity_id").and_then(|id| id.as_str())
+                            != Some(entity_id)
+                        {
+                            continue;
+                        }
+                        let Ok(change) =
+                            serde_json::from_value::<StateChangedData>(event.data.clone())
+                        else {
+                            continue;
+                        };
+                        if watched_value(trigger, change.new_state.as_ref()) != matched {
+                            debug!(entity_id, "State left before `for` elapsed, cancelling");
+                            return false;
+                        }
+                    }
+                    // Missed changes are caught by the final check
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            }
+        }
+
+        still_matched()
+    }
+
     // --- Event trigger evaluation ---
 
     fn eval_event_trigger(
@@ -419,16 +510,20 @@ impl TriggerEvaluator {
         trigger: &HomeassistantTrigger,
         event: &Event<serde_json::Value>,
     ) -> TriggerResult<Option<TriggerData>> {
+        // Start fires once startup has finished, so entities are loaded
         let expected_event_type = match trigger.event {
-            HassEvent::Shutdown => "homeassistant_stop",
-            HassEvent::Start => "homeassistant_start",
+            HassEvent::Shutdown => HOMEASSISTANT_STOP,
+            HassEvent::Start => HOMEASSISTANT_STARTED,
         };
 
         if event.event_type.as_str() != expected_event_type {
             return Ok(None);
         }
 
-        let mut data = TriggerData::new("homeassistant");
+        let mut data = TriggerData::new("homeassistant").with_var(
+            "event",
+            serde_json::to_value(trigger.event).unwrap_or_default(),
+        );
 
         if let Some(id) = &trigger.id {
             data = data.with_id(id);
@@ -438,6 +533,63 @@ impl TriggerEvaluator {
         Ok(Some(data))
     }
 
+    // --- MQTT trigger evaluation ---
+
+    /// Evaluate an MQTT trigger against a received message
+    ///
+    /// The message topic is expected to match the trigger's subscription.
+    /// With a `value_template`, the rendered value is compared to `payload`,
+    /// or must be truthy if no payload is set.
+    pub fn eval_mqtt_message(
+        &self,
+        trigger: &MqttTrigger,
+        message: &MqttMessage,
+    ) -> TriggerResult<Option<TriggerData>> {
+        let payload_json = serde_json::from_str::<serde_json::Value>(&message.payload).ok();
+
+        let value = match &trigger.value_template {
+            Some(template) => {
+                let vars = HashMap::from([
+                    ("value".to_string(), serde_json::json!(message.payload)),
+                    (
+                        "value_json".to_string(),
+                        payload_json.clone().unwrap_or_default(),
+                    ),
+                ]);
+                let rendered = self
+                    .template_engine
+                    .render_with_variables(template, vars)
+                    .map_err(|e| TriggerError::Template(e.to_string()))?;
+                rendered.trim().to_string()
+            }
+            None => message.payload.clone(),
+        };
+
+        let matched = match &trigger.payload {
+            Some(payload) => value == *payload,
+            None => trigger.value_template.is_none() || is_truthy(&value),
+        };
+        if !matched {
+            trace!(topic = %message.topic, "MQTT payload doesn't match");
+            return Ok(None);
+        }
+
+        let mut data = TriggerData::new("mqtt")
+            .with_var("topic", serde_json::json!(message.topic))
+            .with_var("payload", serde_json::json!(message.payload))
+            .with_var("qos", serde_json::json!(message.qos));
+        if let Some(payload_json) = payload_json {
+            data = data.with_var("payload_json", payload_json);
+        }
+
+        if let Some(id) = &trigger.id {
+            data = data.with_id(id);
+        }
+
+        debug!(topic = %message.topic, "MQTT trigger matched");
+        Ok(Some(data))
+    }
+
     // --- Zone trigger evaluation ---
 
     fn eval_zone_trigger(
@@ -684,6 +836,15 @@ fn json_value_to_string(value: &serde_json::Value) -> String {
     }
 }
 
+/// The value a state trigger watches: an attribute, or the state itself
+fn watched_value(trigger: &StateTrigger, state: Option<&State>) -> Option<String> {
+    let state = state?;
+    match &trigger.attribute {
+        Some(attr) => state.attributes.get(attr).map(json_value_to_string),
+        None => Some(state.state.clone()),
+    }
+}
+
 /// Convert JSON value to f64
 fn json_to_f64(value: &serde_json::Value) -> Option<f64> {
     match value {
@@ -897,6 +1058,167 @@ mod tests {
         assert!(result.is_none());
     }
 
+    fn make_brightness_event(
+        old: (&str, Option<u8>),
+        new: (&str, Option<u8>),
+    ) -> Event<serde_json::Value> {
+        let eid: EntityId = "light.test".parse().unwrap();
+        let state = |(state, brightness): (&str, Option<u8>)| {
+            let mut attributes = HashMap::new();
+            if let Some(brightness) = brightness {
+                attributes.insert("brightness".to_string(), serde_json::json!(brightness));
+            }
+            State::new(eid.clone(), state, attributes, Context::new())
+        };
+        let data = StateChangedData {
+            entity_id: eid.clone(),
+            old_state: Some(state(old)),
+            new_state: Some(state(new)),
+        };
+        Event::new(
+            STATE_CHANGED,
+            serde_json::to_value(data).unwrap(),
+            Context::new(),
+        )
+    }
+
+    #[test]
+    fn test_state_trigger_attribute() {
+        let (evaluator, _sm, _bus) = make_test_evaluator();
+        let trigger: Trigger = serde_json::from_value(serde_json::json!({
+            "platform": "state",
+            "entity_id": "light.test",
+            "attribute": "brightness",
+            "to": 200
+        }))
+        .unwrap();
+        let ctx = TriggerEvalContext::new();
+
+        // Main state stays on while brightness changes
+        let event = make_brightness_event(("on", Some(100)), ("on", Some(200)));
+        let data = evaluator.evaluate(&trigger, &event, &ctx).unwrap().unwrap();
+        assert_eq!(data.variables["attribute"], "brightness");
+
+        // Brightness not matching `to`
+        let event = make_brightness_event(("on", Some(100)), ("on", Some(150)));
+        assert!(evaluator
+            .evaluate(&trigger, &event, &ctx)
+            .unwrap()
+            .is_none());
+
+        // Main state changes without a brightness change
+        let event = make_brightness_event(("off", Some(200)), ("on", Some(200)));
+        assert!(evaluator
+            .evaluate(&trigger, &event, &ctx)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_state_trigger_ignores_attribute_only_changes() {
+        let (evaluator, _sm, _bus) = make_test_evaluator();
+        let trigger: Trigger = serde_json::from_value(serde_json::json!({
+            "platform": "state",
+            "entity_id": "light.test",
+            "to": "on"
+        }))
+        .unwrap();
+        let ctx = TriggerEvalContext::new();
+
+        let event = make_brightness_event(("on", Some(100)), ("on", Some(200)));
+        assert!(evaluator
+            .evaluate(&trigger, &event, &ctx)
+            .unwrap()
+            .is_none());
+        let event = make_brightness_event(("off", None), ("on", Some(200)));
+        assert!(evaluator
+            .evaluate(&trigger, &event, &ctx)
+            .unwrap()
+            .is_some());
+    }
+
+    /// Turn `light.test` on, returning the matched data of a `for` trigger
+    /// and a receiver subscribed right after the matching event
+    async fn match_hold_trigger(
+        evaluator: &TriggerEvaluator,
+        states: &StateStore,
+        bus: &EventBus,
+        trigger: &Trigger,
+    ) -> (TriggerData, broadcast::Receiver<ArcEvent>) {
+        let eid: EntityId = "light.test".parse().unwrap();
+        states.set(eid.clone(), "off", HashMap::new(), Context::new());
+
+        let mut matching = bus.subscribe(STATE_CHANGED);
+        states.set(eid, "on", HashMap::new(), Context::new());
+        let event = matching.recv().await.unwrap();
+        let data = evaluator
+            .evaluate(trigger, &event, &TriggerEvalContext::new())
+            .unwrap()
+            .unwrap();
+        (data, bus.subscribe(STATE_CHANGED))
+    }
+
+    fn hold_trigger(for_duration: serde_json::Value) -> (Trigger, StateTrigger) {
+        let trigger: Trigger = serde_json::from_value(serde_json::json!({
+            "platform": "state",
+            "entity_id": "light.test",
+            "to": "on",
+            "for": for_duration
+        }))
+        .unwrap();
+        let Trigger::State(state_trigger) = trigger.clone() else {
+            unreachable!()
+        };
+        (trigger, state_trigger)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_state_trigger_for_fires_after_duration() {
+        let (evaluator, states, bus) = make_test_evaluator();
+        let (trigger, state_trigger) = hold_trigger(serde_json::json!("00:00:05"));
+
+        let (data, events) = match_hold_trigger(&evaluator, &states, &bus, &trigger).await;
+        assert_eq!(data.hold_for, Some(Duration::from_secs(5)));
+
+        // Unrelated changes don't cancel the hold
+        let other: EntityId = "light.other".parse().unwrap();
+        states.set(other, "on", HashMap::new(), Context::new());
+
+        let start = tokio::time::Instant::now();
+        assert!(evaluator.wait_for_hold(&state_trigger, &data, events).await);
+        assert!(start.elapsed() >= Duration::from_secs(5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_state_trigger_for_cancelled_by_early_change() {
+        let (evaluator, states, bus) = make_test_evaluator();
+        let (trigger, state_trigger) = hold_trigger(serde_json::json!({"seconds": 5}));
+
+        let (data, events) = match_hold_trigger(&evaluator, &states, &bus, &trigger).await;
+        let changer = states.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            let eid: EntityId = "light.test".parse().unwrap();
+            changer.set(eid, "off", HashMap::new(), Context::new());
+        });
+
+        let start = tokio::time::Instant::now();
+        assert!(!evaluator.wait_for_hold(&state_trigger, &data, events).await);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_state_trigger_for_template_renders() {
+        let (evaluator, states, bus) = make_test_evaluator();
+        let (trigger, _) = hold_trigger(serde_json::json!(
+            "{{ 3 if trigger.to_state.state == 'on' else 10 }}"
+        ));
+
+        let (data, _) = match_hold_trigger(&evaluator, &states, &bus, &trigger).await;
+        assert_eq!(data.hold_for, Some(Duration::from_secs(3)));
+        assert_eq!(data.variables["for"], 3.0);
+    }
+
     #[test]
     fn test_event_trigger() {
         let (evaluator, _sm, _bus) = make_test_evaluator();
@@ -938,6 +1260,27 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn test_homeassistant_trigger_waits_for_started() {
+        let (evaluator, _sm, _bus) = make_test_evaluator();
+        let trigger: Trigger = serde_json::from_value(
+            serde_json::json!({"platform": "homeassistant", "event": "start"}),
+        )
+        .unwrap();
+        let ctx = TriggerEvalContext::new();
+        let fire = |event_type: &str| {
+            let event = Event::new(event_type, serde_json::json!({}), Context::new());
+            evaluator.evaluate(&trigger, &event, &ctx).unwrap()
+        };
+
+        // Startup has begun but entities may still be loading
+        assert!(fire("homeassistant_start").is_none());
+        let data = fire("homeassistant_started").unwrap();
+        assert_eq!(data.platform, "homeassistant");
+        assert_eq!(data.variables["event"], "start");
+        assert!(fire("homeassistant_stop").is_none());
+    }
+
     #[test]
     fn test_json_matches() {
         // Exact match
@@ -4,7 +4,7 @@
 //! (vendor/ha-core/tests/helpers/test_condition.py, test_trigger.py)
 //! to verify our Rust types correctly parse HA-format configurations.
 
-use ha_automation::{AutomationConfig, Condition, Trigger};
+use ha_automation::{AutomationConfig, Condition, ForDuration, Trigger};
 use serde_json::json;
 
 // ============================================================================
@@ -202,7 +202,7 @@ fn test_ha_compat_trigger_condition() {
 
     let condition: Condition = serde_json::from_value(config).unwrap();
     if let Condition::Trigger(t) = condition {
-        assert_eq!(t.id, "motion_detected");
+        assert_eq!(t.id, ["motion_detected"]);
     } else {
         panic!("Expected Trigger condition");
     }
@@ -240,8 +240,10 @@ fn test_ha_compat_state_trigger_with_for() {
 
     let trigger: Trigger = serde_json::from_value(config).unwrap();
     if let Trigger::State(s) = trigger {
-        assert!(s.r#for.is_some());
-        assert_eq!(s.r#for.unwrap().as_secs(), 5);
+        assert_eq!(
+            s.r#for,
+            Some(ForDuration::Fixed(std::time::Duration::from_secs(5)))
+        );
     } else {
         panic!("Expected State trigger");
     }
@@ -0,0 +1,331 @@
+//! Counter Component
+//!
+//! Implements `counter:` entities holding an integer that automations can
+//! step up and down, bounded by an optional minimum and maximum.
+
+use ha_core::{Context, EntityId, ServiceCall, State, SupportsResponse};
+use ha_service_registry::{ServiceDescription, ServiceError, ServiceRegistry};
+use ha_state_store::StateStore;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+use crate::input_helpers::default_restore;
+use crate::restore_state::{restored_state, RestoreStateStore};
+
+const DOMAIN: &str = "counter";
+
+/// Counter configuration from YAML
+#[derive(Debug, Clone, Deserialize)]
+pub struct CounterConfig {
+    /// Display name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Icon
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Initial value (default: 0)
+    #[serde(default)]
+    pub initial: i64,
+    /// Increment/decrement step (default: 1)
+    #[serde(default = "default_step")]
+    pub step: i64,
+    /// Lowest allowed value
+    #[serde(default)]
+    pub minimum: Option<i64>,
+    /// Highest allowed value
+    #[serde(default)]
+    pub maximum: Option<i64>,
+    /// Keep the last value instead of resetting to `initial` (default: true)
+    #[serde(default = "default_restore")]
+    pub restore: bool,
+}
+
+fn default_step() -> i64 {
+    1
+}
+
+/// Bounds and step of a counter, read back from its state attributes
+struct CounterSettings {
+    initial: i64,
+    step: i64,
+    minimum: Option<i64>,
+    maximum: Option<i64>,
+}
+
+impl CounterSettings {
+    fn from_state(state: &State) -> Self {
+        let attr = |key: &str| state.attributes.get(key).and_then(|v| v.as_i64());
+        Self {
+            initial: attr("initial").unwrap_or(0),
+            step: attr("step").unwrap_or(1),
+            minimum: attr("minimum"),
+            maximum: attr("maximum"),
+        }
+    }
+
+    /// Clamp a value into the counter's bounds
+    fn clamp(&self, value: i64) -> i64 {
+        let value = self.maximum.map_or(value, |max| value.min(max));
+        self.minimum.map_or(value, |min| value.max(min))
+    }
+
+    fn in_bounds(&self, value: i64) -> bool {
+        self.clamp(value) == value
+    }
+}
+
+/// Load counter entities from config and register them in the state machine
+pub fn load_counters(
+    config: &HashMap<String, CounterConfig>,
+    states: &StateStore,
+    restore: Option<&RestoreStateStore>,
+) -> usize {
+    let mut count = 0;
+
+    for (id, config) in config {
+        let entity_id = match EntityId::new(DOMAIN, id) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid counter id '{}': {}", id, e);
+                continue;
+            }
+        };
+
+        let mut attributes = HashMap::new();
+        if let Some(name) = &config.name {
+            attributes.insert("friendly_name".to_string(), json!(name));
+        }
+        if let Some(icon) = &config.icon {
+            attributes.insert("icon".to_string(), json!(icon));
+        }
+        attributes.insert("initial".to_string(), json!(config.initial));
+        attributes.insert("step".to_string(), json!(config.step));
+        if let Some(minimum) = config.minimum {
+            attributes.insert("minimum".to_string(), json!(minimum));
+        }
+        if let Some(maximum) = config.maximum {
+            attributes.insert("maximum".to_string(), json!(maximum));
+        }
+        attributes.insert("editable".to_string(), json!(false));
+
+        let settings = CounterSettings {
+            initial: config.initial,
+            step: config.step,
+            minimum: config.minimum,
+            maximum: config.maximum,
+        };
+        let existing = config
+            .restore
+            .then(|| restored_state(restore, states, &entity_id, false))
+            .flatten()
+            .and_then(|state| state.parse::<i64>().ok());
+        let value = settings.clamp(existing.unwrap_or(config.initial));
+
+        states.set(
+            entity_id.clone(),
+            value.to_string(),
+            attributes,
+            Context::new(),
+        );
+        debug!("Loaded counter.{} = {}", id, value);
+        count += 1;
+    }
+
+    if count > 0 {
+        info!("Loaded {} counter entities", count);
+    }
+    count
+}
+
+/// Register a counter service that computes the new value from the current one
+fn register_counter_service<F>(
+    services: &ServiceRegistry,
+    states: Arc<StateStore>,
+    service: &str,
+    name: &str,
+    schema: Option<serde_json::Value>,
+    compute: F,
+) where
+    F: Fn(&ServiceCall, &CounterSettings, i64) -> Result<i64, ServiceError> + Send + Sync + 'static,
+{
+    let compute = Arc::new(compute);
+    services.register_with_description(
+        ServiceDescription {
+            domain: DOMAIN.to_string(),
+            service: service.to_string(),
+            name: Some(name.to_string()),
+            description: Some(format!("{} a counter", name)),
+            schema,
+            target: Some(json!({"entity": {"domain": DOMAIN}})),
+            supports_response: SupportsResponse::None,
+        },
+        move |call: ServiceCall| {
+            let states = states.clone();
+            let compute = compute.clone();
+            async move {
+                for entity_id in crate::input_helpers::get_target_entities(&call, DOMAIN) {
+                    if let Some(current) = states.get(entity_id.as_str()) {
+                        let settings = CounterSettings::from_state(&current);
+                        let value = current.state.parse().unwrap_or(settings.initial);
+                        let new_value = compute(&call, &settings, value)?;
+                        let attrs = current.attributes.clone();
+                        states.set(
+                            entity_id,
+                            new_value.to_string(),
+                            attrs,
+                            call.context.clone(),
+                        );
+                    }
+                }
+                Ok(None)
+            }
+        },
+    );
+}
+
+/// Register counter services
+pub fn register_counter_services(services: &ServiceRegistry, states: Arc<StateStore>) {
+    register_counter_service(
+        services,
+        states.clone(),
+        "increment",
+        "Increment",
+        None,
+        |_, settings, value| Ok(settings.clamp(value.saturating_add(settings.step))),
+    );
+
+    register_counter_service(
+        services,
+        states.clone(),
+        "decrement",
+        "Decrement",
+        None,
+        |_, settings, value| Ok(settings.clamp(value.saturating_sub(settings.step))),
+    );
+
+    register_counter_service(
+        services,
+        states.clone(),
+        "reset",
+        "Reset",
+        None,
+        |_, settings, _| Ok(settings.clamp(settings.initial)),
+    );
+
+    register_counter_service(
+        services,
+        states,
+        "set_value",
+        "Set",
+        Some(json!({
+            "value": {"required": true, "selector": {"number": {"mode": "box"}}}
+        })),
+        |call, settings, _| {
+            let value = call
+                .service_data
+                .get("value")
+                .and_then(|v| v.as_i64())
+                .ok_or_else(|| ServiceError::InvalidData("value must be an integer".to_string()))?;
+            if !settings.in_bounds(value) {
+                return Err(ServiceError::InvalidData(format!(
+                    "Value {} is outside the counter's bounds",
+                    value
+                )));
+            }
+            Ok(value)
+        },
+    );
+
+    info!("Counter services registered");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (ServiceRegistry, Arc<StateStore>) {
+        crate::tests::setup_helpers(
+            r#"
+            guests:
+              initial: 2
+              step: 2
+              minimum: 0
+              maximum: 5
+            "#,
+            load_counters,
+            register_counter_services,
+        )
+    }
+
+    async fn call(
+        services: &ServiceRegistry,
+        service: &str,
+        data: serde_json::Value,
+    ) -> Result<(), ServiceError> {
+        services
+            .call(DOMAIN, service, data, Context::new(), false)
+            .await
+            .map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn test_counter_increment_clamps_at_maximum() {
+        let (services, states) = setup();
+        let target = json!({"entity_id": "counter.guests"});
+
+        call(&services, "increment", target.clone()).await.unwrap();
+        assert_eq!(states.get_state("counter.guests").unwrap(), "4");
+        call(&services, "increment", target.clone()).await.unwrap();
+        assert_eq!(states.get_state("counter.guests").unwrap(), "5");
+
+        for _ in 0..4 {
+            call(&services, "decrement", target.clone()).await.unwrap();
+        }
+        assert_eq!(states.get_state("counter.guests").unwrap(), "0");
+    }
+
+    #[tokio::test]
+    async fn test_counter_reset_to_initial() {
+        let (services, states) = setup();
+
+        let data = json!({"entity_id": "counter.guests", "value": 5});
+        call(&services, "set_value", data).await.unwrap();
+        assert_eq!(states.get_state("counter.guests").unwrap(), "5");
+
+        call(&services, "reset", json!({"entity_id": "counter.guests"}))
+            .await
+            .unwrap();
+        assert_eq!(states.get_state("counter.guests").unwrap(), "2");
+    }
+
+    #[tokio::test]
+    async fn test_counter_set_value_out_of_bounds() {
+        let (services, states) = setup();
+
+        let data = json!({"entity_id": "counter.guests", "value": 9});
+        let result = call(&services, "set_value", data).await;
+        assert!(matches!(result, Err(ServiceError::InvalidData(_))));
+        assert_eq!(states.get_state("counter.guests").unwrap(), "2");
+    }
+
+    #[test]
+    fn test_counter_restores_existing_value() {
+        let bus = Arc::new(ha_event_bus::EventBus::new());
+        let states = StateStore::new(bus);
+        let entity_id: EntityId = "counter.visits".parse().unwrap();
+        states.set(entity_id, "7", HashMap::new(), Context::new());
+
+        let config: HashMap<String, CounterConfig> =
+            serde_yaml::from_str("visits: {initial: 1}").unwrap();
+        load_counters(&config, &states, None);
+        assert_eq!(states.get_state("counter.visits").unwrap(), "7");
+
+        let config: HashMap<String, CounterConfig> =
+            serde_yaml::from_str("visits: {initial: 1, restore: false}").unwrap();
+        load_counters(&config, &states, None);
+        assert_eq!(states.get_state("counter.visits").unwrap(), "1");
+    }
+}
@@ -0,0 +1,319 @@
+//! Derivative Component
+//!
+//! Implements the `derivative` sensor platform: the rate of change of a
+//! source sensor, e.g. how fast a tank level or an energy meter rises, in
+//! source units per `unit_time`. With a `time_window` the rate is the
+//! time-weighted mean over that window instead of between the last two
+//! samples only.
+
+use chrono::{DateTime, Utc};
+use ha_core::{Context, EntityId, State, STATE_UNKNOWN};
+use ha_event_bus::helpers::track_state_change_event;
+use ha_event_bus::{EventBus, ListenerId};
+use ha_state_store::StateStore;
+use serde::{Deserialize, Deserializer};
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::integration::{object_id_of, UnitPrefix, UnitTime};
+use crate::min_max::{format_value, numeric_state};
+use crate::scene::slugify;
+use crate::timer::parse_duration;
+
+/// Default number of decimals of the rate
+const DEFAULT_ROUND_DIGITS: u32 = 2;
+
+/// Derivative sensor configuration from YAML
+#[derive(Debug, Clone, Deserialize)]
+pub struct DerivativeConfig {
+    /// Display name, also used for the entity_id
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub unique_id: Option<String>,
+    /// Source sensor
+    pub source: String,
+    #[serde(default)]
+    pub unit_prefix: Option<UnitPrefix>,
+    /// Time unit of the rate (default: h)
+    #[serde(default)]
+    pub unit_time: UnitTime,
+    /// Window the rate is averaged over, as seconds or `HH:MM:SS`
+    /// (default: between the last two samples)
+    #[serde(default, deserialize_with = "deserialize_time_window")]
+    pub time_window: Option<Duration>,
+    /// Decimals of the rate (default: 2)
+    #[serde(default)]
+    pub round: Option<u32>,
+}
+
+fn deserialize_time_window<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    parse_duration(&value)
+        .map(|window| Some(window).filter(|w| !w.is_zero()))
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid time_window: {}", value)))
+}
+
+/// Running state of a derivative sensor
+#[derive(Default)]
+struct Rate {
+    /// Last numeric sample, cleared when the source stops being numeric
+    last: Option<(DateTime<Utc>, f64)>,
+    /// Rates between consecutive samples as (start, end, rate), oldest first
+    rates: VecDeque<(DateTime<Utc>, DateTime<Utc>, f64)>,
+    /// `None` until the source reported two numeric samples in a row
+    value: Option<f64>,
+}
+
+impl Rate {
+    /// Add a sample and recompute the rate per second
+    fn add_sample(&mut self, time: DateTime<Utc>, value: f64, window: Option<Duration>) {
+        if let Some((last_time, last_value)) = self.last {
+            let seconds = (time - last_time).num_milliseconds() as f64 / 1000.0;
+            if seconds > 0.0 {
+                self.rates
+                    .push_back((last_time, time, (value - last_value) / seconds));
+            }
+        }
+        self.last = Some((time, value));
+
+        let Some(window) = window.and_then(|w| chrono::Duration::from_std(w).ok()) else {
+            self.rates.drain(..self.rates.len().saturating_sub(1));
+            self.value = self.rates.back().map(|(_, _, rate)| *rate);
+            return;
+        };
+
+        // Weigh each rate by how much of its interval lies in the window
+        let window_start = time - window;
+        self.rates.retain(|(_, end, _)| *end > window_start);
+        let (weighted, total) =
+            self.rates
+                .iter()
+                .fold((0.0, 0.0), |(weighted, total), (start, end, rate)| {
+                    let seconds =
+                        (*end - (*start).max(window_start)).num_milliseconds() as f64 / 1000.0;
+                    (weighted + rate * seconds, total + seconds)
+                });
+        if total > 0.0 {
+            self.value = Some(weighted / total);
+        }
+    }
+}
+
+/// A loaded derivative sensor
+struct DerivativeSensor {
+    entity_id: EntityId,
+    config: DerivativeConfig,
+    rate: Mutex<Rate>,
+}
+
+impl DerivativeSensor {
+    /// Take a new source state into account and write the sensor
+    fn update(&self, states: &StateStore, source: Option<&State>, context: Context) {
+        let value = {
+            let mut rate = self.rate.lock().unwrap();
+            match source.and_then(|s| Some((s.last_updated, numeric_state(s)?))) {
+                Some((time, value)) => rate.add_sample(time, value, self.config.time_window),
+                // The next numeric sample starts over, the rate is kept
+                None => rate.last = None,
+            }
+            rate.value
+        };
+
+        let mut attributes = HashMap::new();
+        attributes.insert("icon".to_string(), json!("mdi:chart-line"));
+        attributes.insert("state_class".to_string(), json!("measurement"));
+        attributes.insert("source".to_string(), json!(self.config.source));
+        if let Some(name) = &self.config.name {
+            attributes.insert("friendly_name".to_string(), json!(name));
+        }
+        if let Some(unit) = source
+            .and_then(|s| s.attributes.get("unit_of_measurement"))
+            .and_then(|u| u.as_str())
+        {
+            let prefix = self.config.unit_prefix.map_or("", UnitPrefix::symbol);
+            let unit = format!("{}{}/{}", prefix, unit, self.config.unit_time.symbol());
+            attributes.insert("unit_of_measurement".to_string(), json!(unit));
+        }
+
+        let scale = self.config.unit_time.seconds()
+            / self.config.unit_prefix.map_or(1.0, UnitPrefix::factor);
+        let state = value.map_or_else(
+            || STATE_UNKNOWN.to_string(),
+            |rate| {
+                format_value(
+                    rate * scale,
+                    self.config.round.unwrap_or(DEFAULT_ROUND_DIGITS),
+                )
+            },
+        );
+        states.set(self.entity_id.clone(), state, attributes, context);
+    }
+}
+
+/// Load derivative sensors from config and feed them their sources' states
+///
+/// A sensor takes its source's current state as the first sample and stays
+/// unknown until a second numeric sample arrives.
+pub fn load_derivative_sensors(
+    config: &[DerivativeConfig],
+    event_bus: &EventBus,
+    states: Arc<StateStore>,
+) -> ListenerId {
+    let mut by_source: HashMap<String, Vec<DerivativeSensor>> = HashMap::new();
+    let mut count = 0;
+
+    for config in config {
+        let mut config = config.clone();
+        config.source = config.source.trim().to_lowercase();
+        let object_id = config
+            .name
+            .as_deref()
+            .or(config.unique_id.as_deref())
+            .map(slugify)
+            .filter(|slug| !slug.is_empty())
+            .unwrap_or_else(|| format!("{}_derivative", object_id_of(&config.source)));
+        let entity_id = match EntityId::new("sensor", &object_id) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid derivative sensor '{}': {}", object_id, e);
+                continue;
+            }
+        };
+
+        let sensor = DerivativeSensor {
+            entity_id,
+            config,
+            rate: Mutex::new(Rate::default()),
+        };
+        let source = states.get(&sensor.config.source);
+        sensor.update(&states, source.as_ref(), Context::new());
+        debug!(
+            "Loaded derivative {} of {}",
+            sensor.entity_id, sensor.config.source
+        );
+        by_source
+            .entry(sensor.config.source.clone())
+            .or_default()
+            .push(sensor);
+        count += 1;
+    }
+
+    if count > 0 {
+        info!("Loaded {} derivative sensors", count);
+    }
+
+    let sources: Vec<String> = by_source.keys().cloned().collect();
+    track_state_change_event(
+        event_bus,
+        sources,
+        Arc::new(move |event| {
+            let Some(sensors) = by_source.get(event.data.entity_id.as_str()) else {
+                return;
+            };
+            for sensor in sensors {
+                sensor.update(
+                    &states,
+                    event.data.new_state.as_ref(),
+                    event.context.clone(),
+                );
+            }
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ha_core::events::StateChangedData;
+    use ha_core::STATE_UNAVAILABLE;
+
+    fn setup(yaml: &str) -> (Arc<EventBus>, Arc<StateStore>) {
+        let bus = Arc::new(EventBus::new());
+        let states = Arc::new(StateStore::new(bus.clone()));
+        let config: Vec<DerivativeConfig> = serde_yaml::from_str(yaml).unwrap();
+        load_derivative_sensors(&config, &bus, states.clone());
+        (bus, states)
+    }
+
+    /// Report a meter reading taken `minutes` after `start`
+    fn report(bus: &EventBus, start: DateTime<Utc>, minutes: i64, value: &str) {
+        let entity_id: EntityId = "sensor.meter".parse().unwrap();
+        let mut state = State::new(
+            entity_id.clone(),
+            value,
+            HashMap::from([("unit_of_measurement".to_string(), json!("Wh"))]),
+            Context::new(),
+        );
+        state.last_updated = start + chrono::Duration::minutes(minutes);
+        bus.fire_typed(
+            StateChangedData {
+                entity_id,
+                old_state: None,
+                new_state: Some(state),
+            },
+            Context::new(),
+        );
+    }
+
+    #[test]
+    fn test_rate_between_samples() {
+        let (bus, states) = setup(
+            r#"
+            - name: Meter Power
+              source: sensor.meter
+              unit_prefix: k
+              unit_time: h
+            "#,
+        );
+        let start = Utc::now();
+        // No rate from a single sample
+        report(&bus, start, 0, "1000");
+        assert_eq!(
+            states.get_state("sensor.meter_power").unwrap(),
+            STATE_UNKNOWN
+        );
+
+        // 500 Wh in 15 minutes is 2 kWh/h
+        report(&bus, start, 15, "1500");
+        let power = states.get("sensor.meter_power").unwrap();
+        assert_eq!(power.state, "2.0");
+        assert_eq!(power.attributes["unit_of_measurement"], "kWh/h");
+
+        // An unavailable source keeps the rate and restarts from the next sample
+        report(&bus, start, 30, STATE_UNAVAILABLE);
+        assert_eq!(states.get_state("sensor.meter_power").unwrap(), "2.0");
+        report(&bus, start, 120, "9000");
+        assert_eq!(states.get_state("sensor.meter_power").unwrap(), "2.0");
+        report(&bus, start, 150, "9250");
+        assert_eq!(states.get_state("sensor.meter_power").unwrap(), "0.5");
+    }
+
+    #[test]
+    fn test_rate_over_time_window() {
+        let (bus, states) = setup(
+            r#"
+            - source: sensor.meter
+              unit_time: min
+              time_window: "00:10:00"
+            "#,
+        );
+        let start = Utc::now();
+        for (minutes, value) in [(0, "0"), (5, "50"), (10, "150")] {
+            report(&bus, start, minutes, value);
+        }
+        // 10/min for 5 minutes, then 20/min for 5 minutes
+        assert_eq!(states.get_state("sensor.meter_derivative").unwrap(), "15.0");
+
+        // The first interval has left the window
+        report(&bus, start, 15, "200");
+        assert_eq!(states.get_state("sensor.meter_derivative").unwrap(), "15.0");
+        report(&bus, start, 20, "200");
+        assert_eq!(states.get_state("sensor.meter_derivative").unwrap(), "5.0");
+    }
+}
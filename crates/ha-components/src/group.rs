@@ -0,0 +1,484 @@
+//! Group Component
+//!
+//! Implements old-style `group:` entities that aggregate the states of their
+//! members. A group is "on" when any member is on (or every member, with
+//! `all: true`), using the on/off pair its members share, e.g. `home` /
+//! `not_home` for device trackers.
+
+use ha_core::{Context, EntityId, State, STATE_UNKNOWN};
+use ha_event_bus::helpers::{track_state_change_event, Throttle};
+use ha_event_bus::{EventBus, ListenerId};
+use ha_state_store::StateStore;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Known (on, off) state pairs, in lookup order
+const ON_OFF_STATES: &[(&str, &str)] = &[
+    ("on", "off"),
+    ("home", "not_home"),
+    ("open", "closed"),
+    ("unlocked", "locked"),
+    ("problem", "ok"),
+];
+
+/// Minimum time between recomputations of a group
+///
+/// A burst of member changes, e.g. a scene turning on every light, updates
+/// the group right away and then once more with the final member states.
+const UPDATE_WINDOW: Duration = Duration::from_millis(100);
+
+/// Group configuration from YAML
+#[derive(Debug, Clone, Deserialize)]
+pub struct GroupConfig {
+    /// Display name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Icon
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Member entity IDs
+    #[serde(default)]
+    pub entities: Vec<String>,
+    /// Only turn on when all members are on (default: any member)
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// A loaded group and its members
+struct Group {
+    entity_id: EntityId,
+    members: Vec<String>,
+    all: bool,
+    attributes: HashMap<String, serde_json::Value>,
+}
+
+impl Group {
+    /// Recompute the group state from its members and write it
+    fn update(&self, states: &StateStore, context: Context) {
+        let member_states: Vec<State> = self
+            .members
+            .iter()
+            .filter_map(|member| states.get(member))
+            .collect();
+        let state = aggregate_state(&member_states, self.all);
+        states.set(
+            self.entity_id.clone(),
+            state,
+            self.attributes.clone(),
+            context,
+        );
+    }
+}
+
+/// Compute a group's state from its member states
+///
+/// Unavailable and unknown members are ignored; a group without any known
+/// member state is `unknown`. Members that aren't on/off style keep their
+/// state if they all agree, otherwise the group is `unknown`.
+fn aggregate_state(members: &[State], all: bool) -> String {
+    let known: Vec<&str> = members
+        .iter()
+        .filter(|s| s.is_available())
+        .map(|s| s.state.as_str())
+        .collect();
+    let Some(first) = known.first() else {
+        return STATE_UNKNOWN.to_string();
+    };
+
+    let is_on = |state: &str| ON_OFF_STATES.iter().any(|(on, _)| *on == state);
+    let pair = ON_OFF_STATES
+        .iter()
+        .find(|(on, off)| first == on || first == off);
+    let Some(&(on_state, off_state)) = pair else {
+        return if known.iter().all(|s| s == first) {
+            first.to_string()
+        } else {
+            STATE_UNKNOWN.to_string()
+        };
+    };
+
+    // Members mixing different on/off pairs report plain on/off
+    let (on_state, off_state) = if known.iter().all(|s| *s == on_state || *s == off_state) {
+        (on_state, off_state)
+    } else {
+        ON_OFF_STATES[0]
+    };
+
+    let on = if all {
+        known.iter().all(|s| is_on(s))
+    } else {
+        known.iter().any(|s| is_on(s))
+    };
+    if on { on_state } else { off_state }.to_string()
+}
+
+/// Whether a group is one of its own members, directly or through nested
+/// groups
+///
+/// Such a group's state would depend on itself, so it can't be computed.
+fn contains_itself(entity_id: &str, members_of: &HashMap<&str, &Vec<String>>) -> bool {
+    let mut pending: Vec<&str> = members_of[entity_id].iter().map(String::as_str).collect();
+    let mut seen = HashSet::new();
+    while let Some(member) = pending.pop() {
+        if member == entity_id {
+            return true;
+        }
+        if seen.insert(member) {
+            if let Some(nested) = members_of.get(member) {
+                pending.extend(nested.iter().map(String::as_str));
+            }
+        }
+    }
+    false
+}
+
+/// Indexes of `groups` ordered so nested groups come before the groups
+/// containing them
+fn dependency_order(groups: &[Arc<Group>]) -> Vec<usize> {
+    fn visit(
+        index: usize,
+        groups: &[Arc<Group>],
+        index_of: &HashMap<&str, usize>,
+        visited: &mut HashSet<usize>,
+        order: &mut Vec<usize>,
+    ) {
+        if !visited.insert(index) {
+            return;
+        }
+        for member in &groups[index].members {
+            if let Some(&nested) = index_of.get(member.as_str()) {
+                visit(nested, groups, index_of, visited, order);
+            }
+        }
+        order.push(index);
+    }
+
+    let index_of: HashMap<&str, usize> = groups
+        .iter()
+        .enumerate()
+        .map(|(index, group)| (group.entity_id.as_str(), index))
+        .collect();
+    let mut visited = HashSet::new();
+    let mut order = Vec::with_capacity(groups.len());
+    for index in 0..groups.len() {
+        visit(index, groups, &index_of, &mut visited, &mut order);
+    }
+    order
+}
+
+/// Load group entities from config and keep them in sync with their members
+///
+/// Each group gets an `entity_id` attribute listing its members, which is
+/// what `expand()` uses to flatten groups in templates. Returns the listener
+/// that recomputes groups on member state changes, throttled to once per
+/// `UPDATE_WINDOW`, so this must be called from within a tokio runtime.
+///
+/// The listener is registered before the initial states are written, and
+/// nested groups are written before the groups containing them, so no
+/// group starts out from a member state that has since changed.
+pub fn load_groups(
+    config: &HashMap<String, GroupConfig>,
+    event_bus: &EventBus,
+    states: Arc<StateStore>,
+) -> ListenerId {
+    let mut members_of = HashMap::new();
+    for (id, config) in config {
+        let entity_id = match EntityId::new("group", id) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid group id '{}': {}", id, e);
+                continue;
+            }
+        };
+        let members: Vec<String> = config
+            .entities
+            .iter()
+            .map(|member| member.trim().to_lowercase())
+            .collect();
+        members_of.insert(entity_id, (config, members));
+    }
+    let nested: HashMap<&str, &Vec<String>> = members_of
+        .iter()
+        .map(|(entity_id, (_, members))| (entity_id.as_str(), members))
+        .collect();
+
+    let mut groups = Vec::new();
+    for (entity_id, (config, members)) in &members_of {
+        if contains_itself(entity_id.as_str(), &nested) {
+            warn!(
+                "Group {} contains itself through its members, not loading it",
+                entity_id
+            );
+            continue;
+        }
+        let entity_id = entity_id.clone();
+        let members = members.clone();
+
+        let mut attributes = HashMap::new();
+        attributes.insert("entity_id".to_string(), json!(members));
+        if let Some(name) = &config.name {
+            attributes.insert("friendly_name".to_string(), json!(name));
+        }
+        if let Some(icon) = &config.icon {
+            attributes.insert("icon".to_string(), json!(icon));
+        }
+
+        let group = Group {
+            entity_id,
+            members,
+            all: config.all,
+            attributes,
+        };
+        debug!(
+            "Loaded {} with {} members",
+            group.entity_id,
+            group.members.len()
+        );
+        groups.push(Arc::new(group));
+    }
+
+    if !groups.is_empty() {
+        info!("Loaded {} group entities", groups.len());
+    }
+
+    // Index groups by member so a state change only touches its groups
+    let mut by_member: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, group) in groups.iter().enumerate() {
+        for member in &group.members {
+            by_member.entry(member.clone()).or_default().push(index);
+        }
+    }
+    let updaters: Vec<Throttle<Context>> = groups
+        .iter()
+        .map(|group| {
+            let group = group.clone();
+            let states = states.clone();
+            Throttle::new(UPDATE_WINDOW, move |context| group.update(&states, context))
+        })
+        .collect();
+
+    let members: Vec<String> = by_member.keys().cloned().collect();
+    let listener = track_state_change_event(
+        event_bus,
+        members,
+        Arc::new(move |event| {
+            let Some(indexes) = by_member.get(event.data.entity_id.as_str()) else {
+                return;
+            };
+            for &index in indexes {
+                updaters[index].call(event.context.clone());
+            }
+        }),
+    );
+
+    for index in dependency_order(&groups) {
+        groups[index].update(&states, Context::new());
+    }
+    listener
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(yaml: &str) -> Arc<StateStore> {
+        setup_with_bus(yaml).1
+    }
+
+    fn setup_with_bus(yaml: &str) -> (Arc<EventBus>, Arc<StateStore>) {
+        let bus = Arc::new(EventBus::new());
+        let states = Arc::new(StateStore::new(bus.clone()));
+        for member in ["light.kitchen", "light.porch"] {
+            set(&states, member, "off");
+        }
+        let config: HashMap<String, GroupConfig> = serde_yaml::from_str(yaml).unwrap();
+        load_groups(&config, &bus, states.clone());
+        (bus, states)
+    }
+
+    fn set(states: &StateStore, entity_id: &str, state: &str) {
+        let entity_id: EntityId = entity_id.parse().unwrap();
+        states.set(entity_id, state, HashMap::new(), Context::new());
+    }
+
+    /// Set a member state and wait out the group's update window
+    async fn set_and_settle(states: &StateStore, entity_id: &str, state: &str) {
+        set(states, entity_id, state);
+        tokio::time::sleep(UPDATE_WINDOW * 2).await;
+    }
+
+    #[tokio::test]
+    async fn test_group_follows_any_member() {
+        let states = setup(
+            r#"
+            lights:
+              name: All Lights
+              entities: [light.kitchen, light.porch]
+            "#,
+        );
+        let group = states.get("group.lights").unwrap();
+        assert_eq!(group.state, "off");
+        assert_eq!(
+            group.attributes["entity_id"],
+            json!(["light.kitchen", "light.porch"])
+        );
+
+        set_and_settle(&states, "light.porch", "on").await;
+        assert_eq!(states.get_state("group.lights").unwrap(), "on");
+
+        set_and_settle(&states, "light.porch", "off").await;
+        assert_eq!(states.get_state("group.lights").unwrap(), "off");
+    }
+
+    #[tokio::test]
+    async fn test_group_with_all_needs_every_member() {
+        let states = setup(
+            r#"
+            lights:
+              entities: [light.kitchen, light.porch]
+              all: true
+            "#,
+        );
+
+        set_and_settle(&states, "light.kitchen", "on").await;
+        assert_eq!(states.get_state("group.lights").unwrap(), "off");
+
+        set_and_settle(&states, "light.porch", "on").await;
+        assert_eq!(states.get_state("group.lights").unwrap(), "on");
+    }
+
+    #[tokio::test]
+    async fn test_group_coalesces_member_bursts() {
+        use ha_core::events::{STATE_CHANGED, STATE_REPORTED};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let (bus, states) = setup_with_bus(
+            r#"
+            lights:
+              entities: [light.kitchen, light.porch]
+            "#,
+        );
+        // Count writes of the group state, changed or not
+        let updates = Arc::new(AtomicUsize::new(0));
+        for event_type in [STATE_CHANGED, STATE_REPORTED] {
+            let counter = updates.clone();
+            bus.listen_sync(
+                event_type,
+                Arc::new(move |event| {
+                    if event.data["entity_id"] == "group.lights" {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                    }
+                }),
+            );
+        }
+
+        for _ in 0..5 {
+            set(&states, "light.kitchen", "on");
+            set(&states, "light.kitchen", "off");
+        }
+        set(&states, "light.porch", "on");
+        // The first change applies right away, the rest once the window ends
+        assert_eq!(updates.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(UPDATE_WINDOW * 2).await;
+        assert_eq!(states.get_state("group.lights").unwrap(), "on");
+        assert_eq!(updates.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_group_ignores_unavailable_members() {
+        let states = setup(
+            r#"
+            lights:
+              entities: [light.kitchen, light.porch]
+              all: true
+            "#,
+        );
+
+        set(&states, "light.kitchen", "on");
+        set_and_settle(&states, "light.porch", "unavailable").await;
+        assert_eq!(states.get_state("group.lights").unwrap(), "on");
+
+        set_and_settle(&states, "light.kitchen", "unavailable").await;
+        assert_eq!(states.get_state("group.lights").unwrap(), STATE_UNKNOWN);
+    }
+
+    #[tokio::test]
+    async fn test_groups_containing_themselves_are_rejected() {
+        let states = setup(
+            r#"
+            lights:
+              entities: [light.kitchen, light.porch]
+            itself:
+              entities: [light.kitchen, group.itself]
+            upstairs:
+              entities: [group.downstairs, group.lights]
+            downstairs:
+              entities: [group.upstairs]
+            "#,
+        );
+        assert!(states.get("group.lights").is_some());
+        assert!(states.get("group.itself").is_none());
+        assert!(states.get("group.upstairs").is_none());
+        assert!(states.get("group.downstairs").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_nested_groups_start_from_their_members() {
+        let states = setup(
+            r#"
+            house:
+              entities: [group.upstairs]
+            upstairs:
+              entities: [group.bedroom]
+            bedroom:
+              entities: [group.lamps]
+            lamps:
+              entities: [light.kitchen]
+            "#,
+        );
+        for group in ["group.house", "group.upstairs", "group.bedroom"] {
+            assert_eq!(states.get_state(group).unwrap(), "off");
+        }
+    }
+
+    #[test]
+    fn test_dependency_order_puts_nested_groups_first() {
+        let group = |entity_id: &str, members: &[&str]| {
+            Arc::new(Group {
+                entity_id: entity_id.parse().unwrap(),
+                members: members.iter().map(|m| m.to_string()).collect(),
+                all: false,
+                attributes: HashMap::new(),
+            })
+        };
+        let groups = [
+            group("group.house", &["group.upstairs", "light.hall"]),
+            group("group.upstairs", &["group.bedroom"]),
+            group("group.bedroom", &["light.lamp"]),
+        ];
+        assert_eq!(dependency_order(&groups), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_aggregate_state_uses_member_pair() {
+        let home = State::new(
+            "device_tracker.phone".parse().unwrap(),
+            "home",
+            HashMap::new(),
+            Context::new(),
+        );
+        let away = State::new(
+            "device_tracker.tablet".parse().unwrap(),
+            "not_home",
+            HashMap::new(),
+            Context::new(),
+        );
+        assert_eq!(aggregate_state(&[home, away.clone()], false), "home");
+        assert_eq!(aggregate_state(&[away], false), "not_home");
+    }
+}
@@ -1,17 +1,21 @@
 //! Input Helper Components
 //!
-//! Implements input_boolean and input_number components for user-controlled
-//! state in automations.
+//! Implements input_boolean, input_number, input_select, input_text and
+//! input_datetime components for user-controlled state in automations.
 
-use ha_core::{Context, EntityId, ServiceCall, SupportsResponse};
-use ha_service_registry::{ServiceDescription, ServiceRegistry};
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike};
+use ha_core::{Context, EntityId, ServiceCall, SupportsResponse, STATE_UNKNOWN};
+use ha_service_registry::{ServiceDescription, ServiceError, ServiceRegistry};
 use ha_state_store::StateStore;
+use regex::Regex;
 use serde::Deserialize;
 use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::{debug, info, warn};
 
+use crate::restore_state::{restored_state, RestoreStateStore};
+
 // =============================================================================
 // Input Boolean
 // =============================================================================
@@ -28,12 +32,20 @@ pub struct InputBooleanConfig {
     /// Initial state (default: false/off)
     #[serde(default)]
     pub initial: Option<bool>,
+    /// Keep the last value instead of resetting to `initial` (default: true)
+    #[serde(default = "default_restore")]
+    pub restore: bool,
+}
+
+pub(crate) fn default_restore() -> bool {
+    true
 }
 
 /// Load input_boolean entities from config and register them in the state machine
 pub fn load_input_booleans(
     config: &HashMap<String, Option<InputBooleanConfig>>,
     states: &StateStore,
+    restore: Option<&RestoreStateStore>,
 ) -> usize {
     let mut count = 0;
 
@@ -50,12 +62,18 @@ pub fn load_input_booleans(
             name: None,
             icon: None,
             initial: None,
+            restore: true,
         });
 
-        let state = if config.initial.unwrap_or(false) {
-            "on"
-        } else {
-            "off"
+        let restored = config
+            .restore
+            .then(|| restored_state(restore, states, &entity_id, config.initial.is_some()))
+            .flatten()
+            .filter(|state| state == "on" || state == "off");
+        let state = match restored.as_deref() {
+            Some(state) => state,
+            None if config.initial.unwrap_or(false) => "on",
+            None => "off",
         };
 
         let mut attributes = HashMap::new();
@@ -98,7 +116,7 @@ pub fn register_input_boolean_services(services: &ServiceRegistry, states: Arc<S
             let states = states_clone.clone();
             async move {
                 for entity_id in get_target_entities(&call, "input_boolean") {
-                    if let Some(current) = states.get(&entity_id.to_string()) {
+                    if let Some(current) = states.get(entity_id.as_str()) {
                         let attrs = current.attributes.clone();
                         states.set(entity_id, "on", attrs, call.context.clone());
                     }
@@ -124,7 +142,7 @@ pub fn register_input_boolean_services(services: &ServiceRegistry, states: Arc<S
             let states = states_clone.clone();
             async move {
                 for entity_id in get_target_entities(&call, "input_boolean") {
-                    if let Some(current) = states.get(&entity_id.to_string()) {
+                    if let Some(current) = states.get(entity_id.as_str()) {
                         let attrs = current.attributes.clone();
                         states.set(entity_id, "off", attrs, call.context.clone());
                     }
@@ -150,7 +168,7 @@ pub fn register_input_boolean_services(services: &ServiceRegistry, states: Arc<S
             let states = states_clone.clone();
             async move {
                 for entity_id in get_target_entities(&call, "input_boolean") {
-                    if let Some(current) = states.get(&entity_id.to_string()) {
+                    if let Some(current) = states.get(entity_id.as_str()) {
                         let new_state = if current.state == "on" { "off" } else { "on" };
                         let attrs = current.attributes.clone();
                         states.set(entity_id, new_state, attrs, call.context.clone());
@@ -193,6 +211,9 @@ pub struct InputNumberConfig {
     /// Display mode (slider or box)
     #[serde(default = "default_mode")]
     pub mode: String,
+    /// Keep the last value instead of resetting to `initial` (default: true)
+    #[serde(default = "default_restore")]
+    pub restore: bool,
 }
 
 fn default_step() -> f64 {
@@ -207,6 +228,7 @@ fn default_mode() -> String {
 pub fn load_input_numbers(
     config: &HashMap<String, InputNumberConfig>,
     states: &StateStore,
+    restore: Option<&RestoreStateStore>,
 ) -> usize {
     let mut count = 0;
 
@@ -229,7 +251,12 @@ pub fn load_input_numbers(
         }
 
         // Determine initial value
-        let initial = config.initial.unwrap_or(config.min);
+        let restored = config
+            .restore
+            .then(|| restored_state(restore, states, &entity_id, config.initial.is_some()))
+            .flatten()
+            .and_then(|state| state.parse::<f64>().ok());
+        let initial = restored.or(config.initial).unwrap_or(config.min);
         let value = initial.clamp(config.min, config.max);
 
         let mut attributes = HashMap::new();
@@ -301,7 +328,7 @@ pub fn register_input_number_services(services: &ServiceRegistry, states: Arc<St
                     .unwrap_or(0.0);
 
                 for entity_id in get_target_entities(&call, "input_number") {
-                    if let Some(current) = states.get(&entity_id.to_string()) {
+                    if let Some(current) = states.get(entity_id.as_str()) {
                         let min = current
                             .attributes
                             .get("min")
@@ -344,7 +371,7 @@ pub fn register_input_number_services(services: &ServiceRegistry, states: Arc<St
             let states = states_clone.clone();
             async move {
                 for entity_id in get_target_entities(&call, "input_number") {
-                    if let Some(current) = states.get(&entity_id.to_string()) {
+                    if let Some(current) = states.get(entity_id.as_str()) {
                         let value: f64 = current.state.parse().unwrap_or(0.0);
                         let step = current
                             .attributes
@@ -388,7 +415,7 @@ pub fn register_input_number_services(services: &ServiceRegistry, states: Arc<St
             let states = states_clone.clone();
             async move {
                 for entity_id in get_target_entities(&call, "input_number") {
-                    if let Some(current) = states.get(&entity_id.to_string()) {
+                    if let Some(current) = states.get(entity_id.as_str()) {
                         let value: f64 = current.state.parse().unwrap_or(0.0);
                         let step = current
                             .attributes
@@ -419,12 +446,787 @@ pub fn register_input_number_services(services: &ServiceRegistry, states: Arc<St
     info!("Input number services registered");
 }
 
+// =============================================================================
+// Input Select
+// =============================================================================
+
+/// Input select configuration from YAML
+#[derive(Debug, Clone, Deserialize)]
+pub struct InputSelectConfig {
+    /// Display name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Icon
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Selectable options
+    pub options: Vec<String>,
+    /// Initial option (default: the first option)
+    #[serde(default)]
+    pub initial: Option<String>,
+    /// Keep the last value instead of resetting to `initial` (default: true)
+    #[serde(default = "default_restore")]
+    pub restore: bool,
+}
+
+/// Load input_select entities from config and register them in the state machine
+pub fn load_input_selects(
+    config: &HashMap<String, InputSelectConfig>,
+    states: &StateStore,
+    restore: Option<&RestoreStateStore>,
+) -> usize {
+    let mut count = 0;
+
+    for (id, config) in config {
+        let entity_id = match EntityId::new("input_select", id) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid input_select id '{}': {}", id, e);
+                continue;
+            }
+        };
+
+        let Some(first) = config.options.first() else {
+            warn!("input_select.{}: options must not be empty", id);
+            continue;
+        };
+
+        let restored = config
+            .restore
+            .then(|| restored_state(restore, states, &entity_id, config.initial.is_some()))
+            .flatten()
+            .filter(|state| config.options.contains(state));
+        let initial = match &config.initial {
+            Some(initial) if config.options.contains(initial) => initial,
+            Some(initial) => {
+                warn!(
+                    "input_select.{}: initial option '{}' is not in options",
+                    id, initial
+                );
+                first
+            }
+            None => first,
+        };
+        let state = restored.as_ref().unwrap_or(initial);
+
+        let mut attributes = HashMap::new();
+        if let Some(name) = &config.name {
+            attributes.insert("friendly_name".to_string(), json!(name));
+        }
+        if let Some(icon) = &config.icon {
+            attributes.insert("icon".to_string(), json!(icon));
+        }
+        attributes.insert("options".to_string(), json!(config.options));
+        attributes.insert("editable".to_string(), json!(false));
+
+        states.set(entity_id.clone(), state, attributes, Context::new());
+        debug!("Loaded input_select.{} = {}", id, state);
+        count += 1;
+    }
+
+    if count > 0 {
+        info!("Loaded {} input_select entities", count);
+    }
+    count
+}
+
+/// Get the options of an input_select state
+fn select_options(attributes: &HashMap<String, serde_json::Value>) -> Vec<String> {
+    attributes
+        .get("options")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Select the option `offset` steps away from the current one
+///
+/// With `cycle` the selection wraps around at either end, otherwise it stops
+/// at the first/last option.
+fn step_option(options: &[String], current: &str, offset: isize, cycle: bool) -> Option<String> {
+    let len = options.len() as isize;
+    if len == 0 {
+        return None;
+    }
+    let index = options.iter().position(|o| o == current).unwrap_or(0) as isize;
+    let next = if cycle {
+        (index + offset).rem_euclid(len)
+    } else {
+        (index + offset).clamp(0, len - 1)
+    };
+    options.get(next as usize).cloned()
+}
+
+/// Register a select_next/select_previous style service
+fn register_input_select_step(
+    services: &ServiceRegistry,
+    states: Arc<StateStore>,
+    service: &str,
+    name: &str,
+    offset: isize,
+) {
+    services.register_with_description(
+        ServiceDescription {
+            domain: "input_select".to_string(),
+            service: service.to_string(),
+            name: Some(name.to_string()),
+            description: Some(format!("{} of an input select", name)),
+            schema: Some(json!({
+                "cycle": {"required": false, "default": true, "selector": {"boolean": {}}}
+            })),
+            target: Some(json!({"entity": {"domain": "input_select"}})),
+            supports_response: SupportsResponse::None,
+        },
+        move |call: ServiceCall| {
+            let states = states.clone();
+            async move {
+                let cycle = call
+                    .service_data
+                    .get("cycle")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(true);
+
+                for entity_id in get_target_entities(&call, "input_select") {
+                    if let Some(current) = states.get(entity_id.as_str()) {
+                        let options = select_options(&current.attributes);
+                        if let Some(option) = step_option(&options, &current.state, offset, cycle) {
+                            let attrs = current.attributes.clone();
+                            states.set(entity_id, option, attrs, call.context.clone());
+                        }
+                    }
+                }
+                Ok(None)
+            }
+        },
+    );
+}
+
+/// Register input_select services
+pub fn register_input_select_services(services: &ServiceRegistry, states: Arc<StateStore>) {
+    const DOMAIN: &str = "input_select";
+
+    // select_option service
+    let states_clone = states.clone();
+    services.register_with_description(
+        ServiceDescription {
+            domain: DOMAIN.to_string(),
+            service: "select_option".to_string(),
+            name: Some("Select".to_string()),
+            description: Some("Select an option of an input select".to_string()),
+            schema: Some(json!({
+                "option": {"required": true, "selector": {"text": {}}}
+            })),
+            target: Some(json!({"entity": {"domain": "input_select"}})),
+            supports_response: SupportsResponse::None,
+        },
+        move |call: ServiceCall| {
+            let states = states_clone.clone();
+            async move {
+                let option = call
+                    .service_data
+                    .get("option")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| ServiceError::InvalidData("option is required".to_string()))?
+                    .to_string();
+
+                for entity_id in get_target_entities(&call, "input_select") {
+                    if let Some(current) = states.get(entity_id.as_str()) {
+                        let options = select_options(&current.attributes);
+                        if !options.contains(&option) {
+                            return Err(ServiceError::InvalidData(format!(
+                                "Invalid option '{}' for {} (possible options: {})",
+                                option,
+                                entity_id,
+                                options.join(", ")
+                            )));
+                        }
+                        let attrs = current.attributes.clone();
+                        states.set(entity_id, option.as_str(), attrs, call.context.clone());
+                    }
+                }
+                Ok(None)
+            }
+        },
+    );
+
+    register_input_select_step(services, states.clone(), "select_next", "Next", 1);
+    register_input_select_step(services, states.clone(), "select_previous", "Previous", -1);
+
+    // set_options service
+    let states_clone = states.clone();
+    services.register_with_description(
+        ServiceDescription {
+            domain: DOMAIN.to_string(),
+            service: "set_options".to_string(),
+            name: Some("Set options".to_string()),
+            description: Some("Set the options of an input select".to_string()),
+            schema: Some(json!({
+                "options": {"required": true, "selector": {"text": {"multiple": true}}}
+            })),
+            target: Some(json!({"entity": {"domain": "input_select"}})),
+            supports_response: SupportsResponse::None,
+        },
+        move |call: ServiceCall| {
+            let states = states_clone.clone();
+            async move {
+                let options: Vec<String> = call
+                    .service_data
+                    .get("options")
+                    .and_then(|v| serde_json::from_value(v.clone()).ok())
+                    .ok_or_else(|| {
+                        ServiceError::InvalidData("options must be a list of strings".to_string())
+                    })?;
+                if options.is_empty() {
+                    return Err(ServiceError::InvalidData(
+                        "options must not be empty".to_string(),
+                    ));
+                }
+
+                for entity_id in get_target_entities(&call, "input_select") {
+                    if let Some(current) = states.get(entity_id.as_str()) {
+                        // Keep the selection if it's still an option
+                        let state = if options.contains(&current.state) {
+                            current.state.clone()
+                        } else {
+                            options[0].clone()
+                        };
+                        let mut attrs = current.attributes.clone();
+                        attrs.insert("options".to_string(), json!(options));
+                        states.set(entity_id, state, attrs, call.context.clone());
+                    }
+                }
+                Ok(None)
+            }
+        },
+    );
+
+    info!("Input select services registered");
+}
+
+// =============================================================================
+// Input Text
+// =============================================================================
+
+/// Input text configuration from YAML
+#[derive(Debug, Clone, Deserialize)]
+pub struct InputTextConfig {
+    /// Display name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Icon
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Minimum length (default: 0)
+    #[serde(default)]
+    pub min: usize,
+    /// Maximum length (default: 100)
+    #[serde(default = "default_text_max")]
+    pub max: usize,
+    /// Regex the value has to match
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// Display mode (text or password)
+    #[serde(default = "default_text_mode")]
+    pub mode: String,
+    /// Initial value
+    #[serde(default)]
+    pub initial: Option<String>,
+    /// Keep the last value instead of resetting to `initial` (default: true)
+    #[serde(default = "default_restore")]
+    pub restore: bool,
+}
+
+fn default_text_max() -> usize {
+    100
+}
+
+fn default_text_mode() -> String {
+    "text".to_string()
+}
+
+/// Check a value against an input_text's length bounds and pattern
+///
+/// Like HA, the pattern only has to match at the start of the value.
+fn validate_text(
+    value: &str,
+    min: usize,
+    max: usize,
+    pattern: Option<&str>,
+) -> Result<(), ServiceError> {
+    let len = value.chars().count();
+    if len < min || len > max {
+        return Err(ServiceError::InvalidData(format!(
+            "Value must be between {} and {} characters long, got {}",
+            min, max, len
+        )));
+    }
+    if let Some(pattern) = pattern {
+        let regex = Regex::new(&format!("^(?:{})", pattern))
+            .map_err(|e| ServiceError::InvalidData(format!("Invalid pattern: {}", e)))?;
+        if !regex.is_match(value) {
+            return Err(ServiceError::InvalidData(format!(
+                "Value '{}' does not match pattern {}",
+                value, pattern
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Load input_text entities from config and register them in the state machine
+pub fn load_input_texts(
+    config: &HashMap<String, InputTextConfig>,
+    states: &StateStore,
+    restore: Option<&RestoreStateStore>,
+) -> usize {
+    let mut count = 0;
+
+    for (id, config) in config {
+        let entity_id = match EntityId::new("input_text", id) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid input_text id '{}': {}", id, e);
+                continue;
+            }
+        };
+
+        if config.min > config.max {
+            warn!(
+                "input_text.{}: min ({}) must not be greater than max ({})",
+                id, config.min, config.max
+            );
+            continue;
+        }
+        if let Some(pattern) = &config.pattern {
+            if let Err(e) = Regex::new(pattern) {
+                warn!("input_text.{}: invalid pattern: {}", id, e);
+                continue;
+            }
+        }
+
+        let restored = config
+            .restore
+            .then(|| restored_state(restore, states, &entity_id, config.initial.is_some()))
+            .flatten()
+            .filter(|state| {
+                validate_text(state, config.min, config.max, config.pattern.as_deref()).is_ok()
+            });
+        let initial = match &config.initial {
+            Some(initial) => {
+                match validate_text(initial, config.min, config.max, config.pattern.as_deref()) {
+                    Ok(()) => initial.clone(),
+                    Err(e) => {
+                        warn!("input_text.{}: invalid initial value: {}", id, e);
+                        String::new()
+                    }
+                }
+            }
+            None => String::new(),
+        };
+        let value = restored.unwrap_or(initial);
+
+        let mut attributes = HashMap::new();
+        if let Some(name) = &config.name {
+            attributes.insert("friendly_name".to_string(), json!(name));
+        }
+        if let Some(icon) = &config.icon {
+            attributes.insert("icon".to_string(), json!(icon));
+        }
+        attributes.insert("min".to_string(), json!(config.min));
+        attributes.insert("max".to_string(), json!(config.max));
+        attributes.insert("pattern".to_string(), json!(config.pattern));
+        attributes.insert("mode".to_string(), json!(config.mode));
+        attributes.insert("editable".to_string(), json!(false));
+
+        states.set(entity_id.clone(), &value, attributes, Context::new());
+        debug!("Loaded input_text.{}", id);
+        count += 1;
+    }
+
+    if count > 0 {
+        info!("Loaded {} input_text entities", count);
+    }
+    count
+}
+
+/// Register input_text services
+pub fn register_input_text_services(services: &ServiceRegistry, states: Arc<StateStore>) {
+    const DOMAIN: &str = "input_text";
+
+    // set_value service
+    let states_clone = states.clone();
+    services.register_with_description(
+        ServiceDescription {
+            domain: DOMAIN.to_string(),
+            service: "set_value".to_string(),
+            name: Some("Set value".to_string()),
+            description: Some("Set the value of an input text".to_string()),
+            schema: Some(json!({
+                "value": {"required": true, "selector": {"text": {}}}
+            })),
+            target: Some(json!({"entity": {"domain": "input_text"}})),
+            supports_response: SupportsResponse::None,
+        },
+        move |call: ServiceCall| {
+            let states = states_clone.clone();
+            async move {
+                let value = call
+                    .service_data
+                    .get("value")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| ServiceError::InvalidData("value is required".to_string()))?
+                    .to_string();
+
+                for entity_id in get_target_entities(&call, "input_text") {
+                    if let Some(current) = states.get(entity_id.as_str()) {
+                        let min = current
+                            .attributes
+                            .get("min")
+                            .and_then(|v| v.as_u64())
+                            .unwrap_or(0) as usize;
+                        let max = current
+                            .attributes
+                            .get("max")
+                            .and_then(|v| v.as_u64())
+                            .unwrap_or(100) as usize;
+                        let pattern = current.attributes.get("pattern").and_then(|v| v.as_str());
+
+                        validate_text(&value, min, max, pattern)?;
+                        let attrs = current.attributes.clone();
+                        states.set(entity_id, value.as_str(), attrs, call.context.clone());
+                    }
+                }
+                Ok(None)
+            }
+        },
+    );
+
+    info!("Input text services registered");
+}
+
+// =============================================================================
+// Input Datetime
+// =============================================================================
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMAT: &str = "%H:%M:%S";
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Input datetime configuration from YAML
+#[derive(Debug, Clone, Deserialize)]
+pub struct InputDatetimeConfig {
+    /// Display name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Icon
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Whether the entity holds a date
+    #[serde(default)]
+    pub has_date: bool,
+    /// Whether the entity holds a time
+    #[serde(default)]
+    pub has_time: bool,
+    /// Initial value, formatted like the state (date, time or both)
+    #[serde(default)]
+    pub initial: Option<String>,
+    /// Keep the last value instead of resetting to `initial` (default: true)
+    #[serde(default = "default_restore")]
+    pub restore: bool,
+}
+
+/// Parse a date (`YYYY-MM-DD`)
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.trim(), DATE_FORMAT).ok()
+}
+
+/// Parse a time (`HH:MM` or `HH:MM:SS`)
+pub(crate) fn parse_time(value: &str) -> Option<NaiveTime> {
+    let value = value.trim();
+    NaiveTime::parse_from_str(value, TIME_FORMAT)
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+        .ok()
+}
+
+/// Parse a datetime (`YYYY-MM-DD HH:MM:SS`, also with a `T` separator)
+fn parse_datetime(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim().replacen('T', " ", 1);
+    NaiveDateTime::parse_from_str(&value, DATETIME_FORMAT)
+        .or_else(|_| NaiveDateTime::parse_from_str(&value, "%Y-%m-%d %H:%M"))
+        .ok()
+}
+
+/// Parse a state value for an entity with the given date/time parts
+fn parse_datetime_state(
+    value: &str,
+    has_date: bool,
+    has_time: bool,
+) -> Option<(Option<NaiveDate>, Option<NaiveTime>)> {
+    match (has_date, has_time) {
+        (true, true) => parse_datetime(value).map(|dt| (Some(dt.date()), Some(dt.time()))),
+        (true, false) => parse_date(value).map(|d| (Some(d), None)),
+        _ => parse_time(value).map(|t| (None, Some(t))),
+    }
+}
+
+/// Build the state value and attributes of an input_datetime
+///
+/// `timestamp` is the local Unix timestamp when there is a date, or the
+/// seconds since midnight for time-only entities.
+fn datetime_state(
+    date: Option<NaiveDate>,
+    time: Option<NaiveTime>,
+    attributes: &mut HashMap<String, serde_json::Value>,
+) -> String {
+    for key in [
+        "year",
+        "month",
+        "day",
+        "hour",
+        "minute",
+        "second",
+        "timestamp",
+    ] {
+        attributes.remove(key);
+    }
+
+    if let Some(date) = date {
+        attributes.insert("year".to_string(), json!(date.year()));
+        attributes.insert("month".to_string(), json!(date.month()));
+        attributes.insert("day".to_string(), json!(date.day()));
+    }
+    if let Some(time) = time {
+        attributes.insert("hour".to_string(), json!(time.hour()));
+        attributes.insert("minute".to_string(), json!(time.minute()));
+        attributes.insert("second".to_string(), json!(time.second()));
+    }
+
+    match (date, time) {
+        (Some(date), time) => {
+            let naive = date.and_time(time.unwrap_or(NaiveTime::MIN));
+            let timestamp = Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|dt| dt.timestamp())
+                .unwrap_or_else(|| naive.and_utc().timestamp());
+            attributes.insert("timestamp".to_string(), json!(timestamp));
+            match time {
+                Some(_) => naive.format(DATETIME_FORMAT).to_string(),
+                None => date.format(DATE_FORMAT).to_string(),
+            }
+        }
+        (None, Some(time)) => {
+            attributes.insert(
+                "timestamp".to_string(),
+                json!(time.num_seconds_from_midnight()),
+            );
+            time.format(TIME_FORMAT).to_string()
+        }
+        (None, None) => STATE_UNKNOWN.to_string(),
+    }
+}
+
+/// Load input_datetime entities from config and register them in the state machine
+pub fn load_input_datetimes(
+    config: &HashMap<String, InputDatetimeConfig>,
+    states: &StateStore,
+    restore: Option<&RestoreStateStore>,
+) -> usize {
+    let mut count = 0;
+
+    for (id, config) in config {
+        let entity_id = match EntityId::new("input_datetime", id) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid input_datetime id '{}': {}", id, e);
+                continue;
+            }
+        };
+
+        if !config.has_date && !config.has_time {
+            warn!(
+                "input_datetime.{}: at least one of has_date or has_time is required",
+                id
+            );
+            continue;
+        }
+
+        let default = (
+            config
+                .has_date
+                .then_some(NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()),
+            config.has_time.then_some(NaiveTime::MIN),
+        );
+        let restored = config
+            .restore
+            .then(|| restored_state(restore, states, &entity_id, config.initial.is_some()))
+            .flatten()
+            .and_then(|state| parse_datetime_state(&state, config.has_date, config.has_time));
+        let initial = match &config.initial {
+            Some(initial) => parse_datetime_state(initial, config.has_date, config.has_time)
+                .unwrap_or_else(|| {
+                    warn!("input_datetime.{}: invalid initial value '{}'", id, initial);
+                    default
+                }),
+            None => default,
+        };
+        let (date, time) = restored.unwrap_or(initial);
+
+        let mut attributes = HashMap::new();
+        if let Some(name) = &config.name {
+            attributes.insert("friendly_name".to_string(), json!(name));
+        }
+        if let Some(icon) = &config.icon {
+            attributes.insert("icon".to_string(), json!(icon));
+        }
+        attributes.insert("has_date".to_string(), json!(config.has_date));
+        attributes.insert("has_time".to_string(), json!(config.has_time));
+        attributes.insert("editable".to_string(), json!(false));
+        let state = datetime_state(date, time, &mut attributes);
+
+        states.set(entity_id.clone(), &state, attributes, Context::new());
+        debug!("Loaded input_datetime.{} = {}", id, state);
+        count += 1;
+    }
+
+    if count > 0 {
+        info!("Loaded {} input_datetime entities", count);
+    }
+    count
+}
+
+/// Read the requested date and time from set_datetime service data
+///
+/// Accepts `date` and/or `time`, or exactly one of `datetime` / `timestamp`.
+fn requested_datetime(
+    data: &serde_json::Value,
+) -> Result<(Option<NaiveDate>, Option<NaiveTime>), ServiceError> {
+    let field = |key: &str| data.get(key).filter(|v| !v.is_null());
+    let invalid = |key: &str| ServiceError::InvalidData(format!("Invalid {}", key));
+
+    let date = field("date").map(|v| {
+        v.as_str()
+            .and_then(parse_date)
+            .ok_or_else(|| invalid("date"))
+    });
+    let time = field("time").map(|v| {
+        v.as_str()
+            .and_then(parse_time)
+            .ok_or_else(|| invalid("time"))
+    });
+    let datetime = field("datetime").map(|v| {
+        v.as_str()
+            .and_then(parse_datetime)
+            .ok_or_else(|| invalid("datetime"))
+    });
+    let timestamp = field("timestamp").map(|v| {
+        v.as_f64()
+            .and_then(|ts| Local.timestamp_opt(ts as i64, 0).single())
+            .map(|dt| dt.naive_local())
+            .ok_or_else(|| invalid("timestamp"))
+    });
+
+    match (date, time, datetime, timestamp) {
+        (None, None, Some(dt), None) | (None, None, None, Some(dt)) => {
+            let dt = dt?;
+            Ok((Some(dt.date()), Some(dt.time())))
+        }
+        (date, time, None, None) if date.is_some() || time.is_some() => {
+            Ok((date.transpose()?, time.transpose()?))
+        }
+        _ => Err(ServiceError::InvalidData(
+            "Specify date and/or time, or one of datetime or timestamp".to_string(),
+        )),
+    }
+}
+
+/// Register input_datetime services
+pub fn register_input_datetime_services(services: &ServiceRegistry, states: Arc<StateStore>) {
+    const DOMAIN: &str = "input_datetime";
+
+    // set_datetime service
+    let states_clone = states.clone();
+    services.register_with_description(
+        ServiceDescription {
+            domain: DOMAIN.to_string(),
+            service: "set_datetime".to_string(),
+            name: Some("Set".to_string()),
+            description: Some("Set the date and/or time of an input datetime".to_string()),
+            schema: Some(json!({
+                "date": {"required": false, "selector": {"text": {}}},
+                "time": {"required": false, "selector": {"time": {}}},
+                "datetime": {"required": false, "selector": {"text": {}}},
+                "timestamp": {"required": false, "selector": {"number": {}}}
+            })),
+            target: Some(json!({"entity": {"domain": "input_datetime"}})),
+            supports_response: SupportsResponse::None,
+        },
+        move |call: ServiceCall| {
+            let states = states_clone.clone();
+            async move {
+                let (date, time) = requested_datetime(&call.service_data)?;
+                let full = call.service_data.get("date").is_none()
+                    && call.service_data.get("time").is_none();
+
+                for entity_id in get_target_entities(&call, "input_datetime") {
+                    if let Some(current) = states.get(entity_id.as_str()) {
+                        let flag = |key: &str| {
+                            current
+                                .attributes
+                                .get(key)
+                                .and_then(|v| v.as_bool())
+                                .unwrap_or(false)
+                        };
+                        let (has_date, has_time) = (flag("has_date"), flag("has_time"));
+
+                        // datetime/timestamp set whichever parts the entity has
+                        if !full {
+                            if date.is_some() && !has_date {
+                                return Err(ServiceError::InvalidData(format!(
+                                    "Date set but {} has no date",
+                                    entity_id
+                                )));
+                            }
+                            if time.is_some() && !has_time {
+                                return Err(ServiceError::InvalidData(format!(
+                                    "Time set but {} has no time",
+                                    entity_id
+                                )));
+                            }
+                        }
+
+                        let (current_date, current_time) =
+                            parse_datetime_state(&current.state, has_date, has_time)
+                                .unwrap_or((None, None));
+                        let new_date = if has_date {
+                            date.or(current_date)
+                        } else {
+                            None
+                        };
+                        let new_time = if has_time {
+                            time.or(current_time)
+                        } else {
+                            None
+                        };
+
+                        let mut attrs = current.attributes.clone();
+                        let state = datetime_state(new_date, new_time, &mut attrs);
+                        states.set(entity_id, state, attrs, call.context.clone());
+                    }
+                }
+                Ok(None)
+            }
+        },
+    );
+
+    info!("Input datetime services registered");
+}
+
 // =============================================================================
 // Helpers
 // =============================================================================
 
 /// Extract target entity IDs from a service call
-fn get_target_entities(call: &ServiceCall, domain: &str) -> Vec<EntityId> {
+pub(crate) fn get_target_entities(call: &ServiceCall, domain: &str) -> Vec<EntityId> {
     let mut entities = Vec::new();
 
     // Check for entity_id in service_data
@@ -498,4 +1300,236 @@ mod tests {
         assert_eq!(config.step, 1.0);
         assert_eq!(config.initial, Some(120.0));
     }
+
+    fn input_select_setup() -> (ServiceRegistry, Arc<StateStore>) {
+        crate::tests::setup_helpers(
+            r#"
+            mode:
+              options: [Home, Away, Night]
+              initial: Away
+            "#,
+            load_input_selects,
+            register_input_select_services,
+        )
+    }
+
+    async fn call(services: &ServiceRegistry, service: &str, data: serde_json::Value) {
+        services
+            .call("input_select", service, data, Context::new(), false)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_input_select_select_option() {
+        let (services, states) = input_select_setup();
+        assert_eq!(states.get_state("input_select.mode").unwrap(), "Away");
+
+        let data = json!({"entity_id": "input_select.mode", "option": "Night"});
+        call(&services, "select_option", data).await;
+        assert_eq!(states.get_state("input_select.mode").unwrap(), "Night");
+
+        let result = services
+            .call(
+                "input_select",
+                "select_option",
+                json!({"entity_id": "input_select.mode", "option": "Party"}),
+                Context::new(),
+                false,
+            )
+            .await;
+        assert!(matches!(result, Err(ServiceError::InvalidData(_))));
+        assert_eq!(states.get_state("input_select.mode").unwrap(), "Night");
+    }
+
+    #[tokio::test]
+    async fn test_input_select_next_previous_wrap_around() {
+        let (services, states) = input_select_setup();
+        let target = json!({"entity_id": "input_select.mode"});
+
+        call(&services, "select_next", target.clone()).await;
+        assert_eq!(states.get_state("input_select.mode").unwrap(), "Night");
+        call(&services, "select_next", target.clone()).await;
+        assert_eq!(states.get_state("input_select.mode").unwrap(), "Home");
+        call(&services, "select_previous", target.clone()).await;
+        assert_eq!(states.get_state("input_select.mode").unwrap(), "Night");
+
+        let no_cycle = json!({"entity_id": "input_select.mode", "cycle": false});
+        call(&services, "select_next", no_cycle).await;
+        assert_eq!(states.get_state("input_select.mode").unwrap(), "Night");
+    }
+
+    #[tokio::test]
+    async fn test_input_select_set_options() {
+        let (services, states) = input_select_setup();
+
+        let data = json!({"entity_id": "input_select.mode", "options": ["Away", "Vacation"]});
+        call(&services, "set_options", data).await;
+        let state = states.get("input_select.mode").unwrap();
+        assert_eq!(state.state, "Away");
+        assert_eq!(state.attributes["options"], json!(["Away", "Vacation"]));
+
+        let data = json!({"entity_id": "input_select.mode", "options": ["Eco", "Comfort"]});
+        call(&services, "set_options", data).await;
+        assert_eq!(states.get_state("input_select.mode").unwrap(), "Eco");
+    }
+
+    fn input_text_setup() -> (ServiceRegistry, Arc<StateStore>) {
+        crate::tests::setup_helpers(
+            r#"
+            code:
+              max: 6
+              pattern: "[0-9]+$"
+              mode: password
+              initial: "1234"
+            "#,
+            load_input_texts,
+            register_input_text_services,
+        )
+    }
+
+    async fn set_text(services: &ServiceRegistry, value: &str) -> Result<(), ServiceError> {
+        let data = json!({"entity_id": "input_text.code", "value": value});
+        services
+            .call("input_text", "set_value", data, Context::new(), false)
+            .await
+            .map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn test_input_text_set_value() {
+        let (services, states) = input_text_setup();
+        let state = states.get("input_text.code").unwrap();
+        assert_eq!(state.state, "1234");
+        assert_eq!(state.attributes["mode"], "password");
+
+        set_text(&services, "424242").await.unwrap();
+        assert_eq!(states.get_state("input_text.code").unwrap(), "424242");
+    }
+
+    #[tokio::test]
+    async fn test_input_text_rejects_too_long_value() {
+        let (services, states) = input_text_setup();
+
+        let result = set_text(&services, "1234567").await;
+        assert!(matches!(result, Err(ServiceError::InvalidData(_))));
+        assert_eq!(states.get_state("input_text.code").unwrap(), "1234");
+    }
+
+    #[tokio::test]
+    async fn test_input_text_rejects_pattern_mismatch() {
+        let (services, states) = input_text_setup();
+
+        let result = set_text(&services, "12ab").await;
+        assert!(matches!(result, Err(ServiceError::InvalidData(_))));
+        assert_eq!(states.get_state("input_text.code").unwrap(), "1234");
+    }
+
+    fn input_datetime_setup() -> (ServiceRegistry, Arc<StateStore>) {
+        crate::tests::setup_helpers(
+            r#"
+            holiday:
+              has_date: true
+              initial: "2024-12-24"
+            alarm:
+              has_time: true
+              initial: "06:30"
+            departure:
+              has_date: true
+              has_time: true
+            "#,
+            load_input_datetimes,
+            register_input_datetime_services,
+        )
+    }
+
+    async fn set_datetime(
+        services: &ServiceRegistry,
+        data: serde_json::Value,
+    ) -> Result<(), ServiceError> {
+        services
+            .call(
+                "input_datetime",
+                "set_datetime",
+                data,
+                Context::new(),
+                false,
+            )
+            .await
+            .map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn test_input_datetime_date_only() {
+        let (services, states) = input_datetime_setup();
+        let state = states.get("input_datetime.holiday").unwrap();
+        assert_eq!(state.state, "2024-12-24");
+        assert_eq!(state.attributes["day"], 24);
+        assert!(!state.attributes.contains_key("hour"));
+
+        let data = json!({"entity_id": "input_datetime.holiday", "date": "2025-01-01"});
+        set_datetime(&services, data).await.unwrap();
+        assert_eq!(
+            states.get_state("input_datetime.holiday").unwrap(),
+            "2025-01-01"
+        );
+
+        let data = json!({"entity_id": "input_datetime.holiday", "time": "10:00"});
+        let result = set_datetime(&services, data).await;
+        assert!(matches!(result, Err(ServiceError::InvalidData(_))));
+    }
+
+    #[tokio::test]
+    async fn test_input_datetime_time_only() {
+        let (services, states) = input_datetime_setup();
+        let state = states.get("input_datetime.alarm").unwrap();
+        assert_eq!(state.state, "06:30:00");
+        assert_eq!(state.attributes["timestamp"], 6 * 3600 + 30 * 60);
+
+        let data = json!({"entity_id": "input_datetime.alarm", "time": "07:15:30"});
+        set_datetime(&services, data).await.unwrap();
+        let state = states.get("input_datetime.alarm").unwrap();
+        assert_eq!(state.state, "07:15:30");
+        assert_eq!(state.attributes["minute"], 15);
+
+        let data = json!({"entity_id": "input_datetime.alarm", "date": "2025-01-01"});
+        let result = set_datetime(&services, data).await;
+        assert!(matches!(result, Err(ServiceError::InvalidData(_))));
+    }
+
+    #[tokio::test]
+    async fn test_input_datetime_combined() {
+        let (services, states) = input_datetime_setup();
+        assert_eq!(
+            states.get_state("input_datetime.departure").unwrap(),
+            "1970-01-01 00:00:00"
+        );
+
+        let data =
+            json!({"entity_id": "input_datetime.departure", "datetime": "2024-06-01T08:45:00"});
+        set_datetime(&services, data).await.unwrap();
+        assert_eq!(
+            states.get_state("input_datetime.departure").unwrap(),
+            "2024-06-01 08:45:00"
+        );
+
+        // Setting only the time keeps the date
+        let data = json!({"entity_id": "input_datetime.departure", "time": "09:00"});
+        set_datetime(&services, data).await.unwrap();
+        let state = states.get("input_datetime.departure").unwrap();
+        assert_eq!(state.state, "2024-06-01 09:00:00");
+        let expected = Local
+            .with_ymd_and_hms(2024, 6, 1, 9, 0, 0)
+            .unwrap()
+            .timestamp();
+        assert_eq!(state.attributes["timestamp"], expected);
+
+        let data = json!({
+            "entity_id": "input_datetime.departure",
+            "datetime": "2024-06-01 08:45:00",
+            "timestamp": 0,
+        });
+        let result = set_datetime(&services, data).await;
+        assert!(matches!(result, Err(ServiceError::InvalidData(_))));
+    }
 }
@@ -0,0 +1,393 @@
+//! Integration Component
+//!
+//! Implements the `integration` sensor platform: a running Riemann sum of a
+//! source sensor over time, e.g. the energy in kWh used by a device whose
+//! power is measured in W. Each new source sample adds the area since the
+//! previous one, scaled by `unit_prefix` and `unit_time`. Gaps where the
+//! source was unavailable or not numeric add nothing.
+
+use chrono::{DateTime, Utc};
+use ha_core::{Context, EntityId, State, STATE_UNKNOWN};
+use ha_event_bus::helpers::track_state_change_event;
+use ha_event_bus::{EventBus, ListenerId};
+use ha_state_store::StateStore;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info, warn};
+
+use crate::min_max::{format_value, numeric_state};
+use crate::scene::slugify;
+
+/// Default number of decimals of the total
+const DEFAULT_ROUND_DIGITS: u32 = 3;
+
+/// Metric prefix of a computed unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum UnitPrefix {
+    #[serde(rename = "k")]
+    Kilo,
+    #[serde(rename = "M")]
+    Mega,
+    #[serde(rename = "G")]
+    Giga,
+    #[serde(rename = "T")]
+    Tera,
+}
+
+impl UnitPrefix {
+    pub fn factor(self) -> f64 {
+        match self {
+            Self::Kilo => 1e3,
+            Self::Mega => 1e6,
+            Self::Giga => 1e9,
+            Self::Tera => 1e12,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Kilo => "k",
+            Self::Mega => "M",
+            Self::Giga => "G",
+            Self::Tera => "T",
+        }
+    }
+}
+
+/// Time unit a rate is expressed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum UnitTime {
+    #[serde(rename = "s")]
+    Seconds,
+    #[serde(rename = "min")]
+    Minutes,
+    #[default]
+    #[serde(rename = "h")]
+    Hours,
+    #[serde(rename = "d")]
+    Days,
+}
+
+impl UnitTime {
+    pub fn seconds(self) -> f64 {
+        match self {
+            Self::Seconds => 1.0,
+            Self::Minutes => 60.0,
+            Self::Hours => 3600.0,
+            Self::Days => 86400.0,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Seconds => "s",
+            Self::Minutes => "min",
+            Self::Hours => "h",
+            Self::Days => "d",
+        }
+    }
+}
+
+/// Slugified object ID of a source, the default base of a sensor's entity_id
+pub(crate) fn object_id_of(source: &str) -> String {
+    slugify(
+        source
+            .split_once('.')
+            .map_or(source, |(_, object_id)| object_id),
+    )
+}
+
+/// Riemann sum method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrationMethod {
+    /// Average of both samples, best for smoothly changing sources
+    #[default]
+    Trapezoidal,
+    /// Previous sample, for sources that hold a value until the next one
+    Left,
+    /// New sample
+    Right,
+}
+
+impl IntegrationMethod {
+    /// Area between two samples `seconds` apart
+    pub fn area(self, previous: f64, value: f64, seconds: f64) -> f64 {
+        match self {
+            Self::Trapezoidal => (previous + value) / 2.0 * seconds,
+            Self::Left => previous * seconds,
+            Self::Right => value * seconds,
+        }
+    }
+}
+
+/// Integration sensor configuration from YAML
+#[derive(Debug, Clone, Deserialize)]
+pub struct IntegrationConfig {
+    /// Display name, also used for the entity_id
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub unique_id: Option<String>,
+    /// Source sensor
+    pub source: String,
+    #[serde(default)]
+    pub method: IntegrationMethod,
+    #[serde(default)]
+    pub unit_prefix: Option<UnitPrefix>,
+    /// Time unit of the total (default: h)
+    #[serde(default)]
+    pub unit_time: UnitTime,
+    /// Decimals of the total (default: 3)
+    #[serde(default)]
+    pub round: Option<u32>,
+}
+
+/// Running state of an integration sensor
+#[derive(Default)]
+struct Integral {
+    /// `None` until the source reported a first numeric value
+    total: Option<f64>,
+    /// Last numeric sample, cleared when the source stops being numeric
+    last: Option<(DateTime<Utc>, f64)>,
+}
+
+/// A loaded integration sensor
+struct IntegrationSensor {
+    entity_id: EntityId,
+    config: IntegrationConfig,
+    integral: Mutex<Integral>,
+}
+
+impl IntegrationSensor {
+    /// Add the area up to a new source state and write the sensor
+    fn update(&self, states: &StateStore, source: Option<&State>, context: Context) {
+        let total = {
+            let mut integral = self.integral.lock().unwrap();
+            let sample = source.and_then(|s| Some((s.last_updated, numeric_state(s)?)));
+            if let Some((time, value)) = sample {
+                let mut total = integral.total.unwrap_or(0.0);
+                if let Some((last_time, last_value)) = integral.last {
+                    let seconds = (time - last_time).num_milliseconds() as f64 / 1000.0;
+                    if seconds > 0.0 {
+                        let prefix = self.config.unit_prefix.map_or(1.0, UnitPrefix::factor);
+                        total += self.config.method.area(last_value, value, seconds)
+                            / (prefix * self.config.unit_time.seconds());
+                    }
+                }
+                integral.total = Some(total);
+            }
+            integral.last = sample;
+            integral.total
+        };
+
+        let mut attributes = HashMap::new();
+        attributes.insert("icon".to_string(), json!("mdi:chart-histogram"));
+        attributes.insert("state_class".to_string(), json!("total"));
+        attributes.insert("source".to_string(), json!(self.config.source));
+        if let Some(name) = &self.config.name {
+            attributes.insert("friendly_name".to_string(), json!(name));
+        }
+        if let Some(source) = source {
+            if let Some(unit) = source
+                .attributes
+                .get("unit_of_measurement")
+                .and_then(|u| u.as_str())
+            {
+                let prefix = self.config.unit_prefix.map_or("", UnitPrefix::symbol);
+                let unit = format!("{}{}{}", prefix, unit, self.config.unit_time.symbol());
+                attributes.insert("unit_of_measurement".to_string(), json!(unit));
+            }
+            if source.attributes.get("device_class") == Some(&json!("power")) {
+                attributes.insert("device_class".to_string(), json!("energy"));
+            }
+        }
+
+        let state = total.map_or_else(
+            || STATE_UNKNOWN.to_string(),
+            |total| format_value(total, self.config.round.unwrap_or(DEFAULT_ROUND_DIGITS)),
+        );
+        states.set(self.entity_id.clone(), state, attributes, context);
+    }
+}
+
+/// Load integration sensors from config and feed them their sources' states
+///
+/// A sensor starts from its source's current state; the first numeric
+/// sample sets the total to 0.
+pub fn load_integration_sensors(
+    config: &[IntegrationConfig],
+    event_bus: &EventBus,
+    states: Arc<StateStore>,
+) -> ListenerId {
+    let mut by_source: HashMap<String, Vec<IntegrationSensor>> = HashMap::new();
+    let mut count = 0;
+
+    for config in config {
+        let mut config = config.clone();
+        config.source = config.source.trim().to_lowercase();
+        let object_id = config
+            .name
+            .as_deref()
+            .or(config.unique_id.as_deref())
+            .map(slugify)
+            .filter(|slug| !slug.is_empty())
+            .unwrap_or_else(|| format!("{}_integral", object_id_of(&config.source)));
+        let entity_id = match EntityId::new("sensor", &object_id) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid integration sensor '{}': {}", object_id, e);
+                continue;
+            }
+        };
+
+        let sensor = IntegrationSensor {
+            entity_id,
+            config,
+            integral: Mutex::new(Integral::default()),
+        };
+        let source = states.get(&sensor.config.source);
+        sensor.update(&states, source.as_ref(), Context::new());
+        debug!(
+            "Loaded integration {} of {}",
+            sensor.entity_id, sensor.config.source
+        );
+        by_source
+            .entry(sensor.config.source.clone())
+            .or_default()
+            .push(sensor);
+        count += 1;
+    }
+
+    if count > 0 {
+        info!("Loaded {} integration sensors", count);
+    }
+
+    let sources: Vec<String> = by_source.keys().cloned().collect();
+    track_state_change_event(
+        event_bus,
+        sources,
+        Arc::new(move |event| {
+            let Some(sensors) = by_source.get(event.data.entity_id.as_str()) else {
+                return;
+            };
+            for sensor in sensors {
+                sensor.update(
+                    &states,
+                    event.data.new_state.as_ref(),
+                    event.context.clone(),
+                );
+            }
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use ha_core::events::StateChangedData;
+    use ha_core::STATE_UNAVAILABLE;
+
+    fn setup<C: serde::de::DeserializeOwned>(
+        yaml: &str,
+        load: fn(&[C], &EventBus, Arc<StateStore>) -> ListenerId,
+    ) -> (Arc<EventBus>, Arc<StateStore>) {
+        let bus = Arc::new(EventBus::new());
+        let states = Arc::new(StateStore::new(bus.clone()));
+        let config: Vec<C> = serde_yaml::from_str(yaml).unwrap();
+        load(&config, &bus, states.clone());
+        (bus, states)
+    }
+
+    /// Report a power reading taken `minutes` after `start`
+    fn report(bus: &EventBus, start: DateTime<Utc>, minutes: i64, value: &str) {
+        let entity_id: EntityId = "sensor.heater_power".parse().unwrap();
+        let mut state = State::new(
+            entity_id.clone(),
+            value,
+            HashMap::from([
+                ("unit_of_measurement".to_string(), json!("W")),
+                ("device_class".to_string(), json!("power")),
+            ]),
+            Context::new(),
+        );
+        state.last_updated = start + Duration::minutes(minutes);
+        bus.fire_typed(
+            StateChangedData {
+                entity_id,
+                old_state: None,
+                new_state: Some(state),
+            },
+            Context::new(),
+        );
+    }
+
+    #[test]
+    fn test_methods() {
+        assert_eq!(
+            IntegrationMethod::Trapezoidal.area(100.0, 300.0, 10.0),
+            2000.0
+        );
+        assert_eq!(IntegrationMethod::Left.area(100.0, 300.0, 10.0), 1000.0);
+        assert_eq!(IntegrationMethod::Right.area(100.0, 300.0, 10.0), 3000.0);
+    }
+
+    #[test]
+    fn test_integrated_energy_total() {
+        let (bus, states) = setup(
+            r#"
+            - name: Heater Energy
+              source: sensor.heater_power
+              unit_prefix: k
+              unit_time: h
+              method: left
+            "#,
+            load_integration_sensors,
+        );
+        assert_eq!(
+            states.get_state("sensor.heater_energy").unwrap(),
+            STATE_UNKNOWN
+        );
+
+        let start = Utc::now();
+        // The first sample only starts the sum
+        report(&bus, start, 0, "1000");
+        assert_eq!(states.get_state("sensor.heater_energy").unwrap(), "0.0");
+
+        // 1 kW for 30 minutes, then 2 kW for 15 minutes
+        report(&bus, start, 30, "2000");
+        report(&bus, start, 45, "0");
+        let energy = states.get("sensor.heater_energy").unwrap();
+        assert_eq!(energy.state, "1.0");
+        assert_eq!(energy.attributes["unit_of_measurement"], "kWh");
+        assert_eq!(energy.attributes["device_class"], "energy");
+
+        // Nothing is added across an unavailable gap
+        report(&bus, start, 60, "3000");
+        report(&bus, start, 90, STATE_UNAVAILABLE);
+        report(&bus, start, 120, "3000");
+        assert_eq!(states.get_state("sensor.heater_energy").unwrap(), "1.0");
+        report(&bus, start, 140, "0");
+        assert_eq!(states.get_state("sensor.heater_energy").unwrap(), "2.0");
+    }
+
+    #[test]
+    fn test_trapezoidal_in_watt_minutes() {
+        let (bus, states) = setup(
+            r#"
+            - source: sensor.heater_power
+              unit_time: min
+            "#,
+            load_integration_sensors,
+        );
+        let start = Utc::now();
+        report(&bus, start, 0, "100");
+        report(&bus, start, 10, "300");
+        let energy = states.get("sensor.heater_power_integral").unwrap();
+        assert_eq!(energy.state, "2000.0");
+        assert_eq!(energy.attributes["unit_of_measurement"], "Wmin");
+    }
+}
@@ -3,11 +3,65 @@
 //! This crate contains implementations of Home Assistant's built-in components
 //! (integrations) that don't require Python.
 
+mod counter;
+mod derivative;
+mod group;
 mod input_helpers;
+mod integration;
+mod min_max;
+pub mod restore_state;
+mod scene;
+mod schedule;
+mod statistics;
 pub mod system_log;
+mod template;
+mod timer;
 
+pub use counter::{load_counters, register_counter_services, CounterConfig};
+pub use derivative::{load_derivative_sensors, DerivativeConfig};
+pub use group::{load_groups, GroupConfig};
 pub use input_helpers::{
-    load_input_booleans, load_input_numbers, register_input_boolean_services,
-    register_input_number_services, InputBooleanConfig, InputNumberConfig,
+    load_input_booleans, load_input_datetimes, load_input_numbers, load_input_selects,
+    load_input_texts, register_input_boolean_services, register_input_datetime_services,
+    register_input_number_services, register_input_select_services, register_input_text_services,
+    InputBooleanConfig, InputDatetimeConfig, InputNumberConfig, InputSelectConfig, InputTextConfig,
 };
+pub use integration::{
+    load_integration_sensors, IntegrationConfig, IntegrationMethod, UnitPrefix, UnitTime,
+};
+pub use min_max::{load_min_max_sensors, Aggregate, MinMaxConfig};
+pub use restore_state::RestoreStateStore;
+pub use scene::{load_scenes, SceneConfig};
+pub use schedule::{load_schedules, ScheduleConfig, ScheduleWindow};
+pub use statistics::{load_statistics_sensors, StateCharacteristic, StatisticsConfig};
 pub use system_log::{register_services as register_system_log_services, SystemLog};
+pub use template::{load_template_entities, TemplateConfig, TemplateEntityConfig};
+pub use timer::{load_timers, register_timer_services, TimerConfig};
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use ha_service_registry::ServiceRegistry;
+    use ha_state_store::StateStore;
+    use serde::de::DeserializeOwned;
+
+    use crate::RestoreStateStore;
+
+    /// Load helper entities from a YAML config and register their services
+    pub(crate) fn setup_helpers<C: DeserializeOwned>(
+        yaml: &str,
+        load: fn(&HashMap<String, C>, &StateStore, Option<&RestoreStateStore>) -> usize,
+        register: fn(&ServiceRegistry, Arc<StateStore>),
+    ) -> (ServiceRegistry, Arc<StateStore>) {
+        let bus = Arc::new(ha_event_bus::EventBus::new());
+        let states = Arc::new(StateStore::new(bus));
+        let config: HashMap<String, C> = serde_yaml::from_str(yaml).unwrap();
+        load(&config, &states, None);
+
+        let services = ServiceRegistry::new();
+        register(&services, states.clone());
+        (services, states)
+    }
+}
@@ -0,0 +1,356 @@
+//! Min/Max Component
+//!
+//! Implements the `min_max` sensor platform: one sensor aggregating the
+//! numeric states of a list of source sensors into their min, max, mean,
+//! median, sum, range or most recent value. Members that are unavailable or
+//! not numeric are left out.
+
+use ha_core::{Context, EntityId, State, STATE_UNKNOWN};
+use ha_event_bus::helpers::{track_state_change_event, Debouncer};
+use ha_event_bus::{EventBus, ListenerId};
+use ha_state_store::StateStore;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::scene::slugify;
+
+/// Quiet time after a member change before the sensor is recomputed
+///
+/// A burst of member updates, e.g. several meters polled together, results
+/// in one recomputation with the final member states.
+const UPDATE_DELAY: Duration = Duration::from_millis(100);
+
+/// Default number of decimals of computed values
+const DEFAULT_ROUND_DIGITS: u32 = 2;
+
+/// How member values are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregate {
+    Min,
+    Max,
+    #[default]
+    Mean,
+    Median,
+    Last,
+    Range,
+    Sum,
+}
+
+impl Aggregate {
+    /// Combine `values`, oldest first; `None` when there are none
+    pub fn apply(self, values: &[f64]) -> Option<f64> {
+        let last = *values.last()?;
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let sum: f64 = values.iter().sum();
+        Some(match self {
+            Self::Min => min,
+            Self::Max => max,
+            Self::Mean => sum / values.len() as f64,
+            Self::Median => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(f64::total_cmp);
+                let middle = sorted.len() / 2;
+                if sorted.len() % 2 == 0 {
+                    (sorted[middle - 1] + sorted[middle]) / 2.0
+                } else {
+                    sorted[middle]
+                }
+            }
+            Self::Last => last,
+            Self::Range => max - min,
+            Self::Sum => sum,
+        })
+    }
+}
+
+/// Round a value and format it like a Python float, e.g. `20.0`
+pub(crate) fn format_value(value: f64, round_digits: u32) -> String {
+    let factor = 10f64.powi(round_digits as i32);
+    let value = (value * factor).round() / factor;
+    if value.fract() == 0.0 {
+        format!("{:.1}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Numeric value of an available state
+pub(crate) fn numeric_state(state: &State) -> Option<f64> {
+    if !state.is_available() {
+        return None;
+    }
+    state.state.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+/// Min/max sensor configuration from YAML
+#[derive(Debug, Clone, Deserialize)]
+pub struct MinMaxConfig {
+    /// Display name, also used for the entity_id
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub unique_id: Option<String>,
+    /// How member values are combined (default: mean)
+    #[serde(default, rename = "type")]
+    pub aggregate: Aggregate,
+    /// Source sensors
+    pub entity_ids: Vec<String>,
+    /// Decimals of the computed value (default: 2)
+    #[serde(default)]
+    pub round_digits: Option<u32>,
+}
+
+/// A loaded min/max sensor
+struct MinMaxSensor {
+    entity_id: EntityId,
+    members: Vec<String>,
+    aggregate: Aggregate,
+    round_digits: u32,
+    name: Option<String>,
+}
+
+impl MinMaxSensor {
+    /// Recompute the sensor from its members and write it
+    fn update(&self, states: &StateStore, context: Context) {
+        // Numeric members, oldest update first, so the last one is "last"
+        let mut members: Vec<(State, f64)> = self
+            .members
+            .iter()
+            .filter_map(|member| states.get(member))
+            .filter_map(|state| numeric_state(&state).map(|value| (state, value)))
+            .collect();
+        members.sort_by_key(|(state, _)| state.last_updated);
+        let values: Vec<f64> = members.iter().map(|(_, value)| *value).collect();
+
+        let mut attributes = HashMap::new();
+        attributes.insert("icon".to_string(), json!("mdi:calculator"));
+        attributes.insert("state_class".to_string(), json!("measurement"));
+        if let Some(name) = &self.name {
+            attributes.insert("friendly_name".to_string(), json!(name));
+        }
+        // Only a unit all numeric members share is meaningful
+        let units: Vec<Option<&serde_json::Value>> = members
+            .iter()
+            .map(|(state, _)| state.attributes.get("unit_of_measurement"))
+            .collect();
+        if let Some(Some(unit)) = units.first() {
+            if units.iter().all(|u| u == &Some(*unit)) {
+                attributes.insert("unit_of_measurement".to_string(), (*unit).clone());
+            }
+        }
+        let member_with = |value: f64| {
+            members
+                .iter()
+                .rev()
+                .find(|(_, v)| *v == value)
+                .map(|(state, _)| json!(state.entity_id.as_str()))
+        };
+        let source = match self.aggregate {
+            Aggregate::Min => Some(("min_entity_id", values.iter().copied().reduce(f64::min))),
+            Aggregate::Max => Some(("max_entity_id", values.iter().copied().reduce(f64::max))),
+            Aggregate::Last => Some(("last_entity_id", values.last().copied())),
+            _ => None,
+        };
+        if let Some((attribute, Some(value))) = source {
+            if let Some(entity_id) = member_with(value) {
+                attributes.insert(attribute.to_string(), entity_id);
+            }
+        }
+
+        let state = self.aggregate.apply(&values).map_or_else(
+            || STATE_UNKNOWN.to_string(),
+            |value| format_value(value, self.round_digits),
+        );
+        states.set(self.entity_id.clone(), state, attributes, context);
+    }
+}
+
+/// Load min/max sensors from config and keep them in sync with their members
+///
+/// Returns the listener that recomputes sensors on member state changes,
+/// debounced by `UPDATE_DELAY`, so this must be called from within a tokio
+/// runtime.
+pub fn load_min_max_sensors(
+    config: &[MinMaxConfig],
+    event_bus: &EventBus,
+    states: Arc<StateStore>,
+) -> ListenerId {
+    let mut sensors = Vec::new();
+
+    for config in config {
+        let object_id = config
+            .name
+            .as_deref()
+            .or(config.unique_id.as_deref())
+            .map(slugify)
+            .filter(|slug| !slug.is_empty())
+            .unwrap_or_else(|| "min_max".to_string());
+        let entity_id = match EntityId::new("sensor", &object_id) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid min_max sensor '{}': {}", object_id, e);
+                continue;
+            }
+        };
+
+        let sensor = MinMaxSensor {
+            entity_id,
+            members: config
+                .entity_ids
+                .iter()
+                .map(|member| member.trim().to_lowercase())
+                .collect(),
+            aggregate: config.aggregate,
+            round_digits: config.round_digits.unwrap_or(DEFAULT_ROUND_DIGITS),
+            name: config.name.clone(),
+        };
+        sensor.update(&states, Context::new());
+        debug!(
+            "Loaded min_max {} with {} members",
+            sensor.entity_id,
+            sensor.members.len()
+        );
+        sensors.push(sensor);
+    }
+
+    if !sensors.is_empty() {
+        info!("Loaded {} min_max sensors", sensors.len());
+    }
+
+    // Index sensors by member so a state change only touches its sensors
+    let mut by_member: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, sensor) in sensors.iter().enumerate() {
+        for member in &sensor.members {
+            by_member.entry(member.clone()).or_default().push(index);
+        }
+    }
+    let updaters: Vec<Debouncer<Context>> = sensors
+        .into_iter()
+        .map(|sensor| {
+            let states = states.clone();
+            Debouncer::new(UPDATE_DELAY, move |context| sensor.update(&states, context))
+        })
+        .collect();
+
+    let members: Vec<String> = by_member.keys().cloned().collect();
+    track_state_change_event(
+        event_bus,
+        members,
+        Arc::new(move |event| {
+            let Some(indexes) = by_member.get(event.data.entity_id.as_str()) else {
+                return;
+            };
+            for &index in indexes {
+                updaters[index].call(event.context.clone());
+            }
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ha_core::STATE_UNAVAILABLE;
+
+    const MEMBERS: [&str; 3] = ["sensor.kitchen", "sensor.bedroom", "sensor.office"];
+
+    fn setup(yaml: &str) -> Arc<StateStore> {
+        let bus = Arc::new(EventBus::new());
+        let states = Arc::new(StateStore::new(bus.clone()));
+        for (member, value) in MEMBERS.iter().zip(["18", "21", "24.5"]) {
+            set(&states, member, value);
+        }
+        let config: Vec<MinMaxConfig> = serde_yaml::from_str(yaml).unwrap();
+        load_min_max_sensors(&config, &bus, states.clone());
+        states
+    }
+
+    fn set(states: &StateStore, entity_id: &str, state: &str) {
+        let entity_id: EntityId = entity_id.parse().unwrap();
+        let attributes = HashMap::from([("unit_of_measurement".to_string(), json!("°C"))]);
+        states.set(entity_id, state, attributes, Context::new());
+    }
+
+    /// Set a member state and wait out the sensor's update delay
+    async fn set_and_settle(states: &StateStore, entity_id: &str, state: &str) {
+        set(states, entity_id, state);
+        tokio::time::sleep(UPDATE_DELAY * 3).await;
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let values = [3.0, 1.0, 4.0, 2.0];
+        assert_eq!(Aggregate::Min.apply(&values), Some(1.0));
+        assert_eq!(Aggregate::Max.apply(&values), Some(4.0));
+        assert_eq!(Aggregate::Mean.apply(&values), Some(2.5));
+        assert_eq!(Aggregate::Median.apply(&values), Some(2.5));
+        assert_eq!(Aggregate::Median.apply(&values[..3]), Some(3.0));
+        assert_eq!(Aggregate::Last.apply(&values), Some(2.0));
+        assert_eq!(Aggregate::Range.apply(&values), Some(3.0));
+        assert_eq!(Aggregate::Sum.apply(&values), Some(10.0));
+        assert_eq!(Aggregate::Mean.apply(&[]), None);
+        assert_eq!(format_value(21.166_666, 2), "21.17");
+        assert_eq!(format_value(20.0, 2), "20.0");
+    }
+
+    #[tokio::test]
+    async fn test_mean_of_three_sensors() {
+        let states = setup(
+            r#"
+            - name: Average Temperature
+              type: mean
+              entity_ids: [sensor.kitchen, sensor.bedroom, sensor.office]
+            - name: Coldest Room
+              type: min
+              entity_ids: [sensor.kitchen, sensor.bedroom, sensor.office]
+            "#,
+        );
+        let mean = states.get("sensor.average_temperature").unwrap();
+        assert_eq!(mean.state, "21.17");
+        assert_eq!(mean.attributes["unit_of_measurement"], "°C");
+        let min = states.get("sensor.coldest_room").unwrap();
+        assert_eq!(min.state, "18.0");
+        assert_eq!(min.attributes["min_entity_id"], "sensor.kitchen");
+
+        set_and_settle(&states, "sensor.kitchen", "21").await;
+        assert_eq!(
+            states.get_state("sensor.average_temperature").unwrap(),
+            "22.17"
+        );
+        assert_eq!(states.get_state("sensor.coldest_room").unwrap(), "21.0");
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_member_is_excluded() {
+        let states = setup(
+            r#"
+            - name: Average Temperature
+              entity_ids: [sensor.kitchen, sensor.bedroom, sensor.office]
+            "#,
+        );
+
+        set_and_settle(&states, "sensor.office", STATE_UNAVAILABLE).await;
+        assert_eq!(
+            states.get_state("sensor.average_temperature").unwrap(),
+            "19.5"
+        );
+
+        set_and_settle(&states, "sensor.bedroom", "n/a").await;
+        assert_eq!(
+            states.get_state("sensor.average_temperature").unwrap(),
+            "18.0"
+        );
+
+        set_and_settle(&states, "sensor.kitchen", STATE_UNAVAILABLE).await;
+        assert_eq!(
+            states.get_state("sensor.average_temperature").unwrap(),
+            STATE_UNKNOWN
+        );
+    }
+}
@@ -0,0 +1,345 @@
+//! Restore State
+//!
+//! Persists the last state of helper entities to `.storage/core.restore_state`
+//! so they come back with their previous value after a restart instead of
+//! resetting to their configured defaults.
+
+use chrono::{DateTime, Utc};
+use ha_core::events::{StateChangedData, STATE_CHANGED};
+use ha_core::{EntityId, State};
+use ha_event_bus::{EventBus, ListenerId};
+use ha_registries::{Storable, Storage, StorageFile, StorageResult};
+use ha_state_store::StateStore;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Storage key for restored states
+pub const STORAGE_KEY: &str = "core.restore_state";
+/// Current storage version
+pub const STORAGE_VERSION: u32 = 1;
+/// Current minor version
+pub const STORAGE_MINOR_VERSION: u32 = 1;
+
+/// A saved entity state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredState {
+    /// The entity's last state
+    #[serde(deserialize_with = "deserialize_state_lenient")]
+    pub state: State,
+    /// Extra data saved alongside the state
+    #[serde(default)]
+    pub extra_data: Option<serde_json::Value>,
+    /// When the state was recorded
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Deserialize a saved state whose entity ID may predate the stricter rules
+fn deserialize_state_lenient<'de, D>(deserializer: D) -> Result<State, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let mut value = serde_json::Value::deserialize(deserializer)?;
+    let entity_id = value
+        .get("entity_id")
+        .and_then(|id| id.as_str())
+        .ok_or_else(|| D::Error::missing_field("entity_id"))?;
+    let entity_id = EntityId::parse_lenient(entity_id).map_err(D::Error::custom)?;
+
+    // State only deserializes strictly valid IDs, so parse the rest with a
+    // stand-in and put the lenient ID back afterwards
+    value["entity_id"] = "restore_state.entity".into();
+    let mut state: State = serde_json::from_value(value).map_err(D::Error::custom)?;
+    state.entity_id = entity_id;
+    Ok(state)
+}
+
+/// Contents of the restore state storage file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RestoreStateData(pub Vec<StoredState>);
+
+impl Storable for RestoreStateData {
+    const KEY: &'static str = STORAGE_KEY;
+    const VERSION: u32 = STORAGE_VERSION;
+    const MINOR_VERSION: u32 = STORAGE_MINOR_VERSION;
+}
+
+/// Store of entity states to restore on startup
+///
+/// Only tracked entities are recorded and saved; helpers track themselves
+/// when they are loaded with `restore` enabled.
+pub struct RestoreStateStore {
+    storage: Arc<Storage>,
+    last_states: Mutex<HashMap<String, StoredState>>,
+    tracked: Mutex<HashSet<String>>,
+    save_requested: Arc<Notify>,
+}
+
+impl RestoreStateStore {
+    /// Create an empty store saving to the given storage
+    pub fn new(storage: Arc<Storage>) -> Self {
+        Self {
+            storage,
+            last_states: Mutex::new(HashMap::new()),
+            tracked: Mutex::new(HashSet::new()),
+            save_requested: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Load the states saved before the last shutdown
+    pub async fn load(&self) -> StorageResult<()> {
+        if let Some(storage_file) = self.storage.load_migrated::<RestoreStateData>().await? {
+            info!(
+                "Loading {} restored states from storage (v{}.{})",
+                storage_file.data.0.len(),
+                storage_file.version,
+                storage_file.minor_version
+            );
+
+            let mut last_states = self.last_states.lock().unwrap();
+            for stored in storage_file.data.0 {
+                last_states.insert(stored.state.entity_id.to_string(), stored);
+            }
+        }
+        Ok(())
+    }
+
+    /// Save the states of all tracked entities
+    pub async fn save(&self) -> StorageResult<()> {
+        let data = {
+            let tracked = self.tracked.lock().unwrap();
+            let last_states = self.last_states.lock().unwrap();
+            RestoreStateData(
+                last_states
+                    .iter()
+                    .filter(|(entity_id, _)| tracked.contains(*entity_id))
+                    .map(|(_, stored)| stored.clone())
+                    .collect(),
+            )
+        };
+        let count = data.0.len();
+
+        let storage_file =
+            StorageFile::new(STORAGE_KEY, data, STORAGE_VERSION, STORAGE_MINOR_VERSION);
+        self.storage.save(&storage_file).await?;
+        debug!("Saved {} restore states to storage", count);
+        Ok(())
+    }
+
+    /// Get the last saved state of an entity
+    pub fn last_state(&self, entity_id: &str) -> Option<State> {
+        self.last_states
+            .lock()
+            .unwrap()
+            .get(entity_id)
+            .map(|stored| stored.state.clone())
+    }
+
+    /// Record the states of an entity from now on
+    pub fn track(&self, entity_id: &EntityId) {
+        self.tracked.lock().unwrap().insert(entity_id.to_string());
+    }
+
+    /// Record state changes of tracked entities, requesting a save for each
+    pub fn attach(self: &Arc<Self>, event_bus: &EventBus) -> ListenerId {
+        let store = Arc::downgrade(self);
+        event_bus.listen_sync(
+            STATE_CHANGED,
+            Arc::new(move |event| {
+                let Some(store) = store.upgrade() else {
+                    return;
+                };
+                // Skip untracked entities without deserializing their states
+                let Some(entity_id) = event.data.get("entity_id").and_then(|id| id.as_str()) else {
+                    return;
+                };
+                if !store.tracked.lock().unwrap().contains(entity_id) {
+                    return;
+                }
+                let entity_id = entity_id.to_string();
+                let Ok(data) = serde_json::from_value::<StateChangedData>(event.data.clone())
+                else {
+                    return;
+                };
+
+                let mut last_states = store.last_states.lock().unwrap();
+                match data.new_state {
+                    Some(state) => {
+                        last_states.insert(
+                            entity_id,
+                            StoredState {
+                                state,
+                                extra_data: None,
+                                last_seen: Utc::now(),
+                            },
+                        );
+                    }
+                    None => {
+                        last_states.remove(&entity_id);
+                    }
+                }
+                drop(last_states);
+                store.save_requested.notify_one();
+            }),
+        )
+    }
+
+    /// Spawn a task that saves the store `delay` after a change was recorded
+    pub fn spawn_save_task(self: &Arc<Self>, delay: Duration) -> JoinHandle<()> {
+        let store = Arc::downgrade(self);
+        let save_requested = self.save_requested.clone();
+        tokio::spawn(async move {
+            loop {
+                save_requested.notified().await;
+                tokio::time::sleep(delay).await;
+                let Some(store) = store.upgrade() else {
+                    break;
+                };
+                if let Err(e) = store.save().await {
+                    warn!("Failed to save restore state: {}", e);
+                }
+            }
+        })
+    }
+}
+
+/// The state a helper with `restore` enabled should start from
+///
+/// Prefers the entity's current state, which survives a config reload, over
+/// the one saved before the last restart. As in HA, a helper with an
+/// explicit `initial` value starts from it after a restart, so the saved
+/// state is skipped when `has_initial` is set. Tracks the entity in
+/// `restore` so its future states are saved.
+pub(crate) fn restored_state(
+    restore: Option<&RestoreStateStore>,
+    states: &StateStore,
+    entity_id: &EntityId,
+    has_initial: bool,
+) -> Option<String> {
+    let current = states.get(entity_id.as_str());
+    let saved = restore.and_then(|restore| {
+        restore.track(entity_id);
+        if has_initial {
+            return None;
+        }
+        restore.last_state(entity_id.as_str())
+    });
+    current
+        .or(saved)
+        .filter(|state| state.is_available())
+        .map(|state| state.state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load_input_numbers, InputNumberConfig};
+    use ha_core::Context;
+    use tempfile::TempDir;
+
+    fn load_volume(restore: &Arc<RestoreStateStore>, yaml: &str) -> Arc<StateStore> {
+        let bus = Arc::new(EventBus::new());
+        let states = Arc::new(StateStore::new(bus.clone()));
+        restore.attach(&bus);
+        let config: HashMap<String, InputNumberConfig> = serde_yaml::from_str(yaml).unwrap();
+        load_input_numbers(&config, &states, Some(restore));
+        states
+    }
+
+    #[tokio::test]
+    async fn test_input_number_restored_after_restart() {
+        let dir = TempDir::new().unwrap();
+        let yaml = "volume: {min: 20, max: 100}";
+
+        let restore = Arc::new(RestoreStateStore::new(Arc::new(Storage::new(dir.path()))));
+        restore.load().await.unwrap();
+        let states = load_volume(&restore, yaml);
+        assert_eq!(states.get_state("input_number.volume").unwrap(), "20");
+
+        let entity_id: EntityId = "input_number.volume".parse().unwrap();
+        let attrs = states.get("input_number.volume").unwrap().attributes;
+        states.set(entity_id, "65", attrs, Context::new());
+        restore.save().await.unwrap();
+
+        // Restart with fresh state and a fresh store
+        let restore = Arc::new(RestoreStateStore::new(Arc::new(Storage::new(dir.path()))));
+        restore.load().await.unwrap();
+        let states = load_volume(&restore, yaml);
+        assert_eq!(states.get_state("input_number.volume").unwrap(), "65");
+
+        // An explicit initial value wins over the restored one
+        let restore = Arc::new(RestoreStateStore::new(Arc::new(Storage::new(dir.path()))));
+        restore.load().await.unwrap();
+        let states = load_volume(&restore, "volume: {min: 0, max: 100, initial: 30}");
+        assert_eq!(states.get_state("input_number.volume").unwrap(), "30");
+
+        // Opting out starts from the minimum
+        let restore = Arc::new(RestoreStateStore::new(Arc::new(Storage::new(dir.path()))));
+        restore.load().await.unwrap();
+        let states = load_volume(&restore, "volume: {min: 20, max: 100, restore: false}");
+        assert_eq!(states.get_state("input_number.volume").unwrap(), "20");
+    }
+
+    #[tokio::test]
+    async fn test_untracked_entities_are_not_saved() {
+        let dir = TempDir::new().unwrap();
+        let restore = Arc::new(RestoreStateStore::new(Arc::new(Storage::new(dir.path()))));
+        let bus = Arc::new(EventBus::new());
+        let states = StateStore::new(bus.clone());
+        restore.attach(&bus);
+
+        let tracked: EntityId = "input_boolean.tracked".parse().unwrap();
+        restore.track(&tracked);
+        states.set(tracked, "on", HashMap::new(), Context::new());
+        let other: EntityId = "light.kitchen".parse().unwrap();
+        states.set(other, "on", HashMap::new(), Context::new());
+        restore.save().await.unwrap();
+
+        let restore = RestoreStateStore::new(Arc::new(Storage::new(dir.path())));
+        restore.load().await.unwrap();
+        assert_eq!(
+            restore.last_state("input_boolean.tracked").unwrap().state,
+            "on"
+        );
+        assert!(restore.last_state("light.kitchen").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_load_keeps_states_with_legacy_entity_ids() {
+        let dir = TempDir::new().unwrap();
+        let storage_dir = dir.path().join(".storage");
+        std::fs::create_dir_all(&storage_dir).unwrap();
+        let state = |entity_id: &str| {
+            serde_json::json!({
+                "state": {
+                    "entity_id": entity_id,
+                    "state": "on",
+                    "last_changed": "2024-01-01T00:00:00Z",
+                    "last_updated": "2024-01-01T00:00:00Z",
+                    "context": {"id": "01HN0000000000000000000000"},
+                },
+                "last_seen": "2024-01-01T00:00:00Z",
+            })
+        };
+        let file = serde_json::json!({
+            "version": STORAGE_VERSION,
+            "minor_version": STORAGE_MINOR_VERSION,
+            "key": STORAGE_KEY,
+            "data": [state("input_boolean._legacy"), state("Input_Boolean.Mixed")],
+        });
+        std::fs::write(storage_dir.join(STORAGE_KEY), file.to_string()).unwrap();
+
+        let restore = RestoreStateStore::new(Arc::new(Storage::new(dir.path())));
+        restore.load().await.unwrap();
+        let legacy = restore.last_state("input_boolean._legacy").unwrap();
+        assert_eq!(legacy.entity_id.as_str(), "input_boolean._legacy");
+        assert!(restore.last_state("input_boolean.mixed").is_some());
+    }
+}
@@ -0,0 +1,415 @@
+//! Scene Component
+//!
+//! Implements `scene:` entities that capture the state of a set of entities
+//! and reapply it with `scene.turn_on`. A scene's state is the time it was
+//! last activated.
+
+use chrono::Utc;
+use ha_core::{Context, EntityId, ServiceCall, SupportsResponse, STATE_UNKNOWN};
+use ha_service_registry::{ServiceDescription, ServiceError, ServiceRegistry};
+use ha_state_store::StateStore;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use tracing::{debug, info, warn};
+
+const DOMAIN: &str = "scene";
+
+/// Scene configuration from YAML
+#[derive(Debug, Clone, Deserialize)]
+pub struct SceneConfig {
+    /// Unique ID
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Display name, also used for the entity ID
+    pub name: String,
+    /// Icon
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Entity states, either a plain state or a map of `state` and attributes
+    #[serde(default)]
+    pub entities: HashMap<String, serde_json::Value>,
+}
+
+/// The state a scene sets on one entity
+#[derive(Debug, Clone)]
+struct SceneEntity {
+    entity_id: EntityId,
+    state: String,
+    attributes: serde_json::Map<String, serde_json::Value>,
+}
+
+impl SceneEntity {
+    fn parse(entity_id: &str, value: &serde_json::Value) -> Result<Self, String> {
+        let entity_id = EntityId::try_from(entity_id.trim().to_lowercase())
+            .map_err(|e| format!("invalid entity '{}': {}", entity_id, e))?;
+
+        let (state, attributes) = match value {
+            serde_json::Value::Object(map) => {
+                let mut attributes = map.clone();
+                let state = attributes
+                    .remove("state")
+                    .ok_or_else(|| format!("no state given for {}", entity_id))?;
+                (state, attributes)
+            }
+            other => (other.clone(), serde_json::Map::new()),
+        };
+        let state = match state {
+            serde_json::Value::String(s) => s,
+            serde_json::Value::Bool(true) => "on".to_string(),
+            serde_json::Value::Bool(false) => "off".to_string(),
+            serde_json::Value::Number(n) => n.to_string(),
+            other => return Err(format!("invalid state for {}: {}", entity_id, other)),
+        };
+
+        Ok(Self {
+            entity_id,
+            state,
+            attributes,
+        })
+    }
+
+    /// Apply the state through the entity's integration, or write it directly
+    ///
+    /// On/off states of domains with `turn_on`/`turn_off` services go
+    /// through those services so the device actually changes, passing the
+    /// attributes (e.g. `brightness`) and `transition` along.
+    async fn apply(
+        &self,
+        services: &ServiceRegistry,
+        states: &StateStore,
+        transition: Option<f64>,
+        context: &Context,
+    ) -> Result<(), ServiceError> {
+        let domain = self.entity_id.domain();
+        let service = match self.state.as_str() {
+            "on" => Some("turn_on"),
+            "off" => Some("turn_off"),
+            _ => None,
+        }
+        .filter(|service| services.has_service(domain, service));
+
+        let Some(service) = service else {
+            let mut attributes = states
+                .get(self.entity_id.as_str())
+                .map(|current| current.attributes)
+                .unwrap_or_default();
+            attributes.extend(self.attributes.clone());
+            states.set(
+                self.entity_id.clone(),
+                self.state.as_str(),
+                attributes,
+                context.clone(),
+            );
+            return Ok(());
+        };
+
+        let mut data = match service {
+            "turn_on" => self.attributes.clone(),
+            _ => serde_json::Map::new(),
+        };
+        data.insert("entity_id".to_string(), json!(self.entity_id.to_string()));
+        if let Some(transition) = transition {
+            data.insert("transition".to_string(), json!(transition));
+        }
+        services
+            .call(
+                domain,
+                service,
+                serde_json::Value::Object(data),
+                context.clone(),
+                false,
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Slugify an entity name for use as its object_id
+pub(crate) fn slugify(name: &str) -> String {
+    let mut result = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            result.extend(c.to_lowercase());
+        } else if !result.is_empty() && !result.ends_with('_') {
+            result.push('_');
+        }
+    }
+    result.trim_end_matches('_').to_string()
+}
+
+/// Load scene entities from config and register `scene.turn_on` to apply them
+///
+/// Scenes with invalid entity states are skipped as a whole, so a scene is
+/// never applied partially. Calling this again replaces the loaded scenes.
+pub fn load_scenes(
+    config: &[SceneConfig],
+    services: &Arc<ServiceRegistry>,
+    states: Arc<StateStore>,
+) -> usize {
+    let mut scenes: HashMap<String, Vec<SceneEntity>> = HashMap::new();
+
+    for config in config {
+        let entity_id = match EntityId::new(DOMAIN, slugify(&config.name)) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid scene name '{}': {}", config.name, e);
+                continue;
+            }
+        };
+
+        let mut entities = match config
+            .entities
+            .iter()
+            .map(|(entity_id, value)| SceneEntity::parse(entity_id, value))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(entities) => entities,
+            Err(e) => {
+                warn!("Invalid scene '{}': {}", config.name, e);
+                continue;
+            }
+        };
+        entities.sort_by_key(|entity| entity.entity_id.to_string());
+
+        let mut attributes = HashMap::new();
+        let members: Vec<String> = entities.iter().map(|e| e.entity_id.to_string()).collect();
+        attributes.insert("entity_id".to_string(), json!(members));
+        attributes.insert("friendly_name".to_string(), json!(config.name));
+        if let Some(id) = &config.id {
+            attributes.insert("id".to_string(), json!(id));
+        }
+        if let Some(icon) = &config.icon {
+            attributes.insert("icon".to_string(), json!(icon));
+        }
+
+        states.set(entity_id.clone(), STATE_UNKNOWN, attributes, Context::new());
+        debug!("Loaded {} with {} entities", entity_id, entities.len());
+        scenes.insert(entity_id.to_string(), entities);
+    }
+
+    let count = scenes.len();
+    if count > 0 {
+        info!("Loaded {} scene entities", count);
+    }
+
+    register_turn_on(services, states, scenes);
+    count
+}
+
+fn register_turn_on(
+    services: &Arc<ServiceRegistry>,
+    states: Arc<StateStore>,
+    scenes: HashMap<String, Vec<SceneEntity>>,
+) {
+    let scenes = Arc::new(scenes);
+    // The handler lives in the registry, so it must not keep it alive
+    let registry: Weak<ServiceRegistry> = Arc::downgrade(services);
+
+    services.register_with_description(
+        ServiceDescription {
+            domain: DOMAIN.to_string(),
+            service: "turn_on".to_string(),
+            name: Some("Activate".to_string()),
+            description: Some("Activate a scene".to_string()),
+            schema: Some(json!({
+                "transition": {
+                    "required": false,
+                    "selector": {"number": {"min": 0, "max": 300, "unit_of_measurement": "s"}}
+                }
+            })),
+            target: Some(json!({"entity": {"domain": DOMAIN}})),
+            supports_response: SupportsResponse::None,
+        },
+        move |call: ServiceCall| {
+            let scenes = scenes.clone();
+            let states = states.clone();
+            let registry = registry.clone();
+            async move {
+                let Some(services) = registry.upgrade() else {
+                    return Ok(None);
+                };
+                let transition = match call.service_data.get("transition") {
+                    Some(value) => Some(value.as_f64().filter(|t| *t >= 0.0).ok_or_else(|| {
+                        ServiceError::InvalidData(format!("Invalid transition: {}", value))
+                    })?),
+                    None => None,
+                };
+
+                // Resolve every scene before touching any entity
+                let targets = crate::input_helpers::get_target_entities(&call, DOMAIN);
+                let mut activated = Vec::new();
+                for entity_id in targets {
+                    let entities = scenes.get(entity_id.as_str()).ok_or_else(|| {
+                        ServiceError::InvalidData(format!("Unknown scene: {}", entity_id))
+                    })?;
+                    activated.push((entity_id, entities));
+                }
+
+                for (entity_id, entities) in activated {
+                    for entity in entities {
+                        entity
+                            .apply(&services, &states, transition, &call.context)
+                            .await?;
+                    }
+
+                    if let Some(current) = states.get(entity_id.as_str()) {
+                        states.set(
+                            entity_id,
+                            Utc::now().to_rfc3339(),
+                            current.attributes,
+                            call.context.clone(),
+                        );
+                    }
+                }
+                Ok(None)
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Registry with light services that record their calls and set states
+    fn setup() -> (
+        Arc<ServiceRegistry>,
+        Arc<StateStore>,
+        Arc<Mutex<Vec<ServiceCall>>>,
+    ) {
+        let bus = Arc::new(ha_event_bus::EventBus::new());
+        let states = Arc::new(StateStore::new(bus));
+        let services = Arc::new(ServiceRegistry::new());
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        for (service, state) in [("turn_on", "on"), ("turn_off", "off")] {
+            let states = states.clone();
+            let calls = calls.clone();
+            services.register(
+                "light",
+                service,
+                move |call: ServiceCall| {
+                    let states = states.clone();
+                    let calls = calls.clone();
+                    async move {
+                        let entity_id: EntityId = call.service_data["entity_id"]
+                            .as_str()
+                            .unwrap()
+                            .parse()
+                            .unwrap();
+                        let mut attributes = HashMap::new();
+                        if let Some(brightness) = call.service_data.get("brightness") {
+                            attributes.insert("brightness".to_string(), brightness.clone());
+                        }
+                        states.set(entity_id, state, attributes, call.context.clone());
+                        calls.lock().unwrap().push(call);
+                        Ok(None)
+                    }
+                },
+                None,
+                SupportsResponse::None,
+            );
+        }
+        (services, states, calls)
+    }
+
+    #[tokio::test]
+    async fn test_scene_sets_light_brightness() {
+        let (services, states, calls) = setup();
+        let config: Vec<SceneConfig> = serde_yaml::from_str(
+            r#"
+            - name: Movie Night
+              entities:
+                light.couch:
+                  state: "on"
+                  brightness: 80
+                light.ceiling:
+                  state: "on"
+                  brightness: 200
+                light.hallway: "off"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(load_scenes(&config, &services, states.clone()), 1);
+        let scene = states.get("scene.movie_night").unwrap();
+        assert_eq!(scene.state, STATE_UNKNOWN);
+        assert_eq!(
+            scene.attributes["entity_id"],
+            json!(["light.ceiling", "light.couch", "light.hallway"])
+        );
+
+        let data = json!({"entity_id": "scene.movie_night", "transition": 2.5});
+        services
+            .call(DOMAIN, "turn_on", data, Context::new(), false)
+            .await
+            .unwrap();
+
+        let couch = states.get("light.couch").unwrap();
+        assert_eq!(couch.state, "on");
+        assert_eq!(couch.attributes["brightness"], 80);
+        let ceiling = states.get("light.ceiling").unwrap();
+        assert_eq!(ceiling.attributes["brightness"], 200);
+        assert_eq!(states.get_state("light.hallway").unwrap(), "off");
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 3);
+        assert!(calls
+            .iter()
+            .all(|call| call.service_data["transition"] == 2.5));
+
+        let activated = states.get_state("scene.movie_night").unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(&activated).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_scene_writes_states_without_services() {
+        let (services, states, _) = setup();
+        let config: Vec<SceneConfig> = serde_yaml::from_str(
+            r#"
+            - name: Away
+              entities:
+                input_select.mode: away
+                climate.living_room:
+                  state: heat
+                  temperature: 17
+            "#,
+        )
+        .unwrap();
+        load_scenes(&config, &services, states.clone());
+
+        services
+            .call(
+                DOMAIN,
+                "turn_on",
+                json!({"entity_id": "scene.away"}),
+                Context::new(),
+                false,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(states.get_state("input_select.mode").unwrap(), "away");
+        let climate = states.get("climate.living_room").unwrap();
+        assert_eq!(climate.state, "heat");
+        assert_eq!(climate.attributes["temperature"], 17);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_scene_applies_nothing() {
+        let (services, states, calls) = setup();
+        let config: Vec<SceneConfig> =
+            serde_yaml::from_str("[{name: Bright, entities: {light.couch: 'on'}}]").unwrap();
+        load_scenes(&config, &services, states.clone());
+
+        let data = json!({"entity_id": ["scene.bright", "scene.missing"]});
+        let result = services
+            .call(DOMAIN, "turn_on", data, Context::new(), false)
+            .await;
+        assert!(matches!(result, Err(ServiceError::InvalidData(_))));
+        assert!(calls.lock().unwrap().is_empty());
+        assert_eq!(states.get_state("scene.bright").unwrap(), STATE_UNKNOWN);
+    }
+}
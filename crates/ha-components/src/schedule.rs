@@ -0,0 +1,330 @@
+//! Schedule Component
+//!
+//! Implements `schedule:` entities defined by weekly time windows. A schedule
+//! is "on" while the local time falls inside one of its windows and "off"
+//! otherwise; a task per schedule re-evaluates it at each window boundary.
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, Timelike};
+use ha_core::{Context, EntityId};
+use ha_state_store::StateStore;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+const DOMAIN: &str = "schedule";
+
+const DAY_SECONDS: u32 = 24 * 60 * 60;
+const WEEK_SECONDS: u32 = 7 * DAY_SECONDS;
+
+/// A time window within a day
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScheduleWindow {
+    /// Start time (`HH:MM` or `HH:MM:SS`)
+    pub from: String,
+    /// End time; `24:00:00` ends at midnight, and an end before the start
+    /// runs past midnight into the next day
+    pub to: String,
+}
+
+/// Schedule configuration from YAML
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ScheduleConfig {
+    /// Display name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Icon
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub monday: Vec<ScheduleWindow>,
+    #[serde(default)]
+    pub tuesday: Vec<ScheduleWindow>,
+    #[serde(default)]
+    pub wednesday: Vec<ScheduleWindow>,
+    #[serde(default)]
+    pub thursday: Vec<ScheduleWindow>,
+    #[serde(default)]
+    pub friday: Vec<ScheduleWindow>,
+    #[serde(default)]
+    pub saturday: Vec<ScheduleWindow>,
+    #[serde(default)]
+    pub sunday: Vec<ScheduleWindow>,
+}
+
+impl ScheduleConfig {
+    /// Windows per weekday, starting on Monday
+    fn days(&self) -> [&[ScheduleWindow]; 7] {
+        [
+            &self.monday,
+            &self.tuesday,
+            &self.wednesday,
+            &self.thursday,
+            &self.friday,
+            &self.saturday,
+            &self.sunday,
+        ]
+    }
+}
+
+/// Parse a time of day into seconds since midnight, allowing `24:00`
+fn parse_time_of_day(value: &str) -> Option<u32> {
+    let value = value.trim();
+    if matches!(value, "24:00" | "24:00:00") {
+        return Some(DAY_SECONDS);
+    }
+    crate::input_helpers::parse_time(value).map(|time| time.num_seconds_from_midnight())
+}
+
+/// Seconds since Monday midnight
+fn seconds_of_week(now: NaiveDateTime) -> u32 {
+    now.weekday().num_days_from_monday() * DAY_SECONDS + now.num_seconds_from_midnight()
+}
+
+/// Weekly windows as `[start, end)` offsets from Monday midnight
+///
+/// A window running past Sunday midnight ends after `WEEK_SECONDS` and
+/// wraps around to the start of the week.
+#[derive(Debug, Default)]
+struct WeeklyWindows(Vec<(u32, u32)>);
+
+impl WeeklyWindows {
+    fn from_config(id: &str, config: &ScheduleConfig) -> Self {
+        let mut windows = Vec::new();
+        for (day, day_windows) in (0..).zip(config.days()) {
+            for window in day_windows {
+                let (Some(from), Some(to)) = (
+                    parse_time_of_day(&window.from),
+                    parse_time_of_day(&window.to),
+                ) else {
+                    warn!(
+                        "Invalid window {}-{} in schedule '{}'",
+                        window.from, window.to, id
+                    );
+                    continue;
+                };
+                if from == to {
+                    warn!("Empty window {} in schedule '{}'", window.from, id);
+                    continue;
+                }
+                let start = day * DAY_SECONDS + from;
+                let mut end = day * DAY_SECONDS + to;
+                if to < from {
+                    end += DAY_SECONDS;
+                }
+                windows.push((start, end));
+            }
+        }
+        Self(windows)
+    }
+
+    /// Whether a point in the week falls inside any window
+    fn contains(&self, seconds: u32) -> bool {
+        self.0.iter().any(|&(start, end)| {
+            (start..end).contains(&seconds) || (start..end).contains(&(seconds + WEEK_SECONDS))
+        })
+    }
+
+    /// Seconds from a point in the week until the state next changes
+    ///
+    /// Boundaries inside overlapping windows don't change the state and are
+    /// skipped.
+    fn next_change(&self, seconds: u32) -> Option<u32> {
+        let on = self.contains(seconds);
+        let mut boundaries: Vec<u32> = self
+            .0
+            .iter()
+            .flat_map(|&(start, end)| [start, end])
+            .map(|boundary| (boundary + WEEK_SECONDS - seconds) % WEEK_SECONDS)
+            .filter(|&offset| offset > 0)
+            .collect();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+        boundaries
+            .into_iter()
+            .find(|offset| self.contains((seconds + offset) % WEEK_SECONDS) != on)
+    }
+}
+
+/// A loaded schedule entity
+struct Schedule {
+    entity_id: EntityId,
+    windows: WeeklyWindows,
+    attributes: HashMap<String, serde_json::Value>,
+}
+
+impl Schedule {
+    /// Write the schedule state for `now`, returning how long until it changes
+    fn update(&self, states: &StateStore, now: DateTime<Local>) -> Option<Duration> {
+        let seconds = seconds_of_week(now.naive_local());
+        let state = if self.windows.contains(seconds) {
+            "on"
+        } else {
+            "off"
+        };
+        let next_change = self.windows.next_change(seconds);
+
+        let mut attributes = self.attributes.clone();
+        if let Some(offset) = next_change {
+            let next_event = now.with_nanosecond(0).unwrap_or(now)
+                + chrono::Duration::seconds(i64::from(offset));
+            attributes.insert("next_event".to_string(), json!(next_event.to_rfc3339()));
+        }
+        states.set(self.entity_id.clone(), state, attributes, Context::new());
+
+        next_change.map(|offset| {
+            Duration::from_secs(u64::from(offset))
+                .saturating_sub(Duration::from_nanos(u64::from(now.nanosecond())))
+        })
+    }
+}
+
+/// Load schedule entities from config and keep them up to date
+///
+/// Spawns a task per schedule that re-evaluates it at its next window
+/// boundary, so this must be called from within a tokio runtime.
+pub fn load_schedules(config: &HashMap<String, ScheduleConfig>, states: Arc<StateStore>) -> usize {
+    let mut count = 0;
+
+    for (id, config) in config {
+        let entity_id = match EntityId::new(DOMAIN, id) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid schedule id '{}': {}", id, e);
+                continue;
+            }
+        };
+
+        let mut attributes = HashMap::new();
+        if let Some(name) = &config.name {
+            attributes.insert("friendly_name".to_string(), json!(name));
+        }
+        if let Some(icon) = &config.icon {
+            attributes.insert("icon".to_string(), json!(icon));
+        }
+        attributes.insert("editable".to_string(), json!(false));
+
+        let schedule = Schedule {
+            entity_id,
+            windows: WeeklyWindows::from_config(id, config),
+            attributes,
+        };
+        let Some(mut wait) = schedule.update(&states, Local::now()) else {
+            debug!("Loaded schedule.{}, which never changes state", id);
+            count += 1;
+            continue;
+        };
+
+        let states = states.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(wait).await;
+                match schedule.update(&states, Local::now()) {
+                    Some(next) => wait = next,
+                    None => break,
+                }
+            }
+        });
+        debug!("Loaded schedule.{}", id);
+        count += 1;
+    }
+
+    if count > 0 {
+        info!("Loaded {} schedule entities", count);
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn windows(yaml: &str) -> WeeklyWindows {
+        let config: ScheduleConfig = serde_yaml::from_str(yaml).unwrap();
+        WeeklyWindows::from_config("test", &config)
+    }
+
+    /// Seconds of the week for a time in the week of Monday 2024-01-01
+    fn at(day: u32, time: &str) -> u32 {
+        let date = NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        let time = crate::input_helpers::parse_time(time).unwrap();
+        seconds_of_week(date.and_time(time))
+    }
+
+    #[test]
+    fn test_schedule_on_during_window_and_off_at_boundary() {
+        let windows = windows("monday: [{from: '07:00', to: '09:30'}]");
+
+        assert!(!windows.contains(at(1, "06:59:59")));
+        assert_eq!(windows.next_change(at(1, "06:59:59")), Some(1));
+        assert!(windows.contains(at(1, "07:00")));
+        assert!(windows.contains(at(1, "09:29:59")));
+        assert_eq!(windows.next_change(at(1, "08:00")), Some(90 * 60));
+        assert!(!windows.contains(at(1, "09:30")));
+        assert!(!windows.contains(at(2, "08:00")));
+        assert_eq!(
+            windows.next_change(at(1, "09:30")),
+            Some(WEEK_SECONDS - 150 * 60)
+        );
+    }
+
+    #[test]
+    fn test_schedule_window_spanning_midnight() {
+        let windows = windows(
+            r#"
+            tuesday: [{from: '22:00', to: '06:00'}]
+            sunday: [{from: '23:00', to: '24:00'}, {from: '23:30', to: '01:00'}]
+            "#,
+        );
+
+        assert!(windows.contains(at(2, "23:00")));
+        assert!(windows.contains(at(3, "05:59")));
+        assert!(!windows.contains(at(3, "06:00")));
+
+        // Sunday's window wraps around to Monday morning
+        assert!(windows.contains(at(7, "23:15")));
+        assert!(windows.contains(at(1, "00:30")));
+        assert!(!windows.contains(at(1, "01:00")));
+    }
+
+    #[test]
+    fn test_schedule_overlapping_windows_merge() {
+        let windows =
+            windows("friday: [{from: '08:00', to: '12:00'}, {from: '10:00', to: '14:00'}]");
+
+        assert!(windows.contains(at(5, "11:00")));
+        assert!(windows.contains(at(5, "13:00")));
+        // The first window's end doesn't turn the schedule off
+        assert_eq!(windows.next_change(at(5, "09:00")), Some(5 * 60 * 60));
+    }
+
+    #[tokio::test]
+    async fn test_load_schedules_sets_state() {
+        let bus = Arc::new(ha_event_bus::EventBus::new());
+        let states = Arc::new(StateStore::new(bus));
+        let config: HashMap<String, ScheduleConfig> = serde_yaml::from_str(
+            r#"
+            always:
+              monday: [{from: '00:00', to: '24:00'}]
+              tuesday: [{from: '00:00', to: '24:00'}]
+              wednesday: [{from: '00:00', to: '24:00'}]
+              thursday: [{from: '00:00', to: '24:00'}]
+              friday: [{from: '00:00', to: '24:00'}]
+              saturday: [{from: '00:00', to: '24:00'}]
+              sunday: [{from: '00:00', to: '24:00'}]
+            never:
+              name: Never
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(load_schedules(&config, states.clone()), 2);
+        assert_eq!(states.get_state("schedule.always").unwrap(), "on");
+        let never = states.get("schedule.never").unwrap();
+        assert_eq!(never.state, "off");
+        assert!(!never.attributes.contains_key("next_event"));
+    }
+}
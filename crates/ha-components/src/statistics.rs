@@ -0,0 +1,323 @@
+//! Statistics Component
+//!
+//! Implements a basic `statistics` sensor platform: a sensor keeping the
+//! recent numeric samples of one source sensor and reporting a
+//! characteristic of them, like their mean or how much they changed.
+//! Samples are bounded by `sampling_size` and, optionally, `max_age`.
+
+use chrono::{DateTime, Utc};
+use ha_core::{Context, EntityId, STATE_UNKNOWN};
+use ha_event_bus::helpers::track_state_change_event;
+use ha_event_bus::{EventBus, ListenerId};
+use ha_state_store::StateStore;
+use serde::{Deserialize, Deserializer};
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::min_max::{format_value, numeric_state, Aggregate};
+use crate::scene::slugify;
+use crate::timer::parse_duration;
+
+/// Default number of samples kept
+const DEFAULT_SAMPLING_SIZE: usize = 20;
+
+/// Default number of decimals of computed values
+const DEFAULT_PRECISION: u32 = 2;
+
+/// How often sensors with a `max_age` drop expired samples
+const EXPIRE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// What a statistics sensor reports about its samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StateCharacteristic {
+    Mean,
+    Median,
+    ValueMin,
+    ValueMax,
+    Sum,
+    /// Number of samples
+    Count,
+    /// Difference between the newest and the oldest sample
+    Change,
+}
+
+impl StateCharacteristic {
+    /// Compute the characteristic of `values`, oldest first
+    fn apply(self, values: &[f64]) -> Option<f64> {
+        let aggregate = match self {
+            Self::Mean => Aggregate::Mean,
+            Self::Median => Aggregate::Median,
+            Self::ValueMin => Aggregate::Min,
+            Self::ValueMax => Aggregate::Max,
+            Self::Sum => Aggregate::Sum,
+            Self::Count => return Some(values.len() as f64),
+            Self::Change => return Some(values.last()? - values.first()?),
+        };
+        aggregate.apply(values)
+    }
+}
+
+/// Statistics sensor configuration from YAML
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatisticsConfig {
+    /// Display name, also used for the entity_id
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub unique_id: Option<String>,
+    /// Source sensor
+    pub entity_id: String,
+    pub state_characteristic: StateCharacteristic,
+    /// Maximum number of samples kept (default: 20)
+    #[serde(default = "default_sampling_size")]
+    pub sampling_size: usize,
+    /// Maximum age of samples, as seconds or `HH:MM:SS` (default: unlimited)
+    #[serde(default, deserialize_with = "deserialize_max_age")]
+    pub max_age: Option<Duration>,
+    /// Decimals of the computed value (default: 2)
+    #[serde(default)]
+    pub precision: Option<u32>,
+}
+
+fn default_sampling_size() -> usize {
+    DEFAULT_SAMPLING_SIZE
+}
+
+fn deserialize_max_age<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    parse_duration(&value)
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid max_age: {}", value)))
+}
+
+/// A loaded statistics sensor
+struct StatisticsSensor {
+    entity_id: EntityId,
+    config: StatisticsConfig,
+    samples: Mutex<VecDeque<(DateTime<Utc>, f64)>>,
+}
+
+impl StatisticsSensor {
+    /// Add a sample of the source
+    fn add_sample(&self, time: DateTime<Utc>, value: f64) {
+        let mut samples = self.samples.lock().unwrap();
+        samples.push_back((time, value));
+        while samples.len() > self.config.sampling_size {
+            samples.pop_front();
+        }
+    }
+
+    /// Drop expired samples, recompute and write the sensor
+    fn update(&self, states: &StateStore, context: Context) {
+        let values: Vec<f64> = {
+            let mut samples = self.samples.lock().unwrap();
+            if let Some(max_age) = self
+                .config
+                .max_age
+                .and_then(|age| chrono::Duration::from_std(age).ok())
+            {
+                let oldest = Utc::now() - max_age;
+                samples.retain(|(time, _)| *time >= oldest);
+            }
+            samples.iter().map(|(_, value)| *value).collect()
+        };
+
+        let mut attributes = HashMap::new();
+        attributes.insert("icon".to_string(), json!("mdi:calculator"));
+        attributes.insert("state_class".to_string(), json!("measurement"));
+        if let Some(name) = &self.config.name {
+            attributes.insert("friendly_name".to_string(), json!(name));
+        }
+        let unit = states
+            .get(&self.config.entity_id)
+            .and_then(|source| source.attributes.get("unit_of_measurement").cloned());
+        if let (Some(unit), false) = (
+            unit,
+            self.config.state_characteristic == StateCharacteristic::Count,
+        ) {
+            attributes.insert("unit_of_measurement".to_string(), unit);
+        }
+        attributes.insert(
+            "buffer_usage_ratio".to_string(),
+            json!(values.len() as f64 / self.config.sampling_size.max(1) as f64),
+        );
+
+        let state = match self.config.state_characteristic.apply(&values) {
+            Some(count) if self.config.state_characteristic == StateCharacteristic::Count => {
+                (count as usize).to_string()
+            }
+            Some(value) => format_value(value, self.config.precision.unwrap_or(DEFAULT_PRECISION)),
+            None => STATE_UNKNOWN.to_string(),
+        };
+        states.set(self.entity_id.clone(), state, attributes, context);
+    }
+}
+
+/// Load statistics sensors from config and feed them their sources' states
+///
+/// Each sensor starts with its source's current state as the first sample.
+/// Sensors with a `max_age` also drop expired samples every
+/// `EXPIRE_INTERVAL`, so this must be called from within a tokio runtime.
+pub fn load_statistics_sensors(
+    config: &[StatisticsConfig],
+    event_bus: &EventBus,
+    states: Arc<StateStore>,
+) -> ListenerId {
+    let mut sensors = Vec::new();
+
+    for config in config {
+        let object_id = config
+            .name
+            .as_deref()
+            .or(config.unique_id.as_deref())
+            .map(slugify)
+            .filter(|slug| !slug.is_empty())
+            .unwrap_or_else(|| "statistics".to_string());
+        let entity_id = match EntityId::new("sensor", &object_id) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid statistics sensor '{}': {}", object_id, e);
+                continue;
+            }
+        };
+
+        let mut config = config.clone();
+        config.entity_id = config.entity_id.trim().to_lowercase();
+        let sensor = StatisticsSensor {
+            entity_id,
+            config,
+            samples: Mutex::new(VecDeque::new()),
+        };
+        if let Some(source) = states.get(&sensor.config.entity_id) {
+            if let Some(value) = numeric_state(&source) {
+                sensor.add_sample(source.last_updated, value);
+            }
+        }
+        sensor.update(&states, Context::new());
+        debug!(
+            "Loaded statistics {} of {}",
+            sensor.entity_id, sensor.config.entity_id
+        );
+        sensors.push(Arc::new(sensor));
+    }
+
+    if !sensors.is_empty() {
+        info!("Loaded {} statistics sensors", sensors.len());
+    }
+
+    let expiring: Vec<Arc<StatisticsSensor>> = sensors
+        .iter()
+        .filter(|sensor| sensor.config.max_age.is_some())
+        .cloned()
+        .collect();
+    if !expiring.is_empty() {
+        let states = states.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EXPIRE_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                for sensor in &expiring {
+                    sensor.update(&states, Context::new());
+                }
+            }
+        });
+    }
+
+    // Index sensors by source so a state change only touches its sensors
+    let mut by_source: HashMap<String, Vec<Arc<StatisticsSensor>>> = HashMap::new();
+    for sensor in sensors {
+        by_source
+            .entry(sensor.config.entity_id.clone())
+            .or_default()
+            .push(sensor);
+    }
+
+    let sources: Vec<String> = by_source.keys().cloned().collect();
+    track_state_change_event(
+        event_bus,
+        sources,
+        Arc::new(move |event| {
+            let Some(sensors) = by_source.get(event.data.entity_id.as_str()) else {
+                return;
+            };
+            // Every numeric update is a sample, other states are skipped
+            let sample = event
+                .data
+                .new_state
+                .as_ref()
+                .and_then(|state| Some((state.last_updated, numeric_state(state)?)));
+            for sensor in sensors {
+                if let Some((time, value)) = sample {
+                    sensor.add_sample(time, value);
+                }
+                sensor.update(&states, event.context.clone());
+            }
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(yaml: &str) -> Arc<StateStore> {
+        let bus = Arc::new(EventBus::new());
+        let states = Arc::new(StateStore::new(bus.clone()));
+        set(&states, "sensor.outside", "10");
+        let config: Vec<StatisticsConfig> = serde_yaml::from_str(yaml).unwrap();
+        load_statistics_sensors(&config, &bus, states.clone());
+        states
+    }
+
+    fn set(states: &StateStore, entity_id: &str, state: &str) {
+        let entity_id: EntityId = entity_id.parse().unwrap();
+        let attributes = HashMap::from([("unit_of_measurement".to_string(), json!("°C"))]);
+        states.set(entity_id, state, attributes, Context::new());
+    }
+
+    #[tokio::test]
+    async fn test_mean_over_sampling_size() {
+        let states = setup(
+            r#"
+            - name: Outside Mean
+              entity_id: sensor.outside
+              state_characteristic: mean
+              sampling_size: 3
+            - name: Outside Samples
+              entity_id: sensor.outside
+              state_characteristic: count
+              max_age: "01:00:00"
+            "#,
+        );
+        assert_eq!(states.get_state("sensor.outside_mean").unwrap(), "10.0");
+
+        for value in ["12", "unavailable", "14", "16"] {
+            set(&states, "sensor.outside", value);
+        }
+        // 10 fell out of the buffer, the unavailable state was skipped
+        let mean = states.get("sensor.outside_mean").unwrap();
+        assert_eq!(mean.state, "14.0");
+        assert_eq!(mean.attributes["unit_of_measurement"], "°C");
+        assert_eq!(mean.attributes["buffer_usage_ratio"], 1.0);
+
+        let count = states.get("sensor.outside_samples").unwrap();
+        assert_eq!(count.state, "4");
+        assert!(!count.attributes.contains_key("unit_of_measurement"));
+    }
+
+    #[test]
+    fn test_characteristics() {
+        let values = [4.0, 1.0, 7.0];
+        assert_eq!(StateCharacteristic::ValueMax.apply(&values), Some(7.0));
+        assert_eq!(StateCharacteristic::Change.apply(&values), Some(3.0));
+        assert_eq!(StateCharacteristic::Count.apply(&[]), Some(0.0));
+        assert_eq!(StateCharacteristic::Change.apply(&[]), None);
+    }
+}
@@ -176,6 +176,26 @@ impl LogEntry {
         }
     }
 
+    /// Format as log file lines, like HA's `home-assistant.log`
+    ///
+    /// Produces `<timestamp> <LEVEL> (MainThread) [<logger>] <message>` for
+    /// each stored message, followed by the exception trace if any.
+    pub fn to_log_lines(&self) -> String {
+        let timestamp = self.timestamp.format("%Y-%m-%d %H:%M:%S%.3f");
+        let mut text = String::new();
+        for message in &self.messages {
+            text.push_str(&format!(
+                "{} {} (MainThread) [{}] {}\n",
+                timestamp, self.level, self.name, message
+            ));
+        }
+        if !self.exception.is_empty() {
+            text.push_str(self.exception.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
     /// Convert to dictionary format for API responses
     pub fn to_dict(&self) -> serde_json::Value {
         serde_json::json!({
@@ -287,6 +307,11 @@ impl DedupStore {
         self.entries.iter().rev().map(|e| e.to_dict()).collect()
     }
 
+    /// Get all entries (oldest first)
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
     /// Get the number of entries
     pub fn len(&self) -> usize {
         self.entries.len()
@@ -361,6 +386,66 @@ impl SystemLog {
         self.store.read().map(|s| s.to_list()).unwrap_or_default()
     }
 
+    /// Get the entries logged by an integration, as log file lines
+    ///
+    /// Matches the `homeassistant.components.<domain>` and
+    /// `custom_components.<domain>` loggers and their children.
+    pub fn integration_log_lines(&self, domain: &str) -> Vec<String> {
+        let Ok(store) = self.store.read() else {
+            return Vec::new();
+        };
+        let loggers = [
+            format!("homeassistant.components.{}", domain),
+            format!("custom_components.{}", domain),
+        ];
+        store
+            .entries()
+            .iter()
+            .filter(|entry| {
+                loggers.iter().any(|logger| {
+                    entry.name == *logger || entry.name.starts_with(&format!("{}.", logger))
+                })
+            })
+            .map(LogEntry::to_log_lines)
+            .collect()
+    }
+
+    /// Render warnings and errors as log file text, oldest first
+    ///
+    /// When the text exceeds `max_bytes`, the oldest entries are dropped so
+    /// the most recent problems are always included. A newest entry that's
+    /// too long on its own is cut off at `max_bytes`.
+    pub fn error_log(&self, max_bytes: usize) -> String {
+        let Ok(store) = self.store.read() else {
+            return String::new();
+        };
+
+        let mut blocks = Vec::new();
+        let mut size = 0;
+        for entry in store.entries().iter().rev() {
+            if matches!(entry.level, LogLevel::Debug | LogLevel::Info) {
+                continue;
+            }
+            let mut block = entry.to_log_lines();
+            if size + block.len() > max_bytes {
+                if blocks.is_empty() {
+                    let mut end = max_bytes;
+                    while !block.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    block.truncate(end);
+                    blocks.push(block);
+                }
+                break;
+            }
+            size += block.len();
+            blocks.push(block);
+        }
+
+        blocks.reverse();
+        blocks.concat()
+    }
+
     /// Get the number of entries
     pub fn len(&self) -> usize {
         self.store.read().map(|s| s.len()).unwrap_or(0)
@@ -639,4 +724,70 @@ mod tests {
         log.clear();
         assert!(log.is_empty());
     }
+
+    #[test]
+    fn test_error_log_text() {
+        let log = SystemLog::with_defaults();
+        log.log("homeassistant.core", LogLevel::Info, "Started", None, None);
+        log.log(
+            "custom.a",
+            LogLevel::Warning,
+            "First",
+            Some("a.rs"),
+            Some(1),
+        );
+        log.log("custom.b", LogLevel::Error, "Second", Some("b.rs"), Some(2));
+
+        let text = log.error_log(usize::MAX);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("WARNING (MainThread) [custom.a] First"));
+        assert!(lines[1].ends_with("ERROR (MainThread) [custom.b] Second"));
+
+        // Oldest entries are dropped first when over the size limit
+        let text = log.error_log(lines[1].len() + 1);
+        assert_eq!(text.lines().count(), 1);
+        assert!(text.contains("Second"));
+
+        // An entry too long on its own is cut off rather than left out
+        let text = log.error_log(20);
+        assert_eq!(text, lines[1][..20]);
+    }
+
+    #[test]
+    fn test_integration_log_lines() {
+        let log = SystemLog::with_defaults();
+        log.log(
+            "homeassistant.components.hue",
+            LogLevel::Error,
+            "Bridge gone",
+            None,
+            None,
+        );
+        log.log(
+            "homeassistant.components.hue.light",
+            LogLevel::Warning,
+            "Slow",
+            None,
+            None,
+        );
+        log.log(
+            "homeassistant.components.hue_ble",
+            LogLevel::Error,
+            "Other",
+            None,
+            None,
+        );
+        log.log(
+            "custom_components.hue",
+            LogLevel::Info,
+            "Custom",
+            None,
+            None,
+        );
+
+        let lines = log.integration_log_lines("hue");
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| !line.contains("Other")));
+    }
 }
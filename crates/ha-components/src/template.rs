@@ -0,0 +1,344 @@
+//! Template Component
+//!
+//! Implements the `template:` integration's `sensor` and `binary_sensor`
+//! entities, whose state is a rendered template. An entity re-renders when
+//! something its templates read changes, as recorded in the engine's
+//! [`RenderInfo`], and once a minute when they use the current time.
+
+use ha_core::events::STATE_CHANGED;
+use ha_core::{Context, EntityId, STATE_UNAVAILABLE, STATE_UNKNOWN};
+use ha_event_bus::helpers::Throttle;
+use ha_event_bus::{EventBus, ListenerId};
+use ha_state_store::StateStore;
+use ha_template::{RenderInfo, TemplateEngine};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::scene::slugify;
+
+/// Minimum time between re-renders of an entity
+const UPDATE_WINDOW: Duration = Duration::from_millis(100);
+
+/// How often entities whose templates use the current time re-render
+const TIME_INTERVAL: Duration = Duration::from_secs(60);
+
+/// One item of the `template:` list
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TemplateConfig {
+    #[serde(default)]
+    pub sensor: Vec<TemplateEntityConfig>,
+    #[serde(default)]
+    pub binary_sensor: Vec<TemplateEntityConfig>,
+}
+
+/// A template sensor or binary sensor
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateEntityConfig {
+    /// Display name, also used for the entity_id
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub unique_id: Option<String>,
+    /// Template rendering the state
+    pub state: String,
+    /// Template deciding whether the entity is available (default: always)
+    #[serde(default)]
+    pub availability: Option<String>,
+    /// Templates rendering extra attributes
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+    /// Template rendering the device class
+    #[serde(default)]
+    pub device_class: Option<String>,
+    #[serde(default)]
+    pub unit_of_measurement: Option<String>,
+    #[serde(default)]
+    pub state_class: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+/// A loaded template entity
+struct TemplateEntity {
+    entity_id: EntityId,
+    config: TemplateEntityConfig,
+    /// What the templates read during the last render
+    info: Mutex<RenderInfo>,
+}
+
+impl TemplateEntity {
+    /// Render the templates and write the resulting state
+    fn update(&self, states: &StateStore, engine: &TemplateEngine, context: Context) {
+        let mut info = RenderInfo::default();
+        let mut render = |template: &str| {
+            let (result, rendered) = engine.render_with_info(template, HashMap::new());
+            info.merge(rendered);
+            result
+                .map(|value| value.trim().to_string())
+                .map_err(|e| warn!("Error rendering template for {}: {}", self.entity_id, e))
+                .ok()
+        };
+
+        let available = self
+            .config
+            .availability
+            .as_deref()
+            .map_or(true, |template| {
+                render(template).is_some_and(|v| is_true(&v))
+            });
+        let rendered = render(&self.config.state);
+
+        let mut attributes = HashMap::new();
+        for (name, template) in &self.config.attributes {
+            if let Some(value) = render(template) {
+                // Rendered lists, numbers and booleans keep their type
+                let value = serde_json::from_str(&value).unwrap_or_else(|_| json!(value));
+                attributes.insert(name.clone(), value);
+            }
+        }
+        if let Some(device_class) = self.config.device_class.as_deref().and_then(&mut render) {
+            attributes.insert("device_class".to_string(), json!(device_class));
+        }
+        let static_attributes = [
+            ("friendly_name", &self.config.name),
+            ("unit_of_measurement", &self.config.unit_of_measurement),
+            ("state_class", &self.config.state_class),
+            ("icon", &self.config.icon),
+        ];
+        for (name, value) in static_attributes {
+            if let Some(value) = value {
+                attributes.insert(name.to_string(), json!(value));
+            }
+        }
+
+        let binary = self.entity_id.domain() == "binary_sensor";
+        let state = match rendered {
+            _ if !available => STATE_UNAVAILABLE.to_string(),
+            None => STATE_UNAVAILABLE.to_string(),
+            Some(value) if binary => if is_true(&value) { "on" } else { "off" }.to_string(),
+            Some(value) if value.is_empty() || value == "None" => STATE_UNKNOWN.to_string(),
+            Some(value) => value,
+        };
+
+        *self.info.lock().unwrap() = info;
+        states.set(self.entity_id.clone(), state, attributes, context);
+    }
+
+    /// Whether a change to `entity_id` can change this entity's state
+    fn is_affected_by(&self, entity_id: &str) -> bool {
+        entity_id != self.entity_id.as_str() && self.info.lock().unwrap().is_affected_by(entity_id)
+    }
+}
+
+/// Whether a rendered value counts as true, like HA's `result_as_boolean`
+fn is_true(value: &str) -> bool {
+    match value.to_lowercase().as_str() {
+        "true" | "on" | "yes" | "enable" | "open" | "home" => true,
+        other => other.parse::<f64>().is_ok_and(|n| n != 0.0),
+    }
+}
+
+/// Load template entities from config and keep their states rendered
+///
+/// Entity IDs come from the slugified name, falling back to the unique_id.
+/// Returns the listener re-rendering entities on state changes, throttled
+/// to once per `UPDATE_WINDOW`, so this must be called from within a tokio
+/// runtime.
+pub fn load_template_entities(
+    config: &[TemplateConfig],
+    event_bus: &EventBus,
+    states: Arc<StateStore>,
+    engine: Arc<TemplateEngine>,
+) -> ListenerId {
+    let mut used = HashSet::new();
+    let mut entities = Vec::new();
+
+    let configs = config.iter().flat_map(|item| {
+        let sensors = item.sensor.iter().map(|c| ("sensor", c));
+        sensors.chain(item.binary_sensor.iter().map(|c| ("binary_sensor", c)))
+    });
+    for (domain, config) in configs {
+        let base = config
+            .name
+            .as_deref()
+            .or(config.unique_id.as_deref())
+            .map(slugify)
+            .filter(|slug| !slug.is_empty())
+            .unwrap_or_else(|| format!("template_{}", domain));
+        let mut object_id = base.clone();
+        let mut suffix = 2;
+        while !used.insert(format!("{}.{}", domain, object_id)) {
+            object_id = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        let entity_id = match EntityId::new(domain, &object_id) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid template entity '{}': {}", object_id, e);
+                continue;
+            }
+        };
+
+        let entity = TemplateEntity {
+            entity_id,
+            config: config.clone(),
+            info: Mutex::new(RenderInfo::default()),
+        };
+        entity.update(&states, &engine, Context::new());
+        debug!("Loaded template entity {}", entity.entity_id);
+        entities.push(Arc::new(entity));
+    }
+
+    if !entities.is_empty() {
+        info!("Loaded {} template entities", entities.len());
+    }
+
+    let entities: Arc<Vec<(Arc<TemplateEntity>, Throttle<Context>)>> = Arc::new(
+        entities
+            .into_iter()
+            .map(|entity| {
+                let states = states.clone();
+                let engine = engine.clone();
+                let updated = entity.clone();
+                let throttle = Throttle::new(UPDATE_WINDOW, move |context| {
+                    updated.update(&states, &engine, context)
+                });
+                (entity, throttle)
+            })
+            .collect(),
+    );
+
+    if !entities.is_empty() {
+        let entities = entities.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TIME_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                for (entity, updater) in entities.iter() {
+                    if entity.info.lock().unwrap().time {
+                        updater.call(Context::new());
+                    }
+                }
+            }
+        });
+    }
+
+    event_bus.listen_sync(
+        STATE_CHANGED,
+        Arc::new(move |event| {
+            let Some(entity_id) = event.data.get("entity_id").and_then(|id| id.as_str()) else {
+                return;
+            };
+            for (entity, updater) in entities.iter() {
+                if entity.is_affected_by(entity_id) {
+                    updater.call(event.context.clone());
+                }
+            }
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(yaml: &str) -> Arc<StateStore> {
+        let bus = Arc::new(EventBus::new());
+        let states = Arc::new(StateStore::new(bus.clone()));
+        set(&states, "sensor.solar", "150");
+        set(&states, "sensor.battery", "50");
+        let config: Vec<TemplateConfig> = serde_yaml::from_str(yaml).unwrap();
+        let engine = Arc::new(TemplateEngine::new(states.clone()));
+        load_template_entities(&config, &bus, states.clone(), engine);
+        states
+    }
+
+    fn set(states: &StateStore, entity_id: &str, state: &str) {
+        let entity_id: EntityId = entity_id.parse().unwrap();
+        states.set(entity_id, state, HashMap::new(), Context::new());
+    }
+
+    /// Set a state and wait out the template entities' update window
+    async fn set_and_settle(states: &StateStore, entity_id: &str, state: &str) {
+        set(states, entity_id, state);
+        tokio::time::sleep(UPDATE_WINDOW * 2).await;
+    }
+
+    #[tokio::test]
+    async fn test_sensor_sums_two_sensors() {
+        let states = setup(
+            r#"
+            - sensor:
+                - name: Total Power
+                  unit_of_measurement: W
+                  device_class: power
+                  state: "{{ states('sensor.solar') | float(0) + states('sensor.battery') | float(0) }}"
+                  attributes:
+                    sources: "{{ ['sensor.solar', 'sensor.battery'] }}"
+            "#,
+        );
+        let total = states.get("sensor.total_power").unwrap();
+        assert_eq!(total.state, "200.0");
+        assert_eq!(total.attributes["friendly_name"], "Total Power");
+        assert_eq!(total.attributes["unit_of_measurement"], "W");
+        assert_eq!(total.attributes["device_class"], "power");
+        assert_eq!(
+            total.attributes["sources"],
+            json!(["sensor.solar", "sensor.battery"])
+        );
+
+        set_and_settle(&states, "sensor.solar", "100").await;
+        assert_eq!(states.get_state("sensor.total_power").unwrap(), "150.0");
+
+        set_and_settle(&states, "sensor.battery", "-20").await;
+        assert_eq!(states.get_state("sensor.total_power").unwrap(), "80.0");
+
+        // Unrelated changes don't re-render
+        let before = states.get("sensor.total_power").unwrap().last_updated;
+        set_and_settle(&states, "sensor.other", "1").await;
+        assert_eq!(
+            states.get("sensor.total_power").unwrap().last_updated,
+            before
+        );
+    }
+
+    #[tokio::test]
+    async fn test_binary_sensor_and_availability() {
+        let states = setup(
+            r#"
+            - binary_sensor:
+                - name: Charging
+                  device_class: "{{ 'battery_charging' }}"
+                  state: "{{ states('sensor.battery') | float(0) > 0 }}"
+                  availability: "{{ has_value('sensor.battery') }}"
+            "#,
+        );
+        let charging = states.get("binary_sensor.charging").unwrap();
+        assert_eq!(charging.state, "on");
+        assert_eq!(charging.attributes["device_class"], "battery_charging");
+
+        set_and_settle(&states, "sensor.battery", "-5").await;
+        assert_eq!(states.get_state("binary_sensor.charging").unwrap(), "off");
+
+        set_and_settle(&states, "sensor.battery", "unavailable").await;
+        assert_eq!(
+            states.get_state("binary_sensor.charging").unwrap(),
+            STATE_UNAVAILABLE
+        );
+    }
+
+    #[test]
+    fn test_is_true() {
+        for value in ["True", "on", "yes", "1", "2.5"] {
+            assert!(is_true(value), "{}", value);
+        }
+        for value in ["False", "off", "0", "None", ""] {
+            assert!(!is_true(value), "{}", value);
+        }
+    }
+}
@@ -0,0 +1,551 @@
+//! Timer Component
+//!
+//! Implements `timer:` entities that count down a duration. Timers are
+//! `idle`, `active` or `paused`, and fire `timer.*` events on the bus as
+//! they change so automations can react. Each active timer runs as its own
+//! tokio task that finishes the timer when it fires.
+
+use chrono::{DateTime, Utc};
+use ha_core::{Context, EntityId, Event, ServiceCall, SupportsResponse};
+use ha_event_bus::EventBus;
+use ha_service_registry::{ServiceDescription, ServiceError, ServiceRegistry};
+use ha_state_store::StateStore;
+use serde::{Deserialize, Deserializer};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+const DOMAIN: &str = "timer";
+
+const STATUS_IDLE: &str = "idle";
+const STATUS_ACTIVE: &str = "active";
+const STATUS_PAUSED: &str = "paused";
+
+/// Fired when an idle timer starts
+pub const EVENT_TIMER_STARTED: &str = "timer.started";
+/// Fired when an active or paused timer is started again
+pub const EVENT_TIMER_RESTARTED: &str = "timer.restarted";
+/// Fired when an active timer is paused
+pub const EVENT_TIMER_PAUSED: &str = "timer.paused";
+/// Fired when a timer runs out or is finished early
+pub const EVENT_TIMER_FINISHED: &str = "timer.finished";
+/// Fired when a running timer is cancelled
+pub const EVENT_TIMER_CANCELLED: &str = "timer.cancelled";
+
+/// Timer configuration from YAML
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimerConfig {
+    /// Display name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Icon
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Default duration, as seconds or `HH:MM:SS` (default: 0)
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub duration: Duration,
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    parse_duration(&value)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid duration: {}", value)))
+}
+
+/// Parse a duration given as seconds or as `HH:MM:SS` / `MM:SS`
+pub(crate) fn parse_duration(value: &serde_json::Value) -> Option<Duration> {
+    if let Some(seconds) = value.as_f64() {
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+
+    let parts: Vec<f64> = value
+        .as_str()?
+        .split(':')
+        .map(|part| part.trim().parse::<f64>())
+        .collect::<Result<_, _>>()
+        .ok()?;
+    let seconds = match parts.as_slice() {
+        [h, m, s] => h * 3600.0 + m * 60.0 + s,
+        [m, s] => m * 60.0 + s,
+        [s] => *s,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Format a duration like Python's `timedelta` (`H:MM:SS`)
+fn format_duration(duration: Duration) -> String {
+    let total = duration.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        total / 3600,
+        (total % 3600) / 60,
+        total % 60
+    )
+}
+
+/// Runtime state of a timer that isn't idle
+struct RunningTimer {
+    /// Time left when the timer was last started or paused
+    remaining: Duration,
+    /// When an active timer runs out
+    finishes_at: Option<DateTime<Utc>>,
+    /// Task finishing an active timer
+    task: Option<JoinHandle<()>>,
+}
+
+impl RunningTimer {
+    fn abort(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+/// Tracks running timers and applies their state changes
+struct Timers {
+    states: Arc<StateStore>,
+    event_bus: Arc<EventBus>,
+    running: Mutex<HashMap<String, RunningTimer>>,
+    this: Weak<Timers>,
+}
+
+impl Timers {
+    fn new(states: Arc<StateStore>, event_bus: Arc<EventBus>) -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            states,
+            event_bus,
+            running: Mutex::new(HashMap::new()),
+            this: this.clone(),
+        })
+    }
+
+    /// Write a timer's state, keeping its configuration attributes
+    fn write_state(
+        &self,
+        entity_id: &EntityId,
+        status: &str,
+        timer: Option<&RunningTimer>,
+        context: &Context,
+    ) {
+        let Some(current) = self.states.get(entity_id.as_str()) else {
+            return;
+        };
+        let mut attrs = current.attributes.clone();
+        attrs.remove("remaining");
+        attrs.remove("finishes_at");
+        if let Some(timer) = timer {
+            attrs.insert(
+                "remaining".to_string(),
+                json!(format_duration(timer.remaining)),
+            );
+            if let Some(finishes_at) = timer.finishes_at {
+                attrs.insert("finishes_at".to_string(), json!(finishes_at.to_rfc3339()));
+            }
+        }
+        self.states
+            .set(entity_id.clone(), status, attrs, context.clone());
+    }
+
+    fn fire(&self, event_type: &str, data: serde_json::Value, context: &Context) {
+        self.event_bus
+            .fire(Event::new(event_type, data, context.clone()));
+    }
+
+    fn start(&self, entity_id: &EntityId, duration: Option<Duration>, context: &Context) {
+        let Some(current) = self.states.get(entity_id.as_str()) else {
+            return;
+        };
+        let configured = current
+            .attributes
+            .get("duration")
+            .and_then(parse_duration)
+            .unwrap_or_default();
+
+        let mut running = self.running.lock().unwrap();
+        let previous = running.remove(entity_id.as_str());
+        let event = if previous.is_some() {
+            EVENT_TIMER_RESTARTED
+        } else {
+            EVENT_TIMER_STARTED
+        };
+        let remaining = match (duration, previous) {
+            (Some(duration), mut previous) => {
+                if let Some(previous) = previous.as_mut() {
+                    previous.abort();
+                }
+                duration
+            }
+            (None, Some(mut previous)) => {
+                previous.abort();
+                previous.remaining
+            }
+            (None, None) => configured,
+        };
+
+        let finishes_at = Utc::now() + chrono::Duration::from_std(remaining).unwrap_or_default();
+        let this = self.this.clone();
+        let finishing_id = entity_id.clone();
+        let finishing_context = context.clone();
+        let task = tokio::spawn(async move {
+            tokio::time::sleep(remaining).await;
+            if let Some(timers) = this.upgrade() {
+                timers.finish(&finishing_id, &finishing_context);
+            }
+        });
+
+        let timer = RunningTimer {
+            remaining,
+            finishes_at: Some(finishes_at),
+            task: Some(task),
+        };
+        self.write_state(entity_id, STATUS_ACTIVE, Some(&timer), context);
+        running.insert(entity_id.to_string(), timer);
+        drop(running);
+
+        debug!("Timer {} active for {:?}", entity_id, remaining);
+        self.fire(event, json!({"entity_id": entity_id.to_string()}), context);
+    }
+
+    fn pause(&self, entity_id: &EntityId, context: &Context) {
+        let mut running = self.running.lock().unwrap();
+        let Some(timer) = running.get_mut(entity_id.as_str()) else {
+            return;
+        };
+        let Some(finishes_at) = timer.finishes_at.take() else {
+            // Already paused
+            return;
+        };
+        timer.abort();
+        timer.remaining = (finishes_at - Utc::now()).to_std().unwrap_or_default();
+        self.write_state(entity_id, STATUS_PAUSED, Some(timer), context);
+        drop(running);
+
+        self.fire(
+            EVENT_TIMER_PAUSED,
+            json!({"entity_id": entity_id.to_string()}),
+            context,
+        );
+    }
+
+    fn cancel(&self, entity_id: &EntityId, context: &Context) {
+        let Some(mut timer) = self.running.lock().unwrap().remove(entity_id.as_str()) else {
+            return;
+        };
+        timer.abort();
+        self.write_state(entity_id, STATUS_IDLE, None, context);
+
+        self.fire(
+            EVENT_TIMER_CANCELLED,
+            json!({"entity_id": entity_id.to_string()}),
+            context,
+        );
+    }
+
+    fn finish(&self, entity_id: &EntityId, context: &Context) {
+        let Some(mut timer) = self.running.lock().unwrap().remove(entity_id.as_str()) else {
+            return;
+        };
+        timer.abort();
+        self.write_state(entity_id, STATUS_IDLE, None, context);
+
+        debug!("Timer {} finished", entity_id);
+        self.fire(
+            EVENT_TIMER_FINISHED,
+            json!({
+                "entity_id": entity_id.to_string(),
+                "finished_at": Utc::now().to_rfc3339(),
+            }),
+            context,
+        );
+    }
+}
+
+impl Drop for Timers {
+    fn drop(&mut self) {
+        if let Ok(running) = self.running.get_mut() {
+            for timer in running.values_mut() {
+                timer.abort();
+            }
+        }
+    }
+}
+
+/// Load timer entities from config and register them in the state machine
+pub fn load_timers(config: &HashMap<String, TimerConfig>, states: &StateStore) -> usize {
+    let mut count = 0;
+
+    for (id, config) in config {
+        let entity_id = match EntityId::new(DOMAIN, id) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Invalid timer id '{}': {}", id, e);
+                continue;
+            }
+        };
+
+        let mut attributes = HashMap::new();
+        if let Some(name) = &config.name {
+            attributes.insert("friendly_name".to_string(), json!(name));
+        }
+        if let Some(icon) = &config.icon {
+            attributes.insert("icon".to_string(), json!(icon));
+        }
+        attributes.insert(
+            "duration".to_string(),
+            json!(format_duration(config.duration)),
+        );
+        attributes.insert("editable".to_string(), json!(false));
+
+        states.set(entity_id, STATUS_IDLE, attributes, Context::new());
+        debug!("Loaded timer.{}", id);
+        count += 1;
+    }
+
+    if count > 0 {
+        info!("Loaded {} timer entities", count);
+    }
+    count
+}
+
+/// Register a timer service applying `action` to each targeted timer
+fn register_timer_service<F>(
+    services: &ServiceRegistry,
+    timers: Arc<Timers>,
+    service: &str,
+    name: &str,
+    schema: Option<serde_json::Value>,
+    action: F,
+) where
+    F: Fn(&Timers, &EntityId, &ServiceCall) -> Result<(), ServiceError> + Send + Sync + 'static,
+{
+    let action = Arc::new(action);
+    services.register_with_description(
+        ServiceDescription {
+            domain: DOMAIN.to_string(),
+            service: service.to_string(),
+            name: Some(name.to_string()),
+            description: Some(format!("{} a timer", name)),
+            schema,
+            target: Some(json!({"entity": {"domain": DOMAIN}})),
+            supports_response: SupportsResponse::None,
+        },
+        move |call: ServiceCall| {
+            let timers = timers.clone();
+            let action = action.clone();
+            async move {
+                for entity_id in crate::input_helpers::get_target_entities(&call, DOMAIN) {
+                    action(&timers, &entity_id, &call)?;
+                }
+                Ok(None)
+            }
+        },
+    );
+}
+
+/// Register timer services
+///
+/// Timers fire their events on `event_bus`; running timers are stopped once
+/// the services are unregistered and dropped.
+pub fn register_timer_services(
+    services: &ServiceRegistry,
+    states: Arc<StateStore>,
+    event_bus: Arc<EventBus>,
+) {
+    let timers = Timers::new(states, event_bus);
+
+    register_timer_service(
+        services,
+        timers.clone(),
+        "start",
+        "Start",
+        Some(json!({
+            "duration": {"required": false, "selector": {"text": {}}}
+        })),
+        |timers, entity_id, call| {
+            let duration = match call.service_data.get("duration") {
+                Some(value) => Some(parse_duration(value).ok_or_else(|| {
+                    ServiceError::InvalidData(format!("Invalid duration: {}", value))
+                })?),
+                None => None,
+            };
+            timers.start(entity_id, duration, &call.context);
+            Ok(())
+        },
+    );
+
+    register_timer_service(
+        services,
+        timers.clone(),
+        "pause",
+        "Pause",
+        None,
+        |timers, entity_id, call| {
+            timers.pause(entity_id, &call.context);
+            Ok(())
+        },
+    );
+
+    register_timer_service(
+        services,
+        timers.clone(),
+        "cancel",
+        "Cancel",
+        None,
+        |timers, entity_id, call| {
+            timers.cancel(entity_id, &call.context);
+            Ok(())
+        },
+    );
+
+    register_timer_service(
+        services,
+        timers,
+        "finish",
+        "Finish",
+        None,
+        |timers, entity_id, call| {
+            timers.finish(entity_id, &call.context);
+            Ok(())
+        },
+    );
+
+    info!("Timer services registered");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Setup {
+        services: ServiceRegistry,
+        states: Arc<StateStore>,
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    fn setup() -> Setup {
+        let bus = Arc::new(EventBus::new());
+        let states = Arc::new(StateStore::new(bus.clone()));
+        let config: HashMap<String, TimerConfig> =
+            serde_yaml::from_str("tea: {duration: '00:03:00'}").unwrap();
+        load_timers(&config, &states);
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        for event_type in [
+            EVENT_TIMER_STARTED,
+            EVENT_TIMER_RESTARTED,
+            EVENT_TIMER_PAUSED,
+            EVENT_TIMER_FINISHED,
+            EVENT_TIMER_CANCELLED,
+        ] {
+            let events = events.clone();
+            bus.listen_sync(
+                event_type,
+                Arc::new(move |event| {
+                    events
+                        .lock()
+                        .unwrap()
+                        .push(event.event_type.as_str().to_string());
+                }),
+            );
+        }
+
+        let services = ServiceRegistry::new();
+        register_timer_services(&services, states.clone(), bus);
+        Setup {
+            services,
+            states,
+            events,
+        }
+    }
+
+    async fn call(setup: &Setup, service: &str, data: serde_json::Value) {
+        setup
+            .services
+            .call(DOMAIN, service, data, Context::new(), false)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration(&json!(90)), Some(Duration::from_secs(90)));
+        assert_eq!(
+            parse_duration(&json!("01:02:03")),
+            Some(Duration::from_secs(3723))
+        );
+        assert_eq!(parse_duration(&json!("soon")), None);
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1:02:03");
+    }
+
+    #[tokio::test]
+    async fn test_timer_start_then_finish() {
+        let setup = setup();
+        let target = json!({"entity_id": "timer.tea"});
+
+        call(&setup, "start", target.clone()).await;
+        let state = setup.states.get("timer.tea").unwrap();
+        assert_eq!(state.state, STATUS_ACTIVE);
+        assert_eq!(state.attributes["remaining"], "0:03:00");
+        assert!(state.attributes.contains_key("finishes_at"));
+
+        call(&setup, "finish", target).await;
+        let state = setup.states.get("timer.tea").unwrap();
+        assert_eq!(state.state, STATUS_IDLE);
+        assert!(!state.attributes.contains_key("remaining"));
+
+        assert_eq!(
+            *setup.events.lock().unwrap(),
+            [EVENT_TIMER_STARTED, EVENT_TIMER_FINISHED]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timer_runs_out() {
+        let setup = setup();
+
+        call(
+            &setup,
+            "start",
+            json!({"entity_id": "timer.tea", "duration": 0.05}),
+        )
+        .await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert_eq!(setup.states.get_state("timer.tea").unwrap(), STATUS_IDLE);
+        assert_eq!(
+            *setup.events.lock().unwrap(),
+            [EVENT_TIMER_STARTED, EVENT_TIMER_FINISHED]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timer_pause_resume_cancel() {
+        let setup = setup();
+        let target = json!({"entity_id": "timer.tea"});
+
+        call(&setup, "start", target.clone()).await;
+        call(&setup, "pause", target.clone()).await;
+        let state = setup.states.get("timer.tea").unwrap();
+        assert_eq!(state.state, STATUS_PAUSED);
+        assert!(!state.attributes.contains_key("finishes_at"));
+
+        call(&setup, "start", target.clone()).await;
+        assert_eq!(setup.states.get_state("timer.tea").unwrap(), STATUS_ACTIVE);
+        call(&setup, "cancel", target).await;
+        assert_eq!(setup.states.get_state("timer.tea").unwrap(), STATUS_IDLE);
+
+        assert_eq!(
+            *setup.events.lock().unwrap(),
+            [
+                EVENT_TIMER_STARTED,
+                EVENT_TIMER_PAUSED,
+                EVENT_TIMER_RESTARTED,
+                EVENT_TIMER_CANCELLED
+            ]
+        );
+    }
+}
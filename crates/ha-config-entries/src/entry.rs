@@ -95,6 +95,55 @@ pub enum ConfigEntryDisabledBy {
     User,
 }
 
+/// A sub-configuration of a config entry
+///
+/// Subentries are stored with their parent entry, e.g. the individual
+/// locations configured under one weather service account.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigSubentry {
+    /// Unique identifier (ULID)
+    pub subentry_id: String,
+
+    /// Kind of subentry, as defined by the integration
+    pub subentry_type: String,
+
+    /// Human-readable display name
+    pub title: String,
+
+    /// Optional unique identifier for duplicate prevention
+    #[serde(default)]
+    pub unique_id: Option<String>,
+
+    /// Subentry configuration data
+    #[serde(default)]
+    pub data: HashMap<String, serde_json::Value>,
+}
+
+impl ConfigSubentry {
+    /// Create a new subentry
+    pub fn new(subentry_type: impl Into<String>, title: impl Into<String>) -> Self {
+        Self {
+            subentry_id: ulid::Ulid::new().to_string(),
+            subentry_type: subentry_type.into(),
+            title: title.into(),
+            unique_id: None,
+            data: HashMap::new(),
+        }
+    }
+
+    /// Set subentry data
+    pub fn with_data(mut self, data: HashMap<String, serde_json::Value>) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Set unique_id
+    pub fn with_unique_id(mut self, unique_id: impl Into<String>) -> Self {
+        self.unique_id = Some(unique_id.into());
+        self
+    }
+}
+
 /// A configuration entry for an integration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfigEntry {
@@ -170,7 +219,7 @@ pub struct ConfigEntry {
 
     /// Hierarchical sub-configurations
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
-    pub subentries: Vec<serde_json::Value>,
+    pub subentries: Vec<ConfigSubentry>,
 
     /// Creation timestamp
     #[serde(default = "Utc::now")]
@@ -330,6 +379,11 @@ impl ConfigEntryUpdate {
         self.options = Some(options);
         self
     }
+
+    pub fn unique_id(mut self, unique_id: Option<String>) -> Self {
+        self.unique_id = Some(unique_id);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -22,12 +22,13 @@ pub mod state_machine;
 // Re-export main types
 pub use entry::{
     ConfigEntry, ConfigEntryDisabledBy, ConfigEntrySource, ConfigEntryState, ConfigEntryUpdate,
+    ConfigSubentry,
 };
 
 pub use manager::{
-    ConfigEntries, ConfigEntriesData, ConfigEntriesError, ConfigEntriesResult, SetupContext,
-    SetupHandler, SetupResult, UnloadHandler, UnloadResult, STORAGE_KEY, STORAGE_MINOR_VERSION,
-    STORAGE_VERSION, WILDCARD_DOMAIN,
+    ConfigEntries, ConfigEntriesData, ConfigEntriesError, ConfigEntriesResult, MigrateHandler,
+    ReauthFlowHandler, SetupContext, SetupHandler, SetupResult, UnloadHandler, UnloadResult,
+    STORAGE_KEY, STORAGE_MINOR_VERSION, STORAGE_VERSION, WILDCARD_DOMAIN,
 };
 
 pub use state_machine::{calculate_retry_delay, InvalidTransition};
@@ -2,14 +2,15 @@
 //!
 //! Manages the lifecycle of configuration entries.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use chrono::Utc;
 use dashmap::DashMap;
 use futures::future::join_all;
+use ha_core::Context;
 use ha_event_bus::EventBus;
-use ha_registries::{Storable, Storage, StorageFile, StorageResult};
+use ha_registries::{Registries, Storable, Storage, StorageFile, StorageResult};
 use ha_service_registry::ServiceRegistry;
 use ha_state_store::StateStore;
 use serde::{Deserialize, Serialize};
@@ -17,7 +18,9 @@ use thiserror::Error;
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
-use crate::entry::{ConfigEntry, ConfigEntryState, ConfigEntryUpdate};
+use crate::entry::{
+    ConfigEntry, ConfigEntryDisabledBy, ConfigEntryState, ConfigEntryUpdate, ConfigSubentry,
+};
 use crate::state_machine::InvalidTransition;
 
 /// Storage key for config entries
@@ -61,8 +64,8 @@ pub enum ConfigEntriesError {
     #[error("Entry not found: {0}")]
     NotFound(String),
 
-    #[error("Entry already exists for domain {domain} with unique_id {unique_id}")]
-    AlreadyExists { domain: String, unique_id: String },
+    #[error("Entry for domain {domain} with unique_id {unique_id} is already configured")]
+    AlreadyConfigured { domain: String, unique_id: String },
 
     #[error("Cannot unload entry in state {0:?}")]
     CannotUnload(ConfigEntryState),
@@ -73,6 +76,15 @@ pub enum ConfigEntriesError {
     #[error("Unload failed: {0}")]
     UnloadFailed(String),
 
+    #[error("Reauth failed: {0}")]
+    ReauthFailed(String),
+
+    #[error("Migration failed: {0}")]
+    MigrationFailed(String),
+
+    #[error("Subentry not found: {0}")]
+    SubentryNotFound(String),
+
     #[error("Invalid state transition: {0}")]
     InvalidTransition(#[from] InvalidTransition),
 
@@ -107,6 +119,8 @@ pub struct SetupContext {
     pub states: Arc<StateStore>,
     /// Service registry for calling/registering services
     pub services: Arc<ServiceRegistry>,
+    /// Registries, used to find the entities of an entry
+    pub registries: Arc<Registries>,
 }
 
 /// Setup handler function type
@@ -121,6 +135,33 @@ pub type SetupHandler =
 pub type UnloadHandler =
     Arc<dyn Fn(&ConfigEntry, &SetupContext) -> UnloadResult + Send + Sync + 'static>;
 
+/// Migrate handler function type
+///
+/// Receives an entry stored with an older version than the integration's
+/// current one and returns its `data` in the current version's shape, or
+/// why it can't be migrated.
+pub type MigrateHandler = Arc<
+    dyn Fn(&ConfigEntry, &SetupContext) -> Result<HashMap<String, serde_json::Value>, String>
+        + Send
+        + Sync
+        + 'static,
+>;
+
+/// An integration's current entry version and how to migrate older entries
+#[derive(Clone)]
+struct Migration {
+    version: u32,
+    minor_version: u32,
+    handler: MigrateHandler,
+}
+
+/// Reauth flow handler function type
+///
+/// Starts a config flow with source `reauth` for the entry and returns the
+/// new flow's ID.
+pub type ReauthFlowHandler =
+    Arc<dyn Fn(&ConfigEntry) -> Result<String, String> + Send + Sync + 'static>;
+
 /// Config Entries Manager
 ///
 /// Manages the lifecycle of configuration entries including:
@@ -149,8 +190,20 @@ pub struct ConfigEntries {
     /// Unload handlers by domain (use WILDCARD_DOMAIN for catch-all)
     unload_handlers: DashMap<String, UnloadHandler>,
 
+    /// Current entry versions and migrate handlers by domain
+    migrations: DashMap<String, Migration>,
+
     /// Setup context (bus, states, services) - set via set_context()
     context: RwLock<Option<SetupContext>>,
+
+    /// Starts reauth flows - set via set_reauth_flow_handler()
+    reauth_flow_handler: std::sync::RwLock<Option<ReauthFlowHandler>>,
+
+    /// Entries needing attention: entry_id -> active reauth flow_id
+    reauth_flows: DashMap<String, String>,
+
+    /// Entries whose reauth was requested before a reauth handler was set
+    pending_reauths: std::sync::Mutex<Vec<String>>,
 }
 
 impl ConfigEntries {
@@ -163,7 +216,11 @@ impl ConfigEntries {
             by_unique_id: DashMap::new(),
             setup_handlers: DashMap::new(),
             unload_handlers: DashMap::new(),
+            migrations: DashMap::new(),
             context: RwLock::new(None),
+            reauth_flow_handler: std::sync::RwLock::new(None),
+            reauth_flows: DashMap::new(),
+            pending_reauths: std::sync::Mutex::new(Vec::new()),
         }
     }
 
@@ -280,7 +337,7 @@ impl ConfigEntries {
         // Check for duplicate unique_id
         if let Some(ref unique_id) = entry.unique_id {
             if self.get_by_unique_id(&entry.domain, unique_id).is_some() {
-                return Err(ConfigEntriesError::AlreadyExists {
+                return Err(ConfigEntriesError::AlreadyConfigured {
                     domain: entry.domain.clone(),
                     unique_id: unique_id.clone(),
                 });
@@ -308,6 +365,18 @@ impl ConfigEntries {
             .get(entry_id)
             .ok_or_else(|| ConfigEntriesError::NotFound(entry_id.to_string()))?;
 
+        // Check the new unique_id isn't taken by another entry
+        if let Some(Some(ref unique_id)) = update.unique_id {
+            if let Some(other) = self.get_by_unique_id(&entry.domain, unique_id) {
+                if other.entry_id != entry.entry_id {
+                    return Err(ConfigEntriesError::AlreadyConfigured {
+                        domain: entry.domain.clone(),
+                        unique_id: unique_id.clone(),
+                    });
+                }
+            }
+        }
+
         // Remove from indexes
         self.unindex_entry(&entry);
 
@@ -354,6 +423,11 @@ impl ConfigEntries {
             .ok_or_else(|| ConfigEntriesError::NotFound(entry_id.to_string()))?;
 
         self.unindex_entry(&entry);
+        self.reauth_flows.remove(entry_id);
+        self.pending_reauths
+            .lock()
+            .unwrap()
+            .retain(|id| id != entry_id);
         self.save().await?;
 
         info!(
@@ -364,6 +438,84 @@ impl ConfigEntries {
         Ok(entry)
     }
 
+    /// Get the subentries of an entry
+    pub fn subentries(&self, entry_id: &str) -> ConfigEntriesResult<Vec<ConfigSubentry>> {
+        self.entries
+            .get(entry_id)
+            .map(|entry| entry.subentries.clone())
+            .ok_or_else(|| ConfigEntriesError::NotFound(entry_id.to_string()))
+    }
+
+    /// Add a subentry to an entry
+    ///
+    /// Subentries of the same type can't share a unique_id.
+    pub async fn add_subentry(
+        &self,
+        entry_id: &str,
+        subentry: ConfigSubentry,
+    ) -> ConfigEntriesResult<ConfigSubentry> {
+        {
+            let mut entry = self
+                .entries
+                .get_mut(entry_id)
+                .ok_or_else(|| ConfigEntriesError::NotFound(entry_id.to_string()))?;
+
+            if let Some(ref unique_id) = subentry.unique_id {
+                let taken = entry.subentries.iter().any(|s| {
+                    s.subentry_type == subentry.subentry_type
+                        && s.unique_id.as_ref() == Some(unique_id)
+                });
+                if taken {
+                    return Err(ConfigEntriesError::AlreadyConfigured {
+                        domain: entry.domain.clone(),
+                        unique_id: unique_id.clone(),
+                    });
+                }
+            }
+
+            entry.subentries.push(subentry.clone());
+            entry.modified_at = Utc::now();
+        }
+        self.save().await?;
+
+        info!(
+            "Added subentry: {} ({}) to entry {}",
+            subentry.title, subentry.subentry_type, entry_id
+        );
+
+        Ok(subentry)
+    }
+
+    /// Remove a subentry from an entry
+    pub async fn remove_subentry(
+        &self,
+        entry_id: &str,
+        subentry_id: &str,
+    ) -> ConfigEntriesResult<ConfigSubentry> {
+        let removed = {
+            let mut entry = self
+                .entries
+                .get_mut(entry_id)
+                .ok_or_else(|| ConfigEntriesError::NotFound(entry_id.to_string()))?;
+
+            let index = entry
+                .subentries
+                .iter()
+                .position(|s| s.subentry_id == subentry_id)
+                .ok_or_else(|| ConfigEntriesError::SubentryNotFound(subentry_id.to_string()))?;
+            entry.modified_at = Utc::now();
+            entry.subentries.remove(index)
+        };
+        self.save().await?;
+
+        info!(
+            "Removed subentry: {} ({}) from entry {}",
+            removed.title, removed.subentry_type, entry_id
+        );
+
+        Ok(removed)
+    }
+
     /// Set entry state with FSM validation
     ///
     /// Returns an error if the transition is invalid.
@@ -394,6 +546,32 @@ impl ConfigEntries {
         debug!("Registered unload handler for domain: {}", domain);
     }
 
+    /// Register a migrate handler for a domain
+    ///
+    /// `version` and `minor_version` are the integration's current entry
+    /// version. Entries stored with an older version are migrated with
+    /// `handler` before they are set up.
+    pub fn register_migrate_handler(
+        &self,
+        domain: &str,
+        version: u32,
+        minor_version: u32,
+        handler: MigrateHandler,
+    ) {
+        self.migrations.insert(
+            domain.to_string(),
+            Migration {
+                version,
+                minor_version,
+                handler,
+            },
+        );
+        debug!(
+            "Registered migrate handler for domain: {} (v{}.{})",
+            domain, version, minor_version
+        );
+    }
+
     /// Setup an entry (call integration's setup)
     ///
     /// Uses per-entry locking to allow concurrent setup of different entries
@@ -419,9 +597,32 @@ impl ConfigEntries {
 
         // Acquire per-entry lock
         let _lock = entry.setup_lock.lock().await;
+        self.setup_locked(entry_id, &context).await
+    }
 
+    /// Run the setup of an entry whose setup_lock is held by the caller
+    ///
+    /// Entries stored with an older version are migrated first. If that
+    /// fails the entry ends up in `MigrationError` and isn't set up.
+    async fn setup_locked(
+        &self,
+        entry_id: &str,
+        context: &SetupContext,
+    ) -> ConfigEntriesResult<()> {
         // Transition to SetupInProgress (validates we're in NotLoaded or error state)
         self.set_state(entry_id, ConfigEntryState::SetupInProgress, None)?;
+        if let Err(reason) = self.migrate_locked(entry_id, context).await? {
+            self.set_state(
+                entry_id,
+                ConfigEntryState::MigrationError,
+                Some(reason.clone()),
+            )?;
+            warn!("Migration failed for entry {}: {}", entry_id, reason);
+            return Err(ConfigEntriesError::MigrationFailed(reason));
+        }
+        let entry = self
+            .get(entry_id)
+            .ok_or_else(|| ConfigEntriesError::NotFound(entry_id.to_string()))?;
 
         // Look up handler: first by domain, then wildcard
         let handler = self
@@ -431,7 +632,7 @@ impl ConfigEntries {
 
         // Call setup handler if registered
         let result = if let Some(handler) = handler {
-            handler(&entry, &context)
+            handler(&entry, context)
         } else {
             // No handler, treat as success
             SetupResult::Success
@@ -441,7 +642,9 @@ impl ConfigEntries {
             SetupResult::AuthFailed(reason) => {
                 self.set_state(entry_id, ConfigEntryState::SetupError, Some(reason.clone()))?;
                 warn!("Auth failed for entry {}: {}", entry_id, reason);
-                // TODO: Trigger reauth flow
+                if let Err(e) = self.async_start_reauth(entry_id) {
+                    warn!("Could not start reauth for entry {}: {}", entry_id, e);
+                }
                 Err(ConfigEntriesError::SetupFailed(reason))
             }
             SetupResult::Failed(reason) => {
@@ -474,6 +677,56 @@ impl ConfigEntries {
         }
     }
 
+    /// Migrate an entry to its integration's current version, if it's older
+    ///
+    /// Saves the migrated entry. Returns why the entry can't be migrated,
+    /// including when it was stored by a newer version of the integration.
+    async fn migrate_locked(
+        &self,
+        entry_id: &str,
+        context: &SetupContext,
+    ) -> ConfigEntriesResult<Result<(), String>> {
+        let entry = self
+            .get(entry_id)
+            .ok_or_else(|| ConfigEntriesError::NotFound(entry_id.to_string()))?;
+        let Some(migration) = self.migrations.get(&entry.domain).map(|m| m.clone()) else {
+            return Ok(Ok(()));
+        };
+
+        if entry.version > migration.version {
+            return Ok(Err(format!(
+                "entry version {} is newer than the integration's version {}",
+                entry.version, migration.version
+            )));
+        }
+        if (entry.version, entry.minor_version) >= (migration.version, migration.minor_version) {
+            return Ok(Ok(()));
+        }
+
+        info!(
+            "Migrating entry {} ({}) from v{}.{} to v{}.{}",
+            entry.title,
+            entry_id,
+            entry.version,
+            entry.minor_version,
+            migration.version,
+            migration.minor_version
+        );
+        let data = match (migration.handler)(&entry, context) {
+            Ok(data) => data,
+            Err(reason) => return Ok(Err(reason)),
+        };
+
+        if let Some(mut entry) = self.entries.get_mut(entry_id) {
+            entry.data = data;
+            entry.version = migration.version;
+            entry.minor_version = migration.minor_version;
+            entry.modified_at = Utc::now();
+        }
+        self.save().await?;
+        Ok(Ok(()))
+    }
+
     /// Unload an entry
     ///
     /// Uses per-entry locking to allow concurrent unload of different entries.
@@ -498,6 +751,14 @@ impl ConfigEntries {
 
         // Acquire per-entry lock
         let _lock = entry.setup_lock.lock().await;
+        self.unload_locked(entry_id, &context)
+    }
+
+    /// Run the unload of an entry whose setup_lock is held by the caller
+    fn unload_locked(&self, entry_id: &str, context: &SetupContext) -> ConfigEntriesResult<()> {
+        let entry = self
+            .get(entry_id)
+            .ok_or_else(|| ConfigEntriesError::NotFound(entry_id.to_string()))?;
 
         // If in error states, we can skip directly to NotLoaded
         if matches!(
@@ -519,6 +780,9 @@ impl ConfigEntries {
 
         // Transition to UnloadInProgress
         self.set_state(entry_id, ConfigEntryState::UnloadInProgress, None)?;
+        let entry = self
+            .get(entry_id)
+            .ok_or_else(|| ConfigEntriesError::NotFound(entry_id.to_string()))?;
 
         // Look up handler: first by domain, then wildcard
         let handler = self
@@ -528,7 +792,7 @@ impl ConfigEntries {
 
         // Call unload handler if registered
         let result = if let Some(handler) = handler {
-            handler(&entry, &context)
+            handler(&entry, context)
         } else {
             // No handler, treat as success
             UnloadResult::Success
@@ -556,6 +820,14 @@ impl ConfigEntries {
                 ))
             }
             UnloadResult::Success => {
+                let entity_ids: Vec<String> = context
+                    .registries
+                    .entities
+                    .get_by_config_entry_id(entry_id)
+                    .iter()
+                    .map(|entity| entity.entity_id.clone())
+                    .collect();
+                context.states.mark_unavailable(entity_ids, Context::new());
                 self.set_state(entry_id, ConfigEntryState::NotLoaded, None)?;
                 info!("Unloaded entry: {} ({})", entry.title, entry_id);
                 Ok(())
@@ -564,9 +836,176 @@ impl ConfigEntries {
     }
 
     /// Reload an entry (unload + setup)
+    ///
+    /// Holds the entry's setup_lock across both steps, so a loaded entry goes
+    /// `Loaded -> UnloadInProgress -> NotLoaded -> SetupInProgress -> Loaded`
+    /// without anything else touching it in between. A failed setup leaves
+    /// the entry in `SetupError` with the failure as its reason. Disabled
+    /// entries are only unloaded.
+    ///
+    /// Requires context to be set via `set_context()` before calling.
     pub async fn reload(&self, entry_id: &str) -> ConfigEntriesResult<()> {
-        self.unload(entry_id).await?;
-        self.setup(entry_id).await
+        let entry = self
+            .get(entry_id)
+            .ok_or_else(|| ConfigEntriesError::NotFound(entry_id.to_string()))?;
+
+        if !entry.state.is_recoverable() {
+            return Err(ConfigEntriesError::CannotUnload(entry.state));
+        }
+
+        let context =
+            self.context.read().await.clone().ok_or_else(|| {
+                ConfigEntriesError::SetupFailed("Setup context not set".to_string())
+            })?;
+
+        let _lock = entry.setup_lock.lock().await;
+        self.unload_locked(entry_id, &context)?;
+
+        if entry.is_disabled() {
+            debug!("Not setting up disabled entry after unload: {}", entry_id);
+            return Ok(());
+        }
+        info!("Reloading entry: {} ({})", entry.title, entry_id);
+        self.setup_locked(entry_id, &context).await
+    }
+
+    /// Enable or disable an entry
+    ///
+    /// Persists the new `disabled_by`, then unloads the entry when it is
+    /// disabled or sets it up when it is re-enabled. Does nothing if the
+    /// entry is already in the requested state.
+    ///
+    /// Requires context to be set via `set_context()` before calling.
+    pub async fn async_set_disabled_by(
+        &self,
+        entry_id: &str,
+        disabled_by: Option<ConfigEntryDisabledBy>,
+    ) -> ConfigEntriesResult<()> {
+        let entry = self
+            .get(entry_id)
+            .ok_or_else(|| ConfigEntriesError::NotFound(entry_id.to_string()))?;
+
+        if entry.disabled_by == disabled_by {
+            return Ok(());
+        }
+
+        let context =
+            self.context.read().await.clone().ok_or_else(|| {
+                ConfigEntriesError::SetupFailed("Setup context not set".to_string())
+            })?;
+
+        let _lock = entry.setup_lock.lock().await;
+        let state = self
+            .get(entry_id)
+            .map(|e| e.state)
+            .ok_or_else(|| ConfigEntriesError::NotFound(entry_id.to_string()))?;
+        // Check before persisting, so a refused disable leaves the entry as is
+        if disabled_by.is_some() && !state.is_recoverable() {
+            return Err(ConfigEntriesError::CannotUnload(state));
+        }
+
+        if let Some(mut entry) = self.entries.get_mut(entry_id) {
+            entry.disabled_by = disabled_by;
+            entry.modified_at = Utc::now();
+        }
+        self.save().await?;
+
+        if disabled_by.is_some() {
+            info!("Disabling entry: {} ({})", entry.title, entry_id);
+            self.unload_locked(entry_id, &context)
+        } else {
+            info!("Enabling entry: {} ({})", entry.title, entry_id);
+            self.setup_locked(entry_id, &context).await
+        }
+    }
+
+    /// Set the handler used to start reauth flows
+    ///
+    /// Reauths requested before a handler was set (e.g. entries that failed
+    /// auth during startup) are started now.
+    pub fn set_reauth_flow_handler(&self, handler: ReauthFlowHandler) {
+        *self.reauth_flow_handler.write().unwrap() = Some(handler);
+
+        let pending = std::mem::take(&mut *self.pending_reauths.lock().unwrap());
+        for entry_id in pending {
+            if let Err(e) = self.async_start_reauth(&entry_id) {
+                warn!("Could not start reauth for entry {}: {}", entry_id, e);
+            }
+        }
+    }
+
+    /// Start a reauth flow for an entry and mark it as needing attention
+    ///
+    /// If a reauth flow is already active for the entry, its flow ID is
+    /// returned and no new flow is started.
+    pub fn async_start_reauth(&self, entry_id: &str) -> ConfigEntriesResult<String> {
+        let entry = self
+            .get(entry_id)
+            .ok_or_else(|| ConfigEntriesError::NotFound(entry_id.to_string()))?;
+
+        if let Some(flow_id) = self.reauth_flow_id(entry_id) {
+            debug!("Reauth already in progress for entry {}", entry_id);
+            return Ok(flow_id);
+        }
+
+        let handler = self.reauth_flow_handler.read().unwrap().clone();
+        let Some(handler) = handler else {
+            let mut pending = self.pending_reauths.lock().unwrap();
+            if !pending.iter().any(|id| id == entry_id) {
+                pending.push(entry_id.to_string());
+            }
+            return Err(ConfigEntriesError::ReauthFailed(
+                "No reauth flow handler set, reauth queued".to_string(),
+            ));
+        };
+
+        // The handler runs without any map lock held, it may call back into
+        // the manager (e.g. to look up the entry or list flows)
+        let flow_id = handler(&entry).map_err(ConfigEntriesError::ReauthFailed)?;
+
+        match self.reauth_flows.entry(entry_id.to_string()) {
+            dashmap::mapref::entry::Entry::Occupied(existing) => {
+                // A concurrent caller won the race, keep its flow
+                debug!(
+                    "Reauth flow {} for entry {} superseded by {}",
+                    flow_id,
+                    entry_id,
+                    existing.get()
+                );
+                Ok(existing.get().clone())
+            }
+            dashmap::mapref::entry::Entry::Vacant(slot) => {
+                info!(
+                    "Started reauth flow {} for entry: {} ({})",
+                    flow_id, entry.title, entry_id
+                );
+                slot.insert(flow_id.clone());
+                Ok(flow_id)
+            }
+        }
+    }
+
+    /// Clear the reauth marker once the entry's reauth flow has finished
+    pub fn reauth_finished(&self, entry_id: &str) {
+        self.reauth_flows.remove(entry_id);
+    }
+
+    /// Get the active reauth flow ID for an entry, if any
+    pub fn reauth_flow_id(&self, entry_id: &str) -> Option<String> {
+        self.reauth_flows.get(entry_id).map(|r| r.value().clone())
+    }
+
+    /// Get the entry a reauth flow was started for, if the flow is one
+    pub fn reauth_entry_for_flow(&self, flow_id: &str) -> Option<String> {
+        self.reauth_flows
+            .iter()
+            .find(|r| r.value() == flow_id)
+            .map(|r| r.key().clone())
+    }
+
+    /// Check whether an entry needs user attention (reauth in progress)
+    pub fn needs_attention(&self, entry_id: &str) -> bool {
+        self.reauth_flows.contains_key(entry_id)
     }
 
     /// Get all entry IDs
@@ -613,14 +1052,16 @@ mod tests {
 
     use tempfile::TempDir;
 
-    fn create_test_context() -> SetupContext {
+    fn create_test_context(dir: &TempDir) -> SetupContext {
         let bus = Arc::new(EventBus::new());
         let states = Arc::new(StateStore::new(bus.clone()));
         let services = Arc::new(ServiceRegistry::new());
+        let registries = Arc::new(Registries::new(dir.path()));
         SetupContext {
             bus,
             states,
             services,
+            registries,
         }
     }
 
@@ -628,7 +1069,7 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let storage = Arc::new(Storage::new(temp_dir.path()));
         let manager = ConfigEntries::new(storage);
-        manager.set_context(create_test_context()).await;
+        manager.set_context(create_test_context(&temp_dir)).await;
         (temp_dir, manager)
     }
 
@@ -664,8 +1105,51 @@ mod tests {
 
         assert!(matches!(
             result,
-            Err(ConfigEntriesError::AlreadyExists { .. })
+            Err(ConfigEntriesError::AlreadyConfigured { .. })
         ));
+
+        // The same unique_id is fine for another integration
+        let other = ConfigEntry::new("deconz", "Gateway").with_unique_id("same-id");
+        manager.add(other).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_update_to_taken_unique_id_rejected() {
+        let (_dir, manager) = create_test_manager();
+
+        manager
+            .add(ConfigEntry::new("hue", "Bridge 1").with_unique_id("taken"))
+            .await
+            .unwrap();
+        let entry = manager
+            .add(ConfigEntry::new("hue", "Bridge 2").with_unique_id("free"))
+            .await
+            .unwrap();
+
+        let result = manager
+            .update(
+                &entry.entry_id,
+                ConfigEntryUpdate::new().unique_id(Some("taken".to_string())),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(ConfigEntriesError::AlreadyConfigured { .. })
+        ));
+        assert_eq!(
+            manager.get(&entry.entry_id).unwrap().unique_id.as_deref(),
+            Some("free")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_entries_without_unique_id_coexist() {
+        let (_dir, manager) = create_test_manager();
+
+        manager.add(ConfigEntry::new("hue", "Hue 1")).await.unwrap();
+        manager.add(ConfigEntry::new("hue", "Hue 2")).await.unwrap();
+
+        assert_eq!(manager.get_by_domain("hue").len(), 2);
     }
 
     #[tokio::test]
@@ -807,6 +1291,49 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_unload_marks_entities_unavailable() {
+        let (_dir, manager) = create_test_manager_with_context().await;
+        let context = manager.context().await.unwrap();
+
+        let entry = manager.add(ConfigEntry::new("hue", "Test")).await.unwrap();
+        manager.setup(&entry.entry_id).await.unwrap();
+
+        for (entity_id, unique_id) in [("light.unload_a", "a"), ("light.unload_b", "b")] {
+            context.registries.entities.get_or_create(
+                "hue",
+                entity_id,
+                Some(unique_id),
+                Some(&entry.entry_id),
+                None,
+            );
+            context.states.set(
+                entity_id.parse().unwrap(),
+                "on",
+                HashMap::from([("friendly_name".to_string(), serde_json::json!(unique_id))]),
+                Context::new(),
+            );
+        }
+        context.states.set(
+            "light.unload_other".parse().unwrap(),
+            "on",
+            HashMap::new(),
+            Context::new(),
+        );
+
+        manager.unload(&entry.entry_id).await.unwrap();
+
+        for (entity_id, unique_id) in [("light.unload_a", "a"), ("light.unload_b", "b")] {
+            let state = context.states.get(entity_id).unwrap();
+            assert_eq!(state.state, "unavailable");
+            assert_eq!(state.attributes["friendly_name"], unique_id);
+        }
+        assert_eq!(
+            context.states.get("light.unload_other").unwrap().state,
+            "on"
+        );
+    }
+
     #[tokio::test]
     async fn test_invalid_state_transition_rejected() {
         let (_dir, manager) = create_test_manager_with_context().await;
@@ -822,6 +1349,171 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_reload_unloads_then_sets_up() {
+        let (_dir, manager) = create_test_manager_with_context().await;
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let log = calls.clone();
+        manager.register_unload_handler(
+            "hue",
+            Arc::new(move |entry, _ctx| {
+                log.lock().unwrap().push(("unload", entry.state));
+                UnloadResult::Success
+            }),
+        );
+        let log = calls.clone();
+        manager.register_setup_handler(
+            "hue",
+            Arc::new(move |entry, _ctx| {
+                log.lock().unwrap().push(("setup", entry.state));
+                SetupResult::Success
+            }),
+        );
+
+        let entry = manager.add(ConfigEntry::new("hue", "Test")).await.unwrap();
+        manager.setup(&entry.entry_id).await.unwrap();
+        calls.lock().unwrap().clear();
+
+        manager.reload(&entry.entry_id).await.unwrap();
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                ("unload", ConfigEntryState::UnloadInProgress),
+                ("setup", ConfigEntryState::SetupInProgress),
+            ]
+        );
+        assert_eq!(
+            manager.get(&entry.entry_id).unwrap().state,
+            ConfigEntryState::Loaded
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reload_setup_failure_sets_error() {
+        let (_dir, manager) = create_test_manager_with_context().await;
+
+        let entry = manager.add(ConfigEntry::new("hue", "Test")).await.unwrap();
+        manager.setup(&entry.entry_id).await.unwrap();
+
+        manager.register_setup_handler(
+            "hue",
+            Arc::new(|_entry, _ctx| SetupResult::Failed("Bridge unreachable".to_string())),
+        );
+        let result = manager.reload(&entry.entry_id).await;
+
+        assert!(matches!(result, Err(ConfigEntriesError::SetupFailed(_))));
+        let entry = manager.get(&entry.entry_id).unwrap();
+        assert_eq!(entry.state, ConfigEntryState::SetupError);
+        assert_eq!(entry.reason.as_deref(), Some("Bridge unreachable"));
+    }
+
+    #[tokio::test]
+    async fn test_setup_migrates_old_entry() {
+        let (_dir, manager) = create_test_manager_with_context().await;
+
+        // v1 stored a "host:port" string, v2 splits it
+        manager.register_migrate_handler(
+            "hue",
+            2,
+            1,
+            Arc::new(|entry, _ctx| {
+                let address = entry.data["address"].as_str().ok_or("no address")?;
+                let (host, port) = address.split_once(':').ok_or("no port")?;
+                let port: u16 = port.parse().map_err(|_| "bad port")?;
+                Ok(HashMap::from([
+                    ("host".to_string(), serde_json::json!(host)),
+                    ("port".to_string(), serde_json::json!(port)),
+                ]))
+            }),
+        );
+        manager.register_setup_handler(
+            "hue",
+            Arc::new(|entry, _ctx| match entry.data.get("port") {
+                Some(_) => SetupResult::Success,
+                None => SetupResult::Failed("Not migrated".to_string()),
+            }),
+        );
+
+        let mut entry = ConfigEntry::new("hue", "Bridge");
+        entry
+            .data
+            .insert("address".to_string(), serde_json::json!("192.168.1.2:8080"));
+        let entry = manager.add(entry).await.unwrap();
+        manager.setup(&entry.entry_id).await.unwrap();
+
+        let migrated = manager.get(&entry.entry_id).unwrap();
+        assert!(migrated.is_loaded());
+        assert_eq!((migrated.version, migrated.minor_version), (2, 1));
+        assert_eq!(migrated.data["host"], "192.168.1.2");
+        assert_eq!(migrated.data["port"], 8080);
+        assert!(!migrated.data.contains_key("address"));
+
+        // The migrated entry is what gets stored
+        let reloaded = ConfigEntries::new(manager.storage.clone());
+        reloaded.load().await.unwrap();
+        assert_eq!(reloaded.get(&entry.entry_id).unwrap().version, 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_skips_setup() {
+        let (_dir, manager) = create_test_manager_with_context().await;
+
+        manager.register_migrate_handler(
+            "hue",
+            2,
+            1,
+            Arc::new(|_entry, _ctx| Err("unsupported bridge".to_string())),
+        );
+        manager
+            .register_setup_handler("hue", Arc::new(|_entry, _ctx| panic!("setup must not run")));
+
+        let entry = manager
+            .add(ConfigEntry::new("hue", "Bridge"))
+            .await
+            .unwrap();
+        let result = manager.setup(&entry.entry_id).await;
+        assert!(matches!(
+            result,
+            Err(ConfigEntriesError::MigrationFailed(_))
+        ));
+
+        let entry = manager.get(&entry.entry_id).unwrap();
+        assert_eq!(entry.state, ConfigEntryState::MigrationError);
+        assert_eq!(entry.reason.as_deref(), Some("unsupported bridge"));
+        assert_eq!(entry.version, 1);
+    }
+
+    #[tokio::test]
+    async fn test_migration_only_for_older_entries() {
+        let (_dir, manager) = create_test_manager_with_context().await;
+
+        manager.register_migrate_handler(
+            "hue",
+            2,
+            1,
+            Arc::new(|_entry, _ctx| panic!("current entries must not be migrated")),
+        );
+
+        let current = manager
+            .add(ConfigEntry::new("hue", "Current").with_version(2, 3))
+            .await
+            .unwrap();
+        manager.setup(&current.entry_id).await.unwrap();
+        assert!(manager.get(&current.entry_id).unwrap().is_loaded());
+
+        // Entries from a newer version of the integration can't be downgraded
+        let newer = manager
+            .add(ConfigEntry::new("hue", "Newer").with_version(3, 1))
+            .await
+            .unwrap();
+        assert!(manager.setup(&newer.entry_id).await.is_err());
+        assert_eq!(
+            manager.get(&newer.entry_id).unwrap().state,
+            ConfigEntryState::MigrationError
+        );
+    }
+
     #[tokio::test]
     async fn test_save_and_load() {
         let temp_dir = TempDir::new().unwrap();
@@ -851,4 +1543,181 @@ mod tests {
             assert_eq!(entry.source, ConfigEntrySource::Import);
         }
     }
+
+    #[tokio::test]
+    async fn test_start_reauth_only_once() {
+        let (_dir, manager) = create_test_manager();
+        let entry = manager.add(ConfigEntry::new("hue", "Hue")).await.unwrap();
+
+        let started = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = started.clone();
+        manager.set_reauth_flow_handler(Arc::new(move |entry: &ConfigEntry| {
+            let n = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(format!("reauth_{}_{}", entry.domain, n))
+        }));
+
+        assert!(!manager.needs_attention(&entry.entry_id));
+        let first = manager.async_start_reauth(&entry.entry_id).unwrap();
+        let second = manager.async_start_reauth(&entry.entry_id).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(started.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(manager.needs_attention(&entry.entry_id));
+
+        manager.reauth_finished(&entry.entry_id);
+        assert!(!manager.needs_attention(&entry.entry_id));
+    }
+
+    #[tokio::test]
+    async fn test_reauth_handler_can_call_back_into_manager() {
+        let (_dir, manager) = create_test_manager();
+        let manager = Arc::new(manager);
+        let entry = manager.add(ConfigEntry::new("hue", "Hue")).await.unwrap();
+
+        let weak = Arc::downgrade(&manager);
+        manager.set_reauth_flow_handler(Arc::new(move |entry: &ConfigEntry| {
+            // Would deadlock if the reauth map entry were still held
+            let manager = weak.upgrade().unwrap();
+            assert!(!manager.needs_attention(&entry.entry_id));
+            Ok("reauth_flow".to_string())
+        }));
+
+        let flow_id = manager.async_start_reauth(&entry.entry_id).unwrap();
+        assert_eq!(flow_id, "reauth_flow");
+        assert_eq!(
+            manager.reauth_entry_for_flow("reauth_flow"),
+            Some(entry.entry_id.clone())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reauth_before_handler_is_started_later() {
+        let (_dir, manager) = create_test_manager();
+        let entry = manager.add(ConfigEntry::new("hue", "Hue")).await.unwrap();
+
+        assert!(manager.async_start_reauth(&entry.entry_id).is_err());
+        assert!(!manager.needs_attention(&entry.entry_id));
+
+        manager.set_reauth_flow_handler(Arc::new(|_: &ConfigEntry| Ok("late_flow".to_string())));
+
+        assert!(manager.needs_attention(&entry.entry_id));
+        assert_eq!(
+            manager.reauth_flow_id(&entry.entry_id),
+            Some("late_flow".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disable_loaded_entry_unloads_it() {
+        let (_dir, manager) = create_test_manager_with_context().await;
+        let entry = manager.add(ConfigEntry::new("hue", "Hue")).await.unwrap();
+
+        let unloads = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = unloads.clone();
+        manager.register_unload_handler(
+            "hue",
+            Arc::new(move |_, _| {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                UnloadResult::Success
+            }),
+        );
+        manager.setup(&entry.entry_id).await.unwrap();
+
+        manager
+            .async_set_disabled_by(&entry.entry_id, Some(ConfigEntryDisabledBy::User))
+            .await
+            .unwrap();
+        let disabled = manager.get(&entry.entry_id).unwrap();
+        assert_eq!(disabled.state, ConfigEntryState::NotLoaded);
+        assert_eq!(disabled.disabled_by, Some(ConfigEntryDisabledBy::User));
+        assert_eq!(unloads.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Already disabled: nothing to do
+        manager
+            .async_set_disabled_by(&entry.entry_id, Some(ConfigEntryDisabledBy::User))
+            .await
+            .unwrap();
+        assert_eq!(unloads.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        manager
+            .async_set_disabled_by(&entry.entry_id, None)
+            .await
+            .unwrap();
+        assert!(manager.get(&entry.entry_id).unwrap().is_loaded());
+    }
+
+    #[tokio::test]
+    async fn test_disable_refused_leaves_entry_enabled() {
+        let (dir, manager) = create_test_manager_with_context().await;
+        let entry = manager.add(ConfigEntry::new("hue", "Hue")).await.unwrap();
+        manager.setup(&entry.entry_id).await.unwrap();
+        manager.register_unload_handler(
+            "hue",
+            Arc::new(|_entry, _ctx| UnloadResult::Failed("Cleanup failed".to_string())),
+        );
+        let _ = manager.unload(&entry.entry_id).await;
+
+        let result = manager
+            .async_set_disabled_by(&entry.entry_id, Some(ConfigEntryDisabledBy::User))
+            .await;
+        assert!(matches!(
+            result,
+            Err(ConfigEntriesError::CannotUnload(
+                ConfigEntryState::FailedUnload
+            ))
+        ));
+        assert_eq!(manager.get(&entry.entry_id).unwrap().disabled_by, None);
+
+        let reloaded = ConfigEntries::new(Arc::new(Storage::new(dir.path())));
+        reloaded.load().await.unwrap();
+        assert_eq!(reloaded.get(&entry.entry_id).unwrap().disabled_by, None);
+    }
+
+    #[tokio::test]
+    async fn test_subentries_persist_with_entry() {
+        let (dir, manager) = create_test_manager();
+        let entry = manager
+            .add(ConfigEntry::new("met", "Weather"))
+            .await
+            .unwrap();
+
+        let home = manager
+            .add_subentry(
+                &entry.entry_id,
+                ConfigSubentry::new("location", "Home").with_unique_id("52.3-4.9"),
+            )
+            .await
+            .unwrap();
+        let duplicate = manager
+            .add_subentry(
+                &entry.entry_id,
+                ConfigSubentry::new("location", "Also home").with_unique_id("52.3-4.9"),
+            )
+            .await;
+        assert!(matches!(
+            duplicate,
+            Err(ConfigEntriesError::AlreadyConfigured { .. })
+        ));
+
+        // Reload from disk
+        let reloaded = ConfigEntries::new(Arc::new(Storage::new(dir.path())));
+        reloaded.load().await.unwrap();
+        assert_eq!(
+            reloaded.subentries(&entry.entry_id).unwrap(),
+            vec![home.clone()]
+        );
+
+        let removed = manager
+            .remove_subentry(&entry.entry_id, &home.subentry_id)
+            .await
+            .unwrap();
+        assert_eq!(removed.title, "Home");
+        assert!(manager.subentries(&entry.entry_id).unwrap().is_empty());
+        assert!(matches!(
+            manager
+                .remove_subentry(&entry.entry_id, &home.subentry_id)
+                .await,
+            Err(ConfigEntriesError::SubentryNotFound(_))
+        ));
+    }
 }
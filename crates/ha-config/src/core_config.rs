@@ -4,6 +4,7 @@
 
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_yaml::Value;
+use std::collections::HashMap;
 use std::path::Path;
 
 use crate::error::{ConfigError, ConfigResult};
@@ -149,6 +150,27 @@ pub struct CoreConfig {
     /// Auth providers configuration
     #[serde(default)]
     pub auth_providers: Vec<Value>,
+
+    /// Access tokens to accept as-is, each a `token` and its `user_id`
+    #[serde(default)]
+    pub auth_access_tokens: Vec<Value>,
+
+    /// What users may change, keyed by user_id (users not listed are admins)
+    #[serde(default)]
+    pub auth_permissions: HashMap<String, Value>,
+
+    /// HTTP server configuration from the top-level `http:` section
+    #[serde(skip)]
+    pub http: HttpConfig,
+}
+
+/// HTTP server configuration from the `http:` section
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HttpConfig {
+    /// Origins allowed to make cross-origin requests with credentials
+    /// (default: the internal and external URLs)
+    #[serde(default)]
+    pub cors_allowed_origins: Vec<String>,
 }
 
 /// Unit system configuration - can be "metric", "imperial", or custom
@@ -176,6 +198,13 @@ impl UnitSystemConfig {
     }
 }
 
+/// Origin (`scheme://host[:port]`) of a URL
+fn url_origin(url: &str) -> Option<String> {
+    let (scheme, rest) = url.trim().split_once("://")?;
+    let host = rest.split(['/', '?', '#']).next()?;
+    (!scheme.is_empty() && !host.is_empty()).then(|| format!("{}://{}", scheme, host))
+}
+
 fn default_name() -> String {
     "Home".to_string()
 }
@@ -214,6 +243,9 @@ impl Default for CoreConfig {
             allowlist_external_dirs: Vec::new(),
             allowlist_external_urls: Vec::new(),
             auth_providers: Vec::new(),
+            auth_access_tokens: Vec::new(),
+            auth_permissions: HashMap::new(),
+            http: HttpConfig::default(),
         }
     }
 }
@@ -241,12 +273,24 @@ impl CoreConfig {
             .unwrap_or(Value::Mapping(serde_yaml::Mapping::new()));
 
         // Parse the homeassistant section
-        let config: CoreConfig =
+        let mut config: CoreConfig =
             serde_yaml::from_value(ha_section).map_err(|e| ConfigError::InvalidValue {
                 key: "homeassistant".to_string(),
                 reason: e.to_string(),
             })?;
 
+        // The http section is a separate top-level section
+        if let Some(http) = mapping
+            .get(Value::String("http".to_string()))
+            .filter(|http| !http.is_null())
+        {
+            config.http =
+                serde_yaml::from_value(http.clone()).map_err(|e| ConfigError::InvalidValue {
+                    key: "http".to_string(),
+                    reason: e.to_string(),
+                })?;
+        }
+
         Ok(config)
     }
 
@@ -255,6 +299,28 @@ impl CoreConfig {
         self.unit_system.to_unit_system()
     }
 
+    /// Origins allowed to make cross-origin requests
+    ///
+    /// The configured `http.cors_allowed_origins`, or else the origins of
+    /// the internal and external URLs.
+    pub fn cors_allowed_origins(&self) -> Vec<String> {
+        if !self.http.cors_allowed_origins.is_empty() {
+            return self
+                .http
+                .cors_allowed_origins
+                .iter()
+                .map(|origin| origin.trim_end_matches('/').to_string())
+                .collect();
+        }
+        let mut origins: Vec<String> = [&self.internal_url, &self.external_url]
+            .into_iter()
+            .flatten()
+            .filter_map(|url| url_origin(url))
+            .collect();
+        origins.dedup();
+        origins
+    }
+
     /// Convert to the API response format for /api/config
     pub fn to_api_response(&self, version: &str, components: &[String]) -> serde_json::Value {
         let unit_system = self.unit_system();
@@ -335,12 +401,19 @@ homeassistant:
   time_zone: UTC
   currency: USD
   country: US
+  auth_permissions:
+    kitchen-tablet:
+      domains: [light]
 "#,
         )
         .unwrap();
 
         let config = CoreConfig::from_yaml(&yaml).unwrap();
         assert_eq!(config.name, "Test Home");
+        assert_eq!(
+            config.auth_permissions["kitchen-tablet"]["domains"][0],
+            "light"
+        );
         assert_eq!(config.latitude, 51.5074);
         assert_eq!(config.longitude, -0.1278);
         assert_eq!(config.elevation, 11);
@@ -364,6 +437,39 @@ homeassistant:
         assert_eq!(units.temperature, "°F");
     }
 
+    #[test]
+    fn test_cors_allowed_origins() {
+        let yaml: Value = serde_yaml::from_str(
+            r#"
+homeassistant:
+  internal_url: "http://192.168.1.10:8123/"
+  external_url: "https://home.example.com/lovelace?x=1"
+"#,
+        )
+        .unwrap();
+        let config = CoreConfig::from_yaml(&yaml).unwrap();
+        assert_eq!(
+            config.cors_allowed_origins(),
+            vec!["http://192.168.1.10:8123", "https://home.example.com"]
+        );
+
+        let yaml: Value = serde_yaml::from_str(
+            r#"
+homeassistant:
+  internal_url: "http://192.168.1.10:8123"
+http:
+  cors_allowed_origins:
+    - https://cast.home-assistant.io/
+"#,
+        )
+        .unwrap();
+        let config = CoreConfig::from_yaml(&yaml).unwrap();
+        assert_eq!(
+            config.cors_allowed_origins(),
+            vec!["https://cast.home-assistant.io"]
+        );
+    }
+
     #[test]
     fn test_to_api_response() {
         let config = CoreConfig {
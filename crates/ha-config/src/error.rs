@@ -53,6 +53,10 @@ pub enum ConfigError {
     #[error("environment variable '{var}' not set")]
     EnvVarNotFound { var: String },
 
+    /// Environment variable could not be coerced to the requested type
+    #[error("environment variable '{var}' is invalid: {reason}")]
+    InvalidEnvVar { var: String, reason: String },
+
     /// Invalid configuration value
     #[error("invalid configuration value for '{key}': {reason}")]
     InvalidValue { key: String, reason: String },
@@ -9,7 +9,7 @@
 //! - `!include_dir_named dir` - Include all YAML files as a mapping
 //! - `!include_dir_merge_named dir` - Merge mappings from all YAML files
 //! - `!secret key` - Substitute from secrets.yaml
-//! - `!env_var VAR` - Environment variable substitution
+//! - `!env_var VAR [type] [default]` - Environment variable substitution
 //!
 //! # Example
 //!
@@ -29,7 +29,7 @@ mod error;
 mod loader;
 mod secrets;
 
-pub use core_config::{CoreConfig, UnitSystem, UnitSystemConfig};
+pub use core_config::{CoreConfig, HttpConfig, UnitSystem, UnitSystemConfig};
 pub use error::{ConfigError, ConfigResult};
 pub use loader::{load_yaml, load_yaml_string, YamlLoader};
 pub use secrets::Secrets;
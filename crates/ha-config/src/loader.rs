@@ -7,7 +7,7 @@
 //! - `!include_dir_named dir` - Include all YAML files as a mapping keyed by filename
 //! - `!include_dir_merge_named dir` - Merge mappings from all YAML files
 //! - `!secret key` - Substitute from secrets.yaml
-//! - `!env_var VAR` - Environment variable substitution
+//! - `!env_var VAR [type] [default]` - Environment variable substitution
 
 use crate::error::{ConfigError, ConfigResult};
 use crate::secrets::Secrets;
@@ -25,6 +25,8 @@ pub struct YamlLoader {
     secrets: Secrets,
     /// Track included files to detect circular includes
     include_stack: HashSet<PathBuf>,
+    /// Errors collected while validating; `None` when loading fail-fast
+    collected_errors: Option<Vec<ConfigError>>,
 }
 
 impl YamlLoader {
@@ -37,6 +39,7 @@ impl YamlLoader {
             config_dir,
             secrets,
             include_stack: HashSet::new(),
+            collected_errors: None,
         })
     }
 
@@ -46,6 +49,7 @@ impl YamlLoader {
             config_dir: config_dir.into(),
             secrets,
             include_stack: HashSet::new(),
+            collected_errors: None,
         }
     }
 
@@ -71,6 +75,28 @@ impl YamlLoader {
         result
     }
 
+    /// Validate a YAML file, collecting every include/secret/env error
+    ///
+    /// Unlike [`load_file`](Self::load_file), which stops at the first
+    /// problem, this keeps going and substitutes `null` for each tag that
+    /// failed so all errors can be reported in one pass. Returns the
+    /// processed configuration when no errors were found.
+    pub fn validate(&mut self, path: impl AsRef<Path>) -> Result<Value, Vec<ConfigError>> {
+        let previous = self.collected_errors.replace(Vec::new());
+        let result = self.load_file(path);
+        let mut errors =
+            std::mem::replace(&mut self.collected_errors, previous).unwrap_or_default();
+
+        match result {
+            Ok(value) if errors.is_empty() => Ok(value),
+            Ok(_) => Err(errors),
+            Err(e) => {
+                errors.push(e);
+                Err(errors)
+            }
+        }
+    }
+
     /// Load and process YAML from a string
     pub fn load_string(&mut self, content: &str, source_path: &Path) -> ConfigResult<Value> {
         let value: Value = serde_yaml::from_str(content).map_err(|e| ConfigError::ParseYaml {
@@ -116,7 +142,7 @@ impl YamlLoader {
 
         trace!("Processing tag '{}' with value {:?}", tag, value);
 
-        match tag.as_str() {
+        let result = match tag.as_str() {
             "!include" => self.process_include(value, source_path),
             "!include_dir_list" => self.process_include_dir_list(value, source_path),
             "!include_dir_merge_list" => self.process_include_dir_merge_list(value, source_path),
@@ -127,11 +153,20 @@ impl YamlLoader {
             _ => {
                 // Unknown tag, keep it as-is but process the inner value
                 let processed = self.process_value(value, source_path)?;
-                Ok(Value::Tagged(Box::new(serde_yaml::value::TaggedValue {
+                return Ok(Value::Tagged(Box::new(serde_yaml::value::TaggedValue {
                     tag: tagged.tag,
                     value: processed,
-                })))
+                })));
             }
+        };
+
+        // When validating, record the error and keep going with a null value
+        match (result, self.collected_errors.as_mut()) {
+            (Err(e), Some(errors)) => {
+                errors.push(e);
+                Ok(Value::Null)
+            }
+            (result, _) => result,
         }
     }
 
@@ -252,7 +287,9 @@ impl YamlLoader {
     }
 
     /// Process !env_var tag
-    /// Supports: `!env_var VAR` or `!env_var VAR default_value`
+    /// Supports: `!env_var VAR`, `!env_var VAR default_value`,
+    /// `!env_var VAR <type>` and `!env_var VAR <type> default_value`
+    /// where `<type>` is one of `str`, `int`, `float` or `bool`
     fn process_env_var(&self, value: Value) -> ConfigResult<Value> {
         let input = match value {
             Value::String(s) => s,
@@ -264,27 +301,41 @@ impl YamlLoader {
             }
         };
 
-        // Parse "VAR_NAME" or "VAR_NAME default_value"
+        // Parse "VAR_NAME", "VAR_NAME default_value" or "VAR_NAME type [default_value]"
         let parts: Vec<&str> = input.splitn(2, ' ').collect();
         let var_name = parts[0];
-        let default_value = parts.get(1).map(|s| s.to_string());
+        let rest = parts.get(1).map(|s| s.trim_start());
+
+        let (env_type, default_value) = match rest {
+            Some(rest) => {
+                let mut rest_parts = rest.splitn(2, ' ');
+                let first = rest_parts.next().unwrap_or_default();
+                match EnvVarType::parse(first) {
+                    Some(env_type) => (env_type, rest_parts.next().map(|s| s.to_string())),
+                    None => (EnvVarType::Str, Some(rest.to_string())),
+                }
+            }
+            None => (EnvVarType::Str, None),
+        };
 
-        match std::env::var(var_name) {
+        let raw = match std::env::var(var_name) {
             Ok(env_value) => {
                 debug!("Substituted env var: {}", var_name);
-                Ok(Value::String(env_value))
+                env_value
             }
             Err(_) => {
                 if let Some(default) = default_value {
                     debug!("Using default for env var {}: {}", var_name, default);
-                    Ok(Value::String(default))
+                    default
                 } else {
-                    Err(ConfigError::EnvVarNotFound {
+                    return Err(ConfigError::EnvVarNotFound {
                         var: var_name.to_string(),
-                    })
+                    });
                 }
             }
-        }
+        };
+
+        env_type.coerce(var_name, raw)
     }
 
     /// Convert a YAML value to a path, resolving relative to source file
@@ -395,6 +446,54 @@ impl YamlLoader {
     }
 }
 
+/// Type hint accepted by `!env_var VAR <type>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EnvVarType {
+    Str,
+    Int,
+    Float,
+    Bool,
+}
+
+impl EnvVarType {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "str" => Some(Self::Str),
+            "int" => Some(Self::Int),
+            "float" => Some(Self::Float),
+            "bool" => Some(Self::Bool),
+            _ => None,
+        }
+    }
+
+    /// Convert a raw environment value into a YAML value of this type
+    fn coerce(self, var: &str, raw: String) -> ConfigResult<Value> {
+        let invalid = |expected: &str| ConfigError::InvalidEnvVar {
+            var: var.to_string(),
+            reason: format!("expected {}, got '{}'", expected, raw),
+        };
+
+        match self {
+            Self::Str => Ok(Value::String(raw)),
+            Self::Int => raw
+                .trim()
+                .parse::<i64>()
+                .map(|i| Value::Number(i.into()))
+                .map_err(|_| invalid("an integer")),
+            Self::Float => raw
+                .trim()
+                .parse::<f64>()
+                .map(|f| Value::Number(f.into()))
+                .map_err(|_| invalid("a float")),
+            Self::Bool => match raw.trim().to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Ok(Value::Bool(true)),
+                "false" | "no" | "off" | "0" => Ok(Value::Bool(false)),
+                _ => Err(invalid("a boolean")),
+            },
+        }
+    }
+}
+
 /// Load a YAML file with full tag processing
 pub fn load_yaml(config_dir: impl Into<PathBuf>, file: impl AsRef<Path>) -> ConfigResult<Value> {
     let mut loader = YamlLoader::new(config_dir)?;
@@ -532,6 +631,103 @@ outer:
         assert!(matches!(result, Err(ConfigError::EnvVarNotFound { .. })));
     }
 
+    #[test]
+    fn test_environment_variable_str_type() {
+        let dir = TempDir::new().unwrap();
+        std::env::set_var("TEST_HA_STR_VAR", "8123");
+        write_file(
+            dir.path(),
+            "config.yaml",
+            "port: !env_var TEST_HA_STR_VAR str\n",
+        );
+
+        let value = load_yaml(dir.path(), "config.yaml").unwrap();
+        let map = value.as_mapping().unwrap();
+        assert_eq!(
+            map.get(Value::String("port".to_string())),
+            Some(&Value::String("8123".to_string()))
+        );
+
+        std::env::remove_var("TEST_HA_STR_VAR");
+    }
+
+    #[test]
+    fn test_environment_variable_int_type() {
+        let dir = TempDir::new().unwrap();
+        std::env::set_var("TEST_HA_INT_VAR", "8123");
+        write_file(
+            dir.path(),
+            "config.yaml",
+            "port: !env_var TEST_HA_INT_VAR int\n",
+        );
+
+        let value = load_yaml(dir.path(), "config.yaml").unwrap();
+        let map = value.as_mapping().unwrap();
+        assert_eq!(
+            map.get(Value::String("port".to_string())),
+            Some(&Value::Number(8123.into()))
+        );
+
+        std::env::remove_var("TEST_HA_INT_VAR");
+    }
+
+    #[test]
+    fn test_environment_variable_bool_type() {
+        let dir = TempDir::new().unwrap();
+        std::env::set_var("TEST_HA_BOOL_VAR", "yes");
+        write_file(
+            dir.path(),
+            "config.yaml",
+            "debug: !env_var TEST_HA_BOOL_VAR bool\n",
+        );
+
+        let value = load_yaml(dir.path(), "config.yaml").unwrap();
+        let map = value.as_mapping().unwrap();
+        assert_eq!(
+            map.get(Value::String("debug".to_string())),
+            Some(&Value::Bool(true))
+        );
+
+        std::env::remove_var("TEST_HA_BOOL_VAR");
+    }
+
+    #[test]
+    fn test_environment_variable_typed_default() {
+        let dir = TempDir::new().unwrap();
+        std::env::remove_var("TEST_HA_UNDEFINED_INT_VAR");
+        write_file(
+            dir.path(),
+            "config.yaml",
+            "port: !env_var TEST_HA_UNDEFINED_INT_VAR int 8080\n",
+        );
+
+        let value = load_yaml(dir.path(), "config.yaml").unwrap();
+        let map = value.as_mapping().unwrap();
+        assert_eq!(
+            map.get(Value::String("port".to_string())),
+            Some(&Value::Number(8080.into()))
+        );
+    }
+
+    #[test]
+    fn test_environment_variable_invalid_coercion() {
+        let dir = TempDir::new().unwrap();
+        std::env::set_var("TEST_HA_BAD_INT_VAR", "abc");
+        write_file(
+            dir.path(),
+            "config.yaml",
+            "port: !env_var TEST_HA_BAD_INT_VAR int\n",
+        );
+
+        let result = load_yaml(dir.path(), "config.yaml");
+        match result {
+            Err(ConfigError::InvalidEnvVar { var, .. }) => assert_eq!(var, "TEST_HA_BAD_INT_VAR"),
+            other => panic!("expected InvalidEnvVar, got {:?}", other),
+        }
+
+        std::env::remove_var("TEST_HA_BAD_INT_VAR");
+    }
+
     // ==================== Secret Tests ====================
 
     #[test]
@@ -891,6 +1087,43 @@ outer:
         assert!(matches!(result, Err(ConfigError::ParseYaml { .. })));
     }
 
+    // ==================== Validation Tests ====================
+
+    #[test]
+    fn test_validate_collects_all_errors() {
+        let dir = TempDir::new().unwrap();
+        write_file(dir.path(), "secrets.yaml", "existing: value\n");
+        write_file(
+            dir.path(),
+            "config.yaml",
+            "password: !secret nonexistent\ndata: !include nonexistent.yaml\n",
+        );
+
+        let mut loader = YamlLoader::new(dir.path()).unwrap();
+        let errors = loader.validate("config.yaml").unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e, ConfigError::SecretNotFound { .. })));
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e, ConfigError::ReadFile { .. })));
+    }
+
+    #[test]
+    fn test_validate_valid_config() {
+        let dir = TempDir::new().unwrap();
+        write_file(dir.path(), "config.yaml", "key: value\n");
+
+        let mut loader = YamlLoader::new(dir.path()).unwrap();
+        let value = loader.validate("config.yaml").unwrap();
+        assert!(value.is_mapping());
+
+        // Fail-fast loading is unaffected after validating
+        write_file(dir.path(), "bad.yaml", "data: !include nonexistent.yaml\n");
+        assert!(loader.load_file("bad.yaml").is_err());
+    }
+
     // ==================== Mixed Usage Tests ====================
 
     #[test]
@@ -3,6 +3,13 @@
 use serde::{Deserialize, Serialize};
 use ulid::Ulid;
 
+/// Deepest context chain an automation will still run for
+///
+/// Each automation run adds a level to the chain of the event that
+/// triggered it, so a chain this deep almost always means an automation
+/// is (indirectly) triggering itself.
+pub const MAX_CONTEXT_DEPTH: u32 = 10;
+
 /// Context for tracking the origin and causality of events and service calls
 ///
 /// Every event and service call in Home Assistant carries a Context that
@@ -20,6 +27,20 @@ pub struct Context {
     /// Parent context ID for tracking causality chains
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_id: Option<String>,
+
+    /// Number of ancestors in the parent chain
+    ///
+    /// Only tracked in-process; contexts received from outside start a new
+    /// chain.
+    #[serde(skip)]
+    depth: u32,
+
+    /// IDs of the ancestors in the parent chain, nearest first
+    ///
+    /// In-process only like `depth`, and limited to the nearest
+    /// `MAX_CONTEXT_DEPTH` ancestors.
+    #[serde(skip)]
+    ancestors: Vec<String>,
 }
 
 impl Context {
@@ -29,6 +50,8 @@ impl Context {
             id: Ulid::new().to_string(),
             user_id: None,
             parent_id: None,
+            depth: 0,
+            ancestors: Vec::new(),
         }
     }
 
@@ -38,6 +61,8 @@ impl Context {
             id: id.into(),
             user_id: None,
             parent_id: None,
+            depth: 0,
+            ancestors: Vec::new(),
         }
     }
 
@@ -47,26 +72,58 @@ impl Context {
             id: Ulid::new().to_string(),
             user_id: Some(user_id.into()),
             parent_id: None,
+            depth: 0,
+            ancestors: Vec::new(),
         }
     }
 
-    /// Create a child context with this context as parent
-    pub fn child(&self) -> Self {
+    /// Create a child context of `parent`, one level deeper in its chain
+    pub fn child_of(parent: &Context) -> Self {
         Self {
             id: Ulid::new().to_string(),
-            user_id: self.user_id.clone(),
-            parent_id: Some(self.id.clone()),
+            user_id: parent.user_id.clone(),
+            parent_id: Some(parent.id.clone()),
+            depth: parent.depth.saturating_add(1),
+            ancestors: std::iter::once(&parent.id)
+                .chain(&parent.ancestors)
+                .take(MAX_CONTEXT_DEPTH as usize)
+                .cloned()
+                .collect(),
         }
     }
 
+    /// Create a child context with this context as parent
+    pub fn child(&self) -> Self {
+        Self::child_of(self)
+    }
+
     /// Create a child context with a different user
     pub fn child_with_user(&self, user_id: impl Into<String>) -> Self {
         Self {
-            id: Ulid::new().to_string(),
             user_id: Some(user_id.into()),
-            parent_id: Some(self.id.clone()),
+            ..Self::child_of(self)
         }
     }
+
+    /// Number of ancestors of this context (0 for a root context)
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Whether this is the context with `id` or one of its descendants
+    ///
+    /// Follows the whole in-process parent chain; for contexts received from
+    /// outside only the direct parent is known.
+    pub fn descends_from(&self, id: &str) -> bool {
+        self.id == id
+            || self.parent_id.as_deref() == Some(id)
+            || self.ancestors.iter().any(|ancestor| ancestor == id)
+    }
+
+    /// Whether the parent chain is deeper than `max_depth`
+    pub fn exceeds_depth(&self, max_depth: u32) -> bool {
+        self.depth > max_depth
+    }
 }
 
 impl Default for Context {
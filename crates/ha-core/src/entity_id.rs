@@ -1,8 +1,12 @@
 //! Entity ID type representing a domain.object_id pair
 
 use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::HashSet;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
+use std::sync::{Arc, OnceLock, RwLock};
 use thiserror::Error;
 
 /// Error type for invalid entity IDs
@@ -17,26 +21,97 @@ pub enum EntityIdError {
     #[error("object_id cannot be empty")]
     EmptyObjectId,
 
+    #[error("domain cannot contain uppercase letters")]
+    UppercaseDomain,
+
+    #[error("object_id cannot contain uppercase letters")]
+    UppercaseObjectId,
+
+    #[error("domain cannot start with an underscore")]
+    InvalidDomainStart,
+
+    #[error("object_id cannot start with an underscore")]
+    InvalidObjectIdStart,
+
     #[error(
-        "domain contains invalid characters (must be lowercase alphanumeric with underscores, cannot start/end with underscore or contain double underscores)"
+        "domain contains invalid characters (must be lowercase alphanumeric with underscores, cannot end with underscore or contain double underscores)"
     )]
     InvalidDomainChars,
 
     #[error(
-        "object_id contains invalid characters (must be lowercase alphanumeric with underscores, cannot start/end with underscore)"
+        "object_id contains invalid characters (must be lowercase alphanumeric with underscores, cannot end with underscore)"
     )]
     InvalidObjectIdChars,
 }
 
+/// Interned `domain.object_id` strings shared by all equal entity IDs
+static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
+
+/// Minimum number of interned IDs before unused ones are swept
+const MIN_SWEEP_AT: usize = 1024;
+
+/// Set of interned IDs, swept of unused entries as it grows
+///
+/// IDs can come from outside (e.g. a REST POST to `/api/states`), so an ID
+/// no `EntityId` refers to anymore is dropped on the next sweep. Sweeping
+/// whenever the set doubles keeps inserts amortized O(1).
+struct Interner {
+    ids: HashSet<Arc<str>>,
+    sweep_at: usize,
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Self {
+            ids: HashSet::new(),
+            sweep_at: MIN_SWEEP_AT,
+        }
+    }
+}
+
+impl Interner {
+    fn insert(&mut self, entity_id: &str) -> Arc<str> {
+        if let Some(existing) = self.ids.get(entity_id) {
+            return existing.clone();
+        }
+        if self.ids.len() >= self.sweep_at {
+            // Only the set itself holds these
+            self.ids.retain(|id| Arc::strong_count(id) > 1);
+            self.sweep_at = (self.ids.len() * 2).max(MIN_SWEEP_AT);
+        }
+        let new: Arc<str> = Arc::from(entity_id);
+        self.ids.insert(new.clone());
+        new
+    }
+}
+
+/// Get the shared string for an entity ID, adding it on first use
+fn intern(entity_id: &str) -> Arc<str> {
+    let interner = INTERNER.get_or_init(Default::default);
+    if let Some(interned) = interner.read().unwrap().ids.get(entity_id) {
+        return interned.clone();
+    }
+    interner.write().unwrap().insert(entity_id)
+}
+
 /// Represents a Home Assistant entity ID (e.g., "light.living_room")
 ///
 /// Entity IDs consist of a domain and an object_id separated by a period.
-/// Both parts must be lowercase alphanumeric with underscores only.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// Both parts contain only lowercase alphanumerics and underscores and can't
+/// start or end with an underscore, as in HA's `valid_entity_id`. Leading
+/// digits are fine (`sensor.1st_floor`). Use [`EntityId::parse_lenient`] for
+/// IDs saved before these rules were enforced.
+///
+/// The full ID is interned, so cloning only bumps a reference count and
+/// equal IDs share one allocation. It hashes and borrows as its
+/// `domain.object_id` string, so maps keyed by `EntityId` can be queried
+/// with a `&str`.
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(try_from = "String", into = "String")]
 pub struct EntityId {
-    domain: String,
-    object_id: String,
+    id: Arc<str>,
+    /// Byte offset of the `.` separator
+    dot: usize,
 }
 
 impl EntityId {
@@ -47,49 +122,106 @@ impl EntityId {
     ) -> Result<Self, EntityIdError> {
         let domain = domain.into();
         let object_id = object_id.into();
+        Self::validate(&domain, &object_id)?;
+
+        Ok(Self {
+            id: intern(&format!("{}.{}", domain, object_id)),
+            dot: domain.len(),
+        })
+    }
 
+    /// Get the domain part of the entity ID
+    pub fn domain(&self) -> &str {
+        &self.id[..self.dot]
+    }
+
+    /// Get the object_id part of the entity ID
+    pub fn object_id(&self) -> &str {
+        &self.id[self.dot + 1..]
+    }
+
+    /// Get the full `domain.object_id` string without allocating
+    pub fn as_str(&self) -> &str {
+        &self.id
+    }
+
+    /// Parse an entity ID saved under looser rules, normalizing it
+    ///
+    /// Lowercases the ID and accepts parts starting or ending with an
+    /// underscore, so data written before the stricter validation can be
+    /// loaded. Parts must still be non-empty and contain only `[a-z0-9_]`
+    /// once lowercased.
+    pub fn parse_lenient(s: &str) -> Result<Self, EntityIdError> {
+        let s = s.trim().to_ascii_lowercase();
+        let (domain, object_id) = Self::split(&s)?;
         if domain.is_empty() {
             return Err(EntityIdError::EmptyDomain);
         }
         if object_id.is_empty() {
             return Err(EntityIdError::EmptyObjectId);
         }
-        if !Self::is_valid_domain(&domain) {
+        if !domain.chars().all(Self::is_valid_char) {
             return Err(EntityIdError::InvalidDomainChars);
         }
-        if !Self::is_valid_object_id(&object_id) {
+        if !object_id.chars().all(Self::is_valid_char) {
             return Err(EntityIdError::InvalidObjectIdChars);
         }
 
-        Ok(Self { domain, object_id })
+        Ok(Self {
+            id: intern(&s),
+            dot: domain.len(),
+        })
     }
 
-    /// Get the domain part of the entity ID
-    pub fn domain(&self) -> &str {
-        &self.domain
+    /// Split an entity ID into its domain and object_id
+    fn split(s: &str) -> Result<(&str, &str), EntityIdError> {
+        match s.split_once('.') {
+            Some((domain, object_id)) if !object_id.contains('.') => Ok((domain, object_id)),
+            _ => Err(EntityIdError::InvalidFormat),
+        }
     }
 
-    /// Get the object_id part of the entity ID
-    pub fn object_id(&self) -> &str {
-        &self.object_id
+    /// Check both parts of an entity ID
+    fn validate(domain: &str, object_id: &str) -> Result<(), EntityIdError> {
+        if domain.is_empty() {
+            return Err(EntityIdError::EmptyDomain);
+        }
+        if object_id.is_empty() {
+            return Err(EntityIdError::EmptyObjectId);
+        }
+        if domain.chars().any(|c| c.is_ascii_uppercase()) {
+            return Err(EntityIdError::UppercaseDomain);
+        }
+        if object_id.chars().any(|c| c.is_ascii_uppercase()) {
+            return Err(EntityIdError::UppercaseObjectId);
+        }
+        if domain.starts_with('_') {
+            return Err(EntityIdError::InvalidDomainStart);
+        }
+        if object_id.starts_with('_') {
+            return Err(EntityIdError::InvalidObjectIdStart);
+        }
+        if !Self::is_valid_domain(domain) {
+            return Err(EntityIdError::InvalidDomainChars);
+        }
+        if !Self::is_valid_object_id(object_id) {
+            return Err(EntityIdError::InvalidObjectIdChars);
+        }
+        Ok(())
     }
 
-    /// Check if an object_id is valid (lowercase alphanumeric + underscore, cannot start/end with _)
+    fn is_valid_char(c: char) -> bool {
+        c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'
+    }
+
+    /// Check if an object_id is valid (lowercase alphanumeric + underscore, cannot end with _)
     ///
-    /// Matches Python HA regex: `(?!_)[\da-z_]+(?<!_)`
+    /// The leading underscore is checked separately in `validate`.
     fn is_valid_object_id(s: &str) -> bool {
-        // Cannot start or end with underscore
-        if s.starts_with('_') || s.ends_with('_') {
-            return false;
-        }
-        // Must contain only lowercase alphanumeric and underscores
-        s.chars()
-            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        !s.ends_with('_') && s.chars().all(Self::is_valid_char)
     }
 
     /// Check if a domain is valid (same as object_id, plus cannot contain __)
-    ///
-    /// Matches Python HA regex: `(?!.+__)(?!_)[\da-z_]+(?<!_)`
     fn is_valid_domain(s: &str) -> bool {
         // Domain cannot contain double underscores
         if s.contains("__") {
@@ -104,11 +236,14 @@ impl FromStr for EntityId {
     type Err = EntityIdError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<&str> = s.split('.').collect();
-        if parts.len() != 2 {
-            return Err(EntityIdError::InvalidFormat);
-        }
-        Self::new(parts[0], parts[1])
+        let (domain, object_id) = Self::split(s)?;
+        Self::validate(domain, object_id)?;
+
+        // Validated in place, so a known ID is looked up without allocating
+        Ok(Self {
+            id: intern(s),
+            dot: domain.len(),
+        })
     }
 }
 
@@ -128,7 +263,35 @@ impl From<EntityId> for String {
 
 impl fmt::Display for EntityId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}.{}", self.domain, self.object_id)
+        f.write_str(&self.id)
+    }
+}
+
+impl fmt::Debug for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EntityId").field(&&*self.id).finish()
+    }
+}
+
+impl PartialEq for EntityId {
+    fn eq(&self, other: &Self) -> bool {
+        // Interned IDs are equal exactly when they share the allocation
+        Arc::ptr_eq(&self.id, &other.id) || self.id == other.id
+    }
+}
+
+impl Eq for EntityId {}
+
+impl Hash for EntityId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Must match `str`'s hash for `Borrow<str>`
+        self.as_str().hash(state)
+    }
+}
+
+impl Borrow<str> for EntityId {
+    fn borrow(&self) -> &str {
+        self.as_str()
     }
 }
 
@@ -179,11 +342,15 @@ mod tests {
     fn test_invalid_chars() {
         assert_eq!(
             "UPPER.case".parse::<EntityId>().unwrap_err(),
-            EntityIdError::InvalidDomainChars
+            EntityIdError::UppercaseDomain
         );
         assert_eq!(
             "light.UPPER".parse::<EntityId>().unwrap_err(),
-            EntityIdError::InvalidObjectIdChars
+            EntityIdError::UppercaseObjectId
+        );
+        assert_eq!(
+            "light.Living_room".parse::<EntityId>().unwrap_err(),
+            EntityIdError::UppercaseObjectId
         );
         assert_eq!(
             "with-dash.object".parse::<EntityId>().unwrap_err(),
@@ -196,7 +363,7 @@ mod tests {
         // Leading underscore in domain - invalid
         assert_eq!(
             "_light.room".parse::<EntityId>().unwrap_err(),
-            EntityIdError::InvalidDomainChars
+            EntityIdError::InvalidDomainStart
         );
         // Trailing underscore in domain - invalid
         assert_eq!(
@@ -206,7 +373,7 @@ mod tests {
         // Leading underscore in object_id - invalid
         assert_eq!(
             "light._room".parse::<EntityId>().unwrap_err(),
-            EntityIdError::InvalidObjectIdChars
+            EntityIdError::InvalidObjectIdStart
         );
         // Trailing underscore in object_id - invalid
         assert_eq!(
@@ -224,6 +391,62 @@ mod tests {
         assert!("my_light.living_room".parse::<EntityId>().is_ok());
     }
 
+    #[test]
+    fn test_leading_digit() {
+        // HA allows digits anywhere, e.g. the 17track integration
+        assert!("sensor.1st_floor".parse::<EntityId>().is_ok());
+        assert!("17track.package".parse::<EntityId>().is_ok());
+        assert!(EntityId::new("sensor", "2").is_ok());
+        assert!("sensor.floor_1".parse::<EntityId>().is_ok());
+        assert!("light.l2b".parse::<EntityId>().is_ok());
+    }
+
+    #[test]
+    fn test_new_matches_parse() {
+        assert_eq!(
+            EntityId::new("Light", "room").unwrap_err(),
+            EntityIdError::UppercaseDomain
+        );
+        assert_eq!(
+            EntityId::new("light", "").unwrap_err(),
+            EntityIdError::EmptyObjectId
+        );
+        assert_eq!(
+            EntityId::new("light", "a_b").unwrap(),
+            "light.a_b".parse::<EntityId>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let id = EntityId::parse_lenient("Sensor.1st_Floor").unwrap();
+        assert_eq!(id.as_str(), "sensor.1st_floor");
+        assert_eq!(id.domain(), "sensor");
+        assert_eq!(id.object_id(), "1st_floor");
+        assert_eq!(
+            EntityId::parse_lenient("light._room").unwrap().as_str(),
+            "light._room"
+        );
+        assert_eq!(
+            EntityId::parse_lenient("light.room_").unwrap().as_str(),
+            "light.room_"
+        );
+
+        // Still rejects IDs that can't be normalized
+        assert_eq!(
+            EntityId::parse_lenient("no_separator").unwrap_err(),
+            EntityIdError::InvalidFormat
+        );
+        assert_eq!(
+            EntityId::parse_lenient("light.").unwrap_err(),
+            EntityIdError::EmptyObjectId
+        );
+        assert_eq!(
+            EntityId::parse_lenient("light.living room").unwrap_err(),
+            EntityIdError::InvalidObjectIdChars
+        );
+    }
+
     #[test]
     fn test_serde_roundtrip() {
         let id = EntityId::new("switch", "kitchen").unwrap();
@@ -233,4 +456,30 @@ mod tests {
         let parsed: EntityId = serde_json::from_str(&json).unwrap();
         assert_eq!(parsed, id);
     }
+
+    #[test]
+    fn test_equal_ids_share_storage() {
+        let a = EntityId::new("light", "porch").unwrap();
+        let b: EntityId = "light.porch".parse().unwrap();
+        assert!(Arc::ptr_eq(&a.id, &b.id));
+        assert_eq!(b.as_str(), "light.porch");
+
+        // Lookups by string work on maps keyed by EntityId
+        let mut map = std::collections::HashMap::new();
+        map.insert(a, 1);
+        assert_eq!(map.get("light.porch"), Some(&1));
+    }
+
+    #[test]
+    fn test_interner_sweeps_unused_ids() {
+        let mut interner = Interner::default();
+        let kept = interner.insert("sensor.kept");
+        for i in 0..MIN_SWEEP_AT * 4 {
+            interner.insert(&format!("sensor.posted_{i}"));
+        }
+
+        // Dropped IDs are swept, so the set stays bounded
+        assert!(interner.ids.len() <= MIN_SWEEP_AT + 1);
+        assert!(Arc::ptr_eq(&kept, &interner.insert("sensor.kept")));
+    }
 }
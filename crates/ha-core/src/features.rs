@@ -0,0 +1,165 @@
+//! `supported_features` bitmasks
+//!
+//! Entities advertise optional capabilities as a bitmask in their
+//! `supported_features` attribute. [`SupportedFeatures`] sets and tests bits,
+//! and the domain modules hold the well-known feature flags with the same
+//! values as in HA.
+
+use serde::{Deserialize, Serialize};
+
+use crate::State;
+
+/// State attribute holding the bitmask
+pub const ATTR_SUPPORTED_FEATURES: &str = "supported_features";
+
+/// A `supported_features` bitmask
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SupportedFeatures(pub u32);
+
+impl SupportedFeatures {
+    /// No features
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Get the raw bitmask
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Whether all bits of `features` are set
+    pub const fn contains(self, features: u32) -> bool {
+        self.0 & features == features
+    }
+
+    /// Whether any bit of `features` is set
+    pub const fn intersects(self, features: u32) -> bool {
+        self.0 & features != 0
+    }
+
+    /// Return the mask with the bits of `features` set
+    pub const fn with(self, features: u32) -> Self {
+        Self(self.0 | features)
+    }
+
+    /// Set the bits of `features`
+    pub fn insert(&mut self, features: u32) {
+        self.0 |= features;
+    }
+
+    /// Clear the bits of `features`
+    pub fn remove(&mut self, features: u32) {
+        self.0 &= !features;
+    }
+
+    /// Read the bitmask of a state, empty if it has none
+    pub fn of(state: &State) -> Self {
+        state
+            .attributes
+            .get(ATTR_SUPPORTED_FEATURES)
+            .and_then(serde_json::Value::as_u64)
+            .and_then(|bits| u32::try_from(bits).ok())
+            .map_or(Self::empty(), Self)
+    }
+}
+
+impl From<u32> for SupportedFeatures {
+    fn from(bits: u32) -> Self {
+        Self(bits)
+    }
+}
+
+/// `LightEntityFeature` flags
+pub mod light {
+    pub const EFFECT: u32 = 4;
+    pub const FLASH: u32 = 8;
+    pub const TRANSITION: u32 = 32;
+}
+
+/// `CoverEntityFeature` flags
+pub mod cover {
+    pub const OPEN: u32 = 1;
+    pub const CLOSE: u32 = 2;
+    pub const SET_POSITION: u32 = 4;
+    pub const STOP: u32 = 8;
+    pub const OPEN_TILT: u32 = 16;
+    pub const CLOSE_TILT: u32 = 32;
+    pub const STOP_TILT: u32 = 64;
+    pub const SET_TILT_POSITION: u32 = 128;
+}
+
+/// `MediaPlayerEntityFeature` flags
+pub mod media_player {
+    pub const PAUSE: u32 = 1;
+    pub const SEEK: u32 = 2;
+    pub const VOLUME_SET: u32 = 4;
+    pub const VOLUME_MUTE: u32 = 8;
+    pub const PREVIOUS_TRACK: u32 = 16;
+    pub const NEXT_TRACK: u32 = 32;
+    pub const TURN_ON: u32 = 128;
+    pub const TURN_OFF: u32 = 256;
+    pub const PLAY_MEDIA: u32 = 512;
+    pub const VOLUME_STEP: u32 = 1024;
+    pub const SELECT_SOURCE: u32 = 2048;
+    pub const STOP: u32 = 4096;
+    pub const CLEAR_PLAYLIST: u32 = 8192;
+    pub const PLAY: u32 = 16384;
+    pub const SHUFFLE_SET: u32 = 32768;
+    pub const SELECT_SOUND_MODE: u32 = 65536;
+    pub const BROWSE_MEDIA: u32 = 131072;
+    pub const REPEAT_SET: u32 = 262144;
+    pub const GROUPING: u32 = 524288;
+    pub const MEDIA_ANNOUNCE: u32 = 1048576;
+    pub const MEDIA_ENQUEUE: u32 = 2097152;
+    pub const SEARCH_MEDIA: u32 = 4194304;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Context;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_combine_and_test_bits() {
+        let mut features = SupportedFeatures::empty()
+            .with(cover::OPEN | cover::CLOSE)
+            .with(cover::STOP);
+        assert_eq!(features.bits(), 11);
+        assert!(features.contains(cover::OPEN | cover::CLOSE));
+        assert!(!features.contains(cover::OPEN | cover::SET_POSITION));
+        assert!(features.intersects(cover::OPEN | cover::SET_POSITION));
+        assert!(!features.intersects(cover::OPEN_TILT | cover::CLOSE_TILT));
+
+        features.remove(cover::STOP);
+        assert!(!features.contains(cover::STOP));
+        features.insert(cover::SET_POSITION);
+        assert_eq!(features, SupportedFeatures::from(7));
+    }
+
+    #[test]
+    fn test_features_of_state() {
+        let state = State::new(
+            "media_player.tv".parse().unwrap(),
+            "on",
+            HashMap::from([(
+                ATTR_SUPPORTED_FEATURES.to_string(),
+                json!(media_player::PAUSE | media_player::PLAY),
+            )]),
+            Context::new(),
+        );
+        let features = SupportedFeatures::of(&state);
+        assert!(features.contains(media_player::PLAY));
+        assert!(!features.contains(media_player::SEEK));
+
+        let plain = State::new(
+            "light.kitchen".parse().unwrap(),
+            "on",
+            HashMap::new(),
+            Context::new(),
+        );
+        assert_eq!(SupportedFeatures::of(&plain), SupportedFeatures::empty());
+    }
+}
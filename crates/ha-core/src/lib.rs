@@ -7,12 +7,14 @@ mod context;
 pub mod domains;
 mod entity_id;
 mod event;
+pub mod features;
 mod service_call;
 mod state;
 
-pub use context::Context;
+pub use context::{Context, MAX_CONTEXT_DEPTH};
 pub use entity_id::{EntityId, EntityIdError};
-pub use event::{Event, EventData, EventType};
+pub use event::{Event, EventData, EventOrigin, EventType};
+pub use features::SupportedFeatures;
 pub use service_call::{ServiceCall, SupportsResponse};
 pub use state::State;
 
@@ -22,6 +24,9 @@ pub const MAX_STATE_LENGTH: usize = 255;
 /// State value used when actual state exceeds MAX_STATE_LENGTH
 pub const STATE_UNKNOWN: &str = "unknown";
 
+/// State value of an entity that can't currently be reached
+pub const STATE_UNAVAILABLE: &str = "unavailable";
+
 /// Standard event types used by Home Assistant
 pub mod events {
     use super::*;
@@ -35,9 +40,36 @@ pub mod events {
     /// Event type for service calls
     pub const CALL_SERVICE: &str = "call_service";
 
+    /// Event type for an automation run starting its actions
+    pub const AUTOMATION_TRIGGERED: &str = "automation_triggered";
+
+    /// Event type for a newly registered service
+    pub const SERVICE_REGISTERED: &str = "service_registered";
+
+    /// Event type for a removed service
+    pub const SERVICE_REMOVED: &str = "service_removed";
+
+    /// Event type for entity registry changes
+    pub const ENTITY_REGISTRY_UPDATED: &str = "entity_registry_updated";
+
+    /// Event type for device registry changes
+    pub const DEVICE_REGISTRY_UPDATED: &str = "device_registry_updated";
+
+    /// Event type for area registry changes
+    pub const AREA_REGISTRY_UPDATED: &str = "area_registry_updated";
+
+    /// Event type for floor registry changes
+    pub const FLOOR_REGISTRY_UPDATED: &str = "floor_registry_updated";
+
+    /// Event type for label registry changes
+    pub const LABEL_REGISTRY_UPDATED: &str = "label_registry_updated";
+
     /// Event type for Home Assistant start
     pub const HOMEASSISTANT_START: &str = "homeassistant_start";
 
+    /// Event type for Home Assistant having finished starting
+    pub const HOMEASSISTANT_STARTED: &str = "homeassistant_started";
+
     /// Event type for Home Assistant stop
     pub const HOMEASSISTANT_STOP: &str = "homeassistant_stop";
 
@@ -61,6 +93,50 @@ pub mod events {
         }
     }
 
+    /// Old and new value of a changed attribute (`None` when absent)
+    pub type AttributeChange<'a> = (Option<&'a serde_json::Value>, Option<&'a serde_json::Value>);
+
+    impl StateChangedData {
+        /// Attributes that were added, removed or changed by this event
+        ///
+        /// A missing old or new state counts as having no attributes, so
+        /// every attribute of an added or removed entity is reported.
+        pub fn changed_attributes(&self) -> std::collections::HashMap<&str, AttributeChange<'_>> {
+            let old = self.old_state.as_ref().map(|s| &s.attributes);
+            let new = self.new_state.as_ref().map(|s| &s.attributes);
+            let keys = old
+                .into_iter()
+                .chain(new)
+                .flat_map(|attributes| attributes.keys());
+
+            let mut changes = std::collections::HashMap::new();
+            for key in keys {
+                let old_value = old.and_then(|attributes| attributes.get(key));
+                let new_value = new.and_then(|attributes| attributes.get(key));
+                if old_value != new_value {
+                    changes.insert(key.as_str(), (old_value, new_value));
+                }
+            }
+            changes
+        }
+
+        /// Whether a single attribute differs between the old and new state
+        ///
+        /// Like checking [`changed_attributes`](Self::changed_attributes)
+        /// for one key, without building the whole map.
+        pub fn attribute_changed(&self, attribute: &str) -> bool {
+            let old = self
+                .old_state
+                .as_ref()
+                .and_then(|s| s.attributes.get(attribute));
+            let new = self
+                .new_state
+                .as_ref()
+                .and_then(|s| s.attributes.get(attribute));
+            old != new
+        }
+    }
+
     /// Data for STATE_REPORTED events (when state is unchanged but reported)
     #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
     pub struct StateReportedData {
@@ -90,4 +166,169 @@ pub mod events {
             CALL_SERVICE
         }
     }
+
+    /// Data for ENTITY_REGISTRY_UPDATED events
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    pub struct EntityRegistryUpdatedData {
+        /// "create", "update" or "remove"
+        pub action: String,
+        pub entity_id: String,
+        /// Previous values of the changed fields (for "update")
+        #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+        pub changes: serde_json::Map<String, serde_json::Value>,
+        /// Previous entity_id when the entity was renamed
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub old_entity_id: Option<String>,
+    }
+
+    impl EventData for EntityRegistryUpdatedData {
+        fn event_type() -> &'static str {
+            ENTITY_REGISTRY_UPDATED
+        }
+    }
+
+    /// Data for DEVICE_REGISTRY_UPDATED events
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    pub struct DeviceRegistryUpdatedData {
+        /// "create", "update" or "remove"
+        pub action: String,
+        pub device_id: String,
+        /// Previous values of the changed fields (for "update")
+        #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+        pub changes: serde_json::Map<String, serde_json::Value>,
+    }
+
+    impl EventData for DeviceRegistryUpdatedData {
+        fn event_type() -> &'static str {
+            DEVICE_REGISTRY_UPDATED
+        }
+    }
+
+    /// Data for AREA_REGISTRY_UPDATED events
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    pub struct AreaRegistryUpdatedData {
+        /// "create", "update" or "remove"
+        pub action: String,
+        pub area_id: String,
+    }
+
+    impl EventData for AreaRegistryUpdatedData {
+        fn event_type() -> &'static str {
+            AREA_REGISTRY_UPDATED
+        }
+    }
+
+    /// Data for FLOOR_REGISTRY_UPDATED events
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    pub struct FloorRegistryUpdatedData {
+        /// "create", "update" or "remove"
+        pub action: String,
+        pub floor_id: String,
+    }
+
+    impl EventData for FloorRegistryUpdatedData {
+        fn event_type() -> &'static str {
+            FLOOR_REGISTRY_UPDATED
+        }
+    }
+
+    /// Data for LABEL_REGISTRY_UPDATED events
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    pub struct LabelRegistryUpdatedData {
+        /// "create", "update" or "remove"
+        pub action: String,
+        pub label_id: String,
+    }
+
+    impl EventData for LabelRegistryUpdatedData {
+        fn event_type() -> &'static str {
+            LABEL_REGISTRY_UPDATED
+        }
+    }
+
+    /// Data for SERVICE_REGISTERED events
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    pub struct ServiceRegisteredData {
+        pub domain: String,
+        pub service: String,
+    }
+
+    impl EventData for ServiceRegisteredData {
+        fn event_type() -> &'static str {
+            SERVICE_REGISTERED
+        }
+    }
+
+    /// Data for SERVICE_REMOVED events
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    pub struct ServiceRemovedData {
+        pub domain: String,
+        pub service: String,
+    }
+
+    impl EventData for ServiceRemovedData {
+        fn event_type() -> &'static str {
+            SERVICE_REMOVED
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::events::StateChangedData;
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn state(attributes: serde_json::Value) -> State {
+        let attributes: HashMap<String, serde_json::Value> =
+            serde_json::from_value(attributes).unwrap();
+        State::new(
+            "light.kitchen".parse().unwrap(),
+            "on",
+            attributes,
+            Context::new(),
+        )
+    }
+
+    #[test]
+    fn test_changed_attributes_single_key() {
+        let data = StateChangedData {
+            entity_id: "light.kitchen".parse().unwrap(),
+            old_state: Some(state(json!({"brightness": 100, "color_mode": "hs"}))),
+            new_state: Some(state(json!({"brightness": 180, "color_mode": "hs"}))),
+        };
+
+        let changes = data.changed_attributes();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes["brightness"],
+            (Some(&json!(100)), Some(&json!(180)))
+        );
+        assert!(data.attribute_changed("brightness"));
+        assert!(!data.attribute_changed("color_mode"));
+        assert!(!data.attribute_changed("effect"));
+    }
+
+    #[test]
+    fn test_changed_attributes_added_and_removed() {
+        let data = StateChangedData {
+            entity_id: "light.kitchen".parse().unwrap(),
+            old_state: Some(state(json!({"effect": "rainbow"}))),
+            new_state: Some(state(json!({"brightness": 50}))),
+        };
+        let changes = data.changed_attributes();
+        assert_eq!(changes["effect"], (Some(&json!("rainbow")), None));
+        assert_eq!(changes["brightness"], (None, Some(&json!(50))));
+        assert!(data.attribute_changed("effect"));
+        assert!(data.attribute_changed("brightness"));
+
+        // A removed entity loses all of its attributes
+        let removed = StateChangedData {
+            new_state: None,
+            ..data
+        };
+        assert_eq!(removed.changed_attributes().len(), 1);
+        assert!(removed.attribute_changed("effect"));
+    }
 }
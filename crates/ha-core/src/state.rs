@@ -5,7 +5,7 @@ use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::{Context, EntityId};
+use crate::{Context, EntityId, STATE_UNAVAILABLE, STATE_UNKNOWN};
 
 /// Represents the state of an entity at a point in time
 ///
@@ -85,12 +85,20 @@ impl State {
 
     /// Check if the state value represents an unavailable entity
     pub fn is_unavailable(&self) -> bool {
-        self.state == "unavailable"
+        self.state == STATE_UNAVAILABLE
     }
 
     /// Check if the state value represents an unknown state
     pub fn is_unknown(&self) -> bool {
-        self.state == "unknown"
+        self.state == STATE_UNKNOWN
+    }
+
+    /// Check if the state holds a real value, i.e. is neither `unavailable`
+    /// nor `unknown`
+    ///
+    /// This is what templates' `has_value` tests.
+    pub fn is_available(&self) -> bool {
+        !self.is_unavailable() && !self.is_unknown()
     }
 
     /// Get an attribute value by key
@@ -111,5 +119,37 @@ impl PartialEq for State {
     }
 }
 
-// Unit tests removed - covered by HA native tests via `make ha-compat-test`
-// See tests/ha_compat/ for comprehensive State testing through Python bindings
+// Unit tests for the Rust-side behavior; run `make ha-compat-test` for the
+// comprehensive State tests in tests/ha_compat/ through Python bindings
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(value: &str) -> State {
+        State::new(
+            "sensor.temperature".parse().unwrap(),
+            value,
+            HashMap::new(),
+            Context::new(),
+        )
+    }
+
+    #[test]
+    fn test_availability() {
+        let unavailable = state("unavailable");
+        assert!(unavailable.is_unavailable());
+        assert!(!unavailable.is_unknown());
+        assert!(!unavailable.is_available());
+
+        let unknown = state("unknown");
+        assert!(!unknown.is_unavailable());
+        assert!(unknown.is_unknown());
+        assert!(!unknown.is_available());
+
+        let value = state("21.5");
+        assert!(!value.is_unavailable());
+        assert!(!value.is_unknown());
+        assert!(value.is_available());
+    }
+}
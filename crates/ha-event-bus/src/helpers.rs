@@ -0,0 +1,116 @@
+//! Helpers for event-driven code
+//!
+//! Counterparts of `homeassistant.helpers.event` for code that reacts to
+//! specific entities, plus utilities to coalesce bursts of updates. Shared
+//! by Rust components and the Python bridge.
+
+pub mod debounce;
+
+pub use debounce::{Debouncer, Throttle};
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use ha_core::events::{StateChangedData, STATE_CHANGED};
+use ha_core::Event;
+
+use crate::{EventBus, ListenerId};
+
+/// Callback for state changes of tracked entities
+pub type StateChangeCallback = Arc<dyn Fn(&Event<StateChangedData>) + Send + Sync>;
+
+/// Call `action` for every `state_changed` event of the given entities
+///
+/// Like Python's `async_track_state_change_event`. Events of other entities
+/// are filtered out before their data is parsed. The action runs inline
+/// during `fire()`; pass the returned listener to
+/// [`EventBus::remove_sync_listener`] to stop tracking.
+pub fn track_state_change_event<I, S>(
+    event_bus: &EventBus,
+    entity_ids: I,
+    action: StateChangeCallback,
+) -> ListenerId
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let entity_ids: HashSet<String> = entity_ids
+        .into_iter()
+        .map(|entity_id| entity_id.as_ref().trim().to_lowercase())
+        .collect();
+
+    event_bus.listen_sync(
+        STATE_CHANGED,
+        Arc::new(move |event| {
+            let tracked = event
+                .data
+                .get("entity_id")
+                .and_then(|entity_id| entity_id.as_str())
+                .is_some_and(|entity_id| entity_ids.contains(entity_id));
+            if !tracked {
+                return;
+            }
+            let Ok(data) = serde_json::from_value::<StateChangedData>(event.data.clone()) else {
+                return;
+            };
+            action(&Event {
+                event_type: event.event_type.clone(),
+                data,
+                origin: event.origin,
+                time_fired: event.time_fired,
+                context: event.context.clone(),
+            });
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ha_core::{Context, State};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    fn fire_state(bus: &EventBus, entity_id: &str, state: &str) {
+        let entity_id: ha_core::EntityId = entity_id.parse().unwrap();
+        let new_state = State::new(entity_id.clone(), state, HashMap::new(), Context::new());
+        bus.fire_typed(
+            StateChangedData {
+                entity_id,
+                old_state: None,
+                new_state: Some(new_state),
+            },
+            Context::new(),
+        );
+    }
+
+    #[test]
+    fn test_track_state_change_event_filters_entities() {
+        let bus = EventBus::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorder = seen.clone();
+        let listener = track_state_change_event(
+            &bus,
+            ["light.kitchen", "Sensor.Temperature"],
+            Arc::new(move |event| {
+                let state = event.data.new_state.as_ref().unwrap().state.clone();
+                recorder
+                    .lock()
+                    .unwrap()
+                    .push(format!("{}={}", event.data.entity_id, state));
+            }),
+        );
+
+        fire_state(&bus, "light.kitchen", "on");
+        fire_state(&bus, "light.porch", "on");
+        fire_state(&bus, "sensor.temperature", "21");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            ["light.kitchen=on", "sensor.temperature=21"]
+        );
+
+        bus.remove_sync_listener(listener);
+        fire_state(&bus, "light.kitchen", "off");
+        assert_eq!(seen.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,240 @@
+//! Debouncing and throttling of callbacks
+//!
+//! Event-driven components often recompute on every change they see. These
+//! wrappers coalesce bursts of calls into fewer callback invocations. Both
+//! always make a trailing invocation with the latest value, so nothing is
+//! lost. Both spawn their tasks on the tokio runtime they were created in,
+//! so they must be created within a runtime but may be called from any
+//! thread, e.g. from sync listeners of a state set outside the runtime.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+
+type Callback<T> = Arc<dyn Fn(T) + Send + Sync>;
+
+/// Calls its callback once calls have stopped for a quiet window
+///
+/// Each call replaces the pending value and restarts the window, so a burst
+/// of calls results in a single invocation with the last value.
+pub struct Debouncer<T> {
+    runtime: Handle,
+    window: Duration,
+    callback: Callback<T>,
+    pending: Arc<Mutex<Option<T>>>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl<T: Send + 'static> Debouncer<T> {
+    /// Create a debouncer calling `callback` after `window` without calls
+    ///
+    /// # Panics
+    ///
+    /// If called outside a tokio runtime.
+    pub fn new(window: Duration, callback: impl Fn(T) + Send + Sync + 'static) -> Self {
+        Self {
+            runtime: Handle::current(),
+            window,
+            callback: Arc::new(callback),
+            pending: Arc::new(Mutex::new(None)),
+            task: Mutex::new(None),
+        }
+    }
+
+    /// Schedule an invocation with `value`, replacing any pending one
+    pub fn call(&self, value: T) {
+        let mut task = self.task.lock().unwrap();
+        if let Some(task) = task.take() {
+            task.abort();
+        }
+        *self.pending.lock().unwrap() = Some(value);
+
+        let window = self.window;
+        let pending = self.pending.clone();
+        let callback = self.callback.clone();
+        *task = Some(self.runtime.spawn(async move {
+            tokio::time::sleep(window).await;
+            let value = pending.lock().unwrap().take();
+            if let Some(value) = value {
+                callback(value);
+            }
+        }));
+    }
+
+    /// Drop the pending invocation, if any
+    pub fn cancel(&self) {
+        if let Some(task) = self.task.lock().unwrap().take() {
+            task.abort();
+        }
+        self.pending.lock().unwrap().take();
+    }
+}
+
+impl<T> Drop for Debouncer<T> {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.get_mut().unwrap().take() {
+            task.abort();
+        }
+    }
+}
+
+/// State shared between a throttle and its cooldown task
+struct ThrottleState<T> {
+    /// Latest value received during the cooldown
+    pending: Option<T>,
+    /// Whether a cooldown task is running
+    cooling_down: bool,
+}
+
+/// Calls its callback at most once per window
+///
+/// A call while idle invokes the callback right away, on the caller's
+/// thread, and starts a cooldown. Calls during the cooldown are coalesced
+/// into one trailing invocation with the latest value when it ends, which
+/// starts another cooldown.
+pub struct Throttle<T> {
+    runtime: Handle,
+    window: Duration,
+    callback: Callback<T>,
+    state: Arc<Mutex<ThrottleState<T>>>,
+}
+
+impl<T: Send + 'static> Throttle<T> {
+    /// Create a throttle calling `callback` at most once per `window`
+    ///
+    /// # Panics
+    ///
+    /// If called outside a tokio runtime.
+    pub fn new(window: Duration, callback: impl Fn(T) + Send + Sync + 'static) -> Self {
+        Self {
+            runtime: Handle::current(),
+            window,
+            callback: Arc::new(callback),
+            state: Arc::new(Mutex::new(ThrottleState {
+                pending: None,
+                cooling_down: false,
+            })),
+        }
+    }
+
+    /// Invoke the callback now, or with the latest value once the cooldown ends
+    pub fn call(&self, value: T) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.cooling_down {
+                state.pending = Some(value);
+                return;
+            }
+            state.cooling_down = true;
+        }
+        (self.callback)(value);
+
+        let window = self.window;
+        let state = self.state.clone();
+        let callback = self.callback.clone();
+        self.runtime.spawn(async move {
+            loop {
+                tokio::time::sleep(window).await;
+                let value = {
+                    let mut state = state.lock().unwrap();
+                    let value = state.pending.take();
+                    if value.is_none() {
+                        state.cooling_down = false;
+                    }
+                    value
+                };
+                match value {
+                    Some(value) => callback(value),
+                    None => break,
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_millis(100);
+
+    fn recorder() -> (Arc<Mutex<Vec<u32>>>, impl Fn(u32) + Send + Sync + 'static) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let record = calls.clone();
+        (calls, move |value| record.lock().unwrap().push(value))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_debouncer_coalesces_rapid_calls() {
+        let (calls, callback) = recorder();
+        let debouncer = Debouncer::new(WINDOW, callback);
+
+        for value in 1..=10 {
+            debouncer.call(value);
+            tokio::time::sleep(WINDOW / 4).await;
+        }
+        assert!(calls.lock().unwrap().is_empty());
+
+        tokio::time::sleep(WINDOW).await;
+        assert_eq!(*calls.lock().unwrap(), [10]);
+
+        // A later call starts a new window
+        debouncer.call(11);
+        tokio::time::sleep(WINDOW * 2).await;
+        assert_eq!(*calls.lock().unwrap(), [10, 11]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_debouncer_cancel() {
+        let (calls, callback) = recorder();
+        let debouncer = Debouncer::new(WINDOW, callback);
+
+        debouncer.call(1);
+        debouncer.cancel();
+        tokio::time::sleep(WINDOW * 2).await;
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttle_leading_and_trailing() {
+        let (calls, callback) = recorder();
+        let throttle = Throttle::new(WINDOW, callback);
+
+        for value in 1..=10 {
+            throttle.call(value);
+        }
+        // The first call runs right away, the rest wait for the cooldown
+        assert_eq!(*calls.lock().unwrap(), [1]);
+
+        tokio::time::sleep(WINDOW + WINDOW / 2).await;
+        assert_eq!(*calls.lock().unwrap(), [1, 10]);
+
+        // Idle again after a quiet window
+        tokio::time::sleep(WINDOW * 2).await;
+        throttle.call(11);
+        assert_eq!(*calls.lock().unwrap(), [1, 10, 11]);
+    }
+
+    #[tokio::test]
+    async fn test_called_from_thread_without_runtime() {
+        let (calls, callback) = recorder();
+        let debouncer = Arc::new(Debouncer::new(Duration::from_millis(10), callback));
+        let (throttled, callback) = recorder();
+        let throttle = Arc::new(Throttle::new(Duration::from_millis(10), callback));
+
+        let (d, t) = (debouncer.clone(), throttle.clone());
+        std::thread::spawn(move || {
+            d.call(1);
+            t.call(1);
+            t.call(2);
+        })
+        .join()
+        .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(*calls.lock().unwrap(), [1]);
+        assert_eq!(*throttled.lock().unwrap(), [1, 2]);
+    }
+}
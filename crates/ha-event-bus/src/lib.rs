@@ -7,6 +7,8 @@
 //! Events are wrapped in `Arc` to avoid cloning event data for each subscriber.
 //! This is a significant optimization for events with large JSON payloads.
 
+pub mod helpers;
+
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
@@ -221,6 +223,11 @@ impl EventBus {
         self.listeners.len()
     }
 
+    /// Get the number of live receivers returned by `subscribe_all`
+    pub fn subscribe_all_count(&self) -> usize {
+        self.match_all_sender.receiver_count()
+    }
+
     /// Get the number of sync listeners across all event types
     pub fn sync_listener_count(&self) -> usize {
         self.sync_listeners
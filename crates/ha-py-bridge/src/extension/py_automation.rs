@@ -184,6 +184,7 @@ impl PyAutomationManager {
 
         self.inner
             .reload(parsed_configs)
+            .map(|_| ())
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
     }
 
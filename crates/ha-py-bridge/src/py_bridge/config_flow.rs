@@ -13,15 +13,17 @@ use tokio::sync::RwLock;
 use tracing::{debug, info};
 use ulid::Ulid;
 
-use ha_api::config_flow::{ConfigFlowHandler, FlowResult, FormField};
+use ha_api::config_flow::{ConfigFlowHandler, FlowResult, FormField, OptionsFlowHandler};
 use ha_api::manifest::get_manifest;
 use ha_api::ApplicationCredentialsStore;
+use ha_config_entries::ConfigEntry;
 use ha_event_bus::EventBus;
 use ha_registries::Registries;
 use ha_service_registry::ServiceRegistry;
 use ha_state_store::StateStore;
 
 use super::async_bridge::AsyncBridge;
+use super::config_entry::config_entry_to_python;
 use super::hass_wrapper::create_hass_wrapper_for_config_flow;
 use super::requirements::RequirementsManager;
 
@@ -33,6 +35,44 @@ struct ActiveFlow {
     flow_instance: PyObject,
     /// Current step ID
     current_step: String,
+    /// Flow context (source, and entry_id for reauth flows)
+    context: serde_json::Value,
+}
+
+/// How a flow was started, decides its context and first step
+enum FlowSource<'a> {
+    /// Started by the user from the integrations page
+    User,
+    /// Started because the entry's credentials stopped working
+    Reauth(&'a ConfigEntry),
+}
+
+impl FlowSource<'_> {
+    /// The flow context as seen by Python and flow listings
+    fn context(&self) -> serde_json::Value {
+        match self {
+            FlowSource::User => serde_json::json!({ "source": "user" }),
+            FlowSource::Reauth(entry) => serde_json::json!({
+                "source": "reauth",
+                "entry_id": entry.entry_id,
+                "unique_id": entry.unique_id,
+                "title_placeholders": { "name": entry.title },
+            }),
+        }
+    }
+
+    /// The first step to run and its input
+    fn first_step(&self) -> (&'static str, Option<serde_json::Value>) {
+        match self {
+            FlowSource::User => ("user", None),
+            FlowSource::Reauth(entry) => (
+                "reauth",
+                Some(serde_json::Value::Object(
+                    entry.data.clone().into_iter().collect(),
+                )),
+            ),
+        }
+    }
 }
 
 /// Manages active configuration flows
@@ -249,9 +289,23 @@ fn convert_flow_result_standalone(
         result: entry_result,
         last_step,
         preview,
+        unique_id: flow_unique_id(result),
     })
 }
 
+/// Unique ID a flow set with `async_set_unique_id`
+///
+/// Results carry the flow's context, which holds the unique ID.
+fn flow_unique_id(result: &pyo3::Bound<'_, pyo3::PyAny>) -> Option<String> {
+    result
+        .get_item("context")
+        .ok()?
+        .get_item("unique_id")
+        .ok()?
+        .extract::<String>()
+        .ok()
+}
+
 /// Convert a voluptuous schema to form fields (standalone version)
 fn convert_schema_to_fields_standalone(
     schema: &pyo3::Bound<'_, pyo3::PyAny>,
@@ -295,6 +349,8 @@ fn convert_schema_to_fields_standalone(
 pub struct ConfigFlowManager {
     /// Active flows: flow_id -> flow state
     flows: RwLock<HashMap<String, ActiveFlow>>,
+    /// Active options flows: flow_id -> flow state (handler is the entry ID)
+    options_flows: RwLock<HashMap<String, ActiveFlow>>,
     /// Event bus reference
     event_bus: Arc<EventBus>,
     /// State machine reference
@@ -327,6 +383,7 @@ impl ConfigFlowManager {
     ) -> Self {
         Self {
             flows: RwLock::new(HashMap::new()),
+            options_flows: RwLock::new(HashMap::new()),
             event_bus,
             state_machine,
             service_registry,
@@ -370,15 +427,63 @@ impl ConfigFlowManager {
         })
     }
 
-    /// Create a Python config flow instance and call async_step_user
+    /// Create a Python config flow instance and call its first step
+    ///
+    /// User flows start at async_step_user, reauth flows at async_step_reauth
+    /// with the entry's data.
     fn create_flow_instance(
         &self,
         py: Python<'_>,
         handler: &str,
         flow_id: &str,
-        _show_advanced_options: bool,
+        source: &FlowSource<'_>,
         hass: &PyObject,
     ) -> Result<(PyObject, FlowResult), String> {
+        let flow_class = self.load_flow_class(py, handler)?;
+
+        // Instantiate the flow
+        let flow_instance = flow_class
+            .call0()
+            .map_err(|e| format!("Failed to instantiate config flow: {}", e))?;
+
+        // Set hass attribute on the flow
+        flow_instance
+            .setattr("hass", hass)
+            .map_err(|e| format!("Failed to set hass on flow: {}", e))?;
+
+        // Set flow_id - HA sets this internally
+        flow_instance
+            .setattr("flow_id", flow_id)
+            .map_err(|e| format!("Failed to set flow_id: {}", e))?;
+
+        // Set context - used by flows for discovery info, reauth entry, etc.
+        let context = json_to_pyobject(py, &source.context())
+            .ok_or_else(|| "Failed to convert flow context".to_string())?;
+        flow_instance
+            .setattr("context", context)
+            .map_err(|e| format!("Failed to set context: {}", e))?;
+
+        // Call the first step to get the initial form
+        let (step, input) = source.first_step();
+        let result = self.call_flow_step(
+            py,
+            &flow_instance.clone().unbind(),
+            step,
+            input,
+            flow_id,
+            handler,
+            hass,
+        )?;
+
+        Ok((flow_instance.unbind(), result))
+    }
+
+    /// Import an integration's config_flow module and get its ConfigFlow class
+    fn load_flow_class<'py>(
+        &self,
+        py: Python<'py>,
+        handler: &str,
+    ) -> Result<pyo3::Bound<'py, pyo3::PyAny>, String> {
         // Ensure requirements are installed before importing
         // This installs packages like 'accuweather' that the integration needs
         self.ensure_integration_requirements(handler)?;
@@ -393,38 +498,48 @@ impl ConfigFlowManager {
 
         // Find the ConfigFlow class (it's usually named {Domain}FlowHandler or ConfigFlow)
         // Try common naming patterns
-        let flow_class = self.find_flow_class(py, &module, handler)?;
+        self.find_flow_class(py, &module, handler)
+    }
+
+    /// Create a Python options flow for an entry and call async_step_init
+    ///
+    /// Like HA, the flow comes from the ConfigFlow class's
+    /// `async_get_options_flow(config_entry)` and its handler is the entry ID.
+    fn create_options_flow_instance(
+        &self,
+        py: Python<'_>,
+        entry: &ConfigEntry,
+        flow_id: &str,
+        hass: &PyObject,
+    ) -> Result<(PyObject, FlowResult), String> {
+        let flow_class = self.load_flow_class(py, &entry.domain)?;
+        let py_entry = config_entry_to_python(py, entry)
+            .map_err(|e| format!("Failed to convert config entry: {}", e))?;
 
-        // Instantiate the flow
         let flow_instance = flow_class
-            .call0()
-            .map_err(|e| format!("Failed to instantiate config flow: {}", e))?;
+            .call_method1("async_get_options_flow", (py_entry,))
+            .map_err(|e| format!("{} does not support options: {}", entry.domain, e))?;
 
-        // Set hass attribute on the flow
         flow_instance
             .setattr("hass", hass)
             .map_err(|e| format!("Failed to set hass on flow: {}", e))?;
-
-        // Set flow_id - HA sets this internally
+        flow_instance
+            .setattr("handler", &entry.entry_id)
+            .map_err(|e| format!("Failed to set handler: {}", e))?;
         flow_instance
             .setattr("flow_id", flow_id)
             .map_err(|e| format!("Failed to set flow_id: {}", e))?;
-
-        // Set context - used by flows for discovery info, etc.
-        let context = PyDict::new_bound(py);
-        context.set_item("source", "user").unwrap();
         flow_instance
-            .setattr("context", context)
+            .setattr("context", PyDict::new_bound(py))
             .map_err(|e| format!("Failed to set context: {}", e))?;
 
-        // Call async_step_user(None) to get the initial form
         let result = self.call_flow_step(
             py,
             &flow_instance.clone().unbind(),
-            "user",
+            "init",
             None,
             flow_id,
-            handler,
+            &entry.entry_id,
             hass,
         )?;
 
@@ -689,6 +804,7 @@ impl ConfigFlowManager {
             result: entry_result,
             last_step,
             preview,
+            unique_id: flow_unique_id(result),
         })
     }
 
@@ -745,35 +861,34 @@ impl ConfigFlowManager {
     }
 }
 
-#[async_trait]
-impl ConfigFlowHandler for ConfigFlowManager {
-    async fn start_flow(
+impl ConfigFlowManager {
+    /// Create the Python flow, run its first step and track it as active
+    async fn start_flow_with_source(
         &self,
         handler: &str,
-        show_advanced_options: bool,
+        flow_id: &str,
+        source: FlowSource<'_>,
     ) -> Result<FlowResult, String> {
-        let flow_id = Ulid::new().to_string().to_lowercase();
-        info!(
-            "Starting config flow for {} with flow_id {}",
-            handler, flow_id
-        );
-
         let hass = self.create_hass()?;
 
         // Import and instantiate the Python config flow
-        let (flow_instance, result) = Python::with_gil(|py| {
-            self.create_flow_instance(py, handler, &flow_id, show_advanced_options, &hass)
-        })?;
+        let (flow_instance, result) =
+            Python::with_gil(|py| self.create_flow_instance(py, handler, flow_id, &source, &hass))?;
 
         // Store the active flow
         {
+            let (first_step, _) = source.first_step();
             let mut flows = self.flows.write().await;
             flows.insert(
-                flow_id.clone(),
+                flow_id.to_string(),
                 ActiveFlow {
                     handler: handler.to_string(),
                     flow_instance,
-                    current_step: result.step_id.clone().unwrap_or_else(|| "user".to_string()),
+                    current_step: result
+                        .step_id
+                        .clone()
+                        .unwrap_or_else(|| first_step.to_string()),
+                    context: source.context(),
                 },
             );
         }
@@ -781,13 +896,17 @@ impl ConfigFlowHandler for ConfigFlowManager {
         Ok(result)
     }
 
-    async fn progress_flow(
+    /// Run the current step of a tracked flow with the user's input
+    ///
+    /// Finished flows are removed from `flows`, others move to their next step.
+    async fn progress_tracked_flow(
         &self,
+        flows: &RwLock<HashMap<String, ActiveFlow>>,
         flow_id: &str,
         user_input: Option<serde_json::Value>,
     ) -> Result<FlowResult, String> {
         let (handler, flow_instance, current_step) = {
-            let flows = self.flows.read().await;
+            let flows = flows.read().await;
             let flow = flows
                 .get(flow_id)
                 .ok_or_else(|| format!("Flow {} not found", flow_id))?;
@@ -804,13 +923,14 @@ impl ConfigFlowHandler for ConfigFlowManager {
         );
 
         let hass = self.create_hass()?;
+        let flow_ref = Python::with_gil(|py| flow_instance.clone_ref(py));
         let async_bridge = self.async_bridge.clone();
         let flow_id_owned = flow_id.to_string();
         let handler_owned = handler.clone();
 
         // Run the Python code on a blocking thread to avoid blocking Tokio workers
         // This is critical for long-running async operations like network I/O (e.g. async_pair())
-        let result = tokio::task::spawn_blocking(move || {
+        let mut result = tokio::task::spawn_blocking(move || {
             run_flow_step_blocking(
                 &async_bridge,
                 &flow_instance,
@@ -824,16 +944,28 @@ impl ConfigFlowHandler for ConfigFlowManager {
         .await
         .map_err(|e| format!("Task panicked: {}", e))??;
 
+        // The entry's unique ID lives on the flow (set via async_set_unique_id)
+        if result.result_type == "create_entry" {
+            result.unique_id = Python::with_gil(|py| {
+                flow_ref
+                    .bind(py)
+                    .getattr("unique_id")
+                    .ok()
+                    .and_then(|u| u.extract::<Option<String>>().ok())
+                    .flatten()
+            });
+        }
+
         // Update flow state or clean up if done
         if result.result_type == "create_entry" || result.result_type == "abort" {
-            let mut flows = self.flows.write().await;
+            let mut flows = flows.write().await;
             flows.remove(flow_id);
             info!(
                 "Flow {} completed with result type: {}",
                 flow_id, result.result_type
             );
         } else if let Some(step_id) = &result.step_id {
-            let mut flows = self.flows.write().await;
+            let mut flows = flows.write().await;
             if let Some(flow) = flows.get_mut(flow_id) {
                 flow.current_step = step_id.clone();
             }
@@ -841,6 +973,34 @@ impl ConfigFlowHandler for ConfigFlowManager {
 
         Ok(result)
     }
+}
+
+#[async_trait]
+impl ConfigFlowHandler for ConfigFlowManager {
+    async fn start_flow(
+        &self,
+        handler: &str,
+        show_advanced_options: bool,
+    ) -> Result<FlowResult, String> {
+        let flow_id = Ulid::new().to_string().to_lowercase();
+        info!(
+            "Starting config flow for {} with flow_id {}",
+            handler, flow_id
+        );
+
+        let _ = show_advanced_options;
+        self.start_flow_with_source(handler, &flow_id, FlowSource::User)
+            .await
+    }
+
+    async fn progress_flow(
+        &self,
+        flow_id: &str,
+        user_input: Option<serde_json::Value>,
+    ) -> Result<FlowResult, String> {
+        self.progress_tracked_flow(&self.flows, flow_id, user_input)
+            .await
+    }
 
     async fn list_flows(&self) -> Vec<serde_json::Value> {
         let flows = self.flows.read().await;
@@ -851,13 +1011,65 @@ impl ConfigFlowHandler for ConfigFlowManager {
                     "flow_id": flow_id,
                     "handler": flow.handler,
                     "step_id": flow.current_step,
-                    "context": {
-                        "source": "user"
-                    }
+                    "context": flow.context,
                 })
             })
             .collect()
     }
+
+    async fn start_reauth_flow(
+        &self,
+        flow_id: &str,
+        entry: &ConfigEntry,
+    ) -> Result<FlowResult, String> {
+        info!(
+            "Starting reauth flow for {} ({}) with flow_id {}",
+            entry.domain, entry.entry_id, flow_id
+        );
+        self.start_flow_with_source(&entry.domain, flow_id, FlowSource::Reauth(entry))
+            .await
+    }
+}
+
+#[async_trait]
+impl OptionsFlowHandler for ConfigFlowManager {
+    async fn start_flow(
+        &self,
+        entry: &ConfigEntry,
+        show_advanced_options: bool,
+    ) -> Result<FlowResult, String> {
+        let _ = show_advanced_options;
+        let flow_id = Ulid::new().to_string().to_lowercase();
+        info!(
+            "Starting options flow for {} ({}) with flow_id {}",
+            entry.domain, entry.entry_id, flow_id
+        );
+
+        let hass = self.create_hass()?;
+        let (flow_instance, result) =
+            Python::with_gil(|py| self.create_options_flow_instance(py, entry, &flow_id, &hass))?;
+
+        self.options_flows.write().await.insert(
+            flow_id,
+            ActiveFlow {
+                handler: entry.entry_id.clone(),
+                flow_instance,
+                current_step: result.step_id.clone().unwrap_or_else(|| "init".to_string()),
+                context: serde_json::json!({}),
+            },
+        );
+
+        Ok(result)
+    }
+
+    async fn progress_flow(
+        &self,
+        flow_id: &str,
+        user_input: Option<serde_json::Value>,
+    ) -> Result<FlowResult, String> {
+        self.progress_tracked_flow(&self.options_flows, flow_id, user_input)
+            .await
+    }
 }
 
 /// Convert JSON value to Python object
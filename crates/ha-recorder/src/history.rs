@@ -0,0 +1,143 @@
+//! In-memory state history
+//!
+//! Keeps every recorded state per entity for a retention window and answers
+//! period queries the way HA's history component does: each entity's state
+//! at the start of the period followed by the changes inside it.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use ha_core::events::{StateChangedData, STATE_CHANGED};
+use ha_core::State;
+use ha_event_bus::{EventBus, ListenerId};
+use tracing::warn;
+
+/// Days of history kept by default, matching HA's `purge_keep_days`
+pub const DEFAULT_RETENTION_DAYS: i64 = 10;
+
+/// Domains whose attribute-only changes are still significant
+const SIGNIFICANT_DOMAINS: &[&str] = &[
+    "climate",
+    "device_tracker",
+    "humidifier",
+    "thermostat",
+    "water_heater",
+];
+
+/// Whether a state belongs in history with `significant_changes_only`
+///
+/// Like HA, attribute-only updates (where `last_changed` stayed behind) are
+/// insignificant, except in domains where attributes carry the reading.
+pub fn is_significant(state: &State) -> bool {
+    state.last_changed == state.last_updated
+        || SIGNIFICANT_DOMAINS.contains(&state.entity_id.domain())
+}
+
+/// In-memory history of entity states
+///
+/// States are stored per entity in the order they were recorded. States older
+/// than the retention window are pruned as new ones arrive, except for the
+/// newest pruned state, which is still needed as the entity's state at the
+/// start of later periods.
+pub struct StateHistory {
+    /// Recorded states keyed by entity_id, oldest first
+    states: DashMap<String, Vec<State>>,
+    /// How long states are kept
+    retention: Duration,
+}
+
+impl StateHistory {
+    /// Create an empty history with the default retention
+    pub fn new() -> Self {
+        Self::with_retention(Duration::days(DEFAULT_RETENTION_DAYS))
+    }
+
+    /// Create an empty history that keeps states for `retention`
+    pub fn with_retention(retention: Duration) -> Self {
+        Self {
+            states: DashMap::new(),
+            retention,
+        }
+    }
+
+    /// Record every STATE_CHANGED event fired on the bus
+    pub fn attach(self: &Arc<Self>, event_bus: &EventBus) -> ListenerId {
+        let history = Arc::clone(self);
+        event_bus.listen_sync(
+            STATE_CHANGED,
+            Arc::new(move |event| {
+                match serde_json::from_value::<StateChangedData>(event.data.clone()) {
+                    Ok(StateChangedData {
+                        new_state: Some(state),
+                        ..
+                    }) => history.record(state),
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to record state change: {}", e),
+                }
+            }),
+        )
+    }
+
+    /// Record a new state for its entity
+    pub fn record(&self, state: State) {
+        let cutoff = state.last_updated - self.retention;
+        let mut entry = self.states.entry(state.entity_id.to_string()).or_default();
+
+        // Keep the newest expired state as the start state for later queries
+        let expired = entry.iter().filter(|s| s.last_updated < cutoff).count();
+        if expired > 1 {
+            entry.drain(..expired - 1);
+        }
+        entry.push(state);
+    }
+
+    /// States in `[start, end]`, grouped per entity and sorted by entity_id
+    ///
+    /// Each group starts with the entity's state at `start` (with its
+    /// timestamps moved to `start`, like HA) when it was recorded earlier,
+    /// followed by every state recorded inside the period. Entities without
+    /// any state in the period are left out. `entity_ids` restricts the
+    /// result to the given entities.
+    pub fn period(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        entity_ids: Option<&[String]>,
+    ) -> Vec<Vec<State>> {
+        let mut keys: Vec<String> = match entity_ids {
+            Some(ids) => ids.to_vec(),
+            None => self.states.iter().map(|e| e.key().clone()).collect(),
+        };
+        keys.sort();
+        keys.dedup();
+
+        keys.iter()
+            .filter_map(|entity_id| {
+                let entry = self.states.get(entity_id)?;
+                let mut group = Vec::new();
+
+                if let Some(initial) = entry.iter().rev().find(|s| s.last_updated < start) {
+                    let mut initial = initial.clone();
+                    initial.last_changed = start;
+                    initial.last_updated = start;
+                    group.push(initial);
+                }
+                group.extend(
+                    entry
+                        .iter()
+                        .filter(|s| s.last_updated >= start && s.last_updated <= end)
+                        .cloned(),
+                );
+
+                (!group.is_empty()).then_some(group)
+            })
+            .collect()
+    }
+}
+
+impl Default for StateHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
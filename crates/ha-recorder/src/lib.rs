@@ -1 +1,34 @@
-//! ha-recorder - TODO: implement
+//! State history recording for Home Assistant
+//!
+//! This crate records entity state changes so they can be queried later,
+//! e.g. by the `/api/history/period` endpoint.
+//!
+//! - `history` - In-memory [`StateHistory`] fed by STATE_CHANGED events
+//! - `sqlite` - Durable [`Recorder`] writing states and events to SQLite
+//!   (`sqlite` feature, on by default)
+//! - `statistics` - Hourly mean/min/max/sum rollups of numeric sensors,
+//!   compiled by the [`Recorder`]
+//! - `logbook` - Human-readable [`LogbookEntry`] lines read from the
+//!   [`Recorder`]
+
+mod history;
+#[cfg(feature = "sqlite")]
+mod logbook;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+mod statistics;
+
+pub use history::{is_significant, StateHistory, DEFAULT_RETENTION_DAYS};
+#[cfg(feature = "sqlite")]
+pub use logbook::{state_message, LogbookEntry};
+#[cfg(feature = "sqlite")]
+pub use sqlite::{
+    Recorder, RecorderConfig, RecorderError, RecorderResult, DEFAULT_COMMIT_INTERVAL,
+    DEFAULT_DB_FILE, MAX_PURGE_KEEP_DAYS,
+};
+#[cfg(feature = "sqlite")]
+pub use statistics::{
+    compile_measurement, compile_sum, StatisticsPeriod, StatisticsRow, ATTR_LAST_RESET,
+    ATTR_STATE_CLASS,
+};
@@ -0,0 +1,594 @@
+//! Logbook
+//!
+//! Human-readable entries built from what the recorder stored, like HA's
+//! `logbook` integration: state changes become "turned on" or "changed to
+//! heat", service calls and automation runs get a line of their own. Each
+//! entry is attributed to whatever started its context, following parent
+//! contexts, so a light turned on by an automation says which one.
+
+use chrono::{DateTime, Utc};
+use ha_core::events::{AUTOMATION_TRIGGERED, CALL_SERVICE};
+use ha_core::{State, STATE_UNAVAILABLE, STATE_UNKNOWN};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::sqlite::{from_timestamp, state_from_row, to_timestamp, STATE_COLUMNS};
+use crate::{Recorder, RecorderResult};
+
+/// How many parent contexts are followed to find who started a change
+const MAX_CONTEXT_DEPTH: usize = 8;
+
+/// How many contexts are looked up per query
+const CONTEXT_BATCH: usize = 500;
+
+/// Attributes of sensors whose every change isn't worth a logbook line
+const CONTINUOUS_ATTRIBUTES: &[&str] = &["unit_of_measurement", "state_class"];
+
+/// One line of the logbook
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogbookEntry {
+    pub when: DateTime<Utc>,
+    pub name: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_user_id: Option<String>,
+    /// Type of the event that started the context
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_event_type: Option<String>,
+    /// Service called in the context
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_domain: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_service: Option<String>,
+    /// Automation that started the context
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_entity_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_message: Option<String>,
+}
+
+impl LogbookEntry {
+    fn new(when: DateTime<Utc>, name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            when,
+            name: name.into(),
+            message: message.into(),
+            entity_id: None,
+            domain: None,
+            state: None,
+            context_id: None,
+            context_user_id: None,
+            context_event_type: None,
+            context_domain: None,
+            context_service: None,
+            context_entity_id: None,
+            context_name: None,
+            context_message: None,
+        }
+    }
+
+    /// Entry for a state change
+    pub fn from_state(state: &State) -> Self {
+        let mut entry = Self::new(state.last_changed, entity_name(state), state_message(state));
+        entry.entity_id = Some(state.entity_id.to_string());
+        entry.domain = Some(state.entity_id.domain().to_string());
+        entry.state = Some(state.state.clone());
+        entry.context_id = Some(state.context.id.clone());
+        entry.context_user_id = state.context.user_id.clone();
+        entry
+    }
+
+    /// Entry for a `call_service` or `automation_triggered` event
+    ///
+    /// Returns `None` for other event types.
+    pub fn from_event(event_type: &str, data: &Value, when: DateTime<Utc>) -> Option<Self> {
+        let entry = match event_type {
+            CALL_SERVICE => {
+                let domain = data.get("domain")?.as_str()?;
+                let service = data.get("service")?.as_str()?;
+                let mut entry =
+                    Self::new(when, format!("{}.{}", domain, service), "service called");
+                entry.domain = Some(domain.to_string());
+                if let [entity_id] = service_targets(data).as_slice() {
+                    entry.entity_id = Some(entity_id.clone());
+                }
+                entry
+            }
+            AUTOMATION_TRIGGERED => {
+                let message = match data.get("source").and_then(Value::as_str) {
+                    Some(source) => format!("triggered by {}", source),
+                    None => "triggered".to_string(),
+                };
+                let name = data.get("name").and_then(Value::as_str).unwrap_or("");
+                let mut entry = Self::new(when, name, message);
+                entry.entity_id = data
+                    .get("entity_id")
+                    .and_then(Value::as_str)
+                    .map(String::from);
+                entry.domain = Some("automation".to_string());
+                entry
+            }
+            _ => return None,
+        };
+        Some(entry)
+    }
+}
+
+/// What happened to an entity, e.g. "turned on" or "changed to 21"
+pub fn state_message(state: &State) -> String {
+    match state.state.as_str() {
+        "on" => "turned on".to_string(),
+        "off" => "turned off".to_string(),
+        STATE_UNAVAILABLE => "became unavailable".to_string(),
+        STATE_UNKNOWN => "became unknown".to_string(),
+        value => format!("changed to {}", value),
+    }
+}
+
+/// Friendly name of an entity, or its object_id with spaces
+fn entity_name(state: &State) -> String {
+    state
+        .attributes
+        .get("friendly_name")
+        .and_then(Value::as_str)
+        .map(String::from)
+        .unwrap_or_else(|| state.entity_id.object_id().replace('_', " "))
+}
+
+/// Entity IDs a `call_service` event targets
+fn service_targets(data: &Value) -> Vec<String> {
+    match data.pointer("/service_data/entity_id") {
+        Some(Value::String(ids)) => ids.split(',').map(|id| id.trim().to_string()).collect(),
+        Some(Value::Array(ids)) => ids
+            .iter()
+            .filter_map(Value::as_str)
+            .map(String::from)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether a state is a numeric reading that changes all the time
+fn is_continuous(state: &State) -> bool {
+    CONTINUOUS_ATTRIBUTES
+        .iter()
+        .any(|attribute| state.attributes.contains_key(*attribute))
+}
+
+/// `?n, ?n+1, …` for `count` parameters starting at `?first`
+fn placeholders(first: usize, count: usize) -> String {
+    (first..first + count)
+        .map(|n| format!("?{}", n))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The first logbook event fired in a context
+struct Origin {
+    event_id: i64,
+    event_type: String,
+    data: Value,
+    when: DateTime<Utc>,
+}
+
+/// How the contexts of a set of entries started, and their parents
+///
+/// Loaded a level of the context chains at a time, so attributing entries
+/// takes a few queries however many entries there are.
+#[derive(Default)]
+struct Contexts {
+    /// First `call_service` or `automation_triggered` event per context
+    origins: HashMap<String, Origin>,
+    /// Parent of each loaded context, `None` for roots and unknown contexts
+    parents: HashMap<String, Option<String>>,
+}
+
+impl Recorder {
+    /// Logbook entries in `[start, end]`, oldest first
+    ///
+    /// With `entity_ids`, only entries about those entities are returned,
+    /// including service calls targeting them.
+    pub fn logbook(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        entity_ids: Option<&[String]>,
+    ) -> RecorderResult<Vec<LogbookEntry>> {
+        let db = self.read_db.lock().unwrap();
+        let wanted =
+            |entity_id: &str| entity_ids.map_or(true, |ids| ids.iter().any(|id| id == entity_id));
+
+        // Entries with their own event and parent context, attributed once
+        // all of them are read
+        let mut entries = Vec::new();
+
+        // Only rows where the state itself changed, not just attributes
+        let mut sql = format!(
+            "SELECT {} FROM states WHERE last_updated_ts >= ?1 AND last_updated_ts <= ?2 \
+             AND last_changed_ts = last_updated_ts",
+            STATE_COLUMNS
+        );
+        let mut values = vec![
+            SqlValue::Real(to_timestamp(start)),
+            SqlValue::Real(to_timestamp(end)),
+        ];
+        if let Some(ids) = entity_ids {
+            sql.push_str(&format!(
+                " AND entity_id IN ({})",
+                placeholders(3, ids.len())
+            ));
+            values.extend(ids.iter().cloned().map(SqlValue::Text));
+        }
+        sql.push_str(" ORDER BY last_updated_ts, state_id");
+        let mut query = db.prepare(&sql)?;
+        for state in query.query_map(params_from_iter(values), state_from_row)? {
+            let state = state?;
+            if !is_continuous(&state) {
+                entries.push((
+                    LogbookEntry::from_state(&state),
+                    None,
+                    state.context.parent_id.clone(),
+                ));
+            }
+        }
+
+        let mut query = db.prepare_cached(
+            "SELECT event_id, event_type, event_data, time_fired_ts, \
+             context_id, context_user_id, context_parent_id FROM events \
+             WHERE time_fired_ts >= ?1 AND time_fired_ts <= ?2 AND event_type IN (?3, ?4) \
+             ORDER BY time_fired_ts, event_id",
+        )?;
+        let rows = query.query_map(
+            params![
+                to_timestamp(start),
+                to_timestamp(end),
+                CALL_SERVICE,
+                AUTOMATION_TRIGGERED
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, f64>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                ))
+            },
+        )?;
+        for row in rows {
+            let (event_id, event_type, data, when, context_id, user_id, parent_id) = row?;
+            let data: Value = serde_json::from_str(&data).unwrap_or(Value::Null);
+            let Some(mut entry) =
+                LogbookEntry::from_event(&event_type, &data, from_timestamp(when))
+            else {
+                continue;
+            };
+            if entity_ids.is_some() {
+                let targets = match event_type.as_str() {
+                    CALL_SERVICE => service_targets(&data),
+                    _ => entry.entity_id.clone().into_iter().collect(),
+                };
+                if !targets.iter().any(|id| wanted(id)) {
+                    continue;
+                }
+            }
+            entry.context_id = context_id;
+            entry.context_user_id = user_id;
+            entries.push((entry, Some(event_id), parent_id));
+        }
+
+        let roots = entries
+            .iter()
+            .flat_map(|(entry, _, parent_id)| [entry.context_id.clone(), parent_id.clone()])
+            .flatten();
+        let contexts = Contexts::load(&db, roots)?;
+        let mut entries: Vec<LogbookEntry> = entries
+            .into_iter()
+            .map(|(mut entry, event_id, parent_id)| {
+                contexts.attribute(&mut entry, event_id, parent_id.as_deref());
+                entry
+            })
+            .collect();
+
+        entries.sort_by_key(|entry| entry.when);
+        Ok(entries)
+    }
+}
+
+impl Contexts {
+    /// Load `roots` and their ancestors, up to `MAX_CONTEXT_DEPTH` deep
+    fn load(db: &Connection, roots: impl IntoIterator<Item = String>) -> RecorderResult<Self> {
+        let mut contexts = Self::default();
+        let mut pending: Vec<String> = roots.into_iter().collect();
+        for _ in 0..=MAX_CONTEXT_DEPTH {
+            pending.sort_unstable();
+            pending.dedup();
+            pending.retain(|id| !contexts.parents.contains_key(id));
+            if pending.is_empty() {
+                break;
+            }
+            for batch in pending.chunks(CONTEXT_BATCH) {
+                contexts.load_batch(db, batch)?;
+            }
+            pending = pending
+                .iter()
+                .filter_map(|id| contexts.parents.get(id).cloned().flatten())
+                .collect();
+        }
+        Ok(contexts)
+    }
+
+    fn load_batch(&mut self, db: &Connection, ids: &[String]) -> RecorderResult<()> {
+        let mut query = db.prepare(&format!(
+            "SELECT event_id, event_type, event_data, time_fired_ts, \
+             context_id, context_parent_id FROM events WHERE context_id IN ({}) \
+             ORDER BY time_fired_ts, event_id",
+            placeholders(1, ids.len())
+        ))?;
+        let mut rows = query.query(params_from_iter(ids))?;
+        while let Some(row) = rows.next()? {
+            let context_id: String = row.get(4)?;
+            self.parents
+                .entry(context_id.clone())
+                .or_insert(row.get(5)?);
+            let event_type: String = row.get(1)?;
+            if self.origins.contains_key(&context_id)
+                || !matches!(event_type.as_str(), CALL_SERVICE | AUTOMATION_TRIGGERED)
+            {
+                continue;
+            }
+            let data: String = row.get(2)?;
+            self.origins.insert(
+                context_id,
+                Origin {
+                    event_id: row.get(0)?,
+                    event_type,
+                    data: serde_json::from_str(&data).unwrap_or(Value::Null),
+                    when: from_timestamp(row.get(3)?),
+                },
+            );
+        }
+        // Contexts without events are known not to lead anywhere
+        for id in ids {
+            self.parents.entry(id.clone()).or_insert(None);
+        }
+        Ok(())
+    }
+
+    /// Fill in the `context_*` fields of an entry from its context chain
+    ///
+    /// The first logbook event of the entry's context tells how it started:
+    /// a service call gives the domain and service, an automation run its
+    /// name. A service call, or a context without events, is followed to
+    /// its parent to find the automation behind it. `event_id` is the
+    /// entry's own event, which doesn't attribute itself.
+    fn attribute(&self, entry: &mut LogbookEntry, event_id: Option<i64>, parent_id: Option<&str>) {
+        let Some(context_id) = entry.context_id.as_deref() else {
+            return;
+        };
+        let mut origin = self
+            .origins
+            .get(context_id)
+            .filter(|o| Some(o.event_id) != event_id);
+
+        if let Some(call) = origin.filter(|o| o.event_type == CALL_SERVICE) {
+            entry.context_event_type = Some(CALL_SERVICE.to_string());
+            entry.context_domain = json_str(&call.data, "domain");
+            entry.context_service = json_str(&call.data, "service");
+            origin = None;
+        }
+
+        let mut parent_id = parent_id;
+        for _ in 0..MAX_CONTEXT_DEPTH {
+            if origin.is_some() {
+                break;
+            }
+            let Some(parent) = parent_id.take() else {
+                break;
+            };
+            origin = self
+                .origins
+                .get(parent)
+                .filter(|o| o.event_type != CALL_SERVICE);
+            parent_id = self.parents.get(parent).and_then(Option::as_deref);
+        }
+
+        if let Some(origin) = origin {
+            let named = LogbookEntry::from_event(&origin.event_type, &origin.data, origin.when);
+            entry.context_event_type = Some(origin.event_type.clone());
+            if let Some(named) = named {
+                entry.context_entity_id = named.entity_id;
+                entry.context_name = Some(named.name);
+                entry.context_message = Some(named.message);
+            }
+        }
+    }
+}
+
+fn json_str(data: &Value, key: &str) -> Option<String> {
+    data.get(key).and_then(Value::as_str).map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RecorderConfig;
+    use chrono::Duration;
+    use ha_core::events::STATE_CHANGED;
+    use ha_core::{Context, Event};
+    use ha_event_bus::EventBus;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn state(entity_id: &str, value: &str, attributes: Value, context: &Context) -> State {
+        let attributes: HashMap<String, Value> = serde_json::from_value(attributes).unwrap();
+        State::new(
+            entity_id.parse().unwrap(),
+            value,
+            attributes,
+            context.clone(),
+        )
+    }
+
+    fn set(bus: &EventBus, state: &State) {
+        bus.fire(Event::new(
+            STATE_CHANGED,
+            json!({"entity_id": state.entity_id, "old_state": null, "new_state": state}),
+            state.context.clone(),
+        ));
+    }
+
+    #[test]
+    fn test_state_messages() {
+        let context = Context::new();
+        let message = |value| state_message(&state("light.porch", value, json!({}), &context));
+        assert_eq!(message("on"), "turned on");
+        assert_eq!(message("off"), "turned off");
+        assert_eq!(message("unavailable"), "became unavailable");
+        assert_eq!(message("heat"), "changed to heat");
+
+        let entry = LogbookEntry::from_state(&state("light.porch_lamp", "on", json!({}), &context));
+        assert_eq!(entry.name, "porch lamp");
+        assert_eq!(entry.domain.as_deref(), Some("light"));
+    }
+
+    #[test]
+    fn test_light_turned_on_by_automation_service_call() {
+        let bus = EventBus::new();
+        let recorder = Recorder::open_in_memory(RecorderConfig {
+            auto_purge: false,
+            ..Default::default()
+        })
+        .unwrap();
+        recorder.attach(&bus);
+        let start = Utc::now() - Duration::seconds(1);
+
+        // The motion sensor triggers the automation, whose run is a child
+        // of the sensor's context; the service call happens in the run
+        let motion = Context::new();
+        set(
+            &bus,
+            &state("binary_sensor.motion", "on", json!({}), &motion),
+        );
+        let run = Context::child_of(&motion);
+        bus.fire(Event::new(
+            AUTOMATION_TRIGGERED,
+            json!({
+                "name": "Porch lights",
+                "entity_id": "automation.porch_lights",
+                "source": "state of binary_sensor.motion",
+            }),
+            run.clone(),
+        ));
+        bus.fire(Event::new(
+            CALL_SERVICE,
+            json!({
+                "domain": "light",
+                "service": "turn_on",
+                "service_data": {"entity_id": "light.porch"},
+            }),
+            run.clone(),
+        ));
+        set(
+            &bus,
+            &state(
+                "light.porch",
+                "on",
+                json!({"friendly_name": "Porch light"}),
+                &run,
+            ),
+        );
+        set(
+            &bus,
+            &state(
+                "sensor.power",
+                "12",
+                json!({"unit_of_measurement": "W"}),
+                &run,
+            ),
+        );
+        recorder.flush();
+
+        let entries = recorder
+            .logbook(start, Utc::now(), Some(&["light.porch".to_string()]))
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let call = &entries[0];
+        assert_eq!(call.name, "light.turn_on");
+        assert_eq!(call.entity_id.as_deref(), Some("light.porch"));
+        assert_eq!(call.context_name.as_deref(), Some("Porch lights"));
+
+        let light = &entries[1];
+        assert_eq!(light.name, "Porch light");
+        assert_eq!(light.message, "turned on");
+        assert_eq!(light.entity_id.as_deref(), Some("light.porch"));
+        assert_eq!(light.context_id.as_deref(), Some(run.id.as_str()));
+        assert_eq!(
+            light.context_event_type.as_deref(),
+            Some(AUTOMATION_TRIGGERED)
+        );
+        assert_eq!(light.context_name.as_deref(), Some("Porch lights"));
+        assert_eq!(
+            light.context_entity_id.as_deref(),
+            Some("automation.porch_lights")
+        );
+        assert_eq!(
+            light.context_message.as_deref(),
+            Some("triggered by state of binary_sensor.motion")
+        );
+
+        // Unfiltered: the sensor and the automation get lines too, the
+        // power reading doesn't
+        let entries = recorder.logbook(start, Utc::now(), None).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["motion", "Porch lights", "light.turn_on", "Porch light"]
+        );
+        assert_eq!(entries[1].context_name, None);
+    }
+
+    #[test]
+    fn test_manual_service_call_attribution() {
+        let bus = EventBus::new();
+        let recorder = Recorder::open_in_memory(RecorderConfig::default()).unwrap();
+        recorder.attach(&bus);
+        let start = Utc::now() - Duration::seconds(1);
+
+        let user = Context::with_user("user-1");
+        bus.fire(Event::new(
+            CALL_SERVICE,
+            json!({"domain": "switch", "service": "turn_off", "service_data": {}}),
+            user.clone(),
+        ));
+        set(&bus, &state("switch.fan", "off", json!({}), &user));
+        recorder.flush();
+
+        let entries = recorder
+            .logbook(start, Utc::now(), Some(&["switch.fan".to_string()]))
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].message, "turned off");
+        assert_eq!(entries[0].context_user_id.as_deref(), Some("user-1"));
+        assert_eq!(entries[0].context_event_type.as_deref(), Some(CALL_SERVICE));
+        assert_eq!(entries[0].context_domain.as_deref(), Some("switch"));
+        assert_eq!(entries[0].context_service.as_deref(), Some("turn_off"));
+        assert_eq!(entries[0].context_name, None);
+    }
+}
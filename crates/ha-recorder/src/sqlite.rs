@@ -0,0 +1,757 @@
+//! SQLite recorder
+//!
+//! Durable counterpart of [`StateHistory`](crate::StateHistory): states and
+//! events are written to a SQLite database, like HA's `recorder`
+//! integration. The event bus listener only queues the raw events it
+//! receives; a background thread parses and writes them in batches, one
+//! transaction per commit interval, so a slow disk never holds up `fire()`.
+//! Queries use their own connection and don't wait for writes. Rows older
+//! than `purge_keep_days` are purged once a day.
+
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration as StdDuration, Instant};
+
+use chrono::{DateTime, Duration, Utc};
+use ha_core::events::{StateChangedData, STATE_CHANGED, STATE_REPORTED};
+use ha_core::{Context, EntityId, Event, EventOrigin, EventType, State};
+use ha_event_bus::{EventBus, ListenerId};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Deserializer};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use crate::history::{is_significant, DEFAULT_RETENTION_DAYS};
+use crate::statistics::{self, StatisticsPeriod};
+
+/// Database file created in the config directory
+pub const DEFAULT_DB_FILE: &str = "home-assistant-rs.db";
+
+/// Seconds between commits by default, matching HA's `commit_interval`
+pub const DEFAULT_COMMIT_INTERVAL: u64 = 5;
+
+/// Queued rows written before the commit interval is up
+const MAX_BATCH_SIZE: usize = 1000;
+
+/// Events waiting for the writer before new ones are dropped, like HA's
+/// `MAX_QUEUE_BACKLOG`
+const MAX_QUEUE_BACKLOG: usize = 65_000;
+
+/// Commits of a batch tried before its rows are given up
+const MAX_COMMIT_ATTEMPTS: u32 = 3;
+
+/// How long after the top of the hour the past hour's statistics are compiled
+const COMPILE_DELAY: Duration = Duration::seconds(10);
+
+/// Most days of states and events that can be kept (about a century)
+pub const MAX_PURGE_KEEP_DAYS: i64 = 36_500;
+
+/// Time between automatic purges
+const PURGE_INTERVAL: StdDuration = StdDuration::from_secs(24 * 60 * 60);
+
+/// Events that aren't written to the `events` table
+///
+/// State changes go to the `states` table instead.
+const EXCLUDED_EVENTS: &[&str] = &[STATE_CHANGED, STATE_REPORTED, "time_changed"];
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS states (
+    state_id INTEGER PRIMARY KEY,
+    entity_id TEXT NOT NULL,
+    state TEXT NOT NULL,
+    attributes TEXT NOT NULL,
+    last_changed_ts REAL NOT NULL,
+    last_updated_ts REAL NOT NULL,
+    context_id TEXT,
+    context_user_id TEXT,
+    context_parent_id TEXT
+);
+CREATE INDEX IF NOT EXISTS ix_states_entity_id_last_updated_ts
+    ON states (entity_id, last_updated_ts);
+CREATE INDEX IF NOT EXISTS ix_states_last_updated_ts ON states (last_updated_ts);
+CREATE TABLE IF NOT EXISTS events (
+    event_id INTEGER PRIMARY KEY,
+    event_type TEXT NOT NULL,
+    event_data TEXT NOT NULL,
+    origin TEXT NOT NULL,
+    time_fired_ts REAL NOT NULL,
+    context_id TEXT,
+    context_user_id TEXT,
+    context_parent_id TEXT
+);
+CREATE INDEX IF NOT EXISTS ix_events_time_fired_ts ON events (time_fired_ts);
+CREATE INDEX IF NOT EXISTS ix_events_context_id ON events (context_id);
+";
+
+pub(crate) const STATE_COLUMNS: &str =
+    "entity_id, state, attributes, last_changed_ts, last_updated_ts, \
+     context_id, context_user_id, context_parent_id";
+
+/// Recorder errors
+#[derive(Debug, Error)]
+pub enum RecorderError {
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("Failed to start the recorder thread: {0}")]
+    Thread(#[from] std::io::Error),
+
+    #[error("Invalid purge_keep_days: {0}")]
+    InvalidKeepDays(i64),
+}
+
+/// Result type for recorder operations
+pub type RecorderResult<T> = Result<T, RecorderError>;
+
+/// The `recorder:` section of configuration.yaml
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RecorderConfig {
+    /// Days of states and events to keep
+    #[serde(deserialize_with = "deserialize_keep_days")]
+    pub purge_keep_days: i64,
+    /// Seconds between commits
+    pub commit_interval: u64,
+    /// Whether to purge old rows once a day
+    pub auto_purge: bool,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            purge_keep_days: DEFAULT_RETENTION_DAYS,
+            commit_interval: DEFAULT_COMMIT_INTERVAL,
+            auto_purge: true,
+        }
+    }
+}
+
+/// Accept `purge_keep_days` only within `0..=MAX_PURGE_KEEP_DAYS`
+fn deserialize_keep_days<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+    let days = i64::deserialize(deserializer)?;
+    if !(0..=MAX_PURGE_KEEP_DAYS).contains(&days) {
+        return Err(serde::de::Error::custom(format!(
+            "purge_keep_days must be between 0 and {}, got {}",
+            MAX_PURGE_KEEP_DAYS, days
+        )));
+    }
+    Ok(days)
+}
+
+/// Work for the writer thread
+enum Message {
+    /// An event as fired on the bus, parsed by the writer
+    Event(Box<Event>),
+    /// Commit what's queued, then signal the sender
+    Flush(Sender<()>),
+}
+
+/// A row waiting to be written
+enum PendingRow {
+    State(Box<State>),
+    Event(Box<Event>),
+}
+
+/// SQLite-backed recorder of states and events
+pub struct Recorder {
+    /// Connection of the writer thread, also used for purges
+    pub(crate) db: Arc<Mutex<Connection>>,
+    /// Connection used by queries
+    ///
+    /// File databases get their own connection so queries don't wait for a
+    /// commit; in-memory databases share the writer's.
+    pub(crate) read_db: Arc<Mutex<Connection>>,
+    /// Queue of the writer thread
+    queue: SyncSender<Message>,
+}
+
+impl Recorder {
+    /// Open (or create) the database at `path` and start the writer thread
+    pub fn open(path: impl AsRef<Path>, config: RecorderConfig) -> RecorderResult<Self> {
+        let db = Connection::open(path.as_ref())?;
+        db.pragma_update(None, "journal_mode", "WAL")?;
+        db.execute_batch(SCHEMA)?;
+        db.execute_batch(statistics::SCHEMA)?;
+        let read_db = Arc::new(Mutex::new(Connection::open(path)?));
+        Self::start(Arc::new(Mutex::new(db)), read_db, config)
+    }
+
+    /// Record to a database that only lives in memory
+    pub fn open_in_memory(config: RecorderConfig) -> RecorderResult<Self> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(SCHEMA)?;
+        db.execute_batch(statistics::SCHEMA)?;
+        let db = Arc::new(Mutex::new(db));
+        Self::start(db.clone(), db, config)
+    }
+
+    fn start(
+        db: Arc<Mutex<Connection>>,
+        read_db: Arc<Mutex<Connection>>,
+        config: RecorderConfig,
+    ) -> RecorderResult<Self> {
+        let (queue, receiver) = mpsc::sync_channel(MAX_QUEUE_BACKLOG);
+
+        let writer = Writer {
+            db: db.clone(),
+            config,
+            pending: Vec::new(),
+            failed_commits: 0,
+        };
+        thread::Builder::new()
+            .name("recorder".to_string())
+            .spawn(move || writer.run(receiver))?;
+
+        Ok(Self { db, read_db, queue })
+    }
+
+    /// Queue every state change and event fired on the bus for writing
+    ///
+    /// Events are dropped with a warning while the queue is full, so a
+    /// stalled database never blocks or grows the bus without bound.
+    pub fn attach(&self, event_bus: &EventBus) -> ListenerId {
+        let queue = self.queue.clone();
+        event_bus.listen_sync(
+            EventType::match_all(),
+            Arc::new(move |event| {
+                let event_type = event.event_type.as_str();
+                if event_type != STATE_CHANGED && EXCLUDED_EVENTS.contains(&event_type) {
+                    return;
+                }
+                match queue.try_send(Message::Event(Box::new(event.clone()))) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        warn!("Recorder queue is full, dropping {} event", event_type)
+                    }
+                    // Only happens once the writer thread is gone
+                    Err(TrySendError::Disconnected(_)) => {}
+                }
+            }),
+        )
+    }
+
+    /// Block until everything queued so far is written
+    pub fn flush(&self) {
+        let (done, wait) = mpsc::channel();
+        if self.queue.send(Message::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+
+    /// States in `[start, end]`, grouped per entity and sorted by entity_id
+    ///
+    /// Answers like [`StateHistory::period`](crate::StateHistory::period):
+    /// each group starts with the entity's state at `start`, followed by
+    /// the states recorded inside the period. With
+    /// `significant_changes_only`, states that only changed attributes are
+    /// left out, see [`is_significant`].
+    pub fn get_significant_states(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        entity_ids: Option<&[String]>,
+        significant_changes_only: bool,
+    ) -> RecorderResult<Vec<Vec<State>>> {
+        let db = self.read_db.lock().unwrap();
+
+        let mut keys: Vec<String> = match entity_ids {
+            Some(ids) => ids.to_vec(),
+            None => db
+                .prepare("SELECT DISTINCT entity_id FROM states WHERE last_updated_ts <= ?1")?
+                .query_map([to_timestamp(end)], |row| row.get(0))?
+                .collect::<Result<_, _>>()?,
+        };
+        keys.sort();
+        keys.dedup();
+
+        let mut initial = db.prepare_cached(&format!(
+            "SELECT {} FROM states WHERE entity_id = ?1 AND last_updated_ts < ?2 \
+             ORDER BY last_updated_ts DESC, state_id DESC LIMIT 1",
+            STATE_COLUMNS
+        ))?;
+        let mut period = db.prepare_cached(&format!(
+            "SELECT {} FROM states WHERE entity_id = ?1 \
+             AND last_updated_ts >= ?2 AND last_updated_ts <= ?3 \
+             ORDER BY last_updated_ts, state_id",
+            STATE_COLUMNS
+        ))?;
+
+        let mut groups = Vec::new();
+        for entity_id in &keys {
+            let mut group = Vec::new();
+
+            let mut rows = initial.query(params![entity_id, to_timestamp(start)])?;
+            if let Some(row) = rows.next()? {
+                let mut state = state_from_row(row)?;
+                state.last_changed = start;
+                state.last_updated = start;
+                group.push(state);
+            }
+
+            let mut rows =
+                period.query(params![entity_id, to_timestamp(start), to_timestamp(end)])?;
+            while let Some(row) = rows.next()? {
+                let state = state_from_row(row)?;
+                if !significant_changes_only || group.is_empty() || is_significant(&state) {
+                    group.push(state);
+                }
+            }
+
+            if !group.is_empty() {
+                groups.push(group);
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Delete states and events older than `keep_days`
+    ///
+    /// Returns the number of deleted rows.
+    pub fn purge(&self, keep_days: i64) -> RecorderResult<usize> {
+        purge(&self.db.lock().unwrap(), keep_days)
+    }
+}
+
+/// Writes queued rows in batches on its own thread
+struct Writer {
+    db: Arc<Mutex<Connection>>,
+    config: RecorderConfig,
+    pending: Vec<PendingRow>,
+    /// Failed attempts to commit `pending`
+    failed_commits: u32,
+}
+
+impl Writer {
+    /// Write until every sender of the queue is dropped
+    fn run(mut self, queue: Receiver<Message>) {
+        let commit_interval = StdDuration::from_secs(self.config.commit_interval);
+        let mut commit_at: Option<Instant> = None;
+        let mut purge_at = Instant::now();
+        let mut compile_at = Utc::now();
+
+        loop {
+            if self.config.auto_purge && Instant::now() >= purge_at {
+                self.purge();
+                purge_at = Instant::now() + PURGE_INTERVAL;
+            }
+            if Utc::now() >= compile_at {
+                self.commit();
+                commit_at = None;
+                let hour = StatisticsPeriod::Hour.start_of(Utc::now());
+                self.compile_statistics(hour - Duration::hours(1));
+                compile_at = hour + Duration::hours(1) + COMPILE_DELAY;
+            }
+
+            let mut timeout = (compile_at - Utc::now()).to_std().unwrap_or_default();
+            if let Some(commit_at) = commit_at {
+                timeout = timeout.min(commit_at.saturating_duration_since(Instant::now()));
+            }
+            if self.config.auto_purge {
+                timeout = timeout.min(purge_at.saturating_duration_since(Instant::now()));
+            }
+            match queue.recv_timeout(timeout) {
+                Ok(Message::Flush(done)) => {
+                    self.commit();
+                    commit_at = None;
+                    let _ = done.send(());
+                }
+                Ok(Message::Event(event)) => {
+                    let Some(row) = row_for(*event) else {
+                        continue;
+                    };
+                    self.pending.push(row);
+                    if self.pending.len() >= MAX_BATCH_SIZE {
+                        self.commit();
+                        commit_at = None;
+                    } else if commit_at.is_none() {
+                        commit_at = Some(Instant::now() + commit_interval);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    if commit_at.is_some_and(|at| Instant::now() >= at) {
+                        self.commit();
+                        commit_at = None;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.commit();
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Write the pending rows in one transaction
+    ///
+    /// A failed batch is kept and tried again with the next commit, until
+    /// it has failed `MAX_COMMIT_ATTEMPTS` times.
+    fn commit(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let mut db = self.db.lock().unwrap();
+        let result = (|| -> rusqlite::Result<()> {
+            let tx = db.transaction()?;
+            for row in &self.pending {
+                match row {
+                    PendingRow::State(state) => insert_state(&tx, state)?,
+                    PendingRow::Event(event) => insert_event(&tx, event)?,
+                }
+            }
+            tx.commit()
+        })();
+        match result {
+            Ok(()) => {
+                debug!("Recorded {} rows", self.pending.len());
+                self.pending.clear();
+                self.failed_commits = 0;
+            }
+            Err(e) => {
+                self.failed_commits += 1;
+                if self.failed_commits >= MAX_COMMIT_ATTEMPTS {
+                    warn!(
+                        "Failed to record {} rows, giving up after {} attempts: {}",
+                        self.pending.len(),
+                        self.failed_commits,
+                        e
+                    );
+                    self.pending.clear();
+                    self.failed_commits = 0;
+                } else {
+                    warn!(
+                        "Failed to record {} rows, will retry: {}",
+                        self.pending.len(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    fn compile_statistics(&self, start: DateTime<Utc>) {
+        match statistics::compile_statistics(&self.db.lock().unwrap(), start) {
+            Ok(compiled) => debug!("Compiled statistics of {} entities", compiled),
+            Err(e) => warn!("Failed to compile statistics: {}", e),
+        }
+    }
+
+    fn purge(&self) {
+        match purge(&self.db.lock().unwrap(), self.config.purge_keep_days) {
+            Ok(deleted) => debug!("Purged {} recorder rows", deleted),
+            Err(e) => warn!("Failed to purge recorder database: {}", e),
+        }
+    }
+}
+
+/// The row to write for an event from the bus, if any
+fn row_for(event: Event) -> Option<PendingRow> {
+    if event.event_type.as_str() != STATE_CHANGED {
+        return Some(PendingRow::Event(Box::new(event)));
+    }
+    match serde_json::from_value::<StateChangedData>(event.data) {
+        Ok(StateChangedData {
+            new_state: Some(state),
+            ..
+        }) => Some(PendingRow::State(Box::new(state))),
+        Ok(_) => None,
+        Err(e) => {
+            warn!("Failed to record state change: {}", e);
+            None
+        }
+    }
+}
+
+fn purge(db: &Connection, keep_days: i64) -> RecorderResult<usize> {
+    let cutoff = Some(keep_days)
+        .filter(|days| *days >= 0)
+        .and_then(Duration::try_days)
+        .and_then(|keep| Utc::now().checked_sub_signed(keep))
+        .ok_or(RecorderError::InvalidKeepDays(keep_days))?;
+    let cutoff = to_timestamp(cutoff);
+    let states = db.execute("DELETE FROM states WHERE last_updated_ts < ?1", [cutoff])?;
+    let events = db.execute("DELETE FROM events WHERE time_fired_ts < ?1", [cutoff])?;
+    Ok(states + events)
+}
+
+fn insert_state(db: &Connection, state: &State) -> rusqlite::Result<()> {
+    db.prepare_cached(&format!(
+        "INSERT INTO states ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        STATE_COLUMNS
+    ))?
+    .execute(params![
+        state.entity_id.as_str(),
+        state.state,
+        serde_json::to_string(&state.attributes).unwrap_or_else(|_| "{}".to_string()),
+        to_timestamp(state.last_changed),
+        to_timestamp(state.last_updated),
+        state.context.id,
+        state.context.user_id,
+        state.context.parent_id,
+    ])?;
+    Ok(())
+}
+
+fn insert_event(db: &Connection, event: &Event) -> rusqlite::Result<()> {
+    let origin = match event.origin {
+        EventOrigin::Local => "LOCAL",
+        EventOrigin::Remote => "REMOTE",
+    };
+    db.prepare_cached(
+        "INSERT INTO events (event_type, event_data, origin, time_fired_ts, \
+         context_id, context_user_id, context_parent_id) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?
+    .execute(params![
+        event.event_type.as_str(),
+        event.data.to_string(),
+        origin,
+        to_timestamp(event.time_fired),
+        event.context.id,
+        event.context.user_id,
+        event.context.parent_id,
+    ])?;
+    Ok(())
+}
+
+/// Read a row selected with [`STATE_COLUMNS`]
+pub(crate) fn state_from_row(row: &Row<'_>) -> rusqlite::Result<State> {
+    let entity_id: String = row.get(0)?;
+    // Rows may predate the stricter entity ID rules
+    let entity_id = EntityId::parse_lenient(&entity_id)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))?;
+    let attributes: String = row.get(2)?;
+    let attributes = serde_json::from_str(&attributes)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, Type::Text, Box::new(e)))?;
+
+    let mut context = Context::with_id(row.get::<_, String>(5)?);
+    context.user_id = row.get(6)?;
+    context.parent_id = row.get(7)?;
+
+    Ok(State {
+        entity_id,
+        state: row.get(1)?,
+        attributes,
+        last_changed: from_timestamp(row.get(3)?),
+        last_updated: from_timestamp(row.get(4)?),
+        last_reported: None,
+        context,
+    })
+}
+
+/// Seconds since the epoch, as HA stores them
+pub(crate) fn to_timestamp(time: DateTime<Utc>) -> f64 {
+    time.timestamp_micros() as f64 / 1_000_000.0
+}
+
+pub(crate) fn from_timestamp(timestamp: f64) -> DateTime<Utc> {
+    DateTime::from_timestamp_micros((timestamp * 1_000_000.0).round() as i64).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn set(bus: &EventBus, entity_id: &str, value: &str, at: DateTime<Utc>) -> State {
+        let mut state = State::new(
+            entity_id.parse().unwrap(),
+            value,
+            HashMap::from([("unit_of_measurement".to_string(), json!("°C"))]),
+            Context::new(),
+        );
+        state.last_changed = at;
+        state.last_updated = at;
+        bus.fire(Event::new(
+            STATE_CHANGED,
+            json!({
+                "entity_id": entity_id,
+                "old_state": null,
+                "new_state": state,
+            }),
+            Context::new(),
+        ));
+        state
+    }
+
+    #[test]
+    fn test_query_recorded_states_by_period() {
+        let bus = EventBus::new();
+        let recorder = Recorder::open_in_memory(RecorderConfig::default()).unwrap();
+        recorder.attach(&bus);
+
+        let now = Utc::now();
+        set(&bus, "sensor.temperature", "19", now - Duration::hours(3));
+        set(&bus, "sensor.temperature", "20", now - Duration::hours(2));
+        set(
+            &bus,
+            "sensor.temperature",
+            "21",
+            now - Duration::minutes(30),
+        );
+        set(&bus, "sensor.humidity", "40", now - Duration::minutes(20));
+        set(&bus, "sensor.outside", "5", now + Duration::hours(1));
+        recorder.flush();
+
+        let start = now - Duration::hours(1);
+        let groups = recorder
+            .get_significant_states(start, now, None, true)
+            .unwrap();
+        assert_eq!(groups.len(), 2);
+
+        let humidity = &groups[0];
+        assert_eq!(humidity.len(), 1);
+        assert_eq!(humidity[0].state, "40");
+
+        // The state at the start of the period comes first
+        let temperature = &groups[1];
+        let values: Vec<&str> = temperature.iter().map(|s| s.state.as_str()).collect();
+        assert_eq!(values, ["20", "21"]);
+        assert_eq!(temperature[0].last_updated, start);
+        assert_eq!(temperature[1].attributes["unit_of_measurement"], "°C");
+
+        let filtered = recorder
+            .get_significant_states(
+                now - Duration::hours(4),
+                now,
+                Some(&["sensor.temperature".to_string()]),
+                true,
+            )
+            .unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].len(), 3);
+    }
+
+    #[test]
+    fn test_attribute_only_changes_are_not_significant() {
+        let bus = EventBus::new();
+        let recorder = Recorder::open_in_memory(RecorderConfig::default()).unwrap();
+        recorder.attach(&bus);
+
+        let now = Utc::now();
+        set(&bus, "light.kitchen", "on", now - Duration::minutes(10));
+        let mut update = set(&bus, "light.kitchen", "on", now - Duration::minutes(5));
+        update.last_changed = now - Duration::minutes(10);
+        bus.fire(Event::new(
+            STATE_CHANGED,
+            json!({"entity_id": "light.kitchen", "old_state": null, "new_state": update}),
+            Context::new(),
+        ));
+        recorder.flush();
+
+        let start = now - Duration::hours(1);
+        let all = recorder
+            .get_significant_states(start, now, None, false)
+            .unwrap();
+        let significant = recorder
+            .get_significant_states(start, now, None, true)
+            .unwrap();
+        assert_eq!(all[0].len(), 3);
+        assert_eq!(significant[0].len(), 2);
+    }
+
+    #[test]
+    fn test_purge_old_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = RecorderConfig {
+            auto_purge: false,
+            ..Default::default()
+        };
+        let recorder = Recorder::open(dir.path().join(DEFAULT_DB_FILE), config).unwrap();
+        let bus = EventBus::new();
+        recorder.attach(&bus);
+
+        let now = Utc::now();
+        set(&bus, "sensor.temperature", "1", now - Duration::days(20));
+        set(&bus, "sensor.temperature", "2", now - Duration::hours(1));
+        bus.fire(Event::new("custom_event", json!({}), Context::new()));
+        recorder.flush();
+
+        assert_eq!(recorder.purge(10).unwrap(), 1);
+        let groups = recorder
+            .get_significant_states(now - Duration::days(30), now, None, true)
+            .unwrap();
+        assert_eq!(groups[0].len(), 1);
+        assert_eq!(groups[0][0].state, "2");
+    }
+
+    #[test]
+    fn test_purge_rejects_out_of_range_keep_days() {
+        let recorder = Recorder::open_in_memory(RecorderConfig {
+            auto_purge: false,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(matches!(
+            recorder.purge(i64::MAX),
+            Err(RecorderError::InvalidKeepDays(i64::MAX))
+        ));
+        assert!(matches!(
+            recorder.purge(-1),
+            Err(RecorderError::InvalidKeepDays(-1))
+        ));
+
+        let parse = |days: i64| {
+            serde_json::from_value::<RecorderConfig>(json!({ "purge_keep_days": days }))
+                .map(|config| config.purge_keep_days)
+        };
+        assert_eq!(parse(30).unwrap(), 30);
+        assert!(parse(-1).is_err());
+        assert!(parse(MAX_PURGE_KEEP_DAYS + 1).is_err());
+        assert!(parse(99_999_999_999_999).is_err());
+    }
+
+    #[test]
+    fn test_failed_batch_is_retried() {
+        let bus = EventBus::new();
+        let recorder = Recorder::open_in_memory(RecorderConfig::default()).unwrap();
+        recorder.attach(&bus);
+        let execute = |sql: &str| recorder.db.lock().unwrap().execute_batch(sql).unwrap();
+        let count = || -> i64 {
+            recorder
+                .read_db
+                .lock()
+                .unwrap()
+                .query_row("SELECT COUNT(*) FROM events", [], |row| row.get(0))
+                .unwrap()
+        };
+
+        // Writes fail while the table is missing
+        execute("ALTER TABLE events RENAME TO events_away");
+        bus.fire(Event::new("custom_event", json!({}), Context::new()));
+        recorder.flush();
+        execute("ALTER TABLE events_away RENAME TO events");
+        assert_eq!(count(), 0);
+
+        // The kept batch is written with the next commit
+        recorder.flush();
+        assert_eq!(count(), 1);
+    }
+
+    #[test]
+    fn test_rows_with_legacy_entity_ids_load() {
+        let recorder = Recorder::open_in_memory(RecorderConfig::default()).unwrap();
+        let now = Utc::now();
+        recorder
+            .db
+            .lock()
+            .unwrap()
+            .execute(
+                &format!(
+                    "INSERT INTO states ({}) VALUES ('sensor._legacy', '7', '{{}}', ?1, ?1, 'ctx', NULL, NULL)",
+                    STATE_COLUMNS
+                ),
+                [to_timestamp(now - Duration::minutes(5))],
+            )
+            .unwrap();
+
+        let groups = recorder
+            .get_significant_states(now - Duration::hours(1), now, None, true)
+            .unwrap();
+        assert_eq!(groups[0][0].entity_id.as_str(), "sensor._legacy");
+        assert_eq!(groups[0][0].state, "7");
+    }
+}
@@ -0,0 +1,605 @@
+//! Long-term statistics
+//!
+//! Hourly rollups of numeric sensors, like HA's recorder statistics. Sensors
+//! with a `state_class` and a `unit_of_measurement` are compiled once an
+//! hour by the recorder thread: `measurement` sensors get a time-weighted
+//! mean plus their min and max, `total` and `total_increasing` sensors get
+//! their last state and a running sum that carries over meter resets.
+//!
+//! Statistics aren't purged; they're what's left of history once the states
+//! themselves are gone.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Duration, DurationRound, Months, TimeZone, Timelike, Utc};
+use ha_core::State;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::sqlite::{from_timestamp, state_from_row, to_timestamp, STATE_COLUMNS};
+use crate::{Recorder, RecorderResult};
+
+pub(crate) const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS statistics_meta (
+    statistic_id TEXT PRIMARY KEY,
+    unit_of_measurement TEXT,
+    has_mean INTEGER NOT NULL,
+    has_sum INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS statistics (
+    id INTEGER PRIMARY KEY,
+    statistic_id TEXT NOT NULL,
+    start_ts REAL NOT NULL,
+    mean REAL,
+    min REAL,
+    max REAL,
+    last_reset_ts REAL,
+    state REAL,
+    sum REAL,
+    UNIQUE (statistic_id, start_ts)
+);
+";
+
+/// Attribute holding how a sensor's value evolves
+pub const ATTR_STATE_CLASS: &str = "state_class";
+
+/// Attribute holding a `total` sensor's last reset
+pub const ATTR_LAST_RESET: &str = "last_reset";
+
+const ATTR_UNIT_OF_MEASUREMENT: &str = "unit_of_measurement";
+
+/// Length of the periods statistics are returned for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatisticsPeriod {
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+impl StatisticsPeriod {
+    /// Start of the period `time` falls in (UTC, weeks start on Monday)
+    pub fn start_of(self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let hour = time.duration_trunc(Duration::hours(1)).unwrap_or(time);
+        let day = hour - Duration::hours(i64::from(hour.hour()));
+        match self {
+            Self::Hour => hour,
+            Self::Day => day,
+            Self::Week => day - Duration::days(i64::from(day.weekday().num_days_from_monday())),
+            Self::Month => Utc
+                .with_ymd_and_hms(day.year(), day.month(), 1, 0, 0, 0)
+                .single()
+                .unwrap_or(day),
+        }
+    }
+
+    /// End of the period starting at `start`
+    pub fn end_of(self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Self::Hour => start + Duration::hours(1),
+            Self::Day => start + Duration::days(1),
+            Self::Week => start + Duration::weeks(1),
+            Self::Month => start
+                .checked_add_months(Months::new(1))
+                .unwrap_or(start + Duration::days(31)),
+        }
+    }
+}
+
+/// Statistics of one entity over one period
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatisticsRow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub mean: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub last_reset: Option<DateTime<Utc>>,
+    pub state: Option<f64>,
+    pub sum: Option<f64>,
+}
+
+/// Time-weighted mean, min and max of `values` over `[start, end)`
+///
+/// `values` are `(time, value)` pairs, oldest first. A value from before
+/// `start` holds from `start` on, each value lasts until the next one.
+pub fn compile_measurement(
+    values: &[(DateTime<Utc>, f64)],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Option<(f64, f64, f64)> {
+    let durations: Vec<(f64, f64)> = values
+        .iter()
+        .enumerate()
+        .map(|(i, (time, value))| {
+            let from = (*time).max(start);
+            let until = values.get(i + 1).map_or(end, |(next, _)| (*next).min(end));
+            (
+                *value,
+                (until - from).num_milliseconds().max(0) as f64 / 1000.0,
+            )
+        })
+        .collect();
+
+    // Values replaced right away don't count, unless nothing lasted
+    let mut held: Vec<(f64, f64)> = durations
+        .iter()
+        .copied()
+        .filter(|(_, d)| *d > 0.0)
+        .collect();
+    if held.is_empty() {
+        held = durations.iter().map(|(v, _)| (*v, 1.0)).collect();
+    }
+
+    let seconds: f64 = held.iter().map(|(_, d)| d).sum();
+    let (first, _) = held.first()?;
+    let mean = held.iter().map(|(v, d)| v * d).sum::<f64>() / seconds;
+    let min = held.iter().map(|(v, _)| *v).fold(*first, f64::min);
+    let max = held.iter().map(|(v, _)| *v).fold(*first, f64::max);
+    Some((mean, min, max))
+}
+
+/// Last state and running sum of a `total` or `total_increasing` sensor
+///
+/// `values` are `(value, last_reset)` pairs, oldest first. `previous` is
+/// the `(state, sum, last_reset)` of the last compiled period; without it
+/// the first value is the baseline the sum starts from. A sensor was reset
+/// when a `total_increasing` one goes down, e.g. a meter that started over
+/// from zero, or a `total` one reports a new `last_reset`: its new value is
+/// then added to the sum as is, counting from zero.
+pub fn compile_sum(
+    values: &[(f64, Option<DateTime<Utc>>)],
+    previous: Option<(f64, f64, Option<DateTime<Utc>>)>,
+    total_increasing: bool,
+) -> Option<(f64, f64)> {
+    let (mut last, mut sum, mut last_reset) = match previous {
+        Some(previous) => previous,
+        None => {
+            let (first, first_reset) = values.first()?;
+            (*first, 0.0, *first_reset)
+        }
+    };
+    for (value, reset) in values {
+        let was_reset = if total_increasing {
+            *value < last
+        } else {
+            reset.is_some() && *reset != last_reset
+        };
+        if was_reset {
+            sum += value;
+        } else {
+            sum += value - last;
+        }
+        last = *value;
+        last_reset = *reset;
+    }
+    Some((last, sum))
+}
+
+/// The `last_reset` attribute of a `total` sensor
+fn last_reset(state: &State) -> Option<DateTime<Utc>> {
+    state
+        .attributes
+        .get(ATTR_LAST_RESET)
+        .and_then(|v| v.as_str())
+        .and_then(|v| DateTime::parse_from_rfc3339(v).ok())
+        .map(|v| v.with_timezone(&Utc))
+}
+
+impl Recorder {
+    /// Compile the statistics of the hour starting at `start`
+    ///
+    /// Runs hourly on the recorder thread; compiling an hour again replaces
+    /// its rows. Returns the number of compiled entities.
+    pub fn compile_statistics(&self, start: DateTime<Utc>) -> RecorderResult<usize> {
+        compile_statistics(&self.db.lock().unwrap(), start)
+    }
+
+    /// Statistics overlapping `[start, end)`, per statistic_id
+    ///
+    /// Hourly rows are combined into longer periods: means are averaged,
+    /// and the last state and sum of each period are kept.
+    pub fn statistics_during_period(
+        &self,
+        start: DateTime<Utc>,
+        end: Option<DateTime<Utc>>,
+        statistic_ids: Option<&[String]>,
+        period: StatisticsPeriod,
+    ) -> RecorderResult<BTreeMap<String, Vec<StatisticsRow>>> {
+        let db = self.read_db.lock().unwrap();
+        let start = period.start_of(start);
+        let end = end.map_or(f64::MAX, to_timestamp);
+
+        let mut query = db.prepare_cached(
+            "SELECT statistic_id, start_ts, mean, min, max, last_reset_ts, state, sum \
+             FROM statistics WHERE start_ts >= ?1 AND start_ts < ?2 \
+             ORDER BY statistic_id, start_ts",
+        )?;
+        let rows = query.query_map(params![to_timestamp(start), end], |row| {
+            let start = from_timestamp(row.get(1)?);
+            Ok((
+                row.get::<_, String>(0)?,
+                StatisticsRow {
+                    start,
+                    end: start + Duration::hours(1),
+                    mean: row.get(2)?,
+                    min: row.get(3)?,
+                    max: row.get(4)?,
+                    last_reset: row.get::<_, Option<f64>>(5)?.map(from_timestamp),
+                    state: row.get(6)?,
+                    sum: row.get(7)?,
+                },
+            ))
+        })?;
+
+        // Hourly rows of each statistic, grouped by period
+        let mut hours: BTreeMap<String, Vec<Vec<StatisticsRow>>> = BTreeMap::new();
+        for row in rows {
+            let (statistic_id, hour) = row?;
+            if statistic_ids.is_some_and(|ids| !ids.contains(&statistic_id)) {
+                continue;
+            }
+            let periods = hours.entry(statistic_id).or_default();
+            match periods.last_mut() {
+                Some(group) if period.start_of(group[0].start) == period.start_of(hour.start) => {
+                    group.push(hour)
+                }
+                _ => periods.push(vec![hour]),
+            }
+        }
+
+        Ok(hours
+            .into_iter()
+            .map(|(statistic_id, periods)| {
+                let rows = periods.iter().map(|group| combine(group, period)).collect();
+                (statistic_id, rows)
+            })
+            .collect())
+    }
+}
+
+/// Combine the hourly rows of one period
+fn combine(hours: &[StatisticsRow], period: StatisticsPeriod) -> StatisticsRow {
+    let start = period.start_of(hours[0].start);
+    let means: Vec<f64> = hours.iter().filter_map(|h| h.mean).collect();
+    let last = &hours[hours.len() - 1];
+    StatisticsRow {
+        start,
+        end: period.end_of(start),
+        mean: (!means.is_empty()).then(|| means.iter().sum::<f64>() / means.len() as f64),
+        min: hours.iter().filter_map(|h| h.min).reduce(f64::min),
+        max: hours.iter().filter_map(|h| h.max).reduce(f64::max),
+        last_reset: last.last_reset,
+        state: last.state,
+        sum: last.sum,
+    }
+}
+
+pub(crate) fn compile_statistics(db: &Connection, start: DateTime<Utc>) -> RecorderResult<usize> {
+    let start = StatisticsPeriod::Hour.start_of(start);
+    let end = start + Duration::hours(1);
+
+    let entity_ids: Vec<String> = db
+        .prepare_cached(
+            "SELECT DISTINCT entity_id FROM states \
+             WHERE entity_id LIKE 'sensor.%' AND last_updated_ts < ?1",
+        )?
+        .query_map([to_timestamp(end)], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+
+    let mut compiled = 0;
+    for entity_id in entity_ids {
+        let states = hour_states(db, &entity_id, start, end)?;
+        let Some(latest) = states.last() else {
+            continue;
+        };
+        let (Some(state_class), Some(unit)) = (
+            latest
+                .attributes
+                .get(ATTR_STATE_CLASS)
+                .and_then(|v| v.as_str()),
+            latest
+                .attributes
+                .get(ATTR_UNIT_OF_MEASUREMENT)
+                .and_then(|v| v.as_str()),
+        ) else {
+            continue;
+        };
+
+        let numeric: Vec<(&State, f64)> = states
+            .iter()
+            .filter_map(|s| Some((s, s.state.parse::<f64>().ok()?)))
+            .filter(|(_, v)| v.is_finite())
+            .collect();
+
+        let mut row = StatisticsRow {
+            start,
+            end,
+            mean: None,
+            min: None,
+            max: None,
+            last_reset: None,
+            state: None,
+            sum: None,
+        };
+        match state_class {
+            "measurement" => {
+                let values: Vec<(DateTime<Utc>, f64)> =
+                    numeric.iter().map(|(s, v)| (s.last_updated, *v)).collect();
+                let Some((mean, min, max)) = compile_measurement(&values, start, end) else {
+                    continue;
+                };
+                (row.mean, row.min, row.max) = (Some(mean), Some(min), Some(max));
+            }
+            "total" | "total_increasing" => {
+                let previous = db
+                    .prepare_cached(
+                        "SELECT state, sum, last_reset_ts FROM statistics \
+                         WHERE statistic_id = ?1 AND start_ts < ?2 \
+                         ORDER BY start_ts DESC LIMIT 1",
+                    )?
+                    .query_row(params![entity_id, to_timestamp(start)], |row| {
+                        Ok((
+                            row.get::<_, f64>(0)?,
+                            row.get::<_, f64>(1)?,
+                            row.get::<_, Option<f64>>(2)?.map(from_timestamp),
+                        ))
+                    })
+                    .optional()?;
+                let values: Vec<(f64, Option<DateTime<Utc>>)> =
+                    numeric.iter().map(|(s, v)| (*v, last_reset(s))).collect();
+                let total_increasing = state_class == "total_increasing";
+                let Some((state, sum)) = compile_sum(&values, previous, total_increasing) else {
+                    continue;
+                };
+                (row.state, row.sum) = (Some(state), Some(sum));
+                row.last_reset = last_reset(latest);
+            }
+            _ => continue,
+        }
+
+        db.prepare_cached(
+            "INSERT OR REPLACE INTO statistics_meta \
+             (statistic_id, unit_of_measurement, has_mean, has_sum) VALUES (?1, ?2, ?3, ?4)",
+        )?
+        .execute(params![
+            entity_id,
+            unit,
+            row.mean.is_some(),
+            row.sum.is_some()
+        ])?;
+        db.prepare_cached(
+            "INSERT OR REPLACE INTO statistics \
+             (statistic_id, start_ts, mean, min, max, last_reset_ts, state, sum) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?
+        .execute(params![
+            entity_id,
+            to_timestamp(start),
+            row.mean,
+            row.min,
+            row.max,
+            row.last_reset.map(to_timestamp),
+            row.state,
+            row.sum,
+        ])?;
+        compiled += 1;
+    }
+    Ok(compiled)
+}
+
+/// The state at `start` followed by the states recorded before `end`
+fn hour_states(
+    db: &Connection,
+    entity_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> RecorderResult<Vec<State>> {
+    let mut states = Vec::new();
+    let initial = db
+        .prepare_cached(&format!(
+            "SELECT {} FROM states WHERE entity_id = ?1 AND last_updated_ts < ?2 \
+             ORDER BY last_updated_ts DESC, state_id DESC LIMIT 1",
+            STATE_COLUMNS
+        ))?
+        .query_row(params![entity_id, to_timestamp(start)], state_from_row)
+        .optional()?;
+    states.extend(initial);
+
+    let mut query = db.prepare_cached(&format!(
+        "SELECT {} FROM states WHERE entity_id = ?1 \
+         AND last_updated_ts >= ?2 AND last_updated_ts < ?3 \
+         ORDER BY last_updated_ts, state_id",
+        STATE_COLUMNS
+    ))?;
+    for state in query.query_map(
+        params![entity_id, to_timestamp(start), to_timestamp(end)],
+        state_from_row,
+    )? {
+        states.push(state?);
+    }
+    Ok(states)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RecorderConfig;
+    use ha_core::events::STATE_CHANGED;
+    use ha_core::{Context, Event};
+    use ha_event_bus::EventBus;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn hour() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap()
+    }
+
+    fn set(bus: &EventBus, entity_id: &str, value: &str, at: DateTime<Utc>, class: &str) {
+        let mut state = State::new(
+            entity_id.parse().unwrap(),
+            value,
+            HashMap::from([
+                (ATTR_STATE_CLASS.to_string(), json!(class)),
+                (ATTR_UNIT_OF_MEASUREMENT.to_string(), json!("kWh")),
+            ]),
+            Context::new(),
+        );
+        state.last_changed = at;
+        state.last_updated = at;
+        bus.fire(Event::new(
+            STATE_CHANGED,
+            json!({"entity_id": entity_id, "old_state": null, "new_state": state}),
+            Context::new(),
+        ));
+    }
+
+    #[test]
+    fn test_compile_measurement_is_time_weighted() {
+        let start = hour();
+        let values = [
+            (start - Duration::minutes(5), 10.0),
+            (start + Duration::minutes(15), 20.0),
+            (start + Duration::minutes(45), 40.0),
+        ];
+        let (mean, min, max) =
+            compile_measurement(&values, start, start + Duration::hours(1)).unwrap();
+        // 15 minutes at 10, 30 at 20, 15 at 40
+        assert_eq!(mean, 22.5);
+        assert_eq!(min, 10.0);
+        assert_eq!(max, 40.0);
+        assert_eq!(compile_measurement(&[], start, start), None);
+    }
+
+    #[test]
+    fn test_compile_sum_over_reset() {
+        let values = |values: &[f64]| -> Vec<(f64, Option<DateTime<Utc>>)> {
+            values.iter().map(|v| (*v, None)).collect()
+        };
+        assert_eq!(
+            compile_sum(&values(&[100.0, 105.0, 110.0]), None, true),
+            Some((110.0, 10.0))
+        );
+        // The meter restarts from zero and counts up to 3
+        assert_eq!(
+            compile_sum(&values(&[112.0, 1.0, 3.0]), Some((110.0, 10.0, None)), true),
+            Some((3.0, 15.0))
+        );
+        // A plain total may go down
+        assert_eq!(
+            compile_sum(&values(&[8.0]), Some((10.0, 4.0, None)), false),
+            Some((8.0, 2.0))
+        );
+    }
+
+    #[test]
+    fn test_compile_sum_over_last_reset() {
+        let first = Some(hour() - Duration::days(1));
+        let second = Some(hour());
+        // A new last_reset starts a new cycle from zero, even going up
+        assert_eq!(
+            compile_sum(
+                &[(12.0, first), (20.0, second), (25.0, second)],
+                Some((10.0, 4.0, first)),
+                false
+            ),
+            Some((25.0, 31.0))
+        );
+        // Without a last_reset the value is a plain difference
+        assert_eq!(
+            compile_sum(&[(20.0, None)], Some((10.0, 4.0, first)), false),
+            Some((20.0, 14.0))
+        );
+    }
+
+    #[test]
+    fn test_hourly_statistics_from_recorded_states() {
+        let bus = EventBus::new();
+        let config = RecorderConfig {
+            auto_purge: false,
+            ..Default::default()
+        };
+        let recorder = Recorder::open_in_memory(config).unwrap();
+        recorder.attach(&bus);
+
+        let start = hour();
+        for (minute, value) in [(0, "4"), (30, "8"), (45, "2")] {
+            set(
+                &bus,
+                "sensor.power",
+                value,
+                start + Duration::minutes(minute),
+                "measurement",
+            );
+        }
+        set(
+            &bus,
+            "sensor.power",
+            "100",
+            start + Duration::hours(1),
+            "measurement",
+        );
+        for (minute, value) in [(0, "50"), (20, "55"), (40, "1")] {
+            set(
+                &bus,
+                "sensor.energy",
+                value,
+                start + Duration::minutes(minute),
+                "total_increasing",
+            );
+        }
+        set(&bus, "sensor.note", "12", start, "");
+        recorder.flush();
+
+        assert_eq!(recorder.compile_statistics(start).unwrap(), 2);
+        assert_eq!(
+            recorder
+                .compile_statistics(start + Duration::hours(1))
+                .unwrap(),
+            2
+        );
+
+        let stats = recorder
+            .statistics_during_period(start, None, None, StatisticsPeriod::Hour)
+            .unwrap();
+        assert_eq!(
+            stats.keys().collect::<Vec<_>>(),
+            ["sensor.energy", "sensor.power"]
+        );
+
+        let power = &stats["sensor.power"];
+        assert_eq!(power.len(), 2);
+        assert_eq!(power[0].start, start);
+        assert_eq!(power[0].end, start + Duration::hours(1));
+        // 30 minutes at 4, 15 at 8, 15 at 2
+        assert_eq!(power[0].mean, Some(4.5));
+        assert_eq!(power[0].min, Some(2.0));
+        assert_eq!(power[0].max, Some(8.0));
+        assert_eq!(power[0].sum, None);
+        // The 2 from before the hour was replaced at its start
+        assert_eq!(power[1].mean, Some(100.0));
+        assert_eq!(power[1].min, Some(100.0));
+
+        // 50 -> 55 adds 5, the reset to 1 adds 1; the next hour carries on
+        let energy = &stats["sensor.energy"];
+        assert_eq!(energy[0].state, Some(1.0));
+        assert_eq!(energy[0].sum, Some(6.0));
+        assert_eq!(energy[1].sum, Some(6.0));
+        assert_eq!(energy[0].mean, None);
+
+        let daily = recorder
+            .statistics_during_period(
+                start,
+                None,
+                Some(&["sensor.power".to_string()]),
+                StatisticsPeriod::Day,
+            )
+            .unwrap();
+        let day = &daily["sensor.power"];
+        assert_eq!(day.len(), 1);
+        assert_eq!(day[0].start, StatisticsPeriod::Day.start_of(start));
+        assert_eq!(day[0].mean, Some(52.25));
+        assert_eq!(day[0].max, Some(100.0));
+    }
+}
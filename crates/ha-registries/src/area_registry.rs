@@ -7,10 +7,13 @@ use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use ha_core::events::AreaRegistryUpdatedData;
+use ha_event_bus::EventBus;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info};
 
-use crate::storage::{Storable, Storage, StorageFile, StorageResult};
+use crate::events::{ChangeEvents, ACTION_CREATE, ACTION_REMOVE, ACTION_UPDATE};
+use crate::storage::{PendingSave, Storable, Storage, StorageFile, StorageResult};
 
 /// Storage key for area registry
 pub const STORAGE_KEY: &str = "core.area_registry";
@@ -140,6 +143,12 @@ pub struct AreaRegistry {
 
     /// Index: label_id -> set of area_ids
     by_label_id: DashMap<String, HashSet<String>>,
+
+    /// Bus that receives an AREA_REGISTRY_UPDATED event for every change
+    events: ChangeEvents,
+
+    /// Set by every change until the next save
+    pending: PendingSave,
 }
 
 impl AreaRegistry {
@@ -151,12 +160,31 @@ impl AreaRegistry {
             by_name: DashMap::new(),
             by_floor_id: DashMap::new(),
             by_label_id: DashMap::new(),
+            events: ChangeEvents::default(),
+            pending: PendingSave::default(),
+        }
+    }
+
+    /// Create an area registry that fires AREA_REGISTRY_UPDATED events
+    pub fn with_event_bus(storage: Arc<Storage>, event_bus: Arc<EventBus>) -> Self {
+        Self {
+            events: ChangeEvents::new(event_bus),
+            ..Self::new(storage)
         }
     }
 
+    /// Schedule a save and announce a change with an AREA_REGISTRY_UPDATED event
+    fn entry_changed(&self, action: &str, area_id: &str) {
+        self.schedule_save();
+        self.events.fire(AreaRegistryUpdatedData {
+            action: action.to_string(),
+            area_id: area_id.to_string(),
+        });
+    }
+
     /// Load from storage
     pub async fn load(&self) -> StorageResult<()> {
-        if let Some(storage_file) = self.storage.load::<AreaRegistryData>(STORAGE_KEY).await? {
+        if let Some(storage_file) = self.storage.load_migrated::<AreaRegistryData>().await? {
             info!(
                 "Loading {} areas from storage (v{}.{})",
                 storage_file.data.areas.len(),
@@ -173,16 +201,33 @@ impl AreaRegistry {
 
     /// Save to storage
     pub async fn save(&self) -> StorageResult<()> {
+        self.storage
+            .save_pending(&self.pending, || self.storage_file())
+            .await?;
+        debug!("Saved {} areas to storage", self.by_id.len());
+        Ok(())
+    }
+
+    /// Mark the registry as having unsaved changes
+    ///
+    /// Nothing is written yet; the next `flush()` saves the registry. The task
+    /// started by `Registries::spawn_save_task` flushes after the save delay.
+    pub fn schedule_save(&self) {
+        self.storage.schedule_save(&self.pending);
+    }
+
+    /// Save to storage if there are unsaved changes
+    pub async fn flush(&self) -> StorageResult<()> {
+        self.storage.flush(&self.pending, || self.save()).await
+    }
+
+    /// Snapshot of the registry as it is written to storage
+    fn storage_file(&self) -> StorageFile<AreaRegistryData> {
         let data = AreaRegistryData {
             areas: self.by_id.iter().map(|r| (**r.value()).clone()).collect(),
         };
 
-        let storage_file =
-            StorageFile::new(STORAGE_KEY, data, STORAGE_VERSION, STORAGE_MINOR_VERSION);
-
-        self.storage.save(&storage_file).await?;
-        debug!("Saved {} areas to storage", self.by_id.len());
-        Ok(())
+        StorageFile::new(STORAGE_KEY, data, STORAGE_VERSION, STORAGE_MINOR_VERSION)
     }
 
     /// Index an entry (takes Arc to avoid cloning)
@@ -281,6 +326,7 @@ impl AreaRegistry {
         let arc_entry = Arc::new(entry);
         info!("Created area: {} ({})", name, arc_entry.id);
         self.index_entry(Arc::clone(&arc_entry));
+        self.entry_changed(ACTION_CREATE, &arc_entry.id);
         Ok(arc_entry)
     }
 
@@ -370,6 +416,10 @@ impl AreaRegistry {
             let new_arc = Arc::new(entry);
             self.index_entry(Arc::clone(&new_arc));
 
+            if changed {
+                self.entry_changed(ACTION_UPDATE, area_id);
+            }
+
             Ok(new_arc)
         } else {
             Err(format!("Area not found: {}", area_id))
@@ -383,6 +433,7 @@ impl AreaRegistry {
         if let Some((_, arc_entry)) = self.by_id.remove(area_id) {
             self.unindex_entry(&arc_entry);
             info!("Removed area: {}", area_id);
+            self.entry_changed(ACTION_REMOVE, area_id);
             Some(arc_entry)
         } else {
             None
@@ -406,6 +457,7 @@ impl AreaRegistry {
                 entry.modified_at = Utc::now();
                 let new_arc = Arc::new(entry);
                 self.by_id.insert(area_id.clone(), new_arc);
+                self.entry_changed(ACTION_UPDATE, area_id);
             }
         }
 
@@ -430,6 +482,7 @@ impl AreaRegistry {
                 entry.modified_at = Utc::now();
                 let new_arc = Arc::new(entry);
                 self.by_id.insert(area_id.clone(), new_arc);
+                self.entry_changed(ACTION_UPDATE, area_id);
             }
         }
 
@@ -9,11 +9,14 @@ use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use ha_core::events::DeviceRegistryUpdatedData;
+use ha_event_bus::EventBus;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info};
 
 use crate::entity_registry::DisabledBy;
-use crate::storage::{Storable, Storage, StorageFile, StorageResult};
+use crate::events::{changed_values, ChangeEvents, ACTION_CREATE, ACTION_REMOVE, ACTION_UPDATE};
+use crate::storage::{PendingSave, Storable, Storage, StorageFile, StorageResult};
 
 /// Storage key for device registry
 pub const STORAGE_KEY: &str = "core.device_registry";
@@ -446,6 +449,12 @@ pub struct DeviceRegistry {
 
     /// Counter for insertion ordering
     insertion_counter: AtomicU64,
+
+    /// Bus that receives a DEVICE_REGISTRY_UPDATED event for every change
+    events: ChangeEvents,
+
+    /// Set by every change until the next save
+    pending: PendingSave,
 }
 
 impl DeviceRegistry {
@@ -461,12 +470,37 @@ impl DeviceRegistry {
             by_via_device_id: DashMap::new(),
             deleted: DashMap::new(),
             insertion_counter: AtomicU64::new(0),
+            events: ChangeEvents::default(),
+            pending: PendingSave::default(),
         }
     }
 
+    /// Create a device registry that fires DEVICE_REGISTRY_UPDATED events
+    pub fn with_event_bus(storage: Arc<Storage>, event_bus: Arc<EventBus>) -> Self {
+        Self {
+            events: ChangeEvents::new(event_bus),
+            ..Self::new(storage)
+        }
+    }
+
+    /// Schedule a save and announce a change with a DEVICE_REGISTRY_UPDATED event
+    fn entry_changed(
+        &self,
+        action: &str,
+        device_id: &str,
+        changes: serde_json::Map<String, serde_json::Value>,
+    ) {
+        self.schedule_save();
+        self.events.fire(DeviceRegistryUpdatedData {
+            action: action.to_string(),
+            device_id: device_id.to_string(),
+            changes,
+        });
+    }
+
     /// Load from storage
     pub async fn load(&self) -> StorageResult<()> {
-        if let Some(storage_file) = self.storage.load::<DeviceRegistryData>(STORAGE_KEY).await? {
+        if let Some(storage_file) = self.storage.load_migrated::<DeviceRegistryData>().await? {
             info!(
                 "Loading {} devices from storage (v{}.{})",
                 storage_file.data.devices.len(),
@@ -491,17 +525,34 @@ impl DeviceRegistry {
 
     /// Save to storage
     pub async fn save(&self) -> StorageResult<()> {
+        self.storage
+            .save_pending(&self.pending, || self.storage_file())
+            .await?;
+        debug!("Saved {} devices to storage", self.by_id.len());
+        Ok(())
+    }
+
+    /// Mark the registry as having unsaved changes
+    ///
+    /// Nothing is written yet; the next `flush()` saves the registry. The task
+    /// started by `Registries::spawn_save_task` flushes after the save delay.
+    pub fn schedule_save(&self) {
+        self.storage.schedule_save(&self.pending);
+    }
+
+    /// Save to storage if there are unsaved changes
+    pub async fn flush(&self) -> StorageResult<()> {
+        self.storage.flush(&self.pending, || self.save()).await
+    }
+
+    /// Snapshot of the registry as it is written to storage
+    fn storage_file(&self) -> StorageFile<DeviceRegistryData> {
         let data = DeviceRegistryData {
             devices: self.by_id.iter().map(|r| (**r.value()).clone()).collect(),
             deleted_devices: self.deleted.iter().map(|r| (**r.value()).clone()).collect(),
         };
 
-        let storage_file =
-            StorageFile::new(STORAGE_KEY, data, STORAGE_VERSION, STORAGE_MINOR_VERSION);
-
-        self.storage.save(&storage_file).await?;
-        debug!("Saved {} devices to storage", self.by_id.len());
-        Ok(())
+        StorageFile::new(STORAGE_KEY, data, STORAGE_VERSION, STORAGE_MINOR_VERSION)
     }
 
     /// Index an entry in all indexes
@@ -790,6 +841,7 @@ impl DeviceRegistry {
         self.index_entry(Arc::clone(&arc_entry));
 
         info!("Registered new device: {:?} ({})", name, arc_entry.id);
+        self.entry_changed(ACTION_CREATE, &arc_entry.id, Default::default());
         arc_entry
     }
 
@@ -939,6 +991,11 @@ impl DeviceRegistry {
             let new_arc = Arc::new(entry);
             self.index_entry(Arc::clone(&new_arc));
 
+            let changes = changed_values(&*arc_entry, &*new_arc);
+            if !changes.is_empty() {
+                self.entry_changed(ACTION_UPDATE, device_id, changes);
+            }
+
             Some(new_arc)
         } else {
             None
@@ -963,6 +1020,7 @@ impl DeviceRegistry {
             self.deleted
                 .insert(device_id.to_string(), Arc::clone(&arc_entry));
             info!("Removed device: {}", device_id);
+            self.entry_changed(ACTION_REMOVE, device_id, Default::default());
             Some(arc_entry)
         } else {
             None
@@ -1156,6 +1214,7 @@ impl DeviceRegistry {
             let new_arc = Arc::new(entry);
             self.index_entry(Arc::clone(&new_arc));
             info!("Restored deleted device: {}", device_id);
+            self.entry_changed(ACTION_CREATE, device_id, Default::default());
             Some(new_arc)
         } else {
             None
@@ -1220,6 +1279,7 @@ impl DeviceRegistry {
             let new_arc = Arc::new(entry);
             self.index_entry(Arc::clone(&new_arc));
             info!("Restored deleted device as fresh: {}", device_id);
+            self.entry_changed(ACTION_CREATE, device_id, Default::default());
             Some(new_arc)
         } else {
             None
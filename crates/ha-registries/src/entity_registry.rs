@@ -8,12 +8,16 @@ use std::sync::{Arc, RwLock};
 
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use ha_core::events::EntityRegistryUpdatedData;
+use ha_core::EntityId;
+use ha_event_bus::EventBus;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{debug, info};
 
-use crate::storage::{Storable, Storage, StorageFile, StorageResult};
+use crate::events::{changed_values, ChangeEvents, ACTION_CREATE, ACTION_REMOVE, ACTION_UPDATE};
+use crate::storage::{PendingSave, Storable, Storage, StorageFile, StorageResult};
 
 /// Errors that can occur in the entity registry
 #[derive(Debug, Error, Clone)]
@@ -21,6 +25,12 @@ pub enum EntityRegistryError {
     /// Entity was not found
     #[error("Entity not found: {0}")]
     NotFound(String),
+    /// Another entity is already registered under this entity_id
+    #[error("Entity with this ID is already registered: {0}")]
+    AlreadyExists(String),
+    /// The new entity_id is malformed or changes the domain
+    #[error("Invalid entity ID: {0}")]
+    InvalidEntityId(String),
 }
 
 /// Storage key for entity registry
@@ -417,6 +427,12 @@ pub struct EntityRegistry {
     /// Keyed by (domain, platform, unique_id) to match native HA semantics
     /// Uses IndexMap + RwLock to preserve insertion order (important for test compatibility)
     deleted: RwLock<IndexMap<(String, String, String), Arc<EntityEntry>>>,
+
+    /// Bus that receives an ENTITY_REGISTRY_UPDATED event for every change
+    events: ChangeEvents,
+
+    /// Set by every change until the next save
+    pending: PendingSave,
 }
 
 impl EntityRegistry {
@@ -432,12 +448,39 @@ impl EntityRegistry {
             by_label_id: DashMap::new(),
             by_platform: DashMap::new(),
             deleted: RwLock::new(IndexMap::new()),
+            events: ChangeEvents::default(),
+            pending: PendingSave::default(),
+        }
+    }
+
+    /// Create an entity registry that fires ENTITY_REGISTRY_UPDATED events
+    pub fn with_event_bus(storage: Arc<Storage>, event_bus: Arc<EventBus>) -> Self {
+        Self {
+            events: ChangeEvents::new(event_bus),
+            ..Self::new(storage)
         }
     }
 
+    /// Schedule a save and announce a change with an ENTITY_REGISTRY_UPDATED event
+    fn entry_changed(
+        &self,
+        action: &str,
+        entity_id: &str,
+        changes: serde_json::Map<String, serde_json::Value>,
+        old_entity_id: Option<&str>,
+    ) {
+        self.schedule_save();
+        self.events.fire(EntityRegistryUpdatedData {
+            action: action.to_string(),
+            entity_id: entity_id.to_string(),
+            changes,
+            old_entity_id: old_entity_id.map(String::from),
+        });
+    }
+
     /// Load from storage
     pub async fn load(&self) -> StorageResult<()> {
-        if let Some(storage_file) = self.storage.load::<EntityRegistryData>(STORAGE_KEY).await? {
+        if let Some(storage_file) = self.storage.load_migrated::<EntityRegistryData>().await? {
             info!(
                 "Loading {} entities from storage (v{}.{})",
                 storage_file.data.entities.len(),
@@ -466,6 +509,31 @@ impl EntityRegistry {
 
     /// Save to storage
     pub async fn save(&self) -> StorageResult<()> {
+        self.storage
+            .save_pending(&self.pending, || self.storage_file())
+            .await?;
+        debug!(
+            "Saved {} entities to storage",
+            self.by_entity_id.read().map(|e| e.len()).unwrap_or(0)
+        );
+        Ok(())
+    }
+
+    /// Mark the registry as having unsaved changes
+    ///
+    /// Nothing is written yet; the next `flush()` saves the registry. The task
+    /// started by `Registries::spawn_save_task` flushes after the save delay.
+    pub fn schedule_save(&self) {
+        self.storage.schedule_save(&self.pending);
+    }
+
+    /// Save to storage if there are unsaved changes
+    pub async fn flush(&self) -> StorageResult<()> {
+        self.storage.flush(&self.pending, || self.save()).await
+    }
+
+    /// Snapshot of the registry as it is written to storage
+    fn storage_file(&self) -> StorageFile<EntityRegistryData> {
         // IndexMap preserves insertion order, no need to sort
         let deleted_entries: Vec<EntityEntry> = self
             .deleted
@@ -484,15 +552,7 @@ impl EntityRegistry {
             deleted_entities: deleted_entries,
         };
 
-        let storage_file =
-            StorageFile::new(STORAGE_KEY, data, STORAGE_VERSION, STORAGE_MINOR_VERSION);
-
-        self.storage.save(&storage_file).await?;
-        debug!(
-            "Saved {} entities to storage",
-            self.by_entity_id.read().map(|e| e.len()).unwrap_or(0)
-        );
-        Ok(())
+        StorageFile::new(STORAGE_KEY, data, STORAGE_VERSION, STORAGE_MINOR_VERSION)
     }
 
     /// Index an entry in all indexes
@@ -551,6 +611,14 @@ impl EntityRegistry {
         }
     }
 
+    /// Remove an entity_id from a reverse index bucket, dropping emptied buckets
+    fn remove_from_index(index: &DashMap<String, HashSet<String>>, key: &str, entity_id: &str) {
+        if let Some(mut ids) = index.get_mut(key) {
+            ids.remove(entity_id);
+        }
+        index.remove_if(key, |_, ids| ids.is_empty());
+    }
+
     /// Remove an entry from all indexes
     fn unindex_entry(&self, entry: &EntityEntry) {
         let entity_id = &entry.entity_id;
@@ -563,36 +631,26 @@ impl EntityRegistry {
 
         // Remove from device_id index
         if let Some(ref device_id) = entry.device_id {
-            if let Some(mut ids) = self.by_device_id.get_mut(device_id) {
-                ids.remove(entity_id);
-            }
+            Self::remove_from_index(&self.by_device_id, device_id, entity_id);
         }
 
         // Remove from config_entry_id index
         if let Some(ref config_entry_id) = entry.config_entry_id {
-            if let Some(mut ids) = self.by_config_entry_id.get_mut(config_entry_id) {
-                ids.remove(entity_id);
-            }
+            Self::remove_from_index(&self.by_config_entry_id, config_entry_id, entity_id);
         }
 
         // Remove from area_id index
         if let Some(ref area_id) = entry.area_id {
-            if let Some(mut ids) = self.by_area_id.get_mut(area_id) {
-                ids.remove(entity_id);
-            }
+            Self::remove_from_index(&self.by_area_id, area_id, entity_id);
         }
 
         // Remove from label_id index
         for label_id in &entry.labels {
-            if let Some(mut ids) = self.by_label_id.get_mut(label_id) {
-                ids.remove(entity_id);
-            }
+            Self::remove_from_index(&self.by_label_id, label_id, entity_id);
         }
 
         // Remove from platform index
-        if let Some(mut ids) = self.by_platform.get_mut(&entry.platform) {
-            ids.remove(entity_id);
-        }
+        Self::remove_from_index(&self.by_platform, &entry.platform, entity_id);
 
         // Remove from primary index
         if let Ok(mut idx) = self.by_entity_id.write() {
@@ -642,6 +700,27 @@ impl EntityRegistry {
             .unwrap_or_default()
     }
 
+    /// Get the sorted entity_ids of a device straight from the device index
+    pub fn entities_for_device(&self, device_id: &str) -> Vec<String> {
+        Self::index_members(&self.by_device_id, device_id)
+    }
+
+    /// Get the sorted entity_ids assigned directly to an area from the area index
+    ///
+    /// Entities that are only in the area through their device are not included.
+    pub fn entities_for_area(&self, area_id: &str) -> Vec<String> {
+        Self::index_members(&self.by_area_id, area_id)
+    }
+
+    fn index_members(index: &DashMap<String, HashSet<String>>, key: &str) -> Vec<String> {
+        let mut entity_ids: Vec<String> = index
+            .get(key)
+            .map(|ids| ids.iter().cloned().collect())
+            .unwrap_or_default();
+        entity_ids.sort();
+        entity_ids
+    }
+
     /// Get all entities for a config entry
     pub fn get_by_config_entry_id(&self, config_entry_id: &str) -> Vec<Arc<EntityEntry>> {
         self.by_config_entry_id
@@ -731,6 +810,7 @@ impl EntityRegistry {
                 self.index_entry(Arc::clone(&arc_entry));
 
                 info!("Restored deleted entity: {}", entity_id);
+                self.entry_changed(ACTION_CREATE, entity_id, Default::default(), None);
                 return arc_entry;
             }
         }
@@ -744,6 +824,7 @@ impl EntityRegistry {
         self.index_entry(Arc::clone(&arc_entry));
 
         info!("Registered new entity: {}", entity_id);
+        self.entry_changed(ACTION_CREATE, entity_id, Default::default(), None);
         arc_entry
     }
 
@@ -773,28 +854,22 @@ impl EntityRegistry {
                 self.by_unique_id.remove(&key);
             }
             if let Some(ref device_id) = entry.device_id {
-                if let Some(mut ids) = self.by_device_id.get_mut(device_id) {
-                    ids.remove(&entry.entity_id);
-                }
+                Self::remove_from_index(&self.by_device_id, device_id, &entry.entity_id);
             }
             if let Some(ref config_entry_id) = entry.config_entry_id {
-                if let Some(mut ids) = self.by_config_entry_id.get_mut(config_entry_id) {
-                    ids.remove(&entry.entity_id);
-                }
+                Self::remove_from_index(
+                    &self.by_config_entry_id,
+                    config_entry_id,
+                    &entry.entity_id,
+                );
             }
             if let Some(ref area_id) = entry.area_id {
-                if let Some(mut ids) = self.by_area_id.get_mut(area_id) {
-                    ids.remove(&entry.entity_id);
-                }
+                Self::remove_from_index(&self.by_area_id, area_id, &entry.entity_id);
             }
             for label_id in &entry.labels {
-                if let Some(mut ids) = self.by_label_id.get_mut(label_id) {
-                    ids.remove(&entry.entity_id);
-                }
-            }
-            if let Some(mut ids) = self.by_platform.get_mut(&entry.platform) {
-                ids.remove(&entry.entity_id);
+                Self::remove_from_index(&self.by_label_id, label_id, &entry.entity_id);
             }
+            Self::remove_from_index(&self.by_platform, &entry.platform, &entry.entity_id);
 
             // Apply update
             f(&mut entry);
@@ -804,12 +879,52 @@ impl EntityRegistry {
             let new_arc = Arc::new(entry);
             self.index_entry(Arc::clone(&new_arc));
 
+            let changes = changed_values(&*arc_entry, &*new_arc);
+            if !changes.is_empty() {
+                let old_entity_id = Some(arc_entry.entity_id.as_str())
+                    .filter(|old_id| *old_id != new_arc.entity_id);
+                self.entry_changed(ACTION_UPDATE, &new_arc.entity_id, changes, old_entity_id);
+            }
+
             Ok(new_arc)
         } else {
             Err(EntityRegistryError::NotFound(entity_id.to_string()))
         }
     }
 
+    /// Change the entity_id of an entity, like HA's
+    /// `async_update_entity(new_entity_id=...)`
+    ///
+    /// The new entity_id must be valid, keep the domain and not be taken.
+    /// Only the registry is updated; moving the entity's state is up to the
+    /// caller.
+    pub fn update_entity_id(
+        &self,
+        entity_id: &str,
+        new_entity_id: &str,
+    ) -> Result<Arc<EntityEntry>, EntityRegistryError> {
+        let new_id: EntityId = new_entity_id
+            .parse()
+            .map_err(|_| EntityRegistryError::InvalidEntityId(new_entity_id.to_string()))?;
+        if entity_id.split_once('.').map(|(domain, _)| domain) != Some(new_id.domain()) {
+            return Err(EntityRegistryError::InvalidEntityId(
+                new_entity_id.to_string(),
+            ));
+        }
+        if self.is_registered(new_entity_id) {
+            return Err(EntityRegistryError::AlreadyExists(
+                new_entity_id.to_string(),
+            ));
+        }
+
+        let updated = self.update(entity_id, |entry| {
+            entry.entity_id = new_entity_id.to_string();
+            entry.modified_at = Utc::now();
+        })?;
+        info!("Renamed entity: {} -> {}", entity_id, new_entity_id);
+        Ok(updated)
+    }
+
     /// Remove an entity
     ///
     /// Returns the removed entry as `Arc<EntityEntry>`.
@@ -832,6 +947,7 @@ impl EntityRegistry {
                 deleted.insert(key, Arc::clone(&arc_entry));
             }
             info!("Removed entity: {}", entity_id);
+            self.entry_changed(ACTION_REMOVE, entity_id, Default::default(), None);
             Some(arc_entry)
         } else {
             None
@@ -853,28 +969,22 @@ impl EntityRegistry {
                         self.by_unique_id.remove(&key);
                     }
                     if let Some(ref device_id) = arc_entry.device_id {
-                        if let Some(mut ids) = self.by_device_id.get_mut(device_id) {
-                            ids.remove(entity_id.as_str());
-                        }
+                        Self::remove_from_index(&self.by_device_id, device_id, entity_id);
                     }
                     if let Some(ref config_entry_id) = arc_entry.config_entry_id {
-                        if let Some(mut ids) = self.by_config_entry_id.get_mut(config_entry_id) {
-                            ids.remove(entity_id.as_str());
-                        }
+                        Self::remove_from_index(
+                            &self.by_config_entry_id,
+                            config_entry_id,
+                            entity_id,
+                        );
                     }
                     if let Some(ref area_id) = arc_entry.area_id {
-                        if let Some(mut ids) = self.by_area_id.get_mut(area_id) {
-                            ids.remove(entity_id.as_str());
-                        }
+                        Self::remove_from_index(&self.by_area_id, area_id, entity_id);
                     }
                     for label_id in &arc_entry.labels {
-                        if let Some(mut ids) = self.by_label_id.get_mut(label_id) {
-                            ids.remove(entity_id.as_str());
-                        }
-                    }
-                    if let Some(mut ids) = self.by_platform.get_mut(&arc_entry.platform) {
-                        ids.remove(entity_id.as_str());
+                        Self::remove_from_index(&self.by_label_id, label_id, entity_id);
                     }
+                    Self::remove_from_index(&self.by_platform, &arc_entry.platform, entity_id);
                     // Add to deleted for tracking
                     let key = (
                         arc_entry.domain().to_string(),
@@ -891,6 +1001,9 @@ impl EntityRegistry {
         if !removed.is_empty() {
             info!("Bulk removed {} entities", removed.len());
         }
+        for entity_id in &removed {
+            self.entry_changed(ACTION_REMOVE, entity_id, Default::default(), None);
+        }
         removed
     }
 
@@ -1160,5 +1273,67 @@ fn slugify(name: &str) -> String {
     result.trim_end_matches('_').to_string()
 }
 
-// Unit tests removed - covered by HA native tests via `make ha-compat-test`
-// See tests/ha_compat/ for comprehensive EntityRegistry testing through Python bindings
+// Unit tests for the Rust-side behavior; run `make ha-compat-test` for the
+// comprehensive EntityRegistry tests in tests/ha_compat/ through Python bindings
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn registry() -> (TempDir, EntityRegistry) {
+        let dir = TempDir::new().unwrap();
+        let registry = EntityRegistry::new(Arc::new(Storage::new(dir.path())));
+        (dir, registry)
+    }
+
+    #[test]
+    fn test_device_index_follows_moves() {
+        let (_dir, registry) = registry();
+        registry.get_or_create("demo", "light.one", None, None, Some("device_a"));
+        registry.get_or_create("demo", "light.two", None, None, Some("device_a"));
+
+        assert_eq!(
+            registry.entities_for_device("device_a"),
+            ["light.one", "light.two"]
+        );
+
+        registry
+            .update("light.one", |e| e.device_id = Some("device_b".to_string()))
+            .unwrap();
+        assert_eq!(registry.entities_for_device("device_a"), ["light.two"]);
+        assert_eq!(registry.entities_for_device("device_b"), ["light.one"]);
+
+        registry.remove("light.two");
+        assert!(registry.entities_for_device("device_a").is_empty());
+        assert!(!registry.by_device_id.contains_key("device_a"));
+    }
+
+    #[test]
+    fn test_area_index_follows_moves() {
+        let (_dir, registry) = registry();
+        registry.get_or_create("demo", "sensor.temp", None, None, None);
+        registry
+            .update("sensor.temp", |e| e.area_id = Some("kitchen".to_string()))
+            .unwrap();
+        assert_eq!(registry.entities_for_area("kitchen"), ["sensor.temp"]);
+
+        registry
+            .update("sensor.temp", |e| e.area_id = Some("office".to_string()))
+            .unwrap();
+        assert!(registry.entities_for_area("kitchen").is_empty());
+        assert!(!registry.by_area_id.contains_key("kitchen"));
+        assert_eq!(registry.entities_for_area("office"), ["sensor.temp"]);
+
+        // Renaming keeps the entity in its area under the new id
+        registry
+            .update_entity_id("sensor.temp", "sensor.office_temp")
+            .unwrap();
+        assert_eq!(registry.entities_for_area("office"), ["sensor.office_temp"]);
+
+        registry
+            .update("sensor.office_temp", |e| e.area_id = None)
+            .unwrap();
+        assert!(registry.entities_for_area("office").is_empty());
+    }
+}
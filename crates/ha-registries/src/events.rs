@@ -0,0 +1,59 @@
+//! Registry change events
+//!
+//! Registries created with an event bus announce every create, update and
+//! remove as a `*_registry_updated` event, like HA does for the frontend.
+
+use std::sync::Arc;
+
+use ha_core::{Context, EventData};
+use ha_event_bus::EventBus;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Action of a registry change event
+pub(crate) const ACTION_CREATE: &str = "create";
+/// Action of a registry change event
+pub(crate) const ACTION_UPDATE: &str = "update";
+/// Action of a registry change event
+pub(crate) const ACTION_REMOVE: &str = "remove";
+
+/// Event bus a registry announces its changes on, if it has one
+#[derive(Default)]
+pub(crate) struct ChangeEvents(Option<Arc<EventBus>>);
+
+impl ChangeEvents {
+    pub(crate) fn new(event_bus: Arc<EventBus>) -> Self {
+        Self(Some(event_bus))
+    }
+
+    /// Fire a `*_registry_updated` event; does nothing without an event bus
+    pub(crate) fn fire<T: EventData + Serialize>(&self, data: T) {
+        if let Some(event_bus) = &self.0 {
+            event_bus.fire_typed(data, Context::new());
+        }
+    }
+}
+
+/// Previous values of the top-level fields that differ between two entries
+///
+/// Fields left out of the serialized form count as `null`. `modified_at` is
+/// bookkeeping rather than a change and is never reported.
+pub(crate) fn changed_values<T: Serialize>(old: &T, new: &T) -> Map<String, Value> {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return Map::new();
+    };
+
+    let mut changes = Map::new();
+    for key in old.keys().chain(new.keys()) {
+        if key == "modified_at" || changes.contains_key(key) {
+            continue;
+        }
+        let before = old.get(key).unwrap_or(&Value::Null);
+        if before != new.get(key).unwrap_or(&Value::Null) {
+            changes.insert(key.clone(), before.clone());
+        }
+    }
+    changes
+}
@@ -6,10 +6,13 @@ use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use ha_core::events::FloorRegistryUpdatedData;
+use ha_event_bus::EventBus;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info};
 
-use crate::storage::{Storable, Storage, StorageFile, StorageResult};
+use crate::events::{ChangeEvents, ACTION_CREATE, ACTION_REMOVE, ACTION_UPDATE};
+use crate::storage::{PendingSave, Storable, Storage, StorageFile, StorageResult};
 
 /// Storage key for floor registry
 pub const STORAGE_KEY: &str = "core.floor_registry";
@@ -122,6 +125,12 @@ pub struct FloorRegistry {
 
     /// Index: level -> floor_id
     by_level: DashMap<i32, String>,
+
+    /// Bus that receives a FLOOR_REGISTRY_UPDATED event for every change
+    events: ChangeEvents,
+
+    /// Set by every change until the next save
+    pending: PendingSave,
 }
 
 impl FloorRegistry {
@@ -132,12 +141,31 @@ impl FloorRegistry {
             by_id: DashMap::new(),
             by_name: DashMap::new(),
             by_level: DashMap::new(),
+            events: ChangeEvents::default(),
+            pending: PendingSave::default(),
+        }
+    }
+
+    /// Create a floor registry that fires FLOOR_REGISTRY_UPDATED events
+    pub fn with_event_bus(storage: Arc<Storage>, event_bus: Arc<EventBus>) -> Self {
+        Self {
+            events: ChangeEvents::new(event_bus),
+            ..Self::new(storage)
         }
     }
 
+    /// Schedule a save and announce a change with a FLOOR_REGISTRY_UPDATED event
+    fn entry_changed(&self, action: &str, floor_id: &str) {
+        self.schedule_save();
+        self.events.fire(FloorRegistryUpdatedData {
+            action: action.to_string(),
+            floor_id: floor_id.to_string(),
+        });
+    }
+
     /// Load from storage
     pub async fn load(&self) -> StorageResult<()> {
-        if let Some(storage_file) = self.storage.load::<FloorRegistryData>(STORAGE_KEY).await? {
+        if let Some(storage_file) = self.storage.load_migrated::<FloorRegistryData>().await? {
             info!(
                 "Loading {} floors from storage (v{}.{})",
                 storage_file.data.floors.len(),
@@ -154,16 +182,33 @@ impl FloorRegistry {
 
     /// Save to storage
     pub async fn save(&self) -> StorageResult<()> {
+        self.storage
+            .save_pending(&self.pending, || self.storage_file())
+            .await?;
+        debug!("Saved {} floors to storage", self.by_id.len());
+        Ok(())
+    }
+
+    /// Mark the registry as having unsaved changes
+    ///
+    /// Nothing is written yet; the next `flush()` saves the registry. The task
+    /// started by `Registries::spawn_save_task` flushes after the save delay.
+    pub fn schedule_save(&self) {
+        self.storage.schedule_save(&self.pending);
+    }
+
+    /// Save to storage if there are unsaved changes
+    pub async fn flush(&self) -> StorageResult<()> {
+        self.storage.flush(&self.pending, || self.save()).await
+    }
+
+    /// Snapshot of the registry as it is written to storage
+    fn storage_file(&self) -> StorageFile<FloorRegistryData> {
         let data = FloorRegistryData {
             floors: self.by_id.iter().map(|r| (**r.value()).clone()).collect(),
         };
 
-        let storage_file =
-            StorageFile::new(STORAGE_KEY, data, STORAGE_VERSION, STORAGE_MINOR_VERSION);
-
-        self.storage.save(&storage_file).await?;
-        debug!("Saved {} floors to storage", self.by_id.len());
-        Ok(())
+        StorageFile::new(STORAGE_KEY, data, STORAGE_VERSION, STORAGE_MINOR_VERSION)
     }
 
     /// Index an entry (takes Arc to avoid cloning)
@@ -240,6 +285,7 @@ impl FloorRegistry {
             name, level, arc_entry.id
         );
         self.index_entry(Arc::clone(&arc_entry));
+        self.entry_changed(ACTION_CREATE, &arc_entry.id);
         Ok(arc_entry)
     }
 
@@ -318,6 +364,10 @@ impl FloorRegistry {
             let new_arc = Arc::new(entry);
             self.index_entry(Arc::clone(&new_arc));
 
+            if changed {
+                self.entry_changed(ACTION_UPDATE, floor_id);
+            }
+
             Ok(new_arc)
         } else {
             Err(format!("Floor not found: {}", floor_id))
@@ -331,6 +381,7 @@ impl FloorRegistry {
         if let Some((_, arc_entry)) = self.by_id.remove(floor_id) {
             self.unindex_entry(&arc_entry);
             info!("Removed floor: {}", floor_id);
+            self.entry_changed(ACTION_REMOVE, floor_id);
             Some(arc_entry)
         } else {
             None
@@ -6,10 +6,13 @@ use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use ha_core::events::LabelRegistryUpdatedData;
+use ha_event_bus::EventBus;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info};
 
-use crate::storage::{Storable, Storage, StorageFile, StorageResult};
+use crate::events::{ChangeEvents, ACTION_CREATE, ACTION_REMOVE, ACTION_UPDATE};
+use crate::storage::{PendingSave, Storable, Storage, StorageFile, StorageResult};
 
 /// Storage key for label registry
 pub const STORAGE_KEY: &str = "core.label_registry";
@@ -132,6 +135,12 @@ pub struct LabelRegistry {
 
     /// Index: normalized_name -> label_id
     by_name: DashMap<String, String>,
+
+    /// Bus that receives a LABEL_REGISTRY_UPDATED event for every change
+    events: ChangeEvents,
+
+    /// Set by every change until the next save
+    pending: PendingSave,
 }
 
 impl LabelRegistry {
@@ -141,12 +150,31 @@ impl LabelRegistry {
             storage,
             by_id: DashMap::new(),
             by_name: DashMap::new(),
+            events: ChangeEvents::default(),
+            pending: PendingSave::default(),
+        }
+    }
+
+    /// Create a label registry that fires LABEL_REGISTRY_UPDATED events
+    pub fn with_event_bus(storage: Arc<Storage>, event_bus: Arc<EventBus>) -> Self {
+        Self {
+            events: ChangeEvents::new(event_bus),
+            ..Self::new(storage)
         }
     }
 
+    /// Schedule a save and announce a change with a LABEL_REGISTRY_UPDATED event
+    fn entry_changed(&self, action: &str, label_id: &str) {
+        self.schedule_save();
+        self.events.fire(LabelRegistryUpdatedData {
+            action: action.to_string(),
+            label_id: label_id.to_string(),
+        });
+    }
+
     /// Load from storage
     pub async fn load(&self) -> StorageResult<()> {
-        if let Some(storage_file) = self.storage.load::<LabelRegistryData>(STORAGE_KEY).await? {
+        if let Some(storage_file) = self.storage.load_migrated::<LabelRegistryData>().await? {
             info!(
                 "Loading {} labels from storage (v{}.{})",
                 storage_file.data.labels.len(),
@@ -163,16 +191,33 @@ impl LabelRegistry {
 
     /// Save to storage
     pub async fn save(&self) -> StorageResult<()> {
+        self.storage
+            .save_pending(&self.pending, || self.storage_file())
+            .await?;
+        debug!("Saved {} labels to storage", self.by_id.len());
+        Ok(())
+    }
+
+    /// Mark the registry as having unsaved changes
+    ///
+    /// Nothing is written yet; the next `flush()` saves the registry. The task
+    /// started by `Registries::spawn_save_task` flushes after the save delay.
+    pub fn schedule_save(&self) {
+        self.storage.schedule_save(&self.pending);
+    }
+
+    /// Save to storage if there are unsaved changes
+    pub async fn flush(&self) -> StorageResult<()> {
+        self.storage.flush(&self.pending, || self.save()).await
+    }
+
+    /// Snapshot of the registry as it is written to storage
+    fn storage_file(&self) -> StorageFile<LabelRegistryData> {
         let data = LabelRegistryData {
             labels: self.by_id.iter().map(|r| (**r.value()).clone()).collect(),
         };
 
-        let storage_file =
-            StorageFile::new(STORAGE_KEY, data, STORAGE_VERSION, STORAGE_MINOR_VERSION);
-
-        self.storage.save(&storage_file).await?;
-        debug!("Saved {} labels to storage", self.by_id.len());
-        Ok(())
+        StorageFile::new(STORAGE_KEY, data, STORAGE_VERSION, STORAGE_MINOR_VERSION)
     }
 
     /// Index an entry (takes Arc to avoid cloning)
@@ -232,6 +277,7 @@ impl LabelRegistry {
         let arc_entry = Arc::new(entry);
         info!("Created label: {} ({})", name, arc_entry.id);
         self.index_entry(Arc::clone(&arc_entry));
+        self.entry_changed(ACTION_CREATE, &arc_entry.id);
         Ok(arc_entry)
     }
 
@@ -267,6 +313,7 @@ impl LabelRegistry {
         let arc_entry = Arc::new(entry);
         info!("Created label: {} ({})", arc_entry.name, arc_entry.id);
         self.index_entry(Arc::clone(&arc_entry));
+        self.entry_changed(ACTION_CREATE, &arc_entry.id);
         Ok(arc_entry)
     }
 
@@ -326,6 +373,10 @@ impl LabelRegistry {
             let new_arc = Arc::new(entry);
             self.index_entry(Arc::clone(&new_arc));
 
+            if changed {
+                self.entry_changed(ACTION_UPDATE, label_id);
+            }
+
             Ok(new_arc)
         } else {
             Err(format!("Label not found: {}", label_id))
@@ -339,6 +390,7 @@ impl LabelRegistry {
         if let Some((_, arc_entry)) = self.by_id.remove(label_id) {
             self.unindex_entry(&arc_entry);
             info!("Removed label: {}", label_id);
+            self.entry_changed(ACTION_REMOVE, label_id);
             Some(arc_entry)
         } else {
             None
@@ -12,6 +12,8 @@
 
 pub mod storage;
 
+mod events;
+
 pub mod area_registry;
 pub mod device_registry;
 pub mod entity_registry;
@@ -19,7 +21,9 @@ pub mod floor_registry;
 pub mod label_registry;
 
 // Re-export main types
-pub use storage::{Storable, Storage, StorageError, StorageFile, StorageResult};
+pub use storage::{
+    PendingSave, Storable, Storage, StorageError, StorageFile, StorageMigrator, StorageResult,
+};
 
 pub use entity_registry::{
     DisabledBy, EntityCategory, EntityEntry, EntityRegistry, EntityRegistryData,
@@ -37,7 +41,16 @@ pub use floor_registry::{FloorEntry, FloorRegistry, FloorRegistryData};
 
 pub use label_registry::{LabelEntry, LabelRegistry, LabelRegistryData};
 
+use std::collections::BTreeSet;
 use std::sync::Arc;
+use std::time::Duration;
+
+use ha_event_bus::EventBus;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// Delay between a registry change and writing it to storage (matches HA)
+pub const SAVE_DELAY: Duration = Duration::from_secs(10);
 
 /// All registries bundled together
 pub struct Registries {
@@ -64,6 +77,24 @@ impl Registries {
         }
     }
 
+    /// Create registries that fire `*_registry_updated` events on the given bus
+    /// whenever an entry is created, updated or removed
+    pub fn with_event_bus(
+        config_dir: impl AsRef<std::path::Path>,
+        event_bus: Arc<EventBus>,
+    ) -> Self {
+        let storage = Arc::new(Storage::new(config_dir));
+
+        Self {
+            entities: EntityRegistry::with_event_bus(storage.clone(), event_bus.clone()),
+            devices: DeviceRegistry::with_event_bus(storage.clone(), event_bus.clone()),
+            areas: AreaRegistry::with_event_bus(storage.clone(), event_bus.clone()),
+            floors: FloorRegistry::with_event_bus(storage.clone(), event_bus.clone()),
+            labels: LabelRegistry::with_event_bus(storage.clone(), event_bus),
+            storage,
+        }
+    }
+
     /// Load all registries from storage
     pub async fn load_all(&self) -> StorageResult<()> {
         self.entities.load().await?;
@@ -74,6 +105,39 @@ impl Registries {
         Ok(())
     }
 
+    /// Save the registries that have unsaved changes
+    pub async fn flush(&self) -> StorageResult<()> {
+        self.entities.flush().await?;
+        self.devices.flush().await?;
+        self.areas.flush().await?;
+        self.floors.flush().await?;
+        self.labels.flush().await?;
+        Ok(())
+    }
+
+    /// Start a background task that saves changed registries
+    ///
+    /// After the first change the task waits `delay` and then flushes once,
+    /// so a burst of changes costs a single write per registry. The task ends
+    /// when the registries are dropped; call `save_all` on shutdown to write
+    /// out changes still waiting for the delay.
+    pub fn spawn_save_task(self: &Arc<Self>, delay: Duration) -> JoinHandle<()> {
+        let registries = Arc::downgrade(self);
+        let storage = self.storage.clone();
+        tokio::spawn(async move {
+            loop {
+                storage.save_requested().await;
+                tokio::time::sleep(delay).await;
+                let Some(registries) = registries.upgrade() else {
+                    break;
+                };
+                if let Err(e) = registries.flush().await {
+                    warn!("Failed to save registries: {}", e);
+                }
+            }
+        })
+    }
+
     /// Save all registries to storage
     pub async fn save_all(&self) -> StorageResult<()> {
         self.entities.save().await?;
@@ -83,7 +147,278 @@ impl Registries {
         self.labels.save().await?;
         Ok(())
     }
+
+    /// Expand a `label_id` service target into the sorted entity_ids it covers
+    ///
+    /// Matches HA's target resolution: entities carrying the label directly,
+    /// plus every entity of a device carrying the label.
+    pub fn expand_label_target(&self, label_id: &str) -> Vec<String> {
+        let mut entity_ids: BTreeSet<String> = self
+            .entities
+            .get_by_label_id(label_id)
+            .iter()
+            .map(|entry| entry.entity_id.clone())
+            .collect();
+
+        for device in self.devices.iter() {
+            if device.labels.iter().any(|l| l == label_id) {
+                entity_ids.extend(self.entities.entities_for_device(&device.id));
+            }
+        }
+
+        entity_ids.into_iter().collect()
+    }
+
+    /// Expand a `device_id` service target into the sorted entity_ids it covers
+    pub fn expand_device_target(&self, device_id: &str) -> Vec<String> {
+        self.entities.entities_for_device(device_id)
+    }
+
+    /// Expand an `area_id` service target into the sorted entity_ids it covers
+    ///
+    /// Collects the entities assigned to the area, plus entities without an
+    /// area of their own whose device is in the area.
+    pub fn expand_area_target(&self, area_id: &str) -> Vec<String> {
+        let mut entity_ids: BTreeSet<String> = self
+            .entities
+            .entities_for_area(area_id)
+            .into_iter()
+            .collect();
+
+        for device in self.devices.get_by_area_id(area_id) {
+            entity_ids.extend(
+                self.entities
+                    .get_by_device_id(&device.id)
+                    .iter()
+                    .filter(|entry| entry.area_id.is_none())
+                    .map(|entry| entry.entity_id.clone()),
+            );
+        }
+
+        entity_ids.into_iter().collect()
+    }
+
+    /// Expand a `floor_id` service target into the sorted entity_ids it covers
+    ///
+    /// Expands each of the floor's areas like [`Self::expand_area_target`].
+    /// Unknown floors and floors without areas expand to nothing.
+    pub fn expand_floor_target(&self, floor_id: &str) -> Vec<String> {
+        let mut entity_ids = BTreeSet::new();
+
+        for area in self.areas.get_by_floor_id(floor_id) {
+            entity_ids.extend(self.expand_area_target(&area.id));
+        }
+
+        entity_ids.into_iter().collect()
+    }
+
+    /// Resolve all targets of a service call into the entity_ids it affects
+    ///
+    /// Merges the `entity_id` of the service data with the expansion of its
+    /// `device_id`, `area_id`, `floor_id` and `label_id` targets, each a
+    /// string or a list. Entities keep the order they're first named in and
+    /// appear once.
+    pub fn resolve_target(&self, service_data: &serde_json::Value) -> Vec<String> {
+        let ids = |key: &str| -> Vec<String> {
+            match service_data.get(key) {
+                Some(serde_json::Value::String(ids)) => ids
+                    .split(',')
+                    .map(|id| id.trim().to_string())
+                    .filter(|id| !id.is_empty())
+                    .collect(),
+                Some(serde_json::Value::Array(ids)) => ids
+                    .iter()
+                    .filter_map(|id| id.as_str().map(String::from))
+                    .collect(),
+                _ => Vec::new(),
+            }
+        };
+
+        let mut all = ids("entity_id");
+        for id in ids("device_id") {
+            all.extend(self.expand_device_target(&id));
+        }
+        for id in ids("area_id") {
+            all.extend(self.expand_area_target(&id));
+        }
+        for id in ids("floor_id") {
+            all.extend(self.expand_floor_target(&id));
+        }
+        for id in ids("label_id") {
+            all.extend(self.expand_label_target(&id));
+        }
+
+        let mut entity_ids = Vec::new();
+        for entity_id in all {
+            if !entity_ids.contains(&entity_id) {
+                entity_ids.push(entity_id);
+            }
+        }
+        entity_ids
+    }
 }
 
-// Unit tests removed - covered by HA native tests via `make ha-compat-test`
-// See tests/ha_compat/ for comprehensive Registries testing through Python bindings
+// Unit tests for the Rust-side behavior; run `make ha-compat-test` for the
+// comprehensive Registries tests in tests/ha_compat/ through Python bindings
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_floor_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let registries = Registries::new(dir.path());
+        let floor = registries.floors.create("Ground", Some(0), None).unwrap();
+        registries.floors.create("Attic", Some(2), None).unwrap();
+
+        for (area_name, entity_id) in [("Kitchen", "light.kitchen"), ("Hall", "sensor.hall")] {
+            let area = registries.areas.create(area_name, None).unwrap();
+            registries
+                .areas
+                .update(&area.id, |a| a.floor_id = Some(floor.id.clone()), None)
+                .unwrap();
+            registries
+                .entities
+                .get_or_create("demo", entity_id, None, None, None);
+            registries
+                .entities
+                .update(entity_id, |e| e.area_id = Some(area.id.clone()))
+                .unwrap();
+        }
+        // An area without a floor is not picked up
+        registries.areas.create("Garage", None).unwrap();
+        registries
+            .entities
+            .get_or_create("demo", "light.garage", None, None, None);
+        registries
+            .entities
+            .update("light.garage", |e| e.area_id = Some("garage".to_string()))
+            .unwrap();
+
+        assert_eq!(
+            registries.expand_floor_target(&floor.id),
+            ["light.kitchen", "sensor.hall"]
+        );
+        assert!(registries.expand_floor_target("attic").is_empty());
+        assert!(registries.expand_floor_target("missing").is_empty());
+    }
+
+    #[test]
+    fn test_resolve_target_merges_and_dedupes() {
+        let dir = tempfile::tempdir().unwrap();
+        let registries = Registries::new(dir.path());
+        let area = registries.areas.create("Office", None).unwrap();
+        for entity_id in ["light.desk", "light.shelf", "fan.office"] {
+            registries
+                .entities
+                .get_or_create("demo", entity_id, None, None, None);
+        }
+        for entity_id in ["light.desk", "fan.office"] {
+            registries
+                .entities
+                .update(entity_id, |e| e.area_id = Some(area.id.clone()))
+                .unwrap();
+        }
+        registries
+            .entities
+            .update("light.shelf", |e| {
+                e.labels.insert("reading".to_string());
+            })
+            .unwrap();
+
+        let data = serde_json::json!({
+            "entity_id": "light.desk, light.porch",
+            "area_id": [area.id],
+            "label_id": "reading",
+        });
+        assert_eq!(
+            registries.resolve_target(&data),
+            ["light.desk", "light.porch", "fan.office", "light.shelf"]
+        );
+        assert!(registries
+            .resolve_target(&serde_json::json!({"brightness": 10}))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_rapid_changes_are_saved_once() {
+        tokio_test::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let registries = Arc::new(Registries::new(dir.path()));
+            let delay = Duration::from_millis(50);
+            let task = registries.spawn_save_task(delay);
+            let path = registries.storage.file_path(entity_registry::STORAGE_KEY);
+
+            for i in 0..100 {
+                registries.entities.get_or_create(
+                    "demo",
+                    &format!("sensor.s{}", i),
+                    None,
+                    None,
+                    None,
+                );
+            }
+            assert!(!path.exists());
+
+            tokio::time::sleep(delay * 4).await;
+            let content = std::fs::read_to_string(&path).unwrap();
+            let file: StorageFile<EntityRegistryData> = serde_json::from_str(&content).unwrap();
+            assert_eq!(file.data.entities.len(), 100);
+
+            // Everything was written in one go, so nothing writes the file again
+            std::fs::remove_file(&path).unwrap();
+            tokio::time::sleep(delay * 4).await;
+            assert!(!path.exists());
+
+            // Shutdown writes out changes that are still waiting for the delay
+            registries
+                .entities
+                .get_or_create("demo", "sensor.late", None, None, None);
+            registries.save_all().await.unwrap();
+            assert!(path.exists());
+            task.abort();
+        });
+    }
+
+    #[test]
+    fn test_registry_updated_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let event_bus = Arc::new(EventBus::new());
+        let registries = Registries::with_event_bus(dir.path(), event_bus.clone());
+        let mut entity_events = event_bus.subscribe(ha_core::events::ENTITY_REGISTRY_UPDATED);
+        let mut area_events = event_bus.subscribe(ha_core::events::AREA_REGISTRY_UPDATED);
+
+        registries
+            .entities
+            .get_or_create("demo", "light.porch", None, None, None);
+        let event = entity_events.try_recv().unwrap();
+        assert_eq!(
+            event.data,
+            serde_json::json!({"action": "create", "entity_id": "light.porch"})
+        );
+
+        registries
+            .entities
+            .update("light.porch", |e| e.name = Some("Porch".to_string()))
+            .unwrap();
+        let event = entity_events.try_recv().unwrap();
+        assert_eq!(event.data["action"], "update");
+        assert_eq!(event.data["changes"], serde_json::json!({"name": null}));
+
+        // An update that changes nothing stays quiet
+        registries.entities.update("light.porch", |_| {}).unwrap();
+        assert!(entity_events.try_recv().is_err());
+
+        registries.entities.remove("light.porch");
+        let event = entity_events.try_recv().unwrap();
+        assert_eq!(event.data["action"], "remove");
+
+        let area = registries.areas.create("Kitchen", None).unwrap();
+        let event = area_events.try_recv().unwrap();
+        assert_eq!(
+            event.data,
+            serde_json::json!({"action": "create", "area_id": area.id})
+        );
+    }
+}
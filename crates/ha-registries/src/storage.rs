@@ -2,11 +2,19 @@
 //!
 //! Implements the Home Assistant `.storage/` directory pattern with versioning.
 
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use thiserror::Error;
 use tokio::fs;
-use tracing::{debug, warn};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Notify;
+use tracing::{debug, error, info, warn};
 
 /// Storage errors
 #[derive(Debug, Error)]
@@ -69,11 +77,32 @@ impl<T> StorageFile<T> {
     }
 }
 
+/// Unsaved-changes flag of one store kept in a `Storage`
+///
+/// Set by `Storage::schedule_save` and cleared when `Storage::save_pending`
+/// writes the store.
+#[derive(Debug, Default)]
+pub struct PendingSave(AtomicBool);
+
+impl PendingSave {
+    /// Whether the store changed since it was last saved
+    pub fn is_pending(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+/// Migration step that upgrades stored `data` by one major version
+pub type StorageMigrator = fn(serde_json::Value) -> StorageResult<serde_json::Value>;
+
 /// Storage manager for handling `.storage/` directory
 #[derive(Debug, Clone)]
 pub struct Storage {
     /// Path to the `.storage/` directory
     storage_dir: PathBuf,
+    /// Registered migrations: storage key -> from_version -> migrator
+    migrations: Arc<DashMap<String, BTreeMap<u32, StorageMigrator>>>,
+    /// Wakes the debounced save task when data sharing this storage changed
+    save_requested: Arc<Notify>,
 }
 
 impl Storage {
@@ -84,9 +113,75 @@ impl Storage {
     pub fn new(config_dir: impl AsRef<Path>) -> Self {
         Self {
             storage_dir: config_dir.as_ref().join(".storage"),
+            migrations: Arc::new(DashMap::new()),
+            save_requested: Arc::new(Notify::new()),
         }
     }
 
+    /// Signal that some data stored here has unsaved changes
+    ///
+    /// A request made while nobody is waiting is kept until the next call
+    /// to `save_requested`.
+    pub fn request_save(&self) {
+        self.save_requested.notify_one();
+    }
+
+    /// Wait until `request_save` is called
+    pub async fn save_requested(&self) {
+        self.save_requested.notified().await;
+    }
+
+    /// Mark a store as having unsaved changes
+    ///
+    /// Nothing is written yet; the debounced save task wakes up and writes the
+    /// store with `flush` after its delay.
+    pub fn schedule_save(&self, pending: &PendingSave) {
+        pending.0.store(true, Ordering::Release);
+        self.request_save();
+    }
+
+    /// Write a store whose changes are tracked by `pending`
+    ///
+    /// The flag is cleared before `snapshot` runs, so changes made while the
+    /// file is written are picked up by the next save. A failed write leaves
+    /// the store marked as unsaved.
+    pub async fn save_pending<T, F>(&self, pending: &PendingSave, snapshot: F) -> StorageResult<()>
+    where
+        T: Serialize,
+        F: FnOnce() -> StorageFile<T>,
+    {
+        pending.0.store(false, Ordering::Release);
+        let result = self.save(&snapshot()).await;
+        if result.is_err() {
+            pending.0.store(true, Ordering::Release);
+        }
+        result
+    }
+
+    /// Run `save` if the store tracked by `pending` has unsaved changes
+    pub async fn flush<F, Fut>(&self, pending: &PendingSave, save: F) -> StorageResult<()>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = StorageResult<()>>,
+    {
+        if pending.is_pending() {
+            save().await?;
+        }
+        Ok(())
+    }
+
+    /// Register a migration that upgrades the data stored under `key` from
+    /// `from_version` to `from_version + 1`
+    ///
+    /// Used by `load_migrated`, which chains the registered steps until the
+    /// data reaches the version the code expects.
+    pub fn register_migration(&self, key: &str, from_version: u32, migrate: StorageMigrator) {
+        self.migrations
+            .entry(key.to_string())
+            .or_default()
+            .insert(from_version, migrate);
+    }
+
     /// Get the storage directory path
     pub fn storage_dir(&self) -> &Path {
         &self.storage_dir
@@ -111,9 +206,23 @@ impl Storage {
         self.file_path(key).exists()
     }
 
+    /// Get the backup path a corrupt storage file found at `at` is moved to
+    ///
+    /// The timestamp keeps an earlier backup from being overwritten.
+    pub fn corrupt_path(&self, key: &str, at: DateTime<Utc>) -> PathBuf {
+        self.file_path(&format!(
+            "{}.corrupt.{}",
+            key,
+            at.format("%Y%m%dT%H%M%S%.6fZ")
+        ))
+    }
+
     /// Load data from storage
     ///
-    /// Returns None if the file doesn't exist.
+    /// Returns None if the file doesn't exist. A file that isn't valid JSON
+    /// (e.g. truncated by a crash) is moved to `<key>.corrupt.<timestamp>` so
+    /// its data can still be recovered by hand, and loading continues as if
+    /// it was missing.
     pub async fn load<T>(&self, key: &str) -> StorageResult<Option<StorageFile<T>>>
     where
         T: DeserializeOwned,
@@ -126,7 +235,19 @@ impl Storage {
         }
 
         let content = fs::read_to_string(&path).await?;
-        let storage_file: StorageFile<T> = serde_json::from_str(&content)?;
+        let raw: serde_json::Value = match serde_json::from_str(&content) {
+            Ok(raw) => raw,
+            Err(e) => {
+                let backup = self.corrupt_path(key, Utc::now());
+                error!(
+                    "Storage file {} is corrupt ({}), moving it to {:?}",
+                    key, e, backup
+                );
+                fs::rename(&path, &backup).await?;
+                return Ok(None);
+            }
+        };
+        let storage_file: StorageFile<T> = serde_json::from_value(raw)?;
 
         debug!(
             "Loaded storage file: {} (v{}.{})",
@@ -136,6 +257,51 @@ impl Storage {
         Ok(Some(storage_file))
     }
 
+    /// Load a `Storable` type, migrating older stored versions first
+    ///
+    /// When the stored major version is below `T::VERSION`, the registered
+    /// migrations run in sequence on the raw JSON before it is deserialized.
+    /// Fails with `MigrationRequired` if a step in the chain is missing.
+    pub async fn load_migrated<T>(&self) -> StorageResult<Option<StorageFile<T>>>
+    where
+        T: Storable,
+    {
+        let Some(mut storage_file) = self.load::<serde_json::Value>(T::KEY).await? else {
+            return Ok(None);
+        };
+
+        if storage_file.version < T::VERSION {
+            let from = storage_file.version;
+            while storage_file.version < T::VERSION {
+                let migrate = self
+                    .migrations
+                    .get(T::KEY)
+                    .and_then(|steps| steps.get(&storage_file.version).copied())
+                    .ok_or_else(|| StorageError::MigrationRequired {
+                        key: T::KEY.to_string(),
+                        from: storage_file.version,
+                        to: T::VERSION,
+                    })?;
+                storage_file.data = migrate(storage_file.data)?;
+                storage_file.version += 1;
+            }
+            storage_file.minor_version = T::MINOR_VERSION;
+            info!(
+                "Migrated storage {} from v{} to v{}",
+                T::KEY,
+                from,
+                T::VERSION
+            );
+        }
+
+        Ok(Some(StorageFile {
+            version: storage_file.version,
+            minor_version: storage_file.minor_version,
+            key: storage_file.key,
+            data: serde_json::from_value(storage_file.data)?,
+        }))
+    }
+
     /// Load data from storage, returning an error if not found
     pub async fn load_required<T>(&self, key: &str) -> StorageResult<StorageFile<T>>
     where
@@ -148,7 +314,9 @@ impl Storage {
 
     /// Save data to storage
     ///
-    /// Writes atomically by first writing to a temp file, then renaming.
+    /// Writes atomically by first writing and syncing a temp file, then
+    /// renaming it over the target and syncing the directory, so a crash at
+    /// any point leaves either the old or the new file intact.
     pub async fn save<T>(&self, storage_file: &StorageFile<T>) -> StorageResult<()>
     where
         T: Serialize,
@@ -161,11 +329,15 @@ impl Storage {
         // Serialize with pretty printing for readability
         let content = serde_json::to_string_pretty(storage_file)?;
 
-        // Write to temp file first
-        fs::write(&temp_path, &content).await?;
+        // Write to temp file first and make sure it hits the disk
+        let mut file = fs::File::create(&temp_path).await?;
+        file.write_all(content.as_bytes()).await?;
+        file.sync_all().await?;
+        drop(file);
 
-        // Atomic rename
+        // Atomic rename, then persist the directory entry
         fs::rename(&temp_path, &path).await?;
+        self.sync_dir().await?;
 
         debug!(
             "Saved storage file: {} (v{}.{})",
@@ -175,6 +347,19 @@ impl Storage {
         Ok(())
     }
 
+    /// Flush the storage directory so a completed rename survives a power cut
+    #[cfg(unix)]
+    async fn sync_dir(&self) -> StorageResult<()> {
+        fs::File::open(&self.storage_dir).await?.sync_all().await?;
+        Ok(())
+    }
+
+    /// Directories can't be opened for syncing on this platform
+    #[cfg(not(unix))]
+    async fn sync_dir(&self) -> StorageResult<()> {
+        Ok(())
+    }
+
     /// Delete a storage file
     pub async fn delete(&self, key: &str) -> StorageResult<()> {
         let path = self.file_path(key);
@@ -200,8 +385,8 @@ impl Storage {
             if let Ok(file_type) = entry.file_type().await {
                 if file_type.is_file() {
                     if let Some(name) = entry.file_name().to_str() {
-                        // Skip temp files
-                        if !name.ends_with(".tmp") {
+                        // Skip temp files and corrupt backups
+                        if !name.ends_with(".tmp") && !name.contains(".corrupt.") {
                             keys.push(name.to_string());
                         }
                     }
@@ -290,5 +475,147 @@ where
     Ok(Some(storage_file.data))
 }
 
-// Unit tests removed - covered by HA native tests via `make ha-compat-test`
-// See tests/ha_compat/ for comprehensive Storage testing through Python bindings
+// Unit tests for the Rust-side behavior; run `make ha-compat-test` for the
+// comprehensive Storage tests in tests/ha_compat/ through Python bindings
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Items {
+        items: Vec<String>,
+    }
+
+    fn items(items: &[&str]) -> StorageFile<Items> {
+        StorageFile::new(
+            "test.items",
+            Items {
+                items: items.iter().map(|i| i.to_string()).collect(),
+            },
+            1,
+            1,
+        )
+    }
+
+    #[test]
+    fn test_partial_write_keeps_last_good_file() {
+        tokio_test::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let storage = Storage::new(dir.path());
+            storage.save(&items(&["a", "b"])).await.unwrap();
+
+            // A crash mid-save leaves a truncated temp file behind
+            let content = serde_json::to_string_pretty(&items(&["a", "b", "c"])).unwrap();
+            std::fs::write(
+                storage.file_path("test.items.tmp"),
+                &content[..content.len() / 2],
+            )
+            .unwrap();
+
+            let loaded = storage.load::<Items>("test.items").await.unwrap().unwrap();
+            assert_eq!(loaded.data, items(&["a", "b"]).data);
+            assert_eq!(storage.list_keys().await.unwrap(), ["test.items"]);
+
+            // The next save replaces the leftover temp file
+            storage.save(&items(&["c"])).await.unwrap();
+            assert!(!storage.file_path("test.items.tmp").exists());
+            let loaded = storage.load::<Items>("test.items").await.unwrap().unwrap();
+            assert_eq!(loaded.data, items(&["c"]).data);
+        });
+    }
+
+    #[test]
+    fn test_corrupt_file_is_backed_up() {
+        tokio_test::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let storage = Storage::new(dir.path());
+            storage.ensure_dir().await.unwrap();
+            std::fs::write(storage.file_path("test.items"), "{\"version\": 1, \"da").unwrap();
+
+            assert!(storage.load::<Items>("test.items").await.unwrap().is_none());
+            assert!(!storage.file_path("test.items").exists());
+
+            // A second corrupt file gets a backup of its own
+            std::fs::write(storage.file_path("test.items"), "{").unwrap();
+            assert!(storage.load::<Items>("test.items").await.unwrap().is_none());
+
+            let mut backups: Vec<String> = std::fs::read_dir(storage.storage_dir())
+                .unwrap()
+                .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+                .collect();
+            backups.sort();
+            assert_eq!(backups, ["{", "{\"version\": 1, \"da"]);
+            assert!(storage.list_keys().await.unwrap().is_empty());
+        });
+    }
+
+    /// v2 splits the v1 list of full names into first and last names
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct People {
+        people: Vec<Person>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Person {
+        first: String,
+        last: String,
+    }
+
+    impl Storable for People {
+        const KEY: &'static str = "test.people";
+        const VERSION: u32 = 2;
+        const MINOR_VERSION: u32 = 1;
+    }
+
+    fn migrate_people_v1(data: serde_json::Value) -> StorageResult<serde_json::Value> {
+        let names: Vec<String> = serde_json::from_value(data["names"].clone())?;
+        let people: Vec<_> = names
+            .iter()
+            .map(|name| {
+                let (first, last) = name.split_once(' ').unwrap_or((name, ""));
+                serde_json::json!({"first": first, "last": last})
+            })
+            .collect();
+        Ok(serde_json::json!({ "people": people }))
+    }
+
+    #[test]
+    fn test_load_migrated_runs_registered_migrations() {
+        tokio_test::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let storage = Storage::new(dir.path());
+            storage.ensure_dir().await.unwrap();
+            let v1 = serde_json::json!({
+                "version": 1,
+                "minor_version": 3,
+                "key": "test.people",
+                "data": {"names": ["Ada Lovelace", "Grace Hopper"]}
+            });
+            std::fs::write(storage.file_path("test.people"), v1.to_string()).unwrap();
+
+            // Without a migrator the old file is refused rather than misread
+            assert!(matches!(
+                storage.load_migrated::<People>().await,
+                Err(StorageError::MigrationRequired { from: 1, to: 2, .. })
+            ));
+
+            storage.register_migration("test.people", 1, migrate_people_v1);
+            let loaded = storage.load_migrated::<People>().await.unwrap().unwrap();
+            assert_eq!((loaded.version, loaded.minor_version), (2, 1));
+            assert_eq!(
+                loaded.data.people,
+                [
+                    Person {
+                        first: "Ada".to_string(),
+                        last: "Lovelace".to_string()
+                    },
+                    Person {
+                        first: "Grace".to_string(),
+                        last: "Hopper".to_string()
+                    },
+                ]
+            );
+        });
+    }
+}
@@ -44,6 +44,9 @@ pub enum ScriptExecutorError {
 
     #[error("Max runs exceeded")]
     MaxRunsExceeded,
+
+    #[error("Invalid script data: {0}")]
+    InvalidData(String),
 }
 
 /// Result type for script execution
@@ -69,6 +72,9 @@ pub struct ExecutionContext {
 
     /// Wait context from last wait_for_trigger
     pub wait: Option<WaitContext>,
+
+    /// Context for service calls and events from this run
+    pub context: Context,
 }
 
 impl ExecutionContext {
@@ -81,6 +87,7 @@ impl ExecutionContext {
             stop_on_condition_fail: true,
             repeat: None,
             wait: None,
+            context: Context::new(),
         }
     }
 
@@ -415,7 +422,7 @@ impl ScriptExecutor {
                 domain,
                 svc_name,
                 Value::Object(service_data),
-                Context::new(),
+                ctx.context.clone(),
                 return_response,
             )
             .await
@@ -537,7 +544,7 @@ impl ScriptExecutor {
         let ha_event = ha_core::Event::new(
             event.event.clone(),
             Value::Object(event_data),
-            Context::new(),
+            ctx.context.clone(),
         );
         self.event_bus.fire(ha_event);
 
@@ -547,7 +554,7 @@ impl ScriptExecutor {
     async fn execute_scene(
         &self,
         scene: &crate::action::SceneAction,
-        _ctx: &mut ExecutionContext,
+        ctx: &mut ExecutionContext,
     ) -> ScriptExecutorResult<ActionResult> {
         // Scene activation is done via scene.turn_on service
         debug!("Activating scene: {}", scene.scene);
@@ -557,7 +564,7 @@ impl ScriptExecutor {
         });
 
         self.service_registry
-            .call("scene", "turn_on", service_data, Context::new(), false)
+            .call("scene", "turn_on", service_data, ctx.context.clone(), false)
             .await
             .map_err(|e| ScriptExecutorError::ServiceCallFailed(e.to_string()))?;
 
@@ -6,19 +6,24 @@
 
 #![allow(clippy::too_many_arguments)]
 
+use chrono::{DateTime, Utc};
+use ha_automation::mqtt::{self, MqttSource, MqttUnsubscribe};
 use ha_automation::{
-    Automation, AutomationManager, ConditionEvaluator, EvalContext, ExecutionMode, TriggerData,
-    TriggerEvalContext, TriggerEvaluator,
+    Automation, AutomationConfig, AutomationManager, ConditionEvaluator, EvalContext,
+    ExecutionMode, ReloadSummary, Trigger, TriggerData, TriggerEvalContext, TriggerEvaluator,
 };
-use ha_core::Event;
+use ha_core::events::{AUTOMATION_TRIGGERED, STATE_CHANGED};
+use ha_core::{Context, Event, MAX_CONTEXT_DEPTH};
 use ha_event_bus::EventBus;
 use ha_service_registry::ServiceRegistry;
 use ha_state_store::StateStore;
 use ha_template::TemplateEngine;
+use serde_json::json;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
+use tokio::task::AbortHandle;
 use tracing::{debug, error, info, trace, warn};
 
 /// Automation engine that orchestrates trigger→condition→action flow
@@ -42,7 +47,13 @@ pub struct AutomationEngine {
     /// Shutdown signal sender
     shutdown_tx: broadcast::Sender<()>,
     /// Currently executing automations (keyed by automation ID)
-    executing: Arc<RwLock<HashMap<String, usize>>>,
+    executing: Arc<RunCounts>,
+    /// Background runs, including those waiting for a `for` to pass
+    run_tasks: Arc<RunTasks>,
+    /// Source of MQTT messages, if an MQTT integration provides one
+    mqtt_source: std::sync::Mutex<Option<Arc<dyn MqttSource>>>,
+    /// Subscriptions of MQTT triggers, ended when the engine stops
+    mqtt_subscriptions: std::sync::Mutex<Vec<MqttUnsubscribe>>,
 }
 
 impl AutomationEngine {
@@ -74,7 +85,10 @@ impl AutomationEngine {
             condition_evaluator,
             running: Arc::new(AtomicBool::new(false)),
             shutdown_tx,
-            executing: Arc::new(RwLock::new(HashMap::new())),
+            executing: Arc::default(),
+            run_tasks: Arc::new(RunTasks::default()),
+            mqtt_source: std::sync::Mutex::new(None),
+            mqtt_subscriptions: std::sync::Mutex::new(Vec::new()),
         }
     }
 
@@ -83,6 +97,14 @@ impl AutomationEngine {
         self.manager.clone()
     }
 
+    /// Set the source MQTT triggers subscribe through
+    ///
+    /// Takes effect the next time the engine starts. Without a source, MQTT
+    /// triggers never fire.
+    pub fn set_mqtt_source(&self, source: Arc<dyn MqttSource>) {
+        *self.mqtt_source.lock().unwrap() = Some(source);
+    }
+
     /// Start the automation engine
     ///
     /// This subscribes to all events and begins processing triggers.
@@ -94,6 +116,8 @@ impl AutomationEngine {
 
         info!("Starting automation engine");
 
+        self.subscribe_mqtt_triggers().await;
+
         // Subscribe to all events
         let mut event_rx = self.event_bus.subscribe_all();
         let mut shutdown_rx = self.shutdown_tx.subscribe();
@@ -107,10 +131,13 @@ impl AutomationEngine {
         let condition_evaluator = self.condition_evaluator.clone();
         let running = self.running.clone();
         let executing = self.executing.clone();
+        let run_tasks = self.run_tasks.clone();
 
         tokio::spawn(async move {
             loop {
                 tokio::select! {
+                    // Handle events fired before a stop, such as homeassistant_stop
+                    biased;
                     event_result = event_rx.recv() => {
                         match event_result {
                             Ok(event) => {
@@ -125,6 +152,7 @@ impl AutomationEngine {
                                     &trigger_evaluator,
                                     &condition_evaluator,
                                     &executing,
+                                    &run_tasks,
                                 ).await;
                             }
                             Err(broadcast::error::RecvError::Lagged(n)) => {
@@ -149,45 +177,185 @@ impl AutomationEngine {
     }
 
     /// Stop the automation engine
+    ///
+    /// Runs in progress, including those waiting for a `for` hold, are cancelled.
     pub fn stop(&self) {
         if !self.running.load(Ordering::SeqCst) {
             return;
         }
 
         info!("Stopping automation engine");
+        for unsubscribe in self.mqtt_subscriptions.lock().unwrap().drain(..) {
+            unsubscribe();
+        }
+        // Runs still waiting for a `for` hold would otherwise fire after stop
+        self.run_tasks.cancel_all();
         let _ = self.shutdown_tx.send(());
     }
 
+    /// Subscribe the MQTT triggers of all loaded automations
+    async fn subscribe_mqtt_triggers(&self) {
+        let Some(source) = self.mqtt_source.lock().unwrap().clone() else {
+            return;
+        };
+
+        // Messages arrive on the source's thread, which may not be in a runtime
+        let runtime = tokio::runtime::Handle::current();
+        let manager = self.manager.read().await;
+        let mut subscriptions = self.mqtt_subscriptions.lock().unwrap();
+        for automation in manager.all() {
+            for (index, trigger) in automation.triggers.iter().enumerate() {
+                let Trigger::Mqtt(mqtt_trigger) = trigger else {
+                    continue;
+                };
+
+                let automation_id = automation.id.clone();
+                let event_bus = self.event_bus.clone();
+                let state_machine = self.state_machine.clone();
+                let service_registry = self.service_registry.clone();
+                let template_engine = self.template_engine.clone();
+                let manager = self.manager.clone();
+                let condition_evaluator = self.condition_evaluator.clone();
+                let executing = self.executing.clone();
+                let run_tasks = self.run_tasks.clone();
+                let runtime = runtime.clone();
+
+                let action = Arc::new(move |trigger_data: TriggerData| {
+                    let trigger_data = trigger_data.with_index(index);
+                    let automation_id = automation_id.clone();
+                    let event_bus = event_bus.clone();
+                    let state_machine = state_machine.clone();
+                    let service_registry = service_registry.clone();
+                    let template_engine = template_engine.clone();
+                    let manager = manager.clone();
+                    let condition_evaluator = condition_evaluator.clone();
+                    let executing = executing.clone();
+                    let task_automation_id = automation_id.clone();
+
+                    // Look the automation up again, it may have been reloaded
+                    let task = runtime.spawn(async move {
+                        let (automation, run_context) = {
+                            let manager = manager.read().await;
+                            let Some(automation) = manager.get(&automation_id) else {
+                                return;
+                            };
+                            if !automation.enabled {
+                                return;
+                            }
+                            let run_context =
+                                manager.start_run_context(&automation_id, &Context::new());
+                            (automation, run_context)
+                        };
+
+                        Self::run_automation(
+                            &automation,
+                            trigger_data,
+                            run_context,
+                            false,
+                            &event_bus,
+                            &state_machine,
+                            &service_registry,
+                            &template_engine,
+                            &condition_evaluator,
+                            &executing,
+                        )
+                        .await;
+                    });
+                    run_tasks.track(&task_automation_id, task.abort_handle());
+                });
+
+                subscriptions.push(mqtt::subscribe_trigger(
+                    source.as_ref(),
+                    self.trigger_evaluator.clone(),
+                    mqtt_trigger.clone(),
+                    action,
+                ));
+            }
+        }
+    }
+
     /// Check if the engine is running
     pub fn is_running(&self) -> bool {
         self.running.load(Ordering::SeqCst)
     }
 
     /// Manually trigger an automation by ID
-    pub async fn trigger(&self, automation_id: &str, trigger_data: Option<TriggerData>) {
-        let manager = self.manager.read().await;
-        if let Some(automation) = manager.get(automation_id) {
+    ///
+    /// With `skip_condition` the actions run without checking the
+    /// automation's conditions. The execution mode still applies. The run's
+    /// context is a child of `parent`, typically the service call's context.
+    pub async fn trigger(
+        &self,
+        automation_id: &str,
+        trigger_data: Option<TriggerData>,
+        skip_condition: bool,
+        parent: &Context,
+    ) {
+        let (automation, run_context) = {
+            let manager = self.manager.read().await;
+            let Some(automation) = manager.get(automation_id) else {
+                warn!(automation_id, "Automation not found");
+                return;
+            };
             if !automation.enabled {
                 debug!(automation_id, "Automation is disabled, not triggering");
                 return;
             }
+            let run_context = manager.start_run_context(automation_id, parent);
+            (automation, run_context)
+        };
 
-            let trigger_data = trigger_data.unwrap_or_else(|| TriggerData::new("manual"));
-
-            Self::run_automation(
-                &automation,
-                trigger_data,
-                &self.event_bus,
-                &self.state_machine,
-                &self.service_registry,
-                &self.template_engine,
-                &self.condition_evaluator,
-                &self.executing,
-            )
-            .await;
-        } else {
-            warn!(automation_id, "Automation not found");
+        let trigger_data = trigger_data.unwrap_or_else(|| TriggerData::new("manual"));
+        Self::run_automation(
+            &automation,
+            trigger_data,
+            run_context,
+            skip_condition,
+            &self.event_bus,
+            &self.state_machine,
+            &self.service_registry,
+            &self.template_engine,
+            &self.condition_evaluator,
+            &self.executing,
+        )
+        .await;
+    }
+
+    /// Reload the automations from their configuration
+    ///
+    /// Runs of removed and changed automations are cancelled, including
+    /// those still waiting for a `for` duration, and MQTT triggers are
+    /// subscribed again so no subscription outlives its automation.
+    pub async fn reload(&self, configs: Vec<AutomationConfig>) -> ReloadSummary {
+        let summary = {
+            let manager = self.manager.write().await;
+            match manager.reload(configs) {
+                Ok(summary) => summary,
+                Err(e) => {
+                    error!(error = %e, "Failed to reload automations");
+                    return ReloadSummary::default();
+                }
+            }
+        };
+
+        let stopped = summary
+            .removed
+            .iter()
+            .map(|automation| &automation.id)
+            .chain(&summary.updated);
+        // Cancelled runs count themselves down when they are dropped
+        for automation_id in stopped {
+            self.run_tasks.cancel(automation_id);
+        }
+
+        for unsubscribe in self.mqtt_subscriptions.lock().unwrap().drain(..) {
+            unsubscribe();
         }
+        if self.is_running() {
+            self.subscribe_mqtt_triggers().await;
+        }
+
+        summary
     }
 
     /// Process an incoming event against all automations
@@ -200,7 +368,8 @@ impl AutomationEngine {
         manager: &Arc<RwLock<AutomationManager>>,
         trigger_evaluator: &Arc<TriggerEvaluator>,
         condition_evaluator: &Arc<ConditionEvaluator>,
-        executing: &Arc<RwLock<HashMap<String, usize>>>,
+        executing: &Arc<RunCounts>,
+        run_tasks: &Arc<RunTasks>,
     ) {
         trace!(event_type = %event.event_type, "Processing event");
 
@@ -213,31 +382,60 @@ impl AutomationEngine {
             }
 
             // Check each trigger
-            for trigger in &automation.triggers {
+            for (index, trigger) in automation.triggers.iter().enumerate() {
                 let ctx = TriggerEvalContext::new();
 
                 match trigger_evaluator.evaluate(trigger, event, &ctx) {
                     Ok(Some(trigger_data)) => {
+                        let trigger_data = trigger_data.with_index(index);
                         debug!(
                             automation_id = %automation.id,
                             trigger_platform = %trigger_data.platform,
                             "Trigger matched"
                         );
 
+                        if !Self::loop_guard_allows(&manager_guard, &automation, event) {
+                            continue;
+                        }
+
+                        // Triggers with `for` wait for the state to hold first;
+                        // subscribe now so no change after this event is missed
+                        let hold = match trigger {
+                            Trigger::State(state_trigger) if trigger_data.hold_for.is_some() => {
+                                Some((state_trigger.clone(), event_bus.subscribe(STATE_CHANGED)))
+                            }
+                            _ => None,
+                        };
+
                         // Run automation in the background
                         let automation = automation.clone();
                         let trigger_data = trigger_data.clone();
+                        let run_context =
+                            manager_guard.start_run_context(&automation.id, &event.context);
+                        let trigger_evaluator = trigger_evaluator.clone();
                         let event_bus = event_bus.clone();
                         let state_machine = state_machine.clone();
                         let service_registry = service_registry.clone();
                         let template_engine = template_engine.clone();
                         let condition_evaluator = condition_evaluator.clone();
                         let executing = executing.clone();
+                        let automation_id = automation.id.clone();
+
+                        let task = tokio::spawn(async move {
+                            if let Some((state_trigger, events)) = hold {
+                                if !trigger_evaluator
+                                    .wait_for_hold(&state_trigger, &trigger_data, events)
+                                    .await
+                                {
+                                    return;
+                                }
+                            }
 
-                        tokio::spawn(async move {
                             Self::run_automation(
                                 &automation,
                                 trigger_data,
+                                run_context,
+                                false,
                                 &event_bus,
                                 &state_machine,
                                 &service_registry,
@@ -247,6 +445,7 @@ impl AutomationEngine {
                             )
                             .await;
                         });
+                        run_tasks.track(&automation_id, task.abort_handle());
                     }
                     Ok(None) => {
                         // Trigger didn't match
@@ -263,22 +462,56 @@ impl AutomationEngine {
         }
     }
 
+    /// Check a matched trigger against the feedback loop guards
+    ///
+    /// Refuses triggers caused by the automation's own recent runs, and
+    /// triggers whose context chain is deeper than `MAX_CONTEXT_DEPTH`, which
+    /// catches loops running through several automations.
+    fn loop_guard_allows(
+        manager: &AutomationManager,
+        automation: &Automation,
+        event: &Event<serde_json::Value>,
+    ) -> bool {
+        if manager.is_self_triggered(&automation.id, &event.context) {
+            debug!(
+                automation_id = %automation.id,
+                "Trigger caused by the automation's own actions, not running"
+            );
+            return false;
+        }
+        if event.context.exceeds_depth(MAX_CONTEXT_DEPTH) {
+            warn!(
+                automation_id = %automation.id,
+                depth = event.context.depth(),
+                "Context chain too deep, not running automation (are automations triggering each other?)"
+            );
+            return false;
+        }
+        true
+    }
+
     /// Run a single automation
+    ///
+    /// Actions run with `run_context`, so events and state changes they
+    /// cause can be traced back to this run. `skip_condition` runs the
+    /// actions without evaluating the conditions.
     async fn run_automation(
         automation: &Automation,
         trigger_data: TriggerData,
+        run_context: Context,
+        skip_condition: bool,
         event_bus: &Arc<EventBus>,
         state_machine: &Arc<StateStore>,
         service_registry: &Arc<ServiceRegistry>,
         template_engine: &Arc<TemplateEngine>,
         condition_evaluator: &Arc<ConditionEvaluator>,
-        executing: &Arc<RwLock<HashMap<String, usize>>>,
+        executing: &Arc<RunCounts>,
     ) {
         let automation_id = automation.id.clone();
 
         // Check execution mode
         {
-            let mut exec_guard = executing.write().await;
+            let mut exec_guard = executing.lock();
             let current_runs = exec_guard.get(&automation_id).copied().unwrap_or(0);
 
             // Use the can_run() logic from automation, simulated here
@@ -302,6 +535,14 @@ impl AutomationEngine {
             // Increment run count
             *exec_guard.entry(automation_id.clone()).or_insert(0) += 1;
         }
+        // Counts down again however the run ends, including being aborted
+        let mut run = ExecutingRun {
+            automation,
+            executing,
+            state_machine,
+            context: run_context.clone(),
+            announced: false,
+        };
 
         debug!(
             automation_id = %automation_id,
@@ -312,7 +553,7 @@ impl AutomationEngine {
         let eval_ctx = EvalContext::with_trigger(trigger_data.clone());
 
         // Evaluate conditions
-        let conditions_pass = if automation.conditions.is_empty() {
+        let conditions_pass = if skip_condition || automation.conditions.is_empty() {
             true
         } else {
             match condition_evaluator.evaluate_all(&automation.conditions, &eval_ctx) {
@@ -333,11 +574,6 @@ impl AutomationEngine {
                 automation_id = %automation_id,
                 "Conditions not met, skipping action execution"
             );
-            // Decrement run count
-            let mut exec_guard = executing.write().await;
-            if let Some(count) = exec_guard.get_mut(&automation_id) {
-                *count = count.saturating_sub(1);
-            }
             return;
         }
 
@@ -347,6 +583,35 @@ impl AutomationEngine {
             "Executing automation actions"
         );
 
+        // Lets the logbook attribute what the actions change to this run
+        let source = match trigger_data
+            .variables
+            .get("entity_id")
+            .and_then(|v| v.as_str())
+        {
+            Some(entity_id) => format!("{} of {}", trigger_data.platform, entity_id),
+            None => trigger_data.platform.clone(),
+        };
+        event_bus.fire(Event::new(
+            AUTOMATION_TRIGGERED,
+            json!({
+                "name": automation.display_name(),
+                "entity_id": automation.entity_id().to_string(),
+                "source": source,
+            }),
+            run_context.clone(),
+        ));
+
+        let current_runs = executing.lock().get(&automation_id).copied().unwrap_or(0);
+        Self::update_entity_attributes(
+            state_machine,
+            automation,
+            current_runs,
+            Some(Utc::now()),
+            &run_context,
+        );
+        run.announced = true;
+
         // Create script executor
         let executor = ha_script::executor::ScriptExecutor::new(
             state_machine.clone(),
@@ -356,6 +621,7 @@ impl AutomationEngine {
         );
 
         let mut exec_ctx = ha_script::executor::ExecutionContext::with_trigger(trigger_data);
+        exec_ctx.context = run_context;
 
         let result = executor.execute(&automation.actions, &mut exec_ctx).await;
 
@@ -374,13 +640,113 @@ impl AutomationEngine {
                 );
             }
         }
+    }
 
-        // Decrement run count
-        {
-            let mut exec_guard = executing.write().await;
-            if let Some(count) = exec_guard.get_mut(&automation_id) {
+    /// Reflect a run on the automation entity's `current` and `last_triggered`
+    ///
+    /// Leaves the state alone; automations without an entity are skipped.
+    fn update_entity_attributes(
+        state_machine: &StateStore,
+        automation: &Automation,
+        current_runs: usize,
+        last_triggered: Option<DateTime<Utc>>,
+        context: &Context,
+    ) {
+        let entity_id = automation.entity_id();
+        let Some(state) = state_machine.get(entity_id.as_str()) else {
+            return;
+        };
+
+        let mut attributes = state.attributes.clone();
+        attributes.insert("current".to_string(), json!(current_runs));
+        if let Some(last_triggered) = last_triggered {
+            attributes.insert(
+                "last_triggered".to_string(),
+                json!(last_triggered.to_rfc3339()),
+            );
+        }
+        state_machine.set(entity_id, state.state.clone(), attributes, context.clone());
+    }
+}
+
+/// Number of executing runs, by automation ID
+#[derive(Default)]
+struct RunCounts(std::sync::Mutex<HashMap<String, usize>>);
+
+impl RunCounts {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, usize>> {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// A run counted in [`RunCounts`] until it is dropped
+///
+/// Dropping counts the run down and, once the run was announced on the
+/// automation entity, updates its `current` attribute. Runs cancelled by a
+/// reload or `stop()` are dropped mid-way and clean up the same way.
+struct ExecutingRun<'a> {
+    automation: &'a Automation,
+    executing: &'a RunCounts,
+    state_machine: &'a StateStore,
+    context: Context,
+    announced: bool,
+}
+
+impl Drop for ExecutingRun<'_> {
+    fn drop(&mut self) {
+        let current_runs = match self.executing.lock().get_mut(&self.automation.id) {
+            Some(count) => {
                 *count = count.saturating_sub(1);
+                *count
             }
+            None => 0,
+        };
+        if self.announced {
+            AutomationEngine::update_entity_attributes(
+                self.state_machine,
+                self.automation,
+                current_runs,
+                None,
+                &self.context,
+            );
+        }
+    }
+}
+
+/// Background runs of automations, by automation ID
+///
+/// Lets a reload cancel the runs of automations it removes or replaces.
+#[derive(Default)]
+struct RunTasks(std::sync::Mutex<HashMap<String, Vec<AbortHandle>>>);
+
+impl RunTasks {
+    /// Remember a run, forgetting runs that already finished
+    fn track(&self, automation_id: &str, task: AbortHandle) {
+        let mut tasks = self.0.lock().unwrap();
+        let runs = tasks.entry(automation_id.to_string()).or_default();
+        runs.retain(|run| !run.is_finished());
+        runs.push(task);
+    }
+
+    /// Abort all runs of an automation
+    fn cancel(&self, automation_id: &str) {
+        for run in self
+            .0
+            .lock()
+            .unwrap()
+            .remove(automation_id)
+            .unwrap_or_default()
+        {
+            run.abort();
+        }
+    }
+
+    /// Abort the runs of all automations
+    fn cancel_all(&self) {
+        for run in self.0.lock().unwrap().drain().flat_map(|(_, runs)| runs) {
+            run.abort();
         }
     }
 }
@@ -3,20 +3,26 @@
 //! Main entry point for the Home Assistant Rust implementation.
 
 mod automation_engine;
+mod script_engine;
 
 use anyhow::Result;
 use ha_api::{
-    auth::AuthState, config_flow::ConfigFlowHandler, frontend::FrontendConfig,
+    auth::{AuthState, ConfiguredAccessToken, TrustedNetworksConfig},
+    config_flow::{ConfigFlowHandler, OptionsFlowHandler},
+    frontend::FrontendConfig,
+    permissions::UserPermissions,
     persistent_notification, AppState,
 };
-use ha_automation::AutomationConfig;
+use ha_automation::{Automation, AutomationConfig, AutomationManager};
 use ha_components::{register_system_log_services, SystemLog};
 use ha_config::CoreConfig;
 use ha_config_entries::ConfigEntries;
-use ha_core::{Context, EntityId, ServiceCall, SupportsResponse};
+use ha_core::events::{HOMEASSISTANT_START, HOMEASSISTANT_STARTED, HOMEASSISTANT_STOP};
+use ha_core::{Context, EntityId, Event, ServiceCall, SupportsResponse};
 use ha_event_bus::EventBus;
+use ha_recorder::{Recorder, RecorderConfig, StateHistory};
 use ha_registries::{Registries, Storage};
-use ha_service_registry::{ServiceDescription, ServiceRegistry};
+use ha_service_registry::{ServiceDescription, ServiceError, ServiceRegistry};
 use ha_state_store::StateStore;
 use ha_template::TemplateEngine;
 use serde_json::json;
@@ -36,13 +42,15 @@ use ha_py_bridge::py_bridge::{
 /// The central Home Assistant instance
 pub struct HomeAssistant {
     /// Automation engine for trigger→condition→action flow
-    pub automation_engine: automation_engine::AutomationEngine,
+    pub automation_engine: Arc<automation_engine::AutomationEngine>,
     /// Event bus for pub/sub communication
     pub bus: Arc<EventBus>,
     /// Config entries manager
     pub config_entries: Arc<RwLock<ConfigEntries>>,
     /// Registries for entities, devices, areas, etc.
     pub registries: Arc<Registries>,
+    /// Script engine running `script.*` services
+    pub script_engine: Arc<script_engine::ScriptEngine>,
     /// Service registry for service calls
     pub services: Arc<ServiceRegistry>,
     /// State machine for entity states
@@ -59,14 +67,15 @@ impl HomeAssistant {
     ///
     /// # Arguments
     /// * `config_dir` - Path to the Home Assistant config directory
+    /// * `bus` - Event bus shared with the registries
     /// * `registries` - Registries for entities, devices, areas, etc.
-    pub fn new(config_dir: &Path, registries: Arc<Registries>) -> Self {
-        let bus = Arc::new(EventBus::new());
+    pub fn new(config_dir: &Path, bus: Arc<EventBus>, registries: Arc<Registries>) -> Self {
         let states = Arc::new(StateStore::new(bus.clone()));
-        let services = Arc::new(ServiceRegistry::new());
+        let services = Arc::new(ServiceRegistry::with_event_bus(bus.clone()));
 
         // Create template engine and load custom templates before wrapping in Arc
-        let mut template_engine = TemplateEngine::new(states.clone());
+        let mut template_engine =
+            TemplateEngine::with_registries(states.clone(), registries.clone());
         match template_engine.load_custom_templates(config_dir) {
             Ok(count) if count > 0 => {
                 info!("Loaded {} custom templates", count);
@@ -78,12 +87,19 @@ impl HomeAssistant {
         }
         let template_engine = Arc::new(template_engine);
 
-        let automation_engine = automation_engine::AutomationEngine::new(
+        let automation_engine = Arc::new(automation_engine::AutomationEngine::new(
             bus.clone(),
             states.clone(),
             services.clone(),
             template_engine.clone(),
-        );
+        ));
+
+        let script_engine = Arc::new(script_engine::ScriptEngine::new(
+            bus.clone(),
+            states.clone(),
+            services.clone(),
+            template_engine.clone(),
+        ));
 
         // Create config entries manager with storage
         let storage = Arc::new(Storage::new(config_dir));
@@ -126,6 +142,7 @@ impl HomeAssistant {
             bus,
             config_entries,
             registries,
+            script_engine,
             services,
             states,
             template_engine,
@@ -347,6 +364,8 @@ impl HomeAssistant {
         );
 
         // Register homeassistant.reload_config_entry service
+        let config_entries = self.config_entries.clone();
+        let registries = self.registries.clone();
         self.services.register_with_description(
             ServiceDescription {
                 domain: "homeassistant".to_string(),
@@ -357,9 +376,44 @@ impl HomeAssistant {
                 target: entity_target(),
                 supports_response: SupportsResponse::None,
             },
-            |_call: ServiceCall| async move {
-                info!("Reload config entry requested");
-                Ok(None)
+            move |call: ServiceCall| {
+                let config_entries = config_entries.clone();
+                let registries = registries.clone();
+                async move {
+                    // Entries can be named directly or through their entities
+                    let mut entry_ids: Vec<String> = call
+                        .service_data
+                        .get("entry_id")
+                        .and_then(|v| v.as_str())
+                        .map(|id| vec![id.to_string()])
+                        .unwrap_or_default();
+                    for entity_id in call.entity_ids() {
+                        if let Some(entry_id) = registries
+                            .entities
+                            .get(&entity_id)
+                            .and_then(|entry| entry.config_entry_id.clone())
+                        {
+                            if !entry_ids.contains(&entry_id) {
+                                entry_ids.push(entry_id);
+                            }
+                        }
+                    }
+                    if entry_ids.is_empty() {
+                        return Err(ServiceError::InvalidData(
+                            "No config entry to reload".to_string(),
+                        ));
+                    }
+
+                    let manager = config_entries.read().await;
+                    for entry_id in &entry_ids {
+                        info!("Reloading config entry {}", entry_id);
+                        manager
+                            .reload(entry_id)
+                            .await
+                            .map_err(|e| ServiceError::CallFailed(e.to_string()))?;
+                    }
+                    Ok(None)
+                }
             },
         );
 
@@ -453,14 +507,14 @@ impl HomeAssistant {
                     {
                         let parts: Vec<&str> = entity_id.splitn(2, '.').collect();
                         if parts.len() == 2 && parts[0] == "automation" {
-                            let automation_id = parts[1];
+                            let automation_id = automation_id_for(&manager, entity_id).await;
 
                             // Enable in automation manager
                             let manager_guard = manager.read().await;
-                            if manager_guard.get(automation_id).is_some() {
+                            if manager_guard.get(&automation_id).is_some() {
                                 drop(manager_guard);
                                 let manager_guard = manager.write().await;
-                                let _ = manager_guard.enable(automation_id);
+                                let _ = manager_guard.enable(&automation_id);
                             }
 
                             // Update entity state
@@ -503,14 +557,14 @@ impl HomeAssistant {
                     {
                         let parts: Vec<&str> = entity_id.splitn(2, '.').collect();
                         if parts.len() == 2 && parts[0] == "automation" {
-                            let automation_id = parts[1];
+                            let automation_id = automation_id_for(&manager, entity_id).await;
 
                             // Disable in automation manager
                             let manager_guard = manager.read().await;
-                            if manager_guard.get(automation_id).is_some() {
+                            if manager_guard.get(&automation_id).is_some() {
                                 drop(manager_guard);
                                 let manager_guard = manager.write().await;
-                                let _ = manager_guard.disable(automation_id);
+                                let _ = manager_guard.disable(&automation_id);
                             }
 
                             // Update entity state
@@ -553,14 +607,14 @@ impl HomeAssistant {
                     {
                         let parts: Vec<&str> = entity_id.splitn(2, '.').collect();
                         if parts.len() == 2 && parts[0] == "automation" {
-                            let automation_id = parts[1];
+                            let automation_id = automation_id_for(&manager, entity_id).await;
 
                             // Toggle in automation manager and get new state
                             let manager_guard = manager.read().await;
-                            let new_enabled = if manager_guard.get(automation_id).is_some() {
+                            let new_enabled = if manager_guard.get(&automation_id).is_some() {
                                 drop(manager_guard);
                                 let manager_guard = manager.write().await;
-                                manager_guard.toggle(automation_id).unwrap_or(true)
+                                manager_guard.toggle(&automation_id).unwrap_or(true)
                             } else {
                                 // Automation not in manager, toggle based on entity state
                                 if let Some(state) = states.get(entity_id) {
@@ -590,7 +644,7 @@ impl HomeAssistant {
         );
 
         // Register automation.trigger service - manually trigger an automation
-        let manager_clone = manager.clone();
+        let engine_clone = self.automation_engine.clone();
         self.services.register_with_description(
             ServiceDescription {
                 domain: "automation".to_string(),
@@ -602,26 +656,25 @@ impl HomeAssistant {
                 supports_response: SupportsResponse::None,
             },
             move |call: ServiceCall| {
-                let manager = manager_clone.clone();
+                let engine = engine_clone.clone();
                 async move {
                     if let Some(entity_id) =
                         call.service_data.get("entity_id").and_then(|v| v.as_str())
                     {
                         let parts: Vec<&str> = entity_id.splitn(2, '.').collect();
                         if parts.len() == 2 && parts[0] == "automation" {
-                            let automation_id = parts[1];
-                            let manager_guard = manager.read().await;
-                            if let Some(automation) = manager_guard.get(automation_id) {
-                                info!(
-                                    "Triggering automation: {} ({})",
-                                    automation.display_name(),
-                                    automation_id
-                                );
-                                // Note: Full trigger would require access to the AutomationEngine
-                                // For now, log the trigger request
-                            } else {
-                                warn!("Automation not found: {}", automation_id);
-                            }
+                            let automation_id =
+                                automation_id_for(&engine.manager(), entity_id).await;
+                            // Like HA, conditions are skipped unless asked otherwise
+                            let skip_condition = call
+                                .service_data
+                                .get("skip_condition")
+                                .and_then(|v| v.as_bool())
+                                .unwrap_or(true);
+                            info!("Triggering automation: {}", automation_id);
+                            engine
+                                .trigger(&automation_id, None, skip_condition, &call.context)
+                                .await;
                         }
                     }
                     Ok(None)
@@ -629,7 +682,21 @@ impl HomeAssistant {
             },
         );
 
-        // Register automation.reload service
+        info!("Automation services registered");
+    }
+
+    /// Register `automation.reload`, re-reading automations from `config_dir`
+    ///
+    /// Entities of removed automations are removed, those of new and changed
+    /// ones (re)created from the configuration.
+    fn register_automation_reload_service(
+        &self,
+        config_dir: &Path,
+        restore_state: Arc<ha_components::RestoreStateStore>,
+    ) {
+        let config_dir = config_dir.to_path_buf();
+        let engine = self.automation_engine.clone();
+        let states = self.states.clone();
         self.services.register_with_description(
             ServiceDescription {
                 domain: "automation".to_string(),
@@ -640,20 +707,45 @@ impl HomeAssistant {
                 target: None,
                 supports_response: SupportsResponse::None,
             },
-            |_call: ServiceCall| async move {
-                // Note: Full reload would require reloading from config
-                // For now, just log the request
-                info!("Reloading automations (not fully implemented)");
-                Ok(None)
+            move |call: ServiceCall| {
+                let config_dir = config_dir.clone();
+                let engine = engine.clone();
+                let states = states.clone();
+                let restore_state = restore_state.clone();
+                async move {
+                    let configs = load_automations(&config_dir);
+                    let summary = engine.reload(configs).await;
+
+                    for automation in &summary.removed {
+                        states.remove(&automation.entity_id(), call.context.clone());
+                    }
+                    let changed: Vec<Automation> = {
+                        let manager = engine.manager();
+                        let manager = manager.read().await;
+                        summary
+                            .added
+                            .iter()
+                            .chain(&summary.updated)
+                            .filter_map(|id| manager.get(id))
+                            .collect()
+                    };
+                    create_automation_entities(&states, &changed, &restore_state);
+
+                    info!(
+                        "Reloaded automations: {} added, {} removed, {} updated",
+                        summary.added.len(),
+                        summary.removed.len(),
+                        summary.updated.len()
+                    );
+                    Ok(None)
+                }
             },
         );
-
-        info!("Automation services registered");
     }
 
     /// Register script domain services
     fn register_script_services(&self) {
-        let states = self.states.clone();
+        let engine = self.script_engine.clone();
 
         // Helper for script entity target
         let script_target = || {
@@ -664,8 +756,8 @@ impl HomeAssistant {
             }))
         };
 
-        // Register script.turn_on service - run the script
-        let states_clone = states.clone();
+        // Register script.turn_on service - start the script in the background
+        let engine_clone = engine.clone();
         self.services.register_with_description(
             ServiceDescription {
                 domain: "script".to_string(),
@@ -677,25 +769,23 @@ impl HomeAssistant {
                 supports_response: SupportsResponse::None,
             },
             move |call: ServiceCall| {
-                let states = states_clone.clone();
+                let engine = engine_clone.clone();
                 async move {
-                    if let Some(entity_id) =
-                        call.service_data.get("entity_id").and_then(|v| v.as_str())
+                    if let Some(script_id) = call
+                        .service_data
+                        .get("entity_id")
+                        .and_then(|v| v.as_str())
+                        .and_then(|entity_id| engine.id_for_entity(entity_id))
                     {
-                        if let Some(state) = states.get(entity_id) {
-                            let parts: Vec<&str> = entity_id.splitn(2, '.').collect();
-                            if parts.len() == 2 && parts[0] == "script" {
-                                if let Ok(entity) = EntityId::new(parts[0], parts[1]) {
-                                    // Set to "on" while running (scripts run once then go to off)
-                                    states.set(
-                                        entity,
-                                        "on",
-                                        state.attributes.clone(),
-                                        Context::new(),
-                                    );
-                                }
-                            }
-                        }
+                        let variables = call
+                            .service_data
+                            .get("variables")
+                            .and_then(|v| v.as_object())
+                            .cloned()
+                            .unwrap_or_default();
+                        engine
+                            .turn_on(&script_id, variables, &call.context)
+                            .map_err(script_service_error)?;
                     }
                     Ok(None)
                 }
@@ -703,7 +793,7 @@ impl HomeAssistant {
         );
 
         // Register script.turn_off service - stop the script
-        let states_clone = states.clone();
+        let engine_clone = engine.clone();
         self.services.register_with_description(
             ServiceDescription {
                 domain: "script".to_string(),
@@ -715,24 +805,15 @@ impl HomeAssistant {
                 supports_response: SupportsResponse::None,
             },
             move |call: ServiceCall| {
-                let states = states_clone.clone();
+                let engine = engine_clone.clone();
                 async move {
-                    if let Some(entity_id) =
-                        call.service_data.get("entity_id").and_then(|v| v.as_str())
+                    if let Some(script_id) = call
+                        .service_data
+                        .get("entity_id")
+                        .and_then(|v| v.as_str())
+                        .and_then(|entity_id| engine.id_for_entity(entity_id))
                     {
-                        if let Some(state) = states.get(entity_id) {
-                            let parts: Vec<&str> = entity_id.splitn(2, '.').collect();
-                            if parts.len() == 2 && parts[0] == "script" {
-                                if let Ok(entity) = EntityId::new(parts[0], parts[1]) {
-                                    states.set(
-                                        entity,
-                                        "off",
-                                        state.attributes.clone(),
-                                        Context::new(),
-                                    );
-                                }
-                            }
-                        }
+                        engine.turn_off(&script_id);
                     }
                     Ok(None)
                 }
@@ -740,7 +821,7 @@ impl HomeAssistant {
         );
 
         // Register script.toggle service
-        let states_clone = states.clone();
+        let engine_clone = engine.clone();
         self.services.register_with_description(
             ServiceDescription {
                 domain: "script".to_string(),
@@ -752,24 +833,20 @@ impl HomeAssistant {
                 supports_response: SupportsResponse::None,
             },
             move |call: ServiceCall| {
-                let states = states_clone.clone();
+                let engine = engine_clone.clone();
                 async move {
-                    if let Some(entity_id) =
-                        call.service_data.get("entity_id").and_then(|v| v.as_str())
+                    if let Some(script_id) = call
+                        .service_data
+                        .get("entity_id")
+                        .and_then(|v| v.as_str())
+                        .and_then(|entity_id| engine.id_for_entity(entity_id))
                     {
-                        if let Some(state) = states.get(entity_id) {
-                            let parts: Vec<&str> = entity_id.splitn(2, '.').collect();
-                            if parts.len() == 2 && parts[0] == "script" {
-                                let new_state = if state.state == "on" { "off" } else { "on" };
-                                if let Ok(entity) = EntityId::new(parts[0], parts[1]) {
-                                    states.set(
-                                        entity,
-                                        new_state,
-                                        state.attributes.clone(),
-                                        Context::new(),
-                                    );
-                                }
-                            }
+                        if engine.is_running(&script_id) {
+                            engine.turn_off(&script_id);
+                        } else {
+                            engine
+                                .turn_on(&script_id, serde_json::Map::new(), &call.context)
+                                .map_err(script_service_error)?;
                         }
                     }
                     Ok(None)
@@ -987,15 +1064,38 @@ impl HomeAssistant {
 
 // Note: HomeAssistant no longer implements Default since new() requires config_dir
 
+/// Render a notification message or title if it contains a template
+///
+/// A template that fails to render is kept as-is so the notification still
+/// shows up.
+fn render_notification_text(template_engine: &TemplateEngine, text: String) -> String {
+    if !TemplateEngine::is_template(&text) {
+        return text;
+    }
+    match template_engine.render(&text) {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            warn!("Error rendering notification template '{}': {}", text, e);
+            text
+        }
+    }
+}
+
 /// Register persistent_notification services
+///
+/// `message` and `title` of created notifications are rendered as templates,
+/// and every change fires `persistent_notifications_updated` on `bus`.
 fn register_persistent_notification_services(
     services: &ServiceRegistry,
     notifications: Arc<persistent_notification::PersistentNotificationManager>,
+    bus: Arc<EventBus>,
+    template_engine: Arc<TemplateEngine>,
 ) {
     const DOMAIN: &str = persistent_notification::DOMAIN;
 
     // Register persistent_notification.create service
     let notifications_clone = notifications.clone();
+    let bus_clone = bus.clone();
     services.register_with_description(
         ServiceDescription {
             domain: DOMAIN.to_string(),
@@ -1012,6 +1112,8 @@ fn register_persistent_notification_services(
         },
         move |call: ServiceCall| {
             let notifications = notifications_clone.clone();
+            let bus = bus_clone.clone();
+            let template_engine = template_engine.clone();
             async move {
                 let message = call
                     .service_data
@@ -1019,12 +1121,13 @@ fn register_persistent_notification_services(
                     .and_then(|v| v.as_str())
                     .unwrap_or("")
                     .to_string();
+                let message = render_notification_text(&template_engine, message);
 
                 let title = call
                     .service_data
                     .get("title")
                     .and_then(|v| v.as_str())
-                    .map(|s| s.to_string());
+                    .map(|s| render_notification_text(&template_engine, s.to_string()));
 
                 let notification_id = call
                     .service_data
@@ -1033,7 +1136,14 @@ fn register_persistent_notification_services(
                     .map(|s| s.to_string())
                     .unwrap_or_else(|| ulid::Ulid::new().to_string().to_lowercase());
 
-                notifications.create(notification_id, message, title);
+                let (notification, update_type) =
+                    notifications.create(notification_id, message, title);
+                persistent_notification::fire_updated(
+                    &bus,
+                    update_type,
+                    &[notification],
+                    call.context,
+                );
                 Ok(None)
             }
         },
@@ -1041,6 +1151,7 @@ fn register_persistent_notification_services(
 
     // Register persistent_notification.dismiss service
     let notifications_clone = notifications.clone();
+    let bus_clone = bus.clone();
     services.register_with_description(
         ServiceDescription {
             domain: DOMAIN.to_string(),
@@ -1055,13 +1166,20 @@ fn register_persistent_notification_services(
         },
         move |call: ServiceCall| {
             let notifications = notifications_clone.clone();
+            let bus = bus_clone.clone();
             async move {
-                if let Some(notification_id) = call
+                let dismissed = call
                     .service_data
                     .get("notification_id")
                     .and_then(|v| v.as_str())
-                {
-                    notifications.dismiss(notification_id);
+                    .and_then(|notification_id| notifications.dismiss(notification_id));
+                if let Some(notification) = dismissed {
+                    persistent_notification::fire_updated(
+                        &bus,
+                        persistent_notification::UpdateType::Removed,
+                        &[notification],
+                        call.context,
+                    );
                 }
                 Ok(None)
             }
@@ -1080,10 +1198,19 @@ fn register_persistent_notification_services(
             target: None,
             supports_response: SupportsResponse::None,
         },
-        move |_call: ServiceCall| {
+        move |call: ServiceCall| {
             let notifications = notifications_clone.clone();
+            let bus = bus.clone();
             async move {
-                notifications.dismiss_all();
+                let dismissed = notifications.dismiss_all();
+                if !dismissed.is_empty() {
+                    persistent_notification::fire_updated(
+                        &bus,
+                        persistent_notification::UpdateType::Removed,
+                        &dismissed,
+                        call.context,
+                    );
+                }
                 Ok(None)
             }
         },
@@ -1114,12 +1241,19 @@ fn load_components(config_dir: &std::path::Path) -> Vec<String> {
     vec![
         "api".to_string(),
         "automation".to_string(),
+        "counter".to_string(),
+        "group".to_string(),
         "homeassistant".to_string(),
         "input_boolean".to_string(),
+        "input_datetime".to_string(),
         "input_number".to_string(),
+        "input_select".to_string(),
+        "input_text".to_string(),
         "persistent_notification".to_string(),
         "scene".to_string(),
+        "schedule".to_string(),
         "script".to_string(),
+        "timer".to_string(),
     ]
 }
 
@@ -1165,6 +1299,53 @@ fn load_events_cache(config_dir: &std::path::Path) -> Option<Arc<serde_json::Val
     None
 }
 
+/// Create the `automation.*` entities of the configured automations
+///
+/// Entities are tracked for restore state, so `last_triggered` survives
+/// restarts.
+fn create_automation_entities(
+    states: &StateStore,
+    automations: &[Automation],
+    restore_state: &ha_components::RestoreStateStore,
+) {
+    for automation in automations {
+        let entity_id = automation.entity_id();
+        let state = if automation.enabled { "on" } else { "off" };
+        // On reloads the live entity is more recent than the saved state
+        let last_triggered = states
+            .get(entity_id.as_str())
+            .or_else(|| restore_state.last_state(entity_id.as_str()))
+            .and_then(|saved| saved.attributes.get("last_triggered").cloned())
+            .unwrap_or(serde_json::Value::Null);
+        restore_state.track(&entity_id);
+
+        let mut attributes = HashMap::new();
+        if let Some(alias) = &automation.alias {
+            attributes.insert("friendly_name".to_string(), json!(alias));
+        }
+        attributes.insert("current".to_string(), json!(0));
+        attributes.insert("last_triggered".to_string(), last_triggered);
+        states.set(entity_id, state, attributes, Context::new());
+    }
+}
+
+/// ID of the automation owning an `automation.*` entity
+///
+/// Entities are named after a slug of the id, so the id is looked up; for
+/// entities of unknown automations the object id is used as is.
+async fn automation_id_for(manager: &RwLock<AutomationManager>, entity_id: &str) -> String {
+    manager
+        .read()
+        .await
+        .id_for_entity(entity_id)
+        .unwrap_or_else(|| {
+            entity_id
+                .split_once('.')
+                .map_or(entity_id, |(_, object_id)| object_id)
+                .to_string()
+        })
+}
+
 /// Load automations from configuration.yaml
 fn load_automations(config_dir: &Path) -> Vec<AutomationConfig> {
     let config_file = config_dir.join("configuration.yaml");
@@ -1216,8 +1397,14 @@ fn load_automations(config_dir: &Path) -> Vec<AutomationConfig> {
     }
 }
 
-/// Load input helpers (input_boolean, input_number) from configuration
-fn load_input_helpers(config_dir: &Path, states: &StateStore) {
+/// Load helpers (input_boolean, input_number, ..., counter, timer) from configuration
+///
+/// Helpers with `restore` enabled start from their state saved in `restore`.
+fn load_input_helpers(
+    config_dir: &Path,
+    states: &StateStore,
+    restore: &ha_components::RestoreStateStore,
+) {
     let config_file = config_dir.join("configuration.yaml");
 
     if !config_file.exists() {
@@ -1234,66 +1421,317 @@ fn load_input_helpers(config_dir: &Path, states: &StateStore) {
         }
     };
 
-    // Collect all input_boolean configs (from root and packages)
-    let mut all_input_booleans: HashMap<String, Option<ha_components::InputBooleanConfig>> =
-        HashMap::new();
-    let mut all_input_numbers: HashMap<String, ha_components::InputNumberConfig> = HashMap::new();
+    // Collect all configs (from root and packages)
+    let all_input_booleans: HashMap<String, Option<ha_components::InputBooleanConfig>> =
+        collect_domain_configs(&yaml, "input_boolean");
+    let all_input_numbers: HashMap<String, ha_components::InputNumberConfig> =
+        collect_domain_configs(&yaml, "input_number");
+    let all_input_selects: HashMap<String, ha_components::InputSelectConfig> =
+        collect_domain_configs(&yaml, "input_select");
+    let all_input_texts: HashMap<String, ha_components::InputTextConfig> =
+        collect_domain_configs(&yaml, "input_text");
+    let all_input_datetimes: HashMap<String, ha_components::InputDatetimeConfig> =
+        collect_domain_configs(&yaml, "input_datetime");
+    let all_counters: HashMap<String, ha_components::CounterConfig> =
+        collect_domain_configs(&yaml, "counter");
+    let all_timers: HashMap<String, ha_components::TimerConfig> =
+        collect_domain_configs(&yaml, "timer");
 
-    // Load from root level
-    if let Some(input_boolean_value) = yaml.get("input_boolean") {
-        if let Ok(configs) = serde_yaml::from_value::<
-            HashMap<String, Option<ha_components::InputBooleanConfig>>,
-        >(input_boolean_value.clone())
-        {
-            all_input_booleans.extend(configs);
+    // Load the collected configs
+    if !all_input_booleans.is_empty() {
+        ha_components::load_input_booleans(&all_input_booleans, states, Some(restore));
+    }
+
+    if !all_input_numbers.is_empty() {
+        ha_components::load_input_numbers(&all_input_numbers, states, Some(restore));
+    }
+
+    if !all_input_selects.is_empty() {
+        ha_components::load_input_selects(&all_input_selects, states, Some(restore));
+    }
+
+    if !all_input_texts.is_empty() {
+        ha_components::load_input_texts(&all_input_texts, states, Some(restore));
+    }
+
+    if !all_input_datetimes.is_empty() {
+        ha_components::load_input_datetimes(&all_input_datetimes, states, Some(restore));
+    }
+
+    if !all_counters.is_empty() {
+        ha_components::load_counters(&all_counters, states, Some(restore));
+    }
+
+    if !all_timers.is_empty() {
+        ha_components::load_timers(&all_timers, states);
+    }
+}
+
+/// Load groups from configuration and keep them in sync with their members
+fn load_groups(config_dir: &Path, bus: &EventBus, states: Arc<StateStore>) {
+    if !config_dir.join("configuration.yaml").exists() {
+        return;
+    }
+
+    let yaml = match ha_config::load_yaml(config_dir, "configuration.yaml") {
+        Ok(yaml) => yaml,
+        Err(e) => {
+            warn!("Failed to load configuration.yaml for groups: {}", e);
+            return;
         }
+    };
+
+    let groups: HashMap<String, ha_components::GroupConfig> =
+        collect_domain_configs(&yaml, "group");
+    if !groups.is_empty() {
+        ha_components::load_groups(&groups, bus, states);
     }
+}
 
-    if let Some(input_number_value) = yaml.get("input_number") {
-        if let Ok(configs) = serde_yaml::from_value::<
-            HashMap<String, ha_components::InputNumberConfig>,
-        >(input_number_value.clone())
-        {
-            all_input_numbers.extend(configs);
+/// Load template sensors and binary sensors from configuration
+fn load_template_entities(
+    config_dir: &Path,
+    bus: &EventBus,
+    states: Arc<StateStore>,
+    engine: Arc<TemplateEngine>,
+) {
+    if !config_dir.join("configuration.yaml").exists() {
+        return;
+    }
+
+    let yaml = match ha_config::load_yaml(config_dir, "configuration.yaml") {
+        Ok(yaml) => yaml,
+        Err(e) => {
+            warn!("Failed to load configuration.yaml for templates: {}", e);
+            return;
         }
+    };
+
+    let templates: Vec<ha_components::TemplateConfig> = collect_domain_list(&yaml, "template");
+    if !templates.is_empty() {
+        ha_components::load_template_entities(&templates, bus, states, engine);
     }
+}
 
-    // Load from packages (homeassistant.packages contains merged package content)
-    if let Some(homeassistant) = yaml.get("homeassistant") {
-        if let Some(packages) = homeassistant.get("packages") {
-            if let Some(packages_map) = packages.as_mapping() {
-                for (_, package_content) in packages_map {
-                    // Each package can have input_boolean and input_number sections
-                    if let Some(input_boolean_value) = package_content.get("input_boolean") {
-                        if let Ok(configs) = serde_yaml::from_value::<
-                            HashMap<String, Option<ha_components::InputBooleanConfig>>,
-                        >(input_boolean_value.clone())
-                        {
-                            all_input_booleans.extend(configs);
-                        }
-                    }
+/// Load the natively implemented `sensor:` platforms from configuration
+///
+/// Entries of other platforms are left alone; they belong to integrations
+/// set up elsewhere.
+fn load_sensor_platforms(config_dir: &Path, bus: &EventBus, states: Arc<StateStore>) {
+    if !config_dir.join("configuration.yaml").exists() {
+        return;
+    }
 
-                    if let Some(input_number_value) = package_content.get("input_number") {
-                        if let Ok(configs) = serde_yaml::from_value::<
-                            HashMap<String, ha_components::InputNumberConfig>,
-                        >(input_number_value.clone())
-                        {
-                            all_input_numbers.extend(configs);
-                        }
-                    }
-                }
+    let yaml = match ha_config::load_yaml(config_dir, "configuration.yaml") {
+        Ok(yaml) => yaml,
+        Err(e) => {
+            warn!("Failed to load configuration.yaml for sensors: {}", e);
+            return;
+        }
+    };
+
+    let sensors: Vec<serde_yaml::Value> = collect_domain_list(&yaml, "sensor");
+    fn parse<T: serde::de::DeserializeOwned>(
+        sensors: &[serde_yaml::Value],
+        platform: &str,
+    ) -> Vec<T> {
+        let configs = sensors
+            .iter()
+            .filter(|sensor| sensor.get("platform").and_then(|p| p.as_str()) == Some(platform))
+            .cloned()
+            .collect();
+        serde_yaml::from_value(serde_yaml::Value::Sequence(configs)).unwrap_or_else(|e| {
+            warn!("Invalid {} sensor configuration: {}", platform, e);
+            Vec::new()
+        })
+    }
+
+    let min_max: Vec<ha_components::MinMaxConfig> = parse(&sensors, "min_max");
+    if !min_max.is_empty() {
+        ha_components::load_min_max_sensors(&min_max, bus, states.clone());
+    }
+    let statistics: Vec<ha_components::StatisticsConfig> = parse(&sensors, "statistics");
+    if !statistics.is_empty() {
+        ha_components::load_statistics_sensors(&statistics, bus, states.clone());
+    }
+    let derivative: Vec<ha_components::DerivativeConfig> = parse(&sensors, "derivative");
+    if !derivative.is_empty() {
+        ha_components::load_derivative_sensors(&derivative, bus, states.clone());
+    }
+    let integration: Vec<ha_components::IntegrationConfig> = parse(&sensors, "integration");
+    if !integration.is_empty() {
+        ha_components::load_integration_sensors(&integration, bus, states);
+    }
+}
+
+/// Open the recorder database configured by the `recorder:` section
+fn open_recorder(config_dir: &Path, bus: &EventBus) -> Option<Arc<Recorder>> {
+    let config = ha_config::load_yaml(config_dir, "configuration.yaml")
+        .ok()
+        .and_then(|yaml| yaml.get("recorder").cloned())
+        .filter(|section| !section.is_null())
+        .map(|section| {
+            serde_yaml::from_value::<RecorderConfig>(section).unwrap_or_else(|e| {
+                warn!("Invalid recorder configuration, using defaults: {}", e);
+                RecorderConfig::default()
+            })
+        })
+        .unwrap_or_default();
+
+    let path = config_dir.join(ha_recorder::DEFAULT_DB_FILE);
+    match Recorder::open(&path, config) {
+        Ok(recorder) => {
+            recorder.attach(bus);
+            info!("Recording to {}", path.display());
+            Some(Arc::new(recorder))
+        }
+        Err(e) => {
+            warn!("Failed to open recorder database {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Load schedules from configuration and start tracking their windows
+fn load_schedules(config_dir: &Path, states: Arc<StateStore>) {
+    if !config_dir.join("configuration.yaml").exists() {
+        return;
+    }
+
+    let yaml = match ha_config::load_yaml(config_dir, "configuration.yaml") {
+        Ok(yaml) => yaml,
+        Err(e) => {
+            warn!("Failed to load configuration.yaml for schedules: {}", e);
+            return;
+        }
+    };
+
+    let schedules: HashMap<String, ha_components::ScheduleConfig> =
+        collect_domain_configs(&yaml, "schedule");
+    if !schedules.is_empty() {
+        ha_components::load_schedules(&schedules, states);
+    }
+}
+
+/// Load scenes from configuration and register `scene.turn_on` to apply them
+fn load_scenes(config_dir: &Path, services: &Arc<ServiceRegistry>, states: Arc<StateStore>) {
+    let yaml = if config_dir.join("configuration.yaml").exists() {
+        match ha_config::load_yaml(config_dir, "configuration.yaml") {
+            Ok(yaml) => yaml,
+            Err(e) => {
+                warn!("Failed to load configuration.yaml for scenes: {}", e);
+                serde_yaml::Value::Null
             }
         }
+    } else {
+        serde_yaml::Value::Null
+    };
+
+    let scenes: Vec<ha_components::SceneConfig> = collect_domain_list(&yaml, "scene");
+    ha_components::load_scenes(&scenes, services, states);
+}
+
+/// Load scripts from configuration and register a `script.<id>` service
+/// running each
+fn load_scripts(
+    config_dir: &Path,
+    services: &ServiceRegistry,
+    engine: Arc<script_engine::ScriptEngine>,
+) {
+    if !config_dir.join("configuration.yaml").exists() {
+        return;
     }
 
-    // Load the collected configs
-    if !all_input_booleans.is_empty() {
-        ha_components::load_input_booleans(&all_input_booleans, states);
+    let yaml = match ha_config::load_yaml(config_dir, "configuration.yaml") {
+        Ok(yaml) => yaml,
+        Err(e) => {
+            warn!("Failed to load configuration.yaml for scripts: {}", e);
+            return;
+        }
+    };
+
+    let configs: HashMap<String, ha_script::ScriptConfig> = collect_domain_configs(&yaml, "script");
+    for script_id in engine.load(configs) {
+        let engine = engine.clone();
+        let id = script_id.clone();
+        services.register(
+            "script",
+            &script_id,
+            move |call: ServiceCall| {
+                let engine = engine.clone();
+                let id = id.clone();
+                async move {
+                    let variables = call.service_data.as_object().cloned().unwrap_or_default();
+                    engine
+                        .run(&id, variables, &call.context)
+                        .await
+                        .map_err(script_service_error)
+                }
+            },
+            None,
+            SupportsResponse::Optional,
+        );
     }
+}
 
-    if !all_input_numbers.is_empty() {
-        ha_components::load_input_numbers(&all_input_numbers, states);
+/// Map a script error to the error of the service call that ran it
+fn script_service_error(e: ha_script::ScriptExecutorError) -> ServiceError {
+    match e {
+        ha_script::ScriptExecutorError::InvalidData(message) => ServiceError::InvalidData(message),
+        e => ServiceError::CallFailed(e.to_string()),
+    }
+}
+
+/// Collect the entity configs of a domain from the root level and packages
+///
+/// `homeassistant.packages` contains the merged package content; each
+/// package can have its own section for the domain.
+fn collect_domain_configs<T: serde::de::DeserializeOwned>(
+    yaml: &serde_yaml::Value,
+    domain: &str,
+) -> HashMap<String, T> {
+    let mut configs = HashMap::new();
+
+    let packages = yaml
+        .get("homeassistant")
+        .and_then(|ha| ha.get("packages"))
+        .and_then(|packages| packages.as_mapping());
+    let sections = std::iter::once(yaml)
+        .chain(packages.into_iter().flat_map(|map| map.values()))
+        .filter_map(|content| content.get(domain));
+
+    for section in sections {
+        match serde_yaml::from_value::<HashMap<String, T>>(section.clone()) {
+            Ok(parsed) => configs.extend(parsed),
+            Err(e) => warn!("Invalid {} configuration: {}", domain, e),
+        }
+    }
+    configs
+}
+
+/// Collect list-style configs of a domain (e.g. `scene:`) from the root
+/// level and packages
+fn collect_domain_list<T: serde::de::DeserializeOwned>(
+    yaml: &serde_yaml::Value,
+    domain: &str,
+) -> Vec<T> {
+    let mut configs = Vec::new();
+
+    let packages = yaml
+        .get("homeassistant")
+        .and_then(|ha| ha.get("packages"))
+        .and_then(|packages| packages.as_mapping());
+    let sections = std::iter::once(yaml)
+        .chain(packages.into_iter().flat_map(|map| map.values()))
+        .filter_map(|content| content.get(domain));
+
+    for section in sections {
+        match serde_yaml::from_value::<Vec<T>>(section.clone()) {
+            Ok(parsed) => configs.extend(parsed),
+            Err(e) => warn!("Invalid {} configuration: {}", domain, e),
+        }
     }
+    configs
 }
 
 /// Load and setup config entries
@@ -1321,6 +1759,7 @@ async fn setup_config_entries(hass: &HomeAssistant) {
             bus: hass.bus.clone(),
             states: hass.states.clone(),
             services: hass.services.clone(),
+            registries: hass.registries.clone(),
         };
         manager.set_context(context).await;
 
@@ -1393,23 +1832,148 @@ async fn setup_config_entries(hass: &HomeAssistant) {
     }
 }
 
-/// Register entity domain services for Python entities
+/// Start reauth flows for config entries through the config flow handler
 ///
-/// After Python integrations load entities, we need to register services like
-/// `light.turn_on`, `light.turn_off` etc. in the Rust ServiceRegistry so that
-/// service calls route to the Python entity methods.
-#[cfg(feature = "python")]
-fn register_python_entity_services(services: &ServiceRegistry) {
-    use ha_core::domains;
-    use std::collections::HashSet;
+/// Entries that failed auth before the handler existed (during startup
+/// setup) are started now. A flow that fails to start clears the entry's
+/// reauth marker so a later auth failure can try again.
+async fn wire_reauth_flows(
+    config_entries: &Arc<RwLock<ConfigEntries>>,
+    config_flow_handler: Arc<dyn ConfigFlowHandler>,
+) {
+    let runtime = tokio::runtime::Handle::current();
+    let entries = config_entries.clone();
+    config_entries
+        .read()
+        .await
+        .set_reauth_flow_handler(Arc::new(move |entry| {
+            let flow_id = ulid::Ulid::new().to_string().to_lowercase();
+            let handler = config_flow_handler.clone();
+            let entries = entries.clone();
+            let entry = entry.clone();
+            let id = flow_id.clone();
+            runtime.spawn(async move {
+                let started = handler.start_reauth_flow(&id, &entry).await;
+                let finished = match &started {
+                    Ok(result) => result.result_type != "form",
+                    Err(e) => {
+                        warn!("Failed to start reauth flow for {}: {}", entry.entry_id, e);
+                        true
+                    }
+                };
+                if finished {
+                    entries.read().await.reauth_finished(&entry.entry_id);
+                }
+            });
+            Ok(flow_id)
+        }));
+}
 
-    // Get all Python entities and extract unique domains
-    let domains: HashSet<String> = match get_python_entities() {
-        Ok(entities) => entities
-            .iter()
-            .filter_map(|entity_id| entity_id.split('.').next().map(String::from))
-            .collect(),
-        Err(e) => {
+/// Register a domain service that runs on each entity it targets
+///
+/// The call's `entity_id`, `device_id`, `area_id`, `floor_id` and
+/// `label_id` targets are resolved through the registries, then
+/// `call_entity(entity_id, service, service_data)` runs once per targeted
+/// entity of `domain`, without the target keys in the data. It returns
+/// whether the entity supports the service.
+#[cfg_attr(not(feature = "python"), allow(dead_code))]
+fn register_entity_service<F>(
+    services: &ServiceRegistry,
+    registries: Arc<Registries>,
+    domain: &str,
+    service: &str,
+    call_entity: F,
+) where
+    F: Fn(&str, &str, serde_json::Value) -> Result<bool, String> + Send + Sync + 'static,
+{
+    let call_entity = Arc::new(call_entity);
+    let domain_clone = domain.to_string();
+    let service_clone = service.to_string();
+
+    services.register_with_description(
+        ServiceDescription {
+            domain: domain.to_string(),
+            service: service.to_string(),
+            name: None,
+            description: Some(format!("Call {} on {} entities", service, domain)),
+            schema: None,
+            target: Some(json!({
+                "entity": {
+                    "domain": domain
+                }
+            })),
+            supports_response: SupportsResponse::None,
+        },
+        move |call: ServiceCall| {
+            let domain = domain_clone.clone();
+            let service = service_clone.clone();
+            let registries = registries.clone();
+            let call_entity = call_entity.clone();
+            async move {
+                let entity_ids = registries.resolve_target(&call.service_data);
+                if entity_ids.is_empty() {
+                    warn!("Service {}.{} called without a target", domain, service);
+                    return Ok(None);
+                }
+
+                // Build service data without the targets
+                let mut service_data = call.service_data.clone();
+                if let Some(obj) = service_data.as_object_mut() {
+                    for key in ["entity_id", "device_id", "area_id", "floor_id", "label_id"] {
+                        obj.remove(key);
+                    }
+                }
+
+                // Call the service on each entity of this domain
+                let prefix = format!("{}.", domain);
+                for entity_id in entity_ids.iter().filter(|id| id.starts_with(&prefix)) {
+                    debug!(
+                        "Calling entity service: {}.{} on {}",
+                        domain, service, entity_id
+                    );
+
+                    match call_entity(entity_id, &service, service_data.clone()) {
+                        Ok(true) => {
+                            debug!("Service {}.{} succeeded on {}", domain, service, entity_id);
+                        }
+                        Ok(false) => {
+                            warn!(
+                                "Service {}.{} not supported on {}",
+                                domain, service, entity_id
+                            );
+                        }
+                        Err(e) => {
+                            warn!(
+                                "Error calling {}.{} on {}: {}",
+                                domain, service, entity_id, e
+                            );
+                        }
+                    }
+                }
+
+                Ok(None)
+            }
+        },
+    );
+}
+
+/// Register entity domain services for Python entities
+///
+/// After Python integrations load entities, we need to register services like
+/// `light.turn_on`, `light.turn_off` etc. in the Rust ServiceRegistry so that
+/// service calls route to the Python entity methods.
+#[cfg(feature = "python")]
+fn register_python_entity_services(services: &ServiceRegistry, registries: &Arc<Registries>) {
+    use ha_core::domains;
+    use std::collections::HashSet;
+
+    // Get all Python entities and extract unique domains
+    let domains: HashSet<String> = match get_python_entities() {
+        Ok(entities) => entities
+            .iter()
+            .filter_map(|entity_id| entity_id.split('.').next().map(String::from))
+            .collect(),
+        Err(e) => {
             warn!("Failed to get Python entities: {}", e);
             return;
         }
@@ -1436,99 +2000,23 @@ fn register_python_entity_services(services: &ServiceRegistry) {
             }
         };
 
+        // Calls to one entity, e.g. a brightness ramp, run in order
+        services.serialize_entity_calls(domain.as_str());
+
         for service_name in services_list {
             // Skip if already registered
             if services.has_service(domain, service_name) {
                 continue;
             }
 
-            let domain_clone = domain.clone();
-            let service_clone = service_name.to_string();
-
-            services.register_with_description(
-                ServiceDescription {
-                    domain: domain.clone(),
-                    service: service_name.to_string(),
-                    name: None,
-                    description: Some(format!(
-                        "Call {} on {} entities (Python)",
-                        service_name, domain
-                    )),
-                    schema: None,
-                    target: Some(json!({
-                        "entity": {
-                            "domain": domain
-                        }
-                    })),
-                    supports_response: SupportsResponse::None,
-                },
-                move |call: ServiceCall| {
-                    let domain = domain_clone.clone();
-                    let service = service_clone.clone();
-                    async move {
-                        // Extract entity_id from service data
-                        let entity_ids: Vec<String> =
-                            if let Some(entity_id) = call.service_data.get("entity_id") {
-                                if let Some(s) = entity_id.as_str() {
-                                    vec![s.to_string()]
-                                } else if let Some(arr) = entity_id.as_array() {
-                                    arr.iter()
-                                        .filter_map(|v| v.as_str().map(String::from))
-                                        .collect()
-                                } else {
-                                    vec![]
-                                }
-                            } else {
-                                vec![]
-                            };
-
-                        if entity_ids.is_empty() {
-                            warn!("Service {}.{} called without entity_id", domain, service);
-                            return Ok(None);
-                        }
-
-                        // Call the service on each entity
-                        for entity_id in entity_ids {
-                            // Verify the entity belongs to this domain
-                            if !entity_id.starts_with(&format!("{}.", domain)) {
-                                continue;
-                            }
-
-                            debug!(
-                                "Calling Python entity service: {}.{} on {}",
-                                domain, service, entity_id
-                            );
-
-                            // Build service data without entity_id
-                            let mut service_data = call.service_data.clone();
-                            if let Some(obj) = service_data.as_object_mut() {
-                                obj.remove("entity_id");
-                            }
-
-                            match call_python_entity_service(&entity_id, &service, service_data) {
-                                Ok(true) => {
-                                    debug!(
-                                        "Service {}.{} succeeded on {}",
-                                        domain, service, entity_id
-                                    );
-                                }
-                                Ok(false) => {
-                                    warn!(
-                                        "Service {}.{} not supported on {}",
-                                        domain, service, entity_id
-                                    );
-                                }
-                                Err(e) => {
-                                    warn!(
-                                        "Error calling {}.{} on {}: {}",
-                                        domain, service, entity_id, e
-                                    );
-                                }
-                            }
-                        }
-
-                        Ok(None)
-                    }
+            register_entity_service(
+                services,
+                registries.clone(),
+                domain,
+                service_name,
+                |entity_id, service, service_data| {
+                    call_python_entity_service(entity_id, service, service_data)
+                        .map_err(|e| e.to_string())
                 },
             );
 
@@ -1538,7 +2026,7 @@ fn register_python_entity_services(services: &ServiceRegistry) {
 }
 
 #[cfg(not(feature = "python"))]
-fn register_python_entity_services(_services: &ServiceRegistry) {
+fn register_python_entity_services(_services: &ServiceRegistry, _registries: &Arc<Registries>) {
     // No-op when Python is not enabled
 }
 
@@ -1580,12 +2068,29 @@ async fn main() -> Result<()> {
     };
 
     // Create registries before HomeAssistant so Python bridge can use them
-    let registries = Arc::new(Registries::new(&config_dir));
+    let bus = Arc::new(EventBus::new());
+    let registries = Arc::new(Registries::with_event_bus(&config_dir, bus.clone()));
     if let Err(e) = registries.load_all().await {
         warn!("Failed to load registries: {}", e);
     }
+    let registry_save_task = registries.spawn_save_task(ha_registries::SAVE_DELAY);
+
+    let hass = HomeAssistant::new(&config_dir, bus, registries);
+    if let Err(e) = hass.template_engine.set_core_config(&config) {
+        warn!("Failed to apply core config to templates: {}", e);
+    }
 
-    let hass = HomeAssistant::new(&config_dir, registries);
+    // Move states along when entities are renamed in the registry
+    ha_api::entity_rename::attach(&hass.bus, hass.states.clone());
+
+    // Record states and events to SQLite, or keep history in memory when
+    // the database can't be opened
+    let recorder = open_recorder(&config_dir, &hass.bus);
+    let history = recorder.is_none().then(|| {
+        let history = Arc::new(StateHistory::new());
+        history.attach(&hass.bus);
+        history
+    });
 
     // Register core services
     hass.register_core_services();
@@ -1597,9 +2102,34 @@ async fn main() -> Result<()> {
     // Register input helper services
     ha_components::register_input_boolean_services(&hass.services, hass.states.clone());
     ha_components::register_input_number_services(&hass.services, hass.states.clone());
+    ha_components::register_input_select_services(&hass.services, hass.states.clone());
+    ha_components::register_input_text_services(&hass.services, hass.states.clone());
+    ha_components::register_input_datetime_services(&hass.services, hass.states.clone());
+    ha_components::register_counter_services(&hass.services, hass.states.clone());
+    ha_components::register_timer_services(&hass.services, hass.states.clone(), hass.bus.clone());
 
     // Load input helpers from configuration
-    load_input_helpers(&config_dir, &hass.states);
+    let restore_state = Arc::new(ha_components::RestoreStateStore::new(
+        hass.registries.storage.clone(),
+    ));
+    if let Err(e) = restore_state.load().await {
+        warn!("Failed to load restore state: {}", e);
+    }
+    restore_state.attach(&hass.bus);
+    hass.register_automation_reload_service(&config_dir, restore_state.clone());
+    let restore_save_task = restore_state.spawn_save_task(ha_registries::SAVE_DELAY);
+    load_input_helpers(&config_dir, &hass.states, &restore_state);
+    load_groups(&config_dir, &hass.bus, hass.states.clone());
+    load_sensor_platforms(&config_dir, &hass.bus, hass.states.clone());
+    load_template_entities(
+        &config_dir,
+        &hass.bus,
+        hass.states.clone(),
+        hass.template_engine.clone(),
+    );
+    load_schedules(&config_dir, hass.states.clone());
+    load_scenes(&config_dir, &hass.services, hass.states.clone());
+    load_scripts(&config_dir, &hass.services, hass.script_engine.clone());
 
     // Load entities from config or use demo entities
     hass.load_entities(&config_dir);
@@ -1623,30 +2153,15 @@ async fn main() -> Result<()> {
     // Load automations from configuration
     let automation_configs = load_automations(&config_dir);
     if !automation_configs.is_empty() {
-        // Create automation entities in state machine
-        for config in &automation_configs {
-            let automation_id = config
-                .id
-                .clone()
-                .unwrap_or_else(|| config.alias.clone().unwrap_or_default());
-            if !automation_id.is_empty() {
-                let entity_id = EntityId::new("automation", &automation_id).unwrap();
-                let state = if config.enabled { "on" } else { "off" };
-                let mut attributes = HashMap::new();
-                if let Some(alias) = &config.alias {
-                    attributes.insert("friendly_name".to_string(), json!(alias));
-                }
-                hass.states
-                    .set(entity_id, state, attributes, Context::new());
-            }
-        }
-
         // Load automations into the engine
         let manager = hass.automation_engine.manager();
         let manager_guard = manager.write().await;
         if let Err(e) = manager_guard.load(automation_configs) {
             warn!("Failed to load automations into engine: {}", e);
         }
+
+        // Create automation entities in state machine
+        create_automation_entities(&hass.states, &manager_guard.all(), &restore_state);
     }
 
     // Start the automation engine
@@ -1657,10 +2172,16 @@ async fn main() -> Result<()> {
 
     // Register Python entity domain services (light.turn_on, etc.)
     // This must happen after config entries are set up so Python entities are registered
-    register_python_entity_services(&hass.services);
+    register_python_entity_services(&hass.services, &hass.registries);
 
     info!("Home Assistant initialized");
 
+    // Start automations run now, once entities and integrations are loaded
+    hass.bus
+        .fire(Event::new(HOMEASSISTANT_START, json!({}), Context::new()));
+    hass.bus
+        .fire(Event::new(HOMEASSISTANT_STARTED, json!({}), Context::new()));
+
     // Configure frontend if HA_FRONTEND_PATH is set
     let frontend_config = std::env::var("HA_FRONTEND_PATH").ok().and_then(|path| {
         let frontend_path = PathBuf::from(&path);
@@ -1693,7 +2214,12 @@ async fn main() -> Result<()> {
     let notifications = persistent_notification::create_manager();
 
     // Register persistent_notification services
-    register_persistent_notification_services(&hass.services, notifications.clone());
+    register_persistent_notification_services(
+        &hass.services,
+        notifications.clone(),
+        hass.bus.clone(),
+        hass.template_engine.clone(),
+    );
 
     // Create system log manager
     let system_log = Arc::new(SystemLog::with_defaults());
@@ -1704,25 +2230,74 @@ async fn main() -> Result<()> {
     // Create application credentials store (shared between API and config flow handler)
     let application_credentials = ha_api::new_application_credentials_store();
 
-    // Create config flow handler for Python integration setup (only with python feature)
+    // Create config and options flow handlers for Python integrations (only with python feature)
     #[cfg(feature = "python")]
-    let config_flow_handler: Option<Arc<dyn ConfigFlowHandler>> =
-        hass.python_bridge
-            .as_ref()
-            .map(|bridge| -> Arc<dyn ConfigFlowHandler> {
-                Arc::new(ConfigFlowManager::new(
-                    hass.bus.clone(),
-                    hass.states.clone(),
-                    hass.services.clone(),
-                    hass.registries.clone(),
-                    Some(config_dir.clone()),
-                    bridge.async_bridge.clone(),
-                    application_credentials.clone(),
-                    bridge.requirements.clone(),
-                ))
-            });
+    let config_flow_manager: Option<Arc<ConfigFlowManager>> =
+        hass.python_bridge.as_ref().map(|bridge| {
+            Arc::new(ConfigFlowManager::new(
+                hass.bus.clone(),
+                hass.states.clone(),
+                hass.services.clone(),
+                hass.registries.clone(),
+                Some(config_dir.clone()),
+                bridge.async_bridge.clone(),
+                application_credentials.clone(),
+                bridge.requirements.clone(),
+            ))
+        });
+    #[cfg(feature = "python")]
+    let config_flow_handler: Option<Arc<dyn ConfigFlowHandler>> = config_flow_manager
+        .clone()
+        .map(|manager| -> Arc<dyn ConfigFlowHandler> { manager });
     #[cfg(not(feature = "python"))]
     let config_flow_handler: Option<Arc<dyn ConfigFlowHandler>> = None;
+    #[cfg(feature = "python")]
+    let options_flow_handler: Option<Arc<dyn OptionsFlowHandler>> =
+        config_flow_manager.map(|manager| -> Arc<dyn OptionsFlowHandler> { manager });
+    #[cfg(not(feature = "python"))]
+    let options_flow_handler: Option<Arc<dyn OptionsFlowHandler>> = None;
+    if let Some(handler) = &config_flow_handler {
+        wire_reauth_flows(&hass.config_entries, handler.clone()).await;
+    }
+
+    let mut auth_state = AuthState::new_onboarded().with_storage(hass.registries.storage.clone());
+    // Clients in trusted networks may log in without a password
+    if let Some(provider) = config
+        .auth_providers
+        .iter()
+        .find(|provider| provider["type"] == "trusted_networks")
+    {
+        match serde_yaml::from_value::<TrustedNetworksConfig>(provider.clone()) {
+            Ok(trusted) => auth_state = auth_state.with_trusted_networks(trusted),
+            Err(e) => warn!("Invalid trusted_networks auth provider: {}", e),
+        }
+    }
+    // Known tokens for test harnesses and scripted setups
+    for token in &config.auth_access_tokens {
+        match serde_yaml::from_value::<ConfiguredAccessToken>(token.clone()) {
+            Ok(token) => auth_state = auth_state.with_access_token(&token.token, &token.user_id),
+            Err(e) => warn!("Invalid auth_access_tokens entry: {}", e),
+        }
+    }
+    // Users without permissions configured are admins; broken permissions
+    // leave the user read-only rather than unrestricted
+    for (user_id, permissions) in &config.auth_permissions {
+        let permissions = serde_yaml::from_value::<UserPermissions>(permissions.clone())
+            .unwrap_or_else(|e| {
+                warn!("Invalid auth_permissions for {}: {}", user_id, e);
+                UserPermissions::read_only()
+            });
+        auth_state.set_user_permissions(user_id, permissions);
+    }
+    // Long-lived access tokens created in earlier runs stay valid
+    if let Err(e) = auth_state.load().await {
+        warn!("Failed to load long-lived access tokens: {}", e);
+    }
+    hass.services
+        .add_interceptor(ha_api::permissions::service_interceptor(
+            auth_state.clone(),
+            hass.registries.clone(),
+        ));
 
     // Create API state with auth (mark as onboarded for dev mode)
     let api_state = AppState {
@@ -1733,15 +2308,21 @@ async fn main() -> Result<()> {
         components: Arc::new(components),
         config_entries: hass.config_entries.clone(),
         registries: hass.registries.clone(),
+        template_engine: hass.template_engine.clone(),
+        history,
+        recorder,
         notifications,
         system_log,
         services_cache,
         events_cache,
         frontend_config,
-        auth_state: AuthState::new_onboarded(),
+        auth_state,
         config_flow_handler,
+        options_flow_handler,
         application_credentials,
         components_path,
+        diagnostics: ha_api::diagnostics::new_diagnostics_providers(),
+        websocket_connections: Default::default(),
     };
 
     // Start API server
@@ -1762,9 +2343,21 @@ async fn main() -> Result<()> {
         }
     }
 
-    // Stop the automation engine
+    // Let shutdown automations see the stop before the engine goes down
+    hass.bus
+        .fire(Event::new(HOMEASSISTANT_STOP, json!({}), Context::new()));
     hass.automation_engine.stop();
 
+    // Write out registry and helper state changes still waiting for the save delay
+    registry_save_task.abort();
+    if let Err(e) = hass.registries.save_all().await {
+        warn!("Failed to save registries: {}", e);
+    }
+    restore_save_task.abort();
+    if let Err(e) = restore_state.save().await {
+        warn!("Failed to save restore state: {}", e);
+    }
+
     info!("Home Assistant stopped");
 
     Ok(())
@@ -1779,8 +2372,9 @@ mod tests {
 
     /// Helper to create a HomeAssistant instance with test registries
     fn create_test_hass(temp_dir: &TempDir) -> HomeAssistant {
-        let registries = Arc::new(Registries::new(temp_dir.path()));
-        HomeAssistant::new(temp_dir.path(), registries)
+        let bus = Arc::new(EventBus::new());
+        let registries = Arc::new(Registries::with_event_bus(temp_dir.path(), bus.clone()));
+        HomeAssistant::new(temp_dir.path(), bus, registries)
     }
 
     #[test]
@@ -1878,6 +2472,70 @@ script: []
         assert!(!hass.automation_engine.is_running());
     }
 
+    #[tokio::test]
+    async fn test_notification_create_renders_template() {
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+        let notifications = persistent_notification::create_manager();
+        register_persistent_notification_services(
+            &hass.services,
+            notifications.clone(),
+            hass.bus.clone(),
+            hass.template_engine.clone(),
+        );
+
+        let updates = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let updates_clone = updates.clone();
+        hass.bus.listen_sync(
+            persistent_notification::EVENT_NOTIFICATIONS_UPDATED,
+            Arc::new(move |event| {
+                updates_clone
+                    .lock()
+                    .unwrap()
+                    .push(event.data["update_type"].clone());
+            }),
+        );
+
+        hass.states.set(
+            EntityId::new("sensor", "washer").unwrap(),
+            "done",
+            HashMap::new(),
+            Context::new(),
+        );
+        let data = json!({
+            "notification_id": "laundry",
+            "title": "Washer {{ states('sensor.washer') }}",
+            "message": "The washer is {{ states('sensor.washer') }}, 50% {plain}",
+        });
+        hass.services
+            .call(
+                "persistent_notification",
+                "create",
+                data,
+                Context::new(),
+                false,
+            )
+            .await
+            .unwrap();
+
+        let notification = notifications.get("laundry").unwrap();
+        assert_eq!(notification.title.as_deref(), Some("Washer done"));
+        assert_eq!(notification.message, "The washer is done, 50% {plain}");
+
+        let data = json!({"notification_id": "laundry"});
+        hass.services
+            .call(
+                "persistent_notification",
+                "dismiss",
+                data,
+                Context::new(),
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(*updates.lock().unwrap(), [json!("added"), json!("removed")]);
+    }
+
     #[tokio::test]
     async fn test_automation_engine_start_stop() {
         let temp_dir = TempDir::new().unwrap();
@@ -2355,4 +3013,757 @@ script: []
 
         hass.automation_engine.stop();
     }
+
+    /// Load an automation firing `ping` on `ping`, counting its runs
+    async fn setup_ping_automation(
+        hass: &HomeAssistant,
+        actions: Vec<serde_json::Value>,
+    ) -> Arc<std::sync::atomic::AtomicUsize> {
+        use ha_automation::trigger::{EventTrigger, Trigger};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        hass.services.register(
+            "test",
+            "count",
+            move |_call| {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Ok(None)
+                }
+            },
+            None,
+            SupportsResponse::None,
+        );
+
+        let configs = vec![AutomationConfig {
+            id: Some("ping".to_string()),
+            alias: None,
+            description: None,
+            triggers: vec![Trigger::Event(EventTrigger {
+                id: None,
+                event_type: "ping".to_string(),
+                event_data: None,
+                context: None,
+            })],
+            conditions: vec![],
+            actions,
+            mode: ha_automation::ExecutionMode::Parallel { max: 100 },
+            max: None,
+            enabled: true,
+            variables: serde_json::Value::Null,
+            trace: None,
+        }];
+        hass.automation_engine
+            .manager()
+            .write()
+            .await
+            .load(configs)
+            .unwrap();
+        hass.automation_engine.start().await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        runs
+    }
+
+    #[tokio::test]
+    async fn test_deep_context_chain_blocks_automation() {
+        use ha_core::{Event, MAX_CONTEXT_DEPTH};
+        use std::sync::atomic::Ordering;
+
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+        let runs = setup_ping_automation(&hass, vec![json!({"service": "test.count"})]).await;
+
+        let mut context = Context::new();
+        for _ in 0..MAX_CONTEXT_DEPTH {
+            context = Context::child_of(&context);
+        }
+        assert_eq!(context.depth(), MAX_CONTEXT_DEPTH);
+        assert!(!context.exceeds_depth(MAX_CONTEXT_DEPTH));
+
+        // At the limit the automation still runs
+        hass.bus
+            .fire(Event::new("ping", json!({}), context.clone()));
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // One level deeper trips the loop guard
+        let context = Context::child_of(&context);
+        assert!(context.exceeds_depth(MAX_CONTEXT_DEPTH));
+        hass.bus.fire(Event::new("ping", json!({}), context));
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        hass.automation_engine.stop();
+    }
+
+    #[tokio::test]
+    async fn test_self_triggering_automation_stops() {
+        use ha_core::Event;
+        use std::sync::atomic::Ordering;
+
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+        let runs = setup_ping_automation(
+            &hass,
+            vec![json!({"service": "test.count"}), json!({"event": "ping"})],
+        )
+        .await;
+
+        hass.bus.fire(Event::new("ping", json!({}), Context::new()));
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+
+        // The ping fired by the run itself doesn't trigger it again
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        hass.automation_engine.stop();
+    }
+
+    #[tokio::test]
+    async fn test_light_ping_pong_runs_once_per_trigger() {
+        use ha_automation::trigger::{EntityIdSpec, StateMatch, StateTrigger, Trigger};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+        let porch = EntityId::new("light", "porch").unwrap();
+
+        // A light that drops straight back to off when turned on
+        let turn_ons = Arc::new(AtomicUsize::new(0));
+        let counter = turn_ons.clone();
+        let states = hass.states.clone();
+        let light = porch.clone();
+        hass.services.register(
+            "light",
+            "turn_on",
+            move |call: ServiceCall| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                states.set(light.clone(), "on", HashMap::new(), call.context.clone());
+                states.set(light.clone(), "off", HashMap::new(), call.context);
+                async move { Ok(None) }
+            },
+            None,
+            SupportsResponse::None,
+        );
+
+        let configs = vec![AutomationConfig {
+            id: Some("keep_porch_on".to_string()),
+            alias: None,
+            description: None,
+            triggers: vec![Trigger::State(StateTrigger {
+                id: None,
+                entity_id: EntityIdSpec::Single("light.porch".to_string()),
+                attribute: None,
+                from: None,
+                to: Some(StateMatch::Single("off".to_string())),
+                not_from: HashSet::new(),
+                not_to: HashSet::new(),
+                r#for: None,
+            })],
+            conditions: vec![],
+            actions: vec![json!({
+                "service": "light.turn_on",
+                "target": {"entity_id": "light.porch"}
+            })],
+            mode: ha_automation::ExecutionMode::Parallel { max: 100 },
+            max: None,
+            enabled: true,
+            variables: serde_json::Value::Null,
+            trace: None,
+        }];
+        hass.automation_engine
+            .manager()
+            .write()
+            .await
+            .load(configs)
+            .unwrap();
+        hass.automation_engine.start().await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        for expected in 1..=2 {
+            hass.states
+                .set(porch.clone(), "on", HashMap::new(), Context::new());
+            hass.states
+                .set(porch.clone(), "off", HashMap::new(), Context::new());
+            tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+            assert_eq!(turn_ons.load(Ordering::SeqCst), expected);
+        }
+
+        hass.automation_engine.stop();
+    }
+
+    #[tokio::test]
+    async fn test_trigger_id_reaches_actions() {
+        use ha_core::Event;
+
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorder = seen.clone();
+        hass.services.register(
+            "test",
+            "record",
+            move |call: ServiceCall| {
+                recorder
+                    .lock()
+                    .unwrap()
+                    .push(call.service_data["which"].clone());
+                async move { Ok(None) }
+            },
+            None,
+            SupportsResponse::None,
+        );
+
+        let config: AutomationConfig = serde_json::from_value(json!({
+            "id": "which_button",
+            "triggers": [
+                {"platform": "event", "event_type": "button_a", "id": "a"},
+                {"platform": "event", "event_type": "button_b", "id": "b"},
+                {"platform": "event", "event_type": "button_c"}
+            ],
+            "actions": [{
+                "service": "test.record",
+                "data": {"which": "{{ trigger.id }}"}
+            }]
+        }))
+        .unwrap();
+        hass.automation_engine
+            .manager()
+            .write()
+            .await
+            .load(vec![config])
+            .unwrap();
+        hass.automation_engine.start().await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        for event_type in ["button_b", "button_a", "button_c"] {
+            hass.bus
+                .fire(Event::new(event_type, json!({}), Context::new()));
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+
+        // Triggers without an id fall back to their index (rendered natively)
+        assert_eq!(*seen.lock().unwrap(), [json!("b"), json!("a"), json!(2)]);
+
+        hass.automation_engine.stop();
+    }
+
+    #[tokio::test]
+    async fn test_start_automation_runs_once_started() {
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+
+        let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = runs.clone();
+        hass.services.register(
+            "test",
+            "started",
+            move |_call: ServiceCall| {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                async move { Ok(None) }
+            },
+            None,
+            SupportsResponse::None,
+        );
+
+        let config: AutomationConfig = serde_json::from_value(json!({
+            "id": "on_start",
+            "triggers": [{"platform": "homeassistant", "event": "start"}],
+            "actions": [{"service": "test.started"}]
+        }))
+        .unwrap();
+        hass.automation_engine
+            .manager()
+            .write()
+            .await
+            .load(vec![config])
+            .unwrap();
+        hass.automation_engine.start().await;
+
+        // Startup has begun, but start automations wait until it finished
+        hass.bus
+            .fire(Event::new(HOMEASSISTANT_START, json!({}), Context::new()));
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        hass.bus
+            .fire(Event::new(HOMEASSISTANT_STARTED, json!({}), Context::new()));
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        hass.automation_engine.stop();
+    }
+
+    #[tokio::test]
+    async fn test_stopped_automation_run_resets_current() {
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+        let restore_state = ha_components::RestoreStateStore::new(hass.registries.storage.clone());
+
+        let config: AutomationConfig = serde_json::from_value(json!({
+            "id": "slow",
+            "triggers": [{"platform": "event", "event_type": "go"}],
+            "actions": [{"delay": {"seconds": 30}}]
+        }))
+        .unwrap();
+        let manager = hass.automation_engine.manager();
+        manager.write().await.load(vec![config]).unwrap();
+        create_automation_entities(&hass.states, &manager.read().await.all(), &restore_state);
+        hass.automation_engine.start().await;
+
+        hass.bus.fire(Event::new("go", json!({}), Context::new()));
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let current = || hass.states.get("automation.slow").unwrap().attributes["current"].clone();
+        assert_eq!(current(), json!(1));
+
+        // Aborting the run counts it down like finishing would
+        hass.automation_engine.stop();
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        assert_eq!(current(), json!(0));
+    }
+
+    #[tokio::test]
+    async fn test_automation_last_triggered_attribute() {
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+        let restore_state = Arc::new(ha_components::RestoreStateStore::new(
+            hass.registries.storage.clone(),
+        ));
+        restore_state.attach(&hass.bus);
+
+        let config: AutomationConfig = serde_json::from_value(json!({
+            "id": "porch",
+            "alias": "Porch light",
+            "triggers": [{"platform": "event", "event_type": "motion"}],
+            "actions": []
+        }))
+        .unwrap();
+        let automation = Automation::from_config(config.clone());
+        create_automation_entities(
+            &hass.states,
+            std::slice::from_ref(&automation),
+            &restore_state,
+        );
+        let state = hass.states.get("automation.porch").unwrap();
+        assert_eq!(state.attributes["last_triggered"], json!(null));
+        assert_eq!(state.attributes["current"], json!(0));
+
+        hass.automation_engine
+            .manager()
+            .write()
+            .await
+            .load(vec![config.clone()])
+            .unwrap();
+        hass.automation_engine
+            .trigger("porch", None, true, &Context::new())
+            .await;
+
+        let state = hass.states.get("automation.porch").unwrap();
+        assert_eq!(state.state, "on");
+        assert_eq!(state.attributes["friendly_name"], "Porch light");
+        assert_eq!(state.attributes["current"], json!(0));
+        let last_triggered = state.attributes["last_triggered"].clone();
+        let timestamp = last_triggered.as_str().unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+
+        // The timestamp comes back after a restart
+        restore_state.save().await.unwrap();
+        let restored = ha_components::RestoreStateStore::new(hass.registries.storage.clone());
+        restored.load().await.unwrap();
+        let states = StateStore::new(Arc::new(EventBus::new()));
+        create_automation_entities(&states, &[automation], &restored);
+        let state = states.get("automation.porch").unwrap();
+        assert_eq!(state.attributes["last_triggered"], last_triggered);
+    }
+
+    #[tokio::test]
+    async fn test_automation_entity_id_stable_across_alias_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+        hass.register_automation_services();
+        let restore_state = ha_components::RestoreStateStore::new(hass.registries.storage.clone());
+
+        let config = |alias: &str| -> AutomationConfig {
+            serde_json::from_value(json!({
+                "id": "Morning Routine",
+                "alias": alias,
+                "triggers": [{"platform": "event", "event_type": "wake_up"}],
+                "actions": []
+            }))
+            .unwrap()
+        };
+
+        create_automation_entities(
+            &hass.states,
+            &[Automation::from_config(config("Wake up"))],
+            &restore_state,
+        );
+        let state = hass.states.get("automation.morning_routine").unwrap();
+        assert_eq!(state.attributes["friendly_name"], "Wake up");
+
+        // Renaming only changes the friendly name
+        create_automation_entities(
+            &hass.states,
+            &[Automation::from_config(config("Good morning"))],
+            &restore_state,
+        );
+        let state = hass.states.get("automation.morning_routine").unwrap();
+        assert_eq!(state.attributes["friendly_name"], "Good morning");
+        assert!(hass.states.get("automation.good_morning").is_none());
+
+        // Services find the automation through its entity
+        let manager = hass.automation_engine.manager();
+        manager
+            .write()
+            .await
+            .load(vec![config("Good morning")])
+            .unwrap();
+        hass.services
+            .call(
+                "automation",
+                "turn_off",
+                json!({"entity_id": "automation.morning_routine"}),
+                Context::new(),
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(!manager.read().await.get("Morning Routine").unwrap().enabled);
+        assert_eq!(
+            hass.states.get("automation.morning_routine").unwrap().state,
+            "off"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_automation_trigger_service_runs_actions() {
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+        hass.register_automation_services();
+
+        let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = runs.clone();
+        hass.services.register(
+            "test",
+            "count",
+            move |_call: ServiceCall| {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                async move { Ok(None) }
+            },
+            None,
+            SupportsResponse::None,
+        );
+
+        hass.states.set(
+            EntityId::new("input_boolean", "gate").unwrap(),
+            "off",
+            HashMap::new(),
+            Context::new(),
+        );
+        let config: AutomationConfig = serde_json::from_value(json!({
+            "id": "gated",
+            "triggers": [{"platform": "event", "event_type": "never_fired"}],
+            "conditions": [{
+                "condition": "state",
+                "entity_id": "input_boolean.gate",
+                "state": "on"
+            }],
+            "actions": [{"service": "test.count"}]
+        }))
+        .unwrap();
+        hass.automation_engine
+            .manager()
+            .write()
+            .await
+            .load(vec![config])
+            .unwrap();
+
+        let trigger = |data: serde_json::Value| {
+            hass.services
+                .call("automation", "trigger", data, Context::new(), false)
+        };
+
+        // Conditions are skipped by default
+        trigger(json!({"entity_id": "automation.gated"}))
+            .await
+            .unwrap();
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Unless the caller asks for them, then the closed gate blocks the run
+        trigger(json!({"entity_id": "automation.gated", "skip_condition": false}))
+            .await
+            .unwrap();
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_automation_reload_service() {
+        use ha_core::Event;
+
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+        let restore_state = Arc::new(ha_components::RestoreStateStore::new(
+            hass.registries.storage.clone(),
+        ));
+        hass.register_automation_reload_service(temp_dir.path(), restore_state.clone());
+
+        let fired = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorder = fired.clone();
+        hass.services.register(
+            "test",
+            "record",
+            move |call: ServiceCall| {
+                recorder
+                    .lock()
+                    .unwrap()
+                    .push(call.service_data["by"].clone());
+                async move { Ok(None) }
+            },
+            None,
+            SupportsResponse::None,
+        );
+
+        let write_config = |ids: &[&str]| {
+            let mut content = String::from("automation:\n");
+            for id in ids {
+                content.push_str(&format!(
+                    "  - id: {id}\n    trigger:\n      - platform: event\n        event_type: ping\n    action:\n      - service: test.record\n        data:\n          by: {id}\n"
+                ));
+            }
+            fs::write(temp_dir.path().join("configuration.yaml"), content).unwrap();
+        };
+
+        write_config(&["old"]);
+        let configs = load_automations(temp_dir.path());
+        let manager = hass.automation_engine.manager();
+        manager.write().await.load(configs).unwrap();
+        create_automation_entities(&hass.states, &manager.read().await.all(), &restore_state);
+        hass.automation_engine.start().await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        write_config(&["new"]);
+        hass.services
+            .call("automation", "reload", json!({}), Context::new(), false)
+            .await
+            .unwrap();
+        assert!(hass.states.get("automation.old").is_none());
+        assert_eq!(hass.states.get("automation.new").unwrap().state, "on");
+
+        hass.bus.fire(Event::new("ping", json!({}), Context::new()));
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        assert_eq!(*fired.lock().unwrap(), [json!("new")]);
+
+        hass.automation_engine.stop();
+    }
+
+    #[tokio::test]
+    async fn test_script_runs_when_called_by_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+        hass.register_script_services();
+
+        let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = runs.clone();
+        hass.services.register(
+            "test",
+            "count",
+            move |_call: ServiceCall| {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                async move { Ok(None) }
+            },
+            None,
+            SupportsResponse::None,
+        );
+
+        let config_content = r#"
+script:
+  count_once:
+    alias: Count once
+    sequence:
+      - service: test.count
+  slow_count:
+    sequence:
+      - delay: "00:00:10"
+      - service: test.count
+"#;
+        fs::write(temp_dir.path().join("configuration.yaml"), config_content).unwrap();
+        load_scripts(temp_dir.path(), &hass.services, hass.script_engine.clone());
+        let state = hass.states.get("script.count_once").unwrap();
+        assert_eq!(state.state, "off");
+        assert_eq!(state.attributes["friendly_name"], "Count once");
+
+        hass.services
+            .call("script", "count_once", json!({}), Context::new(), false)
+            .await
+            .unwrap();
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+        let state = hass.states.get("script.count_once").unwrap();
+        assert_eq!(state.state, "off");
+        assert!(state.attributes["last_triggered"].is_string());
+
+        // turn_on starts a run in the background, turn_off stops it
+        let slow = json!({"entity_id": "script.slow_count"});
+        hass.services
+            .call("script", "turn_on", slow.clone(), Context::new(), false)
+            .await
+            .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        assert_eq!(hass.states.get("script.slow_count").unwrap().state, "on");
+
+        // Single mode ignores a second start while running
+        hass.services
+            .call("script", "turn_on", slow.clone(), Context::new(), false)
+            .await
+            .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        assert_eq!(
+            hass.states.get("script.slow_count").unwrap().attributes["current"],
+            json!(1)
+        );
+
+        hass.services
+            .call("script", "turn_off", slow, Context::new(), false)
+            .await
+            .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        assert_eq!(hass.states.get("script.slow_count").unwrap().state, "off");
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_script_fields_become_variables() {
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorder = seen.clone();
+        hass.services.register(
+            "test",
+            "record",
+            move |call: ServiceCall| {
+                recorder
+                    .lock()
+                    .unwrap()
+                    .push(call.service_data["message"].clone());
+                async move { Ok(None) }
+            },
+            None,
+            SupportsResponse::None,
+        );
+
+        let config_content = r#"
+script:
+  greet:
+    fields:
+      greeting:
+        description: How to greet
+        required: true
+      name:
+        default: World
+    sequence:
+      - service: test.record
+        data:
+          message: "{{ greeting }} {{ name }}"
+"#;
+        fs::write(temp_dir.path().join("configuration.yaml"), config_content).unwrap();
+        load_scripts(temp_dir.path(), &hass.services, hass.script_engine.clone());
+
+        let greet = |data: serde_json::Value| {
+            hass.services
+                .call("script", "greet", data, Context::new(), false)
+        };
+        greet(json!({"greeting": "Hello"})).await.unwrap();
+        greet(json!({"greeting": "Hi", "name": "there"}))
+            .await
+            .unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [json!("Hello World"), json!("Hi there")]
+        );
+
+        // A required field without a default must be given
+        let result = greet(json!({"name": "nobody"})).await;
+        assert!(matches!(result, Err(ServiceError::InvalidData(_))));
+        assert_eq!(seen.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_entity_service_with_area_target() {
+        use ha_registries::DeviceIdentifier;
+        use std::sync::Mutex;
+
+        let temp_dir = TempDir::new().unwrap();
+        let hass = create_test_hass(&temp_dir);
+        let registries = &hass.registries;
+        let kitchen = registries.areas.create("Kitchen", None).unwrap();
+
+        // One light in the area itself, one through its device
+        for entity_id in ["light.ceiling", "switch.kettle", "light.bedroom"] {
+            registries
+                .entities
+                .get_or_create("demo", entity_id, None, None, None);
+        }
+        for entity_id in ["light.ceiling", "switch.kettle"] {
+            registries
+                .entities
+                .update(entity_id, |e| e.area_id = Some(kitchen.id.clone()))
+                .unwrap();
+        }
+        let device = registries.devices.get_or_create(
+            &[DeviceIdentifier("demo".to_string(), "strip".to_string())],
+            &[],
+            None,
+            None,
+            Some("LED strip"),
+            None,
+        );
+        registries
+            .devices
+            .update(&device.id, |d| d.area_id = Some(kitchen.id.clone()));
+        registries
+            .entities
+            .get_or_create("demo", "light.counter", None, None, Some(&device.id));
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let seen = calls.clone();
+        register_entity_service(
+            &hass.services,
+            registries.clone(),
+            "light",
+            "turn_on",
+            move |entity_id, service, data| {
+                seen.lock()
+                    .unwrap()
+                    .push((entity_id.to_string(), service.to_string(), data));
+                Ok(true)
+            },
+        );
+
+        hass.services
+            .call(
+                "light",
+                "turn_on",
+                json!({"area_id": kitchen.id, "brightness": 200}),
+                Context::new(),
+                false,
+            )
+            .await
+            .unwrap();
+
+        let data = json!({"brightness": 200});
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                (
+                    "light.ceiling".to_string(),
+                    "turn_on".to_string(),
+                    data.clone()
+                ),
+                ("light.counter".to_string(), "turn_on".to_string(), data),
+            ]
+        );
+    }
 }
@@ -0,0 +1,312 @@
+//! Script execution engine
+//!
+//! This module provides the `ScriptEngine` which holds the configured scripts
+//! and runs their action sequences with `ha-script`'s `ScriptExecutor`. Runs
+//! are spawned as tasks so they can be stopped with `script.turn_off` or
+//! restarted, and the `script.*` entities follow them.
+
+use chrono::Utc;
+use ha_core::{Context, EntityId};
+use ha_event_bus::EventBus;
+use ha_script::script::MaxExceeded;
+use ha_script::{
+    ExecutionContext, Script, ScriptConfig, ScriptExecutor, ScriptExecutorError,
+    ScriptExecutorResult, ScriptMode,
+};
+use ha_service_registry::ServiceRegistry;
+use ha_state_store::StateStore;
+use ha_template::TemplateEngine;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::task::{AbortHandle, JoinHandle};
+use tracing::{debug, warn};
+
+/// A loaded script and its runs
+struct LoadedScript {
+    script: Script,
+    /// Tasks of started runs, some may have finished
+    runs: Mutex<Vec<AbortHandle>>,
+    /// Runs currently executing actions
+    active: AtomicUsize,
+    /// Held while a run executes, so queued runs go one at a time
+    queue: Arc<tokio::sync::Mutex<()>>,
+}
+
+/// Script engine that runs scripts as services
+pub struct ScriptEngine {
+    /// State machine for the `script.*` entities
+    state_machine: Arc<StateStore>,
+    /// Executor running the action sequences
+    executor: Arc<ScriptExecutor>,
+    /// Loaded scripts by ID
+    scripts: RwLock<HashMap<String, Arc<LoadedScript>>>,
+}
+
+impl ScriptEngine {
+    /// Create a new script engine
+    pub fn new(
+        event_bus: Arc<EventBus>,
+        state_machine: Arc<StateStore>,
+        service_registry: Arc<ServiceRegistry>,
+        template_engine: Arc<TemplateEngine>,
+    ) -> Self {
+        let executor = Arc::new(ScriptExecutor::new(
+            state_machine.clone(),
+            service_registry,
+            template_engine,
+            event_bus,
+        ));
+
+        Self {
+            state_machine,
+            executor,
+            scripts: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Load scripts and create their `script.*` entities
+    ///
+    /// Returns the IDs of the loaded scripts; scripts whose ID isn't a valid
+    /// object ID are skipped.
+    pub fn load(&self, configs: HashMap<String, ScriptConfig>) -> Vec<String> {
+        let mut loaded = Vec::new();
+        let mut scripts = self.scripts.write().unwrap();
+        for (id, config) in configs {
+            let Ok(entity_id) = EntityId::new("script", &id) else {
+                warn!(script_id = %id, "Invalid script ID, skipping");
+                continue;
+            };
+            let script = Script::from_config(id.clone(), config);
+
+            let mut attributes = HashMap::new();
+            attributes.insert("friendly_name".to_string(), json!(script.display_name()));
+            if let Some(icon) = &script.icon {
+                attributes.insert("icon".to_string(), json!(icon));
+            }
+            attributes.insert("mode".to_string(), json!(script.mode));
+            attributes.insert("current".to_string(), json!(0));
+            attributes.insert("last_triggered".to_string(), Value::Null);
+            self.state_machine
+                .set(entity_id, "off", attributes, Context::new());
+
+            scripts.insert(
+                id.clone(),
+                Arc::new(LoadedScript {
+                    script,
+                    runs: Mutex::new(Vec::new()),
+                    active: AtomicUsize::new(0),
+                    queue: Arc::new(tokio::sync::Mutex::new(())),
+                }),
+            );
+            loaded.push(id);
+        }
+        loaded
+    }
+
+    /// Get the ID of the script owning a `script.*` entity
+    pub fn id_for_entity(&self, entity_id: &str) -> Option<String> {
+        let id = entity_id.strip_prefix("script.")?;
+        self.scripts
+            .read()
+            .unwrap()
+            .contains_key(id)
+            .then(|| id.to_string())
+    }
+
+    /// Run a script and wait for it to finish
+    ///
+    /// `variables` are checked against the script's `fields`, see
+    /// [`field_variables`]. Returns the script's response. Runs refused by the script's mode, and
+    /// runs stopped before finishing, return `None`.
+    pub async fn run(
+        &self,
+        id: &str,
+        variables: serde_json::Map<String, Value>,
+        parent: &Context,
+    ) -> ScriptExecutorResult<Option<Value>> {
+        let Some(run) = self.start(id, variables, parent)? else {
+            return Ok(None);
+        };
+        match run.await {
+            Ok(result) => result,
+            Err(e) if e.is_cancelled() => Ok(None),
+            Err(e) => Err(ScriptExecutorError::ActionError(e.to_string())),
+        }
+    }
+
+    /// Start a script without waiting for it to finish
+    pub fn turn_on(
+        &self,
+        id: &str,
+        variables: serde_json::Map<String, Value>,
+        parent: &Context,
+    ) -> ScriptExecutorResult<()> {
+        self.start(id, variables, parent).map(|_| ())
+    }
+
+    /// Stop all runs of a script
+    pub fn turn_off(&self, id: &str) {
+        let Some(loaded) = self.get(id) else {
+            return;
+        };
+        for run in loaded.runs.lock().unwrap().drain(..) {
+            run.abort();
+        }
+    }
+
+    /// Whether a script has runs executing
+    pub fn is_running(&self, id: &str) -> bool {
+        self.get(id)
+            .is_some_and(|loaded| loaded.active.load(Ordering::SeqCst) > 0)
+    }
+
+    fn get(&self, id: &str) -> Option<Arc<LoadedScript>> {
+        self.scripts.read().unwrap().get(id).cloned()
+    }
+
+    /// Spawn a run, if the script's mode allows one
+    fn start(
+        &self,
+        id: &str,
+        variables: serde_json::Map<String, Value>,
+        parent: &Context,
+    ) -> ScriptExecutorResult<Option<JoinHandle<ScriptExecutorResult<Option<Value>>>>> {
+        let loaded = self
+            .get(id)
+            .ok_or_else(|| ScriptExecutorError::ActionError(format!("Unknown script: {}", id)))?;
+        let script = &loaded.script;
+        let variables = field_variables(script, variables)?;
+
+        let mut runs = loaded.runs.lock().unwrap();
+        runs.retain(|run| !run.is_finished());
+        let allowed = match script.mode {
+            ScriptMode::Single => runs.is_empty(),
+            ScriptMode::Restart => {
+                for run in runs.drain(..) {
+                    run.abort();
+                }
+                true
+            }
+            ScriptMode::Queued | ScriptMode::Parallel => runs.len() < script.max,
+        };
+        if !allowed {
+            if script.max_exceeded == MaxExceeded::Warning {
+                warn!(
+                    script_id = %id,
+                    mode = ?script.mode,
+                    "Script already running, maximum number of runs exceeded"
+                );
+            }
+            return Ok(None);
+        }
+
+        let mut ctx = ExecutionContext::new();
+        ctx.context = Context::child_of(parent);
+        ctx.variables.extend(variables);
+
+        let executor = self.executor.clone();
+        let state_machine = self.state_machine.clone();
+        let run_script = loaded.clone();
+        let task = tokio::spawn(async move {
+            let _turn = match run_script.script.mode {
+                ScriptMode::Queued => Some(run_script.queue.clone().lock_owned().await),
+                _ => None,
+            };
+            let _guard = RunGuard::start(run_script.clone(), state_machine, ctx.context.clone());
+
+            debug!(script_id = %run_script.script.id, "Running script");
+            let result = executor
+                .execute(&run_script.script.sequence, &mut ctx)
+                .await;
+            if let Err(e) = &result {
+                warn!(script_id = %run_script.script.id, error = %e, "Script failed");
+            }
+            result
+        });
+        runs.push(task.abort_handle());
+        Ok(Some(task))
+    }
+}
+
+/// Fill in defaults of a script's `fields` and check required ones are set
+///
+/// Values for fields the script doesn't declare are passed through as
+/// variables too.
+fn field_variables(
+    script: &Script,
+    mut variables: serde_json::Map<String, Value>,
+) -> ScriptExecutorResult<serde_json::Map<String, Value>> {
+    let Some(fields) = script.fields.as_object() else {
+        return Ok(variables);
+    };
+    for (name, field) in fields {
+        if variables.contains_key(name) {
+            continue;
+        }
+        if let Some(default) = field.get("default") {
+            variables.insert(name.clone(), default.clone());
+        } else if field
+            .get("required")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+        {
+            return Err(ScriptExecutorError::InvalidData(format!(
+                "required field '{}' is missing",
+                name
+            )));
+        }
+    }
+    Ok(variables)
+}
+
+/// Marks a script as running for as long as it's alive
+///
+/// Dropped when a run ends, including when its task is aborted, so the
+/// entity goes back to `off` once no run is left.
+struct RunGuard {
+    loaded: Arc<LoadedScript>,
+    state_machine: Arc<StateStore>,
+    context: Context,
+}
+
+impl RunGuard {
+    fn start(loaded: Arc<LoadedScript>, state_machine: Arc<StateStore>, context: Context) -> Self {
+        let current = loaded.active.fetch_add(1, Ordering::SeqCst) + 1;
+        let guard = Self {
+            loaded,
+            state_machine,
+            context,
+        };
+        guard.update_entity(current, true);
+        guard
+    }
+
+    /// Reflect the number of runs on the script's entity
+    fn update_entity(&self, current: usize, triggered: bool) {
+        let entity_id = self.loaded.script.entity_id();
+        let Some(state) = self.state_machine.get(&entity_id) else {
+            return;
+        };
+        let Ok(entity_id) = EntityId::new("script", &self.loaded.script.id) else {
+            return;
+        };
+
+        let mut attributes = state.attributes.clone();
+        attributes.insert("current".to_string(), json!(current));
+        if triggered {
+            attributes.insert("last_triggered".to_string(), json!(Utc::now().to_rfc3339()));
+        }
+        let new_state = if current > 0 { "on" } else { "off" };
+        self.state_machine
+            .set(entity_id, new_state, attributes, self.context.clone());
+    }
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        let current = self.loaded.active.fetch_sub(1, Ordering::SeqCst) - 1;
+        self.update_entity(current, false);
+    }
+}
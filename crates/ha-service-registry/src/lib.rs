@@ -4,12 +4,16 @@
 //! services in Home Assistant. Services are the primary way to control
 //! entities and trigger actions.
 
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
+use ha_core::events::{CallServiceData, ServiceRegisteredData, ServiceRemovedData};
 use ha_core::{Context, ServiceCall, SupportsResponse};
+use ha_event_bus::EventBus;
+use jsonschema::JSONSchema;
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use thiserror::Error;
 use tracing::{debug, instrument, warn};
 
@@ -39,6 +43,37 @@ pub enum ServiceError {
 
     #[error("service requires return_response=true")]
     ResponseRequired,
+
+    #[error("service call timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("permission denied: {0}")]
+    PermissionDenied(String),
+}
+
+/// Hook wrapping every service call, e.g. for logging, auth or metrics
+///
+/// Any `Fn(&ServiceCall) -> Result<(), ServiceError>` closure is an
+/// interceptor that only runs before calls.
+pub trait ServiceInterceptor: Send + Sync {
+    /// Run before the handler; an error aborts the call without invoking it
+    fn before_call(&self, call: &ServiceCall) -> Result<(), ServiceError>;
+
+    /// Run after the handler finished (or timed out) with its result
+    ///
+    /// Also runs when a later interceptor's `before_call` aborted the call,
+    /// with that error, so every `before_call` that passed gets its
+    /// `after_call`.
+    fn after_call(&self, _call: &ServiceCall, _result: &ServiceResult) {}
+}
+
+impl<F> ServiceInterceptor for F
+where
+    F: Fn(&ServiceCall) -> Result<(), ServiceError> + Send + Sync,
+{
+    fn before_call(&self, call: &ServiceCall) -> Result<(), ServiceError> {
+        self(call)
+    }
 }
 
 /// Information about a registered service
@@ -64,6 +99,53 @@ pub struct ServiceDescription {
 struct RegisteredService {
     handler: ServiceHandler,
     description: ServiceDescription,
+    /// Compiled `description.schema`, if it is a valid JSON schema
+    validator: Option<Arc<JSONSchema>>,
+}
+
+impl RegisteredService {
+    fn new(handler: ServiceHandler, description: ServiceDescription) -> Self {
+        let validator = description.schema.as_ref().and_then(|schema| {
+            match JSONSchema::compile(schema) {
+                Ok(validator) => Some(Arc::new(validator)),
+                Err(e) => {
+                    // Schemas from Python integrations are not always JSON schemas
+                    debug!(
+                        domain = %description.domain,
+                        service = %description.service,
+                        "Service schema is not a valid JSON schema, skipping validation: {}",
+                        e
+                    );
+                    None
+                }
+            }
+        });
+        Self {
+            handler,
+            description,
+            validator,
+        }
+    }
+
+    /// Check service data against the schema, describing every violation
+    fn validate(&self, service_data: &serde_json::Value) -> Result<(), ServiceError> {
+        let Some(validator) = &self.validator else {
+            return Ok(());
+        };
+        validator.validate(service_data).map_err(|errors| {
+            let messages: Vec<String> = errors
+                .map(|e| {
+                    let path = e.instance_path.to_string();
+                    if path.is_empty() {
+                        e.to_string()
+                    } else {
+                        format!("{} at {}", e, path)
+                    }
+                })
+                .collect();
+            ServiceError::InvalidData(messages.join(", "))
+        })
+    }
 }
 
 /// The service registry manages all registered services
@@ -75,6 +157,14 @@ struct RegisteredService {
 pub struct ServiceRegistry {
     /// Services indexed by "domain.service" key
     services: DashMap<String, RegisteredService>,
+    /// Interceptors in the order they were added
+    interceptors: RwLock<Vec<Arc<dyn ServiceInterceptor>>>,
+    /// Bus that receives a CALL_SERVICE event for every call
+    event_bus: Option<Arc<EventBus>>,
+    /// Domains whose calls run one at a time per target entity
+    serialized_domains: DashSet<String>,
+    /// Locks of entities targeted by running or queued calls
+    entity_locks: DashMap<String, Arc<EntityLock>>,
 }
 
 impl ServiceRegistry {
@@ -82,16 +172,86 @@ impl ServiceRegistry {
     pub fn new() -> Self {
         Self {
             services: DashMap::new(),
+            interceptors: RwLock::new(Vec::new()),
+            event_bus: None,
+            serialized_domains: DashSet::new(),
+            entity_locks: DashMap::new(),
         }
     }
 
+    /// Create a registry that fires a CALL_SERVICE event for every call
+    pub fn with_event_bus(event_bus: Arc<EventBus>) -> Self {
+        Self {
+            event_bus: Some(event_bus),
+            ..Self::new()
+        }
+    }
+
+    /// Add an interceptor that wraps every later service call
+    ///
+    /// Interceptors run in the order they were added, both before and after
+    /// the handler. The first one returning an error stops the call.
+    pub fn add_interceptor(&self, interceptor: impl ServiceInterceptor + 'static) {
+        self.interceptors
+            .write()
+            .unwrap()
+            .push(Arc::new(interceptor));
+    }
+
+    /// Run calls of a domain's services one at a time per target entity
+    ///
+    /// Calls targeting an entity wait for earlier calls targeting it to
+    /// finish, in the order they were made. Calls to other entities still
+    /// run concurrently. Waiting doesn't count towards a call's timeout.
+    ///
+    /// Calls made from within a running call, recognized by their context
+    /// being the running call's or a descendant of it, don't wait for it:
+    /// `toggle` calling `turn_on` for the same entity would wait forever.
+    pub fn serialize_entity_calls(&self, domain: impl Into<String>) {
+        self.serialized_domains.insert(domain.into());
+    }
+
+    /// Wait for the locks of the entities a call targets
+    ///
+    /// Locks are taken in sorted order so calls targeting overlapping sets of
+    /// entities can't deadlock.
+    async fn lock_entities(&self, call: &ServiceCall) -> EntityLocks<'_> {
+        let mut locks = EntityLocks {
+            registry: self,
+            entity_ids: Vec::new(),
+            guards: Vec::new(),
+        };
+        if !self.serialized_domains.contains(&call.domain) {
+            return locks;
+        }
+        locks.entity_ids = call.entity_ids();
+        locks.entity_ids.sort();
+        locks.entity_ids.dedup();
+
+        for entity_id in &locks.entity_ids {
+            let lock = self
+                .entity_locks
+                .entry(entity_id.clone())
+                .or_default()
+                .clone();
+            if lock.is_held_for(&call.context) {
+                continue;
+            }
+            let guard = lock.mutex.clone().lock_owned().await;
+            *lock.holder.lock().unwrap() = Some(call.context.id.clone());
+            locks.guards.push((lock, guard));
+        }
+        locks
+    }
+
     /// Register a new service
     ///
     /// # Arguments
     /// * `domain` - The domain the service belongs to (e.g., "light")
     /// * `service` - The service name (e.g., "turn_on")
     /// * `handler` - Async function to handle service calls
-    /// * `schema` - Optional JSON schema for validating service data
+    /// * `schema` - Optional JSON schema; calls with data that does not match
+    ///   fail with [`ServiceError::InvalidData`]
     /// * `supports_response` - Whether the service can return a response
     #[instrument(skip(self, domain, service, handler, schema))]
     pub fn register<F, Fut>(
@@ -124,13 +284,7 @@ impl ServiceRegistry {
             supports_response,
         };
 
-        self.services.insert(
-            key,
-            RegisteredService {
-                handler,
-                description,
-            },
-        );
+        self.insert_service(key, RegisteredService::new(handler, description));
     }
 
     /// Register a service with full description
@@ -151,13 +305,32 @@ impl ServiceRegistry {
         let handler: ServiceHandler =
             Arc::new(move |call| Box::pin(handler(call)) as ServiceFuture);
 
-        self.services.insert(
-            key,
-            RegisteredService {
-                handler,
-                description,
-            },
-        );
+        self.insert_service(key, RegisteredService::new(handler, description));
+    }
+
+    /// Store a service and announce it with a SERVICE_REGISTERED event
+    fn insert_service(&self, key: String, registered: RegisteredService) {
+        let data = ServiceRegisteredData {
+            domain: registered.description.domain.clone(),
+            service: registered.description.service.clone(),
+        };
+        self.services.insert(key, registered);
+        if let Some(event_bus) = &self.event_bus {
+            event_bus.fire_typed(data, Context::new());
+        }
+    }
+
+    /// Announce a removed service with a SERVICE_REMOVED event
+    fn service_removed(&self, domain: &str, service: &str) {
+        if let Some(event_bus) = &self.event_bus {
+            event_bus.fire_typed(
+                ServiceRemovedData {
+                    domain: domain.to_string(),
+                    service: service.to_string(),
+                },
+                Context::new(),
+            );
+        }
     }
 
     /// Call a service
@@ -176,6 +349,52 @@ impl ServiceRegistry {
         service_data: serde_json::Value,
         context: Context,
         return_response: bool,
+    ) -> ServiceResult {
+        self.call_inner(
+            domain,
+            service,
+            service_data,
+            context,
+            return_response,
+            None,
+        )
+        .await
+    }
+
+    /// Call a service, giving up if the handler runs longer than `timeout`
+    ///
+    /// A handler exceeding the timeout is dropped and the call fails with
+    /// [`ServiceError::Timeout`]. The registry holds no locks while the
+    /// handler runs, so cancelling it leaves the registry untouched.
+    #[instrument(skip(self, service_data, context))]
+    pub async fn call_with_timeout(
+        &self,
+        domain: &str,
+        service: &str,
+        service_data: serde_json::Value,
+        context: Context,
+        return_response: bool,
+        timeout: Duration,
+    ) -> ServiceResult {
+        self.call_inner(
+            domain,
+            service,
+            service_data,
+            context,
+            return_response,
+            Some(timeout),
+        )
+        .await
+    }
+
+    async fn call_inner(
+        &self,
+        domain: &str,
+        service: &str,
+        service_data: serde_json::Value,
+        context: Context,
+        return_response: bool,
+        timeout: Option<Duration>,
     ) -> ServiceResult {
         let key = format!("{}.{}", domain, service);
 
@@ -197,6 +416,9 @@ impl ServiceRegistry {
             return Err(ServiceError::ResponseRequired);
         }
 
+        // Reject malformed data before it reaches the handler
+        registered.validate(&service_data)?;
+
         let call = ServiceCall::new(domain, service, service_data, context);
 
         debug!(domain = %domain, service = %service, "Calling service");
@@ -204,7 +426,47 @@ impl ServiceRegistry {
         let handler = registered.handler.clone();
         drop(registered); // Release the lock before calling the handler
 
-        let result = handler(call).await?;
+        let interceptors = self.interceptors.read().unwrap().clone();
+        for (passed, interceptor) in interceptors.iter().enumerate() {
+            if let Err(e) = interceptor.before_call(&call) {
+                let result = Err(e);
+                for earlier in &interceptors[..passed] {
+                    earlier.after_call(&call, &result);
+                }
+                return result;
+            }
+        }
+        let intercepted_call = (!interceptors.is_empty()).then(|| call.clone());
+
+        if let Some(event_bus) = &self.event_bus {
+            event_bus.fire_typed(
+                CallServiceData {
+                    domain: call.domain.clone(),
+                    service: call.service.clone(),
+                    service_data: call.service_data.clone(),
+                },
+                call.context.clone(),
+            );
+        }
+
+        let locks = self.lock_entities(&call).await;
+        let result = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, handler(call))
+                .await
+                .unwrap_or_else(|_| {
+                    warn!(domain = %domain, service = %service, "Service call timed out");
+                    Err(ServiceError::Timeout(timeout))
+                }),
+            None => handler(call).await,
+        };
+        drop(locks);
+
+        if let Some(call) = intercepted_call {
+            for interceptor in &interceptors {
+                interceptor.after_call(&call, &result);
+            }
+        }
+        let result = result?;
 
         // Only return response if requested and supported
         if return_response {
@@ -269,6 +531,7 @@ impl ServiceRegistry {
 
         if removed {
             debug!(domain = %domain, service = %service, "Unregistered service");
+            self.service_removed(domain, service);
         }
 
         removed
@@ -284,9 +547,12 @@ impl ServiceRegistry {
             .map(|s| format!("{}.{}", s.description.domain, s.description.service))
             .collect();
 
-        let count = keys_to_remove.len();
+        let mut count = 0;
         for key in keys_to_remove {
-            self.services.remove(&key);
+            if let Some((_, removed)) = self.services.remove(&key) {
+                self.service_removed(domain, &removed.description.service);
+                count += 1;
+            }
         }
 
         debug!(domain = %domain, count = count, "Unregistered domain services");
@@ -305,8 +571,484 @@ impl Default for ServiceRegistry {
     }
 }
 
+/// Entity locks held by a serialized call, see
+/// [`ServiceRegistry::serialize_entity_calls`]
+struct EntityLocks<'a> {
+    registry: &'a ServiceRegistry,
+    entity_ids: Vec<String>,
+    guards: Vec<(Arc<EntityLock>, tokio::sync::OwnedMutexGuard<()>)>,
+}
+
+/// Lock serializing the calls targeting one entity
+#[derive(Default)]
+struct EntityLock {
+    mutex: Arc<tokio::sync::Mutex<()>>,
+    /// Context ID of the call holding the lock
+    holder: std::sync::Mutex<Option<String>>,
+}
+
+impl EntityLock {
+    /// Whether the lock is held by the call that `context` was made from
+    fn is_held_for(&self, context: &Context) -> bool {
+        self.holder
+            .lock()
+            .unwrap()
+            .as_deref()
+            .is_some_and(|holder| context.descends_from(holder))
+    }
+}
+
+impl Drop for EntityLocks<'_> {
+    /// Release the locks and forget those no other call holds or waits for
+    fn drop(&mut self) {
+        for (lock, guard) in self.guards.drain(..) {
+            *lock.holder.lock().unwrap() = None;
+            drop(guard);
+        }
+        for entity_id in &self.entity_ids {
+            self.registry
+                .entity_locks
+                .remove_if(entity_id, |_, lock| Arc::strong_count(lock) == 1);
+        }
+    }
+}
+
 /// Thread-safe wrapper for ServiceRegistry
 pub type SharedServiceRegistry = Arc<ServiceRegistry>;
 
-// Unit tests removed - covered by HA native tests via `make ha-compat-test`
+// Most behaviour is covered by HA native tests via `make ha-compat-test`
 // See tests/ha_compat/ for comprehensive ServiceRegistry testing through Python bindings
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry_with_schema() -> ServiceRegistry {
+        let registry = ServiceRegistry::new();
+        registry.register(
+            "light",
+            "turn_on",
+            |call| async move { Ok(Some(call.service_data)) },
+            Some(serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "entity_id": {"type": "string"},
+                    "brightness": {"type": "integer", "minimum": 0, "maximum": 255},
+                },
+                "required": ["entity_id"],
+            })),
+            SupportsResponse::Optional,
+        );
+        registry
+    }
+
+    #[tokio::test]
+    async fn test_call_with_valid_data() {
+        let registry = registry_with_schema();
+        let data = serde_json::json!({"entity_id": "light.kitchen", "brightness": 128});
+
+        let response = registry
+            .call("light", "turn_on", data.clone(), Context::new(), true)
+            .await
+            .unwrap();
+        assert_eq!(response, Some(data));
+    }
+
+    #[tokio::test]
+    async fn test_call_missing_required_field() {
+        let registry = registry_with_schema();
+
+        let err = registry
+            .call(
+                "light",
+                "turn_on",
+                serde_json::json!({"brightness": 128}),
+                Context::new(),
+                false,
+            )
+            .await
+            .unwrap_err();
+        match err {
+            ServiceError::InvalidData(message) => assert!(message.contains("entity_id")),
+            other => panic!("Expected InvalidData, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_with_timeout() {
+        let registry = ServiceRegistry::new();
+        registry.register(
+            "test",
+            "hang",
+            |_call| async {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok(None)
+            },
+            None,
+            SupportsResponse::None,
+        );
+
+        let err = registry
+            .call_with_timeout(
+                "test",
+                "hang",
+                serde_json::json!({}),
+                Context::new(),
+                false,
+                Duration::from_millis(20),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ServiceError::Timeout(_)));
+
+        // The registry is still usable after the handler was cancelled
+        assert!(registry.has_service("test", "hang"));
+        registry.register(
+            "test",
+            "quick",
+            |_call| async { Ok(None) },
+            None,
+            SupportsResponse::None,
+        );
+        registry
+            .call_with_timeout(
+                "test",
+                "quick",
+                serde_json::json!({}),
+                Context::new(),
+                false,
+                Duration::from_millis(20),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_serialized_entity_calls() {
+        let registry = Arc::new(ServiceRegistry::new());
+        let log = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let handler_log = log.clone();
+        registry.register(
+            "light",
+            "turn_on",
+            move |call| {
+                let log = handler_log.clone();
+                async move {
+                    let name = call.service_data["name"].as_str().unwrap().to_string();
+                    log.lock().unwrap().push(format!("start {}", name));
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    log.lock().unwrap().push(format!("end {}", name));
+                    Ok(None)
+                }
+            },
+            None,
+            SupportsResponse::None,
+        );
+        registry.serialize_entity_calls("light");
+
+        let call = |entity_id: &str, name: &str| {
+            registry.call(
+                "light",
+                "turn_on",
+                serde_json::json!({"entity_id": entity_id, "name": name}),
+                Context::new(),
+                false,
+            )
+        };
+        let (first, second, other) = tokio::join!(
+            call("light.kitchen", "first"),
+            call("light.kitchen", "second"),
+            call("light.porch", "other"),
+        );
+        first.unwrap();
+        second.unwrap();
+        other.unwrap();
+
+        let log = log.lock().unwrap();
+        let at = |event: &str| log.iter().position(|e| e == event).unwrap();
+        // Calls to the same entity run in order, one at a time
+        assert!(at("end first") < at("start second"));
+        // Calls to another entity don't wait for them
+        assert!(at("start other") < at("end first"));
+        assert!(registry.entity_locks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_serialized_call_can_call_same_entity() {
+        let registry = Arc::new(ServiceRegistry::new());
+        registry.register(
+            "light",
+            "turn_on",
+            |_call| async move { Ok(None) },
+            None,
+            SupportsResponse::None,
+        );
+        let inner = Arc::downgrade(&registry);
+        registry.register(
+            "light",
+            "toggle",
+            move |call| {
+                let registry = inner.upgrade().unwrap();
+                async move {
+                    registry
+                        .call(
+                            "light",
+                            "turn_on",
+                            serde_json::json!({"entity_id": "light.kitchen"}),
+                            Context::child_of(&call.context),
+                            false,
+                        )
+                        .await
+                }
+            },
+            None,
+            SupportsResponse::None,
+        );
+        registry.serialize_entity_calls("light");
+
+        let toggle = registry.call(
+            "light",
+            "toggle",
+            serde_json::json!({"entity_id": "light.kitchen"}),
+            Context::new(),
+            false,
+        );
+        tokio::time::timeout(Duration::from_secs(1), toggle)
+            .await
+            .expect("nested call waited for the outer one")
+            .unwrap();
+        assert!(registry.entity_locks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_interceptor_blocks_domain() {
+        let registry = ServiceRegistry::new();
+        let invoked = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let invoked_clone = invoked.clone();
+        registry.register(
+            "lock",
+            "unlock",
+            move |_call| {
+                invoked_clone.store(true, std::sync::atomic::Ordering::SeqCst);
+                async { Ok(None) }
+            },
+            None,
+            SupportsResponse::None,
+        );
+        registry.register(
+            "light",
+            "turn_on",
+            |_call| async { Ok(None) },
+            None,
+            SupportsResponse::None,
+        );
+
+        let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let first = order.clone();
+        registry.add_interceptor(move |call: &ServiceCall| {
+            first.lock().unwrap().push(format!("first {}", call.domain));
+            if call.domain == "lock" {
+                return Err(ServiceError::PermissionDenied("lock is blocked".into()));
+            }
+            Ok(())
+        });
+        let second = order.clone();
+        registry.add_interceptor(move |call: &ServiceCall| {
+            second
+                .lock()
+                .unwrap()
+                .push(format!("second {}", call.domain));
+            Ok(())
+        });
+
+        let err = registry
+            .call(
+                "lock",
+                "unlock",
+                serde_json::json!({}),
+                Context::new(),
+                false,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ServiceError::PermissionDenied(_)));
+        assert!(!invoked.load(std::sync::atomic::Ordering::SeqCst));
+
+        registry
+            .call(
+                "light",
+                "turn_on",
+                serde_json::json!({}),
+                Context::new(),
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["first lock", "first light", "second light"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interceptor_sees_result() {
+        struct Recorder(Arc<std::sync::Mutex<Vec<bool>>>);
+
+        impl ServiceInterceptor for Recorder {
+            fn before_call(&self, _call: &ServiceCall) -> Result<(), ServiceError> {
+                Ok(())
+            }
+
+            fn after_call(&self, _call: &ServiceCall, result: &ServiceResult) {
+                self.0.lock().unwrap().push(result.is_ok());
+            }
+        }
+
+        let registry = ServiceRegistry::new();
+        registry.register(
+            "test",
+            "fail",
+            |_call| async { Err(ServiceError::CallFailed("boom".into())) },
+            None,
+            SupportsResponse::None,
+        );
+        let results = Arc::new(std::sync::Mutex::new(Vec::new()));
+        registry.add_interceptor(Recorder(results.clone()));
+
+        let _ = registry
+            .call("test", "fail", serde_json::json!({}), Context::new(), false)
+            .await;
+        assert_eq!(*results.lock().unwrap(), vec![false]);
+
+        // Aborted by a later interceptor, the earlier one still sees the error
+        registry.add_interceptor(|_call: &ServiceCall| {
+            Err(ServiceError::PermissionDenied("blocked".into()))
+        });
+        let err = registry
+            .call("test", "fail", serde_json::json!({}), Context::new(), false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ServiceError::PermissionDenied(_)));
+        assert_eq!(*results.lock().unwrap(), vec![false, false]);
+    }
+
+    #[tokio::test]
+    async fn test_call_fires_call_service_event() {
+        let event_bus = Arc::new(EventBus::new());
+        let registry = ServiceRegistry::with_event_bus(event_bus.clone());
+        registry.register(
+            "light",
+            "turn_on",
+            |_call| async { Ok(None) },
+            None,
+            SupportsResponse::None,
+        );
+        let mut rx = event_bus.subscribe(ha_core::events::CALL_SERVICE);
+
+        let context = Context::new();
+        registry
+            .call(
+                "light",
+                "turn_on",
+                serde_json::json!({"entity_id": "light.kitchen"}),
+                context.clone(),
+                false,
+            )
+            .await
+            .unwrap();
+
+        let event = rx.try_recv().unwrap();
+        assert_eq!(
+            event.data,
+            serde_json::json!({
+                "domain": "light",
+                "service": "turn_on",
+                "service_data": {"entity_id": "light.kitchen"},
+            })
+        );
+        assert_eq!(event.context.id, context.id);
+    }
+
+    #[test]
+    fn test_registration_events() {
+        let event_bus = Arc::new(EventBus::new());
+        let registry = ServiceRegistry::with_event_bus(event_bus.clone());
+        let mut registered = event_bus.subscribe(ha_core::events::SERVICE_REGISTERED);
+        let mut removed = event_bus.subscribe(ha_core::events::SERVICE_REMOVED);
+
+        for service in ["turn_on", "turn_off"] {
+            registry.register(
+                "light",
+                service,
+                |_call| async { Ok(None) },
+                None,
+                SupportsResponse::None,
+            );
+        }
+        registry.register_with_description(
+            ServiceDescription {
+                domain: "switch".to_string(),
+                service: "toggle".to_string(),
+                name: None,
+                description: None,
+                schema: None,
+                target: None,
+                supports_response: SupportsResponse::None,
+            },
+            |_call| async { Ok(None) },
+        );
+        for expected in [
+            ("light", "turn_on"),
+            ("light", "turn_off"),
+            ("switch", "toggle"),
+        ] {
+            let event = registered.try_recv().unwrap();
+            assert_eq!(
+                event.data,
+                serde_json::json!({"domain": expected.0, "service": expected.1})
+            );
+        }
+
+        assert!(registry.unregister("switch", "toggle"));
+        assert!(!registry.unregister("switch", "toggle"));
+        let event = removed.try_recv().unwrap();
+        assert_eq!(
+            event.data,
+            serde_json::json!({"domain": "switch", "service": "toggle"})
+        );
+        assert!(removed.try_recv().is_err());
+
+        // One event per service removed with the domain
+        assert_eq!(registry.unregister_domain("light"), 2);
+        let mut services: Vec<_> = (0..2)
+            .map(|_| {
+                let event = removed.try_recv().unwrap();
+                assert_eq!(event.data["domain"], "light");
+                event.data["service"].as_str().unwrap().to_string()
+            })
+            .collect();
+        services.sort();
+        assert_eq!(services, vec!["turn_off", "turn_on"]);
+        assert!(removed.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_call_with_wrong_type() {
+        let registry = registry_with_schema();
+
+        let err = registry
+            .call(
+                "light",
+                "turn_on",
+                serde_json::json!({"entity_id": "light.kitchen", "brightness": "high"}),
+                Context::new(),
+                false,
+            )
+            .await
+            .unwrap_err();
+        match err {
+            ServiceError::InvalidData(message) => assert!(message.contains("/brightness")),
+            other => panic!("Expected InvalidData, got {:?}", other),
+        }
+    }
+}
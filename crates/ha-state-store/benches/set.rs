@@ -0,0 +1,92 @@
+//! Allocations and time per `StateStore::set` and `EntityId` operations
+//!
+//! Run with `cargo bench -p ha-state-store`. Allocations are counted with a
+//! wrapping global allocator, so the numbers are exact per call.
+
+use ha_core::{Context, EntityId};
+use ha_event_bus::EventBus;
+use ha_state_store::StateStore;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const ITERATIONS: usize = 100_000;
+const ENTITIES: usize = 100;
+
+/// Run `f` `ITERATIONS` times and print allocations and time per call
+fn bench<T>(name: &str, mut f: impl FnMut(usize) -> T) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        black_box(f(i));
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    println!(
+        "{:<28} {:>8.2} allocs/iter {:>10.0} ns/iter",
+        name,
+        allocations as f64 / ITERATIONS as f64,
+        elapsed.as_nanos() as f64 / ITERATIONS as f64
+    );
+}
+
+fn main() {
+    let store = StateStore::new(Arc::new(EventBus::new()));
+    let ids: Vec<EntityId> = (0..ENTITIES)
+        .map(|i| EntityId::new("sensor", format!("bench_{}", i)).unwrap())
+        .collect();
+    let names: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+    for id in &ids {
+        store.set(id.clone(), "off", HashMap::new(), Context::new());
+    }
+
+    bench("EntityId parse", |i| {
+        names[i % ENTITIES].parse::<EntityId>().unwrap()
+    });
+    bench("EntityId clone", |i| ids[i % ENTITIES].clone());
+    bench("StateStore::get", |i| store.get(&names[i % ENTITIES]));
+    bench("StateStore::set (changed)", |i| {
+        let state = if (i / ENTITIES) % 2 == 0 { "on" } else { "off" };
+        store.set(
+            ids[i % ENTITIES].clone(),
+            state,
+            HashMap::new(),
+            Context::new(),
+        )
+    });
+    bench("StateStore::set (reported)", |i| {
+        store.set(
+            ids[i % ENTITIES].clone(),
+            "off",
+            HashMap::new(),
+            Context::new(),
+        )
+    });
+}
@@ -6,7 +6,7 @@
 
 use dashmap::DashMap;
 use ha_core::events::{StateChangedData, StateReportedData};
-use ha_core::{Context, EntityId, State, MAX_STATE_LENGTH, STATE_UNKNOWN};
+use ha_core::{Context, EntityId, State, MAX_STATE_LENGTH, STATE_UNAVAILABLE, STATE_UNKNOWN};
 use ha_event_bus::EventBus;
 use std::sync::Arc;
 use tracing::{debug, instrument, trace, warn};
@@ -19,10 +19,10 @@ use tracing::{debug, instrument, trace, warn};
 /// - Firing STATE_CHANGED events when states change
 /// - Providing thread-safe concurrent access to states
 pub struct StateStore {
-    /// All entity states keyed by entity_id string
-    states: DashMap<String, State>,
+    /// All entity states keyed by entity_id (queryable by `&str`)
+    states: DashMap<EntityId, State>,
     /// Index of entity_ids by domain
-    domain_index: DashMap<String, Vec<String>>,
+    domain_index: DashMap<String, Vec<EntityId>>,
     /// Event bus for firing state change events
     event_bus: Arc<EventBus>,
 }
@@ -75,6 +75,30 @@ impl StateStore {
         self.set_internal(entity_id, state, attributes, context, force_update)
     }
 
+    /// Set entities to `unavailable`, keeping their attributes
+    ///
+    /// Used when the integration providing the entities goes away, e.g. when
+    /// its config entry is unloaded. Each change fires a regular
+    /// `state_changed` event; entities without a state are skipped.
+    pub fn mark_unavailable<I, S>(&self, entity_ids: I, context: Context) -> Vec<State>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        entity_ids
+            .into_iter()
+            .filter_map(|entity_id| self.get(entity_id.as_ref()))
+            .map(|state| {
+                self.set(
+                    state.entity_id,
+                    STATE_UNAVAILABLE,
+                    state.attributes,
+                    context.clone(),
+                )
+            })
+            .collect()
+    }
+
     /// Internal implementation of state setting
     fn set_internal(
         &self,
@@ -84,11 +108,9 @@ impl StateStore {
         context: Context,
         force_update: bool,
     ) -> State {
-        let entity_id_str = entity_id.to_string();
-        let domain = entity_id.domain().to_string();
         let mut state_str = state.into();
 
-        let old_state = self.states.get(&entity_id_str).map(|s| s.clone());
+        let old_state = self.states.get(entity_id.as_str()).map(|s| s.clone());
 
         // Check if state and attributes are the same
         let same_state = old_state
@@ -109,7 +131,7 @@ impl StateStore {
             // Update last_reported on the existing state
             let mut updated = existing.clone();
             updated.last_reported = Some(now);
-            self.states.insert(entity_id_str.clone(), updated.clone());
+            self.states.insert(entity_id.clone(), updated.clone());
 
             debug!(
                 state = %updated.state,
@@ -159,14 +181,14 @@ impl StateStore {
         );
 
         // Update state
-        self.states.insert(entity_id_str.clone(), new_state.clone());
+        self.states.insert(entity_id.clone(), new_state.clone());
 
         // Update domain index if this is a new entity
         if old_state.is_none() {
             self.domain_index
-                .entry(domain)
+                .entry(entity_id.domain().to_string())
                 .or_default()
-                .push(entity_id_str);
+                .push(entity_id.clone());
         }
 
         // Fire STATE_CHANGED event
@@ -199,21 +221,23 @@ impl StateStore {
     pub fn entity_ids(&self, domain: &str) -> Vec<String> {
         self.domain_index
             .get(domain)
-            .map(|v| v.clone())
+            .map(|ids| ids.iter().map(|id| id.to_string()).collect())
             .unwrap_or_default()
     }
 
     /// Get all states for a domain
     pub fn domain_states(&self, domain: &str) -> Vec<State> {
-        self.entity_ids(domain)
-            .iter()
-            .filter_map(|id| self.get(id))
+        let Some(ids) = self.domain_index.get(domain) else {
+            return Vec::new();
+        };
+        ids.iter()
+            .filter_map(|id| self.states.get(id).map(|s| s.clone()))
             .collect()
     }
 
     /// Get all entity IDs
     pub fn all_entity_ids(&self) -> Vec<String> {
-        self.states.iter().map(|r| r.key().clone()).collect()
+        self.states.iter().map(|r| r.key().to_string()).collect()
     }
 
     /// Get all states
@@ -221,6 +245,15 @@ impl StateStore {
         self.states.iter().map(|r| r.value().clone()).collect()
     }
 
+    /// Get the states that hold a real value, see [`State::is_available`]
+    pub fn available_states(&self) -> Vec<State> {
+        self.states
+            .iter()
+            .filter(|r| r.value().is_available())
+            .map(|r| r.value().clone())
+            .collect()
+    }
+
     /// Get all unique domains
     pub fn domains(&self) -> Vec<String> {
         self.domain_index.iter().map(|r| r.key().clone()).collect()
@@ -231,17 +264,16 @@ impl StateStore {
     /// Fires a STATE_CHANGED event with the old state and None for new_state.
     #[instrument(skip(self, context), fields(entity_id = %entity_id))]
     pub fn remove(&self, entity_id: &EntityId, context: Context) -> Option<State> {
-        let entity_id_str = entity_id.to_string();
         let domain = entity_id.domain();
 
-        let old_state = self.states.remove(&entity_id_str).map(|(_, s)| s);
+        let old_state = self.states.remove(entity_id).map(|(_, s)| s);
 
         if let Some(ref state) = old_state {
             trace!("Removing entity state");
 
             // Update domain index
             if let Some(mut ids) = self.domain_index.get_mut(domain) {
-                ids.retain(|id| id != &entity_id_str);
+                ids.retain(|id| id != entity_id);
             }
 
             // Fire STATE_CHANGED event with None for new_state
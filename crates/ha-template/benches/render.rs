@@ -0,0 +1,68 @@
+//! Time per repeated render, with and without the parsed template cache
+//!
+//! Run with `cargo bench -p ha-template`. Each template is rendered over and
+//! over, as automation conditions and template entities do.
+
+use ha_core::{Context, EntityId};
+use ha_event_bus::EventBus;
+use ha_state_store::StateStore;
+use ha_template::{TemplateEngine, DEFAULT_CACHE_SIZE};
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::Instant;
+
+const ITERATIONS: usize = 20_000;
+
+const TEMPLATES: &[(&str, &str)] = &[
+    ("simple", "{{ states('sensor.temperature') }}"),
+    (
+        "condition",
+        "{{ is_state('light.porch', 'on') and states('sensor.temperature') | float > 20 }}",
+    ),
+    (
+        "loop",
+        "{% for state in states.sensor %}{{ state.name }}: {{ state.state }}\n{% endfor %}",
+    ),
+];
+
+/// Render `template` `ITERATIONS` times and return the time per render
+fn bench(engine: &TemplateEngine, template: &str) -> f64 {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(engine.render(template).unwrap());
+    }
+    start.elapsed().as_nanos() as f64 / ITERATIONS as f64
+}
+
+fn main() {
+    let store = Arc::new(StateStore::new(Arc::new(EventBus::new())));
+    store.set(
+        EntityId::new("sensor", "temperature").unwrap(),
+        "21.5",
+        HashMap::new(),
+        Context::new(),
+    );
+    store.set(
+        EntityId::new("light", "porch").unwrap(),
+        "on",
+        HashMap::new(),
+        Context::new(),
+    );
+    let engine = TemplateEngine::new(store);
+
+    for (name, template) in TEMPLATES {
+        engine.set_cache_size(0);
+        let uncached = bench(&engine, template);
+        engine.set_cache_size(DEFAULT_CACHE_SIZE);
+        let cached = bench(&engine, template);
+
+        println!(
+            "{:<12} uncached {:>8.0} ns/iter   cached {:>8.0} ns/iter   {:>5.1}x",
+            name,
+            uncached,
+            cached,
+            uncached / cached
+        );
+    }
+}
@@ -3,15 +3,26 @@
 //! Provides Jinja2-compatible template rendering with Home Assistant-specific
 //! functions and filters.
 
-use crate::error::TemplateResult;
+use crate::error::{TemplateError, TemplateResult};
 use crate::filters;
-use crate::globals;
+use crate::globals::{self, HomeLocation};
+use crate::render_info::{self, RenderInfo};
 use crate::states::{self, StatesObject};
+use chrono_tz::Tz;
+use ha_config::CoreConfig;
+use ha_registries::Registries;
 use ha_state_store::StateStore;
-use minijinja::{Environment, Value};
-use std::sync::Arc;
+use lru::LruCache;
+use minijinja::value::{Kwargs, Rest};
+use minijinja::{Environment, Template, Value};
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
 use tracing::debug;
 
+/// Number of parsed templates kept by default, see [`TemplateEngine::set_cache_size`]
+pub const DEFAULT_CACHE_SIZE: usize = 512;
+
 /// Template engine with Home Assistant extensions
 ///
 /// The engine provides:
@@ -19,15 +30,38 @@ use tracing::debug;
 /// - Time functions like `now()`, `utcnow()`, `relative_time()`
 /// - State functions like `is_state()`, `state_attr()`, `has_value()`
 /// - Filters like `round`, `regex_replace`, `to_json`, `slugify`
+/// - Registry lookups like `expand()` when created with registries
 pub struct TemplateEngine {
-    env: Environment<'static>,
+    /// Environment with the HA extensions, custom templates and cached templates
+    env: RwLock<Environment<'static>>,
+    /// Which rendered sources have a parsed template in `env`
+    cache: Mutex<TemplateCache>,
     states: Arc<StatesObject>,
+    registries: Option<Arc<Registries>>,
+    /// Configured time zone for local times and formatting (system zone if unset)
+    time_zone: Arc<RwLock<Option<Tz>>>,
+    /// Configured home location and length unit for `distance()` and `closest()`
+    home: Arc<RwLock<HomeLocation>>,
 }
 
 impl TemplateEngine {
     /// Create a new template engine with access to the state machine
     pub fn new(state_machine: Arc<StateStore>) -> Self {
+        Self::build(state_machine, None)
+    }
+
+    /// Create a new template engine with access to the state machine and registries
+    ///
+    /// Registry-backed functions (areas, devices, labels) resolve to nothing
+    /// when the engine is created without registries.
+    pub fn with_registries(state_machine: Arc<StateStore>, registries: Arc<Registries>) -> Self {
+        Self::build(state_machine, Some(registries))
+    }
+
+    fn build(state_machine: Arc<StateStore>, registries: Option<Arc<Registries>>) -> Self {
         let states = Arc::new(StatesObject::new(state_machine));
+        let time_zone = Arc::new(RwLock::new(None));
+        let home = Arc::new(RwLock::new(HomeLocation::default()));
         let mut env = Environment::new();
 
         // Configure environment
@@ -39,10 +73,78 @@ impl TemplateEngine {
         // Register global functions
         Self::register_globals(&mut env, states.clone());
 
+        // Register registry-backed functions
+        Self::register_registry_globals(&mut env, states.clone(), registries.clone());
+
+        // Register time zone aware time functions and timestamp formatting
+        Self::register_timestamp_functions(&mut env, time_zone.clone());
+
+        // Register functions relative to the home location
+        Self::register_location_functions(
+            &mut env,
+            states.clone(),
+            registries.clone(),
+            home.clone(),
+        );
+
         // Register tests
-        Self::register_tests(&mut env);
+        Self::register_tests(&mut env, states.clone());
+
+        Self {
+            env: RwLock::new(env),
+            cache: Mutex::new(TemplateCache::new(DEFAULT_CACHE_SIZE)),
+            states,
+            registries,
+            time_zone,
+            home,
+        }
+    }
 
-        Self { env, states }
+    /// Set how many parsed templates are kept for reuse
+    ///
+    /// Templates are cached by their source, evicting the least recently
+    /// rendered one when full. A size of 0 disables caching.
+    pub fn set_cache_size(&self, size: usize) {
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.resize(size);
+        if !cache.evicted.is_empty() {
+            let mut env = self.env.write().unwrap_or_else(PoisonError::into_inner);
+            cache.remove_evicted(&mut env);
+        }
+    }
+
+    /// Get the name of the cached template for `source`, parsing it if needed
+    ///
+    /// Returns `None` when caching is disabled, the source doesn't parse or
+    /// is rendered for the first time. Only sources rendered again are added
+    /// to the environment, so one-off templates never take its write lock.
+    fn cached_template(&self, source: &str) -> Option<String> {
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        if cache.capacity == 0 {
+            return None;
+        }
+        match cache.entries.get(source) {
+            Some(Some(name)) => return Some(name.clone()),
+            Some(None) => {}
+            None => {
+                cache.remember(source, None);
+                return None;
+            }
+        }
+
+        let mut env = self.env.write().unwrap_or_else(PoisonError::into_inner);
+        cache.remove_evicted(&mut env);
+        let name = cache.next_name();
+        if env
+            .add_template_owned(name.clone(), source.to_string())
+            .is_err()
+        {
+            // Leave reporting the syntax error to the uncached path
+            cache.entries.pop(source);
+            return None;
+        }
+        cache.remember(source, Some(name.clone()));
+        Some(name)
     }
 
     fn register_filters(env: &mut Environment<'static>) {
@@ -51,6 +153,7 @@ impl TemplateEngine {
         env.add_filter("regex_replace", filters::regex_replace);
         env.add_filter("regex_findall", filters::regex_findall);
         env.add_filter("regex_match", filters::regex_match);
+        env.add_filter("regex_search", filters::regex_search);
 
         // Type conversion
         env.add_filter("float", filters::to_float);
@@ -75,6 +178,9 @@ impl TemplateEngine {
         env.add_filter("acos", filters::acos);
         env.add_filter("atan", filters::atan);
         env.add_filter("atan2", filters::atan2);
+        env.add_filter("bitwise_and", filters::bitwise_and);
+        env.add_filter("bitwise_or", filters::bitwise_or);
+        env.add_filter("bitwise_xor", filters::bitwise_xor);
 
         // Aggregates
         env.add_filter("average", filters::average);
@@ -100,12 +206,9 @@ impl TemplateEngine {
         env.add_global("states", Value::from_object((*states_clone).clone()));
 
         // Time functions
-        env.add_function("now", globals::now);
         env.add_function("utcnow", globals::utcnow);
-        env.add_function("today_at", globals::today_at);
         env.add_function("as_timestamp", globals::as_timestamp);
         env.add_function("as_datetime", globals::as_datetime);
-        env.add_function("as_local", globals::as_local);
         env.add_function("strptime", globals::strptime);
         env.add_function("timedelta", globals::timedelta);
         env.add_function("as_timedelta", globals::as_timedelta);
@@ -144,7 +247,7 @@ impl TemplateEngine {
 
         // Utility functions
         env.add_function("iif", globals::iif);
-        env.add_function("distance", globals::distance);
+        env.add_function("is_number", filters::is_number);
         env.add_function("typeof", globals::typeof_fn);
         env.add_function("range", globals::range_fn);
 
@@ -190,39 +293,225 @@ impl TemplateEngine {
         });
     }
 
-    fn register_tests(env: &mut Environment<'static>) {
+    fn register_timestamp_functions(
+        env: &mut Environment<'static>,
+        time_zone: Arc<RwLock<Option<Tz>>>,
+    ) {
+        let tz = time_zone.clone();
+        env.add_function("now", move || globals::now(tz.read().ok().and_then(|z| *z)));
+        let tz = time_zone.clone();
+        env.add_function("today_at", move |time_str: &str| {
+            globals::today_at(tz.read().ok().and_then(|z| *z), time_str)
+        });
+        let tz = time_zone.clone();
+        env.add_function("as_local", move |value: Value| {
+            globals::as_local(tz.read().ok().and_then(|z| *z), value)
+        });
+
+        // Each is available both as a function and as a filter, like in HA
+        let tz = time_zone.clone();
+        let timestamp_custom =
+            move |value: Value, format: Option<String>, local: Option<bool>, kwargs: Kwargs| {
+                let zone = tz.read().ok().and_then(|z| *z);
+                globals::timestamp_custom(zone, value, format, local, kwargs)
+            };
+        env.add_function("timestamp_custom", timestamp_custom.clone());
+        env.add_filter("timestamp_custom", timestamp_custom);
+
+        let timestamp_local = move |value: Value, kwargs: Kwargs| {
+            let zone = time_zone.read().ok().and_then(|z| *z);
+            globals::timestamp_local(zone, value, kwargs)
+        };
+        env.add_function("timestamp_local", timestamp_local.clone());
+        env.add_filter("timestamp_local", timestamp_local);
+
+        env.add_function("timestamp_utc", globals::timestamp_utc);
+        env.add_filter("timestamp_utc", globals::timestamp_utc);
+    }
+
+    fn register_location_functions(
+        env: &mut Environment<'static>,
+        states: Arc<StatesObject>,
+        registries: Option<Arc<Registries>>,
+        home: Arc<RwLock<HomeLocation>>,
+    ) {
+        let states_for_distance = states.clone();
+        let home_for_distance = home.clone();
+        env.add_function("distance", move |args: Rest<Value>| {
+            let home = home_for_distance
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
+            globals::distance(&states_for_distance, &home, &args)
+        });
+
+        env.add_function("closest", move |args: Rest<Value>| {
+            let home = home.read().unwrap_or_else(PoisonError::into_inner);
+            globals::closest(&states, registries.as_deref(), &home, &args)
+        });
+    }
+
+    fn register_registry_globals(
+        env: &mut Environment<'static>,
+        states: Arc<StatesObject>,
+        registries: Option<Arc<Registries>>,
+    ) {
+        let registries_for_expand = registries.clone();
+        env.add_function("expand", move |args: Rest<Value>| {
+            globals::expand(&states, registries_for_expand.as_deref(), &args)
+        });
+
+        let registries_for_device_id = registries.clone();
+        env.add_function("device_id", move |lookup: &str| {
+            globals::device_id(registries_for_device_id.as_deref(), lookup)
+        });
+
+        let registries_for_device_entities = registries.clone();
+        env.add_function("device_entities", move |device_id: &str| {
+            globals::device_entities(registries_for_device_entities.as_deref(), device_id)
+        });
+
+        let registries_for_area_id = registries.clone();
+        env.add_function("area_id", move |lookup: &str| {
+            globals::area_id(registries_for_area_id.as_deref(), lookup)
+        });
+
+        let registries_for_area_name = registries.clone();
+        env.add_function("area_name", move |lookup: &str| {
+            globals::area_name(registries_for_area_name.as_deref(), lookup)
+        });
+
+        let registries_for_area_entities = registries.clone();
+        env.add_function("area_entities", move |lookup: &str| {
+            globals::area_entities(registries_for_area_entities.as_deref(), lookup)
+        });
+
+        let registries_for_area_devices = registries.clone();
+        env.add_function("area_devices", move |lookup: &str| {
+            globals::area_devices(registries_for_area_devices.as_deref(), lookup)
+        });
+
+        let registries_for_labels = registries.clone();
+        env.add_function("labels", move |lookup: Option<&str>| {
+            globals::labels(registries_for_labels.as_deref(), lookup)
+        });
+
+        let registries_for_label_id = registries.clone();
+        env.add_function("label_id", move |name: &str| {
+            globals::label_id(registries_for_label_id.as_deref(), name)
+        });
+
+        let registries_for_label_name = registries.clone();
+        env.add_function("label_name", move |label_id: &str| {
+            globals::label_name(registries_for_label_name.as_deref(), label_id)
+        });
+
+        let registries_for_label_entities = registries.clone();
+        env.add_function("label_entities", move |lookup: &str| {
+            globals::label_entities(registries_for_label_entities.as_deref(), lookup)
+        });
+
+        let registries_for_label_devices = registries.clone();
+        env.add_function("label_devices", move |lookup: &str| {
+            globals::label_devices(registries_for_label_devices.as_deref(), lookup)
+        });
+
+        env.add_function("label_areas", move |lookup: &str| {
+            globals::label_areas(registries.as_deref(), lookup)
+        });
+    }
+
+    fn register_tests(env: &mut Environment<'static>, states: Arc<StatesObject>) {
         env.add_test("number", filters::is_number);
         env.add_test("string", filters::is_string);
         env.add_test("list", filters::is_list);
         env.add_test("defined", filters::is_defined);
-        env.add_test("match", filters::match_test);
+        env.add_test("match", filters::regex_match);
+        env.add_test("search", filters::regex_search);
         env.add_test("contains", filters::contains);
+
+        // State functions work as tests on entity IDs, like in HA
+        let states_for_is_state = states.clone();
+        env.add_test("is_state", move |entity_id: &str, state: Value| {
+            states::is_state_fn(states_for_is_state.clone(), entity_id, state)
+        });
+        let states_for_is_state_attr = states.clone();
+        env.add_test(
+            "is_state_attr",
+            move |entity_id: &str, attribute: &str, value: Value| {
+                states::is_state_attr_fn(
+                    states_for_is_state_attr.clone(),
+                    entity_id,
+                    attribute,
+                    value,
+                )
+            },
+        );
+        env.add_test("has_value", move |entity_id: &str| {
+            states::has_value_fn(states.clone(), entity_id)
+        });
     }
 
     /// Render a template string
     pub fn render(&self, template: &str) -> TemplateResult<String> {
         debug!("Rendering template: {}", template);
 
-        let tmpl = self.env.template_from_str(template)?;
-        let result = tmpl.render(())?;
-
-        Ok(result)
+        self.render_with_context(template, ())
     }
 
     /// Render a template with additional context variables
+    ///
+    /// Reuses the parsed form of templates rendered before. Cached templates
+    /// live in the environment under a generated name, so imports of custom
+    /// templates resolve the same as for uncached ones.
     pub fn render_with_context(
         &self,
         template: &str,
         context: impl serde::Serialize,
     ) -> TemplateResult<String> {
-        let tmpl = self.env.template_from_str(template)?;
-        let result = tmpl.render(context)?;
-        Ok(result)
+        let name = self.cached_template(template);
+        let env = self.env.read().unwrap_or_else(PoisonError::into_inner);
+        let tmpl: Template<'_, '_> = match name.as_deref().map(|name| env.get_template(name)) {
+            Some(Ok(tmpl)) => tmpl,
+            // Not cached, or evicted by another render in the meantime
+            _ => env.template_from_str(template)?,
+        };
+        Ok(tmpl.render(context)?)
+    }
+
+    /// Render a template with extra variables such as `trigger`, `this` or `repeat`
+    ///
+    /// The variables only apply to this render and sit alongside the engine's
+    /// globals (`states`, functions) without modifying the shared environment.
+    pub fn render_with_variables(
+        &self,
+        template: &str,
+        vars: HashMap<String, serde_json::Value>,
+    ) -> TemplateResult<String> {
+        let context: std::collections::BTreeMap<String, Value> = vars
+            .into_iter()
+            .map(|(k, v)| (k, states::json_to_value(v)))
+            .collect();
+
+        self.render_with_context(template, Value::from_object(context))
+    }
+
+    /// Render a template with variables, recording what it read
+    ///
+    /// The returned [`RenderInfo`] lists the entities, domains and time
+    /// sources the template touched, even when rendering failed, so callers
+    /// can re-render when one of them changes.
+    pub fn render_with_info(
+        &self,
+        template: &str,
+        vars: HashMap<String, serde_json::Value>,
+    ) -> (TemplateResult<String>, RenderInfo) {
+        render_info::collect(|| self.render_with_variables(template, vars))
     }
 
     /// Evaluate a template and return the value
     pub fn evaluate(&self, template: &str) -> TemplateResult<Value> {
-        let expr = self.env.compile_expression(template)?;
+        let env = self.env.read().unwrap_or_else(PoisonError::into_inner);
+        let expr = env.compile_expression(template)?;
         let result = expr.eval(())?;
         Ok(result)
     }
@@ -233,7 +522,8 @@ impl TemplateEngine {
         template: &str,
         context: impl serde::Serialize,
     ) -> TemplateResult<Value> {
-        let expr = self.env.compile_expression(template)?;
+        let env = self.env.read().unwrap_or_else(PoisonError::into_inner);
+        let expr = env.compile_expression(template)?;
         let result = expr.eval(context)?;
         Ok(result)
     }
@@ -248,6 +538,43 @@ impl TemplateEngine {
         &self.states
     }
 
+    /// Set the time zone used for local times, like `now()`, and formatting
+    ///
+    /// Takes an IANA time zone name like `Europe/Amsterdam`, as configured in
+    /// `homeassistant: time_zone:`.
+    pub fn set_time_zone(&self, time_zone: &str) -> TemplateResult<()> {
+        let tz: Tz = time_zone
+            .parse()
+            .map_err(|_| TemplateError::InvalidArgument {
+                function: "set_time_zone".to_string(),
+                message: format!("unknown time zone '{}'", time_zone),
+            })?;
+        if let Ok(mut current) = self.time_zone.write() {
+            *current = Some(tz);
+        }
+        Ok(())
+    }
+
+    /// Apply the `homeassistant:` core config
+    ///
+    /// Sets the time zone, and the home location and length unit used by
+    /// `distance()` and `closest()`.
+    pub fn set_core_config(&self, config: &CoreConfig) -> TemplateResult<()> {
+        *self.home.write().unwrap_or_else(PoisonError::into_inner) =
+            HomeLocation::from_config(config);
+        self.set_time_zone(&config.time_zone)
+    }
+
+    /// Get the configured time zone, if one was set
+    pub fn time_zone(&self) -> Option<Tz> {
+        self.time_zone.read().ok().and_then(|tz| *tz)
+    }
+
+    /// Get the registries backing registry lookups, if any
+    pub fn registries(&self) -> Option<&Arc<Registries>> {
+        self.registries.as_ref()
+    }
+
     /// Load custom Jinja templates from config_dir/custom_templates/
     ///
     /// Custom templates can define macros that are then imported in other templates:
@@ -258,8 +585,6 @@ impl TemplateEngine {
     ///
     /// Idempotent: safe to call multiple times (overwrites existing templates).
     pub fn load_custom_templates(&mut self, config_dir: &std::path::Path) -> TemplateResult<usize> {
-        use crate::error::TemplateError;
-
         let custom_dir = config_dir.join("custom_templates");
         if !custom_dir.exists() {
             debug!(
@@ -299,6 +624,8 @@ impl TemplateEngine {
                 let content = normalize_escape_sequences(&content);
 
                 self.env
+                    .get_mut()
+                    .unwrap_or_else(PoisonError::into_inner)
                     .add_template_owned(name.clone(), content)
                     .map_err(|e| TemplateError::ParseError {
                         name: name.clone(),
@@ -332,6 +659,65 @@ impl TemplateEngine {
     }
 }
 
+/// Least recently used set of rendered template sources
+///
+/// Maps each source to the name its template was added to the environment
+/// under, or `None` while it has only been rendered once.
+struct TemplateCache {
+    capacity: usize,
+    entries: LruCache<String, Option<String>>,
+    /// Templates of evicted sources, removed from the environment the next
+    /// time it is locked for writing
+    evicted: Vec<String>,
+    /// Counter for generated template names
+    next_id: u64,
+}
+
+impl TemplateCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: LruCache::new(NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN)),
+            evicted: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    fn next_name(&mut self) -> String {
+        self.next_id += 1;
+        format!("<cached:{}>", self.next_id)
+    }
+
+    /// Add or update a source, making it the most recently used
+    fn remember(&mut self, source: &str, name: Option<String>) {
+        if let Some((evicted, Some(name))) = self.entries.push(source.to_string(), name) {
+            if evicted != source {
+                self.evicted.push(name);
+            }
+        }
+    }
+
+    /// Change the capacity, evicting the least recently used sources
+    fn resize(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            if let Some((_, Some(name))) = self.entries.pop_lru() {
+                self.evicted.push(name);
+            }
+        }
+        if let Some(capacity) = NonZeroUsize::new(capacity) {
+            self.entries.resize(capacity);
+        }
+    }
+
+    /// Remove the templates of evicted sources from the environment
+    fn remove_evicted(&mut self, env: &mut Environment<'static>) {
+        for name in self.evicted.drain(..) {
+            env.remove_template(&name);
+        }
+    }
+}
+
 /// Create a standalone template engine for testing
 pub fn create_test_engine() -> TemplateEngine {
     use ha_event_bus::EventBus;
@@ -465,6 +851,45 @@ mod tests {
         assert_eq!(result, "Hello, Test!");
     }
 
+    #[test]
+    fn test_render_with_variables_nested() {
+        let engine = make_test_engine();
+        let vars = HashMap::from([
+            (
+                "trigger".to_string(),
+                serde_json::json!({
+                    "platform": "state",
+                    "to_state": {"state": "on", "attributes": {"brightness": 128}},
+                    "ids": [1, 2, 3],
+                    "missing": null
+                }),
+            ),
+            ("repeat".to_string(), serde_json::json!({"index": 2})),
+        ]);
+        let result = engine
+            .render_with_variables(
+                "{{ trigger.platform }}:{{ trigger.to_state.attributes.brightness }}:\
+                 {{ trigger.ids | sum }}:{{ trigger.missing is none }}:{{ repeat.index }}",
+                vars,
+            )
+            .unwrap();
+        assert_eq!(result, "state:128:6:true:2");
+    }
+
+    #[test]
+    fn test_render_with_variables_keeps_globals() {
+        let engine = make_test_engine();
+        let vars = HashMap::from([("target".to_string(), serde_json::json!("light.living_room"))]);
+        let result = engine
+            .render_with_variables("{{ states(target) }}", vars)
+            .unwrap();
+        assert_eq!(result, "on");
+
+        // Variables do not leak into later renders
+        let result = engine.render("{{ target is defined }}").unwrap();
+        assert_eq!(result, "false");
+    }
+
     // ==================== States Tests ====================
 
     #[test]
@@ -796,6 +1221,66 @@ Light is off
         assert_eq!(result, "v2");
     }
 
+    // ==================== Template Cache Tests ====================
+
+    #[test]
+    fn test_template_cache_reuses_and_evicts() {
+        let engine = make_test_engine();
+        engine.set_cache_size(2);
+        let seen = |source: &str| engine.cache.lock().unwrap().entries.contains(source);
+        let parsed = |source: &str| {
+            engine
+                .cache
+                .lock()
+                .unwrap()
+                .entries
+                .peek(source)
+                .is_some_and(Option::is_some)
+        };
+
+        // Templates are only parsed into the environment when rendered again
+        let first = "{{ states('light.living_room') }}";
+        let second = "{{ states('switch.kitchen') }}";
+        assert_eq!(engine.render(first).unwrap(), "on");
+        assert!(seen(first) && !parsed(first));
+        assert_eq!(engine.render(first).unwrap(), "on");
+        assert!(parsed(first));
+        assert_eq!(engine.render(second).unwrap(), "off");
+        assert_eq!(engine.render(first).unwrap(), "on");
+
+        // The least recently rendered template makes room
+        assert_eq!(engine.render("{{ 1 + 1 }}").unwrap(), "2");
+        assert_eq!(engine.cache.lock().unwrap().entries.len(), 2);
+        assert!(parsed(first));
+        assert!(!seen(second));
+        assert_eq!(engine.render(second).unwrap(), "off");
+
+        // Variables differ per render of the same cached template
+        let template = "{{ name }}";
+        for name in ["a", "b", "c"] {
+            assert_eq!(
+                engine
+                    .render_with_context(template, serde_json::json!({"name": name}))
+                    .unwrap(),
+                name
+            );
+        }
+        assert!(parsed(template));
+
+        // Syntax errors are reported and not cached
+        assert!(engine.render("{{ unclosed").is_err());
+        assert!(engine.render("{{ unclosed").is_err());
+        assert!(!seen("{{ unclosed"));
+
+        // Evicted templates are removed from the environment
+        engine.set_cache_size(0);
+        assert_eq!(engine.cache.lock().unwrap().entries.len(), 0);
+        assert!(engine.cache.lock().unwrap().evicted.is_empty());
+        assert_eq!(engine.render(first).unwrap(), "on");
+        assert_eq!(engine.render(first).unwrap(), "on");
+        assert_eq!(engine.cache.lock().unwrap().entries.len(), 0);
+    }
+
     // ==================== Escape Sequence Normalization Tests ====================
 
     #[test]
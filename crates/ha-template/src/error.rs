@@ -1,11 +1,40 @@
 //! Error types for template rendering
 
+use regex::Regex;
+use std::fmt;
 use std::path::PathBuf;
+use std::sync::OnceLock;
 use thiserror::Error;
 
 /// Result type for template operations
 pub type TemplateResult<T> = Result<T, TemplateError>;
 
+/// Position of an error within the template source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, when minijinja reported a span for the error
+    pub column: Option<usize>,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}", self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ", column {}", column)?;
+        }
+        Ok(())
+    }
+}
+
+/// Format an optional location as a message suffix
+fn at(location: &Option<SourceLocation>) -> String {
+    location
+        .map(|loc| format!(" (at {})", loc))
+        .unwrap_or_default()
+}
+
 /// Errors that can occur during template rendering
 #[derive(Debug, Error)]
 pub enum TemplateError {
@@ -14,16 +43,32 @@ pub enum TemplateError {
     CompileError { message: String },
 
     /// Failed to render template
-    #[error("failed to render template: {message}")]
-    RenderError { message: String },
+    #[error("failed to render template: {message}{}", at(.location))]
+    RenderError {
+        message: String,
+        location: Option<SourceLocation>,
+    },
 
     /// Invalid template syntax
-    #[error("invalid template syntax: {message}")]
-    SyntaxError { message: String },
+    #[error("invalid template syntax: {message}{}", at(.location))]
+    SyntaxError {
+        message: String,
+        location: Option<SourceLocation>,
+    },
 
     /// Undefined variable in template
-    #[error("undefined variable: {name}")]
-    UndefinedVariable { name: String },
+    #[error("undefined variable: {name}{}", at(.location))]
+    UndefinedVariable {
+        name: String,
+        location: Option<SourceLocation>,
+    },
+
+    /// Template accessed an entity that does not exist
+    #[error("entity '{entity_id}' is not defined{}", at(.location))]
+    UndefinedEntity {
+        entity_id: String,
+        location: Option<SourceLocation>,
+    },
 
     /// Type error in template
     #[error("type error: {message}")]
@@ -50,18 +95,109 @@ pub enum TemplateError {
     ParseError { name: String, message: String },
 }
 
+impl TemplateError {
+    /// Where in the template source the error occurred, if known
+    pub fn location(&self) -> Option<SourceLocation> {
+        match self {
+            TemplateError::RenderError { location, .. }
+            | TemplateError::SyntaxError { location, .. }
+            | TemplateError::UndefinedVariable { location, .. }
+            | TemplateError::UndefinedEntity { location, .. } => *location,
+            _ => None,
+        }
+    }
+}
+
+/// Source text around a failing expression
+struct Snippet<'a> {
+    /// The expression minijinja flagged
+    span: &'a str,
+    /// The line up to and including the flagged expression
+    line_prefix: &'a str,
+}
+
+/// Compute the error location and the failing source text from a minijinja error
+fn locate(err: &minijinja::Error) -> (Option<SourceLocation>, Option<Snippet<'_>>) {
+    let Some(line) = err.line() else {
+        return (None, None);
+    };
+    let source = err.template_source();
+    let snippet = source.zip(err.range()).and_then(|(source, range)| {
+        let span = source.get(range.clone())?;
+        let line_start = source[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        Some((
+            line_start,
+            range.start,
+            span,
+            &source[line_start..range.end],
+        ))
+    });
+
+    match (source, snippet) {
+        (Some(source), Some((line_start, start, span, line_prefix))) => {
+            let column = source[line_start..start].chars().count() + 1;
+            let location = SourceLocation {
+                line,
+                column: Some(column),
+            };
+            (Some(location), Some(Snippet { span, line_prefix }))
+        }
+        _ => (Some(SourceLocation { line, column: None }), None),
+    }
+}
+
+/// Extract the entity id of the `states.domain.object` or
+/// `states('domain.object')` expression minijinja flagged
+///
+/// Only an entity access that reaches into the flagged span counts, so an
+/// earlier, unrelated `states(...)` on the same line isn't blamed for the
+/// error.
+fn referenced_entity(snippet: &Snippet<'_>) -> Option<String> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(
+            r#"states(?:\.([a-z0-9_]+)\.([a-z0-9_]+)|[\[(]\s*['"]([a-z0-9_]+\.[a-z0-9_]+)['"])"#,
+        )
+        .expect("valid regex")
+    });
+    let span_start = snippet.line_prefix.len() - snippet.span.len();
+    let caps = re
+        .captures_iter(snippet.line_prefix)
+        .filter(|caps| caps.get(0).is_some_and(|m| m.end() > span_start))
+        .last()?;
+    match (caps.get(1), caps.get(2), caps.get(3)) {
+        (Some(domain), Some(object_id), _) => {
+            Some(format!("{}.{}", domain.as_str(), object_id.as_str()))
+        }
+        (_, _, Some(entity_id)) => Some(entity_id.as_str().to_string()),
+        _ => None,
+    }
+}
+
 impl From<minijinja::Error> for TemplateError {
     fn from(err: minijinja::Error) -> Self {
+        let (location, snippet) = locate(&err);
+        let message = err
+            .detail()
+            .map(str::to_string)
+            .unwrap_or_else(|| err.kind().to_string());
+
         match err.kind() {
-            minijinja::ErrorKind::SyntaxError => TemplateError::SyntaxError {
-                message: err.to_string(),
-            },
-            minijinja::ErrorKind::UndefinedError => TemplateError::UndefinedVariable {
-                name: err.to_string(),
-            },
-            _ => TemplateError::RenderError {
-                message: err.to_string(),
-            },
+            minijinja::ErrorKind::SyntaxError => TemplateError::SyntaxError { message, location },
+            minijinja::ErrorKind::UndefinedError => {
+                let entity_id = snippet.as_ref().and_then(referenced_entity);
+                match entity_id {
+                    Some(entity_id) => TemplateError::UndefinedEntity {
+                        entity_id,
+                        location,
+                    },
+                    None => TemplateError::UndefinedVariable {
+                        name: snippet.map_or(message, |snippet| snippet.span.to_string()),
+                        location,
+                    },
+                }
+            }
+            _ => TemplateError::RenderError { message, location },
         }
     }
 }
@@ -109,12 +109,51 @@ pub fn regex_findall(value: &str, pattern: &str) -> Result<Value, Error> {
     Ok(Value::from(matches))
 }
 
-/// Test if a regex pattern matches
+/// Test if a regex pattern matches at the start of a value, like `re.match`
 ///
-/// Uses cached regex compilation for repeated patterns.
-pub fn regex_match(value: &str, pattern: &str) -> Result<bool, Error> {
-    let re = get_or_compile_regex(pattern)?;
-    Ok(re.is_match(value))
+/// `ignorecase` can be given positionally or as a keyword. Non-string values
+/// are matched against their string form.
+pub fn regex_match(
+    value: Value,
+    pattern: &str,
+    ignorecase: Option<bool>,
+    kwargs: Kwargs,
+) -> Result<bool, Error> {
+    let pattern = format!("\\A(?:{})", pattern);
+    regex_is_match(&value, &pattern, ignorecase, &kwargs)
+}
+
+/// Test if a regex pattern matches anywhere in a value, like `re.search`
+pub fn regex_search(
+    value: Value,
+    pattern: &str,
+    ignorecase: Option<bool>,
+    kwargs: Kwargs,
+) -> Result<bool, Error> {
+    regex_is_match(&value, pattern, ignorecase, &kwargs)
+}
+
+fn regex_is_match(
+    value: &Value,
+    pattern: &str,
+    ignorecase: Option<bool>,
+    kwargs: &Kwargs,
+) -> Result<bool, Error> {
+    let ignorecase = match ignorecase {
+        Some(ignorecase) => ignorecase,
+        None => kwargs.get::<Option<bool>>("ignorecase")?.unwrap_or(false),
+    };
+    kwargs.assert_all_used()?;
+
+    let re = if ignorecase {
+        get_or_compile_regex(&format!("(?i){}", pattern))?
+    } else {
+        get_or_compile_regex(pattern)?
+    };
+    Ok(match value.as_str() {
+        Some(s) => re.is_match(s),
+        None => re.is_match(&value.to_string()),
+    })
 }
 
 // ==================== Type Conversion Filters ====================
@@ -246,16 +285,18 @@ pub fn to_bool(value: Value, default: Option<bool>) -> bool {
 // ==================== Type Checking Filters ====================
 
 /// Check if value is a number (integer or float)
+///
+/// Like HA, `nan` and infinities are not numbers.
 pub fn is_number(value: Value) -> bool {
     if value.as_i64().is_some() {
         return true;
     }
-    if value_to_f64(&value).is_some() {
-        return true;
+    if let Some(f) = value_to_f64(&value) {
+        return f.is_finite();
     }
     // Also check if it's a string that can be parsed as a number
     if let Some(s) = value.as_str() {
-        return s.trim().parse::<f64>().is_ok();
+        return s.trim().parse::<f64>().is_ok_and(f64::is_finite);
     }
     false
 }
@@ -411,6 +452,43 @@ pub fn atan2(y: f64, x: f64) -> f64 {
     y.atan2(x)
 }
 
+/// Coerce a bitwise filter operand to an integer
+///
+/// Accepts integers, floats without a fractional part and integer strings,
+/// like state values.
+fn bitwise_operand(filter: &str, value: &Value) -> Result<i64, Error> {
+    let int = if let Some(i) = value.as_i64() {
+        Some(i)
+    } else if let Some(s) = value.as_str() {
+        s.trim().parse::<i64>().ok()
+    } else {
+        value_to_f64(value)
+            .filter(|f| f.fract() == 0.0 && f.abs() < i64::MAX as f64)
+            .map(|f| f as i64)
+    };
+    int.ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidOperation,
+            format!("{} expects integers, got '{}'", filter, value),
+        )
+    })
+}
+
+/// Bitwise AND of two integers, e.g. to test `supported_features` bits
+pub fn bitwise_and(value: Value, other: Value) -> Result<i64, Error> {
+    Ok(bitwise_operand("bitwise_and", &value)? & bitwise_operand("bitwise_and", &other)?)
+}
+
+/// Bitwise OR of two integers
+pub fn bitwise_or(value: Value, other: Value) -> Result<i64, Error> {
+    Ok(bitwise_operand("bitwise_or", &value)? | bitwise_operand("bitwise_or", &other)?)
+}
+
+/// Bitwise XOR of two integers
+pub fn bitwise_xor(value: Value, other: Value) -> Result<i64, Error> {
+    Ok(bitwise_operand("bitwise_xor", &value)? ^ bitwise_operand("bitwise_xor", &other)?)
+}
+
 // ==================== Aggregate Filters ====================
 
 /// Calculate average of a sequence
@@ -633,11 +711,6 @@ fn flatten_recursive(value: &Value, depth: i32) -> Result<Vec<Value>, Error> {
 
 // ==================== Tests (Jinja2 tests, not unit tests) ====================
 
-/// Test if value matches regex
-pub fn match_test(value: &str, pattern: &str) -> Result<bool, Error> {
-    regex_match(value, pattern)
-}
-
 /// Test if value is defined (not undefined)
 pub fn is_defined(value: Value) -> bool {
     !value.is_undefined()
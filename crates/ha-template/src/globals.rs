@@ -2,10 +2,18 @@
 //!
 //! Provides functions like now(), utcnow(), states(), is_state(), etc.
 
-use chrono::{DateTime, Duration, Local, TimeZone, Utc};
+use crate::render_info;
+use crate::states::{sorted_states, state_to_value, StateWrapper, StatesObject};
+use chrono::{DateTime, Duration, FixedOffset, Local, TimeZone, Utc};
+use chrono_tz::Tz;
+use ha_config::CoreConfig;
+use ha_core::{EntityId, State};
+use ha_registries::{AreaEntry, Registries};
 use minijinja::value::{Kwargs, Value};
 use minijinja::{Error, ErrorKind};
+use std::collections::HashSet;
 use std::convert::TryFrom;
+use std::sync::Arc;
 
 /// Helper to convert Value to f64
 fn value_to_f64(value: &Value) -> Option<f64> {
@@ -22,19 +30,30 @@ fn value_to_bool(value: &Value) -> Option<bool> {
 
 // ==================== Time Functions ====================
 
-/// Get the current local time
-pub fn now() -> Value {
-    Value::from_object(DateTimeWrapper(Local::now().with_timezone(&Utc)))
+/// Convert a UTC datetime to the given time zone (or the system zone if unset)
+fn in_zone(dt: DateTime<Utc>, time_zone: Option<Tz>) -> DateTime<FixedOffset> {
+    match time_zone {
+        Some(tz) => dt.with_timezone(&tz).fixed_offset(),
+        None => dt.with_timezone(&Local).fixed_offset(),
+    }
+}
+
+/// Get the current time in the configured time zone
+pub fn now(time_zone: Option<Tz>) -> Value {
+    render_info::track_time();
+    Value::from_object(DateTimeWrapper(in_zone(Utc::now(), time_zone)))
 }
 
 /// Get the current UTC time
 pub fn utcnow() -> Value {
-    Value::from_object(DateTimeWrapper(Utc::now()))
+    render_info::track_time();
+    Value::from_object(DateTimeWrapper(Utc::now().fixed_offset()))
 }
 
-/// Convert a time string to today's datetime
-pub fn today_at(time_str: &str) -> Result<Value, Error> {
-    let today = Local::now().date_naive();
+/// Convert a time string to today's datetime in the configured time zone
+pub fn today_at(time_zone: Option<Tz>, time_str: &str) -> Result<Value, Error> {
+    render_info::track_time();
+    let today = in_zone(Utc::now(), time_zone).date_naive();
 
     // Parse time in HH:MM or HH:MM:SS format
     let time = chrono::NaiveTime::parse_from_str(time_str, "%H:%M:%S")
@@ -47,14 +66,19 @@ pub fn today_at(time_str: &str) -> Result<Value, Error> {
         })?;
 
     let datetime = today.and_time(time);
-    let local_dt = Local
-        .from_local_datetime(&datetime)
-        .single()
-        .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, "ambiguous local time"))?;
+    let local_dt = match time_zone {
+        Some(tz) => tz
+            .from_local_datetime(&datetime)
+            .single()
+            .map(|dt| dt.fixed_offset()),
+        None => Local
+            .from_local_datetime(&datetime)
+            .single()
+            .map(|dt| dt.fixed_offset()),
+    }
+    .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, "ambiguous local time"))?;
 
-    Ok(Value::from_object(DateTimeWrapper(
-        local_dt.with_timezone(&Utc),
-    )))
+    Ok(Value::from_object(DateTimeWrapper(local_dt)))
 }
 
 /// Convert datetime to UNIX timestamp
@@ -94,17 +118,28 @@ pub fn as_timestamp(value: Value) -> Result<f64, Error> {
 
 /// Convert timestamp to datetime
 pub fn as_datetime(value: Value) -> Result<Value, Error> {
+    Ok(Value::from_object(DateTimeWrapper(parse_datetime(&value)?)))
+}
+
+/// Resolve a datetime, ISO string or UNIX timestamp to a datetime
+///
+/// Timestamps become UTC datetimes, strings keep their offset.
+fn parse_datetime(value: &Value) -> Result<DateTime<FixedOffset>, Error> {
+    if let Some(dt) = value.downcast_object_ref::<DateTimeWrapper>() {
+        return Ok(dt.0);
+    }
+
     let ts = if let Some(s) = value.as_str() {
         // Try parsing ISO format first
         if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
-            return Ok(Value::from_object(DateTimeWrapper(dt.with_timezone(&Utc))));
+            return Ok(dt);
         }
         // Try parsing as timestamp
         s.parse::<i64>()
             .map_err(|_| Error::new(ErrorKind::InvalidOperation, "cannot parse datetime string"))?
     } else if let Some(i) = value.as_i64() {
         i
-    } else if let Some(f) = value_to_f64(&value) {
+    } else if let Some(f) = value_to_f64(value) {
         f as i64
     } else {
         return Err(Error::new(
@@ -115,18 +150,16 @@ pub fn as_datetime(value: Value) -> Result<Value, Error> {
 
     let dt = DateTime::from_timestamp(ts, 0)
         .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, "invalid timestamp"))?;
-
-    Ok(Value::from_object(DateTimeWrapper(dt)))
+    Ok(dt.fixed_offset())
 }
 
-/// Convert datetime to local timezone
-pub fn as_local(value: Value) -> Result<Value, Error> {
-    if let Some(dt) = value.downcast_object_ref::<DateTimeWrapper>() {
-        return Ok(Value::from_object(DateTimeWrapper(dt.0)));
-    }
-
-    // Try to parse as datetime first
-    as_datetime(value)
+/// Convert datetime to the configured time zone
+pub fn as_local(time_zone: Option<Tz>, value: Value) -> Result<Value, Error> {
+    let dt = parse_datetime(&value)?;
+    Ok(Value::from_object(DateTimeWrapper(in_zone(
+        dt.with_timezone(&Utc),
+        time_zone,
+    ))))
 }
 
 /// Parse a datetime string with format
@@ -138,7 +171,9 @@ pub fn strptime(value: &str, format: &str) -> Result<Value, Error> {
         )
     })?;
 
-    Ok(Value::from_object(DateTimeWrapper(dt.and_utc())))
+    Ok(Value::from_object(DateTimeWrapper(
+        dt.and_utc().fixed_offset(),
+    )))
 }
 
 /// Create a timedelta
@@ -158,132 +193,357 @@ pub fn timedelta(kwargs: Kwargs) -> Result<Value, Error> {
     Ok(Value::from_object(TimeDeltaWrapper(duration)))
 }
 
-/// Convert string to timedelta
-pub fn as_timedelta(value: &str) -> Result<Value, Error> {
-    // Parse formats like "1:30:00" (hours:minutes:seconds) or "1 day, 2:30:00"
+/// Convert a duration string to a timedelta
+///
+/// Accepts the formats Home Assistant's `parse_duration` does:
+/// `[-]HH:MM[:SS[.ffffff]]`, `[-]D day[s][,] HH:MM:SS`, a bare number of
+/// seconds, and ISO 8601 durations like `P1DT2H30M`. Returns `None` for
+/// strings that cannot be parsed.
+pub fn as_timedelta(value: &str) -> Value {
+    parse_duration(value)
+        .map(|d| Value::from_object(TimeDeltaWrapper(d)))
+        .unwrap_or(Value::from(()))
+}
+
+/// Parse a duration string into a chrono Duration
+pub fn parse_duration(value: &str) -> Option<Duration> {
     let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
 
-    let mut total_seconds: i64 = 0;
-
-    // Check for "X days" prefix
-    if let Some(rest) = value.strip_suffix(" days").or(value.strip_suffix(" day")) {
-        if let Some((days_str, time_str)) = rest.split_once(", ") {
-            total_seconds += days_str
-                .trim()
-                .parse::<i64>()
-                .map_err(|_| Error::new(ErrorKind::InvalidOperation, "invalid timedelta"))?
-                * 86400;
-            return parse_time_to_seconds(time_str.trim()).map(|s| {
-                Value::from_object(TimeDeltaWrapper(Duration::seconds(total_seconds + s)))
-            });
-        }
+    if let Some(iso) = value
+        .strip_prefix('P')
+        .or_else(|| value.strip_prefix("-P"))
+        .or_else(|| value.strip_prefix("+P"))
+    {
+        let duration = parse_iso_duration(iso)?;
+        return Some(if value.starts_with('-') {
+            -duration
+        } else {
+            duration
+        });
     }
 
-    // Parse HH:MM:SS or MM:SS
-    parse_time_to_seconds(value).map(|s| Value::from_object(TimeDeltaWrapper(Duration::seconds(s))))
+    let (negative, rest) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    // Optional "D day[s][,]" prefix
+    let (days, time_str) = match rest.split_once(' ') {
+        Some((days_str, remainder)) => {
+            let remainder = remainder.trim_start();
+            let remainder = remainder
+                .strip_prefix("days")
+                .or_else(|| remainder.strip_prefix("day"))
+                .unwrap_or(remainder);
+            let remainder = remainder.trim_start_matches(',').trim();
+            (days_str.parse::<i64>().ok()?, remainder)
+        }
+        None => (0, rest),
+    };
+
+    let seconds = if time_str.is_empty() {
+        0.0
+    } else {
+        parse_clock_seconds(time_str)?
+    };
+
+    let duration = Duration::try_days(days)?.checked_add(&seconds_to_duration(seconds)?)?;
+    Some(if negative { -duration } else { duration })
 }
 
-fn parse_time_to_seconds(time_str: &str) -> Result<i64, Error> {
+/// Convert seconds to a duration, or `None` if it does not fit in one
+fn seconds_to_duration(seconds: f64) -> Option<Duration> {
+    let micros = (seconds * 1e6).round();
+    // `as` saturates, so reject anything i64 cannot hold exactly
+    if !micros.is_finite() || micros.abs() >= i64::MAX as f64 {
+        return None;
+    }
+    Some(Duration::microseconds(micros as i64))
+}
+
+/// Parse `HH:MM`, `HH:MM:SS[.ffffff]` or a bare seconds value into seconds
+fn parse_clock_seconds(time_str: &str) -> Option<f64> {
     let parts: Vec<&str> = time_str.split(':').collect();
+    match parts.as_slice() {
+        [secs] => secs.parse::<f64>().ok(),
+        [hours, minutes] => Some(
+            hours.parse::<i64>().ok()? as f64 * 3600.0 + minutes.parse::<i64>().ok()? as f64 * 60.0,
+        ),
+        [hours, minutes, secs] => Some(
+            hours.parse::<i64>().ok()? as f64 * 3600.0
+                + minutes.parse::<i64>().ok()? as f64 * 60.0
+                + secs.parse::<f64>().ok()?,
+        ),
+        _ => None,
+    }
+}
 
-    match parts.len() {
-        2 => {
-            let minutes: i64 = parts[0]
-                .parse()
-                .map_err(|_| Error::new(ErrorKind::InvalidOperation, "invalid timedelta"))?;
-            let seconds: i64 = parts[1]
-                .parse()
-                .map_err(|_| Error::new(ErrorKind::InvalidOperation, "invalid timedelta"))?;
-            Ok(minutes * 60 + seconds)
+/// Parse the part of an ISO 8601 duration after the leading `P`
+fn parse_iso_duration(iso: &str) -> Option<Duration> {
+    let (date_part, time_part) = match iso.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (iso, None),
+    };
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut any = false;
+
+    for c in date_part.chars() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
         }
-        3 => {
-            let hours: i64 = parts[0]
-                .parse()
-                .map_err(|_| Error::new(ErrorKind::InvalidOperation, "invalid timedelta"))?;
-            let minutes: i64 = parts[1]
-                .parse()
-                .map_err(|_| Error::new(ErrorKind::InvalidOperation, "invalid timedelta"))?;
-            let seconds: i64 = parts[2]
-                .parse()
-                .map_err(|_| Error::new(ErrorKind::InvalidOperation, "invalid timedelta"))?;
-            Ok(hours * 3600 + minutes * 60 + seconds)
+        let n: f64 = number.parse().ok()?;
+        number.clear();
+        any = true;
+        let seconds = match c {
+            'Y' => n * 365.0 * 86400.0,
+            'M' => n * 30.0 * 86400.0,
+            'W' => n * 7.0 * 86400.0,
+            'D' => n * 86400.0,
+            _ => return None,
+        };
+        total = total.checked_add(&seconds_to_duration(seconds)?)?;
+    }
+    if !number.is_empty() {
+        return None;
+    }
+
+    for c in time_part.unwrap_or_default().chars() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
         }
-        _ => Err(Error::new(
-            ErrorKind::InvalidOperation,
-            "invalid timedelta format",
-        )),
+        let n: f64 = number.parse().ok()?;
+        number.clear();
+        any = true;
+        let seconds = match c {
+            'H' => n * 3600.0,
+            'M' => n * 60.0,
+            'S' => n,
+            _ => return None,
+        };
+        total = total.checked_add(&seconds_to_duration(seconds)?)?;
+    }
+    if !number.is_empty() || !any {
+        return None;
     }
+
+    Some(total)
 }
 
-/// Get human-readable relative time
-pub fn relative_time(value: Value) -> Result<String, Error> {
-    let dt = if let Some(wrapper) = value.downcast_object_ref::<DateTimeWrapper>() {
-        wrapper.0
-    } else {
-        let ts = as_timestamp(value)?;
-        DateTime::from_timestamp(ts as i64, 0)
-            .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, "invalid timestamp"))?
-    };
+/// Resolve a template value to a UTC datetime
+fn value_to_datetime(value: &Value) -> Result<DateTime<Utc>, Error> {
+    if let Some(wrapper) = value.downcast_object_ref::<DateTimeWrapper>() {
+        return Ok(wrapper.0.with_timezone(&Utc));
+    }
 
-    let now = Utc::now();
-    let diff = now.signed_duration_since(dt);
+    let ts = as_timestamp(value.clone())?;
+    DateTime::from_timestamp(ts as i64, 0)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, "invalid timestamp"))
+}
 
-    Ok(format_duration(diff))
+/// Get human-readable age of a datetime in the past ("2 hours")
+///
+/// Matches Home Assistant: the largest fitting unit is used and rounded,
+/// there is no "ago" suffix, and datetimes in the future are returned as-is.
+pub fn relative_time(value: Value) -> Result<Value, Error> {
+    time_since(value, None)
 }
 
-/// Format time since (positive duration)
-pub fn time_since(value: Value) -> Result<String, Error> {
-    relative_time(value)
+/// Format time since a datetime in the past, with `precision` units
+///
+/// Datetimes in the future are returned as-is.
+pub fn time_since(value: Value, precision: Option<usize>) -> Result<Value, Error> {
+    let dt = value_to_datetime(&value)?;
+    let seconds = (Utc::now() - dt).num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6;
+    if seconds < 0.0 {
+        return Ok(value);
+    }
+
+    Ok(Value::from(format_age(seconds, precision.unwrap_or(1))))
 }
 
-/// Format time until (negative duration)
-pub fn time_until(value: Value) -> Result<String, Error> {
-    let dt = if let Some(wrapper) = value.downcast_object_ref::<DateTimeWrapper>() {
-        wrapper.0
-    } else {
-        let ts = as_timestamp(value)?;
-        DateTime::from_timestamp(ts as i64, 0)
-            .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, "invalid timestamp"))?
-    };
+/// Format time until a datetime in the future, with `precision` units
+///
+/// Datetimes in the past are returned as-is.
+pub fn time_until(value: Value, precision: Option<usize>) -> Result<Value, Error> {
+    let dt = value_to_datetime(&value)?;
+    let seconds = (dt - Utc::now()).num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6;
+    if seconds < 0.0 {
+        return Ok(value);
+    }
 
-    let now = Utc::now();
-    let diff = dt.signed_duration_since(now);
+    Ok(Value::from(format_age(seconds, precision.unwrap_or(1))))
+}
 
-    Ok(format_duration(diff))
+/// Units used by Home Assistant's age formatting, largest first
+///
+/// Months are 30 days and years 12 months, as in `homeassistant.util.dt`.
+const AGE_UNITS: [(&str, f64); 6] = [
+    ("year", 31_104_000.0),
+    ("month", 2_592_000.0),
+    ("day", 86_400.0),
+    ("hour", 3_600.0),
+    ("minute", 60.0),
+    ("second", 1.0),
+];
+
+/// Round half to even, like Python's `round()`
+fn round_half_even(x: f64) -> f64 {
+    let rounded = x.round();
+    if (x - x.trunc()).abs() == 0.5 && rounded % 2.0 != 0.0 {
+        rounded - x.signum()
+    } else {
+        rounded
+    }
 }
 
-fn format_duration(diff: Duration) -> String {
-    let total_seconds = diff.num_seconds().abs();
+fn format_unit(number: i64, unit: &str) -> String {
+    if number == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", number, unit)
+    }
+}
 
-    if total_seconds < 60 {
-        let secs = total_seconds;
-        if secs == 1 {
-            "1 second".to_string()
-        } else {
-            format!("{} seconds", secs)
-        }
-    } else if total_seconds < 3600 {
-        let mins = total_seconds / 60;
-        if mins == 1 {
-            "1 minute".to_string()
-        } else {
-            format!("{} minutes", mins)
+/// Format a number of seconds as an age like "2 hours" or "1 day 3 hours"
+///
+/// The largest unit is chosen after rounding (so 59.6 minutes is "1 hour"),
+/// and the last of the `precision` units shown is rounded.
+pub fn format_age(seconds: f64, precision: usize) -> String {
+    let precision = precision.max(1);
+
+    // Pick the unit the same way HA does: climb from seconds while the
+    // rounded value reaches the next unit's factor
+    let mut start = 0;
+    let mut delta = seconds;
+    for idx in (1..AGE_UNITS.len()).rev() {
+        let factor = AGE_UNITS[idx - 1].1 / AGE_UNITS[idx].1;
+        if round_half_even(delta) < factor {
+            start = idx;
+            break;
         }
-    } else if total_seconds < 86400 {
-        let hours = total_seconds / 3600;
-        if hours == 1 {
-            "1 hour".to_string()
-        } else {
-            format!("{} hours", hours)
+        delta /= factor;
+    }
+
+    let mut parts = Vec::new();
+    let mut remaining = seconds;
+    for (idx, (unit, size)) in AGE_UNITS.iter().enumerate().skip(start) {
+        let last = parts.len() + 1 == precision || idx == AGE_UNITS.len() - 1;
+        if last {
+            let value = round_half_even(remaining / size) as i64;
+            if value > 0 || parts.is_empty() {
+                parts.push(format_unit(value, unit));
+            }
+            break;
         }
-    } else {
-        let days = total_seconds / 86400;
-        if days == 1 {
-            "1 day".to_string()
-        } else {
-            format!("{} days", days)
+
+        let value = (remaining / size).floor();
+        remaining -= value * size;
+        if value > 0.0 {
+            parts.push(format_unit(value as i64, unit));
         }
     }
+
+    parts.join(" ")
+}
+
+// ==================== Timestamp Formatting ====================
+
+/// Default format used by `timestamp_custom`, matching HA's `DATE_STR_FORMAT`
+const TIMESTAMP_CUSTOM_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Convert a UNIX timestamp value to a UTC datetime
+fn timestamp_to_datetime(value: &Value) -> Option<DateTime<Utc>> {
+    let ts = value_to_f64(value).or_else(|| value.as_str()?.trim().parse::<f64>().ok())?;
+    let secs = ts.floor();
+    let nanos = ((ts - secs) * 1e9).round() as u32;
+    DateTime::from_timestamp(secs as i64, nanos.min(999_999_999))
+}
+
+/// Format a UTC datetime in the given time zone (or the system zone if unset)
+fn format_in_zone(dt: DateTime<Utc>, time_zone: Option<Tz>, format: &str) -> String {
+    match time_zone {
+        Some(tz) => dt.with_timezone(&tz).format(format).to_string(),
+        None => dt.with_timezone(&Local).format(format).to_string(),
+    }
+}
+
+fn invalid_timestamp(
+    function: &str,
+    value: &Value,
+    default: Option<Value>,
+) -> Result<Value, Error> {
+    default.ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidOperation,
+            format!("{} got invalid input '{}'", function, value),
+        )
+    })
+}
+
+/// Format a UNIX timestamp with a strftime format
+///
+/// Uses the configured time zone when `local` is true (the default) and UTC
+/// otherwise. Returns `default` for invalid input if one is given.
+pub fn timestamp_custom(
+    time_zone: Option<Tz>,
+    value: Value,
+    format: Option<String>,
+    local: Option<bool>,
+    kwargs: Kwargs,
+) -> Result<Value, Error> {
+    let format = match kwargs.get::<Option<String>>("format")? {
+        Some(format) => format,
+        None => format.unwrap_or_else(|| TIMESTAMP_CUSTOM_FORMAT.to_string()),
+    };
+    let local = kwargs
+        .get::<Option<bool>>("local")?
+        .or(local)
+        .unwrap_or(true);
+    let default = kwargs.get::<Option<Value>>("default")?;
+
+    let Some(dt) = timestamp_to_datetime(&value) else {
+        return invalid_timestamp("timestamp_custom", &value, default);
+    };
+
+    let formatted = if local {
+        format_in_zone(dt, time_zone, &format)
+    } else {
+        dt.format(&format).to_string()
+    };
+    Ok(Value::from(formatted))
+}
+
+/// Format a UNIX timestamp as an ISO 8601 string in the configured time zone
+pub fn timestamp_local(
+    time_zone: Option<Tz>,
+    value: Value,
+    kwargs: Kwargs,
+) -> Result<Value, Error> {
+    let default = kwargs.get::<Option<Value>>("default")?;
+    let Some(dt) = timestamp_to_datetime(&value) else {
+        return invalid_timestamp("timestamp_local", &value, default);
+    };
+    Ok(Value::from(format_in_zone(
+        dt,
+        time_zone,
+        "%Y-%m-%dT%H:%M:%S%:z",
+    )))
+}
+
+/// Format a UNIX timestamp as an ISO 8601 string in UTC
+pub fn timestamp_utc(value: Value, kwargs: Kwargs) -> Result<Value, Error> {
+    let default = kwargs.get::<Option<Value>>("default")?;
+    let Some(dt) = timestamp_to_datetime(&value) else {
+        return invalid_timestamp("timestamp_utc", &value, default);
+    };
+    Ok(Value::from(dt.format("%Y-%m-%dT%H:%M:%S%:z").to_string()))
 }
 
 // ==================== Utility Functions ====================
@@ -304,20 +564,49 @@ pub fn iif(
     }
 }
 
-/// Calculate distance between two points
-pub fn distance(lat1: f64, lon1: f64, lat2: Option<f64>, lon2: Option<f64>) -> Result<f64, Error> {
-    // If only two args, assume second point is home (0, 0 as placeholder)
-    let (lat2, lon2) = match (lat2, lon2) {
-        (Some(lat), Some(lon)) => (lat, lon),
-        _ => {
-            return Err(Error::new(
-                ErrorKind::InvalidOperation,
-                "distance requires 4 arguments (lat1, lon1, lat2, lon2)",
-            ))
+/// Home location and length unit from the core config, for `distance()` and
+/// `closest()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct HomeLocation {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Length unit distances are returned in, like `km` or `mi`
+    pub length_unit: String,
+}
+
+impl Default for HomeLocation {
+    fn default() -> Self {
+        Self::from_config(&CoreConfig::default())
+    }
+}
+
+impl HomeLocation {
+    /// Take the home coordinates and length unit of a core config
+    pub fn from_config(config: &CoreConfig) -> Self {
+        Self {
+            latitude: config.latitude,
+            longitude: config.longitude,
+            length_unit: config.unit_system().length,
         }
-    };
+    }
+
+    /// Convert a distance in kilometers to the configured length unit
+    ///
+    /// Unknown units fall back to kilometers.
+    fn length_from_km(&self, km: f64) -> f64 {
+        match self.length_unit.as_str() {
+            "m" => km * 1000.0,
+            "cm" => km * 100_000.0,
+            "mi" => km / 1.609_344,
+            "yd" => km / 0.000_914_4,
+            "ft" => km / 0.000_304_8,
+            _ => km,
+        }
+    }
+}
 
-    // Haversine formula
+/// Great-circle distance between two coordinates in kilometers (haversine)
+fn distance_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
     let r = 6371.0; // Earth's radius in km
 
     let d_lat = (lat2 - lat1).to_radians();
@@ -329,10 +618,127 @@ pub fn distance(lat1: f64, lon1: f64, lat2: Option<f64>, lon2: Option<f64>) -> R
     let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
     let c = 2.0 * a.sqrt().asin();
 
-    Ok(r * c)
+    r * c
+}
+
+/// Get the coordinates of a state, from its `latitude`/`longitude` attributes
+fn state_location(state: &State) -> Option<(f64, f64)> {
+    let latitude = state.attributes.get("latitude")?.as_f64()?;
+    let longitude = state.attributes.get("longitude")?.as_f64()?;
+    Some((latitude, longitude))
+}
+
+/// Read a coordinate given as a number or numeric string
+fn coordinate(value: &Value) -> Option<f64> {
+    value_to_f64(value).or_else(|| value.as_str()?.trim().parse().ok())
+}
+
+/// Calculate the distance between two locations, or from home to one
+///
+/// Locations are states, entity IDs of entities with `latitude` and
+/// `longitude` attributes, or latitude/longitude pairs. With a single
+/// location the distance is from the home location. The result is in the
+/// configured length unit, or none when a location can't be resolved.
+pub fn distance(
+    states: &StatesObject,
+    home: &HomeLocation,
+    args: &[Value],
+) -> Result<Value, Error> {
+    let mut locations = Vec::new();
+    let mut pending = args.iter();
+
+    while let Some(value) = pending.next() {
+        let state = if let Some(state) = value.downcast_object_ref::<StateWrapper>() {
+            Some(state.0.clone())
+        } else {
+            value
+                .as_str()
+                .filter(|s| s.parse::<EntityId>().is_ok())
+                .and_then(|entity_id| states.get_full_state(entity_id))
+        };
+
+        let location = match state {
+            Some(state) => state_location(&state),
+            // Not a state, expect this and the next value to be a coordinate pair
+            None => pending
+                .next()
+                .and_then(|longitude| coordinate(value).zip(coordinate(longitude))),
+        };
+        match location {
+            Some(location) => locations.push(location),
+            None => return Ok(Value::from(())),
+        }
+    }
+
+    let km = match locations[..] {
+        [] => {
+            return Err(Error::new(
+                ErrorKind::MissingArgument,
+                "distance requires at least one location",
+            ))
+        }
+        [(lat, lon)] => distance_km(home.latitude, home.longitude, lat, lon),
+        [(lat1, lon1), (lat2, lon2), ..] => distance_km(lat1, lon1, lat2, lon2),
+    };
+    Ok(Value::from(home.length_from_km(km)))
+}
+
+/// Find the entity closest to a point, or to home
+///
+/// Takes the same arguments as HA: `closest(entities)` measures from home,
+/// `closest(point, entities)` from an entity or state, and
+/// `closest(latitude, longitude, entities)` from a coordinate. Entities are
+/// resolved with [`expand`], so groups and areas work; those without
+/// coordinates are skipped. Returns undefined when no entity has a location,
+/// and none when the point can't be resolved.
+pub fn closest(
+    states: &StatesObject,
+    registries: Option<&Registries>,
+    home: &HomeLocation,
+    args: &[Value],
+) -> Result<Value, Error> {
+    let ((latitude, longitude), entities) = match args {
+        [] => {
+            return Err(Error::new(
+                ErrorKind::MissingArgument,
+                "closest requires entities to search",
+            ))
+        }
+        [entities] => ((home.latitude, home.longitude), entities),
+        [point, entities] => {
+            let state = if let Some(state) = point.downcast_object_ref::<StateWrapper>() {
+                Some(state.0.clone())
+            } else {
+                point
+                    .as_str()
+                    .and_then(|entity_id| states.get_full_state(entity_id))
+            };
+            match state.as_ref().and_then(state_location) {
+                Some(location) => (location, entities),
+                None => return Ok(Value::from(())),
+            }
+        }
+        [lat, lon, entities, ..] => match coordinate(lat).zip(coordinate(lon)) {
+            Some(location) => (location, entities),
+            None => return Ok(Value::from(())),
+        },
+    };
+
+    let candidates = expand(states, registries, std::slice::from_ref(entities));
+    let Ok(candidates) = candidates.try_iter() else {
+        return Ok(Value::UNDEFINED);
+    };
+    let nearest = candidates
+        .filter_map(|value| {
+            let (lat, lon) = state_location(&value.downcast_object_ref::<StateWrapper>()?.0)?;
+            Some((distance_km(latitude, longitude, lat, lon), value))
+        })
+        .min_by(|(a, _), (b, _)| a.total_cmp(b));
+    Ok(nearest.map_or(Value::UNDEFINED, |(_, value)| value))
 }
 
 /// Get the type of a value
+#[allow(clippy::collapsible_match)]
 pub fn typeof_fn(value: Value) -> &'static str {
     use minijinja::value::ValueKind;
 
@@ -399,11 +805,296 @@ pub fn range_fn(start: i64, stop: Option<i64>, step: Option<i64>) -> Vec<i64> {
     result
 }
 
+// ==================== Registry Functions ====================
+
+/// Get the entity_ids in an area
+///
+/// Includes entities assigned to the area directly, and entities without an
+/// area of their own whose device is in the area.
+pub fn area_entity_ids(registries: &Registries, area_id: &str) -> Vec<String> {
+    registries.expand_area_target(area_id)
+}
+
+/// Resolve an area from an area id or area name
+fn resolve_area(registries: &Registries, lookup: &str) -> Option<Arc<AreaEntry>> {
+    registries
+        .areas
+        .get(lookup)
+        .or_else(|| registries.areas.get_by_name(lookup))
+}
+
+/// Get the device id for an entity id, or for a device name
+pub fn device_id(registries: Option<&Registries>, lookup: &str) -> Value {
+    let Some(registries) = registries else {
+        return Value::UNDEFINED;
+    };
+
+    if let Some(entity) = registries.entities.get(lookup) {
+        return entity
+            .device_id
+            .clone()
+            .map(Value::from)
+            .unwrap_or(Value::UNDEFINED);
+    }
+
+    registries
+        .devices
+        .iter()
+        .find(|d| d.name_by_user.as_deref() == Some(lookup) || d.name.as_deref() == Some(lookup))
+        .map(|d| Value::from(d.id.clone()))
+        .unwrap_or(Value::UNDEFINED)
+}
+
+/// Get the area id for an area name, device id or entity id
+///
+/// Entities without an area of their own inherit their device's area.
+pub fn area_id(registries: Option<&Registries>, lookup: &str) -> Value {
+    let Some(registries) = registries else {
+        return Value::UNDEFINED;
+    };
+
+    lookup_area_id(registries, lookup)
+        .map(Value::from)
+        .unwrap_or(Value::UNDEFINED)
+}
+
+/// Get the area name for an area id, device id or entity id
+pub fn area_name(registries: Option<&Registries>, lookup: &str) -> Value {
+    let Some(registries) = registries else {
+        return Value::UNDEFINED;
+    };
+
+    lookup_area_id(registries, lookup)
+        .and_then(|id| registries.areas.get(&id))
+        .map(|area| Value::from(area.name.clone()))
+        .unwrap_or(Value::UNDEFINED)
+}
+
+fn lookup_area_id(registries: &Registries, lookup: &str) -> Option<String> {
+    if let Some(area) = resolve_area(registries, lookup) {
+        return Some(area.id.clone());
+    }
+
+    if let Some(device) = registries.devices.get(lookup) {
+        return device.area_id.clone();
+    }
+
+    let entity = registries.entities.get(lookup)?;
+    entity.area_id.clone().or_else(|| {
+        entity
+            .device_id
+            .as_deref()
+            .and_then(|id| registries.devices.get(id))
+            .and_then(|device| device.area_id.clone())
+    })
+}
+
+/// Get the entity ids belonging to a device
+pub fn device_entities(registries: Option<&Registries>, device_id: &str) -> Vec<String> {
+    let Some(registries) = registries else {
+        return Vec::new();
+    };
+
+    registries.entities.entities_for_device(device_id)
+}
+
+/// Get the entity ids in an area, looked up by area id or name
+pub fn area_entities(registries: Option<&Registries>, lookup: &str) -> Vec<String> {
+    registries
+        .and_then(|r| resolve_area(r, lookup).map(|area| area_entity_ids(r, &area.id)))
+        .unwrap_or_default()
+}
+
+/// Get the device ids in an area, looked up by area id or name
+pub fn area_devices(registries: Option<&Registries>, lookup: &str) -> Vec<String> {
+    let Some(registries) = registries else {
+        return Vec::new();
+    };
+    let Some(area) = resolve_area(registries, lookup) else {
+        return Vec::new();
+    };
+
+    let mut device_ids: Vec<String> = registries
+        .devices
+        .get_by_area_id(&area.id)
+        .iter()
+        .map(|d| d.id.clone())
+        .collect();
+    device_ids.sort();
+    device_ids
+}
+
+/// Resolve a label id from a label id or label name
+fn resolve_label_id(registries: &Registries, lookup: &str) -> Option<String> {
+    registries
+        .labels
+        .get(lookup)
+        .or_else(|| registries.labels.get_by_name(lookup))
+        .map(|label| label.id.clone())
+}
+
+/// Get label ids
+///
+/// With no argument, returns every label id. Otherwise returns the labels
+/// applied directly to the given area id, device id or entity id. As in
+/// Home Assistant, entities do not inherit their device's labels.
+pub fn labels(registries: Option<&Registries>, lookup: Option<&str>) -> Vec<String> {
+    let Some(registries) = registries else {
+        return Vec::new();
+    };
+
+    let mut label_ids: Vec<String> = match lookup {
+        None => registries.labels.iter().map(|l| l.id.clone()).collect(),
+        Some(lookup) => {
+            if let Some(area) = registries.areas.get(lookup) {
+                area.labels.clone()
+            } else if let Some(device) = registries.devices.get(lookup) {
+                device.labels.clone()
+            } else if let Some(entity) = registries.entities.get(lookup) {
+                entity.labels.iter().cloned().collect()
+            } else {
+                Vec::new()
+            }
+        }
+    };
+    label_ids.sort();
+    label_ids
+}
+
+/// Get the label id for a label name
+pub fn label_id(registries: Option<&Registries>, name: &str) -> Value {
+    registries
+        .and_then(|r| r.labels.get_by_name(name))
+        .map(|label| Value::from(label.id.clone()))
+        .unwrap_or(Value::UNDEFINED)
+}
+
+/// Get the label name for a label id
+pub fn label_name(registries: Option<&Registries>, label_id: &str) -> Value {
+    registries
+        .and_then(|r| r.labels.get(label_id))
+        .map(|label| Value::from(label.name.clone()))
+        .unwrap_or(Value::UNDEFINED)
+}
+
+/// Get the entity ids labeled directly with a label, looked up by id or name
+pub fn label_entities(registries: Option<&Registries>, lookup: &str) -> Vec<String> {
+    let Some((registries, label_id)) =
+        registries.and_then(|r| resolve_label_id(r, lookup).map(|id| (r, id)))
+    else {
+        return Vec::new();
+    };
+
+    let mut entity_ids: Vec<String> = registries
+        .entities
+        .get_by_label_id(&label_id)
+        .iter()
+        .map(|e| e.entity_id.clone())
+        .collect();
+    entity_ids.sort();
+    entity_ids
+}
+
+/// Get the device ids labeled with a label, looked up by id or name
+pub fn label_devices(registries: Option<&Registries>, lookup: &str) -> Vec<String> {
+    let Some((registries, label_id)) =
+        registries.and_then(|r| resolve_label_id(r, lookup).map(|id| (r, id)))
+    else {
+        return Vec::new();
+    };
+
+    let mut device_ids: Vec<String> = registries
+        .devices
+        .iter()
+        .filter(|d| d.labels.contains(&label_id))
+        .map(|d| d.id.clone())
+        .collect();
+    device_ids.sort();
+    device_ids
+}
+
+/// Get the area ids labeled with a label, looked up by id or name
+pub fn label_areas(registries: Option<&Registries>, lookup: &str) -> Vec<String> {
+    let Some((registries, label_id)) =
+        registries.and_then(|r| resolve_label_id(r, lookup).map(|id| (r, id)))
+    else {
+        return Vec::new();
+    };
+
+    let mut area_ids: Vec<String> = registries
+        .areas
+        .get_by_label_id(&label_id)
+        .iter()
+        .map(|a| a.id.clone())
+        .collect();
+    area_ids.sort();
+    area_ids
+}
+
+/// Expand entity ids, group ids and area ids into a list of state objects
+///
+/// Accepts strings, state objects, or lists of either. Groups are flattened
+/// recursively via their `entity_id` attribute. Unknown ids are skipped and
+/// the result is deduplicated and sorted by entity_id.
+pub fn expand(states: &StatesObject, registries: Option<&Registries>, args: &[Value]) -> Value {
+    let mut pending: Vec<Value> = args.iter().rev().cloned().collect();
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut found: Vec<State> = Vec::new();
+
+    while let Some(value) = pending.pop() {
+        let entity_id = if let Some(state) = value.downcast_object_ref::<StateWrapper>() {
+            state.0.entity_id.to_string()
+        } else if let Some(s) = value.as_str() {
+            s.to_string()
+        } else if let Ok(iter) = value.try_iter() {
+            let items: Vec<Value> = iter.collect();
+            pending.extend(items.into_iter().rev());
+            continue;
+        } else {
+            continue;
+        };
+
+        if !seen.insert(entity_id.clone()) {
+            continue;
+        }
+
+        match states.get_full_state(&entity_id) {
+            Some(state) => match state.attributes.get("entity_id") {
+                // Group: expand its members instead of the group itself
+                Some(serde_json::Value::Array(members)) => {
+                    pending.extend(
+                        members
+                            .iter()
+                            .rev()
+                            .filter_map(|m| m.as_str().map(Value::from)),
+                    );
+                }
+                _ => found.push(state),
+            },
+            None => {
+                if let Some(registries) = registries {
+                    if registries.areas.get(&entity_id).is_some() {
+                        let members = area_entity_ids(registries, &entity_id);
+                        pending.extend(members.into_iter().rev().map(Value::from));
+                    }
+                }
+            }
+        }
+    }
+
+    Value::from(
+        sorted_states(found)
+            .into_iter()
+            .map(state_to_value)
+            .collect::<Vec<_>>(),
+    )
+}
+
 // ==================== DateTime Wrapper ====================
 
 /// Wrapper for DateTime to expose to templates
 #[derive(Debug, Clone)]
-pub struct DateTimeWrapper(pub DateTime<Utc>);
+pub struct DateTimeWrapper(pub DateTime<FixedOffset>);
 
 impl std::fmt::Display for DateTimeWrapper {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -538,13 +1229,31 @@ use chrono::Timelike;
 #[derive(Debug, Clone)]
 pub struct TimeDeltaWrapper(pub Duration);
 
+/// Formats like Python's `str(timedelta)`: `[D day[s], ]H:MM:SS[.ffffff]`
 impl std::fmt::Display for TimeDeltaWrapper {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let total_secs = self.0.num_seconds();
-        let hours = total_secs / 3600;
-        let mins = (total_secs % 3600) / 60;
-        let secs = total_secs % 60;
-        write!(f, "{}:{:02}:{:02}", hours, mins, secs)
+        let total_micros = self.0.num_microseconds().unwrap_or(i64::MAX);
+        let days = total_micros.div_euclid(86_400_000_000);
+        let rest = total_micros.rem_euclid(86_400_000_000);
+        let micros = rest % 1_000_000;
+        let secs = rest / 1_000_000;
+
+        match days {
+            0 => {}
+            1 | -1 => write!(f, "{} day, ", days)?,
+            _ => write!(f, "{} days, ", days)?,
+        }
+        write!(
+            f,
+            "{}:{:02}:{:02}",
+            secs / 3600,
+            (secs % 3600) / 60,
+            secs % 60
+        )?;
+        if micros != 0 {
+            write!(f, ".{:06}", micros)?;
+        }
+        Ok(())
     }
 }
 
@@ -559,6 +1268,10 @@ impl minijinja::value::Object for TimeDeltaWrapper {
         }
     }
 
+    fn render(self: &std::sync::Arc<Self>, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+
     fn repr(self: &std::sync::Arc<Self>) -> minijinja::value::ObjectRepr {
         minijinja::value::ObjectRepr::Plain
     }
@@ -570,13 +1283,13 @@ mod tests {
 
     #[test]
     fn test_now_returns_datetime() {
-        let result = now();
+        let result = now(None);
         assert!(result.downcast_object_ref::<DateTimeWrapper>().is_some());
     }
 
     #[test]
     fn test_as_timestamp() {
-        let dt = now();
+        let dt = now(None);
         let ts = as_timestamp(dt).unwrap();
         assert!(ts > 0.0);
     }
@@ -584,8 +1297,87 @@ mod tests {
     #[test]
     fn test_relative_time() {
         let past = Utc::now() - Duration::hours(2);
-        let result = relative_time(Value::from_object(DateTimeWrapper(past))).unwrap();
-        assert_eq!(result, "2 hours");
+        let result =
+            relative_time(Value::from_object(DateTimeWrapper(past.fixed_offset()))).unwrap();
+        assert_eq!(result.as_str(), Some("2 hours"));
+    }
+
+    #[test]
+    fn test_format_age_matches_ha() {
+        // Expected values from homeassistant.util.dt.get_age
+        assert_eq!(format_age(0.0, 1), "0 seconds");
+        assert_eq!(format_age(1.0, 1), "1 second");
+        assert_eq!(format_age(59.4, 1), "59 seconds");
+        assert_eq!(format_age(59.6, 1), "1 minute");
+        assert_eq!(format_age(90.0 * 60.0, 1), "2 hours");
+        assert_eq!(format_age(150.0 * 60.0, 1), "2 hours");
+        assert_eq!(format_age(36.0 * 3600.0, 1), "2 days");
+        assert_eq!(format_age(45.0 * 86400.0, 1), "2 months");
+        assert_eq!(format_age(400.0 * 86400.0, 1), "1 year");
+        assert_eq!(format_age(26.0 * 3600.0 + 2400.0, 2), "1 day 3 hours");
+        assert_eq!(format_age(3600.0 + 61.0, 3), "1 hour 1 minute 1 second");
+        assert_eq!(format_age(3600.0, 2), "1 hour");
+    }
+
+    #[test]
+    fn test_parse_duration_formats() {
+        assert_eq!(parse_duration("1:30:00"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("00:45"), Some(Duration::minutes(45)));
+        assert_eq!(
+            parse_duration("1 day, 2:30:00"),
+            Some(Duration::days(1) + Duration::minutes(150))
+        );
+        assert_eq!(
+            parse_duration("2 days 00:00:10"),
+            Some(Duration::days(2) + Duration::seconds(10))
+        );
+        assert_eq!(parse_duration("-0:05:00"), Some(-Duration::minutes(5)));
+        assert_eq!(
+            parse_duration("0:00:01.500000"),
+            Some(Duration::milliseconds(1500))
+        );
+        assert_eq!(
+            parse_duration("P1DT2H30M"),
+            Some(Duration::days(1) + Duration::minutes(150))
+        );
+        assert_eq!(parse_duration("PT15S"), Some(Duration::seconds(15)));
+        assert_eq!(parse_duration("30"), Some(Duration::seconds(30)));
+        assert_eq!(parse_duration("not a duration"), None);
+        assert_eq!(parse_duration("P"), None);
+    }
+
+    #[test]
+    fn test_parse_duration_out_of_range() {
+        assert_eq!(parse_duration("99999999999999 days"), None);
+        assert_eq!(parse_duration("-99999999999999 days, 1:00:00"), None);
+        assert_eq!(parse_duration("99999999999999999:00:00"), None);
+        assert_eq!(parse_duration("P99999999999999Y"), None);
+        assert_eq!(parse_duration("106700000000 days, 2000000000:00:00"), None);
+        assert_eq!(as_timedelta("99999999999999 days"), Value::from(()));
+    }
+
+    #[test]
+    fn test_timedelta_display_matches_python() {
+        assert_eq!(
+            TimeDeltaWrapper(Duration::minutes(90)).to_string(),
+            "1:30:00"
+        );
+        assert_eq!(
+            TimeDeltaWrapper(Duration::days(1) + Duration::hours(2)).to_string(),
+            "1 day, 2:00:00"
+        );
+        assert_eq!(
+            TimeDeltaWrapper(Duration::days(3)).to_string(),
+            "3 days, 0:00:00"
+        );
+        assert_eq!(
+            TimeDeltaWrapper(-Duration::hours(1)).to_string(),
+            "-1 day, 23:00:00"
+        );
+        assert_eq!(
+            TimeDeltaWrapper(Duration::milliseconds(1500)).to_string(),
+            "0:00:01.500000"
+        );
     }
 
     // Note: timedelta is tested via engine integration tests since Kwargs
@@ -618,8 +1410,14 @@ mod tests {
     #[test]
     fn test_distance() {
         // Distance from NYC to LA is approximately 3944 km
-        let dist = distance(40.7128, -74.0060, Some(34.0522), Some(-118.2437)).unwrap();
+        let dist = distance_km(40.7128, -74.0060, 34.0522, -118.2437);
         assert!(dist > 3900.0 && dist < 4000.0);
+
+        let miles = HomeLocation {
+            length_unit: "mi".to_string(),
+            ..HomeLocation::default()
+        };
+        assert!((miles.length_from_km(1.609_344) - 1.0).abs() < 1e-9);
     }
 
     #[test]
@@ -7,10 +7,22 @@
 //!
 //! - `states('entity_id')` - Get entity state as string
 //! - `states.light.living_room` - Access state object
+//! - `states` / `states.light` - Iterate state objects (sorted by entity_id)
 //! - `is_state('entity_id', 'on')` - Check if entity is in state
 //! - `state_attr('entity_id', 'brightness')` - Get attribute value
 //! - `has_value('entity_id')` - Check if entity has valid value
 //!
+//! # Registry Functions
+//!
+//! Available when the engine is created with [`TemplateEngine::with_registries`]:
+//!
+//! - `expand('group.all_lights', 'kitchen')` - Flatten entities, groups and areas
+//! - `device_id('light.kitchen')` / `device_entities(device_id)` - Entity ⇄ device
+//! - `area_id('light.kitchen')` / `area_name('light.kitchen')` - Resolve an area
+//! - `area_entities('Kitchen')` / `area_devices('Kitchen')` - Area members
+//! - `labels('light.kitchen')` / `label_id('Critical')` / `label_name(id)` - Labels
+//! - `label_entities('critical')` / `label_devices(..)` / `label_areas(..)` - Labeled items
+//!
 //! # Time Functions
 //!
 //! - `now()` - Current local time
@@ -18,10 +30,12 @@
 //! - `today_at('14:30')` - Today at specific time
 //! - `as_timestamp(datetime)` - Convert to UNIX timestamp
 //! - `relative_time(datetime)` - Human-readable age ("2 hours")
-//! - `timedelta(hours=2)` - Create duration
+//! - `time_since(datetime, precision)` / `time_until(datetime, precision)`
+//! - `timedelta(hours=2)` / `as_timedelta('01:30:00')` - Create duration
 //!
 //! # Filters
 //!
+//! - `| timestamp_custom('%H:%M')` / `| timestamp_local` - Format timestamps
 //! - `| round(2)` - Round to precision
 //! - `| int` / `| float` / `| bool` - Type conversion
 //! - `| slugify` - Convert to slug
@@ -29,6 +43,12 @@
 //! - `| regex_replace(pattern, replacement)` - Regex substitution
 //! - `| average` / `| median` - Aggregate functions
 //!
+//! # Render Tracking
+//!
+//! [`TemplateEngine::render_with_info`] returns a [`RenderInfo`] with the
+//! entities, domains and time sources a render touched, so callers can
+//! re-render only when one of them changes.
+//!
 //! # Example
 //!
 //! ```ignore
@@ -53,11 +73,13 @@ mod engine;
 mod error;
 mod filters;
 mod globals;
+mod render_info;
 mod states;
 
-pub use engine::{create_test_engine, TemplateEngine};
-pub use error::{TemplateError, TemplateResult};
+pub use engine::{create_test_engine, TemplateEngine, DEFAULT_CACHE_SIZE};
+pub use error::{SourceLocation, TemplateError, TemplateResult};
 pub use globals::{DateTimeWrapper, TimeDeltaWrapper};
+pub use render_info::RenderInfo;
 pub use states::{StateWrapper, StatesObject};
 
 // Re-export minijinja Value for convenience
@@ -0,0 +1,91 @@
+//! Tracking of what a template reads while rendering
+//!
+//! Mirrors Home Assistant's `RenderInfo`: while a render is being tracked,
+//! state lookups record the entities and domains they touch, and time
+//! functions mark the result as time dependent. Callers use this to decide
+//! which state changes should trigger a re-render.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+/// Entities, domains and time sources a template depended on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderInfo {
+    /// Template iterated over every state
+    pub all_states: bool,
+    /// Domains the template enumerated
+    pub domains: BTreeSet<String>,
+    /// Individual entities the template looked up
+    pub entities: BTreeSet<String>,
+    /// Template called `now()`, `utcnow()` or `today_at()`
+    pub time: bool,
+}
+
+impl RenderInfo {
+    /// Whether a change to `entity_id` can change the rendered result
+    pub fn is_affected_by(&self, entity_id: &str) -> bool {
+        if self.all_states || self.entities.contains(entity_id) {
+            return true;
+        }
+        entity_id
+            .split_once('.')
+            .is_some_and(|(domain, _)| self.domains.contains(domain))
+    }
+
+    /// Add what another render depended on, e.g. for several templates of one entity
+    pub fn merge(&mut self, other: RenderInfo) {
+        self.all_states |= other.all_states;
+        self.domains.extend(other.domains);
+        self.entities.extend(other.entities);
+        self.time |= other.time;
+    }
+
+    /// Whether the template depends on no state or time at all
+    pub fn is_static(&self) -> bool {
+        !self.all_states && !self.time && self.domains.is_empty() && self.entities.is_empty()
+    }
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Option<RenderInfo>> = const { RefCell::new(None) };
+}
+
+/// Run `f` while collecting render info on the current thread
+pub(crate) fn collect<T>(f: impl FnOnce() -> T) -> (T, RenderInfo) {
+    let previous = ACTIVE.with(|active| active.replace(Some(RenderInfo::default())));
+    let result = f();
+    let info = ACTIVE.with(|active| active.replace(previous));
+    (result, info.unwrap_or_default())
+}
+
+fn record(f: impl FnOnce(&mut RenderInfo)) {
+    ACTIVE.with(|active| {
+        if let Some(info) = active.borrow_mut().as_mut() {
+            f(info);
+        }
+    });
+}
+
+/// Record a lookup of a single entity
+pub(crate) fn track_entity(entity_id: &str) {
+    record(|info| {
+        info.entities.insert(entity_id.to_string());
+    });
+}
+
+/// Record enumeration of a whole domain
+pub(crate) fn track_domain(domain: &str) {
+    record(|info| {
+        info.domains.insert(domain.to_string());
+    });
+}
+
+/// Record enumeration of all states
+pub(crate) fn track_all_states() {
+    record(|info| info.all_states = true);
+}
+
+/// Record use of the current time
+pub(crate) fn track_time() {
+    record(|info| info.time = true);
+}
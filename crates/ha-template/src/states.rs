@@ -2,9 +2,10 @@
 //!
 //! Provides the `states` object that allows templates to access entity states.
 
+use crate::render_info;
 use ha_core::State;
 use ha_state_store::StateStore;
-use minijinja::value::{Object, ObjectRepr, Value};
+use minijinja::value::{Enumerator, Object, ObjectRepr, Value};
 use minijinja::{Error, ErrorKind};
 use std::collections::HashMap;
 use std::convert::TryFrom;
@@ -28,6 +29,7 @@ fn value_to_bool(value: &Value) -> Option<bool> {
 /// - `states('entity_id')` - Get state value as string
 /// - `states.entity_id` - Get full state object
 /// - `states.domain` - Get domain proxy for `states.domain.entity`
+/// - `for state in states` - Iterate all state objects, sorted by entity_id
 #[derive(Clone)]
 pub struct StatesObject {
     state_machine: Arc<StateStore>,
@@ -46,16 +48,19 @@ impl StatesObject {
 
     /// Get the state value as a string
     pub fn get_state(&self, entity_id: &str) -> Option<String> {
+        render_info::track_entity(entity_id);
         self.state_machine.get_state(entity_id)
     }
 
     /// Get the full state object
     pub fn get_full_state(&self, entity_id: &str) -> Option<State> {
+        render_info::track_entity(entity_id);
         self.state_machine.get(entity_id)
     }
 
     /// Check if entity is in a specific state
     pub fn is_state(&self, entity_id: &str, state: &str) -> bool {
+        render_info::track_entity(entity_id);
         self.state_machine.is_state(entity_id, state)
     }
 
@@ -70,6 +75,7 @@ impl StatesObject {
 
     /// Get an attribute value
     pub fn state_attr(&self, entity_id: &str, attribute: &str) -> Value {
+        render_info::track_entity(entity_id);
         self.state_machine
             .get(entity_id)
             .and_then(|s| s.attributes.get(attribute).cloned())
@@ -78,29 +84,54 @@ impl StatesObject {
     }
 
     /// Check if entity attribute matches value
+    ///
+    /// Missing entities and attributes never match, like attributes set to
+    /// `None`, even when `value` is `none`.
     pub fn is_state_attr(&self, entity_id: &str, attribute: &str, value: Value) -> bool {
         let attr_value = self.state_attr(entity_id, attribute);
+        if attr_value.is_undefined() || attr_value.is_none() {
+            return false;
+        }
         values_equal(&attr_value, &value)
     }
 
     /// Check if entity has a meaningful value (not unknown/unavailable)
     pub fn has_value(&self, entity_id: &str) -> bool {
-        if let Some(state) = self.state_machine.get(entity_id) {
-            !state.is_unavailable() && !state.is_unknown()
-        } else {
-            false
-        }
+        render_info::track_entity(entity_id);
+        self.state_machine
+            .get(entity_id)
+            .is_some_and(|state| state.is_available())
     }
 
     /// Get all entities for a domain
     pub fn domain_entities(&self, domain: &str) -> Vec<String> {
+        render_info::track_domain(domain);
         self.state_machine.entity_ids(domain)
     }
+
+    /// Get all states, sorted by entity_id for deterministic output
+    pub fn all_states(&self) -> Vec<State> {
+        render_info::track_all_states();
+        sorted_states(self.state_machine.all())
+    }
+}
+
+/// Sort states by entity_id so template iteration order is stable
+pub(crate) fn sorted_states(mut states: Vec<State>) -> Vec<State> {
+    states.sort_by(|a, b| {
+        (a.entity_id.domain(), a.entity_id.object_id())
+            .cmp(&(b.entity_id.domain(), b.entity_id.object_id()))
+    });
+    states
 }
 
 impl Object for StatesObject {
     fn repr(self: &Arc<Self>) -> ObjectRepr {
-        ObjectRepr::Plain
+        ObjectRepr::Iterable
+    }
+
+    fn enumerate(self: &Arc<Self>) -> Enumerator {
+        Enumerator::Values(self.all_states().into_iter().map(state_to_value).collect())
     }
 
     fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
@@ -133,7 +164,8 @@ impl Object for StatesObject {
 
 /// Proxy for accessing entities by domain
 ///
-/// Allows `states.light.living_room` syntax
+/// Allows `states.light.living_room` syntax, and iterating `states.light`
+/// yields that domain's state objects sorted by entity_id
 #[derive(Clone)]
 struct DomainProxy {
     domain: String,
@@ -148,33 +180,40 @@ impl std::fmt::Debug for DomainProxy {
     }
 }
 
+impl DomainProxy {
+    fn states(&self) -> Vec<State> {
+        render_info::track_domain(&self.domain);
+        sorted_states(self.state_machine.domain_states(&self.domain))
+    }
+}
+
 impl Object for DomainProxy {
     fn repr(self: &Arc<Self>) -> ObjectRepr {
-        ObjectRepr::Plain
+        ObjectRepr::Iterable
+    }
+
+    fn enumerate(self: &Arc<Self>) -> Enumerator {
+        Enumerator::Values(self.states().into_iter().map(state_to_value).collect())
     }
 
     fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
         let object_id = key.as_str()?;
         let entity_id = format!("{}.{}", self.domain, object_id);
+        render_info::track_entity(&entity_id);
 
         self.state_machine.get(&entity_id).map(state_to_value)
     }
 
     fn call(self: &Arc<Self>, _state: &minijinja::State, _args: &[Value]) -> Result<Value, Error> {
         // Return all entities in this domain as a list
-        let entities: Vec<Value> = self
-            .state_machine
-            .domain_states(&self.domain)
-            .into_iter()
-            .map(state_to_value)
-            .collect();
+        let entities: Vec<Value> = self.states().into_iter().map(state_to_value).collect();
 
         Ok(Value::from(entities))
     }
 }
 
 /// Convert a State to a template Value
-fn state_to_value(state: State) -> Value {
+pub(crate) fn state_to_value(state: State) -> Value {
     Value::from_object(StateWrapper(state))
 }
 
@@ -228,7 +267,7 @@ impl Object for StateWrapper {
 }
 
 /// Convert serde_json::Value to minijinja Value
-fn json_to_value(json: serde_json::Value) -> Value {
+pub(crate) fn json_to_value(json: serde_json::Value) -> Value {
     match json {
         serde_json::Value::Null => Value::from(()),
         serde_json::Value::Bool(b) => Value::from(b),
@@ -6,7 +6,7 @@
 use ha_core::{Context, EntityId};
 use ha_event_bus::EventBus;
 use ha_state_store::StateStore;
-use ha_template::TemplateEngine;
+use ha_template::{SourceLocation, TemplateEngine, TemplateError};
 use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -531,3 +531,69 @@ fn test_set_and_use_variable() {
     let result = engine.render("{% set x = 5 %}{{ x * 2 }}").unwrap();
     assert_eq!(result, "10");
 }
+
+// ==================== Error reporting ====================
+
+#[test]
+fn test_syntax_error_reports_location() {
+    let engine = setup_engine();
+    let err = engine
+        .render("line one\n{{ states('sensor.zero') }}\n{% if %}")
+        .unwrap_err();
+
+    assert!(matches!(err, TemplateError::SyntaxError { .. }), "{err:?}");
+    let location = err.location().expect("syntax error has a location");
+    assert_eq!(location.line, 3);
+    assert!(location.column.is_some());
+    assert!(err.to_string().contains("line 3"));
+}
+
+#[test]
+fn test_undefined_entity_error() {
+    let engine = setup_engine_with_states();
+    let err = engine
+        .render("{{ states.sensor.zero.state }}\n{{ states.sensor.missing.state }}")
+        .unwrap_err();
+
+    match &err {
+        TemplateError::UndefinedEntity {
+            entity_id,
+            location,
+        } => {
+            assert_eq!(entity_id, "sensor.missing");
+            assert_eq!(
+                *location,
+                Some(SourceLocation {
+                    line: 2,
+                    column: Some(17)
+                })
+            );
+        }
+        other => panic!("expected UndefinedEntity, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_undefined_error_not_blamed_on_earlier_entity() {
+    let engine = setup_engine_with_states();
+    for template in [
+        "{{ states('sensor.zero') }} {{ foo.bar }}",
+        "{{ states.sensor.zero.state ~ foo.bar }}",
+    ] {
+        let err = engine.render(template).unwrap_err();
+        assert!(
+            matches!(err, TemplateError::UndefinedVariable { .. }),
+            "{template}: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn test_undefined_variable_error_is_distinct() {
+    let engine = setup_engine();
+    let err = engine.render("{{ missing_var.attr }}").unwrap_err();
+    assert!(
+        matches!(err, TemplateError::UndefinedVariable { .. }),
+        "{err:?}"
+    );
+}
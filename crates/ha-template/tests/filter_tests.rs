@@ -140,6 +140,63 @@ fn test_regex_findall_no_match() {
     assert!(result == "[]" || result.is_empty());
 }
 
+// ==================== regex_match / regex_search tests ====================
+
+#[test]
+fn test_match_test_anchors_at_start() {
+    let engine = setup_engine();
+    assert_eq!(engine.render("{{ 'abc' is match('^a') }}").unwrap(), "true");
+    assert_eq!(engine.render("{{ 'abc' is match('b') }}").unwrap(), "false");
+    assert_eq!(
+        engine.render("{{ 'abc' | regex_match('ab') }}").unwrap(),
+        "true"
+    );
+}
+
+#[test]
+fn test_search_test_matches_anywhere() {
+    let engine = setup_engine();
+    assert_eq!(engine.render("{{ 'abc' is search('b') }}").unwrap(), "true");
+    assert_eq!(
+        engine.render("{{ 'abc' is search('d') }}").unwrap(),
+        "false"
+    );
+    assert_eq!(
+        engine.render("{{ 'abc' | regex_search('c$') }}").unwrap(),
+        "true"
+    );
+}
+
+#[test]
+fn test_regex_tests_ignorecase() {
+    let engine = setup_engine();
+    assert_eq!(engine.render("{{ 'ABC' is match('a') }}").unwrap(), "false");
+    assert_eq!(
+        engine
+            .render("{{ 'ABC' is match('a', ignorecase=true) }}")
+            .unwrap(),
+        "true"
+    );
+    assert_eq!(
+        engine
+            .render("{{ 'ABC' | regex_search('b', true) }}")
+            .unwrap(),
+        "true"
+    );
+}
+
+#[test]
+fn test_regex_tests_on_numbers() {
+    let engine = setup_engine();
+    assert_eq!(engine.render("{{ 123 is match('12') }}").unwrap(), "true");
+    assert_eq!(
+        engine
+            .render("{{ ['light.a', 'switch.b'] | select('match', 'light[.]') | list }}")
+            .unwrap(),
+        "[\"light.a\"]"
+    );
+}
+
 // ==================== to_json filter tests ====================
 
 #[test]
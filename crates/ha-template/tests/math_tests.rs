@@ -373,3 +373,53 @@ fn test_float_arithmetic() {
     let engine = setup_engine();
     assert_eq!(engine.render("{{ 1.5 + 2.5 }}").unwrap(), "4.0");
 }
+
+// ==================== bitwise filter tests ====================
+
+#[test]
+fn test_bitwise_and_supported_features() {
+    let engine = setup_engine();
+    // OPEN | CLOSE | STOP of a cover
+    assert_eq!(engine.render("{{ 11 | bitwise_and(8) }}").unwrap(), "8");
+    assert_eq!(engine.render("{{ 11 | bitwise_and(4) }}").unwrap(), "0");
+    assert_eq!(
+        engine
+            .render("{{ 'stop' if 11 | bitwise_and(8) > 0 else 'no stop' }}")
+            .unwrap(),
+        "stop"
+    );
+}
+
+#[test]
+fn test_bitwise_or_and_xor() {
+    let engine = setup_engine();
+    assert_eq!(engine.render("{{ 1 | bitwise_or(2) }}").unwrap(), "3");
+    assert_eq!(engine.render("{{ 3 | bitwise_or(2) }}").unwrap(), "3");
+    assert_eq!(engine.render("{{ 3 | bitwise_xor(6) }}").unwrap(), "5");
+    assert_eq!(engine.render("{{ 5 | bitwise_xor(5) }}").unwrap(), "0");
+}
+
+#[test]
+fn test_bitwise_mixed_float_int() {
+    let engine = setup_engine();
+    assert_eq!(engine.render("{{ 12.0 | bitwise_and(4) }}").unwrap(), "4");
+    assert_eq!(engine.render("{{ 1 | bitwise_or(2.0) }}").unwrap(), "3");
+    // State values are strings
+    assert_eq!(engine.render("{{ '11' | bitwise_xor(1) }}").unwrap(), "10");
+}
+
+#[test]
+fn test_bitwise_rejects_non_integers() {
+    let engine = setup_engine();
+    let err = engine
+        .render("{{ 2.5 | bitwise_and(1) }}")
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("bitwise_and expects integers, got '2.5'"),
+        "{}",
+        err
+    );
+    assert!(engine.render("{{ 'abc' | bitwise_or(1) }}").is_err());
+    assert!(engine.render("{{ 1 | bitwise_xor(none) }}").is_err());
+}
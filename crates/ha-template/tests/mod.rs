@@ -6,6 +6,7 @@
 mod edge_cases;
 mod filter_tests;
 mod math_tests;
+mod registry_tests;
 mod state_tests;
 mod time_tests;
 mod type_tests;
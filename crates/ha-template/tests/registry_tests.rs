@@ -0,0 +1,361 @@
+//! Tests for registry-backed template functions
+//!
+//! Tests expand(), device/area/label lookups and related functions that resolve
+//! areas, devices and groups through the registries.
+
+use ha_core::{Context, EntityId};
+use ha_event_bus::EventBus;
+use ha_registries::Registries;
+use ha_state_store::StateStore;
+use ha_template::TemplateEngine;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+fn setup_engine() -> (TemplateEngine, Arc<Registries>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let event_bus = Arc::new(EventBus::new());
+    let state_machine = Arc::new(StateStore::new(event_bus));
+    let registries = Arc::new(Registries::new(temp_dir.path()));
+
+    for (object_id, state) in [("kitchen", "on"), ("hallway", "off"), ("porch", "on")] {
+        state_machine.set(
+            EntityId::new("light", object_id).unwrap(),
+            state,
+            HashMap::new(),
+            Context::new(),
+        );
+    }
+
+    state_machine.set(
+        EntityId::new("sensor", "kitchen_temperature").unwrap(),
+        "21.5",
+        HashMap::new(),
+        Context::new(),
+    );
+
+    state_machine.set(
+        EntityId::new("group", "indoor_lights").unwrap(),
+        "on",
+        HashMap::from([(
+            "entity_id".to_string(),
+            json!(["light.kitchen", "light.hallway", "light.missing"]),
+        )]),
+        Context::new(),
+    );
+
+    state_machine.set(
+        EntityId::new("group", "all_lights").unwrap(),
+        "on",
+        HashMap::from([(
+            "entity_id".to_string(),
+            json!(["group.indoor_lights", "light.porch", "light.kitchen"]),
+        )]),
+        Context::new(),
+    );
+
+    let area = registries.areas.create("Kitchen", None).unwrap();
+    registries
+        .entities
+        .get_or_create("hue", "light.kitchen", Some("kitchen_light"), None, None);
+    registries
+        .entities
+        .update("light.kitchen", |e| e.area_id = Some(area.id.clone()))
+        .unwrap();
+
+    // Sensor inherits its area from its device
+    let device = registries
+        .devices
+        .get_or_create(&[], &[], None, None, Some("Thermometer"), None);
+    registries
+        .devices
+        .update(&device.id, |d| d.area_id = Some(area.id.clone()));
+    registries.entities.get_or_create(
+        "demo",
+        "sensor.kitchen_temperature",
+        Some("kitchen_temp"),
+        None,
+        Some(&device.id),
+    );
+
+    // Labels: one on an entity, one on the device, one on the area
+    let critical = registries.labels.create("Critical", None).unwrap();
+    let battery = registries.labels.create("Battery", None).unwrap();
+    let downstairs = registries.labels.create("Downstairs", None).unwrap();
+    registries
+        .entities
+        .update("light.kitchen", |e| {
+            e.labels.insert(critical.id.clone());
+        })
+        .unwrap();
+    registries
+        .devices
+        .update(&device.id, |d| d.labels.push(battery.id.clone()));
+    registries
+        .areas
+        .update(&area.id, |a| a.labels.push(downstairs.id.clone()), None)
+        .unwrap();
+
+    let engine = TemplateEngine::with_registries(state_machine, registries.clone());
+    (engine, registries, temp_dir)
+}
+
+fn thermometer_id(registries: &Registries) -> String {
+    registries
+        .entities
+        .get("sensor.kitchen_temperature")
+        .and_then(|e| e.device_id.clone())
+        .unwrap()
+}
+
+// ==================== expand() tests ====================
+
+#[test]
+fn test_expand_entity() {
+    let (engine, _, _dir) = setup_engine();
+    let result = engine
+        .render("{{ expand('light.kitchen') | map(attribute='entity_id') | list }}")
+        .unwrap();
+    assert_eq!(result, r#"["light.kitchen"]"#);
+}
+
+#[test]
+fn test_expand_group() {
+    let (engine, _, _dir) = setup_engine();
+    let result = engine
+        .render("{{ expand('group.indoor_lights') | map(attribute='entity_id') | list }}")
+        .unwrap();
+    assert_eq!(result, r#"["light.hallway", "light.kitchen"]"#);
+}
+
+#[test]
+fn test_expand_nested_group_dedupes() {
+    let (engine, _, _dir) = setup_engine();
+    let result = engine
+        .render("{{ expand('group.all_lights') | map(attribute='entity_id') | list }}")
+        .unwrap();
+    assert_eq!(
+        result,
+        r#"["light.hallway", "light.kitchen", "light.porch"]"#
+    );
+}
+
+#[test]
+fn test_expand_area() {
+    let (engine, _, _dir) = setup_engine();
+    let result = engine
+        .render("{{ expand('kitchen') | map(attribute='entity_id') | list }}")
+        .unwrap();
+    assert_eq!(result, r#"["light.kitchen", "sensor.kitchen_temperature"]"#);
+}
+
+#[test]
+fn test_expand_mixed_arguments_and_unknown_ids() {
+    let (engine, _, _dir) = setup_engine();
+    let result = engine
+        .render(
+            "{{ expand(['light.porch', 'light.unknown'], states.light.hallway, 'nowhere') \
+             | map(attribute='entity_id') | list }}",
+        )
+        .unwrap();
+    assert_eq!(result, r#"["light.hallway", "light.porch"]"#);
+}
+
+#[test]
+fn test_expand_without_registries_skips_areas() {
+    let event_bus = Arc::new(EventBus::new());
+    let state_machine = Arc::new(StateStore::new(event_bus));
+    let engine = TemplateEngine::new(state_machine);
+    let result = engine.render("{{ expand('kitchen') | list }}").unwrap();
+    assert_eq!(result, "[]");
+}
+
+// ==================== device/area lookup tests ====================
+
+#[test]
+fn test_device_id_from_entity() {
+    let (engine, registries, _dir) = setup_engine();
+    let result = engine
+        .render("{{ device_id('sensor.kitchen_temperature') }}")
+        .unwrap();
+    assert_eq!(result, thermometer_id(&registries));
+}
+
+#[test]
+fn test_device_id_from_device_name() {
+    let (engine, registries, _dir) = setup_engine();
+    let result = engine.render("{{ device_id('Thermometer') }}").unwrap();
+    assert_eq!(result, thermometer_id(&registries));
+}
+
+#[test]
+fn test_device_entities() {
+    let (engine, registries, _dir) = setup_engine();
+    let template = format!(
+        "{{{{ device_entities('{}') }}}}",
+        thermometer_id(&registries)
+    );
+    let result = engine.render(&template).unwrap();
+    assert_eq!(result, r#"["sensor.kitchen_temperature"]"#);
+}
+
+#[test]
+fn test_area_id_and_name_from_entity() {
+    let (engine, _, _dir) = setup_engine();
+    assert_eq!(
+        engine.render("{{ area_id('light.kitchen') }}").unwrap(),
+        "kitchen"
+    );
+    assert_eq!(
+        engine.render("{{ area_name('light.kitchen') }}").unwrap(),
+        "Kitchen"
+    );
+}
+
+#[test]
+fn test_area_id_inherited_from_device() {
+    let (engine, registries, _dir) = setup_engine();
+    assert_eq!(
+        engine
+            .render("{{ area_id('sensor.kitchen_temperature') }}")
+            .unwrap(),
+        "kitchen"
+    );
+    let template = format!("{{{{ area_name('{}') }}}}", thermometer_id(&registries));
+    assert_eq!(engine.render(&template).unwrap(), "Kitchen");
+}
+
+#[test]
+fn test_area_id_from_name() {
+    let (engine, _, _dir) = setup_engine();
+    assert_eq!(
+        engine.render("{{ area_id('Kitchen') }}").unwrap(),
+        "kitchen"
+    );
+}
+
+#[test]
+fn test_area_entities_and_devices() {
+    let (engine, registries, _dir) = setup_engine();
+    assert_eq!(
+        engine.render("{{ area_entities('Kitchen') }}").unwrap(),
+        r#"["light.kitchen", "sensor.kitchen_temperature"]"#
+    );
+    assert_eq!(
+        engine.render("{{ area_devices('kitchen') }}").unwrap(),
+        format!(r#"["{}"]"#, thermometer_id(&registries))
+    );
+}
+
+#[test]
+fn test_unknown_lookups_are_undefined() {
+    let (engine, _, _dir) = setup_engine();
+    assert_eq!(
+        engine
+            .render("{{ area_id('light.hallway') is defined }}")
+            .unwrap(),
+        "false"
+    );
+    assert_eq!(
+        engine
+            .render("{{ device_id('light.unknown') is defined }}")
+            .unwrap(),
+        "false"
+    );
+    assert_eq!(
+        engine
+            .render("{{ area_name('nowhere') | default('none') }}")
+            .unwrap(),
+        "none"
+    );
+    assert_eq!(
+        engine.render("{{ device_entities('nope') }}").unwrap(),
+        "[]"
+    );
+}
+
+// ==================== label tests ====================
+
+#[test]
+fn test_labels_all() {
+    let (engine, _, _dir) = setup_engine();
+    assert_eq!(
+        engine.render("{{ labels() }}").unwrap(),
+        r#"["battery", "critical", "downstairs"]"#
+    );
+}
+
+#[test]
+fn test_labels_entity_labeled() {
+    let (engine, _, _dir) = setup_engine();
+    assert_eq!(
+        engine.render("{{ labels('light.kitchen') }}").unwrap(),
+        r#"["critical"]"#
+    );
+    assert_eq!(
+        engine.render("{{ label_entities('critical') }}").unwrap(),
+        r#"["light.kitchen"]"#
+    );
+    assert_eq!(
+        engine.render("{{ label_entities('Critical') }}").unwrap(),
+        r#"["light.kitchen"]"#
+    );
+}
+
+#[test]
+fn test_labels_device_labeled() {
+    let (engine, registries, _dir) = setup_engine();
+    let device_id = thermometer_id(&registries);
+
+    let template = format!("{{{{ labels('{}') }}}}", device_id);
+    assert_eq!(engine.render(&template).unwrap(), r#"["battery"]"#);
+    assert_eq!(
+        engine.render("{{ label_devices('battery') }}").unwrap(),
+        format!(r#"["{}"]"#, device_id)
+    );
+
+    // Device labels are not inherited by the device's entities
+    assert_eq!(
+        engine
+            .render("{{ labels('sensor.kitchen_temperature') }}")
+            .unwrap(),
+        "[]"
+    );
+    assert_eq!(
+        engine.render("{{ label_entities('battery') }}").unwrap(),
+        "[]"
+    );
+}
+
+#[test]
+fn test_labels_area_labeled() {
+    let (engine, _, _dir) = setup_engine();
+    assert_eq!(
+        engine.render("{{ labels('kitchen') }}").unwrap(),
+        r#"["downstairs"]"#
+    );
+    assert_eq!(
+        engine.render("{{ label_areas('downstairs') }}").unwrap(),
+        r#"["kitchen"]"#
+    );
+}
+
+#[test]
+fn test_label_id_and_name() {
+    let (engine, _, _dir) = setup_engine();
+    assert_eq!(
+        engine.render("{{ label_id('Critical') }}").unwrap(),
+        "critical"
+    );
+    assert_eq!(
+        engine.render("{{ label_name('critical') }}").unwrap(),
+        "Critical"
+    );
+    assert_eq!(
+        engine
+            .render("{{ label_name('missing') is defined }}")
+            .unwrap(),
+        "false"
+    );
+}
@@ -30,7 +30,10 @@ fn setup_engine() -> TemplateEngine {
     state_machine.set(
         EntityId::new("light", "bedroom").unwrap(),
         "off",
-        HashMap::from([("friendly_name".to_string(), json!("Bedroom Light"))]),
+        HashMap::from([
+            ("friendly_name".to_string(), json!("Bedroom Light")),
+            ("effect".to_string(), json!(null)),
+        ]),
         Context::new(),
     );
 
@@ -170,6 +173,59 @@ fn test_states_object_name_attribute() {
     );
 }
 
+// ==================== states iteration tests ====================
+
+#[test]
+fn test_states_iteration_sorted() {
+    let engine = setup_engine();
+    let result = engine
+        .render("{{ states | map(attribute='entity_id') | list }}")
+        .unwrap();
+    assert_eq!(
+        result,
+        r#"["binary_sensor.motion", "device_tracker.paulus", "light.bedroom", "light.living_room", "sensor.humidity", "sensor.temperature", "switch.unavailable_device", "switch.unknown_device"]"#
+    );
+}
+
+#[test]
+fn test_states_iteration_selectattr() {
+    let engine = setup_engine();
+    let result = engine
+        .render(
+            "{{ states | selectattr('state', 'eq', 'on') | map(attribute='entity_id') | list }}",
+        )
+        .unwrap();
+    assert_eq!(result, r#"["binary_sensor.motion", "light.living_room"]"#);
+}
+
+#[test]
+fn test_states_domain_iteration() {
+    let engine = setup_engine();
+    let result = engine
+        .render("{{ states.light | map(attribute='entity_id') | list }}")
+        .unwrap();
+    assert_eq!(result, r#"["light.bedroom", "light.living_room"]"#);
+}
+
+#[test]
+fn test_states_count() {
+    let engine = setup_engine();
+    assert_eq!(engine.render("{{ states | count }}").unwrap(), "8");
+    assert_eq!(engine.render("{{ states | length }}").unwrap(), "8");
+    assert_eq!(engine.render("{{ states.light | count }}").unwrap(), "2");
+    assert_eq!(engine.render("{{ states.sensor | length }}").unwrap(), "2");
+    assert_eq!(engine.render("{{ states.climate | count }}").unwrap(), "0");
+}
+
+#[test]
+fn test_states_domain_for_loop() {
+    let engine = setup_engine();
+    let result = engine
+        .render("{% for s in states.sensor %}{{ s.object_id }}={{ s.state }};{% endfor %}")
+        .unwrap();
+    assert_eq!(result, "humidity=65;temperature=23.5;");
+}
+
 // ==================== is_state() function tests ====================
 
 #[test]
@@ -324,6 +380,51 @@ fn test_is_state_attr_not_matching() {
     );
 }
 
+#[test]
+fn test_is_state_attr_nonexistent_entity() {
+    let engine = setup_engine();
+    assert_eq!(
+        engine
+            .render("{{ is_state_attr('light.nonexistent', 'brightness', 255) }}")
+            .unwrap(),
+        "false"
+    );
+    assert_eq!(
+        engine
+            .render("{{ is_state_attr('light.nonexistent', 'brightness', none) }}")
+            .unwrap(),
+        "false"
+    );
+}
+
+#[test]
+fn test_is_state_attr_nonexistent_attribute() {
+    let engine = setup_engine();
+    assert_eq!(
+        engine
+            .render("{{ is_state_attr('light.living_room', 'effect', none) }}")
+            .unwrap(),
+        "false"
+    );
+    assert_eq!(
+        engine
+            .render("{{ is_state_attr('light.living_room', 'effect', undefined_var) }}")
+            .unwrap(),
+        "false"
+    );
+}
+
+#[test]
+fn test_is_state_attr_none_attribute() {
+    let engine = setup_engine();
+    assert_eq!(
+        engine
+            .render("{{ is_state_attr('light.bedroom', 'effect', none) }}")
+            .unwrap(),
+        "false"
+    );
+}
+
 // ==================== has_value() function tests ====================
 
 #[test]
@@ -370,6 +471,56 @@ fn test_has_value_nonexistent_entity() {
     );
 }
 
+// ==================== state tests (`is is_state(...)`) ====================
+
+#[test]
+fn test_is_state_as_test() {
+    let engine = setup_engine();
+    assert_eq!(
+        engine
+            .render("{{ 'light.living_room' is is_state('on') }}")
+            .unwrap(),
+        "true"
+    );
+    assert_eq!(
+        engine
+            .render("{{ 'light.nonexistent' is is_state('on') }}")
+            .unwrap(),
+        "false"
+    );
+    assert_eq!(
+        engine
+            .render(
+                "{{ states.light | map(attribute='entity_id') | select('is_state', 'on') | list }}"
+            )
+            .unwrap(),
+        "[\"light.living_room\"]"
+    );
+}
+
+#[test]
+fn test_has_value_and_is_state_attr_as_tests() {
+    let engine = setup_engine();
+    assert_eq!(
+        engine
+            .render("{{ 'switch.unavailable_device' is has_value }}")
+            .unwrap(),
+        "false"
+    );
+    assert_eq!(
+        engine
+            .render("{{ 'sensor.humidity' is has_value }}")
+            .unwrap(),
+        "true"
+    );
+    assert_eq!(
+        engine
+            .render("{{ 'light.living_room' is is_state_attr('brightness', 255) }}")
+            .unwrap(),
+        "true"
+    );
+}
+
 // ==================== Complex state access tests ====================
 
 #[test]
@@ -447,3 +598,47 @@ fn test_direct_attribute_access_shorthand() {
         .unwrap();
     assert_eq!(result, "255");
 }
+
+// ==================== render info tests ====================
+
+#[test]
+fn test_render_info_tracks_entities() {
+    let engine = setup_engine();
+    let (result, info) = engine.render_with_info(
+        "{{ states('light.living_room') }} {{ is_state('light.bedroom', 'off') }} \
+         {{ states.sensor.temperature.state }} {{ state_attr('sensor.missing', 'x') }}",
+        HashMap::new(),
+    );
+    assert!(result.is_ok());
+    assert_eq!(
+        info.entities.iter().map(String::as_str).collect::<Vec<_>>(),
+        [
+            "light.bedroom",
+            "light.living_room",
+            "sensor.missing",
+            "sensor.temperature"
+        ]
+    );
+    assert!(!info.all_states && !info.time && info.domains.is_empty());
+    assert!(info.is_affected_by("sensor.missing"));
+    assert!(!info.is_affected_by("sensor.humidity"));
+}
+
+#[test]
+fn test_render_info_tracks_domains_all_and_time() {
+    let engine = setup_engine();
+
+    let (_, info) = engine.render_with_info("{{ states.light | count }}", HashMap::new());
+    assert!(info.domains.contains("light"));
+    assert!(info.is_affected_by("light.kitchen"));
+    assert!(!info.is_affected_by("sensor.temperature"));
+
+    let (_, info) = engine.render_with_info("{{ states | count }}", HashMap::new());
+    assert!(info.all_states);
+
+    let (_, info) = engine.render_with_info("{{ now().year }}", HashMap::new());
+    assert!(info.time);
+
+    let (_, info) = engine.render_with_info("{{ 1 + 1 }}", HashMap::new());
+    assert!(info.is_static());
+}
@@ -3,6 +3,9 @@
 //! Tests now(), utcnow(), as_timestamp(), relative_time(), timedelta(), etc.
 //! Based on Python Home Assistant's test_template.py time tests.
 
+use chrono::{Timelike, Utc};
+use chrono_tz::Tz;
+use ha_config::CoreConfig;
 use ha_event_bus::EventBus;
 use ha_state_store::StateStore;
 use ha_template::TemplateEngine;
@@ -82,6 +85,55 @@ fn test_now_weekday() {
     assert!((0..=6).contains(&weekday));
 }
 
+#[test]
+fn test_now_in_configured_time_zone() {
+    let engine = setup_engine();
+    // No DST and an odd offset, so it can't match the server zone by accident
+    engine
+        .set_core_config(&CoreConfig {
+            time_zone: "Asia/Kathmandu".to_string(),
+            ..CoreConfig::default()
+        })
+        .unwrap();
+
+    let result = engine.render("{{ now().isoformat() }}").unwrap();
+    assert!(result.ends_with("+05:45"), "got '{}'", result);
+
+    let expected = Utc::now().with_timezone(&Tz::Asia__Kathmandu);
+    let hour: u32 = engine.render("{{ now().hour }}").unwrap().parse().unwrap();
+    // Allow for the hour turning over between the two reads
+    assert!(hour == expected.hour() || hour == (expected.hour() + 1) % 24);
+
+    assert_eq!(
+        engine.render("{{ utcnow().isoformat()[-6:] }}").unwrap(),
+        "+00:00"
+    );
+    // Same instant either way
+    let diff: f64 = engine
+        .render("{{ as_timestamp(now()) - as_timestamp(utcnow()) }}")
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(diff.abs() < 2.0);
+}
+
+#[test]
+fn test_today_at_and_as_local_in_configured_time_zone() {
+    let engine = setup_engine();
+    engine.set_time_zone("Asia/Kathmandu").unwrap();
+    assert!(engine
+        .render("{{ today_at('07:15').isoformat() }}")
+        .unwrap()
+        .ends_with("T07:15:00+05:45"));
+    // 1700000000 is 2023-11-14 22:13:20 UTC
+    assert_eq!(
+        engine
+            .render("{{ as_local(as_datetime(1700000000)).isoformat() }}")
+            .unwrap(),
+        "2023-11-15T03:58:20+05:45"
+    );
+}
+
 // ==================== utcnow() function tests ====================
 
 #[test]
@@ -185,6 +237,169 @@ fn test_relative_time_one_unit() {
     assert_eq!(result, "1 hour");
 }
 
+#[test]
+fn test_relative_time_rounds_like_ha() {
+    let engine = setup_engine();
+    // 90 minutes rounds up to 2 hours, without an "ago" suffix
+    let result = engine
+        .render("{{ relative_time(now().sub(timedelta(minutes=90))) }}")
+        .unwrap();
+    assert_eq!(result, "2 hours");
+}
+
+#[test]
+fn test_relative_time_months_and_years() {
+    let engine = setup_engine();
+    assert_eq!(
+        engine
+            .render("{{ relative_time(now().sub(timedelta(days=45))) }}")
+            .unwrap(),
+        "2 months"
+    );
+    assert_eq!(
+        engine
+            .render("{{ relative_time(now().sub(timedelta(days=400))) }}")
+            .unwrap(),
+        "1 year"
+    );
+}
+
+#[test]
+fn test_relative_time_future_returns_input() {
+    let engine = setup_engine();
+    let result = engine
+        .render("{% set dt = now().add(timedelta(hours=2)) %}{{ relative_time(dt) == dt }}")
+        .unwrap();
+    assert_eq!(result, "true");
+}
+
+#[test]
+fn test_time_since_precision() {
+    let engine = setup_engine();
+    let result = engine
+        .render("{{ time_since(now().sub(timedelta(days=1, hours=3)), 2) }}")
+        .unwrap();
+    assert_eq!(result, "1 day 3 hours");
+}
+
+// ==================== as_timedelta() function tests ====================
+
+#[test]
+fn test_as_timedelta_clock() {
+    let engine = setup_engine();
+    assert_eq!(
+        engine
+            .render("{{ as_timedelta('01:30:00').total_seconds }}")
+            .unwrap(),
+        "5400"
+    );
+}
+
+#[test]
+fn test_as_timedelta_days() {
+    let engine = setup_engine();
+    assert_eq!(
+        engine
+            .render("{{ as_timedelta('2 days, 1:00:00') }}")
+            .unwrap(),
+        "2 days, 1:00:00"
+    );
+}
+
+#[test]
+fn test_as_timedelta_iso8601() {
+    let engine = setup_engine();
+    assert_eq!(
+        engine.render("{{ as_timedelta('PT1H15M') }}").unwrap(),
+        "1:15:00"
+    );
+}
+
+#[test]
+fn test_as_timedelta_invalid_is_none() {
+    let engine = setup_engine();
+    assert_eq!(
+        engine.render("{{ as_timedelta('soon') is none }}").unwrap(),
+        "true"
+    );
+}
+
+// ==================== timestamp_custom() tests ====================
+
+#[test]
+fn test_timestamp_custom_utc() {
+    let engine = setup_engine();
+    // 1700000000 is 2023-11-14 22:13:20 UTC
+    assert_eq!(
+        engine
+            .render("{{ 1700000000 | timestamp_custom('%Y-%m-%d %H:%M', false) }}")
+            .unwrap(),
+        "2023-11-14 22:13"
+    );
+}
+
+#[test]
+fn test_timestamp_custom_configured_time_zone() {
+    let engine = setup_engine();
+    engine.set_time_zone("America/New_York").unwrap();
+    assert_eq!(
+        engine
+            .render("{{ 1700000000 | timestamp_custom('%H:%M %Z') }}")
+            .unwrap(),
+        "17:13 EST"
+    );
+    assert_eq!(
+        engine
+            .render("{{ timestamp_custom(1700000000, '%H:%M', local=true) }}")
+            .unwrap(),
+        "17:13"
+    );
+}
+
+#[test]
+fn test_timestamp_custom_default_format() {
+    let engine = setup_engine();
+    engine.set_time_zone("Europe/Amsterdam").unwrap();
+    assert_eq!(
+        engine
+            .render("{{ 1700000000 | timestamp_custom }}")
+            .unwrap(),
+        "2023-11-14 23:13:20"
+    );
+}
+
+#[test]
+fn test_timestamp_local_and_utc() {
+    let engine = setup_engine();
+    engine.set_time_zone("Europe/Amsterdam").unwrap();
+    assert_eq!(
+        engine.render("{{ 1700000000 | timestamp_local }}").unwrap(),
+        "2023-11-14T23:13:20+01:00"
+    );
+    assert_eq!(
+        engine.render("{{ 1700000000 | timestamp_utc }}").unwrap(),
+        "2023-11-14T22:13:20+00:00"
+    );
+}
+
+#[test]
+fn test_timestamp_custom_invalid_input() {
+    let engine = setup_engine();
+    assert!(engine.render("{{ 'abc' | timestamp_custom }}").is_err());
+    assert_eq!(
+        engine
+            .render("{{ 'abc' | timestamp_custom('%H', default='n/a') }}")
+            .unwrap(),
+        "n/a"
+    );
+}
+
+#[test]
+fn test_set_unknown_time_zone_fails() {
+    let engine = setup_engine();
+    assert!(engine.set_time_zone("Mars/Olympus_Mons").is_err());
+}
+
 // ==================== timedelta() function tests ====================
 
 #[test]
@@ -217,6 +217,25 @@ fn test_is_number_negative_string() {
     assert_eq!(engine.render("{{ '-5' | is_number }}").unwrap(), "true");
 }
 
+#[test]
+fn test_is_number_not_finite() {
+    let engine = setup_engine();
+    assert_eq!(engine.render("{{ 'nan' | is_number }}").unwrap(), "false");
+    assert_eq!(engine.render("{{ 'inf' is number }}").unwrap(), "false");
+}
+
+#[test]
+fn test_is_number_function() {
+    let engine = setup_engine();
+    assert_eq!(engine.render("{{ is_number(42) }}").unwrap(), "true");
+    assert_eq!(engine.render("{{ is_number('4.2') }}").unwrap(), "true");
+    assert_eq!(
+        engine.render("{{ is_number('unavailable') }}").unwrap(),
+        "false"
+    );
+    assert_eq!(engine.render("{{ is_number(none) }}").unwrap(), "false");
+}
+
 // ==================== is_string filter tests ====================
 
 #[test]
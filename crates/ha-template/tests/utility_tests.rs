@@ -3,6 +3,7 @@
 //! Tests iif, distance, and other utility functions.
 //! Based on Python Home Assistant's test_template.py utility tests.
 
+use ha_config::{CoreConfig, UnitSystemConfig};
 use ha_core::{Context, EntityId};
 use ha_event_bus::EventBus;
 use ha_state_store::StateStore;
@@ -17,7 +18,6 @@ fn setup_engine() -> TemplateEngine {
     TemplateEngine::new(state_machine)
 }
 
-#[allow(dead_code)]
 fn setup_engine_with_trackers() -> TemplateEngine {
     let event_bus = Arc::new(EventBus::new());
     let state_machine = Arc::new(StateStore::new(event_bus));
@@ -45,6 +45,24 @@ fn setup_engine_with_trackers() -> TemplateEngine {
         Context::new(),
     );
 
+    state_machine.set(
+        EntityId::new("device_tracker", "watch").unwrap(),
+        "not_home",
+        HashMap::from([
+            ("latitude".to_string(), json!(41.8781)),
+            ("longitude".to_string(), json!(-87.6298)),
+            ("friendly_name".to_string(), json!("Watch")),
+        ]),
+        Context::new(),
+    );
+
+    state_machine.set(
+        EntityId::new("device_tracker", "tablet").unwrap(),
+        "unknown",
+        HashMap::from([("friendly_name".to_string(), json!("Tablet"))]),
+        Context::new(),
+    );
+
     state_machine.set(
         EntityId::new("zone", "home").unwrap(),
         "zoning",
@@ -218,10 +236,139 @@ fn test_distance_southern_hemisphere() {
     assert!(dist > 2100.0 && dist < 2200.0);
 }
 
+#[test]
+fn test_distance_between_entities() {
+    let engine = setup_engine_with_trackers();
+    let by_id: f64 = engine
+        .render("{{ distance('device_tracker.phone', 'device_tracker.car') }}")
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(by_id > 3900.0 && by_id < 4000.0);
+
+    // States and coordinates mix with entity IDs
+    let mixed: f64 = engine
+        .render("{{ distance(states.device_tracker.car, 40.7128, -74.0060) }}")
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((mixed - by_id).abs() < 1e-6);
+}
+
+#[test]
+fn test_distance_from_home() {
+    let engine = setup_engine_with_trackers();
+    engine
+        .set_core_config(&CoreConfig {
+            latitude: 51.5074,
+            longitude: -0.1278,
+            ..CoreConfig::default()
+        })
+        .unwrap();
+    // London to Paris, about 343 km
+    let km: f64 = engine
+        .render("{{ distance(48.8566, 2.3522) }}")
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(km > 330.0 && km < 360.0);
+
+    engine
+        .set_core_config(&CoreConfig {
+            latitude: 51.5074,
+            longitude: -0.1278,
+            unit_system: UnitSystemConfig::Named("imperial".to_string()),
+            ..CoreConfig::default()
+        })
+        .unwrap();
+    let miles: f64 = engine
+        .render("{{ distance('48.8566', '2.3522') }}")
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((miles * 1.609_344 - km).abs() < 1e-6);
+}
+
+#[test]
+fn test_distance_unknown_location() {
+    let engine = setup_engine_with_trackers();
+    assert_eq!(
+        engine
+            .render("{{ distance('device_tracker.missing', 'device_tracker.car') }}")
+            .unwrap(),
+        "none"
+    );
+    assert_eq!(
+        engine.render("{{ distance('zone.home', 'abc') }}").unwrap(),
+        "none"
+    );
+    assert_eq!(engine.render("{{ distance(40.7128) }}").unwrap(), "none");
+    assert!(engine.render("{{ distance() }}").is_err());
+}
+
 // ==================== closest() function tests ====================
 
-// Note: closest() requires zone entities which we may not have fully implemented
-// These tests are placeholders for when that functionality is added
+#[test]
+fn test_closest_to_point() {
+    let engine = setup_engine_with_trackers();
+    // Denver is nearest to Chicago, then LA, then NYC
+    assert_eq!(
+        engine
+            .render("{{ closest(39.7392, -104.9903, states.device_tracker).entity_id }}")
+            .unwrap(),
+        "device_tracker.car"
+    );
+    assert_eq!(
+        engine
+            .render(
+                "{{ closest(39.7392, -104.9903, ['device_tracker.phone', 'device_tracker.watch']).name }}"
+            )
+            .unwrap(),
+        "Watch"
+    );
+    // Measured from an entity
+    assert_eq!(
+        engine
+            .render("{{ closest('zone.home', states.device_tracker).entity_id }}")
+            .unwrap(),
+        "device_tracker.phone"
+    );
+}
+
+#[test]
+fn test_closest_to_home() {
+    let engine = setup_engine_with_trackers();
+    engine
+        .set_core_config(&CoreConfig {
+            latitude: 41.0,
+            longitude: -87.0,
+            ..CoreConfig::default()
+        })
+        .unwrap();
+    assert_eq!(
+        engine
+            .render("{{ closest(states.device_tracker).entity_id }}")
+            .unwrap(),
+        "device_tracker.watch"
+    );
+}
+
+#[test]
+fn test_closest_without_locations() {
+    let engine = setup_engine_with_trackers();
+    assert_eq!(
+        engine
+            .render("{{ closest('device_tracker.tablet') is undefined }}")
+            .unwrap(),
+        "true"
+    );
+    assert_eq!(
+        engine
+            .render("{{ closest('device_tracker.tablet', states.device_tracker) }}")
+            .unwrap(),
+        "none"
+    );
+}
 
 // ==================== Conditional expression tests ====================
 
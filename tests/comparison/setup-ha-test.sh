@@ -11,6 +11,9 @@
 # - Onboarding marked as complete
 # - Demo entities loaded
 #
+# It also writes a config for the Rust server to <config-dir>/rust, which
+# accepts the same long-lived token through `auth_access_tokens`.
+#
 # Usage:
 #   ./setup-ha-test.sh [config-dir]
 #
@@ -261,6 +264,21 @@ cat > "$STORAGE_DIR/person" << EOF
 }
 EOF
 
+# Create the Rust HA configuration, seeding the comparison token
+mkdir -p "$CONFIG_DIR/rust"
+cat > "$CONFIG_DIR/rust/configuration.yaml" << EOF
+# Rust Home Assistant Test Configuration
+# DO NOT use in production!
+
+homeassistant:
+  name: Test Home
+  unit_system: metric
+  time_zone: UTC
+  auth_access_tokens:
+    - token: $LONG_LIVED_ACCESS_TOKEN
+      user_id: $TEST_USER_ID
+EOF
+
 # Create a placeholder token file - real token generated after Docker starts
 # The token will be generated inside the container which has PyJWT
 cat > "$CONFIG_DIR/test-token.txt" << EOF
@@ -292,6 +310,7 @@ echo "  Username: $TEST_USER_NAME"
 echo "  Password: test-password-123"
 echo ""
 echo "Token file: $CONFIG_DIR/test-token.txt (generated after Docker starts)"
+echo "Rust HA config: $CONFIG_DIR/rust (token: $LONG_LIVED_ACCESS_TOKEN)"
 echo ""
 echo "To start: docker compose up -d"
 echo "To access: http://localhost:18123"